@@ -0,0 +1,28 @@
+use std::process::Command;
+
+//Commit and target for `ff version`, FF_GIT_COMMIT overrides the commit for builds outside a checkout
+fn main() {
+    println!("cargo:rerun-if-env-changed=FF_GIT_COMMIT");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    let commit = std::env::var("FF_GIT_COMMIT").ok().or_else(|| {
+        Command::new("git")
+            .args(["rev-parse", "--short=12", "HEAD"])
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+    });
+    println!(
+        "cargo:rustc-env=FF_GIT_COMMIT={}",
+        commit.unwrap_or_else(|| "unknown".to_string())
+    );
+    println!(
+        "cargo:rustc-env=FF_BUILD_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+    println!(
+        "cargo:rustc-env=FF_BUILD_PROFILE={}",
+        std::env::var("PROFILE").unwrap_or_default()
+    );
+}
@@ -0,0 +1,31 @@
+#![no_main]
+
+use dg_fast_farmer::farmer::protocols::decode_message;
+use dg_xch_clients::protocols::farmer::{NewSignagePoint, RequestSignedValues};
+use dg_xch_clients::protocols::harvester::RespondSignatures;
+use dg_xch_clients::websocket::ChiaMessage;
+use dg_xch_serialize::ChiaSerialize;
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+//The first byte picks the message type, the rest is the payload a peer would send
+fuzz_target!(|data: &[u8]| {
+    let Some((kind, payload)) = data.split_first() else {
+        return;
+    };
+    let payload = payload.to_vec();
+    match kind % 4 {
+        0 => {
+            let _ = decode_message::<NewSignagePoint>("NewSignagePoint", &payload);
+        }
+        1 => {
+            let _ = decode_message::<RequestSignedValues>("RequestSignedValues", &payload);
+        }
+        2 => {
+            let _ = decode_message::<RespondSignatures>("RespondSignatures", &payload);
+        }
+        _ => {
+            let _ = ChiaMessage::from_bytes(&mut Cursor::new(&payload));
+        }
+    }
+});
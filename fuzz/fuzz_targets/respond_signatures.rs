@@ -0,0 +1,22 @@
+#![no_main]
+
+use dg_fast_farmer::farmer::protocols::decode_message;
+use dg_fast_farmer::farmer::protocols::harvester::respond_signatures::signature_kind;
+use dg_xch_clients::protocols::farmer::NewSignagePoint;
+use dg_xch_clients::protocols::harvester::RespondSignatures;
+use libfuzzer_sys::fuzz_target;
+
+//A signage point followed by the harvester response, checked the way handle_signature does
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 113 {
+        return;
+    }
+    let (sp, response) = data.split_at(113);
+    let (Ok(sp), Ok(response)) = (
+        decode_message::<NewSignagePoint>("NewSignagePoint", &sp.to_vec()),
+        decode_message::<RespondSignatures>("RespondSignatures", &response.to_vec()),
+    ) else {
+        return;
+    };
+    let _ = signature_kind(&response, &[sp]);
+});
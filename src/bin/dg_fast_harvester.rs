@@ -0,0 +1,125 @@
+use clap::Parser;
+use dg_fast_farmer::cli::{HarvesterAction, HarvesterCli};
+use dg_fast_farmer::farmer::config::{HarvesterNodeConfig, RuntimeConfig};
+use dg_fast_farmer::farmer::discovery::{advertise_harvester, discover_harvesters, DISCOVERY_TIME};
+use dg_fast_farmer::harvesters::plot_dirs::validate_plot_dirs;
+use dg_fast_farmer::harvesters::remote::run_harvester;
+use dg_fast_farmer::{get_harvester_config_path, get_root_path};
+use dg_xch_core::consensus::constants::CONSENSUS_CONSTANTS_MAP;
+use log::{info, warn, LevelFilter};
+use simple_logger::SimpleLogger;
+use std::io::Error;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tokio::fs::create_dir_all;
+
+fn main() -> Result<(), Error> {
+    let cli = HarvesterCli::parse();
+    //The runtime is built before the async main so the config can size it
+    let config_path = cli
+        .config
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(get_harvester_config_path);
+    let runtime = if config_path.exists() {
+        HarvesterNodeConfig::try_from(config_path.as_path())
+            .map(|c| c.runtime)
+            .unwrap_or_default()
+    } else {
+        RuntimeConfig::default()
+    };
+    runtime.build()?.block_on(run(cli))
+}
+
+async fn run(cli: HarvesterCli) -> Result<(), Error> {
+    let config_path = if let Some(s) = &cli.config {
+        PathBuf::from(s)
+    } else {
+        create_dir_all(get_root_path()).await?;
+        get_harvester_config_path()
+    };
+    SimpleLogger::new()
+        .with_colors(true)
+        .with_level(LevelFilter::Info)
+        .env()
+        .init()
+        .unwrap_or_default();
+    match cli.action.unwrap_or_default() {
+        HarvesterAction::Run {} => {
+            if !config_path.exists() {
+                eprintln!(
+                    "Failed to find config at {:?}, please run init",
+                    config_path
+                );
+                return Ok(());
+            }
+            let config = HarvesterNodeConfig::try_from(config_path.as_path())?;
+            //Dropping the daemon withdraws the announcement
+            let _advertisement = if config.advertise {
+                advertise_harvester(&config)
+                    .map_err(|e| warn!("Failed to advertise harvester: {e}"))
+                    .ok()
+            } else {
+                None
+            };
+            run_harvester(Arc::new(config), Arc::new(AtomicBool::new(true))).await
+        }
+        HarvesterAction::Init {
+            farmer_host,
+            farmer_port,
+            ssl_root_path,
+            network,
+            plot_directories,
+            discover,
+            advertise,
+        } => {
+            if config_path.exists() {
+                eprintln!("A config already exists at {:?}", config_path);
+                return Ok(());
+            }
+            let discovered = match (&farmer_host, discover) {
+                (None, true) => discover_farmer().await,
+                _ => None,
+            };
+            let Some((farmer_host, discovered_port)) =
+                farmer_host.map(|h| (h, None)).or(discovered)
+            else {
+                return Err(Error::other(
+                    "No farmer host given, pass --farmer-host or --discover",
+                ));
+            };
+            let mut config = HarvesterNodeConfig {
+                farmer_host,
+                ssl_root_path,
+                advertise,
+                ..Default::default()
+            };
+            if let Some(port) = farmer_port.or(discovered_port) {
+                config.farmer_port = port;
+            }
+            if let Some(network) = network.filter(|n| CONSENSUS_CONSTANTS_MAP.contains_key(n)) {
+                config.selected_network = network;
+            }
+            if let Some(bladebit) = config.harvester_configs.bladebit.as_mut() {
+                bladebit.plot_directories = plot_directories.unwrap_or_default();
+                if let Some(e) = validate_plot_dirs(bladebit).into_iter().next() {
+                    return Err(e.into());
+                }
+            }
+            config.save_as_yaml(&config_path)?;
+            info!("Saved harvester config to {:?}", config_path);
+            Ok(())
+        }
+    }
+}
+
+//Farmer used by the first harvester found advertising on the local network
+async fn discover_farmer() -> Option<(String, Option<u16>)> {
+    let (name, host, port) = discover_harvesters(DISCOVERY_TIME)
+        .await
+        .into_iter()
+        .find_map(|h| Some((h.name, h.farmer_host?, h.farmer_port)))?;
+    info!("Using farmer {host} of harvester {name}");
+    Some((host, port))
+}
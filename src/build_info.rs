@@ -0,0 +1,124 @@
+use crate::farmer::partial_metadata::EXTENDED_PARTIALS_PROTOCOL_VERSION;
+use dg_xch_clients::protocols::pool::POOL_PROTOCOL_VERSION;
+use dg_xch_clients::protocols::shared::{CAPABILITIES, PROTOCOL_VERSION};
+use dg_xch_core::consensus::constants::CONSENSUS_CONSTANTS_MAP;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+const FEATURES: [(&str, bool); 6] = [
+    ("gui", cfg!(feature = "gui")),
+    ("mqtt", cfg!(feature = "mqtt")),
+    ("remote-harvester", cfg!(feature = "remote-harvester")),
+    ("quic", cfg!(feature = "quic")),
+    ("profiling", cfg!(feature = "profiling")),
+    ("chaos", cfg!(feature = "chaos")),
+];
+
+//What a bug report or an inventory needs to know about the binary
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub version: String,
+    pub git_commit: String,
+    pub target: String,
+    pub profile: String,
+    pub features: Vec<String>,
+    pub dg_xch_pos: String,
+    //Chia protocol version of the full node and harvester handshakes
+    pub protocol_version: String,
+    pub capabilities: Vec<u16>,
+    //Pool protocol versions the farmer speaks, the higher one gets extended partials
+    pub pool_protocol_versions: Vec<u8>,
+    //Networks with built in consensus constants
+    pub networks: Vec<String>,
+}
+impl BuildInfo {
+    fn new() -> Self {
+        let mut networks: Vec<String> = CONSENSUS_CONSTANTS_MAP.keys().cloned().collect();
+        networks.sort();
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: env!("FF_GIT_COMMIT").to_string(),
+            target: env!("FF_BUILD_TARGET").to_string(),
+            profile: env!("FF_BUILD_PROFILE").to_string(),
+            features: FEATURES
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            //Without the crate name it is prefixed with
+            dg_xch_pos: dg_xch_pos::version()
+                .rsplit(' ')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            protocol_version: PROTOCOL_VERSION.to_string(),
+            capabilities: CAPABILITIES.iter().map(|(c, _)| *c).collect(),
+            pool_protocol_versions: vec![POOL_PROTOCOL_VERSION, EXTENDED_PARTIALS_PROTOCOL_VERSION],
+            networks,
+        }
+    }
+    pub fn lines(&self) -> Vec<String> {
+        vec![
+            format!(
+                "{} ({}) {} {}",
+                self.version, self.git_commit, self.target, self.profile
+            ),
+            format!(
+                "features={}",
+                if self.features.is_empty() {
+                    "none".to_string()
+                } else {
+                    self.features.join(",")
+                }
+            ),
+            format!(
+                "protocol={} capabilities={} pool_protocol={} dg_xch_pos={}",
+                self.protocol_version,
+                self.capabilities
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                self.pool_protocol_versions
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                self.dg_xch_pos
+            ),
+            format!("networks={}", self.networks.join(",")),
+        ]
+    }
+}
+
+//Output of `ff version`, the network comes from the config when there is one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionReport {
+    #[serde(flatten)]
+    pub build: BuildInfo,
+    pub configured_network: Option<String>,
+}
+
+pub static BUILD_INFO: Lazy<BuildInfo> = Lazy::new(BuildInfo::new);
+//Printed by --version after the binary name, -V keeps the short one
+pub static LONG_VERSION: Lazy<String> = Lazy::new(|| BUILD_INFO.lines().join("\n"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_enabled_features_and_networks() {
+        let info = BuildInfo::new();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(!info.git_commit.is_empty());
+        assert_eq!(
+            info.features.contains(&"gui".to_string()),
+            cfg!(feature = "gui")
+        );
+        assert!(info.networks.contains(&"mainnet".to_string()));
+        assert!(LONG_VERSION.starts_with(&format!("{} ({})", info.version, info.git_commit)));
+        let json = serde_json::to_string(&info).unwrap();
+        assert_eq!(serde_json::from_str::<BuildInfo>(&json).unwrap(), info);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::platform::{create_private, set_mode};
+use crate::{get_forensics_path, get_object_cache_path, get_root_path};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use sha2::Sha256;
+use std::fs;
+use std::io::{Error, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+const BACKUP_MAGIC: &[u8] = b"FFBACKUP1";
+const CONFIG_ENTRY: &str = "fast_farmer.yaml";
+const HASH_ITERS: u32 = 100_000;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+pub const BACKUP_PASSPHRASE_ENV: &str = "FF_BACKUP_PASSPHRASE";
+
+struct BackupEntry {
+    name: PathBuf,
+    mode: u32,
+    data: Vec<u8>,
+}
+
+//Config plus everything the farmer keeps in its root directory (pool and partial caches, plot
+//audit, harvester ids, farm id, control identity and default ssl certs). The object cache and
+//forensics dumps are rebuilt or only useful on the old machine so they are left out
+pub fn backup(config_path: &Path, out: &Path, encrypt: bool) -> Result<(), Error> {
+    let root = get_root_path();
+    let skip = [
+        get_object_cache_path(),
+        get_forensics_path(),
+        out.to_path_buf(),
+        config_path.to_path_buf(),
+        root.join(CONFIG_ENTRY),
+    ];
+    let mut files = vec![(PathBuf::from(CONFIG_ENTRY), config_path.to_path_buf())];
+    if root.exists() {
+        collect_files(&root, &root, &skip, &mut files)?;
+    }
+    let archive = build_archive(&files)?;
+    let (data, note) = if encrypt {
+        let passphrase = read_passphrase(true)?;
+        (encrypt_backup(&archive, &passphrase)?, "encrypted")
+    } else {
+        (archive, "unencrypted, it contains the farmer secret keys")
+    };
+    create_private(out)?.write_all(&data)?;
+    for (name, _) in &files {
+        println!("  {}", name.display());
+    }
+    println!("Wrote {} files to {:?} ({note})", files.len(), out);
+    Ok(())
+}
+
+//Existing files are only replaced with force, nothing is written if one would be overwritten
+pub fn restore(config_path: &Path, input: &Path, force: bool) -> Result<(), Error> {
+    let mut data = fs::read(input)?;
+    if data.starts_with(BACKUP_MAGIC) {
+        let passphrase = read_passphrase(false)?;
+        data = decrypt_backup(&data, &passphrase)?;
+    }
+    let entries = read_archive(&data)?;
+    let root = get_root_path();
+    let targets: Vec<PathBuf> = entries
+        .iter()
+        .map(|e| restore_target(&e.name, &root, config_path))
+        .collect();
+    let existing: Vec<&PathBuf> = targets.iter().filter(|t| t.exists()).collect();
+    if !existing.is_empty() && !force {
+        for target in &existing {
+            eprintln!("  {}", target.display());
+        }
+        return Err(Error::other(format!(
+            "{} files already exist, stop the farmer and pass --force to replace them",
+            existing.len()
+        )));
+    }
+    for (entry, target) in entries.iter().zip(&targets) {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        //Written owner only and given the backed up mode once the keys are in place
+        create_private(target)?.write_all(&entry.data)?;
+        set_mode(target, entry.mode & 0o777)?;
+        println!("  {}", target.display());
+    }
+    println!("Restored {} files from {:?}", entries.len(), input);
+    Ok(())
+}
+
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    skip: &[PathBuf],
+    files: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if skip.iter().any(|s| same_path(s, &path)) {
+            continue;
+        }
+        //Sockets and other special files (like the control socket) are skipped
+        let file_type = fs::symlink_metadata(&path)?.file_type();
+        if file_type.is_dir() {
+            collect_files(root, &path, skip, files)?;
+        } else if file_type.is_file() {
+            let name = path.strip_prefix(root).map_err(Error::other)?.to_path_buf();
+            files.push((name, path));
+        }
+    }
+    Ok(())
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.components().eq(b.components()),
+    }
+}
+
+fn build_archive(files: &[(PathBuf, PathBuf)]) -> Result<Vec<u8>, Error> {
+    let mut builder = tar::Builder::new(zstd::Encoder::new(Vec::new(), 0)?);
+    for (name, path) in files {
+        builder.append_path_with_name(path, name)?;
+    }
+    builder.into_inner()?.finish()
+}
+
+fn read_archive(data: &[u8]) -> Result<Vec<BackupEntry>, Error> {
+    let mut archive = tar::Archive::new(zstd::Decoder::new(data)?);
+    let mut entries = vec![];
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_path_buf();
+        if !name.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Err(Error::other(format!(
+                "Refusing to restore {:?} outside the farmer directory",
+                name
+            )));
+        }
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let mode = entry.header().mode().unwrap_or(0o600);
+        let mut data = vec![];
+        entry.read_to_end(&mut data)?;
+        entries.push(BackupEntry { name, mode, data });
+    }
+    Ok(entries)
+}
+
+fn restore_target(name: &Path, root: &Path, config_path: &Path) -> PathBuf {
+    if name == Path::new(CONFIG_ENTRY) {
+        config_path.to_path_buf()
+    } else {
+        root.join(name)
+    }
+}
+
+fn read_passphrase(confirm: bool) -> Result<String, Error> {
+    if let Ok(passphrase) = std::env::var(BACKUP_PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    let mut prompt = dialoguer::Password::new().with_prompt("Backup passphrase");
+    if confirm {
+        prompt = prompt.with_confirmation("Repeat passphrase", "Passphrases do not match");
+    }
+    prompt
+        .interact()
+        .map_err(|e| Error::other(format!("Failed to read backup passphrase: {e}")))
+}
+
+fn backup_cipher(passphrase: &str, salt: &[u8]) -> ChaCha20Poly1305 {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, HASH_ITERS, &mut key);
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+fn encrypt_backup(data: &[u8], passphrase: &str) -> Result<Vec<u8>, Error> {
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_LENGTH];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let encrypted = backup_cipher(passphrase, &salt)
+        .encrypt(Nonce::from_slice(&nonce), data)
+        .map_err(|e| Error::other(format!("Failed to encrypt backup: {e}")))?;
+    Ok([BACKUP_MAGIC, &salt, &nonce, &encrypted].concat())
+}
+
+fn decrypt_backup(data: &[u8], passphrase: &str) -> Result<Vec<u8>, Error> {
+    let header = BACKUP_MAGIC.len() + SALT_LENGTH + NONCE_LENGTH;
+    if data.len() < header {
+        return Err(Error::other("Backup is truncated"));
+    }
+    let salt = &data[BACKUP_MAGIC.len()..BACKUP_MAGIC.len() + SALT_LENGTH];
+    let nonce = &data[BACKUP_MAGIC.len() + SALT_LENGTH..header];
+    backup_cipher(passphrase, salt)
+        .decrypt(Nonce::from_slice(nonce), &data[header..])
+        .map_err(|_| Error::other("Wrong passphrase or damaged backup"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archive_round_trips_through_encryption() {
+        let dir = std::env::temp_dir().join(format!("ff_backup_test_{}", std::process::id()));
+        fs::create_dir_all(dir.join("ssl")).unwrap();
+        fs::write(dir.join("farm_id"), b"farm").unwrap();
+        fs::write(dir.join("ssl").join("ca.crt"), b"cert").unwrap();
+        let mut files = vec![];
+        collect_files(&dir, &dir, &[], &mut files).unwrap();
+        files.sort();
+        let archive = build_archive(&files).unwrap();
+        let encrypted = encrypt_backup(&archive, "hunter2").unwrap();
+        assert!(decrypt_backup(&encrypted, "hunter3").is_err());
+        let entries = read_archive(&decrypt_backup(&encrypted, "hunter2").unwrap()).unwrap();
+        let restored: Vec<(PathBuf, Vec<u8>)> =
+            entries.into_iter().map(|e| (e.name, e.data)).collect();
+        assert_eq!(
+            restored,
+            vec![
+                (PathBuf::from("farm_id"), b"farm".to_vec()),
+                (PathBuf::from("ssl/ca.crt"), b"cert".to_vec()),
+            ]
+        );
+        assert_eq!(
+            restore_target(Path::new(CONFIG_ENTRY), &dir, Path::new("/etc/ff.yaml")),
+            PathBuf::from("/etc/ff.yaml")
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,342 @@
+use crate::control::{connect, ControlRequest, TailParams};
+use crate::farmer::config::{BladebitHarvesterConfig, Config};
+use crate::farmer::events::{FarmerEvent, FarmerEventKind};
+use crate::farmer::rpc::FullnodeRpc;
+use crossterm::style::Stylize;
+use dg_xch_clients::api::full_node::FullnodeAPI;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use dg_xch_keys::decode_puzzle_hash;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{split, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Command;
+
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const SOCKET_WAIT: Duration = Duration::from_secs(60);
+
+pub struct E2eOptions {
+    pub chia: String,
+    pub plot_dir: PathBuf,
+    pub k: u8,
+    pub plots: u32,
+    pub timeout: u64,
+}
+
+//Steps of the signing path in the order a farmed block passes them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum E2eStage {
+    SignagePoint,
+    ProofDeclared,
+    SignedValues,
+    BlockFarmed,
+}
+impl E2eStage {
+    pub const ALL: [E2eStage; 4] = [
+        E2eStage::SignagePoint,
+        E2eStage::ProofDeclared,
+        E2eStage::SignedValues,
+        E2eStage::BlockFarmed,
+    ];
+}
+impl Display for E2eStage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            E2eStage::SignagePoint => "signage point received",
+            E2eStage::ProofDeclared => "proof declared",
+            E2eStage::SignedValues => "block signed",
+            E2eStage::BlockFarmed => "block farmed",
+        })
+    }
+}
+
+//Time from the start of the run until each stage was first reached
+pub struct E2eProgress {
+    started: Instant,
+    reached: BTreeMap<E2eStage, Duration>,
+}
+impl Default for E2eProgress {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            reached: BTreeMap::new(),
+        }
+    }
+}
+impl E2eProgress {
+    pub fn record(&mut self, stage: E2eStage) {
+        let elapsed = self.started.elapsed();
+        self.reached.entry(stage).or_insert(elapsed);
+    }
+    pub fn record_event(&mut self, kind: &FarmerEventKind) {
+        match kind {
+            FarmerEventKind::SignagePoint { .. } => self.record(E2eStage::SignagePoint),
+            FarmerEventKind::ProofDeclared { .. } => self.record(E2eStage::ProofDeclared),
+            FarmerEventKind::SignedValues { .. } => self.record(E2eStage::SignedValues),
+            _ => {}
+        }
+    }
+    pub fn reached(&self, stage: E2eStage) -> bool {
+        self.reached.contains_key(&stage)
+    }
+    pub fn is_complete(&self) -> bool {
+        E2eStage::ALL.iter().all(|s| self.reached(*s))
+    }
+    //First stage that was not reached, the one to look at when the run fails
+    pub fn first_missing(&self) -> Option<E2eStage> {
+        E2eStage::ALL.into_iter().find(|s| !self.reached(*s))
+    }
+    pub fn print(&self) {
+        for stage in E2eStage::ALL {
+            match self.reached.get(&stage) {
+                Some(at) => println!("{} {stage} after {:.1}s", "PASS".green(), at.as_secs_f64()),
+                None => println!("{} {stage}", "FAIL".red()),
+            }
+        }
+    }
+}
+
+//Plots a throwaway farm with the chia cli, runs `ff run` against it on the configured node and
+//waits until a block paying the payout address is farmed. Fails with the first missing stage
+pub async fn run_e2e(
+    config_path: &Path,
+    mut config: Config,
+    options: E2eOptions,
+) -> Result<(), Error> {
+    if config.selected_network == "mainnet" {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Refusing to run end-to-end tests on mainnet, select a simulator or testnet network",
+        ));
+    }
+    let farmer_info = config.farmer_info.first().cloned().ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "The config has no farmer_info entry",
+        )
+    })?;
+    let pool_public_key = farmer_info.pool_public_key().ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "The first farmer_info entry needs a pool key to create solo plots",
+        )
+    })?;
+    let payout_puzzle_hash = decode_puzzle_hash(&config.payout_address)?;
+    create_plots(
+        &options,
+        &hex::encode(farmer_info.farmer_public_key().to_sized_bytes()),
+        &hex::encode(pool_public_key.to_sized_bytes()),
+    )
+    .await?;
+    //Own config next to the plots so the run neither shares the control socket nor the instance
+    //lock of a farmer already running from the main config
+    let run_dir = options.plot_dir.join("run");
+    std::fs::create_dir_all(&run_dir)?;
+    config.harvester_configs.bladebit = Some(BladebitHarvesterConfig {
+        plot_directories: vec![options.plot_dir.display().to_string()],
+        ..Default::default()
+    });
+    config.farms = vec![];
+    config.high_availability = None;
+    config.control_socket = Some(run_dir.join("fast_farmer.sock").display().to_string());
+    let run_config = run_dir.join("config.yaml");
+    config.save_as_yaml(&run_config)?;
+    let rpc = FullnodeRpc::new(&config, Arc::new(Default::default())).await;
+    let start_height = rpc
+        .get_blockchain_state()
+        .await?
+        .peak
+        .map(|p| p.height + 1)
+        .unwrap_or_default();
+    let log_path = run_dir.join("farmer.log");
+    let log = std::fs::File::create(&log_path)?;
+    println!(
+        "Farming from height {start_height} with config {:?} (template {:?}), log at {:?}",
+        run_config, config_path, log_path
+    );
+    let mut farmer = Command::new(std::env::current_exe()?)
+        .arg("--config")
+        .arg(&run_config)
+        .arg("run")
+        .stdout(Stdio::from(log.try_clone()?))
+        .stderr(Stdio::from(log))
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut progress = E2eProgress::default();
+    let result = watch(
+        &crate::control::get_control_socket_path(&config),
+        &rpc,
+        start_height,
+        &payout_puzzle_hash,
+        Duration::from_secs(options.timeout),
+        &mut progress,
+    )
+    .await;
+    //Already gone when it exited on its own
+    let _ = farmer.kill().await;
+    progress.print();
+    result?;
+    match progress.first_missing() {
+        None => Ok(()),
+        Some(stage) => Err(Error::new(
+            ErrorKind::TimedOut,
+            format!("No {stage} within {}s, see {:?}", options.timeout, log_path),
+        )),
+    }
+}
+
+//Plots are kept between runs, a directory that already has some is used as is
+async fn create_plots(
+    options: &E2eOptions,
+    farmer_public_key: &str,
+    pool_public_key: &str,
+) -> Result<(), Error> {
+    std::fs::create_dir_all(&options.plot_dir)?;
+    let existing = std::fs::read_dir(&options.plot_dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "plot"))
+        .count();
+    if existing > 0 {
+        println!("Using {existing} plots in {:?}", options.plot_dir);
+        return Ok(());
+    }
+    println!(
+        "Creating {} k{} plots in {:?}",
+        options.plots, options.k, options.plot_dir
+    );
+    let status = Command::new(&options.chia)
+        .args(["plots", "create", "--override-k", "-x"])
+        .args(["-k", &options.k.to_string()])
+        .args(["-n", &options.plots.to_string()])
+        .args(["-f", farmer_public_key, "-p", pool_public_key])
+        .arg("-t")
+        .arg(&options.plot_dir)
+        .arg("-d")
+        .arg(&options.plot_dir)
+        .status()
+        .await
+        .map_err(|e| Error::new(e.kind(), format!("Failed to run {}: {e}", options.chia)))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::other(format!("Plotting failed with {status}")))
+    }
+}
+
+async fn watch(
+    socket_path: &Path,
+    rpc: &FullnodeRpc,
+    start_height: u32,
+    payout_puzzle_hash: &Bytes32,
+    timeout: Duration,
+    progress: &mut E2eProgress,
+) -> Result<(), Error> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let socket_deadline = Instant::now() + SOCKET_WAIT;
+    //The farmer needs a moment to load its plots before the socket exists
+    let stream = loop {
+        match connect(socket_path).await {
+            Ok(stream) => break stream,
+            Err(e) if Instant::now() >= socket_deadline => {
+                return Err(Error::new(
+                    e.kind(),
+                    format!(
+                        "Farmer did not open its control socket at {:?}: {e}",
+                        socket_path
+                    ),
+                ))
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(500)).await,
+        }
+    };
+    let (reader, mut writer) = split(stream);
+    let mut request = serde_json::to_string(&ControlRequest::new(
+        "tail",
+        serde_json::to_value(TailParams { filter: None })
+            .map_err(|e| Error::other(format!("{:?}", e)))?,
+    ))
+    .map_err(|e| Error::other(format!("{:?}", e)))?;
+    request.push('\n');
+    writer.write_all(request.as_bytes()).await?;
+    let mut lines = BufReader::new(reader).lines();
+    let mut poll = tokio::time::interval(BLOCK_POLL_INTERVAL);
+    while !progress.is_complete() {
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => break,
+            line = lines.next_line() => match line? {
+                Some(line) => {
+                    if let Ok(event) = serde_json::from_str::<FarmerEvent>(&line) {
+                        progress.record_event(&event.kind);
+                    }
+                }
+                None => return Err(Error::other("Farmer closed its control socket")),
+            },
+            //Only blocks after one was signed count, another farmer could share the payout address
+            _ = poll.tick(), if progress.reached(E2eStage::SignedValues) => {
+                if farmed_block(rpc, start_height, payout_puzzle_hash).await? {
+                    progress.record(E2eStage::BlockFarmed);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn farmed_block(
+    rpc: &FullnodeRpc,
+    start_height: u32,
+    payout_puzzle_hash: &Bytes32,
+) -> Result<bool, Error> {
+    let Some(peak) = rpc.get_blockchain_state().await?.peak else {
+        return Ok(false);
+    };
+    if peak.height < start_height {
+        return Ok(false);
+    }
+    let records = rpc
+        .call("get_block_records", |c| {
+            c.get_block_records(start_height, peak.height + 1)
+        })
+        .await?;
+    Ok(records
+        .iter()
+        .any(|r| r.farmer_puzzle_hash == *payout_puzzle_hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_once_every_stage_was_reached() {
+        let mut progress = E2eProgress::default();
+        progress.record_event(&FarmerEventKind::SignagePoint {
+            challenge_hash: Bytes32::default(),
+            sp_hash: Bytes32::default(),
+            signage_point_index: 1,
+        });
+        progress.record_event(&FarmerEventKind::PlotMissing {
+            path: String::new(),
+        });
+        assert_eq!(progress.first_missing(), Some(E2eStage::ProofDeclared));
+        progress.record_event(&FarmerEventKind::ProofDeclared {
+            challenge_hash: Bytes32::default(),
+            sp_hash: Bytes32::default(),
+            plot_identifier: String::new(),
+            sp: None,
+            launcher_id: None,
+        });
+        progress.record_event(&FarmerEventKind::SignedValues {
+            quality_string: Bytes32::default(),
+            sp: None,
+        });
+        assert!(!progress.is_complete());
+        progress.record(E2eStage::BlockFarmed);
+        assert!(progress.is_complete());
+        assert_eq!(progress.first_missing(), None);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::farmer::config::Config;
+use crate::harvesters::plot_classification::classify_plot;
+use crate::harvesters::plot_dirs::plot_dirs;
+use blst::min_pk::{PublicKey, SecretKey};
+use dg_xch_core::blockchain::proof_of_space::{
+    calculate_plot_id_public_key, calculate_plot_id_puzzle_hash, generate_plot_public_key,
+};
+use dg_xch_core::blockchain::sized_bytes::{Bytes32, Bytes48};
+use dg_xch_core::plots::{PlotHeader, PlotMemo};
+use dg_xch_keys::master_sk_to_local_sk;
+use dg_xch_pos::plots::plot_reader::read_plot_file_header_async;
+use rand::seq::SliceRandom;
+use std::ffi::OsStr;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+//Checks that the configured keys rebuild the plot id of sampled plots, a plot failing here would
+//be dropped with "Key Mismatch" when it wins a signage point. Only needs the public keys, so a
+//watch only config can be checked before the secret keys are moved onto the machine
+pub async fn verify_keys(config: Config, sample: usize) -> Result<(), Error> {
+    let Some(bb_config) = &config.harvester_configs.bladebit else {
+        println!("No plot directories configured");
+        return Ok(());
+    };
+    let pool_contract_hashes: Vec<Bytes32> = config
+        .all_farms()
+        .iter()
+        .flat_map(|f| f.pool_info.iter().map(|p| p.p2_singleton_puzzle_hash))
+        .collect();
+    let dirs = plot_dirs(bb_config);
+    let (farmer_public_keys, pool_public_keys) = config.public_keys();
+    let mut mismatched = 0;
+    for dir in dirs {
+        let plots = match sample_plots(&dir, sample).await {
+            Ok(plots) => plots,
+            Err(e) => {
+                eprintln!("Failed to list plots in {:?}: {:?}", dir, e);
+                continue;
+            }
+        };
+        let mut failed = 0;
+        for path in &plots {
+            let result = match read_plot_file_header_async(path).await {
+                Ok((_, PlotHeader::V1(h))) => verify_plot_keys(
+                    &h.memo,
+                    &h.id,
+                    &farmer_public_keys,
+                    &pool_public_keys,
+                    &pool_contract_hashes,
+                ),
+                Ok((_, PlotHeader::V2(h))) => verify_plot_keys(
+                    &h.memo,
+                    &h.id,
+                    &farmer_public_keys,
+                    &pool_public_keys,
+                    &pool_contract_hashes,
+                ),
+                Err(e) => Err(format!("failed to read header: {e}")),
+            };
+            if let Err(reason) = result {
+                failed += 1;
+                println!("{}: {reason}", path.display());
+            }
+        }
+        println!(
+            "{}: checked {} plots, {} ok, {} mismatched",
+            dir.display(),
+            plots.len(),
+            plots.len() - failed,
+            failed
+        );
+        mismatched += failed;
+    }
+    if mismatched == 0 {
+        println!("The configured keys match every checked plot");
+    }
+    Ok(())
+}
+
+//Random plots of the directory, all of them when sample is 0
+async fn sample_plots(dir: &Path, sample: usize) -> Result<Vec<PathBuf>, Error> {
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    let mut plots = vec![];
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension() == Some(OsStr::new("plot")) {
+            plots.push(path);
+        }
+    }
+    if sample > 0 && plots.len() > sample {
+        plots = plots
+            .choose_multiple(&mut rand::thread_rng(), sample)
+            .cloned()
+            .collect();
+    }
+    plots.sort();
+    Ok(plots)
+}
+
+pub fn verify_plot_keys(
+    memo: &PlotMemo,
+    plot_id: &Bytes32,
+    farmer_public_keys: &[Bytes48],
+    pool_public_keys: &[Bytes48],
+    pool_contract_hashes: &[Bytes32],
+) -> Result<(), String> {
+    classify_plot(
+        memo,
+        farmer_public_keys,
+        pool_public_keys,
+        pool_contract_hashes,
+    )
+    .map_err(|(_, reason)| reason.to_string())?;
+    let farmer_pk = PublicKey::from_bytes(memo.farmer_public_key.as_ref())
+        .map_err(|e| format!("invalid farmer key in memo: {e:?}"))?;
+    let local_master_sk = SecretKey::from_bytes(memo.local_master_secret_key.as_ref())
+        .map_err(|e| format!("invalid local master key in memo: {e:?}"))?;
+    let local_sk = master_sk_to_local_sk(&local_master_sk)
+        .map_err(|e| format!("failed to derive the local key: {e:?}"))?;
+    let include_taproot = memo.pool_contract_puzzle_hash.is_some();
+    let plot_public_key: Bytes48 =
+        generate_plot_public_key(&local_sk.sk_to_pk(), &farmer_pk, include_taproot)
+            .map_err(|e| format!("failed to build the plot key: {e}"))?
+            .to_bytes()
+            .into();
+    let computed_id = match (&memo.pool_contract_puzzle_hash, &memo.pool_public_key) {
+        (Some(contract), _) => calculate_plot_id_puzzle_hash(contract, &plot_public_key),
+        (None, Some(pool_public_key)) => {
+            calculate_plot_id_public_key(pool_public_key, &plot_public_key)
+        }
+        (None, None) => return Err("memo has neither a pool key nor a pool contract".to_string()),
+    };
+    if computed_id != *plot_id {
+        return Err(format!(
+            "Key Mismatch, the configured keys build plot key {plot_public_key} and plot id {computed_id}, the plot id is {plot_id}{}",
+            if include_taproot {
+                " (with taproot)"
+            } else {
+                ""
+            }
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::signing::PlotSigner;
+
+    #[test]
+    fn detects_plots_built_for_other_keys() {
+        let farmer_sk = SecretKey::key_gen(&[1u8; 32], &[]).unwrap();
+        let farmer_pk: Bytes48 = farmer_sk.sk_to_pk().to_bytes().into();
+        let local_master_sk = SecretKey::key_gen(&[2u8; 32], &[]).unwrap();
+        let local_sk = master_sk_to_local_sk(&local_master_sk).unwrap();
+        let contract = Bytes32::from_sized_bytes([3u8; 32]);
+        let memo = PlotMemo {
+            pool_public_key: None,
+            pool_contract_puzzle_hash: Some(contract),
+            farmer_public_key: farmer_pk,
+            local_master_secret_key: Bytes32::from_sized_bytes(local_master_sk.to_bytes()),
+        };
+        let signer = PlotSigner::new(&farmer_sk, &local_sk.sk_to_pk(), true).unwrap();
+        let plot_id =
+            calculate_plot_id_puzzle_hash(&contract, &signer.plot_public_key().to_bytes().into());
+        let keys = [farmer_pk];
+        assert_eq!(
+            verify_plot_keys(&memo, &plot_id, &keys, &[], &[contract]),
+            Ok(())
+        );
+        let err = verify_plot_keys(&memo, &Bytes32::default(), &keys, &[], &[contract]);
+        assert!(err.unwrap_err().starts_with("Key Mismatch"));
+        assert!(verify_plot_keys(&memo, &plot_id, &[], &[], &[contract]).is_err());
+    }
+}
@@ -1,6 +1,29 @@
+use crate::build_info::{VersionReport, BUILD_INFO, LONG_VERSION};
+use crate::control::auth::{is_mutating, ControlIdentity};
+use crate::control::{
+    connect, ControlRequest, FarmStatus, LogLevelParams, LogLevelState, PauseState,
+    RecentEventsParams, ReloadResult, SoftRestartResult, TailParams,
+};
 use crate::farmer::config::{BladebitHarvesterConfig, Config, FarmingInfo, PoolWalletConfig};
+use crate::farmer::discovery::{discover, Discovery, DISCOVERY_TIME};
+use crate::farmer::earnings::format_earnings;
+use crate::farmer::events::{EventFilter, FarmerEvent, FarmerEventKind};
+use crate::farmer::network_stats::{format_time_to_win, EIB};
+use crate::farmer::plot_leaderboard::{LeaderboardParams, LeaderboardSort, PlotScore};
+use crate::farmer::profiling::{
+    ProfileParams, ProfileResult, DEFAULT_PROFILE_SECS, MAX_PROFILE_SECS,
+};
+use crate::farmer::rpc::{EndpointMetrics, FullnodeRpc};
+use crate::farmer::subsystems::{SubsystemParams, SubsystemState};
+use crate::farmer::time_format::format_timestamp;
+use crate::harvesters::dir_latency::DirLatency;
+use crate::harvesters::plot_classification::UnfarmablePlot;
+use crate::harvesters::plot_dirs::validate_plot_dirs;
+use crate::harvesters::stats::HarvesterStatsSnapshot;
 use clap::{Parser, Subcommand};
+use crossterm::style::Stylize;
 use dg_xch_cli::wallets::plotnft_utils::scrounge_for_plotnfts;
+use dg_xch_clients::protocols::pool::SELF_POOLING;
 use dg_xch_clients::rpc::full_node::FullnodeClient;
 use dg_xch_core::blockchain::sized_bytes::Bytes48;
 use dg_xch_core::consensus::constants::CONSENSUS_CONSTANTS_MAP;
@@ -11,13 +34,23 @@ use dg_xch_keys::{
 };
 use dg_xch_puzzles::clvm_puzzles::launcher_id_to_p2_puzzle_hash;
 use dg_xch_puzzles::p2_delegated_puzzle_or_hidden_puzzle::puzzle_hash_for_pk;
-use dialoguer::Confirm;
-use std::collections::HashMap;
+use dialoguer::{Confirm, Select};
+use std::collections::{BTreeMap, HashMap};
 use std::io::{Error, ErrorKind};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tokio::io::{split, AsyncBufReadExt, AsyncWriteExt, BufReader};
+use uuid::Uuid;
+
+pub mod backup;
+pub mod e2e;
+pub mod keys;
+pub mod pool;
+pub mod replay;
+pub mod simulate;
+pub mod validate;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, long_version = LONG_VERSION.as_str(), about, long_about = None)]
 pub struct Cli {
     #[command(subcommand)]
     pub action: Option<Action>,
@@ -28,7 +61,17 @@ pub struct Cli {
 #[derive(Debug, Subcommand)]
 pub enum Action {
     Gui {},
-    Run {},
+    Run {
+        //Record the farmer and harvester protocol messages as json lines for `ff replay`
+        #[arg(long, value_name = "FILE")]
+        capture: Option<String>,
+        //Allow `ff profile` to capture CPU profiles, needs a build with the profiling feature
+        #[arg(long)]
+        profile: bool,
+        //Inject the failures of a scenario file, needs a build with the chaos feature
+        #[arg(long, value_name = "FILE")]
+        chaos: Option<String>,
+    },
     Init {
         #[arg(short, long)]
         mnemonic: String,
@@ -48,11 +91,208 @@ pub enum Action {
         payout_address: Option<String>,
         #[arg(short = 'd', long = "plot-directory")]
         plot_directories: Option<Vec<String>>,
+        //Look for a full node on the local network when no host is given
+        #[arg(long)]
+        discover: bool,
+    },
+    Tail {
+        #[arg(short, long)]
+        filter: Option<EventFilter>,
+    },
+    Events {
+        #[arg(short, long, default_value_t = 50)]
+        limit: usize,
+        #[arg(short, long)]
+        filter: Option<EventFilter>,
+    },
+    RpcMetrics {},
+    Harvesters {},
+    //Proofs and partial points per plot file since the farmer first ran
+    Leaderboard {
+        #[arg(short, long, value_enum, default_value_t = LeaderboardSort::Points)]
+        sort: LeaderboardSort,
+        //0 lists every plot
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+        //Lowest first, to find plots that never produce
+        #[arg(short, long)]
+        ascending: bool,
+    },
+    Plots {},
+    Dirs {},
+    Pause {},
+    Resume {},
+    Status {
+        #[arg(long)]
+        json: bool,
+    },
+    //Build, protocol and network details for bug reports, works without a running farmer
+    Version {
+        #[arg(long)]
+        json: bool,
+    },
+    Reload {},
+    SoftRestart {},
+    LogLevel {
+        level: Option<String>,
+    },
+    //CPU profile of the signing and lookup paths, the farmer has to run with --profile
+    Profile {
+        #[arg(short, long, default_value_t = DEFAULT_PROFILE_SECS)]
+        seconds: u64,
+        //Keep samples outside the signing and lookup paths
+        #[arg(long)]
+        all: bool,
+    },
+    Simulate {
+        #[arg(short, long, default_value_t = 7)]
+        days: u64,
+        #[arg(long)]
+        difficulty: Option<u64>,
+        #[arg(short, long, default_value_t = 10000)]
+        runs: usize,
+        #[arg(short, long)]
+        netspace_eib: Option<f64>,
+    },
+    Keys {
+        #[command(subcommand)]
+        action: KeysAction,
+    },
+    Pool {
+        #[command(subcommand)]
+        action: PoolAction,
+    },
+    //Stops and starts background tasks of a running farmer, like the dashboard or the webhooks
+    Subsystem {
+        #[command(subcommand)]
+        action: SubsystemAction,
+    },
+    Backup {
+        #[arg(short, long, default_value = "farm-backup.tar.zst")]
+        out: String,
+        //Passphrase from FF_BACKUP_PASSPHRASE or a prompt
+        #[arg(short, long)]
+        encrypt: bool,
+    },
+    Restore {
+        input: String,
+        //Replace existing files, stop the farmer first
+        #[arg(short, long)]
+        force: bool,
+    },
+    Replay {
+        capture: String,
+        //Write the messages the farmer would have sent to the full node
+        #[arg(short, long)]
+        out: Option<String>,
+    },
+    ValidateConfig {
+        //Validate with the host vars of another host instead of this one
+        #[arg(long)]
+        host: Option<String>,
+        //Validate every host that has a file in host_vars
+        #[arg(short, long)]
+        all_hosts: bool,
+    },
+    //Farms a block with tiny plots on a simulator or testnet node, exits non zero when none is
+    E2e {
+        //chia executable used to create the plots
+        #[arg(long, default_value = "chia")]
+        chia: String,
+        //Defaults to e2e/plots in the farmer root, plots already there are reused
+        #[arg(long)]
+        plot_dir: Option<String>,
+        #[arg(short, long, default_value_t = 18)]
+        k: u8,
+        #[arg(short, long, default_value_t = 2)]
+        plots: u32,
+        //Seconds to wait for a farmed block
+        #[arg(short, long, default_value_t = 1800)]
+        timeout: u64,
     },
 }
 impl Default for Action {
+    //Builds without the TUI farm headless when no command is given
     fn default() -> Self {
-        Action::Gui {}
+        if cfg!(feature = "gui") {
+            Action::Gui {}
+        } else {
+            Action::Run {
+                capture: None,
+                profile: false,
+                chaos: None,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeysAction {
+    Verify {
+        //Plots checked per directory, 0 checks all of them
+        #[arg(short, long, default_value_t = 5)]
+        sample: usize,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PoolAction {
+    Migrate {
+        #[arg(short, long)]
+        launcher_id: String,
+        #[arg(short, long)]
+        pool_url: String,
+        //Skip the confirmation before the spends are submitted
+        #[arg(short, long)]
+        yes: bool,
+    },
+    //Prints a signed login link for the pool's web ui
+    Login {
+        #[arg(short, long)]
+        launcher_id: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SubsystemAction {
+    List {},
+    Start { name: String },
+    Stop { name: String },
+}
+
+#[derive(Parser, Debug)]
+#[command(author, version, about = "Standalone harvester for dg_fast_farmer or a chia farmer", long_about = None)]
+pub struct HarvesterCli {
+    #[command(subcommand)]
+    pub action: Option<HarvesterAction>,
+    #[arg(short, long, value_name = "FILE")]
+    pub config: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HarvesterAction {
+    Run {},
+    Init {
+        #[arg(short = 'f', long)]
+        farmer_host: Option<String>,
+        #[arg(short = 'p', long)]
+        farmer_port: Option<u16>,
+        #[arg(short = 's', long)]
+        ssl_root_path: Option<String>,
+        #[arg(short = 'n', long)]
+        network: Option<String>,
+        #[arg(short = 'd', long = "plot-directory")]
+        plot_directories: Option<Vec<String>>,
+        //Reuse the farmer of a harvester advertising on the local network when no host is given
+        #[arg(long)]
+        discover: bool,
+        #[arg(long)]
+        advertise: bool,
+    },
+}
+impl Default for HarvesterAction {
+    fn default() -> Self {
+        HarvesterAction::Run {}
     }
 }
 
@@ -68,6 +308,42 @@ pub struct GenerateConfig<'a> {
     pub payout_address: Option<String>,
     pub plot_directories: Option<Vec<String>>,
     pub additional_headers: Option<HashMap<String, String>>,
+    pub discover: bool,
+}
+
+fn choose_full_node(discovery: Discovery) -> Result<Option<String>, Error> {
+    for harvester in &discovery.harvesters {
+        println!(
+            "Found harvester {} ({}) farming to {}:{}",
+            harvester.name,
+            harvester
+                .addresses
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            harvester.farmer_host.as_deref().unwrap_or("?"),
+            harvester.farmer_port.unwrap_or_default()
+        );
+    }
+    if discovery.full_nodes.is_empty() {
+        println!("No full node found on the local network");
+        return Ok(None);
+    }
+    let mut items = discovery.full_nodes.clone();
+    items.push("None of these".to_string());
+    let selected = Select::new()
+        .with_prompt("Full node to farm with")
+        .items(&items)
+        .default(0)
+        .interact()
+        .map_err(|e| {
+            Error::new(
+                ErrorKind::Interrupted,
+                format!("Dialog Interrupted: {:?}", e),
+            )
+        })?;
+    Ok(discovery.full_nodes.into_iter().nth(selected))
 }
 
 pub async fn generate_config_from_mnemonic(
@@ -104,24 +380,46 @@ pub async fn generate_config_from_mnemonic(
         .unwrap_or("mainnet".to_string());
     config.selected_network = network;
     config.payout_address = gen_settings.payout_address.unwrap_or_default();
-    config.harvester_configs.bladebit = Some(BladebitHarvesterConfig {
+    let bladebit = BladebitHarvesterConfig {
         plot_directories: gen_settings.plot_directories.unwrap_or_default(),
-    });
+        ..Default::default()
+    };
+    if let Some(e) = validate_plot_dirs(&bladebit).into_iter().next() {
+        return Err(e.into());
+    }
+    config.harvester_configs.bladebit = Some(bladebit);
     let master_key = key_from_mnemonic(gen_settings.mnemonic)?;
+    let discovered = if gen_settings.discover && gen_settings.fullnode_ws_host.is_none() {
+        choose_full_node(
+            discover(
+                gen_settings.fullnode_ws_port.unwrap_or(8444),
+                DISCOVERY_TIME,
+            )
+            .await,
+        )?
+    } else {
+        None
+    };
     config.fullnode_ws_host = gen_settings
         .fullnode_ws_host
+        .or(discovered.clone())
         .unwrap_or(String::from("localhost"));
     config.fullnode_rpc_host = gen_settings
         .fullnode_rpc_host
+        .or(discovered)
         .unwrap_or(String::from("localhost"));
     config.fullnode_ws_port = gen_settings.fullnode_ws_port.unwrap_or(8444);
     config.fullnode_rpc_port = gen_settings.fullnode_rpc_port.unwrap_or(8555);
     config.ssl_root_path = gen_settings.fullnode_ssl.clone();
-    let client = FullnodeClient::new(
-        &config.fullnode_rpc_host,
-        config.fullnode_rpc_port,
-        gen_settings.fullnode_ssl,
-        &gen_settings.additional_headers,
+    let client = FullnodeRpc::with_client(
+        FullnodeClient::new(
+            &config.fullnode_rpc_host,
+            config.fullnode_rpc_port,
+            gen_settings.fullnode_ssl,
+            &gen_settings.additional_headers,
+        ),
+        config.fullnode_rpc_policy.clone(),
+        Default::default(),
     );
     let mut page = 0;
     let mut plotnfs = vec![];
@@ -145,14 +443,29 @@ pub async fn generate_config_from_mnemonic(
             let pub_key: Bytes48 = hardened_wallet_sk.sk_to_pk().to_bytes().into();
             puzzle_hashes.push(puzzle_hash_for_pk(&pub_key)?);
         }
-        plotnfs.extend(scrounge_for_plotnfts(&client, &puzzle_hashes).await?);
+        plotnfs.extend(
+            client
+                .call("scrounge_for_plotnfts", |c| {
+                    scrounge_for_plotnfts(c, &puzzle_hashes)
+                })
+                .await?,
+        );
         page += 1;
     }
     for plot_nft in plotnfs {
         config.pool_info.push(PoolWalletConfig {
             difficulty: None,
+            partial_submit_percent: None,
+            tls: None,
+            partial_metadata: None,
+            endpoints: None,
             launcher_id: plot_nft.launcher_id,
-            pool_url: plot_nft.pool_state.pool_url.unwrap_or_default(),
+            //Self pooling singletons can carry the url of the pool they left
+            pool_url: if plot_nft.pool_state.state == SELF_POOLING {
+                String::new()
+            } else {
+                plot_nft.pool_state.pool_url.unwrap_or_default()
+            },
             target_puzzle_hash: plot_nft.pool_state.target_puzzle_hash,
             p2_singleton_puzzle_hash: launcher_id_to_p2_puzzle_hash(
                 &plot_nft.launcher_id,
@@ -192,6 +505,9 @@ pub async fn generate_config_from_mnemonic(
                 pool_secret_key: Some(master_sk_to_pool_sk(&master_key)?.into()),
                 owner_secret_key: owner_key,
                 auth_secret_key: auth_key,
+                keyring: None,
+                farmer_public_key: None,
+                pool_public_key: None,
             });
         }
     }
@@ -200,3 +516,578 @@ pub async fn generate_config_from_mnemonic(
     }
     Ok(config)
 }
+
+pub async fn tail_events(socket_path: &Path, filter: Option<EventFilter>) -> Result<(), Error> {
+    let stream = connect(socket_path).await.map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!(
+                "Failed to connect to control socket at {:?}, is the farmer running? {e}",
+                socket_path
+            ),
+        )
+    })?;
+    let (reader, mut writer) = split(stream);
+    let mut request = serde_json::to_string(&ControlRequest::new(
+        "tail",
+        serde_json::to_value(TailParams { filter })
+            .map_err(|e| Error::other(format!("{:?}", e)))?,
+    ))
+    .map_err(|e| Error::other(format!("{:?}", e)))?;
+    request.push('\n');
+    writer.write_all(request.as_bytes()).await?;
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        match serde_json::from_str::<FarmerEvent>(&line) {
+            Ok(event) => println!("{}", format_event(&event)),
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+//Sends a single request to the control socket and returns the response line
+async fn control_call(socket_path: &Path, method: &str) -> Result<Option<String>, Error> {
+    control_call_with_params(socket_path, method, serde_json::Value::Null).await
+}
+
+async fn control_call_with_params(
+    socket_path: &Path,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<Option<String>, Error> {
+    let stream = connect(socket_path).await.map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!(
+                "Failed to connect to control socket at {:?}, is the farmer running? {e}",
+                socket_path
+            ),
+        )
+    })?;
+    let (reader, mut writer) = split(stream);
+    let mut request = ControlRequest::new(method, params);
+    //Mutating methods are signed with the identity key the farmer created next to the config
+    if is_mutating(method) {
+        let identity = ControlIdentity::load(&crate::get_control_key_path()).map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!(
+                    "Failed to load control identity key at {:?}: {e}",
+                    crate::get_control_key_path()
+                ),
+            )
+        })?;
+        identity.sign_request(&mut request);
+    }
+    let mut request =
+        serde_json::to_string(&request).map_err(|e| Error::other(format!("{:?}", e)))?;
+    request.push('\n');
+    writer.write_all(request.as_bytes()).await?;
+    let mut lines = BufReader::new(reader).lines();
+    lines.next_line().await
+}
+
+pub async fn print_recent_events(
+    socket_path: &Path,
+    limit: usize,
+    filter: Option<EventFilter>,
+) -> Result<(), Error> {
+    let params = serde_json::to_value(RecentEventsParams {
+        limit: Some(limit),
+        filter,
+    })
+    .map_err(|e| Error::other(format!("{:?}", e)))?;
+    if let Some(line) = control_call_with_params(socket_path, "recent_events", params).await? {
+        match serde_json::from_str::<Vec<FarmerEvent>>(&line) {
+            Ok(events) if events.is_empty() => println!("No events recorded yet"),
+            Ok(events) => {
+                for event in events {
+                    println!("{}", format_event(&event));
+                }
+            }
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+pub async fn print_rpc_metrics(socket_path: &Path) -> Result<(), Error> {
+    if let Some(line) = control_call(socket_path, "rpc_metrics").await? {
+        match serde_json::from_str::<BTreeMap<String, EndpointMetrics>>(&line) {
+            Ok(metrics) => {
+                for (endpoint, m) in metrics {
+                    println!(
+                        "{endpoint}: calls={} ok={} failed={} timeouts={} retries={} rejected={} avg_latency_ms={}",
+                        m.calls,
+                        m.successes,
+                        m.failures,
+                        m.timeouts,
+                        m.retries,
+                        m.rejected,
+                        m.total_latency_ms.checked_div(m.successes).unwrap_or(0)
+                    );
+                    if let Some(e) = m.last_error {
+                        println!("  last error: {e}");
+                    }
+                }
+            }
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+pub async fn print_harvester_stats(socket_path: &Path) -> Result<(), Error> {
+    if let Some(line) = control_call(socket_path, "harvester_stats").await? {
+        match serde_json::from_str::<HarvesterStatsSnapshot>(&line) {
+            Ok(snapshot) => {
+                let rows = snapshot
+                    .harvesters
+                    .iter()
+                    .map(|(id, stats)| (id.to_string(), stats))
+                    .chain([("farm".to_string(), &snapshot.farm)]);
+                for (name, stats) in rows {
+                    let decompression = stats
+                        .decompression
+                        .map(|d| format!(" decompression={d}"))
+                        .unwrap_or_default();
+                    println!(
+                        "{name}: plots={} space={} og={} pool={} compressed={} signage_points={} eligible_per_sp={:.2} proofs={} partials={}{decompression}",
+                        stats.plots.count,
+                        bytefmt::format_to(stats.plots.space, bytefmt::Unit::TIB),
+                        stats.plots.og,
+                        stats.plots.pool,
+                        stats.plots.compressed,
+                        stats.signage_points,
+                        stats.eligible_per_sp(),
+                        stats.proofs,
+                        stats.partials
+                    );
+                }
+            }
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+pub async fn print_plot_leaderboard(
+    socket_path: &Path,
+    params: LeaderboardParams,
+) -> Result<(), Error> {
+    let params = serde_json::to_value(params).map_err(Error::other)?;
+    if let Some(line) = control_call_with_params(socket_path, "plot_leaderboard", params).await? {
+        match serde_json::from_str::<Vec<PlotScore>>(&line) {
+            Ok(scores) => {
+                let width = scores
+                    .iter()
+                    .map(|s| s.plot.len())
+                    .max()
+                    .unwrap_or_default();
+                for score in &scores {
+                    println!(
+                        "{:width$} proofs={} partials={} points={} last_proof={}{}",
+                        score.plot,
+                        score.proofs,
+                        score.partials,
+                        score.points,
+                        score
+                            .last_proof
+                            .map(format_timestamp)
+                            .unwrap_or_else(|| "never".to_string()),
+                        if score.loaded { "" } else { " (not loaded)" }
+                    );
+                }
+                if scores.is_empty() {
+                    println!("No plots found a proof yet");
+                }
+            }
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+pub async fn print_unfarmable_plots(socket_path: &Path) -> Result<(), Error> {
+    if let Some(line) = control_call(socket_path, "unfarmable_plots").await? {
+        match serde_json::from_str::<BTreeMap<Uuid, Vec<UnfarmablePlot>>>(&line) {
+            Ok(snapshot) => {
+                let mut total = 0;
+                for (id, plots) in snapshot {
+                    for plot in &plots {
+                        println!("{id}: {} ({:?}) {}", plot.path, plot.kind, plot.reason);
+                    }
+                    total += plots.len();
+                }
+                if total == 0 {
+                    println!("All plots can be farmed with the configured keys");
+                }
+            }
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+pub async fn print_dir_latency(socket_path: &Path) -> Result<(), Error> {
+    if let Some(line) = control_call(socket_path, "dir_latency").await? {
+        match serde_json::from_str::<Vec<DirLatency>>(&line) {
+            Ok(latencies) => {
+                for latency in &latencies {
+                    let drive = match &latency.drive {
+                        Some(drive) => match (&drive.error, drive.flags.is_empty()) {
+                            (Some(error), _) => format!(" drive={} error={error}", drive.device),
+                            (None, healthy) => format!(
+                                " drive={} temperature={} {}",
+                                drive.device,
+                                drive
+                                    .temperature_c
+                                    .map(|t| format!("{t}°C"))
+                                    .unwrap_or_else(|| "-".to_string()),
+                                if healthy {
+                                    "healthy".to_string().green()
+                                } else {
+                                    drive.flags.join(", ").red()
+                                }
+                            ),
+                        },
+                        None => String::new(),
+                    };
+                    println!(
+                        "{}: average_ms={:.1} signage_points={}{drive}",
+                        latency.dir, latency.average_ms, latency.signage_points
+                    );
+                }
+                if latencies.is_empty() {
+                    println!("No lookups measured yet");
+                }
+            }
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+pub async fn set_paused(socket_path: &Path, paused: bool) -> Result<(), Error> {
+    let method = if paused { "pause" } else { "resume" };
+    if let Some(line) = control_call(socket_path, method).await? {
+        match serde_json::from_str::<PauseState>(&line) {
+            Ok(state) if state.paused => println!("Farming paused"),
+            Ok(_) => println!("Farming resumed"),
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+pub async fn set_subsystem(socket_path: &Path, action: SubsystemAction) -> Result<(), Error> {
+    let (method, name) = match action {
+        SubsystemAction::List {} => ("subsystems", None),
+        SubsystemAction::Start { name } => ("start_subsystem", Some(name)),
+        SubsystemAction::Stop { name } => ("stop_subsystem", Some(name)),
+    };
+    let line = match name {
+        None => control_call(socket_path, method).await?,
+        Some(name) => {
+            control_call_with_params(
+                socket_path,
+                method,
+                serde_json::to_value(SubsystemParams { name })
+                    .map_err(|e| Error::other(format!("{:?}", e)))?,
+            )
+            .await?
+        }
+    };
+    if let Some(line) = line {
+        match serde_json::from_str::<Vec<SubsystemState>>(&line) {
+            Ok(states) => {
+                for state in states {
+                    let status = match (state.configured, state.running) {
+                        (false, _) => "not configured".dark_grey(),
+                        (true, true) => "running".green(),
+                        (true, false) => "stopped".yellow(),
+                    };
+                    println!("{:<14} {status}", state.name.to_string());
+                }
+            }
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+pub fn print_version(config: Option<&Config>, json: bool) -> Result<(), Error> {
+    let report = VersionReport {
+        build: BUILD_INFO.clone(),
+        configured_network: config.map(|c| c.selected_network.clone()),
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for (i, line) in report.build.lines().iter().enumerate() {
+            if i == 0 {
+                println!("{} {line}", env!("CARGO_PKG_NAME"));
+            } else {
+                println!("{line}");
+            }
+        }
+        if let Some(network) = report.configured_network {
+            println!("configured_network={network}");
+        }
+    }
+    Ok(())
+}
+
+pub async fn print_status(socket_path: &Path, json: bool) -> Result<(), Error> {
+    if let Some(line) = control_call(socket_path, "status").await? {
+        if json {
+            println!("{line}");
+            return Ok(());
+        }
+        match serde_json::from_str::<FarmStatus>(&line) {
+            Ok(status) => {
+                println!("{} on {}", status.version, status.network);
+                //Farmers from before the build details have none
+                if let Some(build) = &status.build {
+                    //The network list only matters for `ff version`
+                    for (i, line) in build.lines().iter().take(3).enumerate() {
+                        if i == 0 {
+                            println!("build {line}");
+                        } else {
+                            println!("{line}");
+                        }
+                    }
+                }
+                println!(
+                    "paused={} standby={} harvester_only={} full_node_connected={} harvesters={} plots={} invalid_messages={} declarations_skipped={} log_level={}",
+                    status.paused,
+                    status.standby,
+                    status.harvester_only,
+                    status.full_node_connected,
+                    status.harvesters,
+                    status.plots,
+                    status.invalid_messages,
+                    status.declarations_skipped,
+                    status.log_level
+                );
+                if let Some(watch_only) = status.watch_only {
+                    println!(
+                        "watch_only proofs={} partials={} points={}",
+                        watch_only.proofs, watch_only.partials, watch_only.points
+                    );
+                }
+                if let Some(sub_slot) = status.sub_slot {
+                    println!(
+                        "challenge={} difficulty={} sub_slot_iters={}",
+                        sub_slot.challenge_hash, sub_slot.difficulty, sub_slot.sub_slot_iters
+                    );
+                }
+                if let Some(network) = status.network_stats {
+                    println!(
+                        "height={} netspace={:.3}EiB network_difficulty={} effective_space={} time_to_win={}",
+                        network.height,
+                        network.netspace as f64 / EIB,
+                        network.difficulty,
+                        bytefmt::format_to(network.effective_space, bytefmt::Unit::TIB),
+                        format_time_to_win(network.time_to_win)
+                    );
+                }
+                if let Some(drift) = &status.clock_drift {
+                    println!(
+                        "clock_offset={}ms server={} round_trip={}ms",
+                        drift.offset_ms, drift.server, drift.round_trip_ms
+                    );
+                }
+                if !status.signing_steps.is_empty() {
+                    println!(
+                        "signing {}",
+                        status
+                            .signing_steps
+                            .iter()
+                            .map(|(step, s)| format!("{step}={}/{}us", s.average_us(), s.max_us))
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    );
+                }
+                if let Some(availability) = &status.availability {
+                    println!(
+                        "availability 24h={:.2}% ({}/{} signage points) 7d={:.2}% ({}/{})",
+                        availability.day.percent,
+                        availability.day.farmed,
+                        availability.day.expected,
+                        availability.week.percent,
+                        availability.week.farmed,
+                        availability.week.expected
+                    );
+                }
+                for (peer, rtt) in &status.protocol_rtt {
+                    println!(
+                        "rtt {peer} last={:.1}ms avg={:.1}ms p95={:.1}ms max={:.1}ms samples={}",
+                        rtt.last_ms, rtt.avg_ms, rtt.p95_ms, rtt.max_ms, rtt.count
+                    );
+                }
+                for (launcher_id, stats) in &status.launchers {
+                    println!(
+                        "launcher={launcher_id} pool={} difficulty={} proofs={} partials_submitted={} partials_accepted={} partials_rejected={} partials_stale={} partials_stale_local={} partials_suppressed={} partials_duplicate={} auth_failures={}",
+                        if stats.pool_host.is_empty() { "none" } else { &stats.pool_host },
+                        stats
+                            .difficulty
+                            .map(|d| d.to_string())
+                            .unwrap_or_else(|| "unknown".to_string()),
+                        stats.proofs_declared,
+                        stats.partials_submitted,
+                        stats.partials_accepted,
+                        stats.partials_rejected,
+                        stats.partials_stale,
+                        stats.partials_stale_local,
+                        stats.partials_suppressed,
+                        stats.partials_duplicate,
+                        stats.auth_failures
+                    );
+                    if let (Some(local), Some(credited)) =
+                        (stats.points_local, stats.points_credited)
+                    {
+                        println!(
+                            "launcher={launcher_id} points_local={local} points_credited={credited} pool_samples={}",
+                            stats.pool_points.len()
+                        );
+                    }
+                    if let Some(earnings) = &stats.earnings {
+                        println!(
+                            "launcher={launcher_id} pool_fee={:.1}% estimated_space={} earnings={}",
+                            stats.pool_fee.unwrap_or_default() * 100.0,
+                            bytefmt::format_to(earnings.estimated_space, bytefmt::Unit::TIB),
+                            format_earnings(earnings)
+                        );
+                    }
+                }
+                for disabled in status.disabled {
+                    println!("{disabled}");
+                }
+            }
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+pub async fn reload_config(socket_path: &Path) -> Result<(), Error> {
+    if let Some(line) = control_call(socket_path, "reload").await? {
+        match serde_json::from_str::<ReloadResult>(&line) {
+            Ok(result) => {
+                println!("Config is valid, pool info is being refreshed");
+                if !result.changed.is_empty() {
+                    println!(
+                        "Changed since start, restart the farmer to apply: {}",
+                        result.changed.join(", ")
+                    );
+                }
+                for disabled in result.disabled {
+                    println!("{disabled}");
+                }
+            }
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+pub async fn soft_restart(socket_path: &Path) -> Result<(), Error> {
+    if let Some(line) = control_call(socket_path, "soft_restart").await? {
+        match serde_json::from_str::<SoftRestartResult>(&line) {
+            Ok(result) => {
+                if result.applied.is_empty() {
+                    println!("Soft restart requested, reloading keys and harvesters");
+                } else {
+                    println!(
+                        "Soft restart requested, applying: {}",
+                        result.applied.join(", ")
+                    );
+                }
+                if !result.restart_required.is_empty() {
+                    println!(
+                        "Restart the farmer to apply: {}",
+                        result.restart_required.join(", ")
+                    );
+                }
+                for disabled in result.disabled {
+                    println!("{disabled}");
+                }
+            }
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+pub async fn log_level(socket_path: &Path, level: Option<String>) -> Result<(), Error> {
+    let params = serde_json::to_value(LogLevelParams { level })
+        .map_err(|e| Error::other(format!("{:?}", e)))?;
+    if let Some(line) = control_call_with_params(socket_path, "log_level", params).await? {
+        match serde_json::from_str::<LogLevelState>(&line) {
+            Ok(state) => println!("Log level is {}", state.level),
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+pub async fn capture_profile(socket_path: &Path, seconds: u64, all: bool) -> Result<(), Error> {
+    let params = serde_json::to_value(ProfileParams {
+        seconds: Some(seconds),
+        all,
+    })
+    .map_err(|e| Error::other(format!("{:?}", e)))?;
+    println!("Capturing for {}s", seconds.clamp(1, MAX_PROFILE_SECS));
+    if let Some(line) = control_call_with_params(socket_path, "profile", params).await? {
+        match serde_json::from_str::<ProfileResult>(&line) {
+            Ok(result) => {
+                println!("{} samples over {}s", result.samples, result.seconds);
+                println!("Flamegraph: {}", result.flamegraph);
+                println!("pprof: {}", result.pprof);
+            }
+            Err(_) => eprintln!("{line}"),
+        }
+    }
+    Ok(())
+}
+
+fn format_event(event: &FarmerEvent) -> String {
+    let timestamp = format_timestamp(event.timestamp);
+    let message = event.kind.to_string();
+    let message = match &event.kind {
+        FarmerEventKind::SignagePoint { .. } => message.dark_grey(),
+        FarmerEventKind::ProofDeclared { .. }
+        | FarmerEventKind::SignedValues { .. }
+        | FarmerEventKind::PlotRecovered { .. }
+        | FarmerEventKind::FarmResumed {}
+        | FarmerEventKind::FarmActive {}
+        | FarmerEventKind::FarmAwake {} => message.green(),
+        FarmerEventKind::PartialSubmitted { .. } | FarmerEventKind::StartupReport { .. } => {
+            message.cyan()
+        }
+        FarmerEventKind::WatchOnlyProof { .. } | FarmerEventKind::WatchOnlyPartial { .. } => {
+            message.magenta()
+        }
+        FarmerEventKind::PartialAccepted { .. } => message.blue(),
+        FarmerEventKind::PartialRejected { .. }
+        | FarmerEventKind::PartialStaleLocal { .. }
+        | FarmerEventKind::PartialDuplicate { .. }
+        | FarmerEventKind::FullNodeDisconnected { .. }
+        | FarmerEventKind::PlotMissing { .. }
+        | FarmerEventKind::FarmPaused {}
+        | FarmerEventKind::FarmStandby {}
+        | FarmerEventKind::FarmIdle { .. }
+        | FarmerEventKind::PoolPointsDiscrepancy { .. }
+        | FarmerEventKind::LatencyRegression { .. } => message.yellow(),
+        FarmerEventKind::FullNodeConnected { .. } => message.white(),
+        FarmerEventKind::Error { .. }
+        | FarmerEventKind::PlotRemoved { .. }
+        | FarmerEventKind::PlotAuditFailed { .. }
+        | FarmerEventKind::PlotUnreadable { .. }
+        | FarmerEventKind::PayoutMismatch { .. }
+        | FarmerEventKind::KeyHealthFailed { .. } => message.red(),
+    };
+    format!("{} {}", timestamp.dark_grey(), message)
+}
@@ -0,0 +1,479 @@
+use crate::farmer::config::{load_keys, Config};
+use crate::farmer::dns::rpc_host;
+use crate::farmer::pool_client::build_pool_client;
+use crate::farmer::pool_endpoints::login_url;
+use dg_xch_cli::wallet_commands::migrate_plot_nft_with_owner_key;
+use dg_xch_cli::wallets::plotnft_utils::get_plotnft_by_launcher_id;
+use dg_xch_clients::api::pool::{DefaultPoolClient, PoolClient};
+use dg_xch_clients::protocols::pool::{
+    get_current_authentication_token, AuthenticationPayload, FARMING_TO_POOL,
+};
+use dg_xch_clients::rpc::full_node::FullnodeClient;
+use dg_xch_core::blockchain::sized_bytes::{hex_to_bytes, Bytes32, SizedBytes};
+use dg_xch_core::clvm::bls_bindings::sign;
+use dg_xch_serialize::{hash_256, ChiaSerialize};
+use dialoguer::Confirm;
+use reqwest::Url;
+use serde_yaml::Value;
+use std::io::Error;
+use std::path::Path;
+use std::sync::Arc;
+
+//Seconds per block on average, only used to tell how long leaving the pool takes
+const BLOCK_TIME: u64 = 19;
+
+fn parse_launcher_id(launcher_id: &str) -> Result<Bytes32, Error> {
+    match hex_to_bytes(launcher_id) {
+        Ok(bytes) if bytes.len() == 32 => Ok(Bytes32::new(&bytes)),
+        _ => Err(Error::other(format!(
+            "Invalid launcher id {launcher_id}, expected 32 bytes of hex"
+        ))),
+    }
+}
+
+//Pools are only reached over https, a url without a scheme gets it added
+fn parse_pool_url(pool_url: &str) -> Result<String, Error> {
+    let pool_url = pool_url.trim().trim_end_matches('/');
+    let pool_url = if pool_url.contains("://") {
+        pool_url.to_string()
+    } else {
+        format!("https://{pool_url}")
+    };
+    match Url::parse(&pool_url) {
+        Ok(parsed) if parsed.scheme() == "https" => Ok(pool_url),
+        Ok(parsed) => Err(Error::other(format!(
+            "Pool url {pool_url} uses {} instead of https",
+            parsed.scheme()
+        ))),
+        Err(e) => Err(Error::other(format!("Invalid pool url {pool_url}: {e}"))),
+    }
+}
+
+//Prints the pool's login link for a launcher, signed with its authentication key. Uses the
+//endpoints of the pool_info entry, so pools behind a path prefix get a working link
+pub async fn pool_login(config: Config, launcher_id: &str) -> Result<(), Error> {
+    let launcher_id = parse_launcher_id(launcher_id)?;
+    let Some(entry) = config
+        .all_farms()
+        .into_iter()
+        .flat_map(|f| f.pool_info)
+        .find(|p| p.launcher_id == launcher_id)
+    else {
+        return Err(Error::other(format!(
+            "No pool_info entry for launcher {launcher_id}"
+        )));
+    };
+    let clients = build_pool_client(&config);
+    let (_, _, auth_secret_keys, _) = load_keys(Arc::new(config)).await;
+    let Some(auth_key) = auth_secret_keys.get(&entry.owner_public_key) else {
+        return Err(Error::other(format!(
+            "The owner key of launcher {launcher_id} is not in the config, its authentication key signs the login"
+        )));
+    };
+    let pool_info = clients.get_pool_info(&entry.pool_url).await.map_err(|e| {
+        Error::other(format!(
+            "Failed to load pool info of {}: {e:?}",
+            entry.pool_url
+        ))
+    })?;
+    let authentication_token =
+        get_current_authentication_token(pool_info.authentication_token_timeout);
+    let payload = AuthenticationPayload {
+        method_name: String::from("get_login"),
+        launcher_id,
+        target_puzzle_hash: pool_info.target_puzzle_hash,
+        authentication_token,
+    };
+    let signature = sign(auth_key, &hash_256(payload.to_bytes()));
+    println!(
+        "{}",
+        login_url(
+            &entry.pool_url,
+            entry.endpoints.as_ref(),
+            &hex::encode(launcher_id.as_slice()),
+            authentication_token,
+            &hex::encode(signature.to_bytes()),
+        )
+    );
+    Ok(())
+}
+
+//Moves a plot NFT to another pool with the owner key of the config. Leaving the current pool
+//waits out its relative lock height, joining waits until the spend is buried. The pool entry in
+//the config is updated once the singleton farms to the new pool
+pub async fn migrate_pool(
+    config_path: &Path,
+    config: Config,
+    launcher_id: &str,
+    pool_url: &str,
+    yes: bool,
+) -> Result<(), Error> {
+    let launcher_id = parse_launcher_id(launcher_id)?;
+    let pool_url = parse_pool_url(pool_url)?;
+    let Some(entry) = config
+        .all_farms()
+        .into_iter()
+        .flat_map(|f| f.pool_info)
+        .find(|p| p.launcher_id == launcher_id)
+    else {
+        return Err(Error::other(format!(
+            "No pool_info entry for launcher {launcher_id}"
+        )));
+    };
+    let config = Arc::new(config);
+    let (_, owner_secret_keys, _, _) = load_keys(config.clone()).await;
+    let Some(owner_key) = owner_secret_keys.get(&entry.owner_public_key) else {
+        return Err(Error::other(format!(
+            "The owner key of launcher {launcher_id} is not in the config, it signs the pool change"
+        )));
+    };
+    let target = DefaultPoolClient::new()
+        .get_pool_info(&pool_url)
+        .await
+        .map_err(|e| Error::other(format!("Failed to load pool info of {pool_url}: {e:?}")))?;
+    let host = rpc_host(&config, &config.fullnode_rpc_host, config.fullnode_rpc_port).await;
+    let client = FullnodeClient::new(
+        &host,
+        config.fullnode_rpc_port,
+        config.node_ssl_root_path(&config.fullnode_rpc_host, config.fullnode_rpc_port),
+        &None,
+    );
+    let Some(plot_nft) = get_plotnft_by_launcher_id(&client, &launcher_id).await? else {
+        return Err(Error::other(format!(
+            "The full node does not know launcher {launcher_id}"
+        )));
+    };
+    let current = plot_nft
+        .pool_state
+        .pool_url
+        .clone()
+        .filter(|_| plot_nft.pool_state.state == FARMING_TO_POOL);
+    println!("Launcher:  {launcher_id}");
+    println!(
+        "Current:   {}",
+        current.as_deref().unwrap_or("self pooling")
+    );
+    println!(
+        "Target:    {} ({}), fee {}",
+        target.name, pool_url, target.fee
+    );
+    if current.is_some() {
+        println!(
+            "Leaving the current pool waits {} blocks (about {} minutes) after the leave spend, partials keep going to it until then",
+            plot_nft.pool_state.relative_lock_height,
+            plot_nft.pool_state.relative_lock_height as u64 * BLOCK_TIME / 60
+        );
+    }
+    if !yes
+        && !Confirm::new()
+            .with_prompt("Submit the singleton spends? (Y/N)")
+            .interact()
+            .map_err(Error::other)?
+    {
+        println!("Nothing was spent");
+        return Ok(());
+    }
+    migrate_plot_nft_with_owner_key(&client, &pool_url, &launcher_id, owner_key).await?;
+    let joined = get_plotnft_by_launcher_id(&client, &launcher_id)
+        .await?
+        .is_some_and(|p| {
+            p.pool_state.state == FARMING_TO_POOL
+                && p.pool_state.pool_url.as_deref() == Some(pool_url.as_str())
+        });
+    if !joined {
+        return Err(Error::other(format!(
+            "Launcher {launcher_id} does not farm to {pool_url} yet, run the command again once the spends are confirmed"
+        )));
+    }
+    println!("Launcher {launcher_id} farms to {pool_url}");
+    update_config(
+        config_path,
+        &launcher_id,
+        &pool_url,
+        &target.target_puzzle_hash,
+    )
+}
+
+fn update_config(
+    config_path: &Path,
+    launcher_id: &Bytes32,
+    pool_url: &str,
+    target_puzzle_hash: &Bytes32,
+) -> Result<(), Error> {
+    let text = std::fs::read_to_string(config_path)?;
+    let mut value: Value = serde_yaml::from_str(&text)
+        .map_err(|e| Error::other(format!("Failed to parse {:?}: {e}", config_path)))?;
+    //The lines are only written when they parse to the same config as the edited value
+    let edited = update_pool_entry(&mut value, launcher_id, pool_url, target_puzzle_hash)
+        .then(|| edit_pool_entry(&text, launcher_id, pool_url, target_puzzle_hash))
+        .flatten()
+        .filter(|edited| serde_yaml::from_str::<Value>(edited).is_ok_and(|v| v == value));
+    let Some(edited) = edited else {
+        println!(
+            "The pool_info entry of {launcher_id} could not be updated in {:?}, set pool_url: {pool_url} and target_puzzle_hash: {target_puzzle_hash} where it is defined",
+            config_path
+        );
+        return Ok(());
+    };
+    let backup = config_path.with_extension("pre-migration.bak");
+    std::fs::copy(config_path, &backup)?;
+    std::fs::write(config_path, edited)?;
+    println!(
+        "Updated the pool entry in {:?}, the previous file is kept as {:?}. Run ff soft-restart to farm to the new pool",
+        config_path, backup
+    );
+    Ok(())
+}
+
+//Points the pool_info entry of the launcher, at the top level or in a farm, to the new pool. The
+//difficulty is cleared so the farmer starts with the one the new pool assigns
+fn update_pool_entry(
+    value: &mut Value,
+    launcher_id: &Bytes32,
+    pool_url: &str,
+    target_puzzle_hash: &Bytes32,
+) -> bool {
+    let mut lists = vec![];
+    if let Some(mapping) = value.as_mapping_mut() {
+        for (key, section) in mapping.iter_mut() {
+            match key.as_str() {
+                Some("pool_info") => lists.push(section),
+                Some("farms") => {
+                    if let Some(farms) = section.as_sequence_mut() {
+                        lists.extend(farms.iter_mut().filter_map(|f| f.get_mut("pool_info")));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    for entry in lists
+        .into_iter()
+        .filter_map(|l| l.as_sequence_mut())
+        .flatten()
+    {
+        let matches = entry
+            .get("launcher_id")
+            .and_then(|l| serde_yaml::from_value::<Bytes32>(l.clone()).ok())
+            .is_some_and(|l| l == *launcher_id);
+        if !matches {
+            continue;
+        }
+        let (Some(entry), Ok(target)) = (
+            entry.as_mapping_mut(),
+            serde_yaml::to_value(target_puzzle_hash),
+        ) else {
+            return false;
+        };
+        entry.insert("pool_url".into(), pool_url.into());
+        entry.insert("target_puzzle_hash".into(), target);
+        if entry.contains_key("difficulty") {
+            entry.insert("difficulty".into(), Value::Null);
+        }
+        //Endpoints of the old pool would send the requests for the new one elsewhere
+        entry.remove("endpoints");
+        return true;
+    }
+    false
+}
+
+//Rewrites the lines of the launcher's pool_info entry and leaves the rest of the file, comments
+//included, as it is. None for entries that are not written as a block mapping
+fn edit_pool_entry(
+    text: &str,
+    launcher_id: &Bytes32,
+    pool_url: &str,
+    target_puzzle_hash: &Bytes32,
+) -> Option<String> {
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let lines: Vec<&str> = text.lines().collect();
+    //Column the keys of a line start at and the key, after the dash of a sequence item
+    let key_of = |line: &str| {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            return None;
+        }
+        let content = content.strip_prefix("- ").unwrap_or(content).trim_start();
+        let key = content.split_once(':')?.0;
+        Some((line.len() - content.len(), key.to_string()))
+    };
+    let indent = |line: &str| {
+        let content = line.trim_start();
+        (!content.is_empty() && !content.starts_with('#')).then_some(line.len() - content.len())
+    };
+    let (found, column) = lines.iter().enumerate().find_map(|(i, line)| {
+        let (column, key) = key_of(line)?;
+        let value = line[column..].split_once(':')?.1;
+        let value = value.split(" #").next()?.trim();
+        (key == "launcher_id"
+            && serde_yaml::from_str::<Bytes32>(value).is_ok_and(|l| l == *launcher_id))
+        .then_some((i, column))
+    })?;
+    let start = (0..=found).rev().find(|i| {
+        lines[*i].trim_start().starts_with("- ") && key_of(lines[*i]).is_some_and(|k| k.0 == column)
+    })?;
+    if (start + 1..found).any(|i| indent(lines[i]).is_some_and(|c| c < column)) {
+        return None;
+    }
+    let end = (start + 1..lines.len())
+        .find(|i| indent(lines[*i]).is_some_and(|c| c < column))
+        .unwrap_or(lines.len());
+    let scalar = |value: Value| {
+        serde_yaml::to_string(&value)
+            .ok()
+            .map(|s| s.trim_end().to_string())
+    };
+    let url = scalar(pool_url.into())?;
+    let target = scalar(serde_yaml::to_value(target_puzzle_hash).ok()?)?;
+    let mut edited: Vec<String> = lines[..start].iter().map(|l| l.to_string()).collect();
+    let (mut has_url, mut has_target) = (false, false);
+    let mut i = start;
+    while i < end {
+        let line = lines[i];
+        let key = key_of(line)
+            .filter(|k| k.0 == column)
+            .map(|k| k.1)
+            .unwrap_or_default();
+        let value = match key.as_str() {
+            "pool_url" => {
+                has_url = true;
+                Some(url.clone())
+            }
+            "target_puzzle_hash" => {
+                has_target = true;
+                Some(target.clone())
+            }
+            "difficulty" => Some("null".to_string()),
+            //The dash of the item goes with its first key, so only later endpoints are removed
+            "endpoints" if i != start => None,
+            _ => {
+                edited.push(line.to_string());
+                i += 1;
+                continue;
+            }
+        };
+        if let Some(value) = value {
+            edited.push(format!("{}{key}: {value}", &line[..column]));
+        }
+        //Values spread over more indented lines go with their key, comments after them stay
+        let next = (i + 1..end)
+            .find(|i| indent(lines[*i]).is_some_and(|c| c <= column))
+            .unwrap_or(end);
+        i = (i + 1..next)
+            .rev()
+            .find(|i| indent(lines[*i]).is_some())
+            .map_or(i + 1, |last| last + 1);
+    }
+    if !has_url || !has_target {
+        return None;
+    }
+    edited.extend(lines[end..].iter().map(|l| l.to_string()));
+    let mut edited = edited.join(newline);
+    if text.ends_with('\n') {
+        edited.push_str(newline);
+    }
+    Some(edited)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::config::PoolWalletConfig;
+    use dg_xch_core::blockchain::sized_bytes::Bytes48;
+
+    #[test]
+    fn updates_the_entry_of_the_launcher() {
+        let launcher = Bytes32::from_sized_bytes([1u8; 32]);
+        let entry = PoolWalletConfig {
+            launcher_id: launcher,
+            pool_url: "https://old.pool".to_string(),
+            difficulty: Some(100),
+            target_puzzle_hash: Bytes32::from_sized_bytes([2u8; 32]),
+            p2_singleton_puzzle_hash: Bytes32::from_sized_bytes([3u8; 32]),
+            owner_public_key: Default::default(),
+            partial_submit_percent: None,
+            tls: None,
+            partial_metadata: None,
+            endpoints: None,
+        };
+        let other = PoolWalletConfig {
+            launcher_id: Bytes32::from_sized_bytes([4u8; 32]),
+            ..entry.clone()
+        };
+        let mut value = serde_yaml::to_value(serde_yaml::Mapping::from_iter([
+            (
+                "pool_info".into(),
+                serde_yaml::to_value(vec![other.clone()]).unwrap(),
+            ),
+            (
+                "farms".into(),
+                serde_yaml::to_value(vec![serde_yaml::Mapping::from_iter([(
+                    "pool_info".into(),
+                    serde_yaml::to_value(vec![entry.clone()]).unwrap(),
+                )])])
+                .unwrap(),
+            ),
+        ]))
+        .unwrap();
+        let target = Bytes32::from_sized_bytes([5u8; 32]);
+        assert!(update_pool_entry(
+            &mut value,
+            &launcher,
+            "https://new.pool",
+            &target
+        ));
+        let updated: PoolWalletConfig =
+            serde_yaml::from_value(value["farms"][0]["pool_info"][0].clone()).unwrap();
+        assert_eq!(
+            updated,
+            PoolWalletConfig {
+                pool_url: "https://new.pool".to_string(),
+                target_puzzle_hash: target,
+                difficulty: None,
+                ..entry
+            }
+        );
+        let untouched: PoolWalletConfig =
+            serde_yaml::from_value(value["pool_info"][0].clone()).unwrap();
+        assert_eq!(untouched, other);
+        assert!(!update_pool_entry(
+            &mut value,
+            &Bytes32::from_sized_bytes([6u8; 32]),
+            "https://new.pool",
+            &target
+        ));
+    }
+
+    #[test]
+    fn edits_only_the_pool_entry_lines() {
+        let launcher = Bytes32::from_sized_bytes([1u8; 32]);
+        let target = Bytes32::from_sized_bytes([5u8; 32]);
+        let text = format!(
+            "# farmer config\npool_info:\n  # main pool\n  - launcher_id: {launcher}\n    pool_url: https://old.pool # old\n    difficulty: 100\n    endpoints:\n      partials: \"{{pool_url}}/partials\"\n    # keys of the nft\n    owner_public_key: {}\n    target_puzzle_hash: {}\n    p2_singleton_puzzle_hash: {}\n  - launcher_id: {}\n    pool_url: https://other.pool\n",
+            Bytes48::default(),
+            Bytes32::from_sized_bytes([2u8; 32]),
+            Bytes32::from_sized_bytes([3u8; 32]),
+            Bytes32::from_sized_bytes([4u8; 32]),
+        );
+        let edited = edit_pool_entry(&text, &launcher, "https://new.pool", &target).unwrap();
+        assert_eq!(
+            edited,
+            format!(
+                "# farmer config\npool_info:\n  # main pool\n  - launcher_id: {launcher}\n    pool_url: https://new.pool\n    difficulty: null\n    # keys of the nft\n    owner_public_key: {}\n    target_puzzle_hash: {target}\n    p2_singleton_puzzle_hash: {}\n  - launcher_id: {}\n    pool_url: https://other.pool\n",
+                Bytes48::default(),
+                Bytes32::from_sized_bytes([3u8; 32]),
+                Bytes32::from_sized_bytes([4u8; 32]),
+            )
+        );
+        let mut value: Value = serde_yaml::from_str(&text).unwrap();
+        assert!(update_pool_entry(
+            &mut value,
+            &launcher,
+            "https://new.pool",
+            &target
+        ));
+        assert_eq!(serde_yaml::from_str::<Value>(&edited).unwrap(), value);
+        assert_eq!(parse_pool_url("new.pool/").unwrap(), "https://new.pool");
+        assert!(parse_pool_url("http://new.pool").is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use crate::farmer::capture::{read_capture, CaptureRecord, Direction, ProtocolCapture};
+use crate::farmer::config::{default_payout_address, load_keys, Config};
+use crate::farmer::farms::FarmIndex;
+use crate::farmer::protocols::decode_message;
+use crate::farmer::protocols::fullnode::new_signage_point::NewSignagePointHandle;
+use crate::farmer::protocols::fullnode::request_signed_values::RequestSignedValuesHandle;
+use crate::farmer::protocols::harvester::new_proof_of_space::NewProofOfSpaceHandle;
+use crate::farmer::protocols::harvester::respond_signatures::RespondSignaturesHandler;
+use crate::farmer::FarmerSharedState;
+use crate::harvesters::{ProofHandler, SignatureHandler};
+use dg_xch_clients::api::pool::DefaultPoolClient;
+use dg_xch_clients::protocols::harvester::{NewProofOfSpace, RespondSignatures};
+use dg_xch_clients::protocols::ProtocolMessageTypes;
+use dg_xch_clients::websocket::{ChiaMessage, MessageHandler};
+use dg_xch_keys::decode_puzzle_hash;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Error;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Instant;
+use uuid::Uuid;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReplaySummary {
+    pub replayed: BTreeMap<String, usize>,
+    //Outbound messages and messages whose handler only exists in a live session
+    pub skipped: BTreeMap<String, usize>,
+    pub errors: Vec<String>,
+}
+
+//Feeds the inbound messages of a capture through the farmer handlers. No harvesters are attached,
+//so signage points cause no lookups and the captured harvester responses stand in for them
+pub async fn replay_capture(
+    shared_state: Arc<FarmerSharedState>,
+    records: &[CaptureRecord],
+) -> ReplaySummary {
+    let pool_client = Arc::new(DefaultPoolClient::new());
+    let harvesters = Arc::new(HashMap::new());
+    let mut summary = ReplaySummary::default();
+    for record in records {
+        let data = match record.bytes() {
+            Ok(data) => data,
+            Err(e) => {
+                summary.errors.push(format!("{}: {e}", record.msg_type));
+                continue;
+            }
+        };
+        let harvester_id = Uuid::parse_str(&record.peer).unwrap_or_default();
+        let result = match (record.direction, record.msg_type.as_str()) {
+            (Direction::In, "NewSignagePoint") => {
+                NewSignagePointHandle {
+                    id: Uuid::new_v4(),
+                    harvester_id: Default::default(),
+                    pool_state: shared_state.pool_states.clone(),
+                    pool_client: pool_client.clone(),
+                    shared_state: shared_state.clone(),
+                    harvesters: harvesters.clone(),
+                    constants: shared_state.constants,
+                }
+                .handle(Arc::new(ChiaMessage {
+                    msg_type: ProtocolMessageTypes::NewSignagePoint,
+                    id: None,
+                    data,
+                }))
+                .await
+            }
+            (Direction::In, "RequestSignedValues") => {
+                RequestSignedValuesHandle {
+                    id: Uuid::new_v4(),
+                    shared_state: shared_state.clone(),
+                    pool_client: pool_client.clone(),
+                    harvesters: harvesters.clone(),
+                    constants: shared_state.constants,
+                }
+                .handle(Arc::new(ChiaMessage {
+                    msg_type: ProtocolMessageTypes::RequestSignedValues,
+                    id: None,
+                    data,
+                }))
+                .await
+            }
+            (Direction::In, "NewProofOfSpace") => {
+                match decode_message::<NewProofOfSpace>("NewProofOfSpace", &data) {
+                    Ok(new_pos) => {
+                        NewProofOfSpaceHandle {
+                            pool_client: pool_client.clone(),
+                            shared_state: shared_state.clone(),
+                            harvester_id,
+                            harvester_partial_id: Default::default(),
+                            harvesters: harvesters.clone(),
+                            constants: shared_state.constants,
+                        }
+                        .handle_proof(new_pos)
+                        .await
+                    }
+                    Err(e) => Err(e.into()),
+                }
+            }
+            (Direction::In, "RespondSignatures") => {
+                match decode_message::<RespondSignatures>("RespondSignatures", &data) {
+                    //A partial is signed with a single signature, its payload only existed in the
+                    //live session
+                    Ok(response) if response.message_signatures.len() == 1 => {
+                        *summary.skipped.entry(record.msg_type.clone()).or_default() += 1;
+                        continue;
+                    }
+                    Ok(response) => {
+                        RespondSignaturesHandler {
+                            pool_client: pool_client.clone(),
+                            shared_state: shared_state.clone(),
+                            harvester_id,
+                            harvesters: harvesters.clone(),
+                            constants: shared_state.constants,
+                            requested_at: Instant::now(),
+                        }
+                        .handle_signature(response)
+                        .await
+                    }
+                    Err(e) => Err(e.into()),
+                }
+            }
+            _ => {
+                *summary.skipped.entry(record.msg_type.clone()).or_default() += 1;
+                continue;
+            }
+        };
+        *summary.replayed.entry(record.msg_type.clone()).or_default() += 1;
+        if let Err(e) = result {
+            summary.errors.push(format!("{}: {e}", record.msg_type));
+        }
+    }
+    summary
+}
+
+//Replays a capture with the keys of the config, the messages the farmer would have sent are
+//written to out
+pub async fn replay(config: Config, capture: &Path, out: Option<&Path>) -> Result<(), Error> {
+    let records = read_capture(capture)?;
+    let config = Arc::new(config);
+    let (farmer_private_keys, owner_secret_keys, auth_secret_keys, pool_public_keys) =
+        load_keys(config.clone()).await;
+    let farmer_target =
+        decode_puzzle_hash(&default_payout_address(config.as_ref())).unwrap_or_default();
+    let shared_state = Arc::new(FarmerSharedState {
+        farmer_private_keys: Arc::new(farmer_private_keys),
+        owner_secret_keys: Arc::new(owner_secret_keys),
+        auth_secret_keys: Arc::new(auth_secret_keys),
+        pool_public_keys: Arc::new(pool_public_keys),
+        run: Arc::new(AtomicBool::new(true)),
+        farmer_target: Arc::new(farmer_target),
+        pool_target: Arc::new(farmer_target),
+        farms: Arc::new(FarmIndex::new(config.as_ref())),
+        constants: config.consensus_constants(),
+        config,
+        capture: Arc::new(ProtocolCapture::replay(out)?),
+        ..Default::default()
+    });
+    let summary = replay_capture(shared_state.clone(), &records).await;
+    println!("Replayed {} records of {:?}", records.len(), capture);
+    for (msg_type, count) in &summary.replayed {
+        println!("  {msg_type}: {count}");
+    }
+    for (msg_type, count) in &summary.skipped {
+        println!("  {msg_type}: {count} skipped");
+    }
+    for (handler, count) in &shared_state.gui_stats.lock().await.handler_errors {
+        println!("  {handler} errors: {count}");
+    }
+    for error in &summary.errors {
+        println!("Error: {error}");
+    }
+    if let Some(out) = out {
+        println!("Messages for the full node written to {:?}", out);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::capture::FULL_NODE_PEER;
+    use dg_xch_clients::protocols::farmer::{NewSignagePoint, RequestSignedValues};
+    use dg_xch_core::blockchain::sized_bytes::Bytes32;
+    use dg_xch_serialize::ChiaSerialize;
+
+    fn record<T: ChiaSerialize>(
+        direction: Direction,
+        msg_type: &str,
+        message: &T,
+    ) -> CaptureRecord {
+        CaptureRecord {
+            timestamp_ms: 0,
+            direction,
+            peer: FULL_NODE_PEER.to_string(),
+            msg_type: msg_type.to_string(),
+            data: hex::encode(message.to_bytes()),
+        }
+    }
+
+    #[tokio::test]
+    async fn replays_inbound_messages_through_the_handlers() {
+        let shared_state = Arc::new(FarmerSharedState {
+            capture: Arc::new(ProtocolCapture::replay(None).unwrap()),
+            ..Default::default()
+        });
+        let sp = NewSignagePoint {
+            challenge_hash: Bytes32::from_sized_bytes([1u8; 32]),
+            challenge_chain_sp: Bytes32::from_sized_bytes([2u8; 32]),
+            reward_chain_sp: Bytes32::from_sized_bytes([3u8; 32]),
+            difficulty: 5,
+            sub_slot_iters: 6,
+            signage_point_index: 1,
+        };
+        let unknown_quality = RequestSignedValues {
+            quality_string: Bytes32::from_sized_bytes([4u8; 32]),
+            foliage_block_data_hash: Default::default(),
+            foliage_transaction_block_hash: Default::default(),
+        };
+        let records = vec![
+            record(Direction::In, "NewSignagePoint", &sp),
+            record(Direction::Out, "SignedValues", &sp),
+            record(Direction::In, "RequestSignedValues", &unknown_quality),
+        ];
+        let summary = replay_capture(shared_state.clone(), &records).await;
+        assert_eq!(summary.replayed.len(), 2);
+        assert_eq!(summary.skipped["SignedValues"], 1);
+        assert_eq!(summary.errors.len(), 1);
+        assert!(shared_state.has_signage_point(&sp.challenge_chain_sp).await);
+    }
+}
@@ -0,0 +1,287 @@
+use crate::farmer::config::{BladebitHarvesterConfig, Config};
+use crate::farmer::network_stats::{
+    expected_plot_size, EIB, SUB_SLOTS_PER_DAY, UI_ACTUAL_SPACE_CONSTANT_FACTOR,
+};
+use crate::farmer::plot_filter::PlotFilterSchedule;
+use crate::farmer::rpc::FullnodeRpc;
+use crate::harvesters::plot_scan::PlotScanFilters;
+use dg_xch_core::consensus::constants::ConsensusConstants;
+use dg_xch_core::plots::PlotHeader;
+use dg_xch_pos::plots::plot_reader::read_all_plot_headers_async;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Poisson};
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub struct SimulateOptions {
+    pub days: u64,
+    pub difficulty: Option<u64>,
+    pub runs: usize,
+    pub netspace_eib: Option<f64>,
+}
+
+//Plot counts keyed by (k, compression level)
+#[derive(Debug, Default)]
+pub struct PlotSet {
+    pub plots: BTreeMap<(u8, u8), u64>,
+}
+impl PlotSet {
+    pub async fn scan(bb_config: &BladebitHarvesterConfig) -> Self {
+        let filters = PlotScanFilters::new(bb_config);
+        let mut plots = BTreeMap::new();
+        for dir in bb_config.plot_directories.iter().map(PathBuf::from) {
+            let excluded = filters.filter_for(&dir).excluded_paths(&dir).await;
+            let excluded: Vec<&Path> = excluded.iter().map(|p| p.as_path()).collect();
+            match read_all_plot_headers_async(&dir, &excluded).await {
+                Ok((headers, _)) => {
+                    for (_, header) in headers {
+                        let key = match header {
+                            PlotHeader::V1(h) => (h.k, 0),
+                            PlotHeader::V2(h) => (h.k, h.compression_level),
+                        };
+                        *plots.entry(key).or_default() += 1;
+                    }
+                }
+                Err(e) => eprintln!("Failed to read plots in {:?}: {:?}", dir, e),
+            }
+        }
+        Self { plots }
+    }
+    pub fn count(&self) -> u64 {
+        self.plots.values().sum()
+    }
+    //Compression only changes lookup cost, compressed plots farm like uncompressed ones
+    pub fn effective_space(&self) -> f64 {
+        self.plots
+            .iter()
+            .map(|((k, _), count)| expected_plot_size(*k) * *count as f64)
+            .sum()
+    }
+    pub fn partials_per_day(
+        &self,
+        difficulty: u64,
+        filter_bits: usize,
+        constants: &ConsensusConstants,
+    ) -> f64 {
+        self.plots
+            .iter()
+            .map(|((k, _), count)| {
+                partials_per_plot_day(*k, difficulty, filter_bits, constants) * *count as f64
+            })
+            .sum()
+    }
+}
+
+//A plot passes the filter once every 2^filter_bits signage points with one proof on average,
+//the proof is a partial when its required iterations are below the signage point interval
+pub fn partials_per_plot_day(
+    k: u8,
+    difficulty: u64,
+    filter_bits: usize,
+    constants: &ConsensusConstants,
+) -> f64 {
+    let sps_per_day = constants.num_sps_sub_slot as f64 * SUB_SLOTS_PER_DAY;
+    let filter = 2f64.powi(filter_bits as i32);
+    let sp_interval_iters =
+        constants.pool_sub_slot_iters as f64 / constants.num_sps_sub_slot as f64;
+    let pass_chance = (sp_interval_iters * expected_plot_size(k)
+        / (difficulty.max(1) as f64 * constants.difficulty_constant_factor as f64))
+        .min(1.0);
+    sps_per_day / filter * pass_chance
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Percentiles {
+    pub p5: u64,
+    pub p50: u64,
+    pub p95: u64,
+}
+impl Percentiles {
+    fn from_samples(mut samples: Vec<u64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_unstable();
+        let at = |p: f64| samples[((samples.len() - 1) as f64 * p).round() as usize];
+        Self {
+            p5: at(0.05),
+            p50: at(0.5),
+            p95: at(0.95),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SimulationResult {
+    pub partials: Percentiles,
+    pub worst_day: Percentiles,
+    pub blocks: Percentiles,
+    pub block_chance: f64,
+}
+
+//Runs the period day by day so the spread of single days shows up next to the period totals
+pub fn simulate<R: Rng>(
+    partials_per_day: f64,
+    blocks_per_day: f64,
+    days: u64,
+    runs: usize,
+    rng: &mut R,
+) -> SimulationResult {
+    let partials = Poisson::new(partials_per_day).ok();
+    let blocks = Poisson::new(blocks_per_day).ok();
+    let mut sample =
+        |d: &Option<Poisson<f64>>| d.as_ref().map(|d| d.sample(rng) as u64).unwrap_or(0);
+    let mut partial_totals = Vec::with_capacity(runs);
+    let mut worst_days = Vec::with_capacity(runs);
+    let mut block_totals = Vec::with_capacity(runs);
+    for _ in 0..runs {
+        let (mut partial_total, mut worst_day, mut block_total) = (0, u64::MAX, 0);
+        for _ in 0..days {
+            let day = sample(&partials);
+            partial_total += day;
+            worst_day = worst_day.min(day);
+            block_total += sample(&blocks);
+        }
+        partial_totals.push(partial_total);
+        worst_days.push(if days == 0 { 0 } else { worst_day });
+        block_totals.push(block_total);
+    }
+    SimulationResult {
+        block_chance: 1.0 - (-blocks_per_day * days as f64).exp(),
+        partials: Percentiles::from_samples(partial_totals),
+        worst_day: Percentiles::from_samples(worst_days),
+        blocks: Percentiles::from_samples(block_totals),
+    }
+}
+
+pub async fn print_simulation(config: &Config, options: SimulateOptions) -> Result<(), Error> {
+    let Some(bb_config) = &config.harvester_configs.bladebit else {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            "No plot directories configured",
+        ));
+    };
+    let constants = config.consensus_constants();
+    let plot_set = PlotSet::scan(bb_config).await;
+    if plot_set.count() == 0 {
+        return Err(Error::new(ErrorKind::NotFound, "No plots found"));
+    }
+    let difficulty = options
+        .difficulty
+        .or_else(|| {
+            config
+                .all_farms()
+                .iter()
+                .flat_map(|f| f.pool_info.iter())
+                .find_map(|p| p.difficulty)
+        })
+        .unwrap_or(1);
+    let state = FullnodeRpc::new(config, Arc::new(Default::default()))
+        .await
+        .get_blockchain_state()
+        .await
+        .map_err(|e| eprintln!("Failed to get the blockchain state: {:?}", e))
+        .ok();
+    let netspace = match options.netspace_eib {
+        Some(eib) => Some(eib * EIB),
+        None => state.as_ref().map(|s| s.space as f64),
+    };
+    if netspace.is_none() {
+        eprintln!("No netspace, skipping blocks");
+    }
+    //Partials follow the plot filter at the current height, the consensus filter without a node
+    let filter_bits = PlotFilterSchedule::for_network(&config.selected_network).prefix_bits(
+        constants.number_zero_bits_plot_filter,
+        state
+            .as_ref()
+            .and_then(|s| s.peak.as_ref())
+            .map(|p| p.height)
+            .unwrap_or_default(),
+    );
+    let effective_space = plot_set.effective_space() * UI_ACTUAL_SPACE_CONSTANT_FACTOR;
+    let blocks_per_day = netspace
+        .filter(|n| *n > 0.0)
+        .map(|n| constants.slot_blocks_target as f64 * SUB_SLOTS_PER_DAY * effective_space / n)
+        .unwrap_or(0.0);
+    let partials_per_day = plot_set.partials_per_day(difficulty, filter_bits, constants);
+    let result = simulate(
+        partials_per_day,
+        blocks_per_day,
+        options.days,
+        options.runs,
+        &mut StdRng::from_entropy(),
+    );
+    let plots = plot_set
+        .plots
+        .iter()
+        .map(|((k, c), count)| format!("k{k} C{c}: {count}"))
+        .collect::<Vec<String>>()
+        .join(", ");
+    println!(
+        "Plots: {} ({plots}), effective space {}",
+        plot_set.count(),
+        bytefmt::format_to(effective_space as u64, bytefmt::Unit::TIB)
+    );
+    println!(
+        "{} days at difficulty {difficulty}, {} runs",
+        options.days, options.runs
+    );
+    println!(
+        "Partials: expected {:.1}, p5={} p50={} p95={}, worst day p5={} p50={}",
+        partials_per_day * options.days as f64,
+        result.partials.p5,
+        result.partials.p50,
+        result.partials.p95,
+        result.worst_day.p5,
+        result.worst_day.p50
+    );
+    println!(
+        "Points: expected {:.0}, p5={} p50={} p95={}",
+        partials_per_day * options.days as f64 * difficulty as f64,
+        result.partials.p5 * difficulty,
+        result.partials.p50 * difficulty,
+        result.partials.p95 * difficulty
+    );
+    if let Some(netspace) = netspace {
+        println!(
+            "Blocks: expected {:.3} on {:.2} EiB netspace, chance of at least one {:.1}%, p95={}",
+            blocks_per_day * options.days as f64,
+            netspace / EIB,
+            result.block_chance * 100.0,
+            result.blocks.p95
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dg_xch_core::consensus::constants::MAINNET;
+
+    #[test]
+    fn k32_earns_about_ten_points_per_day() {
+        for difficulty in [1, 10, 1000] {
+            let points = partials_per_plot_day(
+                32,
+                difficulty,
+                MAINNET.number_zero_bits_plot_filter,
+                &MAINNET,
+            ) * difficulty as f64;
+            assert!((points - 10.0).abs() < 0.5, "{points}");
+        }
+    }
+
+    #[test]
+    fn simulation_centers_on_expectation() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let result = simulate(100.0, 0.0, 7, 2000, &mut rng);
+        assert!(result.partials.p5 < 700 && 700 < result.partials.p95);
+        assert!((result.partials.p50 as i64 - 700).abs() < 15);
+        assert_eq!(result.blocks.p95, 0);
+        assert_eq!(result.block_chance, 0.0);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::farmer::config::Config;
+use crate::farmer::host_vars::{host_name, host_vars_dir};
+use crate::farmer::readiness::Readiness;
+use crossterm::style::Stylize;
+use std::io::Error;
+use std::path::Path;
+
+//Loads the config the way the farmer would, with the host vars of each given host merged over
+//it, and prints what would refuse to load or run disabled. Fails when any host fails to load
+pub fn validate_config(
+    config_path: &Path,
+    host: Option<String>,
+    all_hosts: bool,
+) -> Result<(), Error> {
+    let hosts = if all_hosts {
+        let template = serde_yaml::from_str(&std::fs::read_to_string(config_path)?)
+            .map_err(|e| Error::other(format!("{:?}: {e}", config_path)))?;
+        let dir = host_vars_dir(config_path, &template);
+        let mut hosts: Vec<Option<String>> = std::fs::read_dir(&dir)
+            .map_err(|e| Error::other(format!("Failed to list {:?}: {e}", dir)))?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                (path.extension()? == "yaml")
+                    .then(|| path.file_stem()?.to_str().map(str::to_string))
+                    .flatten()
+            })
+            .map(Some)
+            .collect();
+        hosts.sort();
+        hosts
+    } else {
+        vec![host.or_else(host_name)]
+    };
+    let mut failed = 0;
+    for host in &hosts {
+        let name = host.as_deref().unwrap_or("this host");
+        match Config::load_for_host(config_path, host.as_deref()) {
+            Ok((config, host_file)) => {
+                match host_file {
+                    Some(file) => println!("{} {name} (merged {:?})", "OK".green(), file),
+                    None => println!("{} {name} (template only)", "OK".green()),
+                }
+                for disabled in Readiness::check(&config).disabled {
+                    println!("   {}", disabled.to_string().yellow());
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                println!("{} {name}: {e}", "FAILED".red());
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(Error::other(format!(
+            "{failed} of {} configs failed to load",
+            hosts.len()
+        )));
+    }
+    Ok(())
+}
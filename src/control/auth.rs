@@ -0,0 +1,191 @@
+use crate::control::ControlRequest;
+use crate::platform::create_private;
+use blst::min_pk::{PublicKey, SecretKey, Signature};
+use dg_xch_core::clvm::bls_bindings::{sign, verify_signature};
+use log::info;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind, Write};
+use std::path::Path;
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+
+//Signed requests older or further in the future than this are rejected
+const MAX_CLOCK_SKEW: u64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlAuth {
+    pub timestamp: u64,
+    pub signature: String,
+}
+
+//Methods that change the farm, read only methods like tail stay open
+pub fn is_mutating(method: &str) -> bool {
+    matches!(
+        method,
+        "pause"
+            | "resume"
+            | "reload"
+            | "soft_restart"
+            | "log_level"
+            | "profile"
+            | "start_subsystem"
+            | "stop_subsystem"
+    )
+}
+
+//Local keypair of the farmer, control clients prove they can read the key file by signing their requests
+pub struct ControlIdentity {
+    secret_key: SecretKey,
+}
+impl ControlIdentity {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let bytes = hex::decode(fs::read_to_string(path)?.trim())
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{:?}", e)))?;
+        let secret_key = SecretKey::from_bytes(&bytes)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{:?}", e)))?;
+        Ok(Self { secret_key })
+    }
+    pub fn load_or_create(path: &Path) -> Result<Self, Error> {
+        if path.exists() {
+            return Self::load(path);
+        }
+        let mut ikm = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut ikm);
+        let secret_key =
+            SecretKey::key_gen(&ikm, &[]).map_err(|e| Error::other(format!("{:?}", e)))?;
+        create_private(path)?.write_all(hex::encode(secret_key.to_bytes()).as_bytes())?;
+        info!("Created control identity key at {:?}", path);
+        Ok(Self { secret_key })
+    }
+    pub fn public_key(&self) -> PublicKey {
+        self.secret_key.sk_to_pk()
+    }
+    pub fn sign_request(&self, request: &mut ControlRequest) {
+        let timestamp = now();
+        let signature = sign(
+            &self.secret_key,
+            &request_message(&request.method, &request.params, timestamp),
+        );
+        request.auth = Some(ControlAuth {
+            timestamp,
+            signature: hex::encode(signature.to_bytes()),
+        });
+    }
+}
+
+//Wall clock of the machine, unlike the farmer clock it is never skewed by chaos scenarios so
+//clients and the farmer agree on it
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn request_message(method: &str, params: &serde_json::Value, timestamp: u64) -> Vec<u8> {
+    format!("{method}\n{timestamp}\n{params}").into_bytes()
+}
+
+pub struct ControlAuthenticator {
+    public_key: PublicKey,
+    //Signatures seen within the clock skew window, a captured request can not be replayed
+    seen: Mutex<HashMap<String, u64>>,
+}
+impl ControlAuthenticator {
+    pub fn new(identity: &ControlIdentity) -> Self {
+        Self {
+            public_key: identity.public_key(),
+            seen: Default::default(),
+        }
+    }
+    pub async fn verify(&self, request: &ControlRequest) -> Result<(), String> {
+        let Some(auth) = &request.auth else {
+            return Err(format!("{} requires a signed request", request.method));
+        };
+        let now = now();
+        if auth.timestamp.abs_diff(now) > MAX_CLOCK_SKEW {
+            return Err("Request timestamp is outside the allowed window".to_string());
+        }
+        let signature = hex::decode(&auth.signature)
+            .ok()
+            .and_then(|b| Signature::from_bytes(&b).ok())
+            .ok_or_else(|| "Malformed request signature".to_string())?;
+        if !verify_signature(
+            &self.public_key,
+            &request_message(&request.method, &request.params, auth.timestamp),
+            &signature,
+        ) {
+            return Err("Invalid request signature".to_string());
+        }
+        let mut seen = self.seen.lock().await;
+        seen.retain(|_, timestamp| timestamp.abs_diff(now) <= MAX_CLOCK_SKEW);
+        if seen
+            .insert(auth.signature.clone(), auth.timestamp)
+            .is_some()
+        {
+            return Err("Request was already used".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(seed: u8) -> ControlIdentity {
+        ControlIdentity {
+            secret_key: SecretKey::key_gen(&[seed; 32], &[]).unwrap(),
+        }
+    }
+
+    fn request(method: &str) -> ControlRequest {
+        ControlRequest::new(method, serde_json::Value::Null)
+    }
+
+    #[tokio::test]
+    async fn signed_request_is_accepted_once() {
+        let identity = identity(1);
+        let authenticator = ControlAuthenticator::new(&identity);
+        let mut pause = request("pause");
+        assert!(authenticator.verify(&pause).await.is_err());
+        identity.sign_request(&mut pause);
+        assert!(authenticator.verify(&pause).await.is_ok());
+        assert!(authenticator.verify(&pause).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn tampered_or_foreign_requests_are_rejected() {
+        let identity = identity(2);
+        let authenticator = ControlAuthenticator::new(&identity);
+        let mut pause = request("pause");
+        identity.sign_request(&mut pause);
+        pause.method = "resume".to_string();
+        assert!(authenticator.verify(&pause).await.is_err());
+        let mut foreign = request("pause");
+        self::identity(3).sign_request(&mut foreign);
+        assert!(authenticator.verify(&foreign).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn stale_requests_are_rejected() {
+        let identity = identity(4);
+        let authenticator = ControlAuthenticator::new(&identity);
+        let timestamp = now() - MAX_CLOCK_SKEW - 1;
+        let mut pause = request("pause");
+        pause.auth = Some(ControlAuth {
+            timestamp,
+            signature: hex::encode(
+                sign(
+                    &identity.secret_key,
+                    &request_message("pause", &pause.params, timestamp),
+                )
+                .to_bytes(),
+            ),
+        });
+        assert!(authenticator.verify(&pause).await.is_err());
+    }
+}
@@ -0,0 +1,842 @@
+use crate::build_info::{BuildInfo, BUILD_INFO};
+pub mod auth;
+
+use crate::control::auth::{is_mutating, ControlAuth, ControlAuthenticator, ControlIdentity};
+use crate::error::FarmerError;
+use crate::farmer::availability::AvailabilityReport;
+use crate::farmer::clock_drift::ClockDrift;
+use crate::farmer::config::Config;
+use crate::farmer::events::EventFilter;
+use crate::farmer::launcher_stats::LauncherStatsMap;
+use crate::farmer::network_stats::NetworkStats;
+use crate::farmer::partial_queue::now;
+use crate::farmer::plot_leaderboard::{plot_name, LeaderboardParams, PlotScore};
+use crate::farmer::profiling::{capture, ProfileParams, DEFAULT_PROFILE_SECS};
+use crate::farmer::protocol_rtt::RttStats;
+use crate::farmer::readiness::{DisabledFeature, Readiness};
+use crate::farmer::signing::SigningStep;
+use crate::farmer::sp_chain::SubSlotParams;
+use crate::farmer::subsystems::{Subsystem, SubsystemParams};
+use crate::farmer::{FarmerSharedState, SigningStepStats, WatchOnlyStats};
+use log::{debug, error, info, warn, LevelFilter};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{split, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::sleep;
+
+#[cfg(unix)]
+pub type ControlStream = tokio::net::UnixStream;
+#[cfg(windows)]
+pub type ControlStream = tokio::net::windows::named_pipe::NamedPipeClient;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlRequest {
+    //Set by JSON-RPC 2.0 clients, their responses are wrapped in the JSON-RPC envelope
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jsonrpc: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<ControlAuth>,
+}
+impl ControlRequest {
+    pub fn new(method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: None,
+            id: None,
+            method: method.to_string(),
+            params,
+            auth: None,
+        }
+    }
+    fn is_jsonrpc(&self) -> bool {
+        self.jsonrpc.is_some() || self.id.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TailParams {
+    pub filter: Option<EventFilter>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecentEventsParams {
+    pub limit: Option<usize>,
+    pub filter: Option<EventFilter>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PauseState {
+    pub paused: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogLevelParams {
+    pub level: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogLevelState {
+    pub level: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FarmStatus {
+    pub version: String,
+    pub network: String,
+    pub paused: bool,
+    //Another instance holds the high availability lock
+    #[serde(default)]
+    pub standby: bool,
+    pub harvester_only: bool,
+    pub full_node_connected: bool,
+    pub harvesters: usize,
+    pub plots: u64,
+    pub invalid_messages: u64,
+    pub log_level: String,
+    pub disabled: Vec<DisabledFeature>,
+    #[serde(default)]
+    pub launchers: LauncherStatsMap,
+    #[serde(default)]
+    pub sub_slot: Option<SubSlotParams>,
+    #[serde(default)]
+    pub declarations_skipped: u64,
+    //Set when only public keys are configured
+    #[serde(default)]
+    pub watch_only: Option<WatchOnlyStats>,
+    #[serde(default)]
+    pub network_stats: Option<NetworkStats>,
+    #[serde(default)]
+    pub signing_steps: BTreeMap<SigningStep, SigningStepStats>,
+    #[serde(default)]
+    pub clock_drift: Option<ClockDrift>,
+    #[serde(default)]
+    pub protocol_rtt: BTreeMap<String, RttStats>,
+    //Share of the signage points of the last 24 hours and 7 days farmed in time
+    #[serde(default)]
+    pub availability: Option<AvailabilityReport>,
+    #[serde(default)]
+    pub build: Option<BuildInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReloadResult {
+    //Top level config sections that differ from the config the farmer was started with
+    pub changed: Vec<String>,
+    pub disabled: Vec<DisabledFeature>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoftRestartResult {
+    //Changed sections the soft restart applies
+    pub applied: Vec<String>,
+    //Changed sections only read at startup, they still need a full restart
+    pub restart_required: Vec<String>,
+    pub disabled: Vec<DisabledFeature>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlError {
+    //HTTP style status, 4xx for bad requests and 5xx for farmer side failures
+    #[serde(default)]
+    pub code: u16,
+    pub error: String,
+}
+impl From<&FarmerError> for ControlError {
+    fn from(e: &FarmerError) -> Self {
+        ControlError {
+            code: e.status_code(),
+            error: e.to_string(),
+        }
+    }
+}
+impl ControlError {
+    fn jsonrpc_code(&self) -> i64 {
+        match self.code {
+            400 => -32602,
+            404 => -32601,
+            code => -32000 - code as i64,
+        }
+    }
+}
+
+//Applies to both the plain logger of ff run and the TUI logger
+pub fn set_log_level(level: LevelFilter) {
+    log::set_max_level(level);
+    #[cfg(feature = "gui")]
+    tui_logger::set_default_level(level);
+}
+
+#[cfg(unix)]
+pub fn get_control_socket_path(config: &Config) -> PathBuf {
+    if let Some(path) = &config.control_socket {
+        PathBuf::from(path)
+    } else {
+        crate::get_root_path()
+            .as_path()
+            .join(Path::new("fast_farmer.sock"))
+    }
+}
+
+#[cfg(windows)]
+pub fn get_control_socket_path(config: &Config) -> PathBuf {
+    if let Some(path) = &config.control_socket {
+        PathBuf::from(path)
+    } else {
+        PathBuf::from(r"\\.\pipe\fast_farmer")
+    }
+}
+
+#[cfg(unix)]
+pub async fn connect(path: &Path) -> Result<ControlStream, Error> {
+    tokio::net::UnixStream::connect(path).await
+}
+
+#[cfg(windows)]
+pub async fn connect(path: &Path) -> Result<ControlStream, Error> {
+    tokio::net::windows::named_pipe::ClientOptions::new().open(path)
+}
+
+pub async fn control_server(shared_state: Arc<FarmerSharedState>) {
+    let path = get_control_socket_path(shared_state.config.as_ref());
+    let authenticator = if shared_state.config.control_auth {
+        match ControlIdentity::load_or_create(&crate::get_control_key_path()) {
+            Ok(identity) => Some(ControlAuthenticator::new(&identity)),
+            Err(e) => {
+                //Without a key nobody could sign, mutating requests are refused until it is fixed
+                error!("Failed to load control identity key: {:?}", e);
+                None
+            }
+        }
+    } else {
+        warn!("Control authentication is disabled, anyone with access to the control socket can change the farm");
+        None
+    };
+    let context = Arc::new(ControlContext {
+        authenticator,
+        config_baseline: std::sync::Mutex::new(read_config_value(&shared_state.config_path).ok()),
+    });
+    if let Err(e) = serve(&path, shared_state, context).await {
+        error!("Control Socket at {:?} Failed: {:?}", path, e);
+    }
+}
+
+#[cfg(unix)]
+async fn serve(
+    path: &Path,
+    shared_state: Arc<FarmerSharedState>,
+    context: Arc<ControlContext>,
+) -> Result<(), Error> {
+    if path.exists() {
+        if connect(path).await.is_ok() {
+            return Err(Error::new(
+                std::io::ErrorKind::AddrInUse,
+                "Another instance is already listening on the control socket",
+            ));
+        }
+        //Left over from an instance that did not shut down cleanly
+        std::fs::remove_file(path)?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)?;
+    crate::platform::set_mode(path, 0o600)?;
+    info!("Control Socket Listening on {:?}", path);
+    while shared_state.run.load(Ordering::Relaxed) {
+        let accepted = select! {
+            res = listener.accept() => Some(res),
+            _ = sleep(Duration::from_secs(1)) => None,
+        };
+        match accepted {
+            Some(Ok((stream, _))) => {
+                tokio::spawn(handle_connection(
+                    stream,
+                    shared_state.clone(),
+                    context.clone(),
+                ));
+            }
+            Some(Err(e)) => {
+                warn!("Failed to accept control connection: {:?}", e);
+            }
+            None => {}
+        }
+    }
+    let _ = std::fs::remove_file(path);
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn serve(
+    path: &Path,
+    shared_state: Arc<FarmerSharedState>,
+    context: Arc<ControlContext>,
+) -> Result<(), Error> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(path)?;
+    info!("Control Socket Listening on {:?}", path);
+    while shared_state.run.load(Ordering::Relaxed) {
+        let connected = select! {
+            res = server.connect() => Some(res),
+            _ = sleep(Duration::from_secs(1)) => None,
+        };
+        match connected {
+            Some(Ok(())) => {
+                let client = std::mem::replace(&mut server, ServerOptions::new().create(path)?);
+                tokio::spawn(handle_connection(
+                    client,
+                    shared_state.clone(),
+                    context.clone(),
+                ));
+            }
+            Some(Err(e)) => {
+                warn!("Failed to accept control connection: {:?}", e);
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+struct ControlContext {
+    authenticator: Option<ControlAuthenticator>,
+    //Config file as it was when the farmer started or last soft restarted, reload reports what
+    //changed since
+    config_baseline: std::sync::Mutex<Option<serde_yaml::Value>>,
+}
+
+fn read_config_value(path: &Path) -> Result<serde_yaml::Value, FarmerError> {
+    serde_yaml::from_str(&std::fs::read_to_string(path)?)
+        .map_err(|e| FarmerError::Config(format!("{:?}: {e}", path)))
+}
+
+fn changed_sections(baseline: &serde_yaml::Value, current: &serde_yaml::Value) -> Vec<String> {
+    let empty = serde_yaml::Mapping::new();
+    let baseline = baseline.as_mapping().unwrap_or(&empty);
+    let current = current.as_mapping().unwrap_or(&empty);
+    let mut changed: Vec<String> = baseline
+        .keys()
+        .chain(current.keys().filter(|k| !baseline.contains_key(*k)))
+        .filter(|k| baseline.get(*k) != current.get(*k))
+        .filter_map(|k| k.as_str().map(str::to_string))
+        .collect();
+    changed.sort();
+    changed
+}
+
+async fn handle_connection<S>(
+    stream: S,
+    shared_state: Arc<FarmerSharedState>,
+    context: Arc<ControlContext>,
+) where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (reader, mut writer) = split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let request = match serde_json::from_str::<ControlRequest>(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let _ = write_json(
+                    &mut writer,
+                    &ControlError {
+                        code: 400,
+                        error: format!("Invalid Request: {e}"),
+                    },
+                )
+                .await;
+                continue;
+            }
+        };
+        if let Err(e) = authorize(&request, &shared_state, &context.authenticator).await {
+            warn!("Rejected control request {}: {e}", request.method);
+            let error = ControlError {
+                code: 401,
+                error: format!("Unauthorized: {e}"),
+            };
+            let _ = respond(&mut writer, &request, Err(error)).await;
+            continue;
+        }
+        //Tail streams events until the client disconnects, one event per line
+        if request.method == "tail" {
+            let params: TailParams = serde_json::from_value(request.params).unwrap_or_default();
+            if let Err(e) = tail(&mut writer, params, shared_state.as_ref()).await {
+                debug!("Tail client disconnected: {:?}", e);
+            }
+            return;
+        }
+        let result = dispatch(&request, shared_state.as_ref(), context.as_ref()).await;
+        if respond(&mut writer, &request, result).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn dispatch(
+    request: &ControlRequest,
+    shared_state: &FarmerSharedState,
+    context: &ControlContext,
+) -> Result<Value, ControlError> {
+    match request.method.as_str() {
+        "status" => to_result(&status(shared_state).await),
+        "rpc_metrics" => to_result(&shared_state.rpc_health.metrics().await),
+        "harvester_stats" => to_result(&shared_state.harvester_stats.snapshot().await),
+        "recent_events" => {
+            let params: RecentEventsParams =
+                serde_json::from_value(request.params.clone()).unwrap_or_default();
+            let events = shared_state
+                .event_history
+                .recent(params.limit.unwrap_or(usize::MAX), params.filter)
+                .await;
+            to_result(&events)
+        }
+        "unfarmable_plots" => to_result(&shared_state.unfarmable_plots.snapshot().await),
+        "dir_latency" => to_result(
+            &shared_state
+                .drive_health
+                .attach(shared_state.dir_latencies.snapshot().await)
+                .await,
+        ),
+        "plot_leaderboard" => {
+            let params: LeaderboardParams =
+                serde_json::from_value(request.params.clone()).unwrap_or_default();
+            to_result(&plot_leaderboard(shared_state, &params).await)
+        }
+        "pause" | "resume" => {
+            shared_state.set_paused(request.method == "pause");
+            to_result(&PauseState {
+                paused: shared_state.is_paused(),
+            })
+        }
+        "log_level" => {
+            let params: LogLevelParams =
+                serde_json::from_value(request.params.clone()).map_err(|e| ControlError {
+                    code: 400,
+                    error: format!("Invalid Params: {e}"),
+                })?;
+            if let Some(level) = params.level {
+                let level = level.parse::<LevelFilter>().map_err(|_| ControlError {
+                    code: 400,
+                    error: format!("Unknown log level {level}"),
+                })?;
+                set_log_level(level);
+                info!("Log level set to {level}");
+            }
+            to_result(&LogLevelState {
+                level: log::max_level().to_string(),
+            })
+        }
+        "reload" => reload(shared_state, context),
+        "soft_restart" => soft_restart(shared_state, context).await,
+        "profile" => profile(request, shared_state).await,
+        "subsystems" => to_result(&shared_state.subsystems.states(&shared_state.config)),
+        "start_subsystem" | "stop_subsystem" => set_subsystem(request, shared_state),
+        method => Err(ControlError {
+            code: 404,
+            error: format!("Unknown Method: {method}"),
+        }),
+    }
+}
+
+pub(crate) async fn plot_leaderboard(
+    shared_state: &FarmerSharedState,
+    params: &LeaderboardParams,
+) -> Vec<PlotScore> {
+    let loaded: Vec<String> = shared_state
+        .plot_registry
+        .snapshot()
+        .await
+        .iter()
+        .map(|record| plot_name(&record.path))
+        .collect();
+    shared_state
+        .plot_leaderboard
+        .lock()
+        .await
+        .entries(&loaded, params)
+}
+
+pub(crate) async fn status(shared_state: &FarmerSharedState) -> FarmStatus {
+    let stats = shared_state.harvester_stats.snapshot().await;
+    //Read up front, a guard taken inside the struct literal is held until the literal is complete
+    let (
+        invalid_messages,
+        declarations_skipped,
+        watch_only,
+        network_stats,
+        signing_steps,
+        clock_drift,
+    ) = {
+        let gui_stats = shared_state.gui_stats.lock().await;
+        (
+            gui_stats.handler_errors.values().sum(),
+            gui_stats.declarations_skipped,
+            gui_stats.watch_only,
+            gui_stats.network,
+            gui_stats.signing_steps.clone(),
+            gui_stats.clock_drift.clone(),
+        )
+    };
+    //A harvester only farmer gets no signage points to count
+    let availability = if shared_state.readiness.harvester_only {
+        None
+    } else {
+        Some(shared_state.availability.lock().await.report(now()))
+    };
+    FarmStatus {
+        version: crate::version(),
+        network: shared_state.config.selected_network.clone(),
+        build: Some(BUILD_INFO.clone()),
+        paused: shared_state.is_paused(),
+        standby: shared_state.is_standby(),
+        sub_slot: shared_state.sp_chain.lock().await.current(),
+        harvester_only: shared_state.readiness.harvester_only,
+        full_node_connected: shared_state.full_node_client.lock().await.is_some(),
+        harvesters: stats.harvesters.len(),
+        plots: stats.farm.plots.count,
+        invalid_messages,
+        declarations_skipped,
+        watch_only: shared_state.readiness.watch_only.then_some(watch_only),
+        network_stats,
+        signing_steps,
+        clock_drift,
+        log_level: log::max_level().to_string(),
+        disabled: shared_state.readiness.disabled.clone(),
+        launchers: shared_state.launcher_stats().await,
+        protocol_rtt: shared_state.protocol_rtts.snapshot().await,
+        availability,
+    }
+}
+
+//Answers once the capture is done, the client waits for up to MAX_PROFILE_SECS
+async fn profile(
+    request: &ControlRequest,
+    shared_state: &FarmerSharedState,
+) -> Result<Value, ControlError> {
+    if !shared_state.profiling {
+        return Err(ControlError {
+            code: 409,
+            error: "Profiling is off, start the farmer with ff run --profile".to_string(),
+        });
+    }
+    let params: ProfileParams =
+        serde_json::from_value(request.params.clone()).map_err(|e| ControlError {
+            code: 400,
+            error: format!("Invalid Params: {e}"),
+        })?;
+    let seconds = params.seconds.unwrap_or(DEFAULT_PROFILE_SECS);
+    info!("Capturing a {seconds}s CPU profile");
+    let result = capture(seconds, params.all, &crate::get_profiles_path())
+        .await
+        .map_err(|e| ControlError {
+            code: 500,
+            error: e.to_string(),
+        })?;
+    info!(
+        "Wrote CPU profile to {} and {}",
+        result.flamegraph, result.pprof
+    );
+    to_result(&result)
+}
+
+//Stops or starts one background task, the others and the farming itself carry on
+fn set_subsystem(
+    request: &ControlRequest,
+    shared_state: &FarmerSharedState,
+) -> Result<Value, ControlError> {
+    let params: SubsystemParams =
+        serde_json::from_value(request.params.clone()).map_err(|e| ControlError {
+            code: 400,
+            error: format!("Invalid Params: {e}"),
+        })?;
+    let subsystem: Subsystem = params
+        .name
+        .parse()
+        .map_err(|error| ControlError { code: 400, error })?;
+    if !subsystem.is_configured(&shared_state.config) {
+        return Err(ControlError {
+            code: 409,
+            error: format!("{subsystem} is not configured"),
+        });
+    }
+    let running = request.method == "start_subsystem";
+    if shared_state.subsystems.set_running(subsystem, running) {
+        info!(
+            "{} {subsystem} through the control socket",
+            if running { "Started" } else { "Stopped" }
+        );
+    }
+    to_result(&shared_state.subsystems.states(&shared_state.config))
+}
+
+//Validates the config on disk, most sections still need a restart to take effect
+fn reload(
+    shared_state: &FarmerSharedState,
+    context: &ControlContext,
+) -> Result<Value, ControlError> {
+    let path = shared_state.config_path.as_ref();
+    let config = Config::try_from(path).map_err(|e| ControlError::from(&e))?;
+    let current = read_config_value(path).map_err(|e| ControlError::from(&e))?;
+    let changed = context
+        .config_baseline
+        .lock()
+        .ok()
+        .and_then(|baseline| baseline.as_ref().map(|b| changed_sections(b, &current)))
+        .unwrap_or_default();
+    if changed.is_empty() {
+        info!("Reloaded config {:?}, nothing changed", path);
+    } else {
+        warn!(
+            "Reloaded config {:?}, restart the farmer to apply the changed sections: {}",
+            path,
+            changed.join(", ")
+        );
+    }
+    //Pool info is the part that can be refreshed without a restart
+    shared_state
+        .force_pool_update
+        .store(true, Ordering::Relaxed);
+    to_result(&ReloadResult {
+        changed,
+        disabled: Readiness::check(&config).disabled,
+    })
+}
+
+//Sections used by the full node connection, changing them means reconnecting anyway
+const CONNECTION_SECTIONS: [&str; 10] = [
+    "selected_network",
+    "ssl_root_path",
+    "ssl_root_paths",
+    "verify_node_ca",
+    "fullnode_ws_host",
+    "fullnode_ws_port",
+    "connection_profile",
+    "connection",
+    "consensus_overrides",
+    "dns",
+];
+//Sections read by the harvesters, the signage point and signature handlers and the pool updater,
+//which are all rebuilt by a soft restart
+const SOFT_RESTART_SECTIONS: [&str; 21] = [
+    "config_version",
+    "farmer_info",
+    "pool_info",
+    "payout_address",
+    "farms",
+    "addresses",
+    "harvester_configs",
+    "partial_window",
+    "partial_cutoff_margin",
+    "duplicate_partial_window",
+    "max_proofs_per_sp",
+    "sp_recovery",
+    "max_signature_requests",
+    "pool_info_cache_ttl",
+    "client_metadata",
+    "fullnode_rpc_host",
+    "fullnode_rpc_port",
+    "fullnode_rpc_policy",
+    "spare_rpc",
+    "strict_config",
+    "host_vars",
+];
+
+//Validates the config on disk and hands it to the farmer loop, which swaps in the new config, keys
+//and harvesters without closing the full node connection
+async fn soft_restart(
+    shared_state: &FarmerSharedState,
+    context: &ControlContext,
+) -> Result<Value, ControlError> {
+    let path = shared_state.config_path.as_ref();
+    let config = Config::try_from(path).map_err(|e| ControlError::from(&e))?;
+    if shared_state.full_node_client.lock().await.is_none() {
+        return Err(ControlError {
+            code: 409,
+            error: "No full node connection to keep, restart the farmer instead".to_string(),
+        });
+    }
+    let current = read_config_value(path).map_err(|e| ControlError::from(&e))?;
+    let mut baseline = context.config_baseline.lock().map_err(|_| ControlError {
+        code: 500,
+        error: "Config baseline is poisoned".to_string(),
+    })?;
+    let changed = baseline
+        .as_ref()
+        .map(|b| changed_sections(b, &current))
+        .unwrap_or_default();
+    let connection: Vec<&String> = changed
+        .iter()
+        .filter(|c| CONNECTION_SECTIONS.contains(&c.as_str()))
+        .collect();
+    if !connection.is_empty() {
+        return Err(ControlError {
+            code: 409,
+            error: format!(
+                "{} changed, restart the farmer to reconnect",
+                connection
+                    .iter()
+                    .map(|c| c.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        });
+    }
+    let (applied, restart_required): (Vec<String>, Vec<String>) = changed
+        .into_iter()
+        .partition(|c| SOFT_RESTART_SECTIONS.contains(&c.as_str()));
+    //Sections still waiting for a full restart keep their old value, so reload keeps listing them
+    if let (Some(serde_yaml::Value::Mapping(old)), serde_yaml::Value::Mapping(mut new)) =
+        (baseline.as_ref(), current)
+    {
+        for section in &restart_required {
+            match old.get(section.as_str()) {
+                Some(value) => new.insert(section.as_str().into(), value.clone()),
+                None => new.remove(section.as_str()),
+            };
+        }
+        *baseline = Some(serde_yaml::Value::Mapping(new));
+    }
+    info!(
+        "Soft restart requested, applying: {}",
+        if applied.is_empty() {
+            "nothing changed".to_string()
+        } else {
+            applied.join(", ")
+        }
+    );
+    shared_state.soft_restart.store(true, Ordering::Relaxed);
+    to_result(&SoftRestartResult {
+        applied,
+        restart_required,
+        disabled: Readiness::check(&config).disabled,
+    })
+}
+
+fn to_result<T: Serialize>(value: &T) -> Result<Value, ControlError> {
+    serde_json::to_value(value).map_err(|e| ControlError {
+        code: 500,
+        error: format!("{:?}", e),
+    })
+}
+
+async fn respond<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    request: &ControlRequest,
+    result: Result<Value, ControlError>,
+) -> Result<(), Error> {
+    if !request.is_jsonrpc() {
+        return match result {
+            Ok(value) => write_json(writer, &value).await,
+            Err(e) => write_json(writer, &e).await,
+        };
+    }
+    let id = request.id.clone().unwrap_or(Value::Null);
+    let response = match result {
+        Ok(value) => serde_json::json!({"jsonrpc": "2.0", "id": id, "result": value}),
+        Err(e) => serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {"code": e.jsonrpc_code(), "message": e.error, "data": {"status": e.code}},
+        }),
+    };
+    write_json(writer, &response).await
+}
+
+async fn authorize(
+    request: &ControlRequest,
+    shared_state: &FarmerSharedState,
+    authenticator: &Option<ControlAuthenticator>,
+) -> Result<(), String> {
+    if !is_mutating(&request.method) || !shared_state.config.control_auth {
+        return Ok(());
+    }
+    match authenticator {
+        Some(authenticator) => authenticator.verify(request).await,
+        None => Err("Control identity key is not available".to_string()),
+    }
+}
+
+async fn tail<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    params: TailParams,
+    shared_state: &FarmerSharedState,
+) -> Result<(), Error> {
+    let mut events = shared_state.events.subscribe();
+    while shared_state.run.load(Ordering::Relaxed) {
+        let event = select! {
+            event = events.recv() => event,
+            _ = sleep(Duration::from_secs(1)) => continue,
+        };
+        match event {
+            Ok(event) => {
+                if params
+                    .filter
+                    .map(|f| f.matches(&event.kind))
+                    .unwrap_or(true)
+                {
+                    write_json(writer, &event).await?;
+                }
+            }
+            Err(RecvError::Lagged(count)) => {
+                warn!("Tail client fell behind, dropped {count} events");
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+    Ok(())
+}
+
+async fn write_json<W: AsyncWrite + Unpin, T: Serialize>(
+    writer: &mut W,
+    value: &T,
+) -> Result<(), Error> {
+    let mut line = serde_json::to_string(value).map_err(|e| Error::other(format!("{:?}", e)))?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn jsonrpc_requests_get_the_envelope() {
+        let mut request: ControlRequest =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","id":7,"method":"pause"}"#).unwrap();
+        let mut out = vec![];
+        respond(&mut out, &request, Ok(serde_json::json!({"paused": true})))
+            .await
+            .unwrap();
+        let response: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["result"]["paused"], true);
+        request.jsonrpc = None;
+        request.id = None;
+        let mut out = vec![];
+        let error = ControlError {
+            code: 404,
+            error: "Unknown Method: x".to_string(),
+        };
+        respond(&mut out, &request, Err(error)).await.unwrap();
+        let response: ControlError = serde_json::from_slice(&out).unwrap();
+        assert_eq!(response.code, 404);
+    }
+
+    #[test]
+    fn reports_changed_sections() {
+        let baseline: serde_yaml::Value = serde_yaml::from_str("a: 1\nb: [1, 2]\nc: x").unwrap();
+        let current: serde_yaml::Value = serde_yaml::from_str("a: 1\nb: [1, 3]\nd: y").unwrap();
+        assert_eq!(changed_sections(&baseline, &current), vec!["b", "c", "d"]);
+    }
+}
@@ -0,0 +1,373 @@
+use crate::control::{plot_leaderboard, status};
+use crate::dashboard::public::{public_status, PUBLIC_HTML};
+use crate::farmer::plot_leaderboard::LeaderboardParams;
+use crate::farmer::subsystems::Subsystem;
+use crate::farmer::FarmerSharedState;
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use std::io::Error;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{sleep, timeout};
+use tokio::{join, select};
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+pub mod public;
+
+static INDEX_HTML: &str = include_str!("index.html");
+const MAX_REQUEST_HEAD: usize = 8192;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Request {
+    path: String,
+    query: String,
+    //Sec-WebSocket-Key of a websocket upgrade
+    websocket_key: Option<String>,
+}
+
+//Read only view of the farm for a browser, the control socket stays the only way to change it.
+//The public status page is served on its own address next to it
+pub async fn dashboard_server(shared_state: Arc<FarmerSharedState>) {
+    let dashboard = shared_state.config.dashboard.clone().map(|d| d.bind);
+    let public = shared_state.config.public_status.clone().map(|p| p.bind);
+    join!(
+        serve(shared_state.clone(), dashboard, false),
+        serve(shared_state, public, true)
+    );
+}
+
+async fn serve(shared_state: Arc<FarmerSharedState>, bind: Option<String>, public: bool) {
+    let Some(bind) = bind else {
+        return;
+    };
+    let (name, subsystem) = if public {
+        ("Public status page", Subsystem::PublicStatus)
+    } else {
+        ("Dashboard", Subsystem::Dashboard)
+    };
+    //The listener is dropped while the subsystem is stopped and bound again when it is started
+    while shared_state
+        .subsystems
+        .wait_until_running(subsystem, &shared_state.run)
+        .await
+    {
+        let listener = match TcpListener::bind(&bind).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to bind {name} to {bind}: {:?}", e);
+                shared_state.subsystems.set_running(subsystem, false);
+                continue;
+            }
+        };
+        info!("{name} Listening on http://{bind}");
+        while shared_state.run.load(Ordering::Relaxed)
+            && shared_state.subsystems.is_running(subsystem)
+        {
+            let accepted = select! {
+                res = listener.accept() => Some(res),
+                _ = sleep(Duration::from_secs(1)) => None,
+            };
+            match accepted {
+                Some(Ok((stream, _))) => {
+                    let shared_state = shared_state.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(stream, shared_state, public).await {
+                            debug!("{name} connection failed: {:?}", e);
+                        }
+                    });
+                }
+                Some(Err(e)) => {
+                    warn!("Failed to accept {name} connection: {:?}", e);
+                }
+                None => {}
+            }
+        }
+        info!("{name} Stopped");
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    shared_state: Arc<FarmerSharedState>,
+    public: bool,
+) -> Result<(), Error> {
+    let mut buf = vec![0u8; MAX_REQUEST_HEAD];
+    let request = match timeout(REQUEST_TIMEOUT, read_head(&mut stream, &mut buf)).await {
+        Ok(Ok(Some(head_len))) => parse_request(&String::from_utf8_lossy(&buf[..head_len])),
+        Ok(Err(e)) => return Err(e),
+        Ok(Ok(None)) | Err(_) => None,
+    };
+    let Some(request) = request else {
+        return respond(&mut stream, "400 Bad Request", "text/plain", b"Bad Request").await;
+    };
+    if let Some(key) = &request.websocket_key {
+        if request.path == "/events" && !public {
+            return stream_events(stream, key, shared_state).await;
+        }
+    }
+    if public {
+        return match request.path.as_str() {
+            "/" | "/index.html" => {
+                respond(
+                    &mut stream,
+                    "200 OK",
+                    "text/html; charset=utf-8",
+                    PUBLIC_HTML.as_bytes(),
+                )
+                .await
+            }
+            "/api/status" => respond_json(&mut stream, &public_status(&shared_state).await).await,
+            _ => respond(&mut stream, "404 Not Found", "text/plain", b"Not Found").await,
+        };
+    }
+    match request.path.as_str() {
+        "/" | "/index.html" => {
+            respond(
+                &mut stream,
+                "200 OK",
+                "text/html; charset=utf-8",
+                INDEX_HTML.as_bytes(),
+            )
+            .await
+        }
+        "/api/status" => respond_json(&mut stream, &status(&shared_state).await).await,
+        "/api/harvesters" => {
+            respond_json(&mut stream, &shared_state.harvester_stats.snapshot().await).await
+        }
+        "/api/dir_latency" => {
+            respond_json(
+                &mut stream,
+                &shared_state
+                    .drive_health
+                    .attach(shared_state.dir_latencies.snapshot().await)
+                    .await,
+            )
+            .await
+        }
+        "/api/plot_leaderboard" => {
+            let params = LeaderboardParams::from_query(&request.query);
+            respond_json(&mut stream, &plot_leaderboard(&shared_state, &params).await).await
+        }
+        "/api/events" => {
+            respond_json(
+                &mut stream,
+                &shared_state.event_history.recent(200, None).await,
+            )
+            .await
+        }
+        _ => respond(&mut stream, "404 Not Found", "text/plain", b"Not Found").await,
+    }
+}
+
+//Length of the request head without its blank line, None when the client closed or sent too much
+async fn read_head(stream: &mut TcpStream, buf: &mut [u8]) -> Result<Option<usize>, Error> {
+    let mut len = 0;
+    while len < buf.len() {
+        let read = stream.read(&mut buf[len..]).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        //The blank line can start in the bytes read before
+        let start = len.saturating_sub(3);
+        len += read;
+        if let Some(end) = buf[start..len].windows(4).position(|w| w == b"\r\n\r\n") {
+            return Ok(Some(start + end));
+        }
+    }
+    Ok(None)
+}
+
+fn parse_request(head: &str) -> Option<Request> {
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    if request_line.next()? != "GET" {
+        return None;
+    }
+    let path = request_line.next()?;
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let mut upgrade = false;
+    let mut key = None;
+    for (name, value) in lines.filter_map(|line| line.split_once(':')) {
+        let (name, value) = (name.trim(), value.trim());
+        if name.eq_ignore_ascii_case("upgrade") {
+            upgrade = value.eq_ignore_ascii_case("websocket");
+        } else if name.eq_ignore_ascii_case("sec-websocket-key") {
+            key = Some(value.to_string());
+        }
+    }
+    Some(Request {
+        path: path.to_string(),
+        query: query.to_string(),
+        websocket_key: key.filter(|_| upgrade),
+    })
+}
+
+async fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> Result<(), Error> {
+    let head = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.shutdown().await
+}
+
+async fn respond_json<T: Serialize>(stream: &mut TcpStream, value: &T) -> Result<(), Error> {
+    let body = serde_json::to_vec(value).map_err(Error::other)?;
+    respond(stream, "200 OK", "application/json", &body).await
+}
+
+//Every farmer event as a JSON text frame, the same format as ff tail
+//The request head is already read, so the upgrade is answered here instead of by tungstenite
+async fn stream_events(
+    mut stream: TcpStream,
+    key: &str,
+    shared_state: Arc<FarmerSharedState>,
+) -> Result<(), Error> {
+    let mut events = shared_state.events.subscribe();
+    stream
+        .write_all(
+            format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                derive_accept_key(key.as_bytes())
+            )
+            .as_bytes(),
+        )
+        .await?;
+    let websocket = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+    let (mut sink, mut incoming) = websocket.split();
+    loop {
+        select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let text = serde_json::to_string(&event).map_err(Error::other)?;
+                    sink.send(Message::Text(text))
+                        .await
+                        .map_err(|e| Error::other(format!("{:?}", e)))?;
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!("Dashboard client skipped {skipped} events");
+                }
+                Err(RecvError::Closed) => break,
+            },
+            message = incoming.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                _ => {}
+            },
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::events::FarmerEventKind;
+
+    #[test]
+    fn parses_plain_and_websocket_requests() {
+        assert_eq!(
+            parse_request("GET /api/status?x=1 HTTP/1.1\r\nHost: localhost"),
+            Some(Request {
+                path: "/api/status".to_string(),
+                query: "x=1".to_string(),
+                websocket_key: None
+            })
+        );
+        assert_eq!(
+            parse_request(
+                "GET /events HTTP/1.1\r\nConnection: Upgrade\r\nUpgrade: WebSocket\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ=="
+            ),
+            Some(Request {
+                path: "/events".to_string(),
+                query: String::new(),
+                websocket_key: Some("dGhlIHNhbXBsZSBub25jZQ==".to_string())
+            })
+        );
+        assert_eq!(parse_request("POST /api/status HTTP/1.1"), None);
+    }
+
+    async fn get(path: &str, public: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle_connection(stream, Arc::new(FarmerSharedState::default()), public).await
+        });
+        let mut client = TcpStream::connect(address).await.unwrap();
+        client
+            .write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        server.await.unwrap().unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn serves_status_over_http() {
+        let response = get("/api/status", false).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let status: crate::control::FarmStatus = serde_json::from_str(body).unwrap();
+        assert!(!status.paused);
+    }
+
+    #[tokio::test]
+    async fn public_mode_only_serves_the_sanitized_status() {
+        let response = get("/api/status", true).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let status: public::PublicStatus = serde_json::from_str(body).unwrap();
+        assert!(!status.farming);
+        for path in [
+            "/api/harvesters",
+            "/api/events",
+            "/api/dir_latency",
+            "/api/plot_leaderboard",
+        ] {
+            assert!(get(path, true).await.starts_with("HTTP/1.1 404"));
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_events_over_a_websocket() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let shared_state = Arc::new(FarmerSharedState::default());
+        let server_state = shared_state.clone();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle_connection(stream, server_state, false).await
+        });
+        let stream = TcpStream::connect(address).await.unwrap();
+        let (mut websocket, _) =
+            tokio_tungstenite::client_async(format!("ws://{address}/events"), stream)
+                .await
+                .unwrap();
+        shared_state.emit(FarmerEventKind::SignagePoint {
+            challenge_hash: Default::default(),
+            sp_hash: Default::default(),
+            signage_point_index: 4,
+        });
+        let Some(Ok(Message::Text(text))) = websocket.next().await else {
+            panic!("No event received");
+        };
+        assert!(text.contains("\"signage_point_index\":4"));
+    }
+}
@@ -0,0 +1,63 @@
+use crate::farmer::network_stats::NetworkStats;
+use crate::farmer::FarmerSharedState;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub static PUBLIC_HTML: &str = include_str!("public.html");
+
+//Totals over every plot NFT farming to the pool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicPoolStats {
+    pub proofs_declared: u64,
+    pub partials_submitted: u64,
+    pub partials_accepted: u64,
+    pub partials_stale: u64,
+}
+
+//Status safe to share, built field by field so nothing new in the farm status leaks into it.
+//No keys, launcher ids, addresses, harvester ids or plot paths
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicStatus {
+    pub version: String,
+    pub network: String,
+    pub farming: bool,
+    pub harvesters: usize,
+    pub plots: u64,
+    pub compressed_plots: u64,
+    pub effective_space: u64,
+    //By pool host, solo farmed plot NFTs are listed as solo
+    pub pools: BTreeMap<String, PublicPoolStats>,
+    pub network_stats: Option<NetworkStats>,
+}
+
+pub async fn public_status(shared_state: &FarmerSharedState) -> PublicStatus {
+    let stats = shared_state.harvester_stats.snapshot().await;
+    let network_stats = shared_state.gui_stats.lock().await.network;
+    let mut pools: BTreeMap<String, PublicPoolStats> = BTreeMap::new();
+    for launcher in shared_state.launcher_stats().await.values() {
+        let pool = pools
+            .entry(if launcher.pool_host.is_empty() {
+                "solo".to_string()
+            } else {
+                launcher.pool_host.clone()
+            })
+            .or_default();
+        pool.proofs_declared += launcher.proofs_declared;
+        pool.partials_submitted += launcher.partials_submitted;
+        pool.partials_accepted += launcher.partials_accepted;
+        pool.partials_stale += launcher.partials_stale;
+    }
+    PublicStatus {
+        version: crate::version(),
+        network: shared_state.config.selected_network.clone(),
+        farming: shared_state.full_node_client.lock().await.is_some()
+            && !shared_state.is_paused()
+            && !shared_state.is_standby(),
+        harvesters: stats.harvesters.len(),
+        plots: stats.farm.plots.count,
+        compressed_plots: stats.farm.plots.compressed,
+        effective_space: stats.farm.plots.effective_space,
+        pools,
+        network_stats,
+    }
+}
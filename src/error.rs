@@ -0,0 +1,89 @@
+use dg_xch_clients::protocols::pool::PoolError;
+use std::io::ErrorKind;
+use thiserror::Error;
+
+pub type FarmerResult<T> = Result<T, FarmerError>;
+
+#[derive(Debug, Error)]
+pub enum FarmerError {
+    #[error("Config error: {0}")]
+    Config(String),
+    #[error("Key error: {0}")]
+    Key(String),
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+    #[error("Pool error {code}: {message}")]
+    Pool { code: u8, message: String },
+    #[error("Harvester error: {0}")]
+    Harvester(String),
+    #[error("Signing error: {0}")]
+    Signing(String),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+impl FarmerError {
+    //Connection, protocol and pool errors clear up on a retry, the rest need a config or key change
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            FarmerError::Protocol(_) | FarmerError::Pool { .. } | FarmerError::Harvester(_) => true,
+            FarmerError::Io(e) => !matches!(
+                e.kind(),
+                ErrorKind::NotFound | ErrorKind::PermissionDenied | ErrorKind::InvalidInput
+            ),
+            FarmerError::Config(_)
+            | FarmerError::Key(_)
+            | FarmerError::Signing(_)
+            | FarmerError::Json(_) => false,
+        }
+    }
+    //HTTP style status for control API responses
+    pub fn status_code(&self) -> u16 {
+        match self {
+            FarmerError::Config(_) => 400,
+            FarmerError::Key(_) => 403,
+            FarmerError::Protocol(_) | FarmerError::Pool { .. } | FarmerError::Harvester(_) => 502,
+            FarmerError::Io(e) if e.kind() == ErrorKind::TimedOut => 504,
+            FarmerError::Signing(_) | FarmerError::Json(_) | FarmerError::Io(_) => 500,
+        }
+    }
+}
+impl From<PoolError> for FarmerError {
+    fn from(e: PoolError) -> Self {
+        FarmerError::Pool {
+            code: e.error_code,
+            message: e.error_message,
+        }
+    }
+}
+//Handlers implementing the chia client traits still return std::io::Error
+impl From<FarmerError> for std::io::Error {
+    fn from(e: FarmerError) -> Self {
+        let kind = match e {
+            FarmerError::Io(e) => return e,
+            FarmerError::Config(_) | FarmerError::Signing(_) => ErrorKind::InvalidInput,
+            FarmerError::Key(_) => ErrorKind::NotFound,
+            FarmerError::Protocol(_) | FarmerError::Json(_) => ErrorKind::InvalidData,
+            FarmerError::Pool { .. } | FarmerError::Harvester(_) => ErrorKind::Other,
+        };
+        std::io::Error::new(kind, e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn misconfiguration_is_not_recoverable() {
+        let config = FarmerError::Config("missing farmer_info".to_string());
+        assert!(!config.is_recoverable());
+        assert_eq!(config.status_code(), 400);
+        let protocol = FarmerError::Protocol("unexpected message".to_string());
+        assert!(protocol.is_recoverable());
+        let io: std::io::Error = FarmerError::Key("unknown farmer key".to_string()).into();
+        assert_eq!(io.kind(), ErrorKind::NotFound);
+        assert_eq!(io.to_string(), "Key error: unknown farmer key");
+    }
+}
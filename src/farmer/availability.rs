@@ -0,0 +1,187 @@
+use crate::error::FarmerResult;
+use crate::farmer::partial_queue::now;
+use crate::farmer::sp_digest::SpDigest;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const HOUR: u64 = 3600;
+const DAY_HOURS: u64 = 24;
+const WEEK_HOURS: u64 = 7 * 24;
+//Same budget chia's harvester warns about for a signage point lookup
+const LOOKUP_BUDGET_MS: u64 = 5000;
+//Mainnet's interval until the network constants are known
+const DEFAULT_SP_INTERVAL: Duration = Duration::from_millis(9375);
+const SAVE_INTERVAL: u64 = 60;
+
+//A signage point counts as farmed when at least one harvester finished its lookups within the budget
+pub fn farmed_in_time(digest: &SpDigest) -> bool {
+    !digest.eligible.is_empty() && digest.time_ms <= LOOKUP_BUDGET_MS
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Availability {
+    pub farmed: u64,
+    //Signage points the network produced in the window since tracking started
+    pub expected: u64,
+    pub percent: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct AvailabilityReport {
+    pub day: Availability,
+    pub week: Availability,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct StoredAvailability {
+    tracking_since: u64,
+    //Signage points farmed in time per hour since the epoch
+    hours: BTreeMap<u64, u64>,
+}
+
+//Farmed signage points per hour, kept on disk so the time the farmer was not running counts as
+//lost like a full node disconnect does
+#[derive(Debug)]
+pub struct AvailabilityTracker {
+    path: Option<PathBuf>,
+    sp_interval: Duration,
+    stored: StoredAvailability,
+    last_save: u64,
+}
+impl Default for AvailabilityTracker {
+    fn default() -> Self {
+        Self::new(
+            None,
+            DEFAULT_SP_INTERVAL,
+            StoredAvailability::default(),
+            now(),
+        )
+    }
+}
+impl AvailabilityTracker {
+    fn new(
+        path: Option<PathBuf>,
+        sp_interval: Duration,
+        mut stored: StoredAvailability,
+        now: u64,
+    ) -> Self {
+        if stored.tracking_since == 0 {
+            stored.tracking_since = now;
+        }
+        Self {
+            path,
+            sp_interval,
+            stored,
+            last_save: now,
+        }
+    }
+    pub fn load(path: &Path, sp_interval: Duration) -> Self {
+        let stored = if path.exists() {
+            match fs::read_to_string(path).map(|s| serde_json::from_str(&s)) {
+                Ok(Ok(stored)) => stored,
+                Ok(Err(e)) => {
+                    warn!(
+                        "Ignoring invalid availability history at {:?}: {:?}",
+                        path, e
+                    );
+                    StoredAvailability::default()
+                }
+                Err(e) => {
+                    warn!("Failed to read availability history at {:?}: {:?}", path, e);
+                    StoredAvailability::default()
+                }
+            }
+        } else {
+            StoredAvailability::default()
+        };
+        Self::new(Some(path.to_path_buf()), sp_interval, stored, now())
+    }
+    pub fn record(&mut self, digest: &SpDigest, now: u64) {
+        if farmed_in_time(digest) {
+            *self.stored.hours.entry(now / HOUR).or_default() += 1;
+        }
+        let oldest = (now / HOUR).saturating_sub(WEEK_HOURS);
+        self.stored.hours.retain(|hour, _| *hour > oldest);
+        if now >= self.last_save + SAVE_INTERVAL {
+            self.last_save = now;
+            if let Err(e) = self.save() {
+                warn!("Failed to save availability history: {:?}", e);
+            }
+        }
+    }
+    //Windows of whole hours ending with the current one
+    fn window(&self, now: u64, hours: u64) -> Availability {
+        let first_hour = (now / HOUR + 1).saturating_sub(hours);
+        let start = (first_hour * HOUR).max(self.stored.tracking_since);
+        let expected = (Duration::from_secs(now.saturating_sub(start)).as_millis()
+            / self.sp_interval.as_millis().max(1)) as u64;
+        let farmed = self.stored.hours.range(first_hour..).map(|(_, c)| c).sum();
+        Availability {
+            farmed,
+            expected,
+            percent: if expected == 0 {
+                100.0
+            } else {
+                (farmed as f64 * 100.0 / expected as f64).min(100.0)
+            },
+        }
+    }
+    pub fn report(&self, now: u64) -> AvailabilityReport {
+        AvailabilityReport {
+            day: self.window(now, DAY_HOURS),
+            week: self.window(now, WEEK_HOURS),
+        }
+    }
+    fn save(&self) -> FarmerResult<()> {
+        if let Some(path) = &self.path {
+            Ok(fs::write(path, serde_json::to_string(&self.stored)?)?)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dg_xch_core::blockchain::sized_bytes::Bytes32;
+    use uuid::Uuid;
+
+    #[test]
+    fn counts_signage_points_missed_while_down() {
+        let start = 100 * HOUR;
+        let mut tracker = AvailabilityTracker::new(
+            None,
+            Duration::from_secs(10),
+            StoredAvailability::default(),
+            start,
+        );
+        let digest = |time_ms| SpDigest {
+            challenge_hash: Bytes32::default(),
+            signage_point_index: 0,
+            eligible: BTreeMap::from([(Uuid::nil(), 0)]),
+            proofs: 0,
+            partials_submitted: 0,
+            partials_accepted: 0,
+            time_ms,
+        };
+        //Farmed for 10 minutes, then down for 10
+        for sp in 0..60 {
+            tracker.record(&digest(300), start + sp * 10);
+        }
+        tracker.record(&digest(LOOKUP_BUDGET_MS + 1), start + 600);
+        let report = tracker.report(start + 1200);
+        assert_eq!(report.day.farmed, 60);
+        assert_eq!(report.day.expected, 120);
+        assert_eq!(report.day.percent, 50.0);
+        assert_eq!(report.week, report.day);
+        //Hours older than a day only count for the week
+        let later = tracker.report(start + 24 * HOUR + 1200);
+        assert_eq!(later.day.farmed, 0);
+        assert_eq!(later.week.farmed, 60);
+    }
+}
@@ -0,0 +1,226 @@
+use crate::platform::set_mode;
+use dg_xch_clients::protocols::farmer::DeclareProofOfSpace;
+use dg_xch_clients::protocols::ProtocolMessageTypes;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use dg_xch_serialize::ChiaSerialize;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Error, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use uuid::Uuid;
+
+pub const FULL_NODE_PEER: &str = "full_node";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    In,
+    Out,
+}
+
+//One protocol message, the data is the serialized message as sent on the wire
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureRecord {
+    pub timestamp_ms: u64,
+    pub direction: Direction,
+    //full_node or the id of the harvester
+    pub peer: String,
+    pub msg_type: String,
+    pub data: String,
+}
+impl CaptureRecord {
+    pub fn bytes(&self) -> Result<Vec<u8>, Error> {
+        hex::decode(&self.data).map_err(|e| Error::other(format!("Invalid capture data: {e}")))
+    }
+}
+
+//Records the farmer and harvester protocol messages as json lines for `ff replay`. While replaying
+//no full node is connected, messages for it are only recorded
+#[derive(Debug, Default)]
+pub struct ProtocolCapture {
+    writer: Option<Mutex<BufWriter<File>>>,
+    replaying: bool,
+}
+impl ProtocolCapture {
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        //Plot identifiers and signatures are nobody else's business
+        set_mode(path, 0o600)?;
+        Ok(Self {
+            writer: Some(Mutex::new(BufWriter::new(file))),
+            replaying: false,
+        })
+    }
+    pub fn replay(out: Option<&Path>) -> Result<Self, Error> {
+        let capture = match out {
+            Some(path) => Self::open(path)?,
+            None => Self::default(),
+        };
+        Ok(Self {
+            replaying: true,
+            ..capture
+        })
+    }
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+    pub fn record<T: ChiaSerialize>(
+        &self,
+        direction: Direction,
+        peer: &str,
+        msg_type: ProtocolMessageTypes,
+        message: &T,
+    ) {
+        if self.writer.is_none() {
+            return;
+        }
+        self.record_bytes(
+            direction,
+            peer,
+            msg_type,
+            &redact(msg_type, message.to_bytes()),
+        );
+    }
+    pub fn record_harvester<T: ChiaSerialize>(
+        &self,
+        direction: Direction,
+        harvester_id: Uuid,
+        msg_type: ProtocolMessageTypes,
+        message: &T,
+    ) {
+        self.record(direction, &harvester_id.to_string(), msg_type, message);
+    }
+    pub fn record_bytes(
+        &self,
+        direction: Direction,
+        peer: &str,
+        msg_type: ProtocolMessageTypes,
+        data: &[u8],
+    ) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        let record = CaptureRecord {
+            timestamp_ms: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            direction,
+            peer: peer.to_string(),
+            msg_type: format!("{msg_type:?}"),
+            data: hex::encode(data),
+        };
+        let Ok(line) = serde_json::to_string(&record) else {
+            return;
+        };
+        let mut writer = writer.lock().unwrap_or_else(|e| e.into_inner());
+        //Flushed per message so a crash keeps everything before it
+        if let Err(e) = writeln!(writer, "{line}").and_then(|_| writer.flush()) {
+            warn!("Failed to write protocol capture: {e}");
+        }
+    }
+}
+
+//The protocols carry no keys, the payout targets of declarations are the only thing tying a
+//capture to a wallet and are zeroed
+fn redact(msg_type: ProtocolMessageTypes, data: Vec<u8>) -> Vec<u8> {
+    if msg_type != ProtocolMessageTypes::DeclareProofOfSpace {
+        return data;
+    }
+    match DeclareProofOfSpace::from_bytes(&mut std::io::Cursor::new(&data)) {
+        Ok(mut declaration) => {
+            declaration.farmer_puzzle_hash = Bytes32::default();
+            if let Some(pool_target) = declaration.pool_target.as_mut() {
+                pool_target.puzzle_hash = Bytes32::default();
+            }
+            declaration.to_bytes()
+        }
+        Err(_) => data,
+    }
+}
+
+pub fn read_capture(path: &Path) -> Result<Vec<CaptureRecord>, Error> {
+    let mut records = vec![];
+    for (index, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        records.push(serde_json::from_str(&line).map_err(|e| {
+            Error::other(format!("Invalid capture record on line {}: {e}", index + 1))
+        })?);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::test_fixtures::proof_of_space;
+    use dg_xch_clients::protocols::farmer::NewSignagePoint;
+    use dg_xch_core::blockchain::pool_target::PoolTarget;
+    use dg_xch_core::blockchain::proof_of_space::ProofOfSpace;
+
+    #[test]
+    fn records_round_trip_with_redacted_targets() {
+        let dir = std::env::temp_dir().join(format!("ff-capture-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("capture.jsonl");
+        let capture = ProtocolCapture::open(&path).unwrap();
+        let sp = NewSignagePoint {
+            challenge_hash: Bytes32::from_sized_bytes([1u8; 32]),
+            challenge_chain_sp: Bytes32::from_sized_bytes([2u8; 32]),
+            reward_chain_sp: Bytes32::from_sized_bytes([3u8; 32]),
+            difficulty: 5,
+            sub_slot_iters: 6,
+            signage_point_index: 7,
+        };
+        capture.record(
+            Direction::In,
+            FULL_NODE_PEER,
+            ProtocolMessageTypes::NewSignagePoint,
+            &sp,
+        );
+        let declaration = DeclareProofOfSpace {
+            challenge_hash: sp.challenge_hash,
+            challenge_chain_sp: sp.challenge_chain_sp,
+            signage_point_index: 7,
+            reward_chain_sp: sp.reward_chain_sp,
+            proof_of_space: ProofOfSpace {
+                challenge: sp.challenge_hash,
+                ..proof_of_space()
+            },
+            challenge_chain_sp_signature: Default::default(),
+            reward_chain_sp_signature: Default::default(),
+            farmer_puzzle_hash: Bytes32::from_sized_bytes([9u8; 32]),
+            pool_target: Some(PoolTarget {
+                puzzle_hash: Bytes32::from_sized_bytes([9u8; 32]),
+                max_height: 0,
+            }),
+            pool_signature: None,
+        };
+        capture.record(
+            Direction::Out,
+            FULL_NODE_PEER,
+            ProtocolMessageTypes::DeclareProofOfSpace,
+            &declaration,
+        );
+        let records = read_capture(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].msg_type, "NewSignagePoint");
+        assert_eq!(records[0].bytes().unwrap(), sp.to_bytes());
+        let redacted =
+            DeclareProofOfSpace::from_bytes(&mut std::io::Cursor::new(records[1].bytes().unwrap()))
+                .unwrap();
+        assert_eq!(redacted.farmer_puzzle_hash, Bytes32::default());
+        assert_eq!(
+            redacted.pool_target.unwrap().puzzle_hash,
+            Bytes32::default()
+        );
+        assert_eq!(redacted.challenge_hash, sp.challenge_hash);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
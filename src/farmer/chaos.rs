@@ -0,0 +1,185 @@
+use dg_xch_clients::protocols::pool::{PoolError, PoolErrorCode};
+use log::{debug, warn};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::io::Error;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+//Failures to inject while the farmer runs, read from the yaml file passed with --chaos
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChaosScenario {
+    //Makes a run repeatable, unset seeds from the OS
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub harvester_latency: Option<LatencyFault>,
+    #[serde(default)]
+    pub dropped_frames: Option<Fault>,
+    #[serde(default)]
+    pub pool_errors: Option<PoolFault>,
+    //Milliseconds added to the local clock, negative values put it behind
+    #[serde(default)]
+    pub clock_skew_ms: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fault {
+    pub probability: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LatencyFault {
+    pub probability: f64,
+    pub min_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PoolFault {
+    pub probability: f64,
+    //Empty fails requests to every pool
+    #[serde(default)]
+    pub pool_urls: Vec<String>,
+}
+
+fn hit<R: Rng>(probability: f64, rng: &mut R) -> bool {
+    probability > 0.0 && rng.gen_bool(probability.min(1.0))
+}
+
+impl ChaosScenario {
+    pub fn harvester_latency<R: Rng>(&self, rng: &mut R) -> Option<Duration> {
+        let fault = self.harvester_latency.as_ref()?;
+        hit(fault.probability, rng).then(|| {
+            Duration::from_millis(rng.gen_range(fault.min_ms..=fault.max_ms.max(fault.min_ms)))
+        })
+    }
+    pub fn drops_frame<R: Rng>(&self, rng: &mut R) -> bool {
+        self.dropped_frames
+            .as_ref()
+            .is_some_and(|f| hit(f.probability, rng))
+    }
+    //Fails like a pool answering 500, which the retry and queue logic treats as the pool being down
+    pub fn pool_error<R: Rng>(&self, pool_url: &str, rng: &mut R) -> Option<PoolError> {
+        let fault = self.pool_errors.as_ref()?;
+        let pool_url = pool_url.trim_end_matches('/');
+        let targeted = fault.pool_urls.is_empty()
+            || fault
+                .pool_urls
+                .iter()
+                .any(|u| u.trim_end_matches('/') == pool_url);
+        (targeted && hit(fault.probability, rng)).then(|| PoolError {
+            error_code: PoolErrorCode::RequestFailed as u8,
+            error_message: "Bad Status Code: 500 Internal Server Error, injected by chaos mode"
+                .to_string(),
+        })
+    }
+}
+
+struct Chaos {
+    scenario: ChaosScenario,
+    rng: Mutex<StdRng>,
+}
+
+static CHAOS: OnceLock<Chaos> = OnceLock::new();
+
+pub fn install(path: &Path) -> Result<(), Error> {
+    if !cfg!(feature = "chaos") {
+        return Err(Error::other(
+            "Chaos mode needs a build with the chaos feature",
+        ));
+    }
+    let scenario: ChaosScenario = serde_yaml::from_str(&std::fs::read_to_string(path)?)
+        .map_err(|e| Error::other(format!("Invalid chaos scenario {path:?}: {e}")))?;
+    let rng = match scenario.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    warn!("Chaos mode is injecting failures from {path:?}: {scenario:?}");
+    CHAOS
+        .set(Chaos {
+            scenario,
+            rng: Mutex::new(rng),
+        })
+        .map_err(|_| Error::other("Chaos mode is already enabled"))
+}
+
+//Never set in builds without the chaos feature, so the hooks below are no-ops there
+fn with_chaos<T, F: FnOnce(&ChaosScenario, &mut StdRng) -> T>(f: F) -> Option<T> {
+    if !cfg!(feature = "chaos") {
+        return None;
+    }
+    let chaos = CHAOS.get()?;
+    let mut rng = chaos.rng.lock().ok()?;
+    Some(f(&chaos.scenario, &mut rng))
+}
+
+pub fn harvester_latency() -> Option<Duration> {
+    let latency = with_chaos(|s, rng| s.harvester_latency(rng)).flatten();
+    if let Some(latency) = latency {
+        debug!(
+            "Chaos: delaying harvester lookups by {}ms",
+            latency.as_millis()
+        );
+    }
+    latency
+}
+
+pub fn drop_frame(name: &str) -> bool {
+    let dropped = with_chaos(|s, rng| s.drops_frame(rng)).unwrap_or_default();
+    if dropped {
+        debug!("Chaos: dropping {name} frame");
+    }
+    dropped
+}
+
+pub fn pool_error(pool_url: &str) -> Option<PoolError> {
+    let error = with_chaos(|s, rng| s.pool_error(pool_url, rng)).flatten();
+    if error.is_some() {
+        debug!("Chaos: failing request to {pool_url}");
+    }
+    error
+}
+
+pub fn clock_skew_ms() -> i64 {
+    with_chaos(|s, _| s.clock_skew_ms).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn injects_faults_by_probability() {
+        let scenario: ChaosScenario = serde_yaml::from_str(
+            "harvester_latency: {probability: 1, min_ms: 100, max_ms: 200}\n\
+             dropped_frames: {probability: 0}\n\
+             pool_errors: {probability: 1, pool_urls: [\"https://flaky.pool/\"]}\n\
+             clock_skew_ms: -3000\n",
+        )
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        let latency = scenario.harvester_latency(&mut rng).unwrap();
+        assert!((100..=200).contains(&(latency.as_millis() as u64)));
+        assert!(!scenario.drops_frame(&mut rng));
+        assert_eq!(
+            scenario
+                .pool_error("https://flaky.pool", &mut rng)
+                .unwrap()
+                .error_code,
+            PoolErrorCode::RequestFailed as u8
+        );
+        assert!(scenario
+            .pool_error("https://other.pool", &mut rng)
+            .is_none());
+        assert_eq!(scenario.clock_skew_ms, -3000);
+        assert!(serde_yaml::from_str::<ChaosScenario>("pool_500s: {}").is_err());
+        assert_eq!(ChaosScenario::default().harvester_latency(&mut rng), None);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::farmer::chaos;
+use serde::{Deserialize, Serialize};
+use std::io::Error;
+use std::time::{Duration, SystemTime};
+use tokio::net::UdpSocket;
+
+const NTP_PORT: u16 = 123;
+const NTP_TIMEOUT: Duration = Duration::from_secs(5);
+//Seconds between 1900, the NTP epoch, and 1970
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+//Offset of the system clock against an NTP server, positive when the system clock is ahead
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockDrift {
+    pub offset_ms: i64,
+    pub round_trip_ms: u64,
+    pub server: String,
+    pub checked: u64,
+}
+
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+        .saturating_add_signed(chaos::clock_skew_ms())
+}
+
+fn ntp_millis(bytes: &[u8]) -> u64 {
+    let seconds = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64;
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as u64;
+    seconds.saturating_sub(NTP_UNIX_OFFSET) * 1000 + ((fraction * 1000) >> 32)
+}
+
+//Offset and round trip from a server reply, sent is when the request left and received when the
+//reply arrived, both in unix milliseconds
+fn parse_reply(reply: &[u8], sent: u64, received: u64) -> Result<(i64, u64), Error> {
+    if reply.len() < 48 || reply[0] & 0x07 != 4 {
+        return Err(Error::other("Not an NTP server reply"));
+    }
+    //Stratum 0 is a kiss-o'-death, the server refuses to answer
+    if reply[1] == 0 {
+        return Err(Error::other("NTP server refused the request"));
+    }
+    let server_received = ntp_millis(&reply[32..40]) as i64;
+    let server_sent = ntp_millis(&reply[40..48]) as i64;
+    let (sent, received) = (sent as i64, received as i64);
+    let offset = ((server_received - sent) + (server_sent - received)) / 2;
+    let round_trip = (received - sent) - (server_sent - server_received);
+    Ok((-offset, round_trip.max(0) as u64))
+}
+
+//Single SNTP request, the server address may include a port
+pub async fn query_ntp(server: &str) -> Result<ClockDrift, Error> {
+    let address = if server.contains(':') {
+        server.to_string()
+    } else {
+        format!("{server}:{NTP_PORT}")
+    };
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect(&address).await?;
+    let mut request = [0u8; 48];
+    //Leap indicator 0, version 3, client mode
+    request[0] = 0x1B;
+    let mut reply = [0u8; 48];
+    let sent = unix_millis();
+    socket.send(&request).await?;
+    let read = tokio::time::timeout(NTP_TIMEOUT, socket.recv(&mut reply))
+        .await
+        .map_err(|_| Error::other(format!("No reply from {address}")))??;
+    let received = unix_millis();
+    let (offset_ms, round_trip_ms) = parse_reply(&reply[..read], sent, received)?;
+    Ok(ClockDrift {
+        offset_ms,
+        round_trip_ms,
+        server: server.to_string(),
+        checked: received / 1000,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ntp_timestamp(unix_ms: u64) -> [u8; 8] {
+        let seconds = (unix_ms / 1000 + NTP_UNIX_OFFSET) as u32;
+        let fraction = (((unix_ms % 1000) << 32) / 1000) as u32;
+        let mut bytes = [0u8; 8];
+        bytes[..4].copy_from_slice(&seconds.to_be_bytes());
+        bytes[4..].copy_from_slice(&fraction.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn offset_is_positive_when_the_local_clock_is_ahead() {
+        let mut reply = [0u8; 48];
+        reply[0] = 0x1C;
+        reply[1] = 2;
+        //The local clock is 2s ahead, 40ms each way and 10ms on the server
+        let sent = 1_700_000_002_000;
+        reply[32..40].copy_from_slice(&ntp_timestamp(1_700_000_000_040));
+        reply[40..48].copy_from_slice(&ntp_timestamp(1_700_000_000_050));
+        let (offset, round_trip) = parse_reply(&reply, sent, sent + 90).unwrap();
+        assert!((offset - 2000).abs() <= 1, "offset {offset}");
+        assert!((79..=81).contains(&round_trip), "round trip {round_trip}");
+        reply[1] = 0;
+        assert!(parse_reply(&reply, sent, sent + 90).is_err());
+    }
+}
@@ -1,20 +1,72 @@
+use crate::error::{FarmerError, FarmerResult};
+use crate::farmer::config_migration::{
+    current_version, load_migrated, CONFIG_MIGRATIONS, HARVESTER_CONFIG_MIGRATIONS,
+};
+use crate::farmer::events::EventFilter;
+use crate::farmer::host_vars::{apply_host_vars, host_name};
+use crate::farmer::keyring::KeyringSource;
+use crate::farmer::pool_endpoints::validate_endpoints;
+use crate::farmer::time_format::TimeConfig;
+use crate::farmer::units;
+use crate::farmer::webhook::WebhookEvent;
 use blst::min_pk::SecretKey;
 use dg_xch_core::blockchain::sized_bytes::{Bytes32, Bytes48};
-use dg_xch_core::consensus::constants::CONSENSUS_CONSTANTS_MAP;
+use dg_xch_core::consensus::constants::{ConsensusConstants, CONSENSUS_CONSTANTS_MAP, MAINNET};
 use dg_xch_keys::decode_puzzle_hash;
-use std::collections::HashMap;
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::io::{Error, ErrorKind};
+use std::io::Error;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use tokio::runtime::{Builder, Runtime};
+
+pub const STRICT_CONFIG_KEY: &str = "strict_config";
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct FarmingInfo {
+    #[serde(default)]
     pub farmer_secret_key: Bytes32,
     pub launcher_id: Option<Bytes32>,
     pub pool_secret_key: Option<Bytes32>,
     pub owner_secret_key: Option<Bytes32>,
     pub auth_secret_key: Option<Bytes32>,
+    //Loads the secret keys from the Chia keyring at startup instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyring: Option<KeyringSource>,
+    //Watch only entries set the public keys instead, their plots are farmed but nothing is signed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub farmer_public_key: Option<Bytes48>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_public_key: Option<Bytes48>,
+}
+impl FarmingInfo {
+    pub fn is_watch_only(&self) -> bool {
+        self.farmer_public_key.is_some()
+            && self.keyring.is_none()
+            && self.farmer_secret_key == Bytes32::default()
+    }
+    pub fn farmer_public_key(&self) -> Bytes48 {
+        match self.farmer_public_key {
+            Some(public_key) if self.is_watch_only() => public_key,
+            _ => {
+                let f_sk: SecretKey = self.farmer_secret_key.into();
+                f_sk.sk_to_pk().to_bytes().into()
+            }
+        }
+    }
+    pub fn pool_public_key(&self) -> Option<Bytes48> {
+        match self.pool_secret_key {
+            Some(sk) if !self.is_watch_only() => {
+                let p_sk: SecretKey = sk.into();
+                Some(p_sk.sk_to_pk().to_bytes().into())
+            }
+            _ => self.pool_public_key,
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -25,11 +77,409 @@ pub struct PoolWalletConfig {
     pub target_puzzle_hash: Bytes32,
     pub p2_singleton_puzzle_hash: Bytes32,
     pub owner_public_key: Bytes48,
+    //Only submit partials in the best N percent of the pool threshold, points drop by the same ratio
+    #[serde(default)]
+    pub partial_submit_percent: Option<u8>,
+    #[serde(default)]
+    pub tls: Option<PoolTlsConfig>,
+    //Send lookup time and harvester info with partials, unset sends them when the pool reports
+    //protocol version 2 or higher
+    #[serde(default)]
+    pub partial_metadata: Option<bool>,
+    //Urls of pools that do not serve the protocol at pool_url/<endpoint>
+    #[serde(default)]
+    pub endpoints: Option<PoolEndpoints>,
+}
+
+//Full urls per pool protocol endpoint, {pool_url} is replaced with the pool_url. Unset endpoints
+//stay at pool_url/<endpoint>
+#[derive(Default, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PoolEndpoints {
+    #[serde(default)]
+    pub pool_info: Option<String>,
+    #[serde(default)]
+    pub farmer: Option<String>,
+    #[serde(default)]
+    pub partial: Option<String>,
+    //Batch endpoint of pools that offer one outside the pool protocol, only these get partial_batch
+    #[serde(default)]
+    pub partials: Option<String>,
+    #[serde(default)]
+    pub login: Option<String>,
+}
+
+//TLS options for pools with self signed or private CA certificates, only used for this pool's requests
+#[derive(Default, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PoolTlsConfig {
+    //PEM file with extra CA certificates, trusted next to the built in roots
+    #[serde(default)]
+    pub ca_file: Option<String>,
+    //SHA-256 fingerprints of accepted server certificates, these replace CA verification
+    #[serde(default)]
+    pub pinned_sha256: Vec<String>,
+    //For testing only, any certificate is accepted
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
+}
+
+pub const DEFAULT_FARM_NAME: &str = "default";
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FarmConfig {
+    pub name: String,
+    pub farmer_info: Vec<FarmingInfo>,
+    #[serde(default)]
+    pub pool_info: Vec<PoolWalletConfig>,
+    pub payout_address: String,
+}
+impl FarmConfig {
+    pub fn is_ready(&self) -> bool {
+        !self.name.is_empty()
+            && !self.farmer_info.is_empty()
+            && decode_puzzle_hash(&self.payout_address).is_ok()
+            && self.pool_info.iter().all(|c| {
+                self.farmer_info
+                    .iter()
+                    .any(|f| f.launcher_id == Some(c.launcher_id))
+            })
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PlotIoOptions {
+    //Bypass the OS page cache (O_DIRECT / FILE_FLAG_NO_BUFFERING)
+    #[serde(default)]
+    pub direct_io: bool,
+    //Bytes read per request with direct_io, 0 disables kernel read-ahead for buffered reads
+    #[serde(default, with = "units::bytes::option")]
+    pub read_ahead: Option<u64>,
+    //Number of read_ahead buffers kept per plot with direct_io
+    pub buffer_pool_size: Option<usize>,
+    //Keep the C1 table of these plots in RAM, bounded by ram_cache_mib
+    #[serde(default)]
+    pub ram_cache: bool,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PlotScanOptions {
+    //Glob patterns matched against file names, like *.tmp
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    //Files smaller than this many bytes are not loaded, copies in progress grow to their final size
+    #[serde(default, with = "units::bytes")]
+    pub min_file_size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct BladebitHarvesterConfig {
     pub plot_directories: Vec<String>,
+    #[serde(default)]
+    pub io_options: HashMap<String, PlotIoOptions>,
+    #[serde(default)]
+    pub scan_options: HashMap<String, PlotScanOptions>,
+    #[serde(default, with = "units::mib")]
+    pub ram_cache_mib: u64,
+    //Seconds a plot whose file disappeared is kept before it is dropped from the farm
+    #[serde(default = "default_missing_plot_grace", with = "units::secs")]
+    pub missing_plot_grace: u64,
+    #[serde(default)]
+    pub dir_circuit: DirCircuitConfig,
+    //Experimental, plots stored in S3 compatible buckets
+    #[serde(default)]
+    pub object_storage: Vec<ObjectStorageConfig>,
+    //Seconds after which results of a signage point are useless, its lookups are cancelled once a newer one arrives, 0 disables
+    #[serde(default = "default_sp_deadline", with = "units::secs")]
+    pub sp_deadline: u64,
+    #[serde(default)]
+    pub audit: PlotAuditConfig,
+    #[serde(default)]
+    pub io_recovery: PlotRecoveryConfig,
+    //Plot filter size per network name, networks not listed use their consensus constants
+    #[serde(default)]
+    pub plot_filter_bits: HashMap<String, usize>,
+    #[serde(default)]
+    pub lookup_concurrency: LookupConcurrencyConfig,
+    //Lookup workers per physical drive, 0 schedules all lookups from one shared queue
+    #[serde(default)]
+    pub drive_workers: usize,
+    //Random delay of up to this many ms before each plot directory starts its lookups, 0 disables
+    #[serde(default, with = "units::millis")]
+    pub sp_stagger_ms: u64,
+    #[serde(default)]
+    pub drive_health: DriveHealthConfig,
+    //Plot directory to the NUMA node of the HBA serving it, their lookups and caches stay on it
+    #[serde(default)]
+    pub numa_nodes: HashMap<String, usize>,
+    #[serde(default)]
+    pub decompression: DecompressionConfig,
+}
+impl Default for BladebitHarvesterConfig {
+    fn default() -> Self {
+        Self {
+            plot_directories: vec![],
+            io_options: HashMap::new(),
+            scan_options: HashMap::new(),
+            ram_cache_mib: 0,
+            missing_plot_grace: default_missing_plot_grace(),
+            dir_circuit: DirCircuitConfig::default(),
+            object_storage: vec![],
+            sp_deadline: default_sp_deadline(),
+            audit: PlotAuditConfig::default(),
+            io_recovery: PlotRecoveryConfig::default(),
+            plot_filter_bits: HashMap::new(),
+            lookup_concurrency: LookupConcurrencyConfig::default(),
+            drive_workers: 0,
+            sp_stagger_ms: 0,
+            drive_health: DriveHealthConfig::default(),
+            numa_nodes: HashMap::new(),
+            decompression: DecompressionConfig::default(),
+        }
+    }
+}
+impl BladebitHarvesterConfig {
+    pub fn plot_filter_bits(&self, network: &str, constants: &ConsensusConstants) -> usize {
+        self.plot_filter_bits
+            .get(network)
+            .copied()
+            .unwrap_or(constants.number_zero_bits_plot_filter)
+    }
+}
+fn default_missing_plot_grace() -> u64 {
+    300
+}
+//Pools reject partials that arrive later than this after the signage point
+fn default_sp_deadline() -> u64 {
+    25
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecompressionBackend {
+    Cpu,
+}
+
+//Decompression of compressed plots, the first provider that works on this host is used
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DecompressionConfig {
+    //Tried in order, the CPU is always the last resort
+    #[serde(default = "default_decompression_providers")]
+    pub providers: Vec<DecompressionBackend>,
+    //Threads of the CPU decompressor, 0 uses all cores
+    #[serde(default)]
+    pub threads: usize,
+}
+impl Default for DecompressionConfig {
+    fn default() -> Self {
+        Self {
+            providers: default_decompression_providers(),
+            threads: 0,
+        }
+    }
+}
+fn default_decompression_providers() -> Vec<DecompressionBackend> {
+    vec![DecompressionBackend::Cpu]
+}
+
+//SMART readings of the drives backing the plot directories, smartctl usually needs root
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DriveHealthConfig {
+    //Seconds between two readings of every drive, 0 disables them
+    #[serde(default, with = "units::secs")]
+    pub interval: u64,
+    #[serde(default = "default_smartctl")]
+    pub smartctl: String,
+    //Drives at or above this temperature in °C are flagged
+    #[serde(default = "default_max_drive_temperature")]
+    pub max_temperature: i64,
+}
+impl Default for DriveHealthConfig {
+    fn default() -> Self {
+        Self {
+            interval: 0,
+            smartctl: default_smartctl(),
+            max_temperature: default_max_drive_temperature(),
+        }
+    }
+}
+fn default_smartctl() -> String {
+    "smartctl".to_string()
+}
+fn default_max_drive_temperature() -> i64 {
+    55
+}
+
+//Background proof checks on one plot at a time, only while no signage point lookups are running
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PlotAuditConfig {
+    //Seconds between two plot checks, 0 disables the audit
+    #[serde(default = "default_audit_interval", with = "units::secs")]
+    pub interval: u64,
+    //Random challenges looked up per checked plot
+    #[serde(default = "default_audit_challenges")]
+    pub challenges: usize,
+}
+impl Default for PlotAuditConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_audit_interval(),
+            challenges: default_audit_challenges(),
+        }
+    }
+}
+fn default_audit_interval() -> u64 {
+    30
+}
+fn default_audit_challenges() -> usize {
+    2
+}
+
+//Plots whose lookups fail with an IO error are left out of lookups, reopened and checked again
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PlotRecoveryConfig {
+    //Seconds before the first reopen, doubled after every failed one
+    #[serde(default = "default_recovery_backoff", with = "units::secs")]
+    pub backoff: u64,
+    //Reopens before the plot is marked unreadable, 0 keeps failing plots in the lookups
+    #[serde(default = "default_recovery_attempts")]
+    pub attempts: u32,
+}
+impl Default for PlotRecoveryConfig {
+    fn default() -> Self {
+        Self {
+            backoff: default_recovery_backoff(),
+            attempts: default_recovery_attempts(),
+        }
+    }
+}
+fn default_recovery_backoff() -> u64 {
+    30
+}
+fn default_recovery_attempts() -> u32 {
+    5
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ObjectStorageConfig {
+    //Like https://s3.us-east-1.amazonaws.com or http://minio.local:9000
+    pub endpoint: String,
+    pub bucket: String,
+    //Plots are loaded from directly below this prefix
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default = "default_object_storage_region")]
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    //Bucket in the path instead of the host name, most self hosted stores need this
+    #[serde(default = "default_object_storage_path_style")]
+    pub path_style: bool,
+    //Range requests in flight for this bucket
+    #[serde(default = "default_object_storage_concurrency")]
+    pub max_concurrent_requests: usize,
+    //Bytes fetched per range request
+    #[serde(default = "default_object_storage_read_ahead", with = "units::bytes")]
+    pub read_ahead: u64,
+    //Local copies of plot headers and C tables, defaults to object_cache/ in the root path
+    pub cache_dir: Option<String>,
+    //Also keep C3 locally, about 1.5% of the plot size, saving a range request per lookup
+    #[serde(default)]
+    pub cache_c3: bool,
+}
+fn default_object_storage_region() -> String {
+    String::from("us-east-1")
+}
+fn default_object_storage_path_style() -> bool {
+    true
+}
+fn default_object_storage_concurrency() -> usize {
+    16
+}
+fn default_object_storage_read_ahead() -> u64 {
+    64 * 1024
+}
+
+//Plot lookups running at once, adjusted after every signage point from the lookup latency, the
+//signage point completion time and the Linux IO and memory pressure
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LookupConcurrencyConfig {
+    //Off leaves every eligible plot lookup running at once
+    #[serde(default = "default_adaptive_concurrency")]
+    pub adaptive: bool,
+    #[serde(default = "default_min_lookup_concurrency")]
+    pub min: usize,
+    #[serde(default = "default_max_lookup_concurrency")]
+    pub max: usize,
+    //Milliseconds after the signage point all lookups should be done in
+    #[serde(default = "default_lookup_target_ms", with = "units::millis")]
+    pub target_ms: u64,
+    //Percent of the last 10 seconds tasks stalled on IO or memory before the limit backs off
+    #[serde(default = "default_io_pressure")]
+    pub io_pressure: u8,
+    #[serde(default = "default_memory_pressure")]
+    pub memory_pressure: u8,
+}
+impl Default for LookupConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            adaptive: default_adaptive_concurrency(),
+            min: default_min_lookup_concurrency(),
+            max: default_max_lookup_concurrency(),
+            target_ms: default_lookup_target_ms(),
+            io_pressure: default_io_pressure(),
+            memory_pressure: default_memory_pressure(),
+        }
+    }
+}
+fn default_adaptive_concurrency() -> bool {
+    true
+}
+fn default_min_lookup_concurrency() -> usize {
+    4
+}
+fn default_max_lookup_concurrency() -> usize {
+    256
+}
+//Chia warns about lookups slower than 5 seconds
+fn default_lookup_target_ms() -> u64 {
+    5000
+}
+fn default_io_pressure() -> u8 {
+    40
+}
+fn default_memory_pressure() -> u8 {
+    10
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DirCircuitConfig {
+    //Seconds a plot lookup may take before it counts as a timeout for its directory
+    #[serde(default = "default_dir_lookup_timeout", with = "units::secs")]
+    pub lookup_timeout: u64,
+    //Signage points in a row with timeouts before the directory is skipped, 0 never skips
+    #[serde(default = "default_dir_failure_threshold")]
+    pub failure_threshold: u32,
+    //Seconds a directory is skipped before lookups probe it again
+    #[serde(default = "default_dir_skip_duration", with = "units::secs")]
+    pub skip_duration: u64,
+}
+impl Default for DirCircuitConfig {
+    fn default() -> Self {
+        Self {
+            lookup_timeout: default_dir_lookup_timeout(),
+            failure_threshold: default_dir_failure_threshold(),
+            skip_duration: default_dir_skip_duration(),
+        }
+    }
+}
+fn default_dir_lookup_timeout() -> u64 {
+    20
+}
+fn default_dir_failure_threshold() -> u32 {
+    3
+}
+fn default_dir_skip_duration() -> u64 {
+    120
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -37,26 +487,691 @@ pub struct HarvesterConfig {
     pub bladebit: Option<BladebitHarvesterConfig>,
 }
 
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpPreference {
+    #[default]
+    System,
+    Ipv4,
+    Ipv6,
+    Ipv4Only,
+    Ipv6Only,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DnsConfig {
+    pub nameserver: Option<String>,
+    #[serde(default)]
+    pub ip_preference: IpPreference,
+    #[serde(default)]
+    pub static_hosts: HashMap<String, IpAddr>,
+    //Milliseconds before the next address is tried in parallel, 0 tries addresses one after another
+    #[serde(default = "default_happy_eyeballs_delay", with = "units::millis")]
+    pub happy_eyeballs_delay: u64,
+}
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            nameserver: None,
+            ip_preference: IpPreference::default(),
+            static_hosts: HashMap::new(),
+            happy_eyeballs_delay: default_happy_eyeballs_delay(),
+        }
+    }
+}
+fn default_happy_eyeballs_delay() -> u64 {
+    250
+}
+
+//Only meant for simulators and testnets, values differing from the network break farming
+#[derive(Default, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ConsensusOverrides {
+    pub difficulty_constant_factor: Option<u128>,
+    pub number_zero_bits_plot_filter: Option<usize>,
+    pub num_sps_sub_slot: Option<u32>,
+    pub pool_sub_slot_iters: Option<u64>,
+}
+
+//Second full node for heavy RPC reads, keeps them off the node serving the farming websocket
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SpareRpcConfig {
+    pub host: String,
+    pub port: u16,
+    //Certificates accepted by the spare node, defaults to ssl_root_path
+    #[serde(default)]
+    pub ssl_root_path: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionProfile {
+    #[default]
+    Local,
+    Remote,
+}
+
+//Overrides of the connection_profile defaults for the full node websocket
+#[derive(Default, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ConnectionConfig {
+    //Seconds between keepalive frames, 0 sends none
+    #[serde(default, with = "units::secs::option")]
+    pub ping_interval: Option<u64>,
+    //Seconds without a signage point before the connection is replaced
+    #[serde(default, with = "units::secs::option")]
+    pub sp_timeout: Option<u64>,
+    //Wait before reconnecting, doubled after each failed attempt up to max_reconnect_delay
+    #[serde(default, with = "units::millis::option")]
+    pub reconnect_delay: Option<u64>,
+    #[serde(default, with = "units::millis::option")]
+    pub max_reconnect_delay: Option<u64>,
+    #[serde(default)]
+    pub relay: Option<RelayEndpointConfig>,
+}
+
+//Second way to the same full node, like a tunnel on another host, used while the node is unreachable
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RelayEndpointConfig {
+    pub host: String,
+    pub port: u16,
+    //Failed connects to fullnode_ws_host before the relay is tried
+    #[serde(default = "default_relay_after_failures")]
+    pub after_failures: u32,
+}
+fn default_relay_after_failures() -> u32 {
+    2
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RpcPolicyConfig {
+    //Seconds before a single RPC attempt is abandoned
+    #[serde(default = "default_rpc_timeout", with = "units::secs")]
+    pub timeout: u64,
+    #[serde(default = "default_rpc_retries")]
+    pub retries: u32,
+    //Milliseconds between attempts, doubled after each retry
+    #[serde(default = "default_rpc_retry_delay", with = "units::millis")]
+    pub retry_delay: u64,
+    //Consecutive failed calls that open the circuit, 0 disables the circuit breaker
+    #[serde(default = "default_rpc_failure_threshold")]
+    pub failure_threshold: u32,
+    //Seconds calls fail fast once the circuit is open
+    #[serde(default = "default_rpc_open_duration", with = "units::secs")]
+    pub open_duration: u64,
+}
+impl Default for RpcPolicyConfig {
+    fn default() -> Self {
+        Self {
+            timeout: default_rpc_timeout(),
+            retries: default_rpc_retries(),
+            retry_delay: default_rpc_retry_delay(),
+            failure_threshold: default_rpc_failure_threshold(),
+            open_duration: default_rpc_open_duration(),
+        }
+    }
+}
+fn default_rpc_timeout() -> u64 {
+    10
+}
+fn default_rpc_retries() -> u32 {
+    2
+}
+fn default_rpc_retry_delay() -> u64 {
+    500
+}
+fn default_rpc_failure_threshold() -> u32 {
+    5
+}
+fn default_rpc_open_duration() -> u64 {
+    30
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PartialQueueConfig {
+    #[serde(default = "default_partial_queue_size")]
+    pub max_size: usize,
+    //Seconds after the signage point a queued partial is dropped, pools reject late partials
+    #[serde(default = "default_partial_queue_max_age", with = "units::secs")]
+    pub max_age: u64,
+}
+impl Default for PartialQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_size: default_partial_queue_size(),
+            max_age: default_partial_queue_max_age(),
+        }
+    }
+}
+fn default_partial_queue_size() -> usize {
+    1000
+}
+fn default_partial_queue_max_age() -> u64 {
+    default_sp_deadline()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PartialBatchConfig {
+    //Milliseconds partials for a pool are collected before they are sent together, partials closer
+    //to the end of the pool window are sent sooner
+    #[serde(default = "default_partial_batch_window", with = "units::millis")]
+    pub window_ms: u64,
+    #[serde(default = "default_partial_batch_size")]
+    pub max_size: usize,
+}
+impl Default for PartialBatchConfig {
+    fn default() -> Self {
+        Self {
+            window_ms: default_partial_batch_window(),
+            max_size: default_partial_batch_size(),
+        }
+    }
+}
+fn default_partial_batch_window() -> u64 {
+    500
+}
+fn default_partial_batch_size() -> usize {
+    50
+}
+
+//Aggregate stats posted to a user run endpoint, nothing is sent unless this is configured
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StatsReportConfig {
+    pub endpoint: String,
+    //Minutes between reports
+    #[serde(default = "default_stats_report_interval", with = "units::minutes")]
+    pub interval: u64,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+fn default_stats_report_interval() -> u64 {
+    15
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    //Plain connection upgraded with STARTTLS, usually port 587
+    #[default]
+    Starttls,
+    //TLS from the first byte, usually port 465
+    Tls,
+    //No encryption, only for a relay on localhost or the LAN
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailSchedule {
+    #[default]
+    Daily,
+    Weekly,
+}
+
+//Summary of plots, points, efficiency, incidents and blocks mailed through an SMTP server
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct EmailReportConfig {
+    pub smtp_host: String,
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+    #[serde(default)]
+    pub schedule: EmailSchedule,
+    //Local hour of the day the report is sent, weekly reports go out on mondays
+    #[serde(default)]
+    pub hour: u8,
+    //{{ variable }} templates, see the readme for the variables
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub template: Option<String>,
+}
+fn default_smtp_port() -> u16 {
+    587
+}
+
+//Posts partial results and block proofs to a user endpoint, rendered through an optional template
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    //Only partials for this pool are sent, block proofs are sent to every hook
+    #[serde(default)]
+    pub pool_url: Option<String>,
+    #[serde(default = "default_webhook_events")]
+    pub events: Vec<WebhookEvent>,
+    //Body with {{ variable }} placeholders, the event json is sent when empty
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default = "default_webhook_content_type")]
+    pub content_type: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+fn default_webhook_events() -> Vec<WebhookEvent> {
+    vec![
+        WebhookEvent::PartialAccepted,
+        WebhookEvent::PartialRejected,
+        WebhookEvent::Block,
+    ]
+}
+fn default_webhook_content_type() -> String {
+    "application/json".to_string()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpRelayFormat {
+    //NewSignagePoint as a JSON text frame
+    #[default]
+    Json,
+    //NewSignagePoint in chia serialization as a binary frame
+    Binary,
+}
+
+//Websocket endpoint that receives every signage point the farmer gets from the full node
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SpRelayConfig {
+    pub url: String,
+    #[serde(default)]
+    pub format: SpRelayFormat,
+}
+
+//Two instances sharing one lock file, only the one holding a fresh heartbeat sends proofs and partials
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct HighAvailabilityConfig {
+    //Heartbeat file on storage both instances can reach
+    pub lock_file: String,
+    //The standby instance waits takeover_after seconds for the primary before claiming a missing lock
+    #[serde(default)]
+    pub standby: bool,
+    //Seconds between heartbeats of the active instance
+    #[serde(default = "default_heartbeat_interval", with = "units::secs")]
+    pub heartbeat_interval: u64,
+    //Seconds without a heartbeat before the active instance is considered down
+    #[serde(default = "default_takeover_after", with = "units::secs")]
+    pub takeover_after: u64,
+}
+//What a farmer does when another process already farms its config
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceLockMode {
+    #[default]
+    Exit,
+    //Run without sending proofs and partials until the other process stops
+    Standby,
+    Off,
+}
+
+//Checks that the owner keys still own their plot NFTs on chain and the pools still accept the
+//authentication keys
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct KeyHealthConfig {
+    //0 disables the checks
+    #[serde(default = "default_key_health_interval", with = "units::secs")]
+    pub interval: u64,
+}
+impl Default for KeyHealthConfig {
+    fn default() -> Self {
+        Self {
+            interval: default_key_health_interval(),
+        }
+    }
+}
+fn default_key_health_interval() -> u64 {
+    3600
+}
+
+//Weekly lookup latency per plot directory and submission latency per pool, compared against the
+//week before
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LatencyRegressionConfig {
+    #[serde(default = "default_latency_regression_window", with = "units::secs")]
+    pub window: u64,
+    //p95 of the current window over the baseline's in percent that is reported, 0 disables
+    #[serde(default = "default_latency_regression_factor_percent")]
+    pub factor_percent: u32,
+    //Samples both windows need before they are compared
+    #[serde(default = "default_latency_regression_min_samples")]
+    pub min_samples: u64,
+}
+impl Default for LatencyRegressionConfig {
+    fn default() -> Self {
+        Self {
+            window: default_latency_regression_window(),
+            factor_percent: default_latency_regression_factor_percent(),
+            min_samples: default_latency_regression_min_samples(),
+        }
+    }
+}
+fn default_latency_regression_window() -> u64 {
+    7 * 24 * 3600
+}
+fn default_latency_regression_factor_percent() -> u32 {
+    150
+}
+fn default_latency_regression_min_samples() -> u64 {
+    200
+}
+
+//What the farmer does when a pool pays a launcher's rewards to another address than its farm's
+//payout_address
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutMismatchPolicy {
+    Warn,
+    //Sends the configured payout address with put_farmer on every farmer update until it matches
+    #[default]
+    Correct,
+    //Stops sending partials of the launcher until the pool pays the configured address again
+    Halt,
+}
+
+fn default_heartbeat_interval() -> u64 {
+    5
+}
+fn default_takeover_after() -> u64 {
+    30
+}
+
+//Browser dashboard, read only and without authentication, keep it on a trusted address
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DashboardConfig {
+    #[serde(default = "default_dashboard_bind")]
+    pub bind: String,
+}
+fn default_dashboard_bind() -> String {
+    "127.0.0.1:8650".to_string()
+}
+
+//Harvesters connecting to the farmer need a certificate of the private CA in ssl_root_path
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct HarvesterServerConfig {
+    #[serde(default = "default_harvester_server_bind")]
+    pub bind: String,
+    //Also accept dg_fast_harvester connections over QUIC on the same port (UDP), needs the quic feature
+    #[serde(default)]
+    pub quic: bool,
+}
+fn default_harvester_server_bind() -> String {
+    "0.0.0.0:8447".to_string()
+}
+
+//Status page without keys, addresses or launcher ids, meant to be shared publicly
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PublicStatusConfig {
+    #[serde(default = "default_public_status_bind")]
+    pub bind: String,
+}
+fn default_public_status_bind() -> String {
+    "0.0.0.0:8651".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SpRecoveryConfig {
+    //Signage points the full node received longer ago are not signed for
+    #[serde(default = "default_sp_recovery_max_age", with = "units::secs")]
+    pub max_age: u64,
+}
+fn default_sp_recovery_max_age() -> u64 {
+    30
+}
+
+//Locally accepted partial points compared with the points each pool credited, per window
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PointsReconciliationConfig {
+    #[serde(default = "default_reconciliation_window", with = "units::secs")]
+    pub window: u64,
+    //Share of the accepted points the pool may leave uncredited in a window
+    #[serde(default = "default_reconciliation_missing_percent")]
+    pub missing_percent: u8,
+    //Consecutive short windows before the discrepancy is reported
+    #[serde(default = "default_reconciliation_windows")]
+    pub windows: u32,
+}
+impl Default for PointsReconciliationConfig {
+    fn default() -> Self {
+        Self {
+            window: default_reconciliation_window(),
+            missing_percent: default_reconciliation_missing_percent(),
+            windows: default_reconciliation_windows(),
+        }
+    }
+}
+fn default_reconciliation_window() -> u64 {
+    6 * 3600
+}
+fn default_reconciliation_missing_percent() -> u8 {
+    10
+}
+fn default_reconciliation_windows() -> u32 {
+    2
+}
+
+//Estimated daily earnings per plot NFT from its recent points, the pool fee and the netspace.
+//The price is only fetched from price_url when it is set
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct EarningsConfig {
+    #[serde(default)]
+    pub price_url: Option<String>,
+    //JSON pointer to the price in the response
+    #[serde(default = "default_price_pointer")]
+    pub price_pointer: String,
+    #[serde(default = "default_price_currency")]
+    pub currency: String,
+    //Seconds between two price requests
+    #[serde(default = "default_price_interval", with = "units::secs")]
+    pub price_interval: u64,
+}
+fn default_price_pointer() -> String {
+    "/chia/usd".to_string()
+}
+fn default_price_currency() -> String {
+    "USD".to_string()
+}
+fn default_price_interval() -> u64 {
+    900
+}
+
+//Publishes the farm status and events to an MQTT broker, for Home Assistant and similar
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MqttConfig {
+    pub host: String,
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+    #[serde(default = "default_mqtt_client_id")]
+    pub client_id: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default = "default_mqtt_topic_prefix")]
+    pub topic_prefix: String,
+    //Connect with TLS, ca_file replaces the system roots
+    #[serde(default)]
+    pub tls: bool,
+    #[serde(default)]
+    pub ca_file: Option<String>,
+    #[serde(default)]
+    pub qos: u8,
+    //Event groups to publish, every event when empty
+    #[serde(default)]
+    pub events: Vec<EventFilter>,
+    //Seconds between retained status messages
+    #[serde(default = "default_mqtt_status_interval", with = "units::secs")]
+    pub status_interval: u64,
+}
+fn default_mqtt_port() -> u16 {
+    1883
+}
+fn default_mqtt_client_id() -> String {
+    "dg_fast_farmer".to_string()
+}
+fn default_mqtt_topic_prefix() -> String {
+    "dg_fast_farmer".to_string()
+}
+fn default_mqtt_status_interval() -> u64 {
+    60
+}
+
+//External plugin process, started with the control socket path in FF_CONTROL_SOCKET and restarted when it exits
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PluginConfig {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Config {
+    //Layout version, older configs are upgraded on load
+    #[serde(default)]
+    pub config_version: u32,
     pub selected_network: String,
     pub ssl_root_path: Option<String>,
+    //Certificate sets per full node ("host" or "host:port") or network name, checked in that order
+    //before falling back to ssl_root_path
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub ssl_root_paths: HashMap<String, String>,
+    //Refuse full nodes whose certificate was not issued by the configured CA
+    #[serde(default = "default_verify_node_ca")]
+    pub verify_node_ca: bool,
     pub fullnode_ws_host: String,
     pub fullnode_ws_port: u16,
+    //remote tunes the full node websocket for a node on a VPS reached through NAT
+    #[serde(default)]
+    pub connection_profile: ConnectionProfile,
+    #[serde(default)]
+    pub connection: ConnectionConfig,
     pub fullnode_rpc_host: String,
     pub fullnode_rpc_port: u16,
     pub farmer_info: Vec<FarmingInfo>,
     pub pool_info: Vec<PoolWalletConfig>,
     pub payout_address: String,
     pub harvester_configs: HarvesterConfig,
+    #[serde(default = "default_sp_history_depth")]
+    pub sp_history_depth: u8,
+    #[serde(default)]
+    pub control_socket: Option<String>,
+    //Require requests that change the farm to be signed with the control identity key
+    #[serde(default = "default_control_auth")]
+    pub control_auth: bool,
+    #[serde(default = "default_pool_info_cache_ttl", with = "units::secs")]
+    pub pool_info_cache_ttl: u64,
+    #[serde(default)]
+    pub dns: Option<DnsConfig>,
+    #[serde(default)]
+    pub farms: Vec<FarmConfig>,
+    #[serde(default)]
+    pub partial_queue: Option<PartialQueueConfig>,
+    #[serde(default)]
+    pub partial_batch: Option<PartialBatchConfig>,
+    //Seconds after the signage point pools accept partials for, 0 sends partials of any age
+    #[serde(default = "default_sp_deadline", with = "units::secs")]
+    pub partial_window: u64,
+    //Seconds past partial_window before a partial is no longer sent
+    #[serde(default = "default_partial_cutoff_margin", with = "units::secs")]
+    pub partial_cutoff_margin: u64,
+    //Seconds a proof is remembered so it is not sent to its pool twice, 0 disables the check
+    #[serde(default = "default_duplicate_partial_window", with = "units::secs")]
+    pub duplicate_partial_window: u64,
+    #[serde(default = "default_status_interval", with = "units::minutes")]
+    pub status_interval: u64,
+    #[serde(default = "default_forensics_max_mib", with = "units::mib")]
+    pub forensics_max_mib: u64,
+    #[serde(default)]
+    pub consensus_overrides: Option<ConsensusOverrides>,
+    #[serde(default)]
+    pub fullnode_rpc_policy: RpcPolicyConfig,
+    #[serde(default)]
+    pub stats_report: Option<StatsReportConfig>,
+    #[serde(default)]
+    pub email_report: Option<EmailReportConfig>,
+    #[serde(default = "default_max_proofs_per_sp")]
+    pub max_proofs_per_sp: u32,
+    //Ask the full node for signage points the farmer does not know, like ones from just before a
+    //restart, instead of dropping their proofs
+    #[serde(default)]
+    pub sp_recovery: Option<SpRecoveryConfig>,
+    #[serde(default = "default_max_signature_requests")]
+    pub max_signature_requests: usize,
+    #[serde(default)]
+    pub runtime: RuntimeConfig,
+    #[serde(default = "default_event_history_size")]
+    pub event_history_size: usize,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    //Labels for bech32 addresses, payout_address can reference them as @label
+    #[serde(default)]
+    pub addresses: BTreeMap<String, String>,
+    #[serde(default)]
+    pub sp_relay: Vec<SpRelayConfig>,
+    #[serde(default)]
+    pub spare_rpc: Option<SpareRpcConfig>,
+    #[serde(default)]
+    pub high_availability: Option<HighAvailabilityConfig>,
+    //Ignored with high_availability, whose lock file already decides which instance farms
+    #[serde(default)]
+    pub instance_lock: InstanceLockMode,
+    //Seconds without a signage point before plot readers and caches are released, unset keeps them loaded
+    #[serde(default, with = "units::secs::option")]
+    pub idle_after: Option<u64>,
+    #[serde(default)]
+    pub dashboard: Option<DashboardConfig>,
+    #[serde(default)]
+    pub public_status: Option<PublicStatusConfig>,
+    #[serde(default)]
+    pub harvester_server: Option<HarvesterServerConfig>,
+    #[serde(default)]
+    pub time: TimeConfig,
+    #[serde(default)]
+    pub plugins: Vec<PluginConfig>,
+    #[serde(default)]
+    pub mqtt: Option<MqttConfig>,
+    #[serde(default)]
+    pub earnings: Option<EarningsConfig>,
+    #[serde(default)]
+    pub points_reconciliation: PointsReconciliationConfig,
+    #[serde(default)]
+    pub payout_mismatch: PayoutMismatchPolicy,
+    #[serde(default)]
+    pub key_health: KeyHealthConfig,
+    #[serde(default)]
+    pub latency_regression: LatencyRegressionConfig,
+    //Send the plot count per compression level of the harvester with partials to pools that accept
+    //extended partials
+    #[serde(default)]
+    pub client_metadata: bool,
+    //Fail to load on unknown fields instead of warning about them
+    #[serde(default)]
+    pub strict_config: bool,
+    //Directory of per host overrides (<hostname>.yaml) merged over this config, relative to it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_vars: Option<String>,
 }
 impl Config {
-    pub fn save_as_yaml<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
-        fs::write(
+    //Loads the config with the host_vars file of the given host merged over it, also returns the
+    //file that was merged
+    pub fn load_for_host(path: &Path, host: Option<&str>) -> FarmerResult<(Self, Option<PathBuf>)> {
+        let mut value = load_migrated(path, CONFIG_MIGRATIONS)?;
+        let host_file = apply_host_vars(path, &mut value, host)?;
+        let mut config: Config = parse_config(path, value)?;
+        config.resolve_address_labels()?;
+        config.validate_pool_endpoints()?;
+        Ok((config, host_file))
+    }
+    pub fn save_as_yaml<P: AsRef<Path>>(&self, path: P) -> FarmerResult<()> {
+        Ok(fs::write(
             path.as_ref(),
-            serde_yaml::to_string(&self)
-                .map_err(|e| Error::new(ErrorKind::Other, format!("{:?}", e)))?,
-        )
+            serde_yaml::to_string(&self).map_err(|e| FarmerError::Config(e.to_string()))?,
+        )?)
     }
     pub fn is_ready(&self) -> bool {
         CONSENSUS_CONSTANTS_MAP
@@ -66,19 +1181,113 @@ impl Config {
             && !self.fullnode_rpc_host.is_empty()
             && self.fullnode_ws_port != 0
             && self.fullnode_rpc_port != 0
-            && !self.farmer_info.is_empty()
-            && decode_puzzle_hash(&self.payout_address).is_ok()
-            && self.pool_info.iter().all(|c| {
-                self.farmer_info
-                    .iter()
-                    .any(|f| f.launcher_id == Some(c.launcher_id))
-            })
+            && self.sp_history_depth > 0
+            && {
+                let farms = self.all_farms();
+                let names: HashSet<&String> = farms.iter().map(|f| &f.name).collect();
+                !farms.is_empty()
+                    && names.len() == farms.len()
+                    && farms.iter().all(FarmConfig::is_ready)
+            }
+    }
+    pub fn consensus_constants(&self) -> &'static ConsensusConstants {
+        consensus_constants(&self.selected_network, &self.consensus_overrides)
+    }
+    pub fn node_ssl_root_path(&self, host: &str, port: u16) -> Option<String> {
+        [
+            format!("{host}:{port}"),
+            host.to_string(),
+            self.selected_network.clone(),
+        ]
+        .iter()
+        .find_map(|key| self.ssl_root_paths.get(key))
+        .or(self.ssl_root_path.as_ref())
+        .cloned()
+    }
+    //Farmer and pool public keys of every farmer_info, watch only entries included
+    pub fn public_keys(&self) -> (Vec<Bytes48>, Vec<Bytes48>) {
+        let farms = self.all_farms();
+        let infos = farms.iter().flat_map(|f| f.farmer_info.iter());
+        (
+            infos.clone().map(|info| info.farmer_public_key()).collect(),
+            infos.filter_map(|info| info.pool_public_key()).collect(),
+        )
+    }
+    //Every farmer_info only has public keys, proofs are reported instead of signed
+    pub fn is_watch_only(&self) -> bool {
+        let farms = self.all_farms();
+        let mut infos = farms.iter().flat_map(|f| f.farmer_info.iter()).peekable();
+        infos.peek().is_some() && infos.all(|info| info.is_watch_only())
+    }
+    //The top level farmer_info, pool_info and payout_address make up the default farm
+    pub fn all_farms(&self) -> Vec<FarmConfig> {
+        let mut farms = vec![];
+        if !self.farmer_info.is_empty() {
+            farms.push(FarmConfig {
+                name: DEFAULT_FARM_NAME.to_string(),
+                farmer_info: self.farmer_info.clone(),
+                pool_info: self.pool_info.clone(),
+                payout_address: self.payout_address.clone(),
+            });
+        }
+        farms.extend(self.farms.iter().cloned());
+        farms
+    }
+    pub fn resolve_address(&self, address: &str) -> FarmerResult<String> {
+        match address.strip_prefix('@') {
+            Some(label) => self
+                .addresses
+                .get(label)
+                .cloned()
+                .ok_or_else(|| FarmerError::Config(format!("Unknown address label @{label}"))),
+            None => Ok(address.to_string()),
+        }
+    }
+    //Replaces @label payout addresses with the address book entry, every entry has to decode
+    pub fn resolve_address_labels(&mut self) -> FarmerResult<()> {
+        for (label, address) in &self.addresses {
+            decode_puzzle_hash(address).map_err(|e| {
+                FarmerError::Config(format!("Invalid address for label {label}: {e}"))
+            })?;
+        }
+        self.payout_address = self.resolve_address(&self.payout_address)?;
+        for i in 0..self.farms.len() {
+            self.farms[i].payout_address = self.resolve_address(&self.farms[i].payout_address)?;
+        }
+        Ok(())
+    }
+    pub fn validate_pool_endpoints(&self) -> FarmerResult<()> {
+        for pool in self.all_farms().iter().flat_map(|f| f.pool_info.iter()) {
+            if let Some(endpoints) = &pool.endpoints {
+                validate_endpoints(&pool.pool_url, endpoints).map_err(|e| {
+                    FarmerError::Config(format!(
+                        "Invalid endpoints for launcher {}: {e}",
+                        pool.launcher_id
+                    ))
+                })?;
+            }
+        }
+        Ok(())
+    }
+    pub fn address_label(&self, address: &str) -> Option<&String> {
+        self.addresses
+            .iter()
+            .find(|(_, a)| a.as_str() == address)
+            .map(|(label, _)| label)
+    }
+    //Shows the label next to addresses from the address book
+    pub fn address_display(&self, address: &str) -> String {
+        match self.address_label(address) {
+            Some(label) => format!("@{label} ({address})"),
+            None => address.to_string(),
+        }
     }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
+            config_version: current_version(CONFIG_MIGRATIONS),
             selected_network: "mainnet".to_string(),
             ssl_root_path: None,
             fullnode_rpc_host: "localhost".to_string(),
@@ -91,25 +1300,387 @@ impl Default for Config {
             harvester_configs: HarvesterConfig {
                 bladebit: Some(BladebitHarvesterConfig {
                     plot_directories: vec![],
+                    io_options: HashMap::new(),
+                    scan_options: HashMap::new(),
+                    ram_cache_mib: 0,
+                    missing_plot_grace: default_missing_plot_grace(),
+                    dir_circuit: DirCircuitConfig::default(),
+                    object_storage: vec![],
+                    sp_deadline: default_sp_deadline(),
+                    audit: PlotAuditConfig::default(),
+                    io_recovery: PlotRecoveryConfig::default(),
+                    plot_filter_bits: HashMap::new(),
+                    lookup_concurrency: LookupConcurrencyConfig::default(),
+                    drive_workers: 0,
+                    sp_stagger_ms: 0,
+                    drive_health: DriveHealthConfig::default(),
+                    numa_nodes: HashMap::new(),
+                    decompression: DecompressionConfig::default(),
                 }),
             },
+            sp_history_depth: default_sp_history_depth(),
+            control_socket: None,
+            control_auth: default_control_auth(),
+            pool_info_cache_ttl: default_pool_info_cache_ttl(),
+            dns: None,
+            farms: vec![],
+            partial_queue: None,
+            partial_batch: None,
+            partial_window: default_sp_deadline(),
+            partial_cutoff_margin: default_partial_cutoff_margin(),
+            duplicate_partial_window: default_duplicate_partial_window(),
+            status_interval: default_status_interval(),
+            forensics_max_mib: default_forensics_max_mib(),
+            consensus_overrides: None,
+            fullnode_rpc_policy: RpcPolicyConfig::default(),
+            stats_report: None,
+            email_report: None,
+            max_proofs_per_sp: default_max_proofs_per_sp(),
+            sp_recovery: None,
+            max_signature_requests: default_max_signature_requests(),
+            runtime: RuntimeConfig::default(),
+            event_history_size: default_event_history_size(),
+            webhooks: vec![],
+            addresses: BTreeMap::new(),
+            sp_relay: vec![],
+            spare_rpc: None,
+            high_availability: None,
+            instance_lock: InstanceLockMode::default(),
+            idle_after: None,
+            dashboard: None,
+            public_status: None,
+            harvester_server: None,
+            time: TimeConfig::default(),
+            plugins: vec![],
+            mqtt: None,
+            earnings: None,
+            points_reconciliation: PointsReconciliationConfig::default(),
+            payout_mismatch: PayoutMismatchPolicy::default(),
+            key_health: KeyHealthConfig::default(),
+            latency_regression: LatencyRegressionConfig::default(),
+            client_metadata: false,
+            strict_config: false,
+            host_vars: None,
+            ssl_root_paths: HashMap::new(),
+            verify_node_ca: default_verify_node_ca(),
+            connection_profile: ConnectionProfile::default(),
+            connection: ConnectionConfig::default(),
+        }
+    }
+}
+//Number of sub-slots to keep signage points and proofs for
+fn default_verify_node_ca() -> bool {
+    true
+}
+fn default_sp_history_depth() -> u8 {
+    3
+}
+fn default_control_auth() -> bool {
+    true
+}
+//Seconds a cached pool_info response may be used while the pool API is unreachable
+fn default_pool_info_cache_ttl() -> u64 {
+    7 * 24 * 60 * 60
+}
+
+fn default_partial_cutoff_margin() -> u64 {
+    5
+}
+//Longer than the pool window, a proof older than that is not sent anyway
+fn default_duplicate_partial_window() -> u64 {
+    60
+}
+
+//Minutes between status summary log lines, 0 disables the summary
+fn default_status_interval() -> u64 {
+    5
+}
+
+//Size cap for dumps of partials rejected as invalid, 0 disables the dumps
+fn default_forensics_max_mib() -> u64 {
+    64
+}
+
+//Cap on DeclareProofOfSpace messages per signage point, 0 disables the cap
+fn default_max_proofs_per_sp() -> u32 {
+    20
+}
+
+//Signature requests in flight per harvester, the protocol has no batching so the rest are pipelined, 0 disables the cap
+fn default_max_signature_requests() -> usize {
+    8
+}
+
+//Events kept for the recent_events control call and the gui, 0 disables the history
+fn default_event_history_size() -> usize {
+    1000
+}
+
+//The overridden constants are built and leaked once, changing consensus_overrides needs a restart
+static OVERRIDDEN_CONSTANTS: OnceLock<&'static ConsensusConstants> = OnceLock::new();
+
+fn consensus_constants(
+    selected_network: &str,
+    overrides: &Option<ConsensusOverrides>,
+) -> &'static ConsensusConstants {
+    let constants: &'static ConsensusConstants = CONSENSUS_CONSTANTS_MAP
+        .get(selected_network)
+        .unwrap_or(&MAINNET);
+    let Some(overrides) = overrides else {
+        return constants;
+    };
+    OVERRIDDEN_CONSTANTS.get_or_init(|| override_constants(selected_network, constants, overrides))
+}
+
+fn override_constants(
+    selected_network: &str,
+    constants: &ConsensusConstants,
+    overrides: &ConsensusOverrides,
+) -> &'static ConsensusConstants {
+    warn!(
+        "Overriding consensus constants for {}: {:?}",
+        selected_network, overrides
+    );
+    let mut constants = constants.clone();
+    if let Some(v) = overrides.difficulty_constant_factor {
+        constants.difficulty_constant_factor = v;
+    }
+    if let Some(v) = overrides.number_zero_bits_plot_filter {
+        constants.number_zero_bits_plot_filter = v;
+    }
+    if let Some(v) = overrides.num_sps_sub_slot {
+        constants.num_sps_sub_slot = v;
+    }
+    if let Some(v) = overrides.pool_sub_slot_iters {
+        constants.pool_sub_slot_iters = v;
+    }
+    Box::leak(Box::new(constants))
+}
+
+//Unknown fields, usually typos like plot_directorys, would otherwise silently fall back to the
+//default of the field they were meant for. They are logged, or rejected with strict_config
+fn parse_config<T: DeserializeOwned>(path: &Path, value: Value) -> FarmerResult<T> {
+    let strict = value
+        .get(STRICT_CONFIG_KEY)
+        .and_then(Value::as_bool)
+        .unwrap_or_default();
+    let mut unknown = vec![];
+    let config = serde_ignored::deserialize(value, |field| unknown.push(field_path(&field)))
+        .map_err(|e| FarmerError::Config(format!("{:?}: {e}", path)))?;
+    if unknown.is_empty() {
+        return Ok(config);
+    }
+    if strict {
+        return Err(FarmerError::Config(format!(
+            "{:?}: unknown fields {}",
+            path,
+            unknown.join(", ")
+        )));
+    }
+    for field in unknown {
+        warn!(
+            "Ignoring unknown field {field} in {:?}, set {STRICT_CONFIG_KEY}: true to reject it",
+            path
+        );
+    }
+    Ok(config)
+}
+
+//Dotted path like the YAML nesting, serde_ignored would print optional sections as `?`
+fn field_path(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path as P;
+    match path {
+        P::Root => String::new(),
+        P::Seq { parent, index } => format!("{}[{index}]", field_path(parent)),
+        P::Map { parent, key } => match field_path(parent) {
+            parent if parent.is_empty() => key.clone(),
+            parent => format!("{parent}.{key}"),
+        },
+        P::Some { parent } | P::NewtypeStruct { parent } | P::NewtypeVariant { parent } => {
+            field_path(parent)
         }
     }
 }
+
 impl TryFrom<&Path> for Config {
-    type Error = Error;
+    type Error = FarmerError;
     fn try_from(value: &Path) -> Result<Self, Self::Error> {
-        serde_yaml::from_str::<Config>(&fs::read_to_string(value)?)
-            .map_err(|e| Error::new(ErrorKind::Other, format!("{:?}", e)))
+        Self::load_for_host(value, host_name().as_deref()).map(|(config, _)| config)
     }
 }
 impl TryFrom<&PathBuf> for Config {
-    type Error = Error;
+    type Error = FarmerError;
     fn try_from(value: &PathBuf) -> Result<Self, Self::Error> {
         Self::try_from(value.as_path())
     }
 }
 
+//Config of the standalone dg_fast_harvester, keys are received from the farmer on connect
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct HarvesterNodeConfig {
+    #[serde(default)]
+    pub config_version: u32,
+    pub selected_network: String,
+    pub ssl_root_path: Option<String>,
+    pub farmer_host: String,
+    #[serde(default = "default_farmer_port")]
+    pub farmer_port: u16,
+    pub harvester_configs: HarvesterConfig,
+    #[serde(default)]
+    pub consensus_overrides: Option<ConsensusOverrides>,
+    #[serde(default)]
+    pub dns: Option<DnsConfig>,
+    #[serde(default)]
+    pub link: LinkConfig,
+    #[serde(default)]
+    pub runtime: RuntimeConfig,
+    //Announce this harvester over mDNS so `init --discover` on other machines finds its farmer
+    #[serde(default)]
+    pub advertise: bool,
+    #[serde(default)]
+    pub strict_config: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_vars: Option<String>,
+}
+impl HarvesterNodeConfig {
+    pub fn save_as_yaml<P: AsRef<Path>>(&self, path: P) -> FarmerResult<()> {
+        Ok(fs::write(
+            path.as_ref(),
+            serde_yaml::to_string(&self).map_err(|e| FarmerError::Config(e.to_string()))?,
+        )?)
+    }
+    pub fn consensus_constants(&self) -> &'static ConsensusConstants {
+        consensus_constants(&self.selected_network, &self.consensus_overrides)
+    }
+}
+impl Default for HarvesterNodeConfig {
+    fn default() -> Self {
+        HarvesterNodeConfig {
+            config_version: current_version(HARVESTER_CONFIG_MIGRATIONS),
+            selected_network: "mainnet".to_string(),
+            ssl_root_path: None,
+            farmer_host: "localhost".to_string(),
+            farmer_port: default_farmer_port(),
+            harvester_configs: HarvesterConfig {
+                bladebit: Some(BladebitHarvesterConfig {
+                    plot_directories: vec![],
+                    io_options: HashMap::new(),
+                    scan_options: HashMap::new(),
+                    ram_cache_mib: 0,
+                    missing_plot_grace: default_missing_plot_grace(),
+                    dir_circuit: DirCircuitConfig::default(),
+                    object_storage: vec![],
+                    sp_deadline: default_sp_deadline(),
+                    audit: PlotAuditConfig::default(),
+                    io_recovery: PlotRecoveryConfig::default(),
+                    plot_filter_bits: HashMap::new(),
+                    lookup_concurrency: LookupConcurrencyConfig::default(),
+                    drive_workers: 0,
+                    sp_stagger_ms: 0,
+                    drive_health: DriveHealthConfig::default(),
+                    numa_nodes: HashMap::new(),
+                    decompression: DecompressionConfig::default(),
+                }),
+            },
+            consensus_overrides: None,
+            dns: None,
+            link: LinkConfig::default(),
+            runtime: RuntimeConfig::default(),
+            advertise: false,
+            strict_config: false,
+            host_vars: None,
+        }
+    }
+}
+impl TryFrom<&Path> for HarvesterNodeConfig {
+    type Error = FarmerError;
+    fn try_from(value: &Path) -> Result<Self, Self::Error> {
+        let mut config = load_migrated(value, HARVESTER_CONFIG_MIGRATIONS)?;
+        apply_host_vars(value, &mut config, host_name().as_deref())?;
+        parse_config(value, config)
+    }
+}
+fn default_farmer_port() -> u16 {
+    8447
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LinkConfig {
+    //Seconds without any message from the farmer before the connection is dropped and redialed, 0 disables
+    #[serde(default = "default_link_idle_timeout", with = "units::secs")]
+    pub idle_timeout: u64,
+    #[serde(default)]
+    pub transport: LinkTransport,
+    //zstd compressed batches when the farmer is ff, chia farmers get uncompressed messages
+    #[serde(default)]
+    pub compression: bool,
+}
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout: default_link_idle_timeout(),
+            transport: LinkTransport::default(),
+            compression: false,
+        }
+    }
+}
+fn default_link_idle_timeout() -> u64 {
+    120
+}
+
+//QUIC is only understood by ff with harvester_server.quic set and needs the quic feature
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkTransport {
+    #[default]
+    Websocket,
+    Quic,
+}
+
+//Blocking threads used in low power mode unless max_blocking_threads is set
+const LOW_POWER_BLOCKING_THREADS: usize = 8;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RuntimeConfig {
+    //Defaults to the number of cores, ignored in low power mode
+    #[serde(default)]
+    pub worker_threads: Option<usize>,
+    //Plot reads run on the blocking pool, tokio defaults to 512 threads
+    #[serde(default)]
+    pub max_blocking_threads: Option<usize>,
+    //Runs all tasks on a single thread for devices like a Raspberry Pi
+    #[serde(default)]
+    pub low_power: bool,
+}
+impl RuntimeConfig {
+    pub fn build(&self) -> Result<Runtime, Error> {
+        let mut builder = if self.low_power {
+            let mut builder = Builder::new_current_thread();
+            builder.max_blocking_threads(LOW_POWER_BLOCKING_THREADS);
+            builder
+        } else {
+            let mut builder = Builder::new_multi_thread();
+            if let Some(worker_threads) = self.worker_threads {
+                builder.worker_threads(worker_threads.max(1));
+            }
+            builder
+        };
+        if let Some(max_blocking_threads) = self.max_blocking_threads {
+            builder.max_blocking_threads(max_blocking_threads.max(1));
+        }
+        builder.enable_all().build()
+    }
+}
+
+//Used for proofs that can not be matched to a farm
+pub fn default_payout_address(config: &Config) -> String {
+    config
+        .all_farms()
+        .first()
+        .map(|f| f.payout_address.clone())
+        .unwrap_or_else(|| config.payout_address.clone())
+}
+
 pub async fn load_keys(
     config: Arc<Config>,
 ) -> (
@@ -122,7 +1693,10 @@ pub async fn load_keys(
     let mut owner_secret_keys = HashMap::default();
     let mut auth_secret_keys = HashMap::default();
     let mut pool_secret_keys = HashMap::default();
-    for farmer_info in config.farmer_info.iter() {
+    for farmer_info in config.all_farms().iter().flat_map(|f| f.farmer_info.iter()) {
+        if farmer_info.is_watch_only() {
+            continue;
+        }
         let f_sk: SecretKey = farmer_info.farmer_secret_key.into();
         farmer_secret_keys.insert(f_sk.sk_to_pk().to_bytes().into(), f_sk.clone());
         if let Some(pk) = farmer_info.pool_secret_key {
@@ -145,3 +1719,82 @@ pub async fn load_keys(
         pool_secret_keys,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use dg_xch_keys::encode_puzzle_hash;
+
+    #[test]
+    fn resolves_payout_labels() {
+        let address = encode_puzzle_hash(&Bytes32::default(), "xch").unwrap();
+        let mut config = Config {
+            payout_address: "@cold_wallet".to_string(),
+            addresses: BTreeMap::from([("cold_wallet".to_string(), address.clone())]),
+            ..Default::default()
+        };
+        config.resolve_address_labels().unwrap();
+        assert_eq!(config.payout_address, address);
+        assert_eq!(
+            config.address_display(&address),
+            format!("@cold_wallet ({address})")
+        );
+        config.payout_address = "@hot_wallet".to_string();
+        assert!(matches!(
+            config.resolve_address_labels(),
+            Err(FarmerError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn ssl_root_path_prefers_node_then_network() {
+        let config = Config {
+            ssl_root_path: Some("/ssl".to_string()),
+            ssl_root_paths: HashMap::from([
+                ("testnet11".to_string(), "/testnet".to_string()),
+                ("mainnet".to_string(), "/mainnet".to_string()),
+                ("fork-node:9444".to_string(), "/fork".to_string()),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.node_ssl_root_path("fork-node", 9444).as_deref(),
+            Some("/fork")
+        );
+        assert_eq!(
+            config.node_ssl_root_path("fork-node", 8444).as_deref(),
+            Some("/mainnet")
+        );
+        let config = Config {
+            ssl_root_paths: HashMap::new(),
+            ..config
+        };
+        assert_eq!(
+            config.node_ssl_root_path("localhost", 8444).as_deref(),
+            Some("/ssl")
+        );
+    }
+
+    #[test]
+    fn unknown_fields_fail_only_in_strict_mode() {
+        let mut value = serde_yaml::to_value(HarvesterNodeConfig::default()).unwrap();
+        value["harvester_configs"]["bladebit"]
+            .as_mapping_mut()
+            .unwrap()
+            .insert("plot_directorys".into(), vec!["/plots"].into());
+        value["harvester_configs"]["bladebit"]["missing_plot_grace"] = "5m".into();
+        let path = Path::new("harvester.yaml");
+        let config: HarvesterNodeConfig = parse_config(path, value.clone()).unwrap();
+        let bladebit = config.harvester_configs.bladebit.unwrap();
+        assert!(bladebit.plot_directories.is_empty());
+        assert_eq!(bladebit.missing_plot_grace, 300);
+        value[STRICT_CONFIG_KEY] = true.into();
+        match parse_config::<HarvesterNodeConfig>(path, value) {
+            Err(FarmerError::Config(e)) => {
+                assert!(e.contains("harvester_configs.bladebit.plot_directorys"))
+            }
+            other => panic!("expected unknown field error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
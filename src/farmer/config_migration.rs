@@ -0,0 +1,185 @@
+use crate::error::{FarmerError, FarmerResult};
+use crate::platform::create_private;
+use log::{info, warn};
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_VERSION_KEY: &str = "config_version";
+
+//Upgrades a config from the version at its index to the next one
+pub struct Migration {
+    pub description: &'static str,
+    pub apply: fn(&mut Mapping) -> Result<(), String>,
+}
+
+//Configs written before config_version existed are version 0, every layout they used still parses
+pub const CONFIG_MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "add config_version",
+        apply: |_| Ok(()),
+    },
+    Migration {
+        description: "drop the cuda and opencl decompression providers",
+        apply: drop_gpu_decompression,
+    },
+];
+pub const HARVESTER_CONFIG_MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "add config_version",
+        apply: |_| Ok(()),
+    },
+    Migration {
+        description: "drop the cuda and opencl decompression providers",
+        apply: drop_gpu_decompression,
+    },
+];
+
+//No decompressor for them ever existed, the CPU is used when no provider is left
+fn drop_gpu_decompression(config: &mut Mapping) -> Result<(), String> {
+    let Some(decompression) = ["harvester_configs", "bladebit", "decompression"]
+        .iter()
+        .try_fold(config, |value, key| {
+            value.get_mut(*key).and_then(Value::as_mapping_mut)
+        })
+    else {
+        return Ok(());
+    };
+    let Some(providers) = decompression
+        .get_mut("providers")
+        .and_then(Value::as_sequence_mut)
+    else {
+        return Ok(());
+    };
+    providers.retain(|p| !matches!(p.as_str(), Some("cuda" | "opencl")));
+    if providers.is_empty() {
+        decompression.remove("providers");
+    }
+    Ok(())
+}
+
+pub const fn current_version(migrations: &[Migration]) -> u32 {
+    migrations.len() as u32
+}
+
+fn config_version(config: &Mapping) -> Result<u32, String> {
+    match config.get(CONFIG_VERSION_KEY) {
+        None => Ok(0),
+        Some(version) => version
+            .as_u64()
+            .map(|v| v as u32)
+            .ok_or_else(|| format!("{CONFIG_VERSION_KEY} must be a number")),
+    }
+}
+
+//Returns whether a migration changed the config, only then it is stamped with the current version
+//so configs that need nothing are not rewritten and keep their comments
+pub fn migrate(config: &mut Mapping, migrations: &[Migration]) -> Result<bool, String> {
+    let version = config_version(config)?;
+    let current = current_version(migrations);
+    if version > current {
+        warn!("Config version {version} is newer than this build understands ({current}), unknown fields are ignored");
+        return Ok(false);
+    }
+    if version == current {
+        return Ok(false);
+    }
+    let before = config.clone();
+    for (from, migration) in migrations.iter().enumerate().skip(version as usize) {
+        info!(
+            "Migrating config from version {from} to {}: {}",
+            from + 1,
+            migration.description
+        );
+        (migration.apply)(config)
+            .map_err(|e| format!("migration to version {} failed: {e}", from + 1))?;
+    }
+    if *config == before {
+        return Ok(false);
+    }
+    config.insert(CONFIG_VERSION_KEY.into(), current.into());
+    Ok(true)
+}
+
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{version}.bak"));
+    path.with_file_name(name)
+}
+
+//Reads the config file and upgrades it in place, the previous file is kept next to it as a backup
+pub fn load_migrated(path: &Path, migrations: &[Migration]) -> FarmerResult<Value> {
+    let contents = fs::read_to_string(path)?;
+    let mut value: Value = serde_yaml::from_str(&contents)
+        .map_err(|e| FarmerError::Config(format!("{:?}: {e}", path)))?;
+    let Some(config) = value.as_mapping_mut() else {
+        return Ok(value);
+    };
+    let version =
+        config_version(config).map_err(|e| FarmerError::Config(format!("{:?}: {e}", path)))?;
+    if migrate(config, migrations).map_err(|e| FarmerError::Config(format!("{:?}: {e}", path)))? {
+        let backup = backup_path(path, version);
+        //The config holds the secret keys
+        create_private(&backup)?.write_all(contents.as_bytes())?;
+        fs::write(
+            path,
+            serde_yaml::to_string(&value).map_err(|e| FarmerError::Config(e.to_string()))?,
+        )?;
+        info!(
+            "Upgraded config {:?}, the previous version is saved as {:?}",
+            path, backup
+        );
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIGRATIONS: &[Migration] = &[
+        Migration {
+            description: "add config_version",
+            apply: |_| Ok(()),
+        },
+        Migration {
+            description: "rename old_field",
+            apply: |config| {
+                if let Some(value) = config.remove("old_field") {
+                    config.insert("new_field".into(), value);
+                }
+                Ok(())
+            },
+        },
+    ];
+
+    #[test]
+    fn runs_pending_migrations_only() {
+        let mut config: Mapping = serde_yaml::from_str("old_field: 1").unwrap();
+        assert!(migrate(&mut config, MIGRATIONS).unwrap());
+        assert_eq!(config.get("new_field"), Some(&Value::from(1)));
+        assert_eq!(config.get(CONFIG_VERSION_KEY), Some(&Value::from(2)));
+        assert!(!migrate(&mut config, MIGRATIONS).unwrap());
+        let mut config: Mapping = serde_yaml::from_str("config_version: 2\nold_field: 1").unwrap();
+        assert!(!migrate(&mut config, MIGRATIONS).unwrap());
+        assert!(config.contains_key("old_field"));
+        //Migrations that change nothing leave the config as it is
+        let mut config: Mapping = serde_yaml::from_str("old_field: 1").unwrap();
+        assert!(!migrate(&mut config, &MIGRATIONS[..1]).unwrap());
+        assert!(!config.contains_key(CONFIG_VERSION_KEY));
+        let mut config: Mapping = serde_yaml::from_str(
+            "harvester_configs:\n  bladebit:\n    decompression:\n      providers: [cuda, opencl, cpu]",
+        )
+        .unwrap();
+        assert!(migrate(&mut config, CONFIG_MIGRATIONS).unwrap());
+        assert_eq!(
+            config["harvester_configs"]["bladebit"]["decompression"]["providers"],
+            Value::from(vec!["cpu"])
+        );
+        assert_eq!(
+            backup_path(Path::new("/tmp/farmer.yaml"), 0),
+            PathBuf::from("/tmp/farmer.yaml.v0.bak")
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use crate::farmer::config::{Config, ConnectionProfile};
+use std::time::Duration;
+
+//Settings of the full node websocket, the connection_profile defaults with the connection
+//overrides applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionSettings {
+    pub ping_interval: Option<Duration>,
+    //None follows the sub slot time of the network
+    pub sp_timeout: Option<Duration>,
+    pub reconnect_delay: Duration,
+    pub max_reconnect_delay: Duration,
+}
+impl ConnectionSettings {
+    pub fn new(config: &Config) -> Self {
+        let (ping_interval, sp_timeout, reconnect_delay, max_reconnect_delay) =
+            match config.connection_profile {
+                ConnectionProfile::Local => (0, None, 3000, 3000),
+                //NAT mappings of home routers expire after a minute or so of silence, and a
+                //signage point is due about every 9 seconds, so a quiet link is replaced early
+                ConnectionProfile::Remote => (15, Some(45), 500, 10_000),
+            };
+        let overrides = &config.connection;
+        let reconnect_delay =
+            Duration::from_millis(overrides.reconnect_delay.unwrap_or(reconnect_delay));
+        Self {
+            ping_interval: Some(overrides.ping_interval.unwrap_or(ping_interval))
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            sp_timeout: overrides.sp_timeout.or(sp_timeout).map(Duration::from_secs),
+            reconnect_delay,
+            max_reconnect_delay: Duration::from_millis(
+                overrides.max_reconnect_delay.unwrap_or(max_reconnect_delay),
+            )
+            .max(reconnect_delay),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+    pub relay: bool,
+}
+
+//Endpoint and delay of each connect attempt. After after_failures failed connects to the node the
+//relay and the node take turns, a working connection starts over at the node
+#[derive(Debug)]
+pub struct Reconnector {
+    node: Endpoint,
+    relay: Option<(Endpoint, u32)>,
+    reconnect_delay: Duration,
+    max_reconnect_delay: Duration,
+    failures: u32,
+    next_delay: Duration,
+}
+impl Reconnector {
+    pub fn new(config: &Config, settings: &ConnectionSettings) -> Self {
+        Self {
+            node: Endpoint {
+                host: config.fullnode_ws_host.clone(),
+                port: config.fullnode_ws_port,
+                relay: false,
+            },
+            relay: config.connection.relay.as_ref().map(|r| {
+                (
+                    Endpoint {
+                        host: r.host.clone(),
+                        port: r.port,
+                        relay: true,
+                    },
+                    r.after_failures,
+                )
+            }),
+            reconnect_delay: settings.reconnect_delay,
+            max_reconnect_delay: settings.max_reconnect_delay,
+            failures: 0,
+            next_delay: settings.reconnect_delay,
+        }
+    }
+    pub fn endpoint(&self) -> &Endpoint {
+        match &self.relay {
+            Some((relay, after))
+                if self.failures >= *after && (self.failures - after).is_multiple_of(2) =>
+            {
+                relay
+            }
+            _ => &self.node,
+        }
+    }
+    //Wait before the next attempt
+    pub fn failed(&mut self) -> Duration {
+        self.failures += 1;
+        let delay = self.next_delay;
+        self.next_delay = (self.next_delay * 2).min(self.max_reconnect_delay);
+        delay
+    }
+    pub fn connected(&mut self) {
+        self.failures = 0;
+        self.next_delay = self.reconnect_delay;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::config::{ConnectionConfig, RelayEndpointConfig};
+
+    #[test]
+    fn remote_profile_backs_off_and_falls_back_to_the_relay() {
+        let local = ConnectionSettings::new(&Config::default());
+        assert_eq!(local.ping_interval, None);
+        assert_eq!(local.sp_timeout, None);
+        assert_eq!(local.reconnect_delay, Duration::from_secs(3));
+
+        let config = Config {
+            fullnode_ws_host: "vps.example".to_string(),
+            connection_profile: ConnectionProfile::Remote,
+            connection: ConnectionConfig {
+                max_reconnect_delay: Some(1500),
+                relay: Some(RelayEndpointConfig {
+                    host: "relay.example".to_string(),
+                    port: 18444,
+                    after_failures: 2,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let settings = ConnectionSettings::new(&config);
+        assert_eq!(settings.ping_interval, Some(Duration::from_secs(15)));
+        assert_eq!(settings.sp_timeout, Some(Duration::from_secs(45)));
+        let mut reconnector = Reconnector::new(&config, &settings);
+        let mut attempts = vec![];
+        for _ in 0..5 {
+            let endpoint = reconnector.endpoint().host.clone();
+            attempts.push((endpoint, reconnector.failed().as_millis()));
+        }
+        assert_eq!(
+            attempts,
+            [
+                ("vps.example".to_string(), 500),
+                ("vps.example".to_string(), 1000),
+                ("relay.example".to_string(), 1500),
+                ("vps.example".to_string(), 1500),
+                ("relay.example".to_string(), 1500),
+            ]
+        );
+        reconnector.connected();
+        assert!(!reconnector.endpoint().relay);
+        assert_eq!(reconnector.failed(), Duration::from_millis(500));
+    }
+}
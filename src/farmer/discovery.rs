@@ -0,0 +1,192 @@
+use crate::farmer::config::HarvesterNodeConfig;
+use crate::farmer::host_vars;
+use futures_util::future::join_all;
+use log::{debug, info};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::collections::HashMap;
+use std::io::Error;
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Instant};
+
+pub const HARVESTER_SERVICE: &str = "_dg-fast-harvester._tcp.local.";
+pub const DISCOVERY_TIME: Duration = Duration::from_secs(3);
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredHarvester {
+    pub name: String,
+    pub addresses: Vec<IpAddr>,
+    pub farmer_host: Option<String>,
+    pub farmer_port: Option<u16>,
+    pub network: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Discovery {
+    pub harvesters: Vec<DiscoveredHarvester>,
+    //Hosts accepting connections on the full node port, localhost first
+    pub full_nodes: Vec<String>,
+}
+
+//Announces the harvester until the returned daemon is dropped. Harvesters only dial out to
+//their farmer, the record carries the farmer they use so new machines can be pointed at it
+pub fn advertise_harvester(config: &HarvesterNodeConfig) -> Result<ServiceDaemon, Error> {
+    let daemon = ServiceDaemon::new().map_err(Error::other)?;
+    let name = host_name();
+    let properties = HashMap::from([
+        ("farmer_host".to_string(), config.farmer_host.clone()),
+        ("farmer_port".to_string(), config.farmer_port.to_string()),
+        ("network".to_string(), config.selected_network.clone()),
+    ]);
+    let service = ServiceInfo::new(
+        HARVESTER_SERVICE,
+        &name,
+        &format!("{name}.local."),
+        "",
+        config.farmer_port,
+        properties,
+    )
+    .map_err(Error::other)?
+    .enable_addr_auto();
+    daemon.register(service).map_err(Error::other)?;
+    info!("Advertising harvester {name} on {HARVESTER_SERVICE}");
+    Ok(daemon)
+}
+
+//Browses for advertising harvesters and probes the local /24 for the full node port, Chia nodes
+//do not announce themselves so a reachable port is all there is to go on
+pub async fn discover(full_node_port: u16, wait: Duration) -> Discovery {
+    let (harvesters, full_nodes) =
+        tokio::join!(discover_harvesters(wait), probe_full_nodes(full_node_port));
+    Discovery {
+        harvesters,
+        full_nodes,
+    }
+}
+
+pub async fn discover_harvesters(wait: Duration) -> Vec<DiscoveredHarvester> {
+    browse_harvesters(wait).await.unwrap_or_else(|e| {
+        debug!("mDNS browse failed: {e}");
+        vec![]
+    })
+}
+
+async fn browse_harvesters(wait: Duration) -> Result<Vec<DiscoveredHarvester>, Error> {
+    let daemon = ServiceDaemon::new().map_err(Error::other)?;
+    let events = daemon.browse(HARVESTER_SERVICE).map_err(Error::other)?;
+    let deadline = Instant::now() + wait;
+    let mut found: Vec<DiscoveredHarvester> = vec![];
+    while let Ok(Ok(event)) = timeout(
+        deadline.saturating_duration_since(Instant::now()),
+        events.recv_async(),
+    )
+    .await
+    {
+        if let ServiceEvent::ServiceResolved(info) = event {
+            let harvester = parse_harvester(&info);
+            if !found.iter().any(|h| h.name == harvester.name) {
+                found.push(harvester);
+            }
+        }
+    }
+    let _ = daemon.shutdown();
+    Ok(found)
+}
+
+fn parse_harvester(info: &ServiceInfo) -> DiscoveredHarvester {
+    let mut addresses: Vec<IpAddr> = info.get_addresses().iter().copied().collect();
+    addresses.sort();
+    DiscoveredHarvester {
+        name: info
+            .get_fullname()
+            .trim_end_matches(HARVESTER_SERVICE)
+            .trim_end_matches('.')
+            .to_string(),
+        addresses,
+        farmer_host: info.get_property_val_str("farmer_host").map(String::from),
+        farmer_port: info
+            .get_property_val_str("farmer_port")
+            .and_then(|p| p.parse().ok()),
+        network: info.get_property_val_str("network").map(String::from),
+    }
+}
+
+async fn probe_full_nodes(port: u16) -> Vec<String> {
+    let mut candidates = vec!["localhost".to_string()];
+    if let Some(local) = local_ipv4() {
+        candidates.extend(subnet_hosts(local).into_iter().map(|ip| ip.to_string()));
+    }
+    let open = join_all(candidates.iter().map(|host| async move {
+        matches!(
+            timeout(PROBE_TIMEOUT, TcpStream::connect((host.as_str(), port))).await,
+            Ok(Ok(_))
+        )
+    }))
+    .await;
+    candidates
+        .into_iter()
+        .zip(open)
+        .filter_map(|(host, open)| open.then_some(host))
+        .collect()
+}
+
+//Address of the interface used for outbound traffic, connecting UDP sends nothing
+fn local_ipv4() -> Option<Ipv4Addr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:9").ok()?;
+    match socket.local_addr().ok()?.ip() {
+        IpAddr::V4(ip) if ip.is_private() => Some(ip),
+        _ => None,
+    }
+}
+
+//Other hosts of the /24 around a private address, the address itself is covered by localhost
+fn subnet_hosts(local: Ipv4Addr) -> Vec<Ipv4Addr> {
+    let [a, b, c, own] = local.octets();
+    (1..=254u8)
+        .filter(|d| *d != own)
+        .map(|d| Ipv4Addr::new(a, b, c, d))
+        .collect()
+}
+
+fn host_name() -> String {
+    host_vars::host_name().unwrap_or_else(|| "dg-fast-harvester".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subnet_skips_own_address_and_reads_txt_records() {
+        let hosts = subnet_hosts(Ipv4Addr::new(192, 168, 1, 20));
+        assert_eq!(hosts.len(), 253);
+        assert_eq!(hosts[0], Ipv4Addr::new(192, 168, 1, 1));
+        assert!(!hosts.contains(&Ipv4Addr::new(192, 168, 1, 20)));
+        let info = ServiceInfo::new(
+            HARVESTER_SERVICE,
+            "shed",
+            "shed.local.",
+            "192.168.1.30",
+            8447,
+            HashMap::from([
+                ("farmer_host".to_string(), "192.168.1.10".to_string()),
+                ("farmer_port".to_string(), "8447".to_string()),
+                ("network".to_string(), "mainnet".to_string()),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            parse_harvester(&info),
+            DiscoveredHarvester {
+                name: "shed".to_string(),
+                addresses: vec!["192.168.1.30".parse().unwrap()],
+                farmer_host: Some("192.168.1.10".to_string()),
+                farmer_port: Some(8447),
+                network: Some("mainnet".to_string()),
+            }
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use crate::farmer::config::{Config, DnsConfig, IpPreference};
+use crate::harvesters::object_storage::URI_ENCODE;
+use dg_xch_clients::protocols::shared::{load_certs, load_private_key, NoCertificateVerification};
+use dg_xch_clients::websocket::{Client, ClientSSLConfig, ReadStream};
+use futures_util::stream::FuturesUnordered;
+use futures_util::StreamExt;
+use hyper::client::connect::dns::Name;
+use log::{debug, warn};
+use percent_encoding::utf8_percent_encode;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use rustls::ClientConfig;
+use std::io::{Error, ErrorKind};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{lookup_host, TcpStream, UdpSocket};
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::{client_async_tls_with_config, Connector, MaybeTlsStream, WebSocketStream};
+use uuid::Uuid;
+
+const DNS_PORT: u16 = 53;
+const DNS_TIMEOUT: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const RECORD_TYPE_A: u16 = 1;
+const RECORD_TYPE_AAAA: u16 = 28;
+const RECORD_CLASS_IN: u16 = 1;
+
+#[derive(Debug, Clone, Default)]
+pub struct DnsResolver {
+    config: DnsConfig,
+}
+impl DnsResolver {
+    pub fn new(config: DnsConfig) -> Self {
+        Self { config }
+    }
+    pub async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, Error> {
+        if let Ok(ip) = host
+            .trim_matches(|c| c == '[' || c == ']')
+            .parse::<IpAddr>()
+        {
+            return Ok(vec![ip]);
+        }
+        if let Some(ip) = self.config.static_hosts.get(host) {
+            debug!("Using pinned address {ip} for {host}");
+            return Ok(vec![*ip]);
+        }
+        let mut addrs = if let Some(nameserver) = &self.config.nameserver {
+            let nameserver = parse_nameserver(nameserver)?;
+            let mut addrs = vec![];
+            let mut last_err = None;
+            for record_type in [RECORD_TYPE_A, RECORD_TYPE_AAAA] {
+                match query(nameserver, host, record_type).await {
+                    Ok(found) => addrs.extend(found),
+                    Err(e) => last_err = Some(e),
+                }
+            }
+            if let (true, Some(e)) = (addrs.is_empty(), last_err) {
+                return Err(e);
+            }
+            addrs
+        } else {
+            lookup_host((host, 0))
+                .await?
+                .map(|a| a.ip())
+                .collect::<Vec<IpAddr>>()
+        };
+        match self.config.ip_preference {
+            IpPreference::System => {}
+            IpPreference::Ipv4 => addrs.sort_by_key(|a| !a.is_ipv4()),
+            IpPreference::Ipv6 => addrs.sort_by_key(|a| !a.is_ipv6()),
+            IpPreference::Ipv4Only => addrs.retain(|a| a.is_ipv4()),
+            IpPreference::Ipv6Only => addrs.retain(|a| a.is_ipv6()),
+        }
+        if addrs.is_empty() {
+            Err(Error::new(
+                ErrorKind::NotFound,
+                format!("No addresses found for {host}"),
+            ))
+        } else {
+            debug!("Resolved {host} to {:?}", addrs);
+            Ok(addrs)
+        }
+    }
+}
+impl Resolve for DnsResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let addrs = resolver.lookup(name.as_str()).await?;
+            Ok(Box::new(addrs.into_iter().map(|ip| SocketAddr::new(ip, 0))) as Addrs)
+        })
+    }
+}
+
+//The RPC client of dg_xch_clients builds its own reqwest client without a resolver hook, so with a
+//dns config the addresses are raced here and the one that connected goes into its URL. Without one
+//the configured host is kept, the reqwest connector already races the system resolved addresses
+pub async fn rpc_host(config: &Config, host: &str, port: u16) -> String {
+    if config.dns.is_none() {
+        return host.to_string();
+    }
+    match connect_tcp(&config.dns, host, port)
+        .await
+        .and_then(|stream| stream.peer_addr())
+    {
+        Ok(addr) => url_host(&addr.ip().to_string()),
+        Err(e) => {
+            warn!("Failed to connect to {host}:{port}, falling back to system resolution: {e:?}");
+            host.to_string()
+        }
+    }
+}
+
+//Resolves host through the dns config, or the system resolver without one, and races connections
+//to all its addresses. Callers keep the configured host for TLS and the Host header
+pub async fn connect_tcp(
+    dns: &Option<DnsConfig>,
+    host: &str,
+    port: u16,
+) -> Result<TcpStream, Error> {
+    let dns = dns.clone().unwrap_or_default();
+    let delay = Duration::from_millis(dns.happy_eyeballs_delay);
+    let addrs = DnsResolver::new(dns).lookup(host).await?;
+    happy_eyeballs(&addrs, port, delay).await
+}
+
+//Client certificate of the node, the farmer certificate is not checked like in dg_xch_clients
+pub fn client_tls_config(ssl_info: &ClientSSLConfig<'_>) -> Result<ClientConfig, Error> {
+    ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(NoCertificateVerification))
+        .with_client_auth_cert(
+            load_certs(ssl_info.ssl_crt_path)?,
+            load_private_key(ssl_info.ssl_key_path)?,
+        )
+        .map_err(|e| Error::other(format!("Error building client: {e:?}")))
+}
+
+//The websocket get_client_tls of dg_xch_clients opens, over a connection from connect_tcp
+pub async fn connect_websocket(
+    dns: &Option<DnsConfig>,
+    host: &str,
+    port: u16,
+    ssl_info: ClientSSLConfig<'_>,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, Error> {
+    let tls_config = client_tls_config(&ssl_info)?;
+    let mut request = format!("wss://{}:{port}/ws", url_host(host))
+        .into_client_request()
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("Invalid request: {e}")))?;
+    let cert = tokio::fs::read_to_string(ssl_info.ssl_crt_path)
+        .await
+        .unwrap_or_default();
+    request.headers_mut().insert(
+        "chia-client-cert",
+        HeaderValue::from_str(&utf8_percent_encode(&cert, URI_ENCODE).to_string())
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("Invalid certificate: {e}")))?,
+    );
+    let stream = connect_tcp(dns, host, port).await?;
+    let (stream, _) = client_async_tls_with_config(
+        request,
+        stream,
+        None,
+        Some(Connector::Rustls(Arc::new(tls_config))),
+    )
+    .await
+    .map_err(|e| Error::other(format!("Error connecting to {host}:{port}: {e:?}")))?;
+    Ok(stream)
+}
+
+//get_client_tls of dg_xch_clients over a connection from connect_tcp
+pub async fn get_client_tls(
+    dns: &Option<DnsConfig>,
+    host: &str,
+    port: u16,
+    ssl_info: ClientSSLConfig<'_>,
+) -> Result<(Client, ReadStream), Error> {
+    Ok(Client::new(
+        connect_websocket(dns, host, port, ssl_info).await?,
+    ))
+}
+
+//IPv6 literals need brackets in the URLs the clients build from host and port
+fn url_host(host: &str) -> String {
+    match host.parse::<Ipv6Addr>() {
+        Ok(ip) => format!("[{ip}]"),
+        Err(_) => host.to_string(),
+    }
+}
+
+//RFC 8305 style connection racing, the next address is tried after delay or as soon as an attempt
+//fails. The first connection is kept, the attempts still running are dropped
+pub async fn happy_eyeballs(
+    addrs: &[IpAddr],
+    port: u16,
+    delay: Duration,
+) -> Result<TcpStream, Error> {
+    let mut pending = interleave_families(addrs).into_iter().peekable();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_err = None;
+    while pending.peek().is_some() || !attempts.is_empty() {
+        if let Some(ip) = pending.next() {
+            attempts.push(connect_attempt(ip, port));
+        }
+        let finished = if pending.peek().is_some() && !delay.is_zero() {
+            match timeout(delay, attempts.next()).await {
+                Ok(finished) => finished,
+                Err(_) => continue,
+            }
+        } else {
+            attempts.next().await
+        };
+        match finished {
+            Some((ip, Ok(stream))) => {
+                debug!("Connected to {ip}:{port}");
+                return Ok(stream);
+            }
+            Some((ip, Err(e))) => {
+                debug!("Failed to connect to {ip}:{port}: {e:?}");
+                last_err = Some(e);
+            }
+            None => {}
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            "No addresses to connect to".to_string(),
+        )
+    }))
+}
+
+async fn connect_attempt(ip: IpAddr, port: u16) -> (IpAddr, Result<TcpStream, Error>) {
+    let result = match timeout(
+        CONNECT_TIMEOUT,
+        TcpStream::connect(SocketAddr::new(ip, port)),
+    )
+    .await
+    {
+        Ok(result) => result,
+        Err(_) => Err(Error::new(
+            ErrorKind::TimedOut,
+            format!("Timed out connecting to {ip}:{port}"),
+        )),
+    };
+    (ip, result)
+}
+
+//Alternates address families starting with the preferred first address
+fn interleave_families(addrs: &[IpAddr]) -> Vec<IpAddr> {
+    let Some(first) = addrs.first() else {
+        return vec![];
+    };
+    let (mut preferred, mut other): (Vec<IpAddr>, Vec<IpAddr>) =
+        addrs.iter().partition(|a| a.is_ipv4() == first.is_ipv4());
+    preferred.reverse();
+    other.reverse();
+    let mut ordered = Vec::with_capacity(addrs.len());
+    while let Some(ip) = preferred.pop() {
+        ordered.push(ip);
+        if let Some(ip) = other.pop() {
+            ordered.push(ip);
+        }
+    }
+    ordered.extend(other.into_iter().rev());
+    ordered
+}
+
+fn parse_nameserver(nameserver: &str) -> Result<SocketAddr, Error> {
+    if let Ok(addr) = nameserver.parse::<SocketAddr>() {
+        Ok(addr)
+    } else {
+        nameserver
+            .trim_matches(|c| c == '[' || c == ']')
+            .parse::<IpAddr>()
+            .map(|ip| SocketAddr::new(ip, DNS_PORT))
+            .map_err(|e| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Invalid nameserver {nameserver}: {e}"),
+                )
+            })
+    }
+}
+
+async fn query(nameserver: SocketAddr, host: &str, record_type: u16) -> Result<Vec<IpAddr>, Error> {
+    let random = Uuid::new_v4();
+    let id = u16::from_be_bytes([random.as_bytes()[0], random.as_bytes()[1]]);
+    let mut packet = Vec::with_capacity(512);
+    packet.extend_from_slice(&id.to_be_bytes());
+    //Recursion Desired, 1 Question
+    packet.extend_from_slice(&[0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    for label in host.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid hostname {host}"),
+            ));
+        }
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&record_type.to_be_bytes());
+    packet.extend_from_slice(&RECORD_CLASS_IN.to_be_bytes());
+    let bind_addr: SocketAddr = if nameserver.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect(nameserver).await?;
+    socket.send(&packet).await?;
+    let mut buf = [0u8; 1500];
+    let len = timeout(DNS_TIMEOUT, socket.recv(&mut buf))
+        .await
+        .map_err(|_| {
+            Error::new(
+                ErrorKind::TimedOut,
+                format!("Timed out querying {nameserver} for {host}"),
+            )
+        })??;
+    parse_response(&buf[..len], id, record_type)
+}
+
+fn parse_response(buf: &[u8], id: u16, record_type: u16) -> Result<Vec<IpAddr>, Error> {
+    let invalid = || Error::new(ErrorKind::InvalidData, "Malformed DNS response");
+    if buf.len() < 12 || u16::from_be_bytes([buf[0], buf[1]]) != id {
+        return Err(invalid());
+    }
+    let rcode = buf[3] & 0x0F;
+    if rcode != 0 {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("DNS query failed with rcode {rcode}"),
+        ));
+    }
+    let question_count = u16::from_be_bytes([buf[4], buf[5]]);
+    let answer_count = u16::from_be_bytes([buf[6], buf[7]]);
+    let mut pos = 12;
+    for _ in 0..question_count {
+        pos = skip_name(buf, pos).ok_or_else(invalid)? + 4;
+    }
+    let mut addrs = vec![];
+    for _ in 0..answer_count {
+        pos = skip_name(buf, pos).ok_or_else(invalid)?;
+        let header = buf.get(pos..pos + 10).ok_or_else(invalid)?;
+        let answer_type = u16::from_be_bytes([header[0], header[1]]);
+        let data_len = u16::from_be_bytes([header[8], header[9]]) as usize;
+        pos += 10;
+        let data = buf.get(pos..pos + data_len).ok_or_else(invalid)?;
+        pos += data_len;
+        if answer_type != record_type {
+            continue;
+        }
+        if let Ok(octets) = <[u8; 4]>::try_from(data) {
+            addrs.push(IpAddr::from(octets));
+        } else if let Ok(octets) = <[u8; 16]>::try_from(data) {
+            addrs.push(IpAddr::from(octets));
+        }
+    }
+    Ok(addrs)
+}
+
+fn skip_name(buf: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *buf.get(pos)?;
+        if len & 0xC0 == 0xC0 {
+            return Some(pos + 2);
+        } else if len == 0 {
+            return Some(pos + 1);
+        }
+        pos += 1 + len as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn connects_to_the_pinned_address_and_keeps_the_stream() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let dns = DnsConfig {
+            static_hosts: HashMap::from([(
+                "node.example".to_string(),
+                IpAddr::from(Ipv4Addr::LOCALHOST),
+            )]),
+            ..Default::default()
+        };
+        let stream = connect_tcp(&Some(dns), "node.example", port).await.unwrap();
+        let (accepted, _) = listener.accept().await.unwrap();
+        assert_eq!(stream.local_addr().unwrap(), accepted.peer_addr().unwrap());
+        //Without a dns config the system resolver feeds the same racing
+        let stream = connect_tcp(&None, "localhost", port).await.unwrap();
+        let (accepted, _) = listener.accept().await.unwrap();
+        assert_eq!(stream.local_addr().unwrap(), accepted.peer_addr().unwrap());
+    }
+}
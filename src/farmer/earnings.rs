@@ -0,0 +1,167 @@
+use crate::farmer::network_stats::{
+    NetworkStats, SUB_SLOTS_PER_DAY, UI_ACTUAL_SPACE_CONSTANT_FACTOR,
+};
+use dg_xch_core::consensus::constants::ConsensusConstants;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+//Block rewards halve every three years of blocks, the pool gets 7/8 of them
+const BLOCKS_PER_YEAR: u32 = 1_681_920;
+const POOL_SHARE: f64 = 7.0 / 8.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct XchPrice {
+    pub price: f64,
+    pub currency: String,
+    pub updated: u64,
+}
+
+//What a plot NFT earns per day at its recent points rate, before pool payout delays
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EstimatedEarnings {
+    //Space in the netspace units that earns the points of the last day
+    pub estimated_space: u64,
+    pub xch_per_day: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_per_day: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+//Pools report their fee as a fraction ("0.01"), some as a percentage ("1%" or "1")
+pub fn parse_pool_fee(fee: &str) -> Option<f64> {
+    let fee = fee.trim();
+    let (value, percent) = match fee.strip_suffix('%') {
+        Some(value) => (value.trim(), true),
+        None => (fee, false),
+    };
+    let value: f64 = value.parse().ok()?;
+    let fraction = if percent || value > 1.0 {
+        value / 100.0
+    } else {
+        value
+    };
+    (0.0..=1.0).contains(&fraction).then_some(fraction)
+}
+
+//The pool share of the block reward at a height, the same schedule as the reference node
+pub fn pool_reward(height: u32) -> f64 {
+    let block_reward = match height / BLOCKS_PER_YEAR {
+        0..=2 => 2.0,
+        3..=5 => 1.0,
+        6..=8 => 0.5,
+        9..=11 => 0.25,
+        _ => 0.125,
+    };
+    block_reward * POOL_SHARE
+}
+
+//Inverse of the partial rate the simulation uses: a plot passes the filter once every
+//2^filter_bits signage points and earns its points when the proof fits the signage point interval
+pub fn space_from_points(
+    points_per_day: f64,
+    filter_bits: usize,
+    constants: &ConsensusConstants,
+) -> f64 {
+    let sps_per_day = constants.num_sps_sub_slot as f64 * SUB_SLOTS_PER_DAY;
+    let filter = 2f64.powi(filter_bits as i32);
+    let sp_interval_iters =
+        constants.pool_sub_slot_iters as f64 / constants.num_sps_sub_slot as f64;
+    let raw_space = points_per_day * filter * constants.difficulty_constant_factor as f64
+        / (sps_per_day * sp_interval_iters);
+    raw_space * UI_ACTUAL_SPACE_CONSTANT_FACTOR
+}
+
+//The share of the netspace the points stand for times the pool rewards of a day, less the pool
+//fee and the points the pool did not credit
+pub fn estimate_earnings(
+    points_per_day: f64,
+    credited_share: Option<f64>,
+    pool_fee: Option<f64>,
+    network: &NetworkStats,
+    price: Option<&XchPrice>,
+    filter_bits: usize,
+    constants: &ConsensusConstants,
+) -> Option<EstimatedEarnings> {
+    if network.netspace == 0 {
+        return None;
+    }
+    let space = space_from_points(points_per_day, filter_bits, constants);
+    let blocks_per_day = constants.slot_blocks_target as f64 * SUB_SLOTS_PER_DAY;
+    let xch_per_day = space / network.netspace as f64
+        * blocks_per_day
+        * pool_reward(network.height)
+        * (1.0 - pool_fee.unwrap_or_default())
+        * credited_share.unwrap_or(1.0).min(1.0);
+    Some(EstimatedEarnings {
+        estimated_space: space as u64,
+        xch_per_day,
+        price_per_day: price.map(|p| xch_per_day * p.price),
+        currency: price.map(|p| p.currency.clone()),
+    })
+}
+
+//Reads the price at the JSON pointer, as a number or a numeric string
+pub fn parse_price(body: &Value, pointer: &str) -> Option<f64> {
+    match body.pointer(pointer)? {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+    .filter(|p: &f64| p.is_finite() && *p >= 0.0)
+}
+
+pub fn format_earnings(earnings: &EstimatedEarnings) -> String {
+    let mut formatted = format!("{:.6} XCH/day", earnings.xch_per_day);
+    if let (Some(price), Some(currency)) = (earnings.price_per_day, &earnings.currency) {
+        formatted.push_str(&format!(" ({price:.2} {currency})"));
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::network_stats::{effective_plot_size, EIB};
+    use dg_xch_core::consensus::constants::MAINNET;
+
+    #[test]
+    fn estimates_a_k32_from_its_daily_points() {
+        assert_eq!(parse_pool_fee("0.01"), Some(0.01));
+        assert_eq!(parse_pool_fee("1%"), Some(0.01));
+        assert_eq!(parse_pool_fee("2"), Some(0.02));
+        assert_eq!(parse_pool_fee("none"), None);
+        assert_eq!(pool_reward(5_000_000), 1.75);
+        assert_eq!(pool_reward(6_000_000), 0.875);
+        let space = space_from_points(10.0, MAINNET.number_zero_bits_plot_filter, &MAINNET);
+        let k32 = effective_plot_size(32) as f64;
+        assert!((space / k32 - 1.0).abs() < 0.05, "{space}");
+        let network = NetworkStats {
+            height: 6_000_000,
+            netspace: (20.0 * EIB) as u128,
+            ..Default::default()
+        };
+        let price = XchPrice {
+            price: 20.0,
+            currency: "USD".to_string(),
+            updated: 0,
+        };
+        let earnings = estimate_earnings(
+            10.0,
+            Some(0.5),
+            Some(0.01),
+            &network,
+            Some(&price),
+            MAINNET.number_zero_bits_plot_filter,
+            &MAINNET,
+        )
+        .unwrap();
+        let expected = space / (20.0 * EIB) * 4608.0 * 0.875 * 0.99 * 0.5;
+        assert!((earnings.xch_per_day - expected).abs() < 1e-15);
+        assert_eq!(earnings.price_per_day, Some(earnings.xch_per_day * 20.0));
+        let body = serde_json::json!({"chia": {"usd": 20.5}, "price": "19.9"});
+        assert_eq!(parse_price(&body, "/chia/usd"), Some(20.5));
+        assert_eq!(parse_price(&body, "/price"), Some(19.9));
+        assert_eq!(parse_price(&body, "/chia/eur"), None);
+    }
+}
@@ -0,0 +1,318 @@
+use crate::error::FarmerResult;
+use crate::farmer::availability::AvailabilityReport;
+use crate::farmer::config::{EmailReportConfig, EmailSchedule};
+use crate::farmer::events::{EventFilter, FarmerEvent, FarmerEventKind};
+use crate::farmer::smtp::address;
+use crate::farmer::time_format::format_timestamp;
+use crate::farmer::webhook::WebhookTemplate;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+const DAY: u64 = 24 * 60 * 60;
+//Incidents listed in full, the rest only count
+const MAX_INCIDENTS: usize = 10;
+
+pub const REPORT_VARIABLES: &[&str] = &[
+    "schedule",
+    "period_start",
+    "period_end",
+    "network",
+    "version",
+    "plots",
+    "space",
+    "points",
+    "partials_accepted",
+    "partials_rejected",
+    "acceptance",
+    "availability_day",
+    "availability_week",
+    "proofs",
+    "blocks",
+    "incident_count",
+    "incidents",
+];
+
+const DEFAULT_SUBJECT: &str =
+    "Farm report {{ period_end }}: {{ blocks }} blocks, {{ points }} points";
+const DEFAULT_TEMPLATE: &str = "Farm report for {{ period_start }} to {{ period_end }} on {{ network }}
+
+Plots:          {{ plots }} ({{ space }})
+Points:         {{ points }}
+Partials:       {{ partials_accepted }} accepted, {{ partials_rejected }} rejected ({{ acceptance }} accepted)
+Availability:   {{ availability_day }} last 24 hours, {{ availability_week }} last 7 days
+Proofs:         {{ proofs }}
+Blocks won:     {{ blocks }}
+Incidents:      {{ incident_count }}
+{{ incidents }}
+--
+{{ version }}
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailTemplates {
+    subject: WebhookTemplate,
+    body: WebhookTemplate,
+}
+impl EmailTemplates {
+    pub fn parse(config: &EmailReportConfig) -> FarmerResult<Self> {
+        Ok(Self {
+            subject: WebhookTemplate::parse_with(
+                config.subject.as_deref().unwrap_or(DEFAULT_SUBJECT),
+                REPORT_VARIABLES,
+            )?,
+            body: WebhookTemplate::parse_with(
+                config.template.as_deref().unwrap_or(DEFAULT_TEMPLATE),
+                REPORT_VARIABLES,
+            )?,
+        })
+    }
+}
+
+//Events of one report period
+#[derive(Debug, Clone, Default)]
+pub struct ReportCounters {
+    pub proofs: u64,
+    pub blocks: u64,
+    pub partials_accepted: u64,
+    pub partials_rejected: u64,
+    pub points: u64,
+    pub incident_count: u64,
+    pub incidents: VecDeque<FarmerEvent>,
+    //Pools credit the difficulty a partial was sent at, the events carry the one after it
+    difficulties: HashMap<Bytes32, u64>,
+}
+impl ReportCounters {
+    pub fn record(&mut self, event: &FarmerEvent) {
+        match &event.kind {
+            FarmerEventKind::ProofDeclared { .. } => self.proofs += 1,
+            FarmerEventKind::SignedValues { .. } => self.blocks += 1,
+            FarmerEventKind::PartialAccepted {
+                launcher_id,
+                new_difficulty,
+                ..
+            } => {
+                self.partials_accepted += 1;
+                self.points += self
+                    .difficulties
+                    .insert(*launcher_id, *new_difficulty)
+                    .unwrap_or(*new_difficulty);
+            }
+            FarmerEventKind::PartialRejected { .. } => self.partials_rejected += 1,
+            _ => {}
+        }
+        if EventFilter::Errors.matches(&event.kind) {
+            self.incident_count += 1;
+            if self.incidents.len() >= MAX_INCIDENTS {
+                self.incidents.pop_front();
+            }
+            self.incidents.push_back(event.clone());
+        }
+    }
+    //Starts the next period, the known difficulties carry over
+    pub fn reset(&mut self) {
+        *self = Self {
+            difficulties: std::mem::take(&mut self.difficulties),
+            ..Default::default()
+        };
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub schedule: EmailSchedule,
+    pub period_start: u64,
+    pub period_end: u64,
+    pub network: String,
+    pub version: String,
+    pub plots: u64,
+    pub space: u64,
+    pub availability: AvailabilityReport,
+}
+impl Summary {
+    pub fn variables(&self, counters: &ReportCounters) -> HashMap<&'static str, String> {
+        let answered = counters.partials_accepted + counters.partials_rejected;
+        let mut incidents: Vec<String> = counters
+            .incidents
+            .iter()
+            .map(|e| format!("  {} {}", format_timestamp(e.timestamp), e.kind))
+            .collect();
+        let unlisted = counters.incident_count - counters.incidents.len() as u64;
+        if unlisted > 0 {
+            incidents.insert(0, format!("  ... {unlisted} earlier"));
+        }
+        HashMap::from([
+            (
+                "schedule",
+                match self.schedule {
+                    EmailSchedule::Daily => "daily",
+                    EmailSchedule::Weekly => "weekly",
+                }
+                .to_string(),
+            ),
+            ("period_start", format_timestamp(self.period_start)),
+            ("period_end", format_timestamp(self.period_end)),
+            ("network", self.network.clone()),
+            ("version", self.version.clone()),
+            ("plots", self.plots.to_string()),
+            ("space", bytefmt::format_to(self.space, bytefmt::Unit::TIB)),
+            ("points", counters.points.to_string()),
+            ("partials_accepted", counters.partials_accepted.to_string()),
+            ("partials_rejected", counters.partials_rejected.to_string()),
+            (
+                "acceptance",
+                if answered > 0 {
+                    format!(
+                        "{:.2}%",
+                        counters.partials_accepted as f64 * 100.0 / answered as f64
+                    )
+                } else {
+                    "n/a".to_string()
+                },
+            ),
+            (
+                "availability_day",
+                format!("{:.2}%", self.availability.day.percent),
+            ),
+            (
+                "availability_week",
+                format!("{:.2}%", self.availability.week.percent),
+            ),
+            ("proofs", counters.proofs.to_string()),
+            ("blocks", counters.blocks.to_string()),
+            ("incident_count", counters.incident_count.to_string()),
+            ("incidents", incidents.join("\n")),
+        ])
+    }
+}
+
+//Plain text message with the headers the SMTP DATA command expects
+pub fn compose_message(
+    config: &EmailReportConfig,
+    templates: &EmailTemplates,
+    vars: &HashMap<&'static str, String>,
+    now: u64,
+) -> String {
+    //A value with a line break would start a new header
+    let subject = templates
+        .subject
+        .render(vars, false)
+        .replace(['\r', '\n'], " ");
+    let subject = if subject.is_ascii() {
+        subject
+    } else {
+        format!("=?utf-8?B?{}?=", STANDARD.encode(subject))
+    };
+    let date = DateTime::<Utc>::from_timestamp(now as i64, 0)
+        .unwrap_or_default()
+        .to_rfc2822();
+    let domain = address(&config.from)
+        .rsplit_once('@')
+        .map(|(_, domain)| domain)
+        .unwrap_or("localhost");
+    let body = templates
+        .body
+        .render(vars, false)
+        .replace("\r\n", "\n")
+        .replace('\n', "\r\n");
+    format!(
+        "From: {}\r\nTo: {}\r\nSubject: {subject}\r\nDate: {date}\r\nMessage-ID: <{}@{domain}>\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n{body}",
+        config.from,
+        config.to.join(", "),
+        Uuid::new_v4()
+    )
+}
+
+//Unix timestamp of the first send time after now, offset is the seconds the configured time zone
+//is ahead of utc
+pub fn next_report(now: u64, schedule: EmailSchedule, hour: u8, offset: i32) -> u64 {
+    let day_secs = DAY as i64;
+    let local = now as i64 + offset as i64;
+    let mut day = local.div_euclid(day_secs);
+    let period = match schedule {
+        EmailSchedule::Daily => 1,
+        EmailSchedule::Weekly => {
+            //The epoch was a thursday, go back to monday
+            day -= (day + 3).rem_euclid(7);
+            7
+        }
+    };
+    let mut next = day * day_secs + hour.min(23) as i64 * 3600;
+    while next <= local {
+        next += period * day_secs;
+    }
+    (next - offset as i64).max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_the_digest_and_schedules_the_next_one() {
+        let config = EmailReportConfig {
+            smtp_host: "smtp.example".to_string(),
+            smtp_port: 587,
+            tls: Default::default(),
+            username: None,
+            password: None,
+            from: "Farmer <farm@example.org>".to_string(),
+            to: vec!["me@example.org".to_string()],
+            schedule: EmailSchedule::Daily,
+            hour: 7,
+            subject: Some("{{ blocks }} blocks, {{ network }}\r\nBcc: x@y".to_string()),
+            template: Some(
+                "{{ points }} points, {{ acceptance }}\n.{{ incident_count }}".to_string(),
+            ),
+        };
+        let templates = EmailTemplates::parse(&config).unwrap();
+        let launcher_id = Bytes32::default();
+        let mut counters = ReportCounters::default();
+        for difficulty in [10, 10, 20] {
+            counters.record(&FarmerEvent::new(FarmerEventKind::PartialAccepted {
+                launcher_id,
+                pool_url: "https://pool.example".to_string(),
+                new_difficulty: difficulty,
+                sp: None,
+            }));
+        }
+        counters.record(&FarmerEvent::new(FarmerEventKind::PartialRejected {
+            launcher_id,
+            pool_url: "https://pool.example".to_string(),
+            error_code: 2,
+            error_message: "too late".to_string(),
+            sp: None,
+        }));
+        let summary = Summary {
+            network: "mainnet".to_string(),
+            ..Default::default()
+        };
+        let message = compose_message(&config, &templates, &summary.variables(&counters), 0);
+        assert!(message.contains("\r\nSubject: 0 blocks, mainnet  Bcc: x@y\r\n"));
+        assert!(message.contains("Message-ID: <"));
+        assert!(message.ends_with("\r\n\r\n30 points, 75.00%\r\n.1"));
+        counters.reset();
+        assert_eq!(counters.points, 0);
+        assert_eq!(counters.difficulties.get(&launcher_id), Some(&20));
+        assert!(EmailTemplates::parse(&EmailReportConfig {
+            template: Some("{{ launcher_id }}".to_string()),
+            ..config
+        })
+        .is_err());
+
+        //Thursday 1970-01-01 10:00 utc, two hours ahead of utc
+        let now = 10 * 3600;
+        assert_eq!(
+            next_report(now, EmailSchedule::Daily, 7, 7200),
+            DAY + 5 * 3600
+        );
+        assert_eq!(next_report(now, EmailSchedule::Daily, 13, 7200), 11 * 3600);
+        assert_eq!(
+            next_report(now, EmailSchedule::Weekly, 7, 7200),
+            4 * DAY + 5 * 3600
+        );
+    }
+}
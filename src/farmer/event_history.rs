@@ -0,0 +1,63 @@
+use crate::farmer::events::{EventFilter, FarmerEvent, FarmerEventKind};
+use std::collections::VecDeque;
+use tokio::sync::Mutex;
+
+//The last farm events for clients connecting after an incident, oldest first
+#[derive(Debug, Default)]
+pub struct EventHistory {
+    events: Mutex<VecDeque<FarmerEvent>>,
+}
+impl EventHistory {
+    //Signage points arrive every few seconds and would push everything else out
+    pub async fn record(&self, event: FarmerEvent, capacity: usize) {
+        if capacity == 0 || matches!(event.kind, FarmerEventKind::SignagePoint { .. }) {
+            return;
+        }
+        let mut events = self.events.lock().await;
+        while events.len() >= capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+    pub async fn recent(&self, limit: usize, filter: Option<EventFilter>) -> Vec<FarmerEvent> {
+        let events = self.events.lock().await;
+        let mut recent: Vec<FarmerEvent> = events
+            .iter()
+            .rev()
+            .filter(|e| filter.map(|f| f.matches(&e.kind)).unwrap_or(true))
+            .take(limit)
+            .cloned()
+            .collect();
+        recent.reverse();
+        recent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(message: &str) -> FarmerEvent {
+        FarmerEvent::new(FarmerEventKind::Error {
+            message: message.to_string(),
+        })
+    }
+
+    #[tokio::test]
+    async fn keeps_the_newest_events() {
+        let history = EventHistory::default();
+        for message in ["a", "b", "c"] {
+            history.record(error(message), 2).await;
+        }
+        history
+            .record(FarmerEvent::new(FarmerEventKind::FarmPaused {}), 2)
+            .await;
+        let recent = history.recent(10, None).await;
+        assert_eq!(
+            recent,
+            vec![error("c"), FarmerEvent::new(FarmerEventKind::FarmPaused {})]
+        );
+        let errors = history.recent(10, Some(EventFilter::Errors)).await;
+        assert_eq!(errors, vec![error("c")]);
+    }
+}
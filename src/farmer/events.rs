@@ -0,0 +1,394 @@
+use crate::farmer::config::PayoutMismatchPolicy;
+use crate::farmer::latency_regression::LatencyKind;
+use crate::farmer::launcher_stats::pool_host;
+use crate::farmer::sp_tag::SpTag;
+use crate::farmer::startup_report::StartupReport;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::time::SystemTime;
+
+pub const EVENT_CHANNEL_SIZE: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FarmerEventKind {
+    SignagePoint {
+        challenge_hash: Bytes32,
+        sp_hash: Bytes32,
+        signage_point_index: u8,
+    },
+    ProofDeclared {
+        challenge_hash: Bytes32,
+        sp_hash: Bytes32,
+        plot_identifier: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sp: Option<SpTag>,
+        //Set for plots pooled with one of the configured plot NFTs
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        launcher_id: Option<Bytes32>,
+    },
+    SignedValues {
+        quality_string: Bytes32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sp: Option<SpTag>,
+    },
+    PartialSubmitted {
+        launcher_id: Bytes32,
+        pool_url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sp: Option<SpTag>,
+    },
+    PartialAccepted {
+        launcher_id: Bytes32,
+        pool_url: String,
+        new_difficulty: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sp: Option<SpTag>,
+    },
+    PartialRejected {
+        launcher_id: Bytes32,
+        pool_url: String,
+        error_code: u8,
+        error_message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sp: Option<SpTag>,
+    },
+    //Not sent, the pool window of its signage point had already passed
+    PartialStaleLocal {
+        launcher_id: Bytes32,
+        pool_url: String,
+        sp_age_ms: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sp: Option<SpTag>,
+    },
+    //Not sent, the same proof already went to the pool within duplicate_partial_window
+    PartialDuplicate {
+        launcher_id: Bytes32,
+        pool_url: String,
+        plot_identifier: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sp: Option<SpTag>,
+    },
+    FullNodeConnected {
+        host: String,
+        port: u16,
+    },
+    FullNodeDisconnected {
+        host: String,
+        port: u16,
+    },
+    Error {
+        message: String,
+    },
+    StartupReport {
+        report: Box<StartupReport>,
+    },
+    PlotMissing {
+        path: String,
+    },
+    PlotRecovered {
+        path: String,
+    },
+    PlotRemoved {
+        path: String,
+        file_size: u64,
+    },
+    PlotAuditFailed {
+        path: String,
+        error: String,
+    },
+    //Left out of lookups, reopening it after an IO error kept failing
+    PlotUnreadable {
+        path: String,
+        error: String,
+    },
+    FarmPaused {},
+    FarmResumed {},
+    FarmStandby {},
+    FarmActive {},
+    FarmIdle {
+        seconds: u64,
+    },
+    FarmAwake {},
+    //Found with watch only keys, nothing was signed or sent
+    WatchOnlyProof {
+        sp_hash: Bytes32,
+        plot_identifier: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sp: Option<SpTag>,
+    },
+    WatchOnlyPartial {
+        launcher_id: Bytes32,
+        difficulty: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        sp: Option<SpTag>,
+    },
+    //The pool credited fewer points than the partials it accepted add up to, in consecutive windows
+    PoolPointsDiscrepancy {
+        launcher_id: Bytes32,
+        pool_url: String,
+        local_points: u64,
+        pool_points: u64,
+        #[serde(default)]
+        windows: u32,
+    },
+    //The pool reported other payout instructions than the payout address of the launcher's farm
+    PayoutMismatch {
+        launcher_id: Bytes32,
+        pool_url: String,
+        configured: String,
+        pool: String,
+        policy: PayoutMismatchPolicy,
+    },
+    //The owner key no longer controls the plot NFT or the pool rejects the authentication key
+    KeyHealthFailed {
+        launcher_id: Bytes32,
+        problem: String,
+    },
+    //p95 latency of a plot directory or pool this window is worse than the baseline window
+    LatencyRegression {
+        kind: LatencyKind,
+        target: String,
+        baseline_p95_ms: u64,
+        current_p95_ms: u64,
+    },
+}
+
+impl Display for FarmerEventKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FarmerEventKind::SignagePoint {
+                sp_hash,
+                signage_point_index,
+                ..
+            } => write!(f, "Signage Point {signage_point_index}: {sp_hash}"),
+            FarmerEventKind::ProofDeclared {
+                sp_hash,
+                plot_identifier,
+                sp,
+                ..
+            } => write!(
+                f,
+                "{}Proof Declared for {sp_hash}: {plot_identifier}",
+                sp_prefix(sp)
+            ),
+            FarmerEventKind::SignedValues { quality_string, sp } => {
+                write!(f, "{}Signed Values Sent: {quality_string}", sp_prefix(sp))
+            }
+            FarmerEventKind::PartialSubmitted {
+                launcher_id,
+                pool_url,
+                sp,
+            } => write!(
+                f,
+                "{}Partial Submitted for {launcher_id} to {pool_url}",
+                sp_prefix(sp)
+            ),
+            FarmerEventKind::PartialAccepted {
+                launcher_id,
+                pool_url,
+                new_difficulty,
+                sp,
+            } => write!(
+                f,
+                "{}Partial Accepted for {launcher_id} by {pool_url}, Difficulty: {new_difficulty}",
+                sp_prefix(sp)
+            ),
+            FarmerEventKind::PartialRejected {
+                launcher_id,
+                pool_url,
+                error_code,
+                error_message,
+                sp,
+            } => write!(
+                f,
+                "{}Partial Rejected for {launcher_id} by {pool_url}: ({error_code}) {error_message}",
+                sp_prefix(sp)
+            ),
+            FarmerEventKind::PartialStaleLocal {
+                launcher_id,
+                pool_url,
+                sp_age_ms,
+                sp,
+            } => write!(
+                f,
+                "{}Partial for {launcher_id} not sent to {pool_url}, signage point is {:.1}s old",
+                sp_prefix(sp),
+                *sp_age_ms as f64 / 1000.0
+            ),
+            FarmerEventKind::PartialDuplicate {
+                launcher_id,
+                pool_url,
+                plot_identifier,
+                sp,
+            } => write!(
+                f,
+                "{}Duplicate partial for {launcher_id} from {plot_identifier} not sent to {pool_url}",
+                sp_prefix(sp)
+            ),
+            FarmerEventKind::FullNodeConnected { host, port } => {
+                write!(f, "Connected to Full Node {host}:{port}")
+            }
+            FarmerEventKind::FullNodeDisconnected { host, port } => {
+                write!(f, "Disconnected from Full Node {host}:{port}")
+            }
+            FarmerEventKind::Error { message } => f.write_str(message),
+            FarmerEventKind::StartupReport { report } => write!(
+                f,
+                "Startup: {} plots on {} harvesters, {} warnings",
+                report.harvesters.iter().map(|h| h.plots).sum::<u64>(),
+                report.harvesters.len(),
+                report.warnings.len()
+            ),
+            FarmerEventKind::PlotMissing { path } => write!(f, "Plot missing: {path}"),
+            FarmerEventKind::PlotRecovered { path } => write!(f, "Plot recovered: {path}"),
+            FarmerEventKind::PlotRemoved { path, .. } => write!(f, "Plot removed: {path}"),
+            FarmerEventKind::PlotAuditFailed { path, error } => {
+                write!(f, "Plot failed its audit: {path}, {error}")
+            }
+            FarmerEventKind::PlotUnreadable { path, error } => {
+                write!(f, "Plot unreadable: {path}, {error}")
+            }
+            FarmerEventKind::FarmPaused {} => f.write_str("Farming paused"),
+            FarmerEventKind::FarmResumed {} => f.write_str("Farming resumed"),
+            FarmerEventKind::FarmStandby {} => f.write_str("Standing by for another instance"),
+            FarmerEventKind::FarmActive {} => f.write_str("Took over farming"),
+            FarmerEventKind::FarmIdle { seconds } => write!(
+                f,
+                "No signage point for {seconds} seconds, released plots and caches"
+            ),
+            FarmerEventKind::FarmAwake {} => f.write_str("Signage points resumed, reloading plots"),
+            FarmerEventKind::WatchOnlyProof {
+                sp_hash,
+                plot_identifier,
+                sp,
+            } => write!(
+                f,
+                "{}Watch Only Proof for {sp_hash}: {plot_identifier}",
+                sp_prefix(sp)
+            ),
+            FarmerEventKind::WatchOnlyPartial {
+                launcher_id,
+                difficulty,
+                sp,
+            } => write!(
+                f,
+                "{}Watch Only Partial for {launcher_id}, Difficulty: {difficulty}",
+                sp_prefix(sp)
+            ),
+            FarmerEventKind::PoolPointsDiscrepancy {
+                launcher_id,
+                pool_url,
+                local_points,
+                pool_points,
+                windows,
+            } => write!(
+                f,
+                "Pool {} credited {pool_points} of {local_points} locally accepted points for {launcher_id} over {windows} windows",
+                pool_host(pool_url)
+            ),
+            FarmerEventKind::PayoutMismatch {
+                launcher_id,
+                pool_url,
+                configured,
+                pool,
+                policy,
+            } => write!(
+                f,
+                "Pool {} pays {launcher_id} to {pool} instead of {configured}, {}",
+                pool_host(pool_url),
+                match policy {
+                    PayoutMismatchPolicy::Warn => "not corrected",
+                    PayoutMismatchPolicy::Correct => "correcting",
+                    PayoutMismatchPolicy::Halt => "partials halted",
+                }
+            ),
+            FarmerEventKind::KeyHealthFailed {
+                launcher_id,
+                problem,
+            } => write!(f, "Key check of {launcher_id} failed, {problem}"),
+            FarmerEventKind::LatencyRegression {
+                kind,
+                target,
+                baseline_p95_ms,
+                current_p95_ms,
+            } => {
+                let what = match kind {
+                    LatencyKind::Lookup => "Lookup latency of",
+                    LatencyKind::Submission => "Partial submission latency to",
+                };
+                write!(
+                    f,
+                    "{what} {target} regressed, p95 {current_p95_ms}ms against {baseline_p95_ms}ms the window before"
+                )
+            }
+        }
+    }
+}
+
+fn sp_prefix(sp: &Option<SpTag>) -> String {
+    sp.map(|t| format!("{t} ")).unwrap_or_default()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FarmerEvent {
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub kind: FarmerEventKind,
+}
+impl FarmerEvent {
+    pub fn new(kind: FarmerEventKind) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum EventFilter {
+    Proofs,
+    Partials,
+    Errors,
+}
+impl EventFilter {
+    pub fn matches(&self, kind: &FarmerEventKind) -> bool {
+        match self {
+            EventFilter::Proofs => matches!(
+                kind,
+                FarmerEventKind::ProofDeclared { .. }
+                    | FarmerEventKind::SignedValues { .. }
+                    | FarmerEventKind::WatchOnlyProof { .. }
+            ),
+            EventFilter::Partials => matches!(
+                kind,
+                FarmerEventKind::PartialSubmitted { .. }
+                    | FarmerEventKind::PartialAccepted { .. }
+                    | FarmerEventKind::PartialRejected { .. }
+                    | FarmerEventKind::PartialStaleLocal { .. }
+                    | FarmerEventKind::PartialDuplicate { .. }
+                    | FarmerEventKind::WatchOnlyPartial { .. }
+                    | FarmerEventKind::PoolPointsDiscrepancy { .. }
+            ),
+            EventFilter::Errors => matches!(
+                kind,
+                FarmerEventKind::PartialRejected { .. }
+                    | FarmerEventKind::FullNodeDisconnected { .. }
+                    | FarmerEventKind::Error { .. }
+                    | FarmerEventKind::PlotMissing { .. }
+                    | FarmerEventKind::PlotRemoved { .. }
+                    | FarmerEventKind::PlotAuditFailed { .. }
+                    | FarmerEventKind::PlotUnreadable { .. }
+                    | FarmerEventKind::PoolPointsDiscrepancy { .. }
+                    | FarmerEventKind::PayoutMismatch { .. }
+                    | FarmerEventKind::KeyHealthFailed { .. }
+                    | FarmerEventKind::LatencyRegression { .. }
+            ),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::farmer::config::Config;
+use dg_xch_core::blockchain::sized_bytes::{Bytes32, Bytes48};
+use dg_xch_keys::decode_puzzle_hash;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FarmStats {
+    pub proofs_declared: u64,
+    pub partials_submitted: u64,
+    pub partials_accepted: u64,
+    pub partials_rejected: u64,
+    pub partials_suppressed: u64,
+}
+
+//Maps keys and pool singletons back to the farm that owns them
+#[derive(Debug, Clone, Default)]
+pub struct FarmIndex {
+    by_farmer_key: HashMap<Bytes48, String>,
+    by_p2_singleton: HashMap<Bytes32, String>,
+    targets: HashMap<String, Bytes32>,
+}
+impl FarmIndex {
+    //Farms with an invalid payout address get no target, Readiness keeps them from farming
+    pub fn new(config: &Config) -> Self {
+        let mut index = Self::default();
+        for farm in config.all_farms() {
+            if let Ok(target) = decode_puzzle_hash(&farm.payout_address) {
+                index.targets.insert(farm.name.clone(), target);
+            }
+            for farmer_info in &farm.farmer_info {
+                index
+                    .by_farmer_key
+                    .insert(farmer_info.farmer_public_key(), farm.name.clone());
+            }
+            for pool_info in &farm.pool_info {
+                index
+                    .by_p2_singleton
+                    .insert(pool_info.p2_singleton_puzzle_hash, farm.name.clone());
+            }
+        }
+        index
+    }
+    pub fn farm_for_farmer_key(&self, farmer_public_key: &Bytes48) -> Option<&String> {
+        self.by_farmer_key.get(farmer_public_key)
+    }
+    pub fn farm_for_p2_singleton(&self, p2_singleton_puzzle_hash: &Bytes32) -> Option<&String> {
+        self.by_p2_singleton.get(p2_singleton_puzzle_hash)
+    }
+    pub fn target_for_farmer_key(&self, farmer_public_key: &Bytes48) -> Option<Bytes32> {
+        self.farm_for_farmer_key(farmer_public_key)
+            .and_then(|farm| self.targets.get(farm))
+            .copied()
+    }
+}
@@ -0,0 +1,65 @@
+use crate::error::FarmerResult;
+use dg_xch_clients::protocols::pool::{PoolErrorCode, PostPartialRequest};
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+//Everything needed to compare local and pool verification of a rejected partial
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectedPartialDump {
+    pub timestamp: u64,
+    pub pool_url: String,
+    pub error_code: u8,
+    pub error_message: String,
+    pub plot_identifier: String,
+    pub pool_difficulty: Option<u64>,
+    pub request: PostPartialRequest,
+    pub serialized_payload: String,
+    pub payload_hash: String,
+    pub local_quality_string: Option<Bytes32>,
+    //Plot signature checked again against the plot key of the proof
+    pub local_plot_signature_valid: bool,
+    pub local_auth_signature_valid: bool,
+}
+
+pub fn should_dump(error_code: u8) -> bool {
+    error_code == PoolErrorCode::InvalidProof as u8
+        || error_code == PoolErrorCode::InvalidSignature as u8
+}
+
+pub fn write_dump(dir: &Path, max_bytes: u64, dump: &RejectedPartialDump) -> FarmerResult<()> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!(
+        "{}_{}.json",
+        dump.timestamp, dump.request.payload.launcher_id
+    ));
+    fs::write(&path, serde_json::to_string_pretty(dump)?)?;
+    info!("Wrote rejected partial dump to {:?}", path);
+    Ok(enforce_size_cap(dir, max_bytes)?)
+}
+
+//Removes the oldest dumps until the directory fits in max_bytes
+fn enforce_size_cap(dir: &Path, max_bytes: u64) -> Result<(), std::io::Error> {
+    let mut files: Vec<(PathBuf, u64, std::time::SystemTime)> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let meta = e.metadata().ok().filter(|m| m.is_file())?;
+            Some((e.path(), meta.len(), meta.modified().ok()?))
+        })
+        .collect();
+    files.sort_by_key(|(_, _, modified)| *modified);
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    for (path, len, _) in files {
+        if total <= max_bytes {
+            break;
+        }
+        if let Err(e) = fs::remove_file(&path) {
+            warn!("Failed to remove old partial dump {:?}: {:?}", path, e);
+        } else {
+            total -= len;
+        }
+    }
+    Ok(())
+}
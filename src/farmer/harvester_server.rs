@@ -0,0 +1,858 @@
+use crate::farmer::config::Config;
+use crate::farmer::node_ssl::trust_anchor;
+use crate::get_ssl_root_path;
+#[cfg(feature = "quic")]
+use crate::harvesters::link::{quic_endpoint, quic_link};
+use crate::harvesters::link::{
+    read_message, upgrade_link, websocket_link, LinkOptions, LinkReader, LinkWriter,
+};
+use crate::harvesters::{Harvester, ProofHandler, SignatureHandler};
+use async_trait::async_trait;
+use dg_xch_clients::protocols::harvester::{
+    HarvesterHandshake, NewProofOfSpace, NewSignagePointHarvester, PlotSyncIdentifier,
+    PlotSyncResponse, RequestSignatures, RespondSignatures,
+};
+use dg_xch_clients::protocols::shared::{
+    load_certs, load_private_key, Handshake, PROTOCOL_VERSION, SOFTWARE_VERSION,
+};
+use dg_xch_clients::protocols::ProtocolMessageTypes;
+use dg_xch_clients::websocket::{ChiaMessage, NodeType};
+use dg_xch_core::ssl::create_all_ssl;
+use dg_xch_serialize::ChiaSerialize;
+use futures_util::StreamExt;
+use log::{debug, info, warn};
+use rustls::server::AllowAnyAuthenticatedClient;
+use rustls::{RootCertStore, ServerConfig};
+use std::collections::HashMap;
+use std::io::{Cursor, Error, ErrorKind};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, oneshot, Mutex};
+use tokio::time::{sleep, timeout, timeout_at, Instant};
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::accept_async;
+use uuid::Uuid;
+
+static FARMER_CRT: &str = "farmer/private_farmer.crt";
+static FARMER_KEY: &str = "farmer/private_farmer.key";
+static CA_PRIVATE_CRT: &str = "ca/private_ca.crt";
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(15);
+//Proofs arriving later than this are too late for the signage point and its partials
+const PROOF_WINDOW: Duration = Duration::from_secs(30);
+const PROOF_CHANNEL_SIZE: usize = 256;
+//QUIC connections without a packet for this long are dropped, like the link idle timeout of
+//dg_fast_harvester. Keep-alives are sent well within it
+#[cfg(feature = "quic")]
+const QUIC_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+//One harvester connected to this farmer
+pub struct HarvesterPeer {
+    pub id: Uuid,
+    pub address: SocketAddr,
+    link: LinkWriter,
+    //Signature requests waiting for their response, by message id
+    pending: Mutex<HashMap<u16, oneshot::Sender<RespondSignatures>>>,
+    next_id: AtomicU16,
+}
+impl HarvesterPeer {
+    async fn send(&self, msg: ChiaMessage) -> Result<(), Error> {
+        self.link.send(msg).await
+    }
+    async fn request_signatures(
+        &self,
+        request: &RequestSignatures,
+    ) -> Result<RespondSignatures, Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().await.insert(id, sender);
+        let sent = self
+            .send(ChiaMessage::new(
+                ProtocolMessageTypes::RequestSignatures,
+                request,
+                Some(id),
+            ))
+            .await;
+        if let Err(e) = sent {
+            self.pending.lock().await.remove(&id);
+            return Err(e);
+        }
+        receiver.await.map_err(|_| {
+            Error::new(
+                ErrorKind::ConnectionAborted,
+                format!("Harvester {} disconnected", self.address),
+            )
+        })
+    }
+}
+
+//All harvesters connected over the harvester protocol, farmed as one harvester. Proofs are routed
+//back to the connection that found them
+pub struct RemoteHarvesters {
+    id: Uuid,
+    peers: Mutex<HashMap<Uuid, Arc<HarvesterPeer>>>,
+    plot_peers: Mutex<HashMap<String, Uuid>>,
+    proofs: broadcast::Sender<NewProofOfSpace>,
+}
+impl RemoteHarvesters {
+    pub fn new(id: Uuid) -> Self {
+        Self {
+            id,
+            peers: Default::default(),
+            plot_peers: Default::default(),
+            proofs: broadcast::channel(PROOF_CHANNEL_SIZE).0,
+        }
+    }
+    pub async fn peer_count(&self) -> usize {
+        self.peers.lock().await.len()
+    }
+    async fn connected(&self, peer: Arc<HarvesterPeer>) {
+        self.peers.lock().await.insert(peer.id, peer);
+    }
+    async fn disconnected(&self, peer_id: Uuid) {
+        self.peers.lock().await.remove(&peer_id);
+        self.plot_peers.lock().await.retain(|_, id| *id != peer_id);
+    }
+    async fn proof_found(&self, peer_id: Uuid, new_pos: NewProofOfSpace) {
+        self.plot_peers
+            .lock()
+            .await
+            .insert(new_pos.plot_identifier.clone(), peer_id);
+        let _ = self.proofs.send(new_pos);
+    }
+}
+#[async_trait]
+impl Harvester for RemoteHarvesters {
+    async fn new_signage_point<T>(
+        &self,
+        signage_point: Arc<NewSignagePointHarvester>,
+        proof_handle: T,
+    ) -> Result<(), Error>
+    where
+        T: ProofHandler + Sync + Send,
+    {
+        let peers: Vec<Arc<HarvesterPeer>> = self.peers.lock().await.values().cloned().collect();
+        if peers.is_empty() {
+            return Ok(());
+        }
+        //Subscribed before sending, a fast harvester may answer before the last send is done
+        let mut proofs = self.proofs.subscribe();
+        let msg = ChiaMessage::new(
+            ProtocolMessageTypes::NewSignagePointHarvester,
+            signage_point.as_ref(),
+            None,
+        );
+        for peer in peers {
+            if let Err(e) = peer.send(msg.clone()).await {
+                warn!(
+                    "Failed to send signage point to harvester {}: {:?}",
+                    peer.address, e
+                );
+            }
+        }
+        let deadline = Instant::now() + PROOF_WINDOW;
+        loop {
+            match timeout_at(deadline, proofs.recv()).await {
+                Ok(Ok(new_pos)) if new_pos.sp_hash == signage_point.sp_hash => {
+                    if let Err(e) = proof_handle.handle_proof(new_pos).await {
+                        warn!("Failed to handle proof of remote harvester: {:?}", e);
+                    }
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(RecvError::Lagged(skipped))) => {
+                    warn!("Skipped {skipped} proofs of remote harvesters");
+                }
+                Ok(Err(RecvError::Closed)) | Err(_) => return Ok(()),
+            }
+        }
+    }
+    async fn request_signatures<T>(
+        &self,
+        request_signatures: RequestSignatures,
+        response_handle: T,
+    ) -> Result<(), Error>
+    where
+        T: SignatureHandler + Sync + Send,
+    {
+        let peer_id = self
+            .plot_peers
+            .lock()
+            .await
+            .get(&request_signatures.plot_identifier)
+            .copied();
+        let peer = match peer_id {
+            Some(id) => self.peers.lock().await.get(&id).cloned(),
+            None => None,
+        }
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "No connected harvester has plot {}",
+                    request_signatures.plot_identifier
+                ),
+            )
+        })?;
+        let response = peer.request_signatures(&request_signatures).await?;
+        response_handle.handle_signature(response).await
+    }
+    fn uuid(&self) -> Uuid {
+        self.id
+    }
+}
+
+//Only harvesters with a certificate of the farmer's private CA may connect, like on a chia farmer
+fn tls_config(ssl_path: &Path) -> Result<ServerConfig, Error> {
+    let ca = load_certs(&ssl_path.join(CA_PRIVATE_CRT).to_string_lossy())?
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "No private CA certificate"))?;
+    //Harvesters present a certificate issued by the private CA of the farmer
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(std::iter::once(
+        trust_anchor(&ca).map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+    ));
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+        .with_single_cert(
+            load_certs(&ssl_path.join(FARMER_CRT).to_string_lossy())?,
+            load_private_key(&ssl_path.join(FARMER_KEY).to_string_lossy())?,
+        )
+        .map_err(|e| Error::other(format!("Invalid farmer certificate: {e:?}")))
+}
+
+//Accepts dg_fast_harvester and chia harvester connections while the farmer runs
+pub async fn harvester_server(
+    config: Arc<Config>,
+    remote: Arc<RemoteHarvesters>,
+    run: Arc<AtomicBool>,
+) {
+    let Some(server_config) = config.harvester_server.clone() else {
+        return;
+    };
+    let ssl_path = get_ssl_root_path(&config.ssl_root_path);
+    let cert_path = ssl_path.clone();
+    let tls = match tokio::task::spawn_blocking(move || create_all_ssl(&cert_path, false))
+        .await
+        .map_err(Error::other)
+        .and_then(|created| created)
+        .and_then(|_| tls_config(&ssl_path))
+    {
+        Ok(tls) => tls,
+        Err(e) => {
+            warn!(
+                "Not accepting harvester connections, failed to load certificates from {:?}: {:?}",
+                ssl_path, e
+            );
+            return;
+        }
+    };
+    let listener = match TcpListener::bind(&server_config.bind).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!(
+                "Failed to bind harvester server to {}: {:?}",
+                server_config.bind, e
+            );
+            return;
+        }
+    };
+    info!(
+        "Accepting harvester connections on {}, harvesters need a certificate of {:?}",
+        server_config.bind,
+        ssl_path.join(CA_PRIVATE_CRT)
+    );
+    if server_config.quic {
+        #[cfg(feature = "quic")]
+        match listener
+            .local_addr()
+            .and_then(|bind| quic_endpoint(tls.clone(), bind, QUIC_IDLE_TIMEOUT))
+        {
+            Ok(endpoint) => {
+                info!(
+                    "Accepting QUIC harvester connections on {} (UDP)",
+                    server_config.bind
+                );
+                tokio::spawn(serve_quic(
+                    endpoint,
+                    config.clone(),
+                    remote.clone(),
+                    run.clone(),
+                ));
+            }
+            Err(e) => warn!(
+                "Failed to bind QUIC harvester server to {}: {:?}",
+                server_config.bind, e
+            ),
+        }
+        #[cfg(not(feature = "quic"))]
+        warn!("QUIC is enabled for the harvester server but this build has no quic feature");
+    }
+    serve(
+        listener,
+        TlsAcceptor::from(Arc::new(tls)),
+        config,
+        remote,
+        run,
+    )
+    .await;
+    info!("Harvester Server Stopped");
+}
+
+async fn serve(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    config: Arc<Config>,
+    remote: Arc<RemoteHarvesters>,
+    run: Arc<AtomicBool>,
+) {
+    let port = listener.local_addr().map(|a| a.port()).unwrap_or_default();
+    while run.load(Ordering::Relaxed) {
+        let accepted = select! {
+            res = listener.accept() => Some(res),
+            _ = sleep(Duration::from_secs(1)) => None,
+        };
+        match accepted {
+            Some(Ok((stream, address))) => {
+                let acceptor = acceptor.clone();
+                let config = config.clone();
+                let remote = remote.clone();
+                let run = run.clone();
+                tokio::spawn(async move {
+                    if let Err(e) =
+                        handle_harvester(stream, address, acceptor, &config, remote, port, run)
+                            .await
+                    {
+                        warn!("Harvester connection from {address} failed: {:?}", e);
+                    }
+                });
+            }
+            Some(Err(e)) => warn!("Failed to accept harvester connection: {:?}", e),
+            None => {}
+        }
+    }
+}
+
+async fn handle_harvester(
+    stream: TcpStream,
+    address: SocketAddr,
+    acceptor: TlsAcceptor,
+    config: &Config,
+    remote: Arc<RemoteHarvesters>,
+    port: u16,
+    run: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    let stream = timeout(HANDSHAKE_TIMEOUT, async {
+        accept_async(acceptor.accept(stream).await?)
+            .await
+            .map_err(Error::other)
+    })
+    .await
+    .map_err(|_| Error::new(ErrorKind::TimedOut, "Timed out in the TLS handshake"))??;
+    let (link, read) = websocket_link(stream);
+    run_peer(link, read, address, config, remote, port, run).await
+}
+
+//dg_fast_harvester connections over QUIC, on the port of the websocket listener
+#[cfg(feature = "quic")]
+async fn serve_quic(
+    endpoint: quinn::Endpoint,
+    config: Arc<Config>,
+    remote: Arc<RemoteHarvesters>,
+    run: Arc<AtomicBool>,
+) {
+    let port = endpoint.local_addr().map(|a| a.port()).unwrap_or_default();
+    while run.load(Ordering::Relaxed) {
+        let connecting = select! {
+            connecting = endpoint.accept() => match connecting {
+                Some(connecting) => connecting,
+                None => break,
+            },
+            _ = sleep(Duration::from_secs(1)) => continue,
+        };
+        let config = config.clone();
+        let remote = remote.clone();
+        let run = run.clone();
+        tokio::spawn(async move {
+            let address = connecting.remote_address();
+            if let Err(e) = handle_quic_harvester(connecting, &config, remote, port, run).await {
+                warn!("Harvester connection from {address} failed: {:?}", e);
+            }
+        });
+    }
+    endpoint.close(0u32.into(), b"");
+}
+
+#[cfg(feature = "quic")]
+async fn handle_quic_harvester(
+    connecting: quinn::Connecting,
+    config: &Config,
+    remote: Arc<RemoteHarvesters>,
+    port: u16,
+    run: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    //The harvester opens the stream with its handshake
+    let (connection, send, recv) = timeout(HANDSHAKE_TIMEOUT, async {
+        let connection = connecting.await.map_err(Error::other)?;
+        let (send, recv) = connection.accept_bi().await.map_err(Error::other)?;
+        Ok::<_, Error>((connection, send, recv))
+    })
+    .await
+    .map_err(|_| Error::new(ErrorKind::TimedOut, "Timed out in the QUIC handshake"))??;
+    let (link, read) = quic_link(send, recv);
+    let address = connection.remote_address();
+    let result = run_peer(link, read, address, config, remote, port, run).await;
+    connection.close(0u32.into(), b"");
+    result
+}
+
+async fn run_peer(
+    link: LinkWriter,
+    mut read: LinkReader,
+    address: SocketAddr,
+    config: &Config,
+    remote: Arc<RemoteHarvesters>,
+    port: u16,
+    run: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    let handshake = match timeout(HANDSHAKE_TIMEOUT, read_message(&mut read)).await {
+        Ok(Some(Ok(msg))) => {
+            if msg.msg_type != ProtocolMessageTypes::Handshake {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Expected a handshake, got {:?}", msg.msg_type),
+                ));
+            }
+            Handshake::from_bytes(&mut Cursor::new(&msg.data))?
+        }
+        _ => return Err(Error::new(ErrorKind::InvalidData, "No handshake received")),
+    };
+    if handshake.network_id != config.selected_network {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Harvester is on network {}, expected {}",
+                handshake.network_id, config.selected_network
+            ),
+        ));
+    }
+    if NodeType::from(handshake.node_type) != NodeType::Harvester {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Node type {} is not a harvester", handshake.node_type),
+        ));
+    }
+    //Everything dg_fast_harvester asks for is accepted, chia harvesters ask for nothing
+    let options = LinkOptions {
+        batching: true,
+        compression: true,
+    }
+    .negotiate(&handshake.capabilities);
+    link.send(ChiaMessage::new(
+        ProtocolMessageTypes::Handshake,
+        &Handshake {
+            network_id: config.selected_network.clone(),
+            protocol_version: PROTOCOL_VERSION.to_string(),
+            software_version: SOFTWARE_VERSION.to_string(),
+            server_port: port,
+            node_type: NodeType::Farmer as u8,
+            capabilities: options.capabilities(),
+        },
+        None,
+    ))
+    .await?;
+    let (link, mut read) = upgrade_link(link, read, options);
+    let peer = Arc::new(HarvesterPeer {
+        id: Uuid::new_v4(),
+        address,
+        link,
+        pending: Default::default(),
+        next_id: AtomicU16::new(0),
+    });
+    let (farmer_public_keys, pool_public_keys) = config.public_keys();
+    peer.send(ChiaMessage::new(
+        ProtocolMessageTypes::HarvesterHandshake,
+        &HarvesterHandshake {
+            farmer_public_keys,
+            pool_public_keys,
+        },
+        None,
+    ))
+    .await?;
+    info!(
+        "Harvester {address} connected, running {} with {:?}",
+        handshake.software_version, options
+    );
+    remote.connected(peer.clone()).await;
+    let result = read_messages(&peer, &mut read, &remote, &run).await;
+    remote.disconnected(peer.id).await;
+    info!("Harvester {address} disconnected");
+    result
+}
+
+async fn read_messages(
+    peer: &HarvesterPeer,
+    read: &mut LinkReader,
+    remote: &RemoteHarvesters,
+    run: &AtomicBool,
+) -> Result<(), Error> {
+    while run.load(Ordering::Relaxed) {
+        let received = select! {
+            frame = read.next() => frame,
+            _ = sleep(Duration::from_secs(1)) => continue,
+        };
+        let frame = match received {
+            Some(Ok(frame)) => frame,
+            Some(Err(e)) => return Err(e),
+            None => return Ok(()),
+        };
+        let msg = match ChiaMessage::from_bytes(&mut Cursor::new(frame)) {
+            Ok(msg) => msg,
+            Err(e) => {
+                warn!("Invalid message from harvester {}: {:?}", peer.address, e);
+                continue;
+            }
+        };
+        if let Err(e) = handle_message(peer, msg, remote).await {
+            warn!(
+                "Failed to handle message from harvester {}: {:?}",
+                peer.address, e
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn handle_message(
+    peer: &HarvesterPeer,
+    msg: ChiaMessage,
+    remote: &RemoteHarvesters,
+) -> Result<(), Error> {
+    let mut cursor = Cursor::new(&msg.data);
+    match msg.msg_type {
+        ProtocolMessageTypes::NewProofOfSpace => {
+            let new_pos = NewProofOfSpace::from_bytes(&mut cursor)?;
+            debug!(
+                "Proof for {} from harvester {}",
+                new_pos.plot_identifier, peer.address
+            );
+            remote.proof_found(peer.id, new_pos).await;
+        }
+        ProtocolMessageTypes::RespondSignatures => {
+            let response = RespondSignatures::from_bytes(&mut cursor)?;
+            let pending = match msg.id {
+                Some(id) => peer.pending.lock().await.remove(&id),
+                None => None,
+            };
+            match pending {
+                Some(sender) => {
+                    let _ = sender.send(response);
+                }
+                None => debug!(
+                    "Dropping late signatures for {} from harvester {}",
+                    response.plot_identifier, peer.address
+                ),
+            }
+        }
+        //Plots are not tracked per harvester, the sync is only acknowledged so chia harvesters
+        //do not restart it. Every sync message starts with its identifier
+        ProtocolMessageTypes::PlotSyncStart
+        | ProtocolMessageTypes::PlotSyncLoaded
+        | ProtocolMessageTypes::PlotSyncRemoved
+        | ProtocolMessageTypes::PlotSyncInvalid
+        | ProtocolMessageTypes::PlotSyncKeysMissing
+        | ProtocolMessageTypes::PlotSyncDuplicates
+        | ProtocolMessageTypes::PlotSyncDone => {
+            let identifier = PlotSyncIdentifier::from_bytes(&mut cursor)?;
+            peer.send(ChiaMessage::new(
+                ProtocolMessageTypes::PlotSyncResponse,
+                &PlotSyncResponse {
+                    identifier,
+                    message_type: msg.msg_type as u8 as i16,
+                    error: None,
+                },
+                msg.id,
+            ))
+            .await?;
+        }
+        other => debug!("Ignoring {:?} from harvester {}", other, peer.address),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::dns::get_client_tls;
+    use crate::farmer::test_fixtures::proof_of_space;
+    use dg_xch_clients::websocket::{
+        oneshot as ws_oneshot, ChiaMessageFilter, ChiaMessageHandler, ClientSSLConfig,
+        MessageHandler, Websocket,
+    };
+    use dg_xch_core::blockchain::sized_bytes::Bytes32;
+    use std::net::Ipv4Addr;
+
+    struct Forward(tokio::sync::mpsc::Sender<Arc<ChiaMessage>>);
+    #[async_trait]
+    impl MessageHandler for Forward {
+        async fn handle(&self, msg: Arc<ChiaMessage>) -> Result<(), Error> {
+            let _ = self.0.send(msg).await;
+            Ok(())
+        }
+    }
+
+    struct Collect(tokio::sync::mpsc::Sender<NewProofOfSpace>);
+    #[async_trait]
+    impl ProofHandler for Collect {
+        async fn handle_proof(&self, new_pos: NewProofOfSpace) -> Result<(), Error> {
+            let _ = self.0.send(new_pos).await;
+            Ok(())
+        }
+    }
+
+    struct Keep(tokio::sync::mpsc::Sender<RespondSignatures>);
+    #[async_trait]
+    impl SignatureHandler for Keep {
+        async fn handle_signature(&self, response: RespondSignatures) -> Result<(), Error> {
+            let _ = self.0.send(response).await;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn routes_signage_points_and_proofs_through_connected_harvesters() {
+        let ssl_path = std::env::temp_dir().join(format!("ff-harvester-server-{}", Uuid::new_v4()));
+        create_all_ssl(&ssl_path, false).unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(tls_config(&ssl_path).unwrap()));
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let config = Arc::new(Config::default());
+        let remote = Arc::new(RemoteHarvesters::new(Uuid::new_v4()));
+        let run = Arc::new(AtomicBool::new(true));
+        tokio::spawn(serve(
+            listener,
+            acceptor,
+            config.clone(),
+            remote.clone(),
+            run.clone(),
+        ));
+
+        let (client, mut stream) = get_client_tls(
+            &None,
+            "localhost",
+            port,
+            ClientSSLConfig {
+                ssl_crt_path: &ssl_path
+                    .join("harvester/private_harvester.crt")
+                    .to_string_lossy(),
+                ssl_key_path: &ssl_path
+                    .join("harvester/private_harvester.key")
+                    .to_string_lossy(),
+                ssl_ca_crt_path: &ssl_path.join(CA_PRIVATE_CRT).to_string_lossy(),
+            },
+        )
+        .await
+        .unwrap();
+        let client = Arc::new(Mutex::new(client));
+        let (sender, mut received) = tokio::sync::mpsc::channel(8);
+        for msg_type in [
+            ProtocolMessageTypes::HarvesterHandshake,
+            ProtocolMessageTypes::NewSignagePointHarvester,
+            ProtocolMessageTypes::RequestSignatures,
+        ] {
+            client
+                .lock()
+                .await
+                .subscribe(
+                    Uuid::new_v4(),
+                    ChiaMessageHandler::new(
+                        ChiaMessageFilter {
+                            msg_type: Some(msg_type),
+                            id: None,
+                        },
+                        Arc::new(Forward(sender.clone())),
+                    ),
+                )
+                .await;
+        }
+        let client_run = run.clone();
+        tokio::spawn(async move { stream.run(client_run).await });
+        let farmer_handshake = ws_oneshot::<Handshake, _>(
+            client.clone(),
+            ChiaMessage::new(
+                ProtocolMessageTypes::Handshake,
+                &Handshake {
+                    network_id: config.selected_network.clone(),
+                    protocol_version: PROTOCOL_VERSION.to_string(),
+                    software_version: SOFTWARE_VERSION.to_string(),
+                    server_port: port,
+                    node_type: NodeType::Harvester as u8,
+                    capabilities: vec![],
+                },
+                None,
+            ),
+            Some(ProtocolMessageTypes::Handshake),
+            None,
+            Some(5000),
+        )
+        .await
+        .unwrap();
+        assert_eq!(farmer_handshake.node_type, NodeType::Farmer as u8);
+        let keys = received.recv().await.unwrap();
+        assert_eq!(keys.msg_type, ProtocolMessageTypes::HarvesterHandshake);
+        while remote.peer_count().await == 0 {
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        let sp = Arc::new(NewSignagePointHarvester {
+            challenge_hash: Default::default(),
+            difficulty: 1,
+            sub_slot_iters: 1,
+            signage_point_index: 3,
+            sp_hash: Bytes32::from_sized_bytes([7u8; 32]),
+            pool_difficulties: vec![],
+        });
+        let (proof_sender, mut proofs) = tokio::sync::mpsc::channel(8);
+        let lookup_remote = remote.clone();
+        let lookup_sp = sp.clone();
+        tokio::spawn(async move {
+            lookup_remote
+                .new_signage_point(lookup_sp, Collect(proof_sender))
+                .await
+        });
+        let forwarded = received.recv().await.unwrap();
+        assert_eq!(
+            NewSignagePointHarvester::from_bytes(&mut Cursor::new(&forwarded.data)).unwrap(),
+            *sp
+        );
+        let new_pos = NewProofOfSpace {
+            challenge_hash: sp.challenge_hash,
+            sp_hash: sp.sp_hash,
+            plot_identifier: "plot-1".to_string(),
+            proof: proof_of_space(),
+            signage_point_index: sp.signage_point_index,
+        };
+        client
+            .lock()
+            .await
+            .send(ChiaMessage::new(ProtocolMessageTypes::NewProofOfSpace, &new_pos, None).into())
+            .await
+            .unwrap();
+        assert_eq!(proofs.recv().await.unwrap(), new_pos);
+
+        //The signature request goes to the harvester that found the proof
+        let request = RequestSignatures {
+            plot_identifier: "plot-1".to_string(),
+            challenge_hash: sp.challenge_hash,
+            sp_hash: sp.sp_hash,
+            messages: vec![],
+        };
+        let signer = remote.clone();
+        let (signature_sender, mut signatures) = tokio::sync::mpsc::channel(1);
+        let signing = tokio::spawn(async move {
+            signer
+                .request_signatures(request, Keep(signature_sender))
+                .await
+        });
+        let asked = received.recv().await.unwrap();
+        assert_eq!(asked.msg_type, ProtocolMessageTypes::RequestSignatures);
+        let response = RespondSignatures {
+            plot_identifier: "plot-1".to_string(),
+            challenge_hash: sp.challenge_hash,
+            sp_hash: sp.sp_hash,
+            local_pk: Default::default(),
+            farmer_pk: Default::default(),
+            message_signatures: vec![],
+        };
+        client
+            .lock()
+            .await
+            .send(
+                ChiaMessage::new(ProtocolMessageTypes::RespondSignatures, &response, asked.id)
+                    .into(),
+            )
+            .await
+            .unwrap();
+        signing.await.unwrap().unwrap();
+        assert_eq!(signatures.recv().await, Some(response));
+        run.store(false, Ordering::Relaxed);
+        let _ = std::fs::remove_dir_all(&ssl_path);
+    }
+
+    #[cfg(feature = "quic")]
+    #[tokio::test]
+    async fn accepts_dg_fast_harvester_over_quic() {
+        use crate::farmer::dns::client_tls_config;
+        use crate::harvesters::link::quic_connect;
+        let ssl_path = std::env::temp_dir().join(format!("ff-harvester-quic-{}", Uuid::new_v4()));
+        create_all_ssl(&ssl_path, false).unwrap();
+        let endpoint = quic_endpoint(
+            tls_config(&ssl_path).unwrap(),
+            (Ipv4Addr::LOCALHOST, 0).into(),
+            QUIC_IDLE_TIMEOUT,
+        )
+        .unwrap();
+        let port = endpoint.local_addr().unwrap().port();
+        let config = Arc::new(Config::default());
+        let remote = Arc::new(RemoteHarvesters::new(Uuid::new_v4()));
+        let run = Arc::new(AtomicBool::new(true));
+        tokio::spawn(serve_quic(
+            endpoint,
+            config.clone(),
+            remote.clone(),
+            run.clone(),
+        ));
+
+        let (crt, key, ca) = (
+            ssl_path.join("harvester/private_harvester.crt"),
+            ssl_path.join("harvester/private_harvester.key"),
+            ssl_path.join(CA_PRIVATE_CRT),
+        );
+        let tls = client_tls_config(&ClientSSLConfig {
+            ssl_crt_path: &crt.to_string_lossy(),
+            ssl_key_path: &key.to_string_lossy(),
+            ssl_ca_crt_path: &ca.to_string_lossy(),
+        })
+        .unwrap();
+        let (_connection, link, mut read) = quic_connect(&None, "localhost", port, tls, None)
+            .await
+            .unwrap();
+        let options = LinkOptions {
+            batching: true,
+            compression: true,
+        };
+        link.send(ChiaMessage::new(
+            ProtocolMessageTypes::Handshake,
+            &Handshake {
+                network_id: config.selected_network.clone(),
+                protocol_version: PROTOCOL_VERSION.to_string(),
+                software_version: SOFTWARE_VERSION.to_string(),
+                server_port: port,
+                node_type: NodeType::Harvester as u8,
+                capabilities: options.capabilities(),
+            },
+            None,
+        ))
+        .await
+        .unwrap();
+        let handshake = read_message(&mut read).await.unwrap().unwrap();
+        let handshake = Handshake::from_bytes(&mut Cursor::new(&handshake.data)).unwrap();
+        assert_eq!(handshake.node_type, NodeType::Farmer as u8);
+        //Everything after the handshakes goes in compressed batches
+        assert_eq!(options.negotiate(&handshake.capabilities), options);
+        let (_link, mut read) = upgrade_link(link, read, options);
+        let keys = read_message(&mut read).await.unwrap().unwrap();
+        assert_eq!(keys.msg_type, ProtocolMessageTypes::HarvesterHandshake);
+        while remote.peer_count().await == 0 {
+            sleep(Duration::from_millis(10)).await;
+        }
+        run.store(false, Ordering::Relaxed);
+        let _ = std::fs::remove_dir_all(&ssl_path);
+    }
+}
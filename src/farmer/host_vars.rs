@@ -0,0 +1,109 @@
+use crate::error::{FarmerError, FarmerResult};
+use log::{debug, info};
+use serde_yaml::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const HOST_VARS_KEY: &str = "host_vars";
+const DEFAULT_HOST_VARS_DIR: &str = "host_vars";
+
+pub fn host_name() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty() && h.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+}
+
+//The host_vars directory of a template, relative paths are resolved next to the config
+pub fn host_vars_dir(config_path: &Path, config: &Value) -> PathBuf {
+    let dir = config
+        .get(HOST_VARS_KEY)
+        .and_then(Value::as_str)
+        .unwrap_or(DEFAULT_HOST_VARS_DIR);
+    config_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(Path::new(dir))
+}
+
+//Merges host_vars/<host>.yaml over the template, returns the file that was merged. Hosts without
+//a file run the template as is
+pub fn apply_host_vars(
+    config_path: &Path,
+    config: &mut Value,
+    host: Option<&str>,
+) -> FarmerResult<Option<PathBuf>> {
+    let Some(host) = host else {
+        return Ok(None);
+    };
+    let file = host_vars_dir(config_path, config).join(format!("{host}.yaml"));
+    if !file.exists() {
+        debug!("No host vars at {:?}, using the template as is", file);
+        return Ok(None);
+    }
+    let overrides: Value = serde_yaml::from_str(&fs::read_to_string(&file)?)
+        .map_err(|e| FarmerError::Config(format!("{:?}: {e}", file)))?;
+    if !overrides.is_mapping() && !overrides.is_null() {
+        return Err(FarmerError::Config(format!(
+            "{:?}: host vars must be a mapping of config fields",
+            file
+        )));
+    }
+    merge(config, overrides);
+    info!("Merged host vars from {:?}", file);
+    Ok(Some(file))
+}
+
+//Mappings are merged key by key, anything else replaces the template value. A null removes the key
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                if value.is_null() {
+                    base.remove(&key);
+                } else if let Some(existing) = base.get_mut(&key) {
+                    merge(existing, value);
+                } else {
+                    base.insert(key, value);
+                }
+            }
+        }
+        (_, Value::Null) => {}
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_file_overrides_template_fields() {
+        let dir = std::env::temp_dir().join(format!("ff-host-vars-{}", std::process::id()));
+        fs::create_dir_all(dir.join("host_vars")).unwrap();
+        fs::write(
+            dir.join("host_vars/farm07.yaml"),
+            "fullnode_ws_host: node07\nharvester_configs:\n  bladebit:\n    plot_directories: [/mnt/07]\ndashboard: null\n",
+        )
+        .unwrap();
+        let mut config: Value = serde_yaml::from_str(
+            "fullnode_ws_host: node\nfullnode_ws_port: 8444\nharvester_configs:\n  bladebit:\n    plot_directories: [/mnt/a]\n    sp_deadline: 20\ndashboard:\n  bind: localhost:8080\n",
+        )
+        .unwrap();
+        let config_path = dir.join("farmer.yaml");
+        assert_eq!(
+            apply_host_vars(&config_path, &mut config.clone(), Some("other")).unwrap(),
+            None
+        );
+        let merged = apply_host_vars(&config_path, &mut config, Some("farm07")).unwrap();
+        assert_eq!(merged, Some(dir.join("host_vars/farm07.yaml")));
+        assert_eq!(config["fullnode_ws_host"], "node07");
+        assert_eq!(config["fullnode_ws_port"], 8444);
+        let bladebit = &config["harvester_configs"]["bladebit"];
+        assert_eq!(bladebit["plot_directories"][0], "/mnt/07");
+        assert_eq!(bladebit["sp_deadline"], 20);
+        assert!(config.get("dashboard").is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+//Time since the last signage point, after idle_after seconds without one the farm releases its plot
+//readers and caches and rebuilds them once signage points arrive again
+#[derive(Debug)]
+pub struct IdleState {
+    idle_after: Option<Duration>,
+    last_sp: Mutex<Instant>,
+}
+impl Default for IdleState {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+impl IdleState {
+    pub fn new(idle_after: Option<u64>) -> Self {
+        Self {
+            idle_after: idle_after.map(Duration::from_secs),
+            last_sp: Mutex::new(Instant::now()),
+        }
+    }
+    pub fn sp_received(&self) {
+        if let Ok(mut last_sp) = self.last_sp.lock() {
+            *last_sp = Instant::now();
+        }
+    }
+    pub fn is_idle(&self) -> bool {
+        self.idle_for(Instant::now()).is_some()
+    }
+    //How long signage points have been missing, None while the farm is not idle
+    pub fn idle_for(&self, now: Instant) -> Option<Duration> {
+        let idle_after = self.idle_after?;
+        let last_sp = *self.last_sp.lock().ok()?;
+        let elapsed = now.saturating_duration_since(last_sp);
+        (elapsed >= idle_after).then_some(elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idles_only_when_enabled_and_signage_points_stop() {
+        let idle = IdleState::new(Some(60));
+        let later = Instant::now() + Duration::from_secs(120);
+        assert_eq!(IdleState::default().idle_for(later), None);
+        assert!(!idle.is_idle());
+        assert!(idle.idle_for(later).unwrap() >= Duration::from_secs(120));
+        idle.sp_received();
+        assert_eq!(idle.idle_for(Instant::now()), None);
+    }
+}
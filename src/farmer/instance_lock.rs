@@ -0,0 +1,113 @@
+use crate::farmer::config::{Config, InstanceLockMode};
+use log::warn;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+
+//OS lock on <config>.lock, released by the OS when the process dies, so a crashed farmer never
+//blocks its replacement. The file holds the pid of the holder for the error message
+pub struct InstanceLock {
+    _file: File,
+}
+
+pub enum InstanceClaim {
+    Held(InstanceLock),
+    //Another process holds the lock, this one stands by until it is free
+    Waiting(PathBuf),
+    Unlocked,
+}
+impl InstanceClaim {
+    pub fn is_waiting(&self) -> bool {
+        matches!(self, InstanceClaim::Waiting(_))
+    }
+}
+
+pub fn lock_path(config_path: &Path) -> PathBuf {
+    config_path.with_extension("lock")
+}
+
+//None while another process holds the lock
+pub fn try_lock(path: &Path) -> Result<Option<InstanceLock>, Error> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    match file.try_lock() {
+        Ok(()) => {
+            file.set_len(0)?;
+            write!(file, "{}", std::process::id())?;
+            Ok(Some(InstanceLock { _file: file }))
+        }
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
+
+pub fn claim(config: &Config, config_path: &Path) -> Result<InstanceClaim, Error> {
+    if config.high_availability.is_some() || config.instance_lock == InstanceLockMode::Off {
+        return Ok(InstanceClaim::Unlocked);
+    }
+    let path = lock_path(config_path);
+    match try_lock(&path) {
+        Ok(Some(lock)) => Ok(InstanceClaim::Held(lock)),
+        Ok(None) => {
+            let holder = std::fs::read_to_string(&path)
+                .ok()
+                .filter(|pid| !pid.trim().is_empty())
+                .map(|pid| format!("pid {}", pid.trim()))
+                .unwrap_or_else(|| "unknown pid".to_string());
+            if config.instance_lock == InstanceLockMode::Exit {
+                return Err(Error::other(format!(
+                    "Another farmer ({holder}) is farming {}, pools penalize duplicate partials. Set instance_lock: standby to wait for it instead",
+                    config_path.display()
+                )));
+            }
+            warn!(
+                "Another farmer ({holder}) is farming {}, standing by until it stops",
+                config_path.display()
+            );
+            Ok(InstanceClaim::Waiting(path))
+        }
+        //A read only config directory, farming goes on without the guard
+        Err(e) => {
+            warn!(
+                "Failed to lock {}, a second farmer with this config would not be stopped: {e}",
+                path.display()
+            );
+            Ok(InstanceClaim::Unlocked)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn second_claim_exits_or_waits() {
+        let dir = std::env::temp_dir().join(format!("ff-instance-lock-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("farmer.yaml");
+        let mut config = Config::default();
+        let first = claim(&config, &config_path).unwrap();
+        assert!(matches!(first, InstanceClaim::Held(_)));
+        assert!(claim(&config, &config_path)
+            .err()
+            .unwrap()
+            .to_string()
+            .contains(&format!("pid {}", std::process::id())));
+        config.instance_lock = InstanceLockMode::Standby;
+        assert!(claim(&config, &config_path).unwrap().is_waiting());
+        drop(first);
+        assert!(try_lock(&lock_path(&config_path)).unwrap().is_some());
+        config.instance_lock = InstanceLockMode::Off;
+        assert!(matches!(
+            claim(&config, &config_path).unwrap(),
+            InstanceClaim::Unlocked
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,177 @@
+use crate::farmer::config::PoolWalletConfig;
+use crate::farmer::pool_auth::is_auth_error;
+use blst::min_pk::SecretKey;
+use dg_xch_clients::protocols::pool::{GetFarmerResponse, PoolError};
+use dg_xch_core::blockchain::sized_bytes::{Bytes32, Bytes48};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyCheck {
+    Owner,
+    Auth,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyProblem {
+    //None of the loaded keys is the configured owner key
+    OwnerKeyMissing,
+    //The singleton names another owner, it was transferred or its wallet recreated
+    OwnerChanged { on_chain: Bytes48 },
+    //The pool rejects requests signed with the authentication key
+    AuthRejected { error: String },
+    //The pool has another authentication key on record for the launcher
+    AuthKeyChanged { pool: Bytes48 },
+}
+impl Display for KeyProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyProblem::OwnerKeyMissing => f.write_str("owner key is not among the loaded keys"),
+            KeyProblem::OwnerChanged { on_chain } => {
+                write!(
+                    f,
+                    "singleton is owned by {on_chain} instead of the configured owner key"
+                )
+            }
+            KeyProblem::AuthRejected { error } => {
+                write!(f, "pool rejects the authentication key: {error}")
+            }
+            KeyProblem::AuthKeyChanged { pool } => {
+                write!(f, "pool has authentication key {pool} on record")
+            }
+        }
+    }
+}
+
+pub fn check_owner(
+    pool_config: &PoolWalletConfig,
+    on_chain: Bytes48,
+    owner_keys: &HashMap<Bytes48, SecretKey>,
+) -> Option<KeyProblem> {
+    if on_chain != pool_config.owner_public_key {
+        Some(KeyProblem::OwnerChanged { on_chain })
+    } else if !owner_keys.contains_key(&pool_config.owner_public_key) {
+        Some(KeyProblem::OwnerKeyMissing)
+    } else {
+        None
+    }
+}
+
+//Err for pool errors that say nothing about the key, like an unreachable pool
+pub fn check_auth(
+    auth_sk: &SecretKey,
+    response: Result<GetFarmerResponse, PoolError>,
+) -> Result<Option<KeyProblem>, PoolError> {
+    match response {
+        Ok(farmer) => {
+            let local: Bytes48 = auth_sk.sk_to_pk().to_bytes().into();
+            Ok(
+                (farmer.authentication_public_key != local).then_some(KeyProblem::AuthKeyChanged {
+                    pool: farmer.authentication_public_key,
+                }),
+            )
+        }
+        Err(e) if is_auth_error(e.error_code) => Ok(Some(KeyProblem::AuthRejected {
+            error: format!("({}) {}", e.error_code, e.error_message),
+        })),
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum KeyHealthChange {
+    Unchanged,
+    Failed(KeyProblem),
+    Recovered(KeyProblem),
+}
+
+//Last result of each check per launcher so a problem alerts once and its recovery is logged
+#[derive(Debug, Default)]
+pub struct KeyHealth {
+    problems: HashMap<(Bytes32, KeyCheck), KeyProblem>,
+}
+impl KeyHealth {
+    pub fn update(
+        &mut self,
+        launcher_id: Bytes32,
+        check: KeyCheck,
+        problem: Option<KeyProblem>,
+    ) -> KeyHealthChange {
+        let key = (launcher_id, check);
+        match problem {
+            Some(problem) if self.problems.get(&key) == Some(&problem) => {
+                KeyHealthChange::Unchanged
+            }
+            Some(problem) => {
+                self.problems.insert(key, problem.clone());
+                KeyHealthChange::Failed(problem)
+            }
+            None => self
+                .problems
+                .remove(&key)
+                .map_or(KeyHealthChange::Unchanged, KeyHealthChange::Recovered),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dg_xch_clients::protocols::pool::PoolErrorCode;
+
+    #[test]
+    fn alerts_once_per_problem_and_on_recovery() {
+        let owner = SecretKey::key_gen(&[1u8; 32], &[]).unwrap();
+        let owner_pk: Bytes48 = owner.sk_to_pk().to_bytes().into();
+        let pool_config = PoolWalletConfig {
+            owner_public_key: owner_pk,
+            ..Default::default()
+        };
+        let keys = HashMap::from([(owner_pk, owner.clone())]);
+        assert_eq!(check_owner(&pool_config, owner_pk, &keys), None);
+        assert_eq!(
+            check_owner(&pool_config, owner_pk, &HashMap::new()),
+            Some(KeyProblem::OwnerKeyMissing)
+        );
+        let moved = check_owner(&pool_config, Bytes48::default(), &keys);
+        assert_eq!(
+            moved,
+            Some(KeyProblem::OwnerChanged {
+                on_chain: Bytes48::default()
+            })
+        );
+
+        let rejected = PoolError {
+            error_code: PoolErrorCode::InvalidSignature as u8,
+            error_message: "bad".to_string(),
+        };
+        assert!(matches!(
+            check_auth(&owner, Err(rejected)),
+            Ok(Some(KeyProblem::AuthRejected { .. }))
+        ));
+        let down = PoolError {
+            error_code: PoolErrorCode::ServerException as u8,
+            error_message: String::new(),
+        };
+        assert!(check_auth(&owner, Err(down)).is_err());
+
+        let mut health = KeyHealth::default();
+        let launcher = Bytes32::default();
+        assert_eq!(
+            health.update(launcher, KeyCheck::Owner, moved.clone()),
+            KeyHealthChange::Failed(moved.clone().unwrap())
+        );
+        assert_eq!(
+            health.update(launcher, KeyCheck::Owner, moved.clone()),
+            KeyHealthChange::Unchanged
+        );
+        assert_eq!(
+            health.update(launcher, KeyCheck::Auth, None),
+            KeyHealthChange::Unchanged
+        );
+        assert_eq!(
+            health.update(launcher, KeyCheck::Owner, None),
+            KeyHealthChange::Recovered(moved.unwrap())
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use crate::error::{FarmerError, FarmerResult};
+use crate::farmer::config::{Config, FarmingInfo, PoolWalletConfig};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bip39::Mnemonic;
+use blst::min_pk::{PublicKey, SecretKey};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use dg_xch_core::blockchain::sized_bytes::Bytes48;
+use dg_xch_keys::{
+    fingerprint, key_from_mnemonic, master_sk_to_farmer_sk, master_sk_to_pool_sk,
+    master_sk_to_pooling_authentication_sk, master_sk_to_singleton_owner_sk,
+};
+use home::home_dir;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+//Chia encrypts with this passphrase until the user runs `chia passphrase set`
+const DEFAULT_PASSPHRASE: &str = "$ chia passphrase set # all the cool kids are doing it!";
+const CHECKBYTES: &[u8] = b"5f365b8292ee505b";
+const HASH_ITERS: u32 = 100_000;
+const PUBLIC_KEY_LENGTH: usize = 48;
+const OWNER_KEY_SEARCH_DEPTH: u32 = 150;
+pub const PASSPHRASE_ENV: &str = "FF_KEYRING_PASSPHRASE";
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyringSource {
+    pub fingerprint: u32,
+    //Defaults to keyring.yaml in CHIA_KEYS_ROOT or ~/.chia_keys
+    #[serde(default)]
+    pub keyring_path: Option<String>,
+    #[serde(default)]
+    pub passphrase_file: Option<String>,
+}
+impl KeyringSource {
+    pub fn path(&self) -> PathBuf {
+        match &self.keyring_path {
+            Some(path) => PathBuf::from(path),
+            None => std::env::var("CHIA_KEYS_ROOT")
+                .map(PathBuf::from)
+                .unwrap_or_else(|_| {
+                    home_dir()
+                        .unwrap_or_else(|| PathBuf::from("/"))
+                        .join(".chia_keys")
+                })
+                .join("keyring.yaml"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyringFile {
+    salt: String,
+    nonce: String,
+    data: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct KeyringData {
+    #[serde(default)]
+    keys: HashMap<String, HashMap<String, String>>,
+}
+
+//None when the passphrase does not open the keyring
+fn decrypt(file: &KeyringFile, passphrase: &str) -> FarmerResult<Option<Vec<u8>>> {
+    let salt = hex::decode(file.salt.trim())
+        .map_err(|e| FarmerError::Config(format!("Invalid keyring salt: {e}")))?;
+    let nonce = hex::decode(file.nonce.trim())
+        .map_err(|e| FarmerError::Config(format!("Invalid keyring nonce: {e}")))?;
+    if nonce.len() != 12 {
+        return Err(FarmerError::Config(format!(
+            "Invalid keyring nonce length {}",
+            nonce.len()
+        )));
+    }
+    let data = STANDARD
+        .decode(file.data.split_whitespace().collect::<String>())
+        .map_err(|e| FarmerError::Config(format!("Invalid keyring data: {e}")))?;
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), &salt, HASH_ITERS, &mut key);
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+    Ok(cipher
+        .decrypt(Nonce::from_slice(&nonce), data.as_slice())
+        .ok()
+        .filter(|plaintext| plaintext.starts_with(CHECKBYTES))
+        .map(|plaintext| plaintext[CHECKBYTES.len()..].to_vec()))
+}
+
+fn read_passphrase(source: &KeyringSource, path: &Path) -> FarmerResult<String> {
+    if let Some(file) = &source.passphrase_file {
+        return fs::read_to_string(file)
+            .map(|s| s.trim_end_matches(['\r', '\n']).to_string())
+            .map_err(|e| {
+                FarmerError::Config(format!("Failed to read passphrase file {file}: {e}"))
+            });
+    }
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    dialoguer::Password::new()
+        .with_prompt(format!("Passphrase for keyring {:?}", path))
+        .interact()
+        .map_err(|e| FarmerError::Config(format!("Failed to read keyring passphrase: {e}")))
+}
+
+//Entries are the hex of the master public key followed by the mnemonic entropy
+fn find_master_key(data: &KeyringData, wanted: u32) -> FarmerResult<Option<SecretKey>> {
+    for value in data.keys.values().flat_map(|users| users.values()) {
+        let Ok(bytes) = hex::decode(value) else {
+            continue;
+        };
+        if bytes.len() < PUBLIC_KEY_LENGTH {
+            continue;
+        }
+        let Ok(public_key) = PublicKey::from_bytes(&bytes[..PUBLIC_KEY_LENGTH]) else {
+            continue;
+        };
+        if fingerprint(&public_key) != wanted {
+            continue;
+        }
+        if bytes.len() == PUBLIC_KEY_LENGTH {
+            return Err(FarmerError::Key(format!(
+                "Key {wanted} in the keyring is an observer key without a secret"
+            )));
+        }
+        let mnemonic = Mnemonic::from_entropy(&bytes[PUBLIC_KEY_LENGTH..])
+            .map_err(|e| FarmerError::Key(format!("Invalid entropy for key {wanted}: {e}")))?;
+        return Ok(Some(key_from_mnemonic(&mnemonic.to_string())?));
+    }
+    Ok(None)
+}
+
+pub fn load_master_key(source: &KeyringSource) -> FarmerResult<SecretKey> {
+    let path = source.path();
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| FarmerError::Config(format!("Failed to read keyring {:?}: {e}", path)))?;
+    let file: KeyringFile = serde_yaml::from_str(&contents)
+        .map_err(|e| FarmerError::Config(format!("Invalid keyring {:?}: {e}", path)))?;
+    let plaintext = match decrypt(&file, DEFAULT_PASSPHRASE)? {
+        Some(plaintext) => plaintext,
+        None => decrypt(&file, &read_passphrase(source, &path)?)?
+            .ok_or_else(|| FarmerError::Key(format!("Wrong passphrase for keyring {:?}", path)))?,
+    };
+    let data: KeyringData = serde_yaml::from_slice(&plaintext)
+        .map_err(|e| FarmerError::Config(format!("Invalid keyring contents: {e}")))?;
+    find_master_key(&data, source.fingerprint)?.ok_or_else(|| {
+        FarmerError::Key(format!(
+            "Fingerprint {} not found in keyring {:?}",
+            source.fingerprint, path
+        ))
+    })
+}
+
+fn apply_master_key(
+    info: &mut FarmingInfo,
+    master_key: &SecretKey,
+    pool_info: &[PoolWalletConfig],
+) -> FarmerResult<()> {
+    info.farmer_secret_key = master_sk_to_farmer_sk(master_key)?.into();
+    info.pool_secret_key = Some(master_sk_to_pool_sk(master_key)?.into());
+    let Some(launcher_id) = info.launcher_id else {
+        return Ok(());
+    };
+    let Some(pool) = pool_info.iter().find(|p| p.launcher_id == launcher_id) else {
+        return Ok(());
+    };
+    for i in 0..OWNER_KEY_SEARCH_DEPTH {
+        let owner_key = master_sk_to_singleton_owner_sk(master_key, i)?;
+        let owner_public_key: Bytes48 = owner_key.sk_to_pk().to_bytes().into();
+        if owner_public_key == pool.owner_public_key {
+            info.owner_secret_key = Some(owner_key.into());
+            info.auth_secret_key =
+                Some(master_sk_to_pooling_authentication_sk(master_key, i, 0)?.into());
+            return Ok(());
+        }
+    }
+    warn!(
+        "No owner key for launcher {} found in keyring key {}",
+        launcher_id,
+        fingerprint(&master_key.sk_to_pk())
+    );
+    Ok(())
+}
+
+//Fills the secret keys of every farmer_info with a keyring source, each fingerprint is unlocked once
+pub fn resolve_keyring_keys(config: &mut Config) -> FarmerResult<()> {
+    let mut master_keys: HashMap<u32, SecretKey> = HashMap::new();
+    let mut resolve = |farmer_info: &mut Vec<FarmingInfo>, pool_info: &[PoolWalletConfig]| {
+        for info in farmer_info.iter_mut() {
+            let Some(source) = info.keyring.clone() else {
+                continue;
+            };
+            let master_key = match master_keys.get(&source.fingerprint) {
+                Some(key) => key.clone(),
+                None => {
+                    let key = load_master_key(&source)?;
+                    info!("Loaded key {} from the keyring", source.fingerprint);
+                    master_keys.insert(source.fingerprint, key.clone());
+                    key
+                }
+            };
+            apply_master_key(info, &master_key, pool_info)?;
+        }
+        Ok::<(), FarmerError>(())
+    };
+    resolve(&mut config.farmer_info, &config.pool_info)?;
+    for farm in config.farms.iter_mut() {
+        resolve(&mut farm.farmer_info, &farm.pool_info)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyring_file(passphrase: &str, data: &str) -> KeyringFile {
+        let salt = [3u8; 16];
+        let nonce = [5u8; 12];
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), &salt, HASH_ITERS, &mut key);
+        let plaintext = [CHECKBYTES, data.as_bytes()].concat();
+        let encrypted = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+            .unwrap();
+        KeyringFile {
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            data: STANDARD.encode(encrypted),
+        }
+    }
+
+    #[test]
+    fn finds_key_by_fingerprint() {
+        let entropy = [7u8; 32];
+        let mnemonic = Mnemonic::from_entropy(&entropy).unwrap();
+        let master_key = key_from_mnemonic(&mnemonic.to_string()).unwrap();
+        let public_key = master_key.sk_to_pk();
+        let data = format!(
+            "keys:\n  chia-user-chia-1.8:\n    wallet-user-chia-1.8-0: {}{}\n",
+            hex::encode(public_key.to_bytes()),
+            hex::encode(entropy)
+        );
+        let file = keyring_file("hunter2", &data);
+        assert_eq!(decrypt(&file, DEFAULT_PASSPHRASE).unwrap(), None);
+        let plaintext = decrypt(&file, "hunter2").unwrap().unwrap();
+        let data: KeyringData = serde_yaml::from_slice(&plaintext).unwrap();
+        let found = find_master_key(&data, fingerprint(&public_key)).unwrap();
+        assert_eq!(found.map(|k| k.to_bytes()), Some(master_key.to_bytes()));
+        assert!(find_master_key(&data, 1).unwrap().is_none());
+    }
+}
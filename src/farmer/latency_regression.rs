@@ -0,0 +1,216 @@
+use crate::error::FarmerResult;
+use crate::farmer::config::LatencyRegressionConfig;
+use crate::farmer::events::FarmerEventKind;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const SAVE_INTERVAL: u64 = 300;
+//Upper bounds of the histogram buckets in ms, slower samples go to an overflow bucket
+const BUCKET_BOUNDS_MS: [u64; 24] = [
+    1, 2, 5, 10, 20, 35, 50, 75, 100, 150, 200, 300, 500, 750, 1000, 1500, 2000, 3000, 5000, 7500,
+    10000, 15000, 20000, 30000,
+];
+const OVERFLOW_MS: u64 = 60000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LatencyKind {
+    //Slowest lookup of a plot directory per signage point
+    Lookup,
+    //Partial submission round trip to a pool
+    Submission,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Histogram {
+    counts: Vec<u64>,
+}
+impl Histogram {
+    fn record(&mut self, ms: u64) {
+        if self.counts.len() <= BUCKET_BOUNDS_MS.len() {
+            self.counts.resize(BUCKET_BOUNDS_MS.len() + 1, 0);
+        }
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|bound| ms <= *bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.counts[bucket] += 1;
+    }
+    fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+    //Upper bound of the bucket holding the 95th percentile
+    fn p95_ms(&self) -> u64 {
+        let rank = (self.total() * 95).div_ceil(100);
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return BUCKET_BOUNDS_MS.get(bucket).copied().unwrap_or(OVERFLOW_MS);
+            }
+        }
+        0
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct LatencyWindow {
+    started: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    baseline: Option<Histogram>,
+    current: Histogram,
+    reported: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct StoredWindows {
+    #[serde(default)]
+    lookups: BTreeMap<String, LatencyWindow>,
+    #[serde(default)]
+    submissions: BTreeMap<String, LatencyWindow>,
+}
+
+//Latency distribution of each plot directory and pool for the current window and the baseline it
+//is compared against. A window that regressed does not become the baseline, so a drive or ISP
+//that got slow keeps being reported each window until it recovers
+#[derive(Debug, Default)]
+pub struct LatencyBaselines {
+    config: LatencyRegressionConfig,
+    path: Option<PathBuf>,
+    windows: StoredWindows,
+    last_save: u64,
+}
+impl LatencyBaselines {
+    pub fn load(path: &Path, config: LatencyRegressionConfig) -> Self {
+        let windows = match fs::read_to_string(path).map(|s| serde_json::from_str(&s)) {
+            Ok(Ok(windows)) => windows,
+            Ok(Err(e)) => {
+                warn!("Ignoring invalid latency baselines at {:?}: {:?}", path, e);
+                StoredWindows::default()
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => StoredWindows::default(),
+            Err(e) => {
+                warn!("Failed to read latency baselines at {:?}: {:?}", path, e);
+                StoredWindows::default()
+            }
+        };
+        Self {
+            config,
+            path: Some(path.to_path_buf()),
+            windows,
+            last_save: 0,
+        }
+    }
+    //The regression event the first time the current window's p95 exceeds the baseline's by
+    //factor_percent
+    pub fn record(
+        &mut self,
+        kind: LatencyKind,
+        target: &str,
+        latency: Duration,
+        now: u64,
+    ) -> Option<FarmerEventKind> {
+        let windows = match kind {
+            LatencyKind::Lookup => &mut self.windows.lookups,
+            LatencyKind::Submission => &mut self.windows.submissions,
+        };
+        let window = windows
+            .entry(target.to_string())
+            .or_insert_with(|| LatencyWindow {
+                started: now,
+                ..Default::default()
+            });
+        if now >= window.started + self.config.window {
+            let finished = std::mem::take(&mut window.current);
+            if !window.reported && finished.total() >= self.config.min_samples {
+                window.baseline = Some(finished);
+            }
+            window.started = now;
+            window.reported = false;
+        }
+        window.current.record(latency.as_millis() as u64);
+        let regression = match &window.baseline {
+            Some(baseline)
+                if self.config.factor_percent > 0
+                    && !window.reported
+                    && window.current.total() >= self.config.min_samples =>
+            {
+                let (baseline_p95_ms, current_p95_ms) =
+                    (baseline.p95_ms(), window.current.p95_ms());
+                (current_p95_ms * 100 > baseline_p95_ms * self.config.factor_percent as u64)
+                    .then_some(FarmerEventKind::LatencyRegression {
+                        kind,
+                        target: target.to_string(),
+                        baseline_p95_ms,
+                        current_p95_ms,
+                    })
+            }
+            _ => None,
+        };
+        window.reported |= regression.is_some();
+        if now >= self.last_save + SAVE_INTERVAL {
+            self.last_save = now;
+            if let Err(e) = self.save() {
+                warn!("Failed to save latency baselines: {:?}", e);
+            }
+        }
+        regression
+    }
+    pub fn save(&self) -> FarmerResult<()> {
+        if let Some(path) = &self.path {
+            Ok(fs::write(path, serde_json::to_string(&self.windows)?)?)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_a_worse_p95_once_per_window() {
+        let mut baselines = LatencyBaselines {
+            config: LatencyRegressionConfig {
+                window: 100,
+                factor_percent: 150,
+                min_samples: 20,
+            },
+            ..Default::default()
+        };
+        let dir = "/mnt/usb";
+        let mut record = |ms: u64, now: u64| {
+            baselines.record(LatencyKind::Lookup, dir, Duration::from_millis(ms), now)
+        };
+        for i in 0..20 {
+            assert_eq!(record(if i == 0 { 900 } else { 180 }, i), None);
+        }
+        //Nothing to compare against in the first window
+        for i in 0..20 {
+            assert_eq!(record(180, 100 + i), None);
+        }
+        let mut events = vec![];
+        for i in 0..20 {
+            events.extend(record(if i < 2 { 180 } else { 700 }, 200 + i));
+        }
+        assert_eq!(
+            events,
+            vec![FarmerEventKind::LatencyRegression {
+                kind: LatencyKind::Lookup,
+                target: dir.to_string(),
+                baseline_p95_ms: 200,
+                current_p95_ms: 750,
+            }]
+        );
+        //The regressed window is not the new baseline, the same latency is reported again
+        let again: Vec<_> = (0..20).filter_map(|i| record(700, 300 + i)).collect();
+        assert_eq!(again.len(), 1);
+        assert!(baselines.windows.submissions.is_empty());
+    }
+}
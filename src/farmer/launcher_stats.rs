@@ -0,0 +1,165 @@
+use crate::farmer::earnings::EstimatedEarnings;
+use crate::farmer::events::{FarmerEvent, FarmerEventKind};
+use crate::farmer::pool_points::PoolPointsSample;
+use dg_xch_clients::protocols::pool::PoolErrorCode;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+//Results of one plot NFT, so a multi NFT farm can tell which one falls behind
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LauncherStats {
+    pub pool_host: String,
+    pub proofs_declared: u64,
+    pub partials_submitted: u64,
+    pub partials_accepted: u64,
+    pub partials_rejected: u64,
+    pub partials_stale: u64,
+    //Not sent because the pool window had passed, partials_stale are the ones the pool rejected
+    #[serde(default)]
+    pub partials_stale_local: u64,
+    #[serde(default)]
+    pub partials_suppressed: u64,
+    //Not sent because the same proof was already sent
+    #[serde(default)]
+    pub partials_duplicate: u64,
+    //Requests and partials the pool rejected for their signature or authentication token
+    #[serde(default)]
+    pub auth_failures: u64,
+    #[serde(default)]
+    pub difficulty: Option<u64>,
+    //Locally accepted and pool credited points over the pool points history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub points_local: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub points_credited: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pool_points: Vec<PoolPointsSample>,
+    //Fraction the pool keeps, from its pool info
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_fee: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub earnings: Option<EstimatedEarnings>,
+}
+
+//Keyed by the launcher id in hex, Bytes32 has no ordering
+pub type LauncherStatsMap = BTreeMap<String, LauncherStats>;
+
+pub fn record_launcher_event(stats: &mut LauncherStatsMap, event: &FarmerEvent) {
+    match &event.kind {
+        FarmerEventKind::ProofDeclared {
+            launcher_id: Some(launcher_id),
+            ..
+        } => {
+            stats
+                .entry(launcher_id.to_string())
+                .or_default()
+                .proofs_declared += 1
+        }
+        FarmerEventKind::PartialSubmitted {
+            launcher_id,
+            pool_url,
+            ..
+        } => entry(stats, launcher_id, pool_url).partials_submitted += 1,
+        FarmerEventKind::PartialAccepted {
+            launcher_id,
+            pool_url,
+            new_difficulty,
+            ..
+        } => {
+            let launcher = entry(stats, launcher_id, pool_url);
+            launcher.partials_accepted += 1;
+            launcher.difficulty = Some(*new_difficulty);
+        }
+        FarmerEventKind::PartialRejected {
+            launcher_id,
+            pool_url,
+            error_code,
+            ..
+        } => {
+            let launcher = entry(stats, launcher_id, pool_url);
+            launcher.partials_rejected += 1;
+            if *error_code == PoolErrorCode::TooLate as u8 {
+                launcher.partials_stale += 1;
+            }
+        }
+        FarmerEventKind::PartialStaleLocal {
+            launcher_id,
+            pool_url,
+            ..
+        } => entry(stats, launcher_id, pool_url).partials_stale_local += 1,
+        FarmerEventKind::PartialDuplicate {
+            launcher_id,
+            pool_url,
+            ..
+        } => entry(stats, launcher_id, pool_url).partials_duplicate += 1,
+        _ => {}
+    }
+}
+
+fn entry<'a>(
+    stats: &'a mut LauncherStatsMap,
+    launcher_id: &Bytes32,
+    pool_url: &str,
+) -> &'a mut LauncherStats {
+    let launcher = stats.entry(launcher_id.to_string()).or_default();
+    launcher.pool_host = pool_host(pool_url);
+    launcher
+}
+
+//The host is enough to tell pools apart without repeating paths in every label
+pub fn pool_host(pool_url: &str) -> String {
+    Url::parse(pool_url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_string()))
+        .unwrap_or_else(|| pool_url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_results_per_launcher() {
+        let (a, b) = (Bytes32::default(), Bytes32::from_sized_bytes([1u8; 32]));
+        let mut stats = LauncherStatsMap::new();
+        let events = [
+            FarmerEventKind::PartialAccepted {
+                launcher_id: a,
+                pool_url: "https://pool.example:8443/farm".to_string(),
+                new_difficulty: 20,
+                sp: None,
+            },
+            FarmerEventKind::PartialRejected {
+                launcher_id: b,
+                pool_url: "https://other.example".to_string(),
+                error_code: PoolErrorCode::TooLate as u8,
+                error_message: String::new(),
+                sp: None,
+            },
+            FarmerEventKind::PartialStaleLocal {
+                launcher_id: b,
+                pool_url: "https://other.example".to_string(),
+                sp_age_ms: 31_000,
+                sp: None,
+            },
+            FarmerEventKind::ProofDeclared {
+                challenge_hash: Bytes32::default(),
+                sp_hash: Bytes32::default(),
+                plot_identifier: String::new(),
+                sp: None,
+                launcher_id: Some(a),
+            },
+        ];
+        for kind in events {
+            record_launcher_event(&mut stats, &FarmerEvent::new(kind));
+        }
+        assert_eq!(stats[&a.to_string()].pool_host, "pool.example");
+        assert_eq!(stats[&a.to_string()].difficulty, Some(20));
+        assert_eq!(stats[&a.to_string()].proofs_declared, 1);
+        assert_eq!(stats[&b.to_string()].partials_stale, 1);
+        assert_eq!(stats[&b.to_string()].partials_stale_local, 1);
+        assert_eq!(stats[&b.to_string()].partials_accepted, 0);
+    }
+}
@@ -1,40 +1,140 @@
-use crate::farmer::config::Config;
+use crate::error::FarmerResult;
+use crate::farmer::availability::AvailabilityTracker;
+use crate::farmer::capture::{Direction, ProtocolCapture, FULL_NODE_PEER};
+use crate::farmer::clock_drift::ClockDrift;
+use crate::farmer::config::{default_payout_address, load_keys, Config, DEFAULT_FARM_NAME};
+use crate::farmer::connection::{ConnectionSettings, Endpoint, Reconnector};
+use crate::farmer::earnings::{estimate_earnings, XchPrice};
+use crate::farmer::event_history::EventHistory;
+use crate::farmer::events::{FarmerEvent, FarmerEventKind, EVENT_CHANNEL_SIZE};
+use crate::farmer::farms::{FarmIndex, FarmStats};
+use crate::farmer::harvester_server::harvester_server;
+use crate::farmer::idle::IdleState;
+use crate::farmer::keyring::resolve_keyring_keys;
+use crate::farmer::latency_regression::LatencyBaselines;
+use crate::farmer::launcher_stats::{pool_host, LauncherStatsMap};
+use crate::farmer::network_stats::NetworkStats;
+use crate::farmer::node_ssl::verify_node_certificate;
+use crate::farmer::partial_batch::PartialBatcher;
+use crate::farmer::partial_dedup::PartialDedup;
+use crate::farmer::partial_queue::PartialQueue;
+use crate::farmer::peer::FullNodePeer;
+use crate::farmer::plot_filter::PlotFilterSchedule;
+use crate::farmer::plot_leaderboard::PlotLeaderboard;
+use crate::farmer::protocol_rtt::ProtocolRtts;
 use crate::farmer::protocols::fullnode::new_signage_point::NewSignagePointHandle;
 use crate::farmer::protocols::fullnode::request_signed_values::RequestSignedValuesHandle;
+use crate::farmer::readiness::Readiness;
+use crate::farmer::rpc::RpcHealth;
+use crate::farmer::signed_values::SignedValuesCache;
+use crate::farmer::signing::{SigningStep, SigningTimings};
+use crate::farmer::sp_chain::SpChain;
+use crate::farmer::sp_digest::SpDigests;
+use crate::farmer::sp_tag::{SpArrival, SpTag};
+use crate::farmer::sp_timing::SpTiming;
+use crate::farmer::startup_report::StartupReport;
+use crate::farmer::subsystems::Subsystems;
 use crate::get_ssl_root_path;
-use crate::harvesters::{load_harvesters, Harvesters};
-use crate::tasks::pool_state_updater::FarmerPoolState;
+use crate::harvesters::dir_latency::DirLatencies;
+use crate::harvesters::drive_health::DriveHealths;
+use crate::harvesters::plot_classification::UnfarmablePlots;
+use crate::harvesters::plot_io::{IoPlot, PlotIoFile};
+use crate::harvesters::plot_registry::PlotRegistry;
+use crate::harvesters::stats::HarvesterStatsRegistry;
+use crate::harvesters::{
+    keep_remote_harvesters, load_harvesters, remote_harvesters, wait_for_plots, Harvesters,
+};
+use crate::tasks::pool_state_updater::{pool_updater, FarmerPoolState};
 use blst::min_pk::SecretKey;
 use dg_xch_clients::api::pool::PoolClient;
 use dg_xch_clients::protocols::farmer::NewSignagePoint;
 use dg_xch_clients::protocols::ProtocolMessageTypes;
-use dg_xch_clients::websocket::farmer::FarmerClient;
 use dg_xch_clients::websocket::{
-    ChiaMessageFilter, ChiaMessageHandler, ClientSSLConfig, Websocket,
+    ChiaMessage, ChiaMessageFilter, ChiaMessageHandler, ClientSSLConfig, Websocket,
 };
 use dg_xch_core::blockchain::proof_of_space::ProofOfSpace;
 use dg_xch_core::blockchain::sized_bytes::{Bytes32, Bytes48, SizedBytes};
-use dg_xch_core::consensus::constants::{CONSENSUS_CONSTANTS_MAP, MAINNET};
+use dg_xch_core::consensus::constants::{ConsensusConstants, MAINNET};
 use dg_xch_core::ssl::create_all_ssl;
-use dg_xch_pos::plots::disk_plot::DiskPlot;
+use dg_xch_keys::decode_puzzle_hash;
 use dg_xch_pos::plots::plot_reader::PlotReader;
-use dg_xch_serialize::hash_256;
-use log::{error, info};
-use std::collections::HashMap;
+use dg_xch_serialize::{hash_256, ChiaSerialize};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::io::Error;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::fs::File;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex, OwnedSemaphorePermit, Semaphore};
+use tokio_tungstenite::tungstenite::Message;
 use uuid::Uuid;
 
+pub mod availability;
+pub mod capture;
+pub mod chaos;
+pub mod clock_drift;
 pub mod config;
+pub mod config_migration;
+pub mod connection;
+pub mod discovery;
+pub mod dns;
+pub mod earnings;
+pub mod email_report;
+pub mod event_history;
+pub mod events;
+pub mod farms;
+pub mod forensics;
+pub mod harvester_server;
+pub mod host_vars;
+pub mod idle;
+pub mod instance_lock;
+pub mod key_health;
+pub mod keyring;
+pub mod latency_regression;
+pub mod launcher_stats;
+pub mod network_stats;
+pub mod node_ssl;
+pub mod partial_batch;
+pub mod partial_deadline;
+pub mod partial_dedup;
+pub mod partial_metadata;
+pub mod partial_queue;
+pub mod payout_check;
+pub mod peer;
+pub mod plot_filter;
+pub mod plot_leaderboard;
+pub mod pool_auth;
+pub mod pool_client;
+pub mod pool_difficulty;
+pub mod pool_endpoints;
+pub mod pool_info_cache;
+pub mod pool_points;
+pub mod profiling;
+pub mod protocol_rtt;
 pub mod protocols;
+pub mod readiness;
+pub mod rpc;
+pub mod signed_values;
+pub mod signing;
+pub mod smtp;
+pub mod sp_chain;
+pub mod sp_digest;
+pub mod sp_recovery;
+pub mod sp_tag;
+pub mod sp_timing;
+pub mod standby;
+pub mod startup_report;
+pub mod subsystems;
+#[cfg(test)]
+pub(crate) mod test_fixtures;
+pub mod time_format;
+pub mod units;
+pub mod webhook;
 
-type ProofsMap = Arc<Mutex<HashMap<Bytes32, Vec<(String, ProofOfSpace)>>>>;
+//Proofs by sp_hash and plot_identifier, shared with the signing path instead of cloned
 static PUBLIC_CRT: &str = "farmer/public_farmer.crt";
 static PUBLIC_KEY: &str = "farmer/public_farmer.key";
 static CA_PUBLIC_CRT: &str = "ca/chia_ca.crt";
@@ -43,51 +143,675 @@ static CA_PUBLIC_CRT: &str = "ca/chia_ca.crt";
 pub struct GuiStats {
     pub keys: Vec<Bytes48>,
     pub most_recent_sp: (Bytes32, u8),
-    pub total_plot_count: u64,
-    pub total_plot_space: u64,
     pub last_pool_update: u64,
+    pub signing_delays: BTreeMap<Uuid, SigningDelayStats>,
+    pub signing_steps: BTreeMap<SigningStep, SigningStepStats>,
+    pub farm_stats: BTreeMap<String, FarmStats>,
+    pub queued_partials: usize,
+    //Farm name to payout address, with its address book label
+    pub payout_targets: BTreeMap<String, String>,
+    pub disabled: Vec<String>,
+    //Messages a handler dropped as invalid, by handler name
+    pub handler_errors: BTreeMap<String, u64>,
+    //Proofs too weak to win the block, only their partial was sent
+    pub declarations_skipped: u64,
+    pub watch_only: WatchOnlyStats,
+    pub network: Option<NetworkStats>,
+    pub xch_price: Option<XchPrice>,
+    pub clock_drift: Option<ClockDrift>,
+}
+
+//What a watch only farm would have declared and submitted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchOnlyStats {
+    pub proofs: u64,
+    pub partials: u64,
+    pub points: u64,
+}
+
+//Time between requesting signatures from a harvester and receiving RespondSignatures
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SigningDelayStats {
+    pub count: u64,
+    pub last: Duration,
+    pub average: Duration,
+    pub max: Duration,
+    pub queued: u64,
+    pub queue_wait: Duration,
+}
+impl SigningDelayStats {
+    //Part of the delay spent waiting for a free request slot, the rest is the harvester round trip
+    pub fn record_queue_wait(&mut self, wait: Duration) {
+        self.queued += 1;
+        self.queue_wait += wait;
+    }
+    pub fn record(&mut self, delay: Duration) {
+        self.count += 1;
+        self.last = delay;
+        self.max = self.max.max(delay);
+        self.average = (self.average * (self.count - 1) as u32 + delay) / self.count as u32;
+    }
+}
+
+//Farmer side BLS and proof work per RespondSignatures, by step
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SigningStepStats {
+    pub count: u64,
+    pub total_us: u64,
+    pub max_us: u64,
+}
+impl SigningStepStats {
+    pub fn record(&mut self, duration: Duration) {
+        let us = duration.as_micros() as u64;
+        self.count += 1;
+        self.total_us += us;
+        self.max_us = self.max_us.max(us);
+    }
+    pub fn average_us(&self) -> u64 {
+        self.total_us.checked_div(self.count).unwrap_or_default()
+    }
+}
+
+//Plots passing the filter per signage point, reset by each status summary
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EligibilityStats {
+    pub signage_points: u64,
+    pub eligible_plots: u64,
+}
+impl EligibilityStats {
+    pub fn record(&mut self, eligible_plots: u64) {
+        self.signage_points += 1;
+        self.eligible_plots += eligible_plots;
+    }
+    pub fn average(&self) -> f64 {
+        if self.signage_points == 0 {
+            0.0
+        } else {
+            self.eligible_plots as f64 / self.signage_points as f64
+        }
+    }
+}
+
+//Signage points, proofs and qualities of the recent signage points, only reached through the
+//FarmerSharedState methods that keep them consistent
+#[derive(Default)]
+pub struct SpCache {
+    signage_points: Mutex<HashMap<Bytes32, Vec<NewSignagePoint>>>,
+    quality_to_identifiers: Mutex<HashMap<Bytes32, FarmerIdentifier>>,
+    proofs_of_space: Mutex<HashMap<Bytes32, HashMap<String, Arc<ProofOfSpace>>>>,
+    cache_time: Mutex<HashMap<Bytes32, Instant>>,
+    sp_arrivals: Mutex<HashMap<Bytes32, SpArrival>>,
+    declared_proofs: Mutex<HashMap<Bytes32, u32>>,
 }
 
 #[derive(Clone)]
 pub struct FarmerSharedState {
-    pub(crate) signage_points: Arc<Mutex<HashMap<Bytes32, Vec<NewSignagePoint>>>>,
-    pub(crate) quality_to_identifiers: Arc<Mutex<HashMap<Bytes32, FarmerIdentifier>>>,
-    pub(crate) proofs_of_space: ProofsMap,
-    pub(crate) cache_time: Arc<Mutex<HashMap<Bytes32, Instant>>>,
+    pub(crate) sp_cache: Arc<SpCache>,
+    pub(crate) signed_values: Arc<Mutex<SignedValuesCache>>,
     pub(crate) pool_states: Arc<Mutex<HashMap<Bytes32, FarmerPoolState>>>,
     pub(crate) farmer_private_keys: Arc<HashMap<Bytes48, SecretKey>>,
     pub(crate) owner_secret_keys: Arc<HashMap<Bytes48, SecretKey>>,
     pub(crate) auth_secret_keys: Arc<HashMap<Bytes48, SecretKey>>,
     pub(crate) pool_public_keys: Arc<HashMap<Bytes48, SecretKey>>,
     pub(crate) config: Arc<Config>,
+    pub(crate) config_path: Arc<PathBuf>,
     pub(crate) run: Arc<AtomicBool>,
+    //Cleared once a soft restart replaced this state, stops its harvesters and pool updater
+    pub(crate) active: Arc<AtomicBool>,
+    pub(crate) soft_restart: Arc<AtomicBool>,
     pub(crate) force_pool_update: Arc<AtomicBool>,
-    pub(crate) full_node_client: Arc<Mutex<Option<FarmerClient>>>,
+    pub(crate) full_node_client: Arc<Mutex<Option<FullNodePeer>>>,
     pub(crate) farmer_target: Arc<Bytes32>,
     pub(crate) pool_target: Arc<Bytes32>,
     pub(crate) gui_stats: Arc<Mutex<GuiStats>>,
+    pub(crate) farms: Arc<FarmIndex>,
     pub(crate) last_sp_timestamp: Arc<Mutex<Instant>>,
+    pub(crate) events: broadcast::Sender<FarmerEvent>,
+    pub(crate) partial_queue: Arc<Mutex<PartialQueue>>,
+    pub(crate) partial_batcher: Arc<Mutex<PartialBatcher>>,
+    pub(crate) partial_dedup: Arc<Mutex<PartialDedup>>,
+    pub(crate) eligibility_stats: Arc<Mutex<EligibilityStats>>,
+    pub(crate) constants: &'static ConsensusConstants,
+    pub(crate) rpc_health: Arc<RpcHealth>,
+    pub(crate) paused: Arc<AtomicBool>,
+    pub(crate) standby: Arc<AtomicBool>,
+    pub(crate) harvester_stats: Arc<HarvesterStatsRegistry>,
+    pub(crate) sp_digests: Arc<SpDigests>,
+    //Started with --profile, the control socket accepts profile captures
+    pub(crate) profiling: bool,
+    pub(crate) subsystems: Arc<Subsystems>,
+    pub(crate) protocol_rtts: Arc<ProtocolRtts>,
+    pub(crate) availability: Arc<Mutex<AvailabilityTracker>>,
+    pub(crate) unfarmable_plots: Arc<UnfarmablePlots>,
+    pub(crate) dir_latencies: Arc<DirLatencies>,
+    pub(crate) drive_health: Arc<DriveHealths>,
+    pub(crate) plot_registry: Arc<PlotRegistry>,
+    pub(crate) plot_leaderboard: Arc<Mutex<PlotLeaderboard>>,
+    pub(crate) latency_baselines: Arc<Mutex<LatencyBaselines>>,
+    pub(crate) event_history: Arc<EventHistory>,
+    pub(crate) readiness: Arc<Readiness>,
+    pub(crate) signature_slots: Arc<Mutex<HashMap<Uuid, Arc<Semaphore>>>>,
+    pub(crate) sp_chain: Arc<Mutex<SpChain>>,
+    pub(crate) launcher_stats: Arc<Mutex<LauncherStatsMap>>,
+    pub(crate) idle: Arc<IdleState>,
+    pub(crate) capture: Arc<ProtocolCapture>,
+    //Peak height from the full node RPC, 0 until it was read once
+    pub(crate) chain_height: Arc<AtomicU32>,
+}
+impl FarmerSharedState {
+    pub fn emit(&self, kind: FarmerEventKind) {
+        //No receivers is not an error, events are only consumed while someone is listening
+        let _ = self.events.send(FarmerEvent::new(kind));
+    }
+    pub async fn record_farm_stat<F: FnOnce(&mut FarmStats)>(&self, farm: Option<&String>, f: F) {
+        let farm = farm.map(|s| s.as_str()).unwrap_or(DEFAULT_FARM_NAME);
+        f(self
+            .gui_stats
+            .lock()
+            .await
+            .farm_stats
+            .entry(farm.to_string())
+            .or_default());
+    }
+    pub fn is_active(&self) -> bool {
+        self.run.load(Ordering::Relaxed) && self.active.load(Ordering::Relaxed)
+    }
+    //The state for a reloaded config with its keys, everything else including the full node
+    //connection and the caches is shared with this one
+    pub(crate) async fn reloaded(&self, config: Config) -> Self {
+        let config = Arc::new(config);
+        let (farmer_private_keys, owner_secret_keys, auth_secret_keys, pool_public_keys) =
+            load_keys(config.clone()).await;
+        let readiness = Readiness::check(config.as_ref());
+        readiness.log();
+        let farmer_target_encoded = default_payout_address(config.as_ref());
+        let farmer_target = decode_puzzle_hash(&farmer_target_encoded).unwrap_or_default();
+        Self {
+            farmer_private_keys: Arc::new(farmer_private_keys),
+            owner_secret_keys: Arc::new(owner_secret_keys),
+            auth_secret_keys: Arc::new(auth_secret_keys),
+            pool_public_keys: Arc::new(pool_public_keys),
+            farmer_target: Arc::new(farmer_target),
+            pool_target: Arc::new(farmer_target),
+            farms: Arc::new(FarmIndex::new(config.as_ref())),
+            readiness: Arc::new(readiness),
+            active: Arc::new(AtomicBool::new(true)),
+            config,
+            ..self.clone()
+        }
+    }
+    //Counters start over after a soft restart, plot totals come back as the harvesters load
+    pub(crate) async fn reset_stats(&self) {
+        *self.eligibility_stats.lock().await = EligibilityStats::default();
+        self.launcher_stats.lock().await.clear();
+        self.harvester_stats.reset().await;
+        let mut gui_stats = self.gui_stats.lock().await;
+        gui_stats.signing_delays.clear();
+        gui_stats.signing_steps.clear();
+        gui_stats.farm_stats.clear();
+        gui_stats.handler_errors.clear();
+        gui_stats.declarations_skipped = 0;
+        gui_stats.watch_only = WatchOnlyStats::default();
+        gui_stats.payout_targets = self
+            .config
+            .all_farms()
+            .iter()
+            .map(|f| {
+                (
+                    f.name.clone(),
+                    self.config.address_display(&f.payout_address),
+                )
+            })
+            .collect();
+        gui_stats.disabled = self
+            .readiness
+            .disabled
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+    }
+    //False without a full node connection, while replaying a capture the message is only recorded
+    pub(crate) async fn send_to_full_node<T: ChiaSerialize>(
+        &self,
+        msg_type: ProtocolMessageTypes,
+        message: &T,
+    ) -> bool {
+        if let Some(client) = self.full_node_client.lock().await.as_mut() {
+            self.capture
+                .record(Direction::Out, FULL_NODE_PEER, msg_type, message);
+            let _ = client
+                .client
+                .lock()
+                .await
+                .send(Message::Binary(
+                    ChiaMessage::new(msg_type, message, None).to_bytes(),
+                ))
+                .await;
+            true
+        } else if self.capture.is_replaying() {
+            self.capture
+                .record(Direction::Out, FULL_NODE_PEER, msg_type, message);
+            true
+        } else {
+            false
+        }
+    }
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+    //Only proofs and partials are held back, connections and stats keep running while paused
+    pub fn set_paused(&self, paused: bool) {
+        if self.paused.swap(paused, Ordering::Relaxed) == paused {
+            return;
+        }
+        if paused {
+            warn!("Farming paused, proofs and partials will not be sent until resumed");
+            self.emit(FarmerEventKind::FarmPaused {});
+        } else {
+            info!("Farming resumed");
+            self.emit(FarmerEventKind::FarmResumed {});
+        }
+    }
+    pub fn is_standby(&self) -> bool {
+        self.standby.load(Ordering::Relaxed)
+    }
+    //Like a pause, set while another instance holds the high availability lock
+    pub fn set_standby(&self, standby: bool) {
+        if self.standby.swap(standby, Ordering::Relaxed) == standby {
+            return;
+        }
+        if standby {
+            warn!("Another instance holds the farming lock, standing by");
+            self.emit(FarmerEventKind::FarmStandby {});
+        } else {
+            info!("Holding the farming lock, sending proofs and partials");
+            self.emit(FarmerEventKind::FarmActive {});
+        }
+    }
+    //Counters since startup per plot NFT, difficulty, suppressed partials and auth failures come from the pool state
+    pub async fn launcher_stats(&self) -> LauncherStatsMap {
+        let (network, xch_price) = if self.config.earnings.is_some() {
+            let gui_stats = self.gui_stats.lock().await;
+            (gui_stats.network, gui_stats.xch_price.clone())
+        } else {
+            (None, None)
+        };
+        let mut stats = self.launcher_stats.lock().await.clone();
+        for pool_state in self.pool_states.lock().await.values() {
+            let Some(pool_config) = &pool_state.pool_config else {
+                continue;
+            };
+            let launcher = stats
+                .entry(pool_config.launcher_id.to_string())
+                .or_default();
+            if launcher.pool_host.is_empty() && !pool_config.pool_url.is_empty() {
+                launcher.pool_host = pool_host(&pool_config.pool_url);
+            }
+            launcher.partials_suppressed = pool_state.suppressed_partials;
+            launcher.auth_failures = pool_state.auth.failures;
+            if pool_state.current_difficulty.is_some() {
+                launcher.difficulty = pool_state.current_difficulty;
+            }
+            if let Some((local, credited)) = pool_state.points_history.compared() {
+                launcher.points_local = Some(local);
+                launcher.points_credited = Some(credited);
+            }
+            launcher.pool_points = pool_state.points_history.samples().copied().collect();
+            launcher.pool_fee = pool_state.pool_fee;
+            if let (Some(network), Some(points_per_day), false) = (
+                &network,
+                pool_state.points_history.points_per_day(),
+                pool_state.is_solo(),
+            ) {
+                let credited_share = launcher
+                    .points_local
+                    .zip(launcher.points_credited)
+                    .filter(|(local, _)| *local > 0)
+                    .map(|(local, credited)| credited as f64 / local as f64);
+                launcher.earnings = estimate_earnings(
+                    points_per_day,
+                    credited_share,
+                    pool_state.pool_fee,
+                    network,
+                    xch_price.as_ref(),
+                    self.plot_filter_bits(network.height),
+                    self.constants,
+                );
+            }
+        }
+        stats
+    }
+    //Consensus plot filter at the height, the harvester config only changes what is looked up
+    pub fn plot_filter_bits(&self, height: u32) -> usize {
+        PlotFilterSchedule::for_network(&self.config.selected_network)
+            .prefix_bits(self.constants.number_zero_bits_plot_filter, height)
+    }
+    pub async fn sp_tag(&self, sp_hash: &Bytes32) -> Option<SpTag> {
+        self.sp_cache
+            .sp_arrivals
+            .lock()
+            .await
+            .get(sp_hash)
+            .map(|a| a.tag())
+    }
+    //Later copies of the same signage point do not move the time proofs are measured from
+    pub async fn record_signage_point(&self, sp: NewSignagePoint) {
+        let now = Instant::now();
+        let sp_hash = sp.challenge_chain_sp;
+        self.sp_cache.cache_time.lock().await.insert(sp_hash, now);
+        self.sp_cache
+            .sp_arrivals
+            .lock()
+            .await
+            .entry(sp_hash)
+            .or_insert(SpArrival {
+                challenge_hash: sp.challenge_hash,
+                signage_point_index: sp.signage_point_index,
+                arrived: now,
+            });
+        self.sp_cache
+            .signage_points
+            .lock()
+            .await
+            .entry(sp_hash)
+            .or_default()
+            .push(sp);
+    }
+    //Fills a gap only, a recovered signage point has no arrival time to measure proofs from
+    pub async fn record_recovered_signage_point(&self, sp_hash: Bytes32, sp: NewSignagePoint) {
+        self.sp_cache
+            .cache_time
+            .lock()
+            .await
+            .insert(sp_hash, Instant::now());
+        self.sp_cache
+            .signage_points
+            .lock()
+            .await
+            .entry(sp_hash)
+            .or_insert_with(|| vec![sp]);
+    }
+    pub async fn has_signage_point(&self, sp_hash: &Bytes32) -> bool {
+        self.sp_cache
+            .signage_points
+            .lock()
+            .await
+            .contains_key(sp_hash)
+    }
+    //Every copy the full node sent, cloned so handlers do not hold the lock while they verify and sign
+    pub async fn signage_points_for(&self, sp_hash: &Bytes32) -> Option<Vec<NewSignagePoint>> {
+        self.sp_cache
+            .signage_points
+            .lock()
+            .await
+            .get(sp_hash)
+            .cloned()
+    }
+    //Keeps a proof that is waiting for its signatures, until its signage point expires
+    pub async fn record_proof(
+        &self,
+        quality_string: Bytes32,
+        identifier: FarmerIdentifier,
+        proof: ProofOfSpace,
+    ) {
+        let now = Instant::now();
+        self.sp_cache
+            .proofs_of_space
+            .lock()
+            .await
+            .entry(identifier.sp_hash)
+            .or_default()
+            .insert(identifier.plot_identifier.clone(), Arc::new(proof));
+        let mut cache_time = self.sp_cache.cache_time.lock().await;
+        cache_time.insert(identifier.sp_hash, now);
+        cache_time.insert(quality_string, now);
+        drop(cache_time);
+        self.sp_cache
+            .quality_to_identifiers
+            .lock()
+            .await
+            .insert(quality_string, identifier);
+    }
+    //Not taken out, the block signatures of a proof are requested after its signage point ones
+    pub async fn proof_for(
+        &self,
+        sp_hash: &Bytes32,
+        plot_identifier: &str,
+    ) -> Option<Arc<ProofOfSpace>> {
+        self.sp_cache
+            .proofs_of_space
+            .lock()
+            .await
+            .get(sp_hash)
+            .and_then(|proofs| proofs.get(plot_identifier).cloned())
+    }
+    pub async fn identifier_for_quality(
+        &self,
+        quality_string: &Bytes32,
+    ) -> Option<FarmerIdentifier> {
+        self.sp_cache
+            .quality_to_identifiers
+            .lock()
+            .await
+            .get(quality_string)
+            .cloned()
+    }
+    //The keys are stored by their public key, so this is a lookup instead of deriving every public key
+    pub fn farmer_key_for(&self, farmer_pk: &Bytes48) -> Option<&SecretKey> {
+        self.farmer_private_keys.get(farmer_pk)
+    }
+    //Drops the signage points, proofs and qualities older than max_age
+    pub async fn evict_expired(&self, max_age: Duration) {
+        let expired = {
+            let mut cache_time = self.sp_cache.cache_time.lock().await;
+            let expired: HashSet<Bytes32> = cache_time
+                .iter()
+                .filter(|(_, v)| v.elapsed() > max_age)
+                .map(|(k, _)| *k)
+                .collect();
+            cache_time.retain(|k, _| !expired.contains(k));
+            expired
+        };
+        self.sp_cache
+            .signage_points
+            .lock()
+            .await
+            .retain(|k, _| !expired.contains(k));
+        self.sp_cache
+            .sp_arrivals
+            .lock()
+            .await
+            .retain(|k, _| !expired.contains(k));
+        self.sp_cache
+            .declared_proofs
+            .lock()
+            .await
+            .retain(|k, _| !expired.contains(k));
+        self.sp_cache
+            .quality_to_identifiers
+            .lock()
+            .await
+            .retain(|k, _| !expired.contains(k));
+        self.sp_cache
+            .proofs_of_space
+            .lock()
+            .await
+            .retain(|k, _| !expired.contains(k));
+        self.signed_values.lock().await.prune(max_age);
+    }
+    //Everything cached belongs to signage points older than the idle period, shrinking returns the
+    //memory of busy farms
+    pub async fn release_caches(&self) {
+        let mut signage_points = self.sp_cache.signage_points.lock().await;
+        signage_points.clear();
+        signage_points.shrink_to_fit();
+        drop(signage_points);
+        let mut quality_to_identifiers = self.sp_cache.quality_to_identifiers.lock().await;
+        quality_to_identifiers.clear();
+        quality_to_identifiers.shrink_to_fit();
+        drop(quality_to_identifiers);
+        let mut proofs_of_space = self.sp_cache.proofs_of_space.lock().await;
+        proofs_of_space.clear();
+        proofs_of_space.shrink_to_fit();
+        drop(proofs_of_space);
+        let mut cache_time = self.sp_cache.cache_time.lock().await;
+        cache_time.clear();
+        cache_time.shrink_to_fit();
+        drop(cache_time);
+        let mut sp_arrivals = self.sp_cache.sp_arrivals.lock().await;
+        sp_arrivals.clear();
+        sp_arrivals.shrink_to_fit();
+        drop(sp_arrivals);
+        let mut declared_proofs = self.sp_cache.declared_proofs.lock().await;
+        declared_proofs.clear();
+        declared_proofs.shrink_to_fit();
+        drop(declared_proofs);
+        self.signed_values.lock().await.clear();
+    }
+    //False once max_proofs_per_sp declarations were sent for the signage point, guards the full node against a looping harvester
+    pub async fn allow_declaration(&self, sp_hash: &Bytes32) -> bool {
+        let limit = self.config.max_proofs_per_sp;
+        let mut declared_proofs = self.sp_cache.declared_proofs.lock().await;
+        let declared = declared_proofs.entry(*sp_hash).or_default();
+        *declared += 1;
+        if limit == 0 || *declared <= limit {
+            return true;
+        }
+        if *declared == limit + 1 {
+            warn!("Reached {limit} proof declarations for signage point {sp_hash}, dropping further proofs");
+        } else {
+            debug!(
+                "Dropped proof declaration {} for signage point {sp_hash}",
+                *declared
+            );
+        }
+        false
+    }
+    //Invalid input from a peer is dropped with the context needed to debug it, the farmer keeps running
+    pub async fn record_handler_error(&self, handler: &str, message: &str) {
+        warn!("{handler} failed: {message}");
+        *self
+            .gui_stats
+            .lock()
+            .await
+            .handler_errors
+            .entry(handler.to_string())
+            .or_default() += 1;
+    }
+    //Waits for a free signature request slot of the harvester, dropping the permit frees it
+    pub async fn signature_slot(&self, harvester_id: Uuid) -> Option<OwnedSemaphorePermit> {
+        let limit = self.config.max_signature_requests;
+        if limit == 0 {
+            return None;
+        }
+        let slots = self
+            .signature_slots
+            .lock()
+            .await
+            .entry(harvester_id)
+            .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+            .clone();
+        if slots.available_permits() > 0 {
+            return slots.acquire_owned().await.ok();
+        }
+        let queued_at = Instant::now();
+        let permit = slots.acquire_owned().await.ok();
+        let wait = queued_at.elapsed();
+        debug!("Signature request to harvester {harvester_id} waited {wait:?} for a free slot");
+        self.gui_stats
+            .lock()
+            .await
+            .signing_delays
+            .entry(harvester_id)
+            .or_default()
+            .record_queue_wait(wait);
+        permit
+    }
+    pub async fn record_signing_steps(&self, label: &str, timings: &SigningTimings) {
+        if timings.steps.is_empty() {
+            return;
+        }
+        debug!(
+            "{label} Signing took {:?}: {}",
+            timings.total(),
+            timings
+                .steps
+                .iter()
+                .map(|(step, d)| format!("{step}={d:?}"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        let mut gui_stats = self.gui_stats.lock().await;
+        for (step, duration) in &timings.steps {
+            gui_stats
+                .signing_steps
+                .entry(*step)
+                .or_default()
+                .record(*duration);
+        }
+    }
+    pub async fn record_signing_delay(&self, harvester_id: Uuid, delay: Duration) {
+        debug!("Harvester {harvester_id} responded to signature request in {delay:?}");
+        self.gui_stats
+            .lock()
+            .await
+            .signing_delays
+            .entry(harvester_id)
+            .or_default()
+            .record(delay);
+    }
 }
 impl Default for FarmerSharedState {
     fn default() -> Self {
         Self {
-            signage_points: Default::default(),
-            quality_to_identifiers: Arc::new(Default::default()),
-            proofs_of_space: Arc::new(Default::default()),
-            cache_time: Arc::new(Default::default()),
+            sp_cache: Arc::new(Default::default()),
+            signed_values: Arc::new(Default::default()),
             pool_states: Arc::new(Default::default()),
             farmer_private_keys: Arc::new(Default::default()),
             owner_secret_keys: Arc::new(Default::default()),
             auth_secret_keys: Arc::new(Default::default()),
             pool_public_keys: Arc::new(Default::default()),
             config: Arc::new(Default::default()),
+            config_path: Arc::new(crate::get_config_path()),
             run: Arc::new(Default::default()),
+            active: Arc::new(AtomicBool::new(true)),
+            soft_restart: Arc::new(Default::default()),
             force_pool_update: Arc::new(Default::default()),
             full_node_client: Arc::new(Default::default()),
             farmer_target: Arc::new(Default::default()),
             pool_target: Arc::new(Default::default()),
             gui_stats: Arc::new(Default::default()),
+            farms: Arc::new(Default::default()),
             last_sp_timestamp: Arc::new(Mutex::new(Instant::now())),
+            events: broadcast::channel(EVENT_CHANNEL_SIZE).0,
+            partial_queue: Arc::new(Default::default()),
+            partial_batcher: Arc::new(Default::default()),
+            partial_dedup: Arc::new(Default::default()),
+            eligibility_stats: Arc::new(Default::default()),
+            constants: &MAINNET,
+            rpc_health: Default::default(),
+            paused: Arc::new(Default::default()),
+            standby: Arc::new(Default::default()),
+            harvester_stats: Arc::new(Default::default()),
+            sp_digests: Arc::new(Default::default()),
+            profiling: false,
+            subsystems: Arc::new(Default::default()),
+            protocol_rtts: Arc::new(Default::default()),
+            availability: Arc::new(Default::default()),
+            unfarmable_plots: Arc::new(Default::default()),
+            dir_latencies: Arc::new(Default::default()),
+            drive_health: Arc::new(Default::default()),
+            plot_registry: Arc::new(Default::default()),
+            plot_leaderboard: Arc::new(Default::default()),
+            latency_baselines: Arc::new(Default::default()),
+            event_history: Arc::new(Default::default()),
+            readiness: Arc::new(Default::default()),
+            signature_slots: Arc::new(Default::default()),
+            sp_chain: Arc::new(Default::default()),
+            launcher_stats: Arc::new(Default::default()),
+            idle: Arc::new(IdleState::default()),
+            capture: Arc::new(Default::default()),
+            chain_height: Arc::new(AtomicU32::new(0)),
         }
     }
 }
@@ -121,7 +845,7 @@ impl PartialEq for PathInfo {
 
 #[derive(Debug)]
 pub struct PlotInfo {
-    pub reader: PlotReader<File, DiskPlot<File>>,
+    pub reader: PlotReader<PlotIoFile, IoPlot>,
     pub pool_public_key: Option<Bytes48>,
     pub pool_contract_puzzle_hash: Option<Bytes32>,
     pub plot_public_key: Bytes48,
@@ -129,7 +853,7 @@ pub struct PlotInfo {
     pub time_modified: u64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FarmerIdentifier {
     pub plot_identifier: String,
     pub challenge_hash: Bytes32,
@@ -156,15 +880,34 @@ impl<T: PoolClient + Sized + Sync + Send> Farmer<T> {
     }
 
     pub async fn run(self) {
-        let s = self;
+        let mut s = self;
+        if let Some(remote) = remote_harvesters(&s.harvesters) {
+            let config = s.shared_state.config.clone();
+            let run = s.shared_state.run.clone();
+            tokio::spawn(async move { harvester_server(config, remote, run).await });
+        }
         let mut client_run = Arc::new(AtomicBool::new(true));
+        //Reported once the first full node connection is up and the plots are loaded
+        let mut reported = false;
+        if s.shared_state.readiness.harvester_only {
+            warn!("Running harvester only, no full node connection is made until the config is complete");
+            s.spawn_startup_report();
+            while s.shared_state.run.load(Ordering::Relaxed) {
+                tokio::time::sleep(Duration::from_millis(25)).await;
+            }
+            s.shared_state.active.store(false, Ordering::Relaxed);
+            return;
+        }
+        let settings = ConnectionSettings::new(&s.shared_state.config);
+        let mut reconnector = Reconnector::new(&s.shared_state.config, &settings);
         'retry: loop {
             if !s.shared_state.run.load(Ordering::Relaxed) {
                 break;
             }
+            let mut endpoint = reconnector.endpoint().clone();
             info!(
                 "Starting Farmer FullNode Connection to: {}:{}",
-                &s.shared_state.config.fullnode_ws_host, s.shared_state.config.fullnode_ws_port
+                &endpoint.host, endpoint.port
             );
             loop {
                 if !s.shared_state.run.load(Ordering::Relaxed) {
@@ -182,7 +925,7 @@ impl<T: PoolClient + Sized + Sync + Send> Farmer<T> {
                 }
                 client_run = Arc::new(AtomicBool::new(true));
                 match s
-                    .create_farmer_client(&s.shared_state, client_run.clone())
+                    .create_farmer_client(&s.shared_state, &endpoint, client_run.clone())
                     .await
                 {
                     Ok(mut c) => {
@@ -190,22 +933,63 @@ impl<T: PoolClient + Sized + Sync + Send> Farmer<T> {
                             error!("Failed to attach socket listeners: {:?}", e);
                             continue;
                         } else {
-                            info!("Farmer Client Initialized");
+                            if endpoint.relay {
+                                info!(
+                                    "Farmer Client Initialized through relay {}:{}",
+                                    endpoint.host, endpoint.port
+                                );
+                            } else {
+                                info!("Farmer Client Initialized");
+                            }
+                            reconnector.connected();
+                            s.shared_state.emit(FarmerEventKind::FullNodeConnected {
+                                host: endpoint.host.clone(),
+                                port: endpoint.port,
+                            });
+                            s.shared_state
+                                .protocol_rtts
+                                .record(
+                                    format!("full_node {}:{}", endpoint.host, endpoint.port),
+                                    c.handshake_rtt,
+                                )
+                                .await;
                             *s.shared_state.full_node_client.lock().await = Some(c);
+                            if !reported {
+                                reported = true;
+                                s.spawn_startup_report();
+                            }
                             break;
                         }
                     }
                     Err(e) => {
-                        error!(
-                            "Failed to Start Farmer Client, Waiting and trying again: {:?}",
-                            e
-                        );
-                        tokio::time::sleep(Duration::from_secs(3)).await;
+                        let delay = reconnector.failed();
+                        //Misconfiguration will not fix itself, retry slower and say what to check
+                        let delay = if e.is_recoverable() {
+                            error!(
+                                "Failed to Start Farmer Client for {}:{}, Waiting and trying again: {:?}",
+                                endpoint.host, endpoint.port, e
+                            );
+                            delay
+                        } else {
+                            error!(
+                                "Failed to Start Farmer Client, check the config and ssl files: {e}"
+                            );
+                            delay.max(Duration::from_secs(30))
+                        };
+                        s.shared_state.emit(FarmerEventKind::Error {
+                            message: format!("Failed to Start Farmer Client: {e}"),
+                        });
+                        tokio::time::sleep(delay).await;
+                        endpoint = reconnector.endpoint().clone();
                         continue;
                     }
                 }
             }
             let mut last_clear = Instant::now();
+            let mut last_ping = Instant::now();
+            let timing = SpTiming::new(s.shared_state.constants);
+            let history_duration = timing.retention(s.shared_state.config.sp_history_depth);
+            let sp_timeout = settings.sp_timeout.unwrap_or(timing.sp_timeout());
             loop {
                 if let Some(client) = s.shared_state.full_node_client.lock().await.as_ref() {
                     if client.is_closed() {
@@ -214,14 +998,39 @@ impl<T: PoolClient + Sized + Sync + Send> Farmer<T> {
                             break 'retry;
                         } else {
                             info!("Unexpected Farmer Client Closed, Reconnecting");
+                            s.shared_state.emit(FarmerEventKind::FullNodeDisconnected {
+                                host: endpoint.host.clone(),
+                                port: endpoint.port,
+                            });
                             break;
                         }
                     }
+                    //Unsolicited pongs keep NAT mappings and proxies open without asking the node
+                    //for an answer
+                    if let Some(interval) = settings.ping_interval {
+                        if last_ping.elapsed() >= interval {
+                            last_ping = Instant::now();
+                            if let Err(e) =
+                                client.client.lock().await.send(Message::Pong(vec![])).await
+                            {
+                                warn!(
+                                    "Failed to send keepalive to the full node, reconnecting: {:?}",
+                                    e
+                                );
+                                client_run.store(false, Ordering::Relaxed);
+                                s.shared_state.emit(FarmerEventKind::FullNodeDisconnected {
+                                    host: endpoint.host.clone(),
+                                    port: endpoint.port,
+                                });
+                                break;
+                            }
+                        }
+                    }
                 }
                 let dur = Instant::now()
                     .duration_since(*s.shared_state.last_sp_timestamp.lock().await)
                     .as_secs();
-                if dur >= 180 {
+                if dur >= sp_timeout.as_secs() {
                     info!(
                         "Failed to get Signage Point after {dur} seconds, restarting farmer client"
                     );
@@ -229,51 +1038,20 @@ impl<T: PoolClient + Sized + Sync + Send> Farmer<T> {
                     if let Some(c) = &*s.shared_state.full_node_client.lock().await {
                         info!(
                             "Shutting Down old Farmer Client: {}:{}",
-                            s.shared_state.config.fullnode_ws_host,
-                            s.shared_state.config.fullnode_ws_host
+                            endpoint.host, endpoint.port
                         );
                         client_run.store(false, Ordering::Relaxed);
                         c.client.lock().await.shutdown().await.unwrap_or_default();
                         break;
                     }
                 }
-                if last_clear.elapsed() > Duration::from_secs(300) {
-                    let expired: Vec<Bytes32> = s
-                        .shared_state
-                        .cache_time
-                        .lock()
-                        .await
-                        .iter()
-                        .filter_map(|(k, v)| {
-                            if v.elapsed() > Duration::from_secs(1800) {
-                                Some(*k)
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
-                    s.shared_state
-                        .cache_time
-                        .lock()
-                        .await
-                        .retain(|k, _| !expired.contains(k));
-                    s.shared_state
-                        .signage_points
-                        .lock()
-                        .await
-                        .retain(|k, _| !expired.contains(k));
-                    s.shared_state
-                        .quality_to_identifiers
-                        .lock()
-                        .await
-                        .retain(|k, _| !expired.contains(k));
-                    s.shared_state
-                        .proofs_of_space
-                        .lock()
-                        .await
-                        .retain(|k, _| !expired.contains(k));
+                if last_clear.elapsed() > timing.prune_interval() {
+                    s.shared_state.evict_expired(history_duration).await;
                     last_clear = Instant::now();
                 }
+                if s.shared_state.soft_restart.swap(false, Ordering::Relaxed) {
+                    s.soft_restart().await;
+                }
                 if !s.shared_state.run.load(Ordering::Relaxed) {
                     info!("Farmer Stopping");
                     break 'retry;
@@ -281,26 +1059,119 @@ impl<T: PoolClient + Sized + Sync + Send> Farmer<T> {
                 tokio::time::sleep(Duration::from_millis(25)).await;
             }
         }
+        s.shared_state.active.store(false, Ordering::Relaxed);
+    }
+
+    fn spawn_startup_report(&self) {
+        let shared_state = self.shared_state.clone();
+        let harvesters = self.harvesters.clone();
+        tokio::spawn(async move {
+            wait_for_plots(&harvesters).await;
+            let report = StartupReport::build(&shared_state, &harvesters).await;
+            report.log();
+            shared_state.emit(FarmerEventKind::StartupReport {
+                report: Box::new(report),
+            });
+        });
+    }
+
+    //Reloads the config, keys and harvesters while the full node connection stays up. The old
+    //harvesters keep answering signage points until the new ones are loaded and take over
+    async fn soft_restart(&mut self) {
+        let started = Instant::now();
+        info!(
+            "Soft restart, reloading {:?}",
+            self.shared_state.config_path
+        );
+        let config_path = self.shared_state.config_path.clone();
+        let config = tokio::task::spawn_blocking(move || {
+            Config::try_from(config_path.as_path()).and_then(|mut c| {
+                resolve_keyring_keys(&mut c)?;
+                Ok(c)
+            })
+        })
+        .await
+        .unwrap_or_else(|e| Err(Error::other(e).into()));
+        let config = match config {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Soft restart failed, keeping the running config: {e}");
+                self.shared_state.emit(FarmerEventKind::Error {
+                    message: format!("Soft restart failed: {e}"),
+                });
+                return;
+            }
+        };
+        let shared_state = Arc::new(self.shared_state.reloaded(config).await);
+        shared_state.reset_stats().await;
+        let harvesters = match load_harvesters(shared_state.clone()).await {
+            Ok(harvesters) => harvesters,
+            Err(e) => {
+                error!("Soft restart failed to load the harvesters, keeping the old ones: {e:?}");
+                shared_state.active.store(false, Ordering::Relaxed);
+                return;
+            }
+        };
+        let harvesters = keep_remote_harvesters(&self.harvesters, harvesters);
+        wait_for_plots(&harvesters).await;
+        //Launchers removed from the config no longer get partials or status lines
+        let launchers: Vec<Bytes32> = shared_state
+            .config
+            .all_farms()
+            .iter()
+            .flat_map(|f| f.pool_info.iter().map(|p| p.p2_singleton_puzzle_hash))
+            .collect();
+        shared_state
+            .pool_states
+            .lock()
+            .await
+            .retain(|k, _| launchers.contains(k));
+        self.shared_state.active.store(false, Ordering::Relaxed);
+        self.shared_state = shared_state.clone();
+        self.harvesters = harvesters;
+        if let Some(client) = shared_state.full_node_client.lock().await.as_mut() {
+            if let Err(e) = self.attach_client_handlers(&shared_state, client).await {
+                error!(
+                    "Failed to attach socket listeners after soft restart: {:?}",
+                    e
+                );
+            }
+        }
+        let pool_state = shared_state.clone();
+        tokio::spawn(async move { pool_updater(pool_state).await });
+        info!(
+            "Soft restart done in {:.1}s, the full node connection was kept",
+            started.elapsed().as_secs_f64()
+        );
     }
 
+    //The relay leads to the same full node, so it is checked against the node's certificates
     async fn create_farmer_client(
         &self,
         shared_state: &FarmerSharedState,
+        endpoint: &Endpoint,
         client_run: Arc<AtomicBool>,
-    ) -> Result<FarmerClient, Error> {
+    ) -> FarmerResult<FullNodePeer> {
         let network_id = shared_state.config.selected_network.as_str();
-        let ssl_path = get_ssl_root_path(shared_state);
-        create_all_ssl(&ssl_path, false)?;
-        FarmerClient::new_ssl(
-            &shared_state.config.fullnode_ws_host,
-            shared_state.config.fullnode_ws_port,
+        let ssl_path = node_ssl_path(shared_state);
+        let cert_path = ssl_path.clone();
+        tokio::task::spawn_blocking(move || create_all_ssl(&cert_path, false))
+            .await
+            .map_err(Error::other)??;
+        if shared_state.config.verify_node_ca {
+            verify_node_certificate(&endpoint.host, endpoint.port, &ssl_path.join(CA_PUBLIC_CRT))
+                .await?;
+        }
+        FullNodePeer::connect(
+            &shared_state.config.dns,
+            &endpoint.host,
+            endpoint.port,
             ClientSSLConfig {
                 ssl_crt_path: &ssl_path.join(PUBLIC_CRT).to_string_lossy(),
                 ssl_key_path: &ssl_path.join(PUBLIC_KEY).to_string_lossy(),
                 ssl_ca_crt_path: &ssl_path.join(CA_PUBLIC_CRT).to_string_lossy(),
             },
             network_id,
-            &None,
             client_run.clone(),
         )
         .await
@@ -309,7 +1180,7 @@ impl<T: PoolClient + Sized + Sync + Send> Farmer<T> {
     async fn attach_client_handlers(
         &self,
         shared_state: &FarmerSharedState,
-        client: &mut FarmerClient,
+        client: &mut FullNodePeer,
     ) -> Result<(), Error> {
         client.client.lock().await.clear().await;
         let signage_handle_id = Uuid::new_v4();
@@ -331,12 +1202,8 @@ impl<T: PoolClient + Sized + Sync + Send> Farmer<T> {
                         shared_state: self.shared_state.clone(),
                         pool_state: self.shared_state.pool_states.clone(),
                         pool_client: self.pool_client.clone(),
-                        signage_points: self.shared_state.signage_points.clone(),
-                        cache_time: self.shared_state.cache_time.clone(),
                         harvesters: self.harvesters.clone(),
-                        constants: CONSENSUS_CONSTANTS_MAP
-                            .get(&self.shared_state.config.selected_network)
-                            .unwrap_or(&MAINNET),
+                        constants: self.shared_state.constants,
                     }),
                 ),
             )
@@ -358,9 +1225,7 @@ impl<T: PoolClient + Sized + Sync + Send> Farmer<T> {
                         shared_state: self.shared_state.clone(),
                         pool_client: self.pool_client.clone(),
                         harvesters: self.harvesters.clone(),
-                        constants: CONSENSUS_CONSTANTS_MAP
-                            .get(&self.shared_state.config.selected_network)
-                            .unwrap_or(&MAINNET),
+                        constants: self.shared_state.constants,
                     }),
                 ),
             )
@@ -371,8 +1236,105 @@ impl<T: PoolClient + Sized + Sync + Send> Farmer<T> {
 
 static HARVESTER_CRT: &str = "harvester/private_harvester.crt";
 
+//Certificates of the full node serving the farming websocket
+fn node_ssl_path(shared_state: &FarmerSharedState) -> PathBuf {
+    get_ssl_root_path(&shared_state.config.node_ssl_root_path(
+        &shared_state.config.fullnode_ws_host,
+        shared_state.config.fullnode_ws_port,
+    ))
+}
+
 async fn load_client_id(shared_state: &FarmerSharedState) -> Result<Bytes32, Error> {
-    let ssl_path = get_ssl_root_path(shared_state).join(Path::new(HARVESTER_CRT));
+    let ssl_path = node_ssl_path(shared_state).join(Path::new(HARVESTER_CRT));
     let cert = tokio::fs::read_to_string(ssl_path).await?;
     Ok(Bytes32::new(&hash_256(cert)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::test_fixtures::proof_of_space;
+
+    #[tokio::test]
+    async fn signature_requests_are_pipelined_per_harvester() {
+        let shared_state = Arc::new(FarmerSharedState {
+            config: Arc::new(Config {
+                max_signature_requests: 1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let harvester_id = Uuid::new_v4();
+        let first = shared_state.signature_slot(harvester_id).await;
+        assert!(first.is_some());
+        assert!(shared_state.signature_slot(Uuid::new_v4()).await.is_some());
+        let waiting = shared_state.clone();
+        let second = tokio::spawn(async move { waiting.signature_slot(harvester_id).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!second.is_finished());
+        drop(first);
+        assert!(second.await.unwrap().is_some());
+        let stats = shared_state.gui_stats.lock().await.signing_delays[&harvester_id];
+        assert_eq!(stats.queued, 1);
+        assert!(stats.queue_wait >= Duration::from_millis(20));
+    }
+
+    #[tokio::test]
+    async fn signage_points_and_proofs_expire_together() {
+        let shared_state = FarmerSharedState::default();
+        let sp_hash = Bytes32::from([1; 32]);
+        let quality_string = Bytes32::from([2; 32]);
+        let sp = NewSignagePoint {
+            challenge_hash: Bytes32::from([3; 32]),
+            challenge_chain_sp: sp_hash,
+            reward_chain_sp: Default::default(),
+            difficulty: 1,
+            sub_slot_iters: 1,
+            signage_point_index: 4,
+        };
+        shared_state.record_signage_point(sp.clone()).await;
+        let arrived = shared_state.sp_cache.sp_arrivals.lock().await[&sp_hash].arrived;
+        shared_state.record_signage_point(sp.clone()).await;
+        assert_eq!(
+            shared_state.signage_points_for(&sp_hash).await,
+            Some(vec![sp.clone(), sp])
+        );
+        assert_eq!(
+            shared_state.sp_cache.sp_arrivals.lock().await[&sp_hash].arrived,
+            arrived
+        );
+        let proof = proof_of_space();
+        shared_state
+            .record_proof(
+                quality_string,
+                FarmerIdentifier {
+                    plot_identifier: "a.plot".to_string(),
+                    challenge_hash: Default::default(),
+                    sp_hash,
+                    harvester_id: Uuid::new_v4(),
+                },
+                proof.clone(),
+            )
+            .await;
+        //Still there for the block signatures after the signage point ones
+        for _ in 0..2 {
+            assert_eq!(
+                shared_state.proof_for(&sp_hash, "a.plot").await.as_deref(),
+                Some(&proof)
+            );
+        }
+        assert!(shared_state.proof_for(&sp_hash, "b.plot").await.is_none());
+        assert!(shared_state.farmer_key_for(&Bytes48::default()).is_none());
+        shared_state.evict_expired(Duration::from_secs(60)).await;
+        assert!(shared_state.has_signage_point(&sp_hash).await);
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        shared_state.evict_expired(Duration::ZERO).await;
+        assert!(!shared_state.has_signage_point(&sp_hash).await);
+        assert!(shared_state.proof_for(&sp_hash, "a.plot").await.is_none());
+        assert!(shared_state
+            .identifier_for_quality(&quality_string)
+            .await
+            .is_none());
+        assert!(shared_state.sp_tag(&sp_hash).await.is_none());
+    }
+}
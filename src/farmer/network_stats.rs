@@ -0,0 +1,95 @@
+use crate::farmer::sp_timing::SpTiming;
+use dg_xch_core::blockchain::blockchain_state::BlockchainState;
+use dg_xch_core::consensus::constants::ConsensusConstants;
+use serde::{Deserialize, Serialize};
+
+//Sub-slots target 600 seconds
+pub const SUB_SLOTS_PER_DAY: f64 = 144.0;
+//Chia reports netspace in these units, plot sizes are scaled the same way before comparing
+pub const UI_ACTUAL_SPACE_CONSTANT_FACTOR: f64 = 0.762;
+pub const EIB: f64 = 1024.0 * 1024.0 * 1024.0 * 1024.0 * 1024.0 * 1024.0;
+
+pub fn expected_plot_size(k: u8) -> f64 {
+    (2.0 * k as f64 + 1.0) * 2f64.powi(k as i32 - 1)
+}
+
+//Space of a plot as counted in the netspace, compression does not change it
+pub fn effective_plot_size(k: u8) -> u64 {
+    (expected_plot_size(k) * UI_ACTUAL_SPACE_CONSTANT_FACTOR) as u64
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkStats {
+    pub updated: u64,
+    pub height: u32,
+    pub difficulty: u64,
+    pub netspace: u128,
+    pub effective_space: u64,
+    //Seconds until the farm wins a block on average, None without plots or netspace
+    pub time_to_win: Option<u64>,
+}
+impl NetworkStats {
+    pub fn new(
+        state: &BlockchainState,
+        effective_space: u64,
+        constants: &ConsensusConstants,
+        updated: u64,
+    ) -> Self {
+        Self {
+            updated,
+            height: state.peak.as_ref().map(|p| p.height).unwrap_or_default(),
+            difficulty: state.difficulty,
+            netspace: state.space,
+            effective_space,
+            time_to_win: expected_time_to_win(effective_space, state.space, constants),
+        }
+    }
+}
+
+//Same estimate as the Chia UI, the share of the netspace times the average block time
+pub fn expected_time_to_win(
+    effective_space: u64,
+    netspace: u128,
+    constants: &ConsensusConstants,
+) -> Option<u64> {
+    if effective_space == 0 || netspace == 0 {
+        return None;
+    }
+    let block_secs = SpTiming::new(constants).sub_slot.as_secs_f64()
+        / constants.slot_blocks_target.max(1) as f64;
+    Some((netspace as f64 / effective_space as f64 * block_secs) as u64)
+}
+
+pub fn format_time_to_win(secs: Option<u64>) -> String {
+    let Some(secs) = secs else {
+        return "unknown".to_string();
+    };
+    let (days, hours, minutes) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    match (days, hours) {
+        (d, _) if d >= 365 => format!("{:.1} years", d as f64 / 365.0),
+        (0, 0) => format!("{minutes} minutes"),
+        (0, h) => format!("{h} hours {minutes} minutes"),
+        (d, h) => format!("{d} days {h} hours"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dg_xch_core::consensus::constants::MAINNET;
+
+    #[test]
+    fn time_to_win_follows_share_of_netspace() {
+        let plot = effective_plot_size(32);
+        assert_eq!(plot, 106_364_865_085);
+        //One k32 in 20 EiB of netspace wins about every 129 years, twice the space halves that
+        let secs = expected_time_to_win(plot, (20.0 * EIB) as u128, &MAINNET).unwrap();
+        assert_eq!(secs / (365 * 86400), 128);
+        let doubled = expected_time_to_win(2 * plot, (20.0 * EIB) as u128, &MAINNET).unwrap();
+        assert!(doubled.abs_diff(secs / 2) <= 1);
+        assert_eq!(expected_time_to_win(0, 1, &MAINNET), None);
+        assert_eq!(format_time_to_win(Some(90_061)), "1 days 1 hours");
+        assert_eq!(format_time_to_win(Some(3_720)), "1 hours 2 minutes");
+        assert_eq!(format_time_to_win(None), "unknown");
+    }
+}
@@ -0,0 +1,191 @@
+use crate::error::{FarmerError, FarmerResult};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use log::{debug, info};
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{
+    Certificate, CertificateError, ClientConfig, ClientConnection, OwnedTrustAnchor, RootCertStore,
+};
+use rustls::{Error as TlsError, ServerName};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+//Chia node certificates are issued for this name, the host they run on is not checked
+const NODE_CERT_NAME: &str = "chia.net";
+
+pub fn pem_to_der(pem: &str) -> Option<Vec<u8>> {
+    let body: String = pem
+        .lines()
+        .skip_while(|l| !l.starts_with("-----BEGIN CERTIFICATE"))
+        .skip(1)
+        .take_while(|l| !l.starts_with("-----END"))
+        .collect();
+    STANDARD.decode(body).ok()
+}
+
+//Length of the tag and length bytes and of the content of the DER element der starts with
+fn der_element(der: &[u8]) -> Option<(u8, usize, usize)> {
+    let tag = *der.first()?;
+    let first = *der.get(1)? as usize;
+    let (header, len) = if first < 0x80 {
+        (2, first)
+    } else {
+        let count = first & 0x7f;
+        if count == 0 || count > 4 {
+            return None;
+        }
+        let len = der
+            .get(2..2 + count)?
+            .iter()
+            .fold(0, |len, b| len << 8 | *b as usize);
+        (2 + count, len)
+    };
+    (der.len() >= header + len).then_some((tag, header, len))
+}
+
+//webpki refuses CA certificates it can not fully parse, like the private CA dg_xch generates with
+//its basic constraints twice. A trust anchor is only the subject and key, so they are taken from
+//the certificate directly and the certificates issued by it are still verified by webpki
+pub fn trust_anchor(ca: &Certificate) -> Result<OwnedTrustAnchor, String> {
+    let invalid = || "invalid CA certificate".to_string();
+    let (_, header, _) = der_element(&ca.0).ok_or_else(invalid)?;
+    let tbs = &ca.0[header..];
+    let (_, header, len) = der_element(tbs).ok_or_else(invalid)?;
+    let mut fields = &tbs[header..header + len];
+    let mut contents = vec![];
+    while let Some((tag, header, len)) = der_element(fields) {
+        contents.push((tag, &fields[header..header + len]));
+        fields = &fields[header + len..];
+    }
+    //The version is the only optional field before the subject, serial number, signature
+    //algorithm, issuer and validity follow it
+    let skip = usize::from(contents.first().map(|(tag, _)| *tag) == Some(0xA0));
+    match contents.get(skip + 4..skip + 6) {
+        Some([(_, subject), (_, spki)]) => Ok(
+            OwnedTrustAnchor::from_subject_spki_name_constraints(*subject, *spki, None::<Vec<u8>>),
+        ),
+        _ => Err(invalid()),
+    }
+}
+
+//Checks the certificate chain only, Chia certificates are not tied to a host name
+pub fn chains_to_ca(ca: &Certificate, chain: &[Certificate]) -> Result<(), String> {
+    let Some((end_entity, intermediates)) = chain.split_first() else {
+        return Err("no certificate presented".to_string());
+    };
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(std::iter::once(trust_anchor(ca)?));
+    let name = ServerName::try_from(NODE_CERT_NAME).map_err(|e| e.to_string())?;
+    match WebPkiVerifier::new(roots, None).verify_server_cert(
+        end_entity,
+        intermediates,
+        &name,
+        &mut std::iter::empty(),
+        &[],
+        SystemTime::now(),
+    ) {
+        Ok(_) | Err(TlsError::InvalidCertificate(CertificateError::NotValidForName)) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+//Keeps the chain the node presented, the handshake is dropped right after
+#[derive(Default)]
+struct CaptureVerifier {
+    chain: Mutex<Vec<Certificate>>,
+}
+impl ServerCertVerifier for CaptureVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, TlsError> {
+        if let Ok(mut chain) = self.chain.lock() {
+            chain.push(end_entity.clone());
+            chain.extend_from_slice(intermediates);
+        }
+        Err(TlsError::General("certificate captured".to_string()))
+    }
+}
+
+fn presented_chain(host: &str, port: u16) -> Option<Vec<Certificate>> {
+    let addr = (host, port).to_socket_addrs().ok()?.next()?;
+    let mut stream = TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).ok()?;
+    stream.set_read_timeout(Some(PROBE_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(PROBE_TIMEOUT)).ok()?;
+    let verifier = Arc::new(CaptureVerifier::default());
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(verifier.clone())
+        .with_no_client_auth();
+    let name = ServerName::try_from(NODE_CERT_NAME).ok()?;
+    let mut connection = ClientConnection::new(Arc::new(config), name).ok()?;
+    while connection.is_handshaking() {
+        if connection.complete_io(&mut stream).is_err() {
+            break;
+        }
+    }
+    let chain = verifier.chain.lock().ok()?.clone();
+    (!chain.is_empty()).then_some(chain)
+}
+
+//Fails when the node presents a certificate that was not issued by the CA in ca_path, like the
+//certificates of another fork. Nodes that can not be reached are left to the connection itself
+pub async fn verify_node_certificate(host: &str, port: u16, ca_path: &Path) -> FarmerResult<()> {
+    let ca_pem = tokio::fs::read_to_string(ca_path).await?;
+    let ca = pem_to_der(&ca_pem)
+        .map(Certificate)
+        .ok_or_else(|| FarmerError::Config(format!("{:?} is not a PEM certificate", ca_path)))?;
+    let probe_host = host.to_string();
+    let Ok(Some(chain)) =
+        tokio::task::spawn_blocking(move || presented_chain(&probe_host, port)).await
+    else {
+        debug!("Could not read the certificate of {host}:{port}, skipping the CA check");
+        return Ok(());
+    };
+    chains_to_ca(&ca, &chain).map_err(|e| {
+        FarmerError::Config(format!(
+            "The certificate of {host}:{port} was not issued by {:?}, is ssl_root_path set for the right network? ({e})",
+            ca_path
+        ))
+    })?;
+    info!("Certificate of {host}:{port} chains to {:?}", ca_path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dg_xch_core::ssl::{generate_ca_signed_cert_data, make_ca_cert, CHIA_CA_CRT, CHIA_CA_KEY};
+
+    #[test]
+    fn node_certificate_must_chain_to_configured_ca() {
+        let (node_pem, _) = generate_ca_signed_cert_data(CHIA_CA_CRT, CHIA_CA_KEY).unwrap();
+        let ca = Certificate(pem_to_der(CHIA_CA_CRT).unwrap());
+        let node = Certificate(pem_to_der(&node_pem).unwrap());
+        let chain = [node];
+        assert_eq!(chains_to_ca(&ca, &chain), Ok(()));
+        //A fork or private CA did not issue it
+        assert!(chains_to_ca(&chain[0], &chain).is_err());
+        assert!(chains_to_ca(&ca, &[]).is_err());
+        //webpki can not parse a private CA of dg_xch as a root, its certificates still chain to it
+        let dir = std::env::temp_dir().join(format!("ff-node-ssl-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (ca_pem, ca_key) = make_ca_cert(&dir.join("ca.crt"), &dir.join("ca.key")).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        let (node_pem, _) = generate_ca_signed_cert_data(&ca_pem, &ca_key).unwrap();
+        let ca = Certificate(pem_to_der(&ca_pem).unwrap());
+        assert!(RootCertStore::empty().add(&ca).is_err());
+        assert_eq!(
+            chains_to_ca(&ca, &[Certificate(pem_to_der(&node_pem).unwrap())]),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use crate::farmer::config::PartialBatchConfig;
+use crate::farmer::partial_metadata::PartialMetadata;
+use dg_xch_clients::protocols::pool::{PoolError, PostPartialRequest, PostPartialResponse};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+pub type PartialResult = Result<PostPartialResponse, PoolError>;
+
+//One partial of a POST to the partials endpoint, metadata is only set for pools taking extended
+//partials
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchPartial {
+    pub request: PostPartialRequest,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<PartialMetadata>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PostPartialsRequest<'a> {
+    pub partials: Vec<&'a BatchPartial>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BatchResult {
+    Accepted(PostPartialResponse),
+    Rejected(PoolError),
+}
+
+#[derive(Debug, Deserialize)]
+struct PostPartialsResponse {
+    responses: Vec<BatchResult>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BatchError {
+    //The pool has no batch endpoint
+    Unsupported,
+    Failed(String),
+}
+
+//Results in the order the partials were sent, Err when the body can't be matched to the batch
+pub fn parse_batch_response(body: &str, sent: usize) -> Result<Vec<PartialResult>, String> {
+    let response: PostPartialsResponse =
+        serde_json::from_str(body).map_err(|e| format!("invalid batch response: {e}"))?;
+    if response.responses.len() != sent {
+        return Err(format!(
+            "batch response has {} results for {sent} partials",
+            response.responses.len()
+        ));
+    }
+    Ok(response
+        .responses
+        .into_iter()
+        .map(|r| match r {
+            BatchResult::Accepted(r) => Ok(r),
+            BatchResult::Rejected(e) => Err(e),
+        })
+        .collect())
+}
+
+//Time until the pool window of a partial ends, None when it is not limited or unknown
+pub fn time_left(partial_window: u64, sp_age: Option<Duration>) -> Option<Duration> {
+    match sp_age {
+        Some(age) if partial_window > 0 => {
+            Some(Duration::from_secs(partial_window).saturating_sub(age))
+        }
+        _ => None,
+    }
+}
+
+pub struct BatchedPartial {
+    pub partial: BatchPartial,
+    flush_at: Instant,
+    respond: oneshot::Sender<PartialResult>,
+}
+impl BatchedPartial {
+    //Dropping a partial instead makes its handler post it on its own
+    pub fn respond(self, result: PartialResult) {
+        let _ = self.respond.send(result);
+    }
+}
+
+//Partials waiting to be sent to pools with a configured batch endpoint, the pool protocol has
+//none. Pools answering it with not found are dropped and get each partial on its own
+#[derive(Default)]
+pub struct PartialBatcher {
+    config: Option<PartialBatchConfig>,
+    pending: HashMap<String, Vec<BatchedPartial>>,
+    pools: HashSet<String>,
+}
+impl PartialBatcher {
+    pub fn new(config: Option<PartialBatchConfig>, pools: Vec<String>) -> Self {
+        Self {
+            config,
+            pools: pools
+                .iter()
+                .map(|url| url.trim_end_matches('/').to_string())
+                .collect(),
+            ..Default::default()
+        }
+    }
+    //Partials wait up to window_ms for others. None when the partial is not batched, the caller
+    //posts it itself
+    pub fn enqueue(
+        &mut self,
+        pool_url: &str,
+        partial: BatchPartial,
+        now: Instant,
+    ) -> Option<oneshot::Receiver<PartialResult>> {
+        let config = self.config.as_ref()?;
+        let pool_url = pool_url.trim_end_matches('/');
+        if !self.pools.contains(pool_url) {
+            return None;
+        }
+        let (respond, receiver) = oneshot::channel();
+        self.pending
+            .entry(pool_url.to_string())
+            .or_default()
+            .push(BatchedPartial {
+                partial,
+                flush_at: now + Duration::from_millis(config.window_ms),
+                respond,
+            });
+        Some(receiver)
+    }
+    pub fn window(&self) -> Option<Duration> {
+        self.config
+            .as_ref()
+            .map(|c| Duration::from_millis(c.window_ms))
+    }
+    //The pending partials of a pool go out with the next take_due, for partials that can't wait
+    //out the window
+    pub fn flush_now(&mut self, pool_url: &str, now: Instant) {
+        if let Some(batch) = self.pending.get_mut(pool_url.trim_end_matches('/')) {
+            for partial in batch {
+                partial.flush_at = partial.flush_at.min(now);
+            }
+        }
+    }
+    //Full batches and batches holding a partial that can't wait any longer
+    pub fn take_due(&mut self, now: Instant) -> Vec<(String, Vec<BatchedPartial>)> {
+        let max_size = self.config.as_ref().map_or(1, |c| c.max_size.max(1));
+        let is_due = |batch: &[BatchedPartial]| {
+            batch.len() >= max_size || batch.iter().any(|p| p.flush_at <= now)
+        };
+        let due: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, batch)| is_due(batch))
+            .map(|(url, _)| url.clone())
+            .collect();
+        let mut batches = vec![];
+        for url in due {
+            let Some(mut batch) = self.pending.remove(&url) else {
+                continue;
+            };
+            while batch.len() > max_size {
+                let rest = batch.split_off(max_size);
+                batches.push((url.clone(), batch));
+                batch = rest;
+            }
+            if is_due(&batch) {
+                batches.push((url, batch));
+            } else {
+                self.pending.insert(url, batch);
+            }
+        }
+        batches
+    }
+    pub fn mark_unsupported(&mut self, pool_url: &str) {
+        self.pools.remove(pool_url.trim_end_matches('/'));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::test_fixtures::partial_request;
+
+    #[test]
+    fn batches_per_pool_until_the_window_or_validity_ends() {
+        assert_eq!(
+            time_left(25, Some(Duration::from_millis(24_800))),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(time_left(0, Some(Duration::from_secs(60))), None);
+        let partial = BatchPartial {
+            request: partial_request(),
+            metadata: None,
+        };
+        let start = Instant::now();
+        let mut disabled = PartialBatcher::default();
+        assert!(disabled
+            .enqueue("https://pool", partial.clone(), start)
+            .is_none());
+        let mut batcher = PartialBatcher::new(
+            Some(PartialBatchConfig {
+                window_ms: 500,
+                max_size: 2,
+            }),
+            vec!["https://pool".to_string(), "https://other/".to_string()],
+        );
+        //Pools without a batch endpoint get every partial on its own
+        assert!(batcher
+            .enqueue("https://standard", partial.clone(), start)
+            .is_none());
+        let window = Duration::from_millis(500);
+        let mut receivers = vec![];
+        for _ in 0..3 {
+            receivers.push(batcher.enqueue("https://pool/", partial.clone(), start));
+        }
+        batcher.enqueue("https://other", partial.clone(), start);
+        //The full batch is sent right away, the rest waits for its window
+        let due = batcher.take_due(start);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, "https://pool");
+        assert_eq!(due[0].1.len(), 2);
+        assert!(batcher
+            .take_due(start + Duration::from_millis(99))
+            .is_empty());
+        batcher.flush_now("https://other", start + Duration::from_millis(100));
+        let due = batcher.take_due(start + Duration::from_millis(100));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, "https://other");
+        assert_eq!(batcher.take_due(start + window)[0].1.len(), 1);
+
+        let body =
+            r#"{"responses":[{"new_difficulty":10},{"error_code":2,"error_message":"late"}]}"#;
+        let results = parse_batch_response(body, 2).unwrap();
+        assert_eq!(results[0].as_ref().unwrap().new_difficulty, 10);
+        assert_eq!(results[1].as_ref().unwrap_err().error_code, 2);
+        assert!(parse_batch_response(body, 3).is_err());
+
+        batcher.mark_unsupported("https://pool/");
+        assert!(batcher.enqueue("https://pool", partial, start).is_none());
+    }
+}
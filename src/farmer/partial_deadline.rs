@@ -0,0 +1,45 @@
+use crate::farmer::config::Config;
+use std::time::Duration;
+
+//Pools reject partials that reach them more than partial_window seconds after the signage point.
+//Partials older than the window plus the margin are certain to be late and are not sent, the
+//margin covers pools whose node saw the signage point later than ours
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialDeadline {
+    cutoff: Option<Duration>,
+}
+impl PartialDeadline {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            cutoff: (config.partial_window > 0)
+                .then(|| Duration::from_secs(config.partial_window + config.partial_cutoff_margin)),
+        }
+    }
+    //Partials of signage points that already left the cache are sent, their age is unknown
+    pub fn is_late(&self, sp_age: Option<Duration>) -> bool {
+        matches!((self.cutoff, sp_age), (Some(cutoff), Some(age)) if age > cutoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cutoff_is_window_plus_margin() {
+        let config = Config {
+            partial_window: 25,
+            partial_cutoff_margin: 5,
+            ..Default::default()
+        };
+        let deadline = PartialDeadline::new(&config);
+        assert!(!deadline.is_late(Some(Duration::from_secs(30))));
+        assert!(deadline.is_late(Some(Duration::from_millis(30_001))));
+        assert!(!deadline.is_late(None));
+        let disabled = PartialDeadline::new(&Config {
+            partial_window: 0,
+            ..config
+        });
+        assert!(!disabled.is_late(Some(Duration::from_secs(600))));
+    }
+}
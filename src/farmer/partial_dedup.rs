@@ -0,0 +1,54 @@
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+//Signage point, plot id and quality of a proof, the same proof reported twice has the same key
+pub type PartialKey = (Bytes32, Bytes32, Bytes32);
+
+//Proofs already sent to their pool, a harvester retry or a reconnect race can report a proof again
+//and some pools penalize the duplicate partial
+#[derive(Debug, Default)]
+pub struct PartialDedup {
+    seen: HashMap<PartialKey, Instant>,
+}
+impl PartialDedup {
+    //False when the proof was seen within the window, a zero window lets every proof through
+    pub fn first_submission(&mut self, key: PartialKey, window: Duration, now: Instant) -> bool {
+        if window.is_zero() {
+            return true;
+        }
+        self.seen
+            .retain(|_, seen| now.saturating_duration_since(*seen) < window);
+        match self.seen.entry(key) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(now);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suppresses_repeats_within_the_window() {
+        let mut dedup = PartialDedup::default();
+        let window = Duration::from_secs(60);
+        let start = Instant::now();
+        let key = (
+            Bytes32::default(),
+            Bytes32::from_sized_bytes([1u8; 32]),
+            Bytes32::default(),
+        );
+        assert!(dedup.first_submission(key, window, start));
+        assert!(!dedup.first_submission(key, window, start + Duration::from_secs(59)));
+        let other = (key.0, Bytes32::from_sized_bytes([2u8; 32]), key.2);
+        assert!(dedup.first_submission(other, window, start));
+        assert!(dedup.first_submission(key, window, start + window));
+        assert!(dedup.first_submission(key, Duration::ZERO, start + window));
+    }
+}
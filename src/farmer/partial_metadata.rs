@@ -0,0 +1,123 @@
+use crate::farmer::config::PoolWalletConfig;
+use crate::HEADERS;
+use dg_xch_clients::protocols::pool::GetPoolInfoResponse;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+//Pools reporting at least this protocol version in pool_info receive diagnostics with every partial
+pub const EXTENDED_PARTIALS_PROTOCOL_VERSION: u8 = 2;
+
+pub fn supports_extended_partials(pool_info: &GetPoolInfoResponse) -> bool {
+    pool_info.protocol_version >= EXTENDED_PARTIALS_PROTOCOL_VERSION
+}
+
+//The pool config decides over the protocol version, for pools that read the headers without
+//announcing it or that should not get them
+pub fn sends_partial_metadata(
+    pool_config: Option<&PoolWalletConfig>,
+    pool_info: &GetPoolInfoResponse,
+) -> bool {
+    pool_config
+        .and_then(|c| c.partial_metadata)
+        .unwrap_or_else(|| supports_extended_partials(pool_info))
+}
+
+//Extra partial diagnostics, sent as headers so the standard partial body stays untouched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialMetadata {
+    pub harvester_id: Uuid,
+    pub plot_size: u8,
+    //Time from the signage point reaching the farmer until the harvester reported the proof
+    pub lookup_time_ms: Option<u64>,
+    //Plot count per compression level of the harvester, only set with client_metadata enabled in
+    //the config
+    #[serde(default)]
+    pub compression: Option<BTreeMap<u8, u64>>,
+}
+impl PartialMetadata {
+    pub fn headers(&self) -> HashMap<String, String> {
+        let mut headers = HEADERS.clone();
+        headers.insert(
+            String::from("X-fast-farmer-harvester-id"),
+            self.harvester_id.to_string(),
+        );
+        headers.insert(
+            String::from("X-fast-farmer-plot-size"),
+            self.plot_size.to_string(),
+        );
+        if let Some(lookup_time_ms) = self.lookup_time_ms {
+            headers.insert(
+                String::from("X-fast-farmer-lookup-time-ms"),
+                lookup_time_ms.to_string(),
+            );
+        }
+        if let Some(compression) = &self.compression {
+            //level:plots pairs, like 0:120,7:300
+            headers.insert(
+                String::from("X-fast-farmer-compression"),
+                compression
+                    .iter()
+                    .map(|(level, plots)| format!("{level}:{plots}"))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+        }
+        headers
+    }
+}
+
+pub fn partial_headers(metadata: &Option<PartialMetadata>) -> HashMap<String, String> {
+    metadata
+        .as_ref()
+        .map(PartialMetadata::headers)
+        .unwrap_or_else(|| HEADERS.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_pools_only_get_default_headers() {
+        assert_eq!(partial_headers(&None), *HEADERS);
+        let metadata = PartialMetadata {
+            harvester_id: Uuid::nil(),
+            plot_size: 32,
+            lookup_time_ms: Some(1250),
+            compression: None,
+        };
+        let headers = partial_headers(&Some(metadata.clone()));
+        assert_eq!(headers.len(), HEADERS.len() + 3);
+        assert_eq!(headers["X-fast-farmer-lookup-time-ms"], "1250");
+        assert_eq!(headers["X-fast-farmer-plot-size"], "32");
+        let headers = partial_headers(&Some(PartialMetadata {
+            compression: Some(BTreeMap::from([(0, 120), (7, 300)])),
+            ..metadata
+        }));
+        assert_eq!(headers.len(), HEADERS.len() + 4);
+        assert_eq!(headers["X-fast-farmer-compression"], "0:120,7:300");
+    }
+
+    #[test]
+    fn pool_config_overrides_protocol_version() {
+        let pool_info = |protocol_version| GetPoolInfoResponse {
+            name: String::new(),
+            logo_url: String::new(),
+            minimum_difficulty: 1,
+            relative_lock_height: 100,
+            protocol_version,
+            fee: String::new(),
+            description: String::new(),
+            target_puzzle_hash: Default::default(),
+            authentication_token_timeout: 5,
+        };
+        let mut pool_config = PoolWalletConfig::default();
+        assert!(!sends_partial_metadata(Some(&pool_config), &pool_info(1)));
+        assert!(sends_partial_metadata(None, &pool_info(2)));
+        pool_config.partial_metadata = Some(true);
+        assert!(sends_partial_metadata(Some(&pool_config), &pool_info(1)));
+        pool_config.partial_metadata = Some(false);
+        assert!(!sends_partial_metadata(Some(&pool_config), &pool_info(2)));
+    }
+}
@@ -0,0 +1,175 @@
+use crate::error::FarmerResult;
+use crate::farmer::chaos;
+use crate::farmer::config::PartialQueueConfig;
+use crate::farmer::partial_metadata::PartialMetadata;
+use dg_xch_clients::protocols::pool::{PoolError, PoolErrorCode, PostPartialRequest};
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedPartial {
+    pub created_at: u64,
+    //Unix time the signage point reached the farmer, unknown for partials queued by older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sp_time: Option<u64>,
+    pub pool_url: String,
+    pub p2_singleton_puzzle_hash: Bytes32,
+    pub request: PostPartialRequest,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<PartialMetadata>,
+    //Pool difficulty when the partial was first submitted, the points it is credited with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<u64>,
+}
+
+//Signed partials the pool could not be reached for, kept on disk until they expire. With the
+//default max_age only a restart finishing within the pool window finds them still valid
+#[derive(Debug, Default)]
+pub struct PartialQueue {
+    path: Option<PathBuf>,
+    max_size: usize,
+    max_age: u64,
+    entries: VecDeque<QueuedPartial>,
+}
+impl PartialQueue {
+    pub fn load(path: &Path, config: &PartialQueueConfig) -> Self {
+        let entries = if path.exists() {
+            match fs::read_to_string(path).map(|s| serde_json::from_str(&s)) {
+                Ok(Ok(entries)) => entries,
+                Ok(Err(e)) => {
+                    warn!("Ignoring invalid partial queue at {:?}: {:?}", path, e);
+                    VecDeque::new()
+                }
+                Err(e) => {
+                    warn!("Failed to read partial queue at {:?}: {:?}", path, e);
+                    VecDeque::new()
+                }
+            }
+        } else {
+            VecDeque::new()
+        };
+        let mut queue = Self {
+            path: Some(path.to_path_buf()),
+            max_size: config.max_size,
+            max_age: config.max_age,
+            entries,
+        };
+        queue.expire();
+        info!("Loaded {} queued partials", queue.entries.len());
+        queue
+    }
+    pub fn is_enabled(&self) -> bool {
+        self.path.is_some() && self.max_size > 0
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn push(&mut self, partial: QueuedPartial) {
+        self.entries.push_back(partial);
+        while self.entries.len() > self.max_size {
+            if let Some(dropped) = self.entries.pop_front() {
+                warn!(
+                    "Partial queue full, dropping oldest partial for {}",
+                    dropped.request.payload.launcher_id
+                );
+            }
+        }
+        self.save_or_warn();
+    }
+    //Returns all partials that are still valid, leaving the queue empty
+    pub fn take_valid(&mut self) -> Vec<QueuedPartial> {
+        self.expire();
+        let taken = self.entries.drain(..).collect();
+        self.save_or_warn();
+        taken
+    }
+    //Puts partials back in front of the queue, oldest first
+    pub fn requeue(&mut self, partials: Vec<QueuedPartial>) {
+        for partial in partials.into_iter().rev() {
+            self.entries.push_front(partial);
+        }
+        self.entries.truncate(self.max_size);
+        self.save_or_warn();
+    }
+    fn expire(&mut self) {
+        let now = now();
+        let before = self.entries.len();
+        //Pools judge a partial by the age of its signage point, not by when it was queued
+        self.entries
+            .retain(|p| now.saturating_sub(p.sp_time.unwrap_or(p.created_at)) <= self.max_age);
+        if self.entries.len() < before {
+            warn!(
+                "Dropped {} queued partials with signage points older than {}s",
+                before - self.entries.len(),
+                self.max_age
+            );
+        }
+    }
+    fn save_or_warn(&self) {
+        if let Err(e) = self.save() {
+            warn!("Failed to save partial queue: {:?}", e);
+        }
+    }
+    fn save(&self) -> FarmerResult<()> {
+        if let Some(path) = &self.path {
+            Ok(fs::write(path, serde_json::to_string(&self.entries)?)?)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+//Errors where the pool never judged the partial, anything else would be rejected again
+pub fn is_retryable(error: &PoolError) -> bool {
+    error.error_code == PoolErrorCode::RequestFailed as u8
+        || error.error_code == PoolErrorCode::ServerException as u8
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+        .saturating_add_signed(chaos::clock_skew_ms() / 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::test_fixtures::partial_request;
+
+    #[test]
+    fn expires_on_the_signage_point_age() {
+        let queued = |created_at, sp_time| QueuedPartial {
+            created_at,
+            sp_time,
+            pool_url: "https://pool.example".to_string(),
+            p2_singleton_puzzle_hash: Bytes32::default(),
+            request: partial_request(),
+            metadata: None,
+            difficulty: None,
+        };
+        let now = now();
+        let mut queue = PartialQueue {
+            max_size: 10,
+            max_age: 25,
+            entries: VecDeque::from([
+                queued(now, Some(now - 30)),
+                queued(now - 30, None),
+                queued(now - 5, Some(now - 20)),
+            ]),
+            ..Default::default()
+        };
+        let valid = queue.take_valid();
+        assert_eq!(valid.len(), 1);
+        assert_eq!(valid[0].sp_time, Some(now - 20));
+    }
+}
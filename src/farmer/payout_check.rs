@@ -0,0 +1,85 @@
+use crate::farmer::config::PayoutMismatchPolicy;
+use dg_xch_core::blockchain::sized_bytes::hex_to_bytes;
+use dg_xch_keys::decode_puzzle_hash;
+use std::io::Error;
+
+//Addresses and puzzle hashes are both compared as lower case puzzle hash hex
+pub fn parse_payout_address(s: String) -> Result<String, Error> {
+    Ok(if s.starts_with("xch") || s.starts_with("txch") {
+        hex::encode(decode_puzzle_hash(&s)?)
+    } else if s.len() == 64 {
+        match hex_to_bytes(&s) {
+            Ok(h) => hex::encode(h),
+            Err(_) => s,
+        }
+    } else {
+        s
+    })
+}
+
+//Payout instructions of a pool that differ from the payout address of the farm
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayoutMismatch {
+    pub configured: String,
+    pub pool: String,
+    pub policy: PayoutMismatchPolicy,
+    pub reported: bool,
+}
+impl PayoutMismatch {
+    //Only halted launchers stop sending partials, correcting ones keep farming while put_farmer is retried
+    pub fn halts_partials(&self) -> bool {
+        self.policy == PayoutMismatchPolicy::Halt
+    }
+}
+
+//None while the pool pays the configured address or the configured address can't be read, then
+//nothing can be compared. Instructions of the pool that don't parse are a mismatch
+pub fn check_payout(
+    configured: &str,
+    pool_instructions: &str,
+    policy: PayoutMismatchPolicy,
+) -> Option<PayoutMismatch> {
+    let configured = parse_payout_address(configured.to_ascii_lowercase()).ok()?;
+    let pool = pool_instructions.to_ascii_lowercase();
+    let pool = pool.strip_prefix("0x").unwrap_or(&pool).to_string();
+    let pool = parse_payout_address(pool.clone()).unwrap_or(pool);
+    (configured != pool).then_some(PayoutMismatch {
+        configured,
+        pool,
+        policy,
+        reported: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dg_xch_core::blockchain::sized_bytes::Bytes32;
+    use dg_xch_keys::encode_puzzle_hash;
+
+    #[test]
+    fn compares_addresses_and_puzzle_hashes() {
+        let puzzle_hash = Bytes32::from_sized_bytes([7u8; 32]);
+        let address = encode_puzzle_hash(&puzzle_hash, "xch").unwrap();
+        let hex = hex::encode(puzzle_hash);
+        assert_eq!(
+            check_payout(
+                &address,
+                &format!("0x{}", hex.to_ascii_uppercase()),
+                Default::default()
+            ),
+            None
+        );
+        let other = "ab".repeat(32);
+        let mismatch = check_payout(&address, &other, PayoutMismatchPolicy::Halt).unwrap();
+        assert_eq!(mismatch.configured, hex);
+        assert_eq!(mismatch.pool, other);
+        assert!(mismatch.halts_partials());
+        assert!(
+            !check_payout(&address, "garbage", PayoutMismatchPolicy::Warn)
+                .unwrap()
+                .halts_partials()
+        );
+        assert_eq!(check_payout("xch1invalid", &hex, Default::default()), None);
+    }
+}
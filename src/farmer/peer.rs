@@ -0,0 +1,169 @@
+use crate::error::{FarmerError, FarmerResult};
+use crate::farmer::config::DnsConfig;
+use crate::farmer::dns::get_client_tls;
+use dg_xch_clients::protocols::shared::{
+    Capability, Handshake, CAPABILITIES, PROTOCOL_VERSION, SOFTWARE_VERSION,
+};
+use dg_xch_clients::protocols::ProtocolMessageTypes;
+use dg_xch_clients::websocket::{oneshot, ChiaMessage, Client, ClientSSLConfig, NodeType};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::io::{Cursor, Error};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+//What the full node reported in its handshake, capabilities only holds the ones both sides enabled
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerInfo {
+    pub protocol_version: String,
+    pub software_version: String,
+    pub capabilities: Vec<u16>,
+}
+impl PeerInfo {
+    pub fn from_handshake(handshake: &Handshake) -> Self {
+        let capabilities = CAPABILITIES
+            .iter()
+            .filter(|(id, value)| {
+                *value == "1"
+                    && handshake
+                        .capabilities
+                        .iter()
+                        .any(|(peer_id, peer_value)| peer_id == id && peer_value == "1")
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        Self {
+            protocol_version: handshake.protocol_version.clone(),
+            software_version: handshake.software_version.clone(),
+            capabilities,
+        }
+    }
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&(capability as u16))
+    }
+    //Newer nodes may append fields to farmer messages that this build does not know yet
+    pub fn is_newer_protocol(&self) -> bool {
+        compare_versions(&self.protocol_version, PROTOCOL_VERSION) == Ordering::Greater
+    }
+}
+
+pub struct FullNodePeer {
+    pub client: Arc<Mutex<Client>>,
+    pub info: PeerInfo,
+    //The handshake is the only farmer protocol exchange the node answers directly
+    pub handshake_rtt: Duration,
+    handle: JoinHandle<()>,
+}
+impl FullNodePeer {
+    pub async fn connect(
+        dns: &Option<DnsConfig>,
+        host: &str,
+        port: u16,
+        ssl_info: ClientSSLConfig<'_>,
+        network_id: &str,
+        run: Arc<AtomicBool>,
+    ) -> FarmerResult<Self> {
+        let (client, mut stream) = get_client_tls(dns, host, port, ssl_info).await?;
+        let handle = tokio::spawn(async move { stream.run(run).await });
+        let client = Arc::new(Mutex::new(client));
+        let started = Instant::now();
+        let handshake = match handshake(client.clone(), network_id, port).await {
+            Ok(handshake) => handshake,
+            Err(e) => {
+                handle.abort();
+                return Err(e.into());
+            }
+        };
+        if handshake.network_id != network_id {
+            handle.abort();
+            return Err(FarmerError::Config(format!(
+                "Full node is on network {}, expected {network_id}",
+                handshake.network_id
+            )));
+        }
+        let handshake_rtt = started.elapsed();
+        let info = PeerInfo::from_handshake(&handshake);
+        info!(
+            "Full node running {} (protocol {}), shared capabilities: {:?}",
+            info.software_version, info.protocol_version, info.capabilities
+        );
+        if info.is_newer_protocol() {
+            warn!(
+                "Full node protocol {} is newer than {PROTOCOL_VERSION}, fields added to farmer messages are ignored",
+                info.protocol_version
+            );
+        }
+        Ok(Self {
+            client,
+            info,
+            handshake_rtt,
+            handle,
+        })
+    }
+    pub fn is_closed(&self) -> bool {
+        self.handle.is_finished()
+    }
+}
+
+async fn handshake(
+    client: Arc<Mutex<Client>>,
+    network_id: &str,
+    port: u16,
+) -> Result<Handshake, Error> {
+    oneshot::<Handshake, Client>(
+        client,
+        ChiaMessage::new(
+            ProtocolMessageTypes::Handshake,
+            &Handshake {
+                network_id: network_id.to_string(),
+                protocol_version: PROTOCOL_VERSION.to_string(),
+                software_version: SOFTWARE_VERSION.to_string(),
+                server_port: port,
+                node_type: NodeType::Farmer as u8,
+                capabilities: CAPABILITIES
+                    .iter()
+                    .map(|e| (e.0, e.1.to_string()))
+                    .collect(),
+            },
+            None,
+        ),
+        Some(ProtocolMessageTypes::Handshake),
+        None,
+        Some(15000),
+    )
+    .await
+}
+
+//Bytes left after parsing the fields this build knows, newer nodes append optional fields at the end
+pub fn unknown_trailing_bytes(cursor: &Cursor<&Vec<u8>>) -> usize {
+    cursor
+        .get_ref()
+        .len()
+        .saturating_sub(cursor.position() as usize)
+}
+
+//Compares dotted versions numerically, so 0.0.100 is newer than 0.0.36
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let parse = |v: &str| -> Vec<u64> {
+        v.split('.')
+            .map(|p| p.trim().parse::<u64>().unwrap_or(0))
+            .collect()
+    };
+    let (a, b) = (parse(a), parse(b));
+    for i in 0..a.len().max(b.len()) {
+        match a
+            .get(i)
+            .copied()
+            .unwrap_or(0)
+            .cmp(&b.get(i).copied().unwrap_or(0))
+        {
+            Ordering::Equal => continue,
+            other => return other,
+        }
+    }
+    Ordering::Equal
+}
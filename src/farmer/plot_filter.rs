@@ -0,0 +1,115 @@
+use log::info;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+//Heights at which the plot filter shrinks, the first step comes with the hard fork
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlotFilterSchedule {
+    pub hard_fork_height: u32,
+    pub plot_filter_128_height: u32,
+    pub plot_filter_64_height: u32,
+    pub plot_filter_32_height: u32,
+}
+impl PlotFilterSchedule {
+    //Networks without a schedule keep the filter of their consensus constants
+    pub const NONE: Self = Self {
+        hard_fork_height: u32::MAX,
+        plot_filter_128_height: u32::MAX,
+        plot_filter_64_height: u32::MAX,
+        plot_filter_32_height: u32::MAX,
+    };
+    pub fn for_network(network: &str) -> Self {
+        match network {
+            "mainnet" => Self {
+                hard_fork_height: 5_496_000,
+                plot_filter_128_height: 10_542_000,
+                plot_filter_64_height: 15_592_000,
+                plot_filter_32_height: 20_643_000,
+            },
+            "testnet11" => Self {
+                hard_fork_height: 0,
+                plot_filter_128_height: 6_029_568,
+                plot_filter_64_height: 11_075_328,
+                plot_filter_32_height: 16_121_856,
+            },
+            _ => Self::NONE,
+        }
+    }
+    //Same steps as calculate_prefix_bits of the full node
+    pub fn prefix_bits(&self, base_bits: usize, height: u32) -> usize {
+        let reduction = if height >= self.plot_filter_32_height {
+            4
+        } else if height >= self.plot_filter_64_height {
+            3
+        } else if height >= self.plot_filter_128_height {
+            2
+        } else if height >= self.hard_fork_height {
+            1
+        } else {
+            0
+        };
+        base_bits.saturating_sub(reduction)
+    }
+}
+
+//Plot filter of a harvester at the latest known peak. Filter bits set in the harvester config are
+//kept as they are, without a known height the consensus filter applies
+pub struct PlotFilter {
+    base_bits: usize,
+    configured: bool,
+    schedule: PlotFilterSchedule,
+    height: Arc<AtomicU32>,
+    bits: AtomicUsize,
+}
+impl PlotFilter {
+    pub fn new(
+        base_bits: usize,
+        configured: bool,
+        schedule: PlotFilterSchedule,
+        height: Arc<AtomicU32>,
+    ) -> Self {
+        Self {
+            base_bits,
+            configured,
+            schedule,
+            height,
+            bits: AtomicUsize::new(base_bits),
+        }
+    }
+    pub fn bits(&self) -> usize {
+        if self.configured {
+            return self.base_bits;
+        }
+        let height = self.height.load(Ordering::Relaxed);
+        let bits = self.schedule.prefix_bits(self.base_bits, height);
+        if self.bits.swap(bits, Ordering::Relaxed) != bits {
+            info!(
+                "Plot filter is 1 in {} at height {height}, eligible plots per signage point change accordingly",
+                1u64 << bits
+            );
+        }
+        bits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_shrinks_at_the_fork_heights() {
+        let mainnet = PlotFilterSchedule::for_network("mainnet");
+        assert_eq!(mainnet.prefix_bits(9, 5_495_999), 9);
+        assert_eq!(mainnet.prefix_bits(9, 5_496_000), 8);
+        assert_eq!(mainnet.prefix_bits(9, 10_542_000), 7);
+        assert_eq!(mainnet.prefix_bits(9, 20_643_000), 5);
+        assert_eq!(PlotFilterSchedule::NONE.prefix_bits(9, u32::MAX - 1), 9);
+        let height = Arc::new(AtomicU32::new(0));
+        let filter = PlotFilter::new(9, false, mainnet, height.clone());
+        assert_eq!(filter.bits(), 9);
+        height.store(10_600_000, Ordering::Relaxed);
+        assert_eq!(filter.bits(), 7);
+        let configured = PlotFilter::new(9, true, mainnet, height);
+        assert_eq!(configured.bits(), 9);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::error::FarmerResult;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+const SAVE_INTERVAL: u64 = 60;
+const DEFAULT_LIMIT: usize = 20;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardSort {
+    Proofs,
+    Partials,
+    #[default]
+    Points,
+    LastProof,
+    Name,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaderboardParams {
+    #[serde(default)]
+    pub sort: LeaderboardSort,
+    //Plots listed, 0 lists all of them
+    #[serde(default)]
+    pub limit: Option<usize>,
+    //Lowest first, to find the plots that never produce
+    #[serde(default)]
+    pub ascending: bool,
+}
+impl LeaderboardParams {
+    //From the query of a dashboard request like sort=proofs&limit=50&ascending=true
+    pub fn from_query(query: &str) -> Self {
+        let mut params = Self::default();
+        for (key, value) in query.split('&').filter_map(|p| p.split_once('=')) {
+            match key {
+                "sort" => {
+                    if let Ok(sort) = serde_json::from_value(serde_json::Value::from(value)) {
+                        params.sort = sort;
+                    }
+                }
+                "limit" => params.limit = value.parse().ok(),
+                "ascending" => params.ascending = value == "true" || value == "1",
+                _ => {}
+            }
+        }
+        params
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlotScore {
+    pub plot: String,
+    pub proofs: u64,
+    //Partials the pools accepted and the difficulty they were credited at
+    pub partials: u64,
+    pub points: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_proof: Option<u64>,
+    //Loaded by a local harvester, plots of remote harvesters only show up once they found a proof
+    #[serde(default)]
+    pub loaded: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct StoredScore {
+    proofs: u64,
+    partials: u64,
+    points: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_proof: Option<u64>,
+}
+
+//The plot file name of a plot identifier, which is the quality string in hex followed by the plot
+//path for both the local and chia harvesters
+pub fn plot_name(plot_identifier: &str) -> String {
+    let path = match plot_identifier.get(..64) {
+        Some(quality) if quality.bytes().all(|b| b.is_ascii_hexdigit()) => &plot_identifier[64..],
+        _ => plot_identifier,
+    };
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+//Proofs and partial points per plot file, kept on disk so the leaderboard covers the farm's
+//lifetime and not only the current run
+#[derive(Debug, Default)]
+pub struct PlotLeaderboard {
+    path: Option<PathBuf>,
+    scores: BTreeMap<String, StoredScore>,
+    last_save: u64,
+}
+impl PlotLeaderboard {
+    pub fn load(path: &Path) -> Self {
+        let scores = match fs::read_to_string(path).map(|s| serde_json::from_str(&s)) {
+            Ok(Ok(scores)) => scores,
+            Ok(Err(e)) => {
+                warn!("Ignoring invalid plot leaderboard at {:?}: {:?}", path, e);
+                BTreeMap::new()
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                warn!("Failed to read plot leaderboard at {:?}: {:?}", path, e);
+                BTreeMap::new()
+            }
+        };
+        Self {
+            path: Some(path.to_path_buf()),
+            scores,
+            last_save: 0,
+        }
+    }
+    pub fn record_proof(&mut self, plot_identifier: &str, now: u64) {
+        let score = self.scores.entry(plot_name(plot_identifier)).or_default();
+        score.proofs += 1;
+        score.last_proof = Some(now);
+        self.save_due(now);
+    }
+    pub fn record_partial(&mut self, plot_identifier: &str, points: u64, now: u64) {
+        let score = self.scores.entry(plot_name(plot_identifier)).or_default();
+        score.partials += 1;
+        score.points += points;
+        self.save_due(now);
+    }
+    //Plots the local harvesters load are listed even without a proof yet
+    pub fn entries(&self, loaded: &[String], params: &LeaderboardParams) -> Vec<PlotScore> {
+        let mut entries: BTreeMap<&str, PlotScore> = self
+            .scores
+            .iter()
+            .map(|(plot, s)| {
+                (
+                    plot.as_str(),
+                    PlotScore {
+                        plot: plot.clone(),
+                        proofs: s.proofs,
+                        partials: s.partials,
+                        points: s.points,
+                        last_proof: s.last_proof,
+                        loaded: false,
+                    },
+                )
+            })
+            .collect();
+        for plot in loaded {
+            entries
+                .entry(plot.as_str())
+                .or_insert_with(|| PlotScore {
+                    plot: plot.clone(),
+                    ..Default::default()
+                })
+                .loaded = true;
+        }
+        let mut entries: Vec<PlotScore> = entries.into_values().collect();
+        //Stable, ties stay sorted by name
+        entries.sort_by(|a, b| {
+            let order = match params.sort {
+                LeaderboardSort::Proofs => a.proofs.cmp(&b.proofs),
+                LeaderboardSort::Partials => a.partials.cmp(&b.partials),
+                LeaderboardSort::Points => a.points.cmp(&b.points),
+                LeaderboardSort::LastProof => a.last_proof.cmp(&b.last_proof),
+                //Alphabetical unless ascending is asked for
+                LeaderboardSort::Name => b.plot.cmp(&a.plot),
+            };
+            if params.ascending {
+                order
+            } else {
+                order.reverse()
+            }
+        });
+        match params.limit.unwrap_or(DEFAULT_LIMIT) {
+            0 => entries,
+            limit => entries.into_iter().take(limit).collect(),
+        }
+    }
+    fn save_due(&mut self, now: u64) {
+        if now >= self.last_save + SAVE_INTERVAL {
+            self.last_save = now;
+            if let Err(e) = self.save() {
+                warn!("Failed to save plot leaderboard: {:?}", e);
+            }
+        }
+    }
+    pub fn save(&self) -> FarmerResult<()> {
+        if let Some(path) = &self.path {
+            Ok(fs::write(path, serde_json::to_string(&self.scores)?)?)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_plots_and_lists_the_silent_ones() {
+        let quality = "ab".repeat(32);
+        let plot = "plot-k32-2024-01-01-00-00-aa.plot";
+        assert_eq!(plot_name(&format!("{quality}/mnt/a/{plot}")), plot);
+        assert_eq!(plot_name(&format!("{quality}{plot}")), plot);
+        assert_eq!(plot_name("short.plot"), "short.plot");
+
+        let mut leaderboard = PlotLeaderboard::default();
+        leaderboard.record_proof(&format!("{quality}a.plot"), 10);
+        leaderboard.record_proof(&format!("{quality}a.plot"), 20);
+        leaderboard.record_partial(&format!("{quality}a.plot"), 5, 20);
+        leaderboard.record_proof(&format!("{quality}b.plot"), 30);
+        leaderboard.record_partial(&format!("{quality}b.plot"), 50, 30);
+        let loaded = ["a.plot".to_string(), "c.plot".to_string()];
+
+        let by_points = leaderboard.entries(&loaded, &LeaderboardParams::default());
+        let names: Vec<&str> = by_points.iter().map(|s| s.plot.as_str()).collect();
+        assert_eq!(names, ["b.plot", "a.plot", "c.plot"]);
+        assert_eq!(by_points[1].proofs, 2);
+        assert_eq!(by_points[1].last_proof, Some(20));
+        assert!(!by_points[0].loaded && by_points[2].loaded);
+
+        let params = LeaderboardParams::from_query("sort=proofs&limit=1&ascending=true");
+        assert_eq!(params.sort, LeaderboardSort::Proofs);
+        let silent = leaderboard.entries(&loaded, &params);
+        assert_eq!(silent.len(), 1);
+        assert_eq!(silent[0].plot, "c.plot");
+        assert_eq!(silent[0].proofs, 0);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::farmer::config::PoolWalletConfig;
+use blst::min_pk::SecretKey;
+use dg_xch_clients::protocols::pool::{AuthenticationPayload, PoolError, PoolErrorCode};
+use dg_xch_core::blockchain::sized_bytes::Bytes96;
+use dg_xch_core::clvm::bls_bindings::{sign, verify_signature};
+use dg_xch_serialize::{hash_256, ChiaSerialize};
+use log::warn;
+
+//Pool errors caused by the authentication key or token rather than the proof
+pub fn is_auth_error(error_code: u8) -> bool {
+    error_code == PoolErrorCode::InvalidSignature as u8
+        || error_code == PoolErrorCode::InvalidAuthenticationToken as u8
+}
+
+//Authentication state of one plot NFT. The GET /farmer signature only covers the launcher, target
+//and token, so it is signed and verified once per token window instead of on every update
+#[derive(Debug, Clone, Default)]
+pub struct PoolAuth {
+    get_farmer: Option<(u64, Bytes96)>,
+    pub failures: u64,
+}
+impl PoolAuth {
+    pub fn get_farmer_signature(
+        &mut self,
+        pool_config: &PoolWalletConfig,
+        authentication_token: u64,
+        authentication_sk: &SecretKey,
+    ) -> Result<Bytes96, PoolError> {
+        if let Some((token, signature)) = &self.get_farmer {
+            if *token == authentication_token {
+                return Ok(*signature);
+            }
+        }
+        let msg = AuthenticationPayload {
+            method_name: "get_farmer".to_string(),
+            launcher_id: pool_config.launcher_id,
+            target_puzzle_hash: pool_config.target_puzzle_hash,
+            authentication_token,
+        }
+        .to_bytes();
+        let to_sign = hash_256(&msg);
+        let signature = sign(authentication_sk, &to_sign);
+        if !verify_signature(&authentication_sk.sk_to_pk(), &to_sign, &signature) {
+            return Err(PoolError {
+                error_code: PoolErrorCode::InvalidSignature as u8,
+                error_message: "Local Failed to Validate Signature".to_string(),
+            });
+        }
+        let signature: Bytes96 = signature.to_bytes().into();
+        self.get_farmer = Some((authentication_token, signature));
+        Ok(signature)
+    }
+    //Counts authentication errors and drops the cached signature so the next request signs anew,
+    //returns false for errors that have nothing to do with authentication
+    pub fn record_error(&mut self, pool_config: &PoolWalletConfig, error: &PoolError) -> bool {
+        if !is_auth_error(error.error_code) {
+            return false;
+        }
+        self.failures += 1;
+        self.get_farmer = None;
+        warn!(
+            "Authentication for {} rejected by {} ({} failures): ({}) {}",
+            pool_config.launcher_id,
+            pool_config.pool_url,
+            self.failures,
+            error.error_code,
+            error.error_message
+        );
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_signature_within_token_window() {
+        let sk = SecretKey::key_gen(&[7u8; 32], &[]).unwrap();
+        let pool_config = PoolWalletConfig::default();
+        let mut auth = PoolAuth::default();
+        let first = auth.get_farmer_signature(&pool_config, 100, &sk).unwrap();
+        assert_eq!(
+            auth.get_farmer_signature(&pool_config, 100, &sk).unwrap(),
+            first
+        );
+        assert_ne!(
+            auth.get_farmer_signature(&pool_config, 101, &sk).unwrap(),
+            first
+        );
+        let not_auth = PoolError {
+            error_code: PoolErrorCode::TooLate as u8,
+            error_message: String::new(),
+        };
+        assert!(!auth.record_error(&pool_config, &not_auth));
+        let token = PoolError {
+            error_code: PoolErrorCode::InvalidAuthenticationToken as u8,
+            error_message: String::new(),
+        };
+        assert!(auth.record_error(&pool_config, &token));
+        assert_eq!(auth.failures, 1);
+        assert!(auth.get_farmer.is_none());
+    }
+}
@@ -0,0 +1,414 @@
+use crate::farmer::chaos;
+use crate::farmer::config::{Config, PoolEndpoints, PoolTlsConfig};
+use crate::farmer::dns::DnsResolver;
+use crate::farmer::partial_batch::{
+    parse_batch_response, BatchError, BatchPartial, PartialResult, PostPartialsRequest,
+};
+use crate::farmer::pool_endpoints::{custom_url, endpoint_url, PoolEndpoint};
+use crate::HEADERS;
+use async_trait::async_trait;
+use dg_xch_clients::api::pool::{DefaultPoolClient, PoolClient};
+use dg_xch_clients::protocols::pool::{
+    GetFarmerRequest, GetFarmerResponse, GetPoolInfoResponse, PoolError, PoolErrorCode,
+    PostFarmerRequest, PostFarmerResponse, PostPartialRequest, PostPartialResponse,
+    PutFarmerRequest, PutFarmerResponse,
+};
+use log::{info, warn};
+use reqwest::{Certificate, Client, ClientBuilder, RequestBuilder, StatusCode};
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::ServerName;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+//Routes each pool url to its own client, pools without tls options share the default one.
+//Pools with endpoint templates are sent to those urls instead of the standard layout
+#[derive(Debug, Default)]
+pub struct PoolClients {
+    default: DefaultPoolClient,
+    pools: HashMap<String, DefaultPoolClient>,
+    endpoints: HashMap<String, PoolEndpoints>,
+}
+impl PoolClients {
+    fn client(&self, url: &str) -> &DefaultPoolClient {
+        self.pools
+            .get(url.trim_end_matches('/'))
+            .unwrap_or(&self.default)
+    }
+    fn custom_url(&self, url: &str, endpoint: PoolEndpoint) -> Option<String> {
+        self.endpoints
+            .get(url.trim_end_matches('/'))
+            .and_then(|e| custom_url(url, e, endpoint))
+    }
+    pub fn endpoint_url(&self, url: &str, endpoint: PoolEndpoint) -> String {
+        endpoint_url(url, self.endpoints.get(url.trim_end_matches('/')), endpoint)
+    }
+    //Pools with a partials endpoint in their config, the pool protocol has no batch endpoint
+    pub fn batch_pools(&self) -> Vec<String> {
+        self.endpoints
+            .iter()
+            .filter(|(_, e)| e.partials.is_some())
+            .map(|(url, _)| url.clone())
+            .collect()
+    }
+    //Sends partials of one pool in a single request, pools supporting it answer with a result per
+    //partial in the order they were sent
+    pub async fn post_partials(
+        &self,
+        url: &str,
+        partials: &[BatchPartial],
+    ) -> Result<Vec<PartialResult>, BatchError> {
+        if let Some(e) = chaos::pool_error(url) {
+            return Err(BatchError::Failed(e.error_message));
+        }
+        let Some(batch_url) = self.custom_url(url, PoolEndpoint::Partials) else {
+            return Err(BatchError::Unsupported);
+        };
+        let mut request_builder = self.client(url).client.post(batch_url);
+        for (k, v) in HEADERS.iter() {
+            request_builder = request_builder.header(k, v);
+        }
+        let request = PostPartialsRequest {
+            partials: partials.iter().collect(),
+        };
+        match request_builder.json(&request).send().await {
+            Ok(resp) => match resp.status() {
+                StatusCode::OK => match resp.text().await {
+                    Ok(body) => {
+                        parse_batch_response(&body, partials.len()).map_err(BatchError::Failed)
+                    }
+                    Err(e) => Err(BatchError::Failed(e.to_string())),
+                },
+                StatusCode::NOT_FOUND
+                | StatusCode::METHOD_NOT_ALLOWED
+                | StatusCode::NOT_IMPLEMENTED => Err(BatchError::Unsupported),
+                status => Err(BatchError::Failed(format!("bad status code {status}"))),
+            },
+            Err(e) => Err(BatchError::Failed(e.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl PoolClient for PoolClients {
+    async fn get_farmer(
+        &self,
+        url: &str,
+        request: GetFarmerRequest,
+        headers: &Option<HashMap<String, String>>,
+    ) -> Result<GetFarmerResponse, PoolError> {
+        if let Some(e) = chaos::pool_error(url) {
+            return Err(e);
+        }
+        match self.custom_url(url, PoolEndpoint::Farmer) {
+            Some(custom) => {
+                let builder = self.client(url).client.get(custom).query(&request);
+                send(builder, headers, "Get Farmer").await
+            }
+            None => self.client(url).get_farmer(url, request, headers).await,
+        }
+    }
+    async fn post_farmer(
+        &self,
+        url: &str,
+        request: PostFarmerRequest,
+        headers: &Option<HashMap<String, String>>,
+    ) -> Result<PostFarmerResponse, PoolError> {
+        if let Some(e) = chaos::pool_error(url) {
+            return Err(e);
+        }
+        match self.custom_url(url, PoolEndpoint::Farmer) {
+            Some(custom) => {
+                let builder = self.client(url).client.post(custom).json(&request);
+                send(builder, headers, "Post Farmer").await
+            }
+            None => self.client(url).post_farmer(url, request, headers).await,
+        }
+    }
+    async fn put_farmer(
+        &self,
+        url: &str,
+        request: PutFarmerRequest,
+        headers: &Option<HashMap<String, String>>,
+    ) -> Result<PutFarmerResponse, PoolError> {
+        if let Some(e) = chaos::pool_error(url) {
+            return Err(e);
+        }
+        match self.custom_url(url, PoolEndpoint::Farmer) {
+            Some(custom) => {
+                let builder = self.client(url).client.put(custom).json(&request);
+                send(builder, headers, "Put Farmer").await
+            }
+            None => self.client(url).put_farmer(url, request, headers).await,
+        }
+    }
+    async fn post_partial(
+        &self,
+        url: &str,
+        request: PostPartialRequest,
+        headers: &Option<HashMap<String, String>>,
+    ) -> Result<PostPartialResponse, PoolError> {
+        if let Some(e) = chaos::pool_error(url) {
+            return Err(e);
+        }
+        match self.custom_url(url, PoolEndpoint::Partial) {
+            Some(custom) => {
+                let builder = self.client(url).client.post(custom).json(&request);
+                send(builder, headers, "Post Partial").await
+            }
+            None => self.client(url).post_partial(url, request, headers).await,
+        }
+    }
+    async fn get_pool_info(&self, pool_url: &str) -> Result<GetPoolInfoResponse, PoolError> {
+        if let Some(e) = chaos::pool_error(pool_url) {
+            return Err(e);
+        }
+        match self.custom_url(pool_url, PoolEndpoint::PoolInfo) {
+            Some(custom) => {
+                let builder = self.client(pool_url).client.get(custom);
+                send(builder, &None, "Get Pool Info").await
+            }
+            None => self.client(pool_url).get_pool_info(pool_url).await,
+        }
+    }
+}
+
+fn request_failed(error_message: String) -> PoolError {
+    PoolError {
+        error_code: PoolErrorCode::RequestFailed as u8,
+        error_message,
+    }
+}
+
+//Same handling of the answer as the standard pool client, so retries and error codes do not
+//depend on whether a pool uses endpoint templates
+async fn send<T: DeserializeOwned>(
+    mut builder: RequestBuilder,
+    headers: &Option<HashMap<String, String>>,
+    what: &str,
+) -> Result<T, PoolError> {
+    for (k, v) in headers.iter().flatten() {
+        builder = builder.header(k, v);
+    }
+    let resp = builder.send().await.map_err(|e| {
+        warn!("Failed to send {what}: {:?}", e);
+        request_failed(e.to_string())
+    })?;
+    let status = resp.status();
+    let body = resp.text().await.map_err(|e| {
+        warn!("Failed to {what}, Invalid Body: {:?}", e);
+        request_failed(e.to_string())
+    })?;
+    if status != StatusCode::OK {
+        warn!("Failed to {what}, Bad Status Code: {:?}, {}", status, body);
+        return Err(request_failed(format!(
+            "Failed to {what}, Bad Status Code: {:?}, {}",
+            status, body
+        )));
+    }
+    match serde_json::from_str(&body) {
+        Ok(c) => Ok(c),
+        Err(_) => match serde_json::from_str::<PoolError>(&body) {
+            Ok(e) => {
+                warn!("Failed to {what}: {:?}", e);
+                Err(e)
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to parse {what} response, Invalid Json: {:?}, {}",
+                    e, body
+                );
+                Err(request_failed(e.to_string()))
+            }
+        },
+    }
+}
+
+fn client_builder(config: &Config) -> ClientBuilder {
+    let builder = Client::builder();
+    match &config.dns {
+        //Resolution is overridden per request, the URL hostname is still used for SNI and Host headers
+        Some(dns) => builder.dns_resolver(Arc::new(DnsResolver::new(dns.clone()))),
+        None => builder,
+    }
+}
+
+pub fn build_pool_client(config: &Config) -> PoolClients {
+    let default = match client_builder(config)
+        .danger_accept_invalid_certs(true)
+        .build()
+    {
+        Ok(client) => DefaultPoolClient { client },
+        Err(e) => {
+            warn!("Failed to build pool client with dns config, using defaults: {e:?}");
+            DefaultPoolClient::new()
+        }
+    };
+    let mut pools = HashMap::new();
+    let mut endpoints = HashMap::new();
+    for pool in config.all_farms().iter().flat_map(|f| f.pool_info.iter()) {
+        if let Some(pool_endpoints) = &pool.endpoints {
+            endpoints.insert(
+                pool.pool_url.trim_end_matches('/').to_string(),
+                pool_endpoints.clone(),
+            );
+        }
+        let Some(tls) = &pool.tls else {
+            continue;
+        };
+        match build_tls_client(config, tls) {
+            Ok(client) => {
+                info!("Using custom TLS settings for pool {}", pool.pool_url);
+                pools.insert(
+                    pool.pool_url.trim_end_matches('/').to_string(),
+                    DefaultPoolClient { client },
+                );
+            }
+            //Falling back to the default client would silently drop the pinning, so the pool gets none
+            Err(e) => {
+                warn!(
+                    "Invalid tls config for pool {}, requests to it will fail: {e}",
+                    pool.pool_url
+                );
+                pools.insert(
+                    pool.pool_url.trim_end_matches('/').to_string(),
+                    DefaultPoolClient {
+                        client: client_builder(config)
+                            .https_only(true)
+                            .tls_built_in_root_certs(false)
+                            .build()
+                            .unwrap_or_default(),
+                    },
+                );
+            }
+        }
+    }
+    PoolClients {
+        default,
+        pools,
+        endpoints,
+    }
+}
+
+fn build_tls_client(config: &Config, tls: &PoolTlsConfig) -> Result<Client, String> {
+    let builder = client_builder(config);
+    let builder = if tls.insecure_skip_verify {
+        warn!("Certificate verification is disabled for a pool, only use insecure_skip_verify for testing");
+        builder.danger_accept_invalid_certs(true)
+    } else if !tls.pinned_sha256.is_empty() {
+        let pins = tls
+            .pinned_sha256
+            .iter()
+            .map(|pin| parse_fingerprint(pin))
+            .collect::<Result<Vec<[u8; 32]>, String>>()?;
+        let tls_config = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier { pins }))
+            .with_no_client_auth();
+        builder.use_preconfigured_tls(tls_config)
+    } else if let Some(ca_file) = &tls.ca_file {
+        let pem = std::fs::read(ca_file).map_err(|e| format!("failed to read {ca_file}: {e}"))?;
+        let ca = Certificate::from_pem(&pem).map_err(|e| format!("invalid {ca_file}: {e}"))?;
+        builder.add_root_certificate(ca)
+    } else {
+        builder
+    };
+    builder.build().map_err(|e| format!("{e:?}"))
+}
+
+//Accepts colon separated fingerprints as printed by openssl
+fn parse_fingerprint(pin: &str) -> Result<[u8; 32], String> {
+    let hex_pin: String = pin.chars().filter(|c| *c != ':').collect();
+    hex::decode(&hex_pin)
+        .ok()
+        .and_then(|b| <[u8; 32]>::try_from(b).ok())
+        .ok_or_else(|| format!("{pin} is not a SHA-256 fingerprint"))
+}
+
+//Pinned certificates are trusted on their own, self signed ones included, the hostname is not checked
+struct PinnedCertVerifier {
+    pins: Vec<[u8; 32]>,
+}
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let fingerprint: [u8; 32] = Sha256::digest(&end_entity.0).into();
+        if self.pins.contains(&fingerprint) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(format!(
+                "certificate {} is not pinned",
+                hex::encode(fingerprint)
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::config::{FarmingInfo, PoolWalletConfig};
+
+    #[test]
+    fn routes_pools_with_tls_options_to_their_client() {
+        let pin = "AB:".repeat(31) + "AB";
+        assert_eq!(parse_fingerprint(&pin), Ok([0xab; 32]));
+        assert!(parse_fingerprint("abcd").is_err());
+        let config = Config {
+            farmer_info: vec![FarmingInfo::default()],
+            pool_info: vec![
+                PoolWalletConfig {
+                    pool_url: "https://private.pool/".to_string(),
+                    tls: Some(PoolTlsConfig {
+                        pinned_sha256: vec![pin],
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                PoolWalletConfig {
+                    pool_url: "https://public.pool".to_string(),
+                    ..Default::default()
+                },
+                PoolWalletConfig {
+                    pool_url: "https://prefixed.pool:8443".to_string(),
+                    endpoints: Some(PoolEndpoints {
+                        partial: Some("{pool_url}/api/v1/partial".to_string()),
+                        partials: Some("{pool_url}/api/v1/partials".to_string()),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let clients = build_pool_client(&config);
+        assert_eq!(clients.pools.len(), 1);
+        assert!(std::ptr::eq(
+            clients.client("https://public.pool"),
+            &clients.default
+        ));
+        assert!(!std::ptr::eq(
+            clients.client("https://private.pool"),
+            &clients.default
+        ));
+        assert_eq!(
+            clients.endpoint_url("https://prefixed.pool:8443/", PoolEndpoint::Partial),
+            "https://prefixed.pool:8443/api/v1/partial"
+        );
+        assert_eq!(
+            clients.custom_url("https://prefixed.pool:8443", PoolEndpoint::Farmer),
+            None
+        );
+        //Only pools that configure a batch endpoint get batches
+        assert_eq!(clients.batch_pools(), vec!["https://prefixed.pool:8443"]);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::error::FarmerResult;
+use crate::farmer::partial_queue::now;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredDifficulty {
+    pub pool_url: String,
+    pub difficulty: u64,
+    pub updated: u64,
+}
+
+//The difficulty each pool last assigned per launcher, so a restart resumes at it instead of the
+//configured or minimum difficulty. A launcher that moved to another pool starts over
+#[derive(Debug, Default)]
+pub struct PoolDifficultyStore {
+    path: Option<PathBuf>,
+    entries: HashMap<String, StoredDifficulty>,
+}
+impl PoolDifficultyStore {
+    pub fn load(path: &Path) -> Self {
+        let entries = if path.exists() {
+            match fs::read_to_string(path).map(|s| serde_json::from_str(&s)) {
+                Ok(Ok(entries)) => entries,
+                Ok(Err(e)) => {
+                    warn!("Ignoring invalid pool difficulties at {:?}: {:?}", path, e);
+                    HashMap::new()
+                }
+                Err(e) => {
+                    warn!("Failed to read pool difficulties at {:?}: {:?}", path, e);
+                    HashMap::new()
+                }
+            }
+        } else {
+            HashMap::new()
+        };
+        info!("Loaded {} stored pool difficulties", entries.len());
+        Self {
+            path: Some(path.to_path_buf()),
+            entries,
+        }
+    }
+    pub fn get(&self, launcher_id: &Bytes32, pool_url: &str) -> Option<u64> {
+        self.entries
+            .get(&launcher_id.to_string())
+            .filter(|e| e.pool_url == pool_url)
+            .map(|e| e.difficulty)
+    }
+    //Only writes the file when a difficulty changed
+    pub fn set(&mut self, launcher_id: &Bytes32, pool_url: &str, difficulty: u64) {
+        if self.get(launcher_id, pool_url) == Some(difficulty) {
+            return;
+        }
+        self.entries.insert(
+            launcher_id.to_string(),
+            StoredDifficulty {
+                pool_url: pool_url.to_string(),
+                difficulty,
+                updated: now(),
+            },
+        );
+        if let Err(e) = self.save() {
+            warn!("Failed to save pool difficulties: {:?}", e);
+        }
+    }
+    fn save(&self) -> FarmerResult<()> {
+        if let Some(path) = &self.path {
+            Ok(fs::write(
+                path,
+                serde_json::to_string_pretty(&self.entries)?,
+            )?)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn difficulty_survives_a_reload_for_the_same_pool() {
+        let path = std::env::temp_dir().join(format!(
+            "ff_pool_difficulty_test_{}.json",
+            std::process::id()
+        ));
+        let launcher = Bytes32::from_sized_bytes([7u8; 32]);
+        let mut store = PoolDifficultyStore::load(&path);
+        store.set(&launcher, "https://pool.example", 120);
+        let store = PoolDifficultyStore::load(&path);
+        assert_eq!(store.get(&launcher, "https://pool.example"), Some(120));
+        assert_eq!(store.get(&launcher, "https://other.example"), None);
+        fs::remove_file(path).unwrap();
+    }
+}
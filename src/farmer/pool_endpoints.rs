@@ -0,0 +1,155 @@
+use crate::farmer::config::PoolEndpoints;
+use reqwest::Url;
+
+const POOL_URL: &str = "{pool_url}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolEndpoint {
+    PoolInfo,
+    Farmer,
+    Partial,
+    Partials,
+    Login,
+}
+impl PoolEndpoint {
+    const ALL: [PoolEndpoint; 5] = [
+        PoolEndpoint::PoolInfo,
+        PoolEndpoint::Farmer,
+        PoolEndpoint::Partial,
+        PoolEndpoint::Partials,
+        PoolEndpoint::Login,
+    ];
+    fn path(&self) -> &'static str {
+        match self {
+            PoolEndpoint::PoolInfo => "pool_info",
+            PoolEndpoint::Farmer => "farmer",
+            PoolEndpoint::Partial => "partial",
+            PoolEndpoint::Partials => "partials",
+            PoolEndpoint::Login => "login",
+        }
+    }
+    fn template(self, endpoints: &PoolEndpoints) -> Option<&String> {
+        match self {
+            PoolEndpoint::PoolInfo => endpoints.pool_info.as_ref(),
+            PoolEndpoint::Farmer => endpoints.farmer.as_ref(),
+            PoolEndpoint::Partial => endpoints.partial.as_ref(),
+            PoolEndpoint::Partials => endpoints.partials.as_ref(),
+            PoolEndpoint::Login => endpoints.login.as_ref(),
+        }
+    }
+}
+
+//The configured url of the endpoint, None when the pool serves it at the standard path
+pub fn custom_url(
+    pool_url: &str,
+    endpoints: &PoolEndpoints,
+    endpoint: PoolEndpoint,
+) -> Option<String> {
+    endpoint
+        .template(endpoints)
+        .map(|t| t.replace(POOL_URL, pool_url.trim_end_matches('/')))
+}
+
+pub fn endpoint_url(
+    pool_url: &str,
+    endpoints: Option<&PoolEndpoints>,
+    endpoint: PoolEndpoint,
+) -> String {
+    endpoints
+        .and_then(|e| custom_url(pool_url, e, endpoint))
+        .unwrap_or_else(|| format!("{}/{}", pool_url.trim_end_matches('/'), endpoint.path()))
+}
+
+//Login link of the pool web ui for one launcher, the query is appended to a template that may
+//already have one
+pub fn login_url(
+    pool_url: &str,
+    endpoints: Option<&PoolEndpoints>,
+    launcher_id: &str,
+    authentication_token: u64,
+    signature: &str,
+) -> String {
+    let url = endpoint_url(pool_url, endpoints, PoolEndpoint::Login);
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{separator}launcher_id={launcher_id}&authentication_token={authentication_token}&signature={signature}")
+}
+
+//Every set endpoint has to be an http(s) url once {pool_url} is filled in
+pub fn validate_endpoints(pool_url: &str, endpoints: &PoolEndpoints) -> Result<(), String> {
+    for endpoint in PoolEndpoint::ALL {
+        let Some(template) = endpoint.template(endpoints) else {
+            continue;
+        };
+        let url = template.replace(POOL_URL, pool_url.trim_end_matches('/'));
+        if url.contains(['{', '}']) {
+            return Err(format!(
+                "{} url {template} has a placeholder other than {POOL_URL}",
+                endpoint.path()
+            ));
+        }
+        match Url::parse(&url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+            Ok(parsed) => {
+                return Err(format!(
+                    "{} url {url} uses {} instead of http or https",
+                    endpoint.path(),
+                    parsed.scheme()
+                ))
+            }
+            Err(e) => return Err(format!("{} url {url} is invalid: {e}", endpoint.path())),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_in_templates_and_keeps_the_standard_layout() {
+        let endpoints = PoolEndpoints {
+            partial: Some("{pool_url}/v2/submit".to_string()),
+            farmer: Some("https://api.pool.example:8443/pool/farmer".to_string()),
+            ..Default::default()
+        };
+        let pool = "https://pool.example/";
+        assert_eq!(
+            endpoint_url(pool, Some(&endpoints), PoolEndpoint::Partial),
+            "https://pool.example/v2/submit"
+        );
+        assert_eq!(
+            endpoint_url(pool, Some(&endpoints), PoolEndpoint::Farmer),
+            "https://api.pool.example:8443/pool/farmer"
+        );
+        assert_eq!(
+            endpoint_url(pool, Some(&endpoints), PoolEndpoint::PoolInfo),
+            "https://pool.example/pool_info"
+        );
+        assert_eq!(custom_url(pool, &endpoints, PoolEndpoint::Partials), None);
+        assert_eq!(
+            login_url(pool, None, "aa", 7, "bb"),
+            "https://pool.example/login?launcher_id=aa&authentication_token=7&signature=bb"
+        );
+        let with_query = PoolEndpoints {
+            login: Some("{pool_url}/ui/?lang=en".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            login_url(pool, Some(&with_query), "aa", 7, "bb"),
+            "https://pool.example/ui/?lang=en&launcher_id=aa&authentication_token=7&signature=bb"
+        );
+        assert_eq!(validate_endpoints(pool, &endpoints), Ok(()));
+        for bad in [
+            "{pool_url}/{launcher_id}",
+            "ftp://pool.example/login",
+            "pool",
+        ] {
+            let endpoints = PoolEndpoints {
+                login: Some(bad.to_string()),
+                ..Default::default()
+            };
+            assert!(validate_endpoints(pool, &endpoints).is_err(), "{bad}");
+        }
+    }
+}
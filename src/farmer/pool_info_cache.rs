@@ -0,0 +1,83 @@
+use crate::error::FarmerResult;
+use dg_xch_clients::protocols::pool::GetPoolInfoResponse;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedPoolInfo {
+    pub fetched_at: u64,
+    pub pool_info: GetPoolInfoResponse,
+}
+
+#[derive(Debug, Default)]
+pub struct PoolInfoCache {
+    path: Option<PathBuf>,
+    entries: HashMap<String, CachedPoolInfo>,
+}
+impl PoolInfoCache {
+    pub fn load(path: &Path) -> Self {
+        let entries = if path.exists() {
+            match fs::read_to_string(path).map(|s| serde_json::from_str(&s)) {
+                Ok(Ok(entries)) => entries,
+                Ok(Err(e)) => {
+                    warn!("Ignoring invalid pool info cache at {:?}: {:?}", path, e);
+                    HashMap::new()
+                }
+                Err(e) => {
+                    warn!("Failed to read pool info cache at {:?}: {:?}", path, e);
+                    HashMap::new()
+                }
+            }
+        } else {
+            HashMap::new()
+        };
+        info!("Loaded {} cached pool info entries", entries.len());
+        Self {
+            path: Some(path.to_path_buf()),
+            entries,
+        }
+    }
+    pub fn insert(&mut self, pool_url: &str, pool_info: GetPoolInfoResponse) {
+        self.entries.insert(
+            pool_url.to_string(),
+            CachedPoolInfo {
+                fetched_at: now(),
+                pool_info,
+            },
+        );
+        if let Err(e) = self.save() {
+            warn!("Failed to save pool info cache: {:?}", e);
+        }
+    }
+    pub fn get(&self, pool_url: &str, ttl: u64) -> Option<(&GetPoolInfoResponse, u64)> {
+        self.entries.get(pool_url).and_then(|c| {
+            let age = now().saturating_sub(c.fetched_at);
+            if age <= ttl {
+                Some((&c.pool_info, age))
+            } else {
+                None
+            }
+        })
+    }
+    fn save(&self) -> FarmerResult<()> {
+        if let Some(path) = &self.path {
+            Ok(fs::write(
+                path,
+                serde_json::to_string_pretty(&self.entries)?,
+            )?)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,188 @@
+use crate::farmer::config::PointsReconciliationConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+//A day of GET /farmer results at the 5 minute update interval
+const MAX_SAMPLES: usize = 288;
+//Fewer partials than this are too noisy to compare
+const MIN_PARTIALS: u64 = 10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolPointsSample {
+    pub timestamp: u64,
+    pub pool_points: u64,
+    pub difficulty: u64,
+    //Points of partials accepted since the previous sample
+    pub local_points: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointsDiscrepancy {
+    pub local_points: u64,
+    pub pool_points: u64,
+    pub windows: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PointsWindow {
+    started: u64,
+    local_points: u64,
+    pool_points: u64,
+}
+
+//Points reported by the pool next to the points of partials it accepted. Pools confirm partials
+//with a delay, so the points accepted since the latest sample are not expected to be credited yet.
+//A drop of the pool points is a payout, the comparison starts over from there
+#[derive(Debug, Clone, Default)]
+pub struct PoolPointsHistory {
+    samples: VecDeque<PoolPointsSample>,
+    pending_local: u64,
+    window: Option<PointsWindow>,
+    //Sum of the consecutive windows the pool credited too few points in
+    short: PointsWindow,
+    short_windows: u32,
+    pub(crate) unreported: Vec<PointsDiscrepancy>,
+}
+impl PoolPointsHistory {
+    pub fn record_local(&mut self, difficulty: u64) {
+        self.pending_local += difficulty;
+    }
+    pub fn record_pool(&mut self, timestamp: u64, pool_points: u64, difficulty: u64) {
+        match self.samples.back() {
+            Some(last) if pool_points < last.pool_points => {
+                self.samples.clear();
+                self.window = None;
+            }
+            //Points credited since the last sample are for the partials accepted before it
+            Some(last) => {
+                let window = self.window.get_or_insert(PointsWindow {
+                    started: last.timestamp,
+                    ..Default::default()
+                });
+                window.local_points += last.local_points;
+                window.pool_points += pool_points - last.pool_points;
+            }
+            None => {}
+        }
+        self.samples.push_back(PoolPointsSample {
+            timestamp,
+            pool_points,
+            difficulty,
+            local_points: std::mem::take(&mut self.pending_local),
+        });
+        while self.samples.len() > MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+    //Closes the window once it is long enough. Windows with too few partials leave the count of
+    //short windows as is, the discrepancy is reported once when it reaches config.windows
+    pub fn reconcile(&mut self, now: u64, config: &PointsReconciliationConfig) {
+        let Some(window) = self
+            .window
+            .filter(|w| now.saturating_sub(w.started) >= config.window)
+        else {
+            return;
+        };
+        self.window = None;
+        let difficulty = self.samples.back().map(|s| s.difficulty).unwrap_or(1);
+        if window.local_points < MIN_PARTIALS * difficulty.max(1) {
+            return;
+        }
+        let credited = window.pool_points as f64
+            >= window.local_points as f64 * (100 - config.missing_percent.min(100)) as f64 / 100.0;
+        if credited {
+            self.short = PointsWindow::default();
+            self.short_windows = 0;
+            return;
+        }
+        self.short.local_points += window.local_points;
+        self.short.pool_points += window.pool_points;
+        self.short_windows += 1;
+        if self.short_windows == config.windows.max(1) {
+            self.unreported.push(PointsDiscrepancy {
+                local_points: self.short.local_points,
+                pool_points: self.short.pool_points,
+                windows: self.short_windows,
+            });
+        }
+    }
+    //Locally accepted and pool credited points since the oldest sample, without the latest interval
+    pub fn compared(&self) -> Option<(u64, u64)> {
+        if self.samples.len() < 3 {
+            return None;
+        }
+        let (first, last) = (self.samples.front()?, self.samples.back()?);
+        let local = self
+            .samples
+            .iter()
+            .skip(1)
+            .take(self.samples.len() - 2)
+            .map(|s| s.local_points)
+            .sum();
+        Some((local, last.pool_points - first.pool_points))
+    }
+    //Points accepted per day over the sampled period, the latest interval included
+    pub fn points_per_day(&self) -> Option<f64> {
+        let (first, last) = (self.samples.front()?, self.samples.back()?);
+        let period = last
+            .timestamp
+            .checked_sub(first.timestamp)
+            .filter(|p| *p > 0)?;
+        let points: u64 = self.samples.iter().skip(1).map(|s| s.local_points).sum();
+        Some(points as f64 * 86400.0 / period as f64)
+    }
+    pub fn samples(&self) -> impl Iterator<Item = &PoolPointsSample> {
+        self.samples.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_consecutive_short_windows_once_and_restarts_after_payout() {
+        let config = PointsReconciliationConfig {
+            window: 10,
+            missing_percent: 10,
+            windows: 2,
+        };
+        let mut history = PoolPointsHistory::default();
+        history.record_pool(0, 0, 10);
+        let mut pool_points = 0;
+        let mut t = 0;
+        //Ten seconds of ten partials per second, the pool credits each second's partials a second later
+        let mut window = |history: &mut PoolPointsHistory, credited: u64| {
+            for _ in 0..10 {
+                t += 1;
+                for _ in 0..10 {
+                    history.record_local(10);
+                }
+                if t > 1 {
+                    pool_points += credited;
+                }
+                history.record_pool(t, pool_points, 10);
+                history.reconcile(t, &config);
+            }
+        };
+        window(&mut history, 100);
+        window(&mut history, 80);
+        assert!(history.unreported.is_empty());
+        window(&mut history, 80);
+        assert_eq!(
+            history.unreported,
+            vec![PointsDiscrepancy {
+                local_points: 2000,
+                pool_points: 1600,
+                windows: 2,
+            }]
+        );
+        window(&mut history, 80);
+        window(&mut history, 100);
+        window(&mut history, 80);
+        assert_eq!(history.unreported.len(), 1);
+        history.record_pool(61, 20, 10);
+        assert_eq!(history.compared(), None);
+        assert_eq!(history.samples().count(), 1);
+    }
+}
@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::io::Error;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const DEFAULT_PROFILE_SECS: u64 = 30;
+pub const MAX_PROFILE_SECS: u64 = 300;
+//Odd so the sampling does not line up with timers firing at round intervals
+#[cfg(feature = "profiling")]
+const SAMPLE_HZ: i32 = 99;
+//Frames of the signature, signage point and plot lookup handling, a sample is kept when any frame
+//of its stack matches
+const HOT_PATHS: [&str; 4] = [
+    "::signing::",
+    "::protocols::",
+    "::harvesters::",
+    "dg_xch_pos::",
+];
+
+static CAPTURING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileParams {
+    pub seconds: Option<u64>,
+    //Keeps samples outside the signing and lookup paths
+    #[serde(default)]
+    pub all: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileResult {
+    pub seconds: u64,
+    pub samples: u64,
+    pub flamegraph: String,
+    pub pprof: String,
+}
+
+pub fn is_hot_path<I: IntoIterator<Item = S>, S: AsRef<str>>(frames: I) -> bool {
+    frames
+        .into_iter()
+        .any(|name| HOT_PATHS.iter().any(|p| name.as_ref().contains(p)))
+}
+
+//Samples the whole process for seconds, one capture at a time, and writes a flamegraph svg and a
+//pprof protobuf to dir
+pub async fn capture(seconds: u64, all: bool, dir: &Path) -> Result<ProfileResult, Error> {
+    if CAPTURING.swap(true, Ordering::Relaxed) {
+        return Err(Error::other("A profile is already being captured"));
+    }
+    let dir = dir.to_path_buf();
+    let seconds = seconds.clamp(1, MAX_PROFILE_SECS);
+    let result = tokio::task::spawn_blocking(move || write_profile(seconds, all, &dir))
+        .await
+        .map_err(|e| Error::other(format!("Profiler thread failed: {e:?}")))
+        .and_then(|r| r);
+    CAPTURING.store(false, Ordering::Relaxed);
+    result
+}
+
+#[cfg(feature = "profiling")]
+fn write_profile(seconds: u64, all: bool, dir: &Path) -> Result<ProfileResult, Error> {
+    use pprof::protos::Message;
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(SAMPLE_HZ)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()
+        .map_err(|e| Error::other(format!("Failed to start profiler: {e}")))?;
+    std::thread::sleep(std::time::Duration::from_secs(seconds));
+    let mut report = guard
+        .report()
+        .build()
+        .map_err(|e| Error::other(format!("Failed to build profile: {e}")))?;
+    drop(guard);
+    if !all {
+        report
+            .data
+            .retain(|frames, _| is_hot_path(frames.frames.iter().flatten().map(|s| s.name())));
+    }
+    //The flamegraph can not be drawn without a single stack
+    if report.data.is_empty() {
+        return Err(Error::other(
+            "No samples in the signing and lookup paths, capture while signage points arrive or use --all",
+        ));
+    }
+    std::fs::create_dir_all(dir)?;
+    let name = format!("profile-{}", crate::farmer::partial_queue::now());
+    let flamegraph = dir.join(format!("{name}.svg"));
+    let pprof = dir.join(format!("{name}.pb"));
+    report
+        .flamegraph(std::fs::File::create(&flamegraph)?)
+        .map_err(|e| Error::other(format!("Failed to write flamegraph: {e}")))?;
+    let profile = report
+        .pprof()
+        .map_err(|e| Error::other(format!("Failed to encode profile: {e}")))?;
+    std::fs::write(&pprof, profile.encode_to_vec())?;
+    Ok(ProfileResult {
+        seconds,
+        samples: report.data.values().map(|c| *c as u64).sum(),
+        flamegraph: flamegraph.display().to_string(),
+        pprof: pprof.display().to_string(),
+    })
+}
+
+#[cfg(not(feature = "profiling"))]
+fn write_profile(_seconds: u64, _all: bool, _dir: &Path) -> Result<ProfileResult, Error> {
+    Err(Error::other(
+        "This build has no profiler, build with --features profiling",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_signing_and_lookup_stacks() {
+        assert!(is_hot_path([
+            "std::thread::local::LocalKey<T>::with",
+            "dg_fast_farmer::harvesters::druid_garden::DruidGardenHarvester::new_signage_point",
+        ]));
+        assert!(is_hot_path([
+            "dg_fast_farmer::farmer::signing::sign_partial"
+        ]));
+        assert!(!is_hot_path([
+            "tokio::runtime::park::Inner::park",
+            "dg_fast_farmer::tasks::status_summary::status_summary",
+        ]));
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+//Round trips kept per peer for the rolling stats
+const WINDOW: usize = 100;
+
+//Request to response time of one peer over its last WINDOW exchanges, count is since startup
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RttStats {
+    pub count: u64,
+    pub last_ms: f64,
+    pub avg_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Default)]
+struct RttWindow {
+    count: u64,
+    samples: VecDeque<f64>,
+}
+impl RttWindow {
+    fn stats(&self) -> RttStats {
+        let mut sorted: Vec<f64> = self.samples.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let p95 = sorted
+            .len()
+            .checked_sub(1)
+            .map(|last| sorted[(last as f64 * 0.95).round() as usize])
+            .unwrap_or_default();
+        RttStats {
+            count: self.count,
+            last_ms: self.samples.back().copied().unwrap_or_default(),
+            avg_ms: sorted.iter().sum::<f64>() / sorted.len().max(1) as f64,
+            p95_ms: p95,
+            max_ms: sorted.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+//Round trips of the protocol exchanges that get a response: the full node handshake of each
+//connection and the signature requests to each harvester. Keyed by peer, like full_node host:port
+#[derive(Debug, Default)]
+pub struct ProtocolRtts {
+    peers: Mutex<BTreeMap<String, RttWindow>>,
+}
+impl ProtocolRtts {
+    pub async fn record(&self, peer: String, rtt: Duration) {
+        let mut peers = self.peers.lock().await;
+        let window = peers.entry(peer).or_default();
+        window.count += 1;
+        window.samples.push_back(rtt.as_secs_f64() * 1000.0);
+        while window.samples.len() > WINDOW {
+            window.samples.pop_front();
+        }
+    }
+    pub async fn snapshot(&self) -> BTreeMap<String, RttStats> {
+        self.peers
+            .lock()
+            .await
+            .iter()
+            .map(|(peer, window)| (peer.clone(), window.stats()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn keeps_rolling_stats_per_peer() {
+        let rtts = ProtocolRtts::default();
+        for ms in 1..=120 {
+            rtts.record("harvester a".to_string(), Duration::from_millis(ms))
+                .await;
+        }
+        rtts.record("full_node node:8444".to_string(), Duration::from_millis(40))
+            .await;
+        let snapshot = rtts.snapshot().await;
+        let harvester = snapshot["harvester a"];
+        assert_eq!(harvester.count, 120);
+        assert_eq!(harvester.last_ms, 120.0);
+        assert_eq!(harvester.max_ms, 120.0);
+        //Only the last 100 round trips, 21 to 120ms
+        assert_eq!(harvester.avg_ms, 70.5);
+        assert_eq!(harvester.p95_ms, 115.0);
+        assert_eq!(snapshot["full_node node:8444"].avg_ms, 40.0);
+    }
+}
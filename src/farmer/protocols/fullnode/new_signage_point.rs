@@ -1,3 +1,7 @@
+use crate::farmer::capture::{Direction, FULL_NODE_PEER};
+use crate::farmer::chaos;
+use crate::farmer::events::FarmerEventKind;
+use crate::farmer::protocols::decode_message;
 use crate::farmer::protocols::harvester::new_proof_of_space::NewProofOfSpaceHandle;
 use crate::farmer::FarmerSharedState;
 use crate::harvesters::{Harvester, Harvesters};
@@ -6,15 +10,13 @@ use async_trait::async_trait;
 use dg_xch_clients::api::pool::PoolClient;
 use dg_xch_clients::protocols::farmer::NewSignagePoint;
 use dg_xch_clients::protocols::harvester::{NewSignagePointHarvester, PoolDifficulty};
+use dg_xch_clients::protocols::ProtocolMessageTypes;
 use dg_xch_clients::websocket::{ChiaMessage, MessageHandler};
 use dg_xch_core::blockchain::sized_bytes::Bytes32;
 use dg_xch_core::consensus::constants::ConsensusConstants;
-use dg_xch_core::consensus::pot_iterations::POOL_SUB_SLOT_ITERS;
-use dg_xch_serialize::ChiaSerialize;
 use log::{debug, info, warn};
-use std::collections::hash_map::Entry;
 use std::collections::HashMap;
-use std::io::{Cursor, Error};
+use std::io::Error;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::Mutex;
@@ -25,8 +27,6 @@ pub struct NewSignagePointHandle<T: PoolClient + Sized + Sync + Send + 'static>
     pub harvester_id: Bytes32,
     pub pool_state: Arc<Mutex<HashMap<Bytes32, FarmerPoolState>>>,
     pub pool_client: Arc<T>,
-    pub signage_points: Arc<Mutex<HashMap<Bytes32, Vec<NewSignagePoint>>>>,
-    pub cache_time: Arc<Mutex<HashMap<Bytes32, Instant>>>,
     pub shared_state: Arc<FarmerSharedState>,
     pub harvesters: Arc<HashMap<Uuid, Arc<Harvesters>>>,
     pub constants: &'static ConsensusConstants,
@@ -34,19 +34,39 @@ pub struct NewSignagePointHandle<T: PoolClient + Sized + Sync + Send + 'static>
 #[async_trait]
 impl<T: PoolClient + Sized + Sync + Send + 'static> MessageHandler for NewSignagePointHandle<T> {
     async fn handle(&self, msg: Arc<ChiaMessage>) -> Result<(), Error> {
-        let mut cursor = Cursor::new(&msg.data);
-        let sp = NewSignagePoint::from_bytes(&mut cursor)?;
+        self.shared_state.capture.record_bytes(
+            Direction::In,
+            FULL_NODE_PEER,
+            msg.msg_type,
+            &msg.data,
+        );
+        if chaos::drop_frame("NewSignagePoint") {
+            return Ok(());
+        }
+        let sp: NewSignagePoint = decode_message("NewSignagePoint", &msg.data)?;
+        //Signage points that contradict their sub-slot would only waste lookups
+        let chained = self
+            .shared_state
+            .sp_chain
+            .lock()
+            .await
+            .check(&sp, self.constants.num_sps_sub_slot);
+        if let Err(e) = chained {
+            self.shared_state
+                .record_handler_error("new_signage_point", &format!("skipped: {e}"))
+                .await;
+            return Ok(());
+        }
         let mut pool_difficulties = vec![];
         for (p2_singleton_puzzle_hash, pool_dict) in self.pool_state.lock().await.iter() {
             if let Some(config) = &pool_dict.pool_config {
-                if config.pool_url.is_empty() {
-                    //Self Pooling
+                if pool_dict.is_solo() {
                     continue;
                 } else if let Some(difficulty) = pool_dict.current_difficulty {
                     debug!("Setting Difficulty for pool: {}", difficulty);
                     pool_difficulties.push(PoolDifficulty {
                         difficulty,
-                        sub_slot_iters: POOL_SUB_SLOT_ITERS,
+                        sub_slot_iters: self.constants.pool_sub_slot_iters,
                         pool_contract_puzzle_hash: *p2_singleton_puzzle_hash,
                     })
                 } else {
@@ -60,6 +80,7 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> MessageHandler for NewSignag
             sp.signage_point_index, sp.challenge_hash
         );
         *self.shared_state.last_sp_timestamp.lock().await = Instant::now();
+        self.shared_state.idle.sp_received();
         let harvester_point = Arc::new(NewSignagePointHarvester {
             challenge_hash: sp.challenge_hash,
             difficulty: sp.difficulty,
@@ -68,25 +89,15 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> MessageHandler for NewSignag
             sp_hash: sp.challenge_chain_sp,
             pool_difficulties,
         });
-        self.cache_time
-            .lock()
-            .await
-            .insert(sp.challenge_chain_sp, Instant::now());
         self.shared_state.gui_stats.lock().await.most_recent_sp =
             (sp.challenge_hash, sp.signage_point_index);
-        match self
-            .signage_points
-            .lock()
-            .await
-            .entry(sp.challenge_chain_sp)
-        {
-            Entry::Occupied(mut e) => {
-                e.get_mut().push(sp);
-            }
-            Entry::Vacant(e) => {
-                e.insert(vec![sp]);
-            }
-        }
+        self.shared_state.record_signage_point(sp).await;
+        //Emitted once stored so consumers of the event can look the full signage point up
+        self.shared_state.emit(FarmerEventKind::SignagePoint {
+            challenge_hash: harvester_point.challenge_hash,
+            sp_hash: harvester_point.sp_hash,
+            signage_point_index: harvester_point.signage_point_index,
+        });
         for (_, harvester) in self.harvesters.iter() {
             let harvester_point = harvester_point.clone();
             let harvesters = self.harvesters.clone();
@@ -96,19 +107,26 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> MessageHandler for NewSignag
             let constants = self.constants;
             let harvester = harvester.clone();
             tokio::spawn(async move {
+                shared_state.capture.record_harvester(
+                    Direction::Out,
+                    harvester.uuid(),
+                    ProtocolMessageTypes::NewSignagePointHarvester,
+                    harvester_point.as_ref(),
+                );
+                let proof_handle = NewProofOfSpaceHandle {
+                    pool_client,
+                    shared_state,
+                    harvester_id: harvester.uuid(),
+                    harvester_partial_id,
+                    harvesters,
+                    constants,
+                };
                 match harvester.as_ref() {
-                    Harvesters::DruidGarden(harvester) => {
-                        let proof_handle = NewProofOfSpaceHandle {
-                            pool_client,
-                            shared_state,
-                            harvester_id: harvester.uuid(),
-                            harvester_partial_id,
-                            harvesters,
-                            constants,
-                        };
-                        harvester
-                            .new_signage_point(harvester_point, proof_handle)
-                            .await?;
+                    Harvesters::DruidGarden(h) => {
+                        h.new_signage_point(harvester_point, proof_handle).await?
+                    }
+                    Harvesters::Remote(h) => {
+                        h.new_signage_point(harvester_point, proof_handle).await?
                     }
                 }
                 Ok::<(), Error>(())
@@ -1,17 +1,24 @@
+use crate::error::FarmerError;
+use crate::farmer::capture::{Direction, FULL_NODE_PEER};
+use crate::farmer::chaos;
+use crate::farmer::events::FarmerEventKind;
+use crate::farmer::protocols::decode_message;
 use crate::farmer::protocols::harvester::respond_signatures::RespondSignaturesHandler;
+use crate::farmer::signed_values::{SignedValuesKey, SignedValuesRequest};
 use crate::farmer::FarmerSharedState;
-use crate::harvesters::{Harvester, Harvesters};
+use crate::harvesters::{request_signatures_with_retry, Harvesters};
 use async_trait::async_trait;
 use dg_xch_clients::api::pool::PoolClient;
-use dg_xch_clients::protocols::farmer::RequestSignedValues;
+use dg_xch_clients::protocols::farmer::{RequestSignedValues, SignedValues};
 use dg_xch_clients::protocols::harvester::RequestSignatures;
+use dg_xch_clients::protocols::ProtocolMessageTypes;
 use dg_xch_clients::websocket::{ChiaMessage, MessageHandler};
 use dg_xch_core::consensus::constants::ConsensusConstants;
-use dg_xch_serialize::ChiaSerialize;
-use log::error;
+use log::{error, info};
 use std::collections::HashMap;
-use std::io::{Cursor, Error, ErrorKind};
+use std::io::Error;
 use std::sync::Arc;
+use std::time::Instant;
 use uuid::Uuid;
 
 pub struct RequestSignedValuesHandle<T: PoolClient + Sized + Sync + Send + 'static> {
@@ -26,16 +33,22 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> MessageHandler
     for RequestSignedValuesHandle<T>
 {
     async fn handle(&self, msg: Arc<ChiaMessage>) -> Result<(), Error> {
-        let mut cursor = Cursor::new(&msg.data);
-        let request = RequestSignedValues::from_bytes(&mut cursor)?;
+        self.shared_state.capture.record_bytes(
+            Direction::In,
+            FULL_NODE_PEER,
+            msg.msg_type,
+            &msg.data,
+        );
+        if chaos::drop_frame("RequestSignedValues") {
+            return Ok(());
+        }
+        let request: RequestSignedValues = decode_message("RequestSignedValues", &msg.data)?;
         if let Some(identifier) = self
             .shared_state
-            .quality_to_identifiers
-            .lock()
+            .identifier_for_quality(&request.quality_string)
             .await
-            .get(&request.quality_string)
         {
-            let request = RequestSignatures {
+            let signatures = RequestSignatures {
                 plot_identifier: identifier.plot_identifier.clone(),
                 challenge_hash: identifier.challenge_hash,
                 sp_hash: identifier.sp_hash,
@@ -50,25 +63,68 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> MessageHandler
                 harvester_id: identifier.harvester_id,
                 harvesters: self.harvesters.clone(),
                 constants: self.constants,
+                requested_at: Instant::now(),
             };
             if let Some(h) = self.harvesters.get(&identifier.harvester_id) {
-                let harvester = h.clone();
-                tokio::spawn(async move {
-                    match harvester.as_ref() {
-                        Harvesters::DruidGarden(harvester) => {
-                            harvester.request_signatures(request, sig_handle).await?;
-                        }
+                let key = SignedValuesKey {
+                    quality_string: request.quality_string,
+                    foliage_block_data_hash: request.foliage_block_data_hash,
+                    foliage_transaction_block_hash: request.foliage_transaction_block_hash,
+                };
+                let existing = self.shared_state.signed_values.lock().await.request(key);
+                match existing {
+                    SignedValuesRequest::New => {}
+                    SignedValuesRequest::Pending => {
+                        info!(
+                            "Full node resent RequestSignedValues for quality {} while the harvester is still signing, waiting for it",
+                            request.quality_string
+                        );
+                        return Ok(());
+                    }
+                    SignedValuesRequest::Signed(values) => {
+                        info!(
+                            "Full node resent RequestSignedValues for quality {}, replaying the signed values",
+                            request.quality_string
+                        );
+                        send_signed_values(&self.shared_state, &values).await;
+                        return Ok(());
                     }
-                    Ok::<(), Error>(())
-                });
+                }
+                tokio::spawn(request_signatures_with_retry(
+                    h.clone(),
+                    signatures,
+                    sig_handle,
+                    self.shared_state.clone(),
+                ));
             }
             Ok(())
         } else {
             error!("Do not have quality {}", &request.quality_string);
-            Err(Error::new(
-                ErrorKind::NotFound,
-                format!("Do not have quality {}", &request.quality_string),
-            ))
+            Err(
+                FarmerError::Protocol(format!("Do not have quality {}", &request.quality_string))
+                    .into(),
+            )
         }
     }
 }
+
+//False without a full node connection, the node then asks again after reconnecting
+pub(crate) async fn send_signed_values(
+    shared_state: &FarmerSharedState,
+    values: &SignedValues,
+) -> bool {
+    if shared_state
+        .send_to_full_node(ProtocolMessageTypes::SignedValues, values)
+        .await
+    {
+        true
+    } else {
+        shared_state.emit(FarmerEventKind::Error {
+            message: format!(
+                "Failed to send Signed Values for {}, No Client",
+                values.quality_string
+            ),
+        });
+        false
+    }
+}
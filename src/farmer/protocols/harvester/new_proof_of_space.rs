@@ -1,31 +1,44 @@
+use crate::error::FarmerError;
+use crate::farmer::capture::Direction;
+use crate::farmer::events::FarmerEventKind;
+use crate::farmer::forensics::{should_dump, write_dump, RejectedPartialDump};
+use crate::farmer::latency_regression::LatencyKind;
+use crate::farmer::partial_batch::{time_left, BatchPartial};
+use crate::farmer::partial_deadline::PartialDeadline;
+use crate::farmer::partial_metadata::{partial_headers, PartialMetadata};
+use crate::farmer::partial_queue::{is_retryable, now, QueuedPartial};
 use crate::farmer::protocols::harvester::respond_signatures::RespondSignaturesHandler;
+use crate::farmer::signing::{verify_plot_signature, PlotSigner, SigningStep, SigningTimings};
+use crate::farmer::sp_recovery::recover_signage_point;
+use crate::farmer::sp_tag::sp_label;
 use crate::farmer::{FarmerIdentifier, FarmerSharedState};
-use crate::harvesters::{Harvester, Harvesters, ProofHandler, SignatureHandler};
-use crate::HEADERS;
+use crate::get_forensics_path;
+use crate::harvesters::{
+    request_signatures_with_retry, Harvesters, ProofHandler, SignatureHandler,
+};
 use async_trait::async_trait;
 use blst::min_pk::{AggregateSignature, PublicKey, Signature};
-use blst::BLST_ERROR;
 use dg_xch_clients::api::pool::PoolClient;
 use dg_xch_clients::protocols::farmer::NewSignagePoint;
 use dg_xch_clients::protocols::harvester::{NewProofOfSpace, RequestSignatures, RespondSignatures};
 use dg_xch_clients::protocols::pool::{
     get_current_authentication_token, PoolErrorCode, PostPartialPayload, PostPartialRequest,
 };
-use dg_xch_core::blockchain::proof_of_space::{generate_plot_public_key, generate_taproot_sk};
+use dg_xch_clients::protocols::ProtocolMessageTypes;
 use dg_xch_core::blockchain::sized_bytes::{Bytes32, SizedBytes};
-use dg_xch_core::clvm::bls_bindings::{sign, sign_prepend, AUG_SCHEME_DST};
+use dg_xch_core::clvm::bls_bindings::{sign, verify_signature};
 use dg_xch_core::consensus::constants::ConsensusConstants;
 use dg_xch_core::consensus::pot_iterations::{
     calculate_iterations_quality, calculate_sp_interval_iters,
 };
 use dg_xch_pos::verify_and_get_quality_string;
 use dg_xch_serialize::{hash_256, ChiaSerialize};
-use log::{error, info, warn};
-use std::collections::HashMap;
-use std::io::{Error, ErrorKind};
+use log::{debug, error, info, warn};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Error;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 pub struct NewProofOfSpaceHandle<T: PoolClient + Sized + Sync + Send + 'static> {
@@ -40,46 +53,107 @@ pub struct NewProofOfSpaceHandle<T: PoolClient + Sized + Sync + Send + 'static>
 #[async_trait]
 impl<T: PoolClient + Sized + Sync + Send + 'static> ProofHandler for NewProofOfSpaceHandle<T> {
     async fn handle_proof(&self, new_pos: NewProofOfSpace) -> Result<(), Error> {
-        if let Some(sps) = self
-            .shared_state
-            .signage_points
-            .lock()
-            .await
-            .get(&new_pos.sp_hash)
-        {
-            for sp in sps {
+        self.shared_state.capture.record_harvester(
+            Direction::In,
+            self.harvester_id,
+            ProtocolMessageTypes::NewProofOfSpace,
+            &new_pos,
+        );
+        let label = sp_label(&self.shared_state.sp_tag(&new_pos.sp_hash).await);
+        if self.shared_state.is_paused() {
+            debug!(
+                "{label} Farming paused, dropping proof for {} from {}",
+                new_pos.sp_hash, new_pos.plot_identifier
+            );
+            return Ok(());
+        }
+        if self.shared_state.is_standby() {
+            debug!(
+                "{label} Standing by, dropping proof for {} from {}",
+                new_pos.sp_hash, new_pos.plot_identifier
+            );
+            return Ok(());
+        }
+        if !self.shared_state.has_signage_point(&new_pos.sp_hash).await {
+            recover_signage_point(
+                &self.shared_state,
+                &new_pos.challenge_hash,
+                &new_pos.sp_hash,
+                Some(new_pos.signage_point_index),
+                &label,
+            )
+            .await;
+        }
+        if let Some(sps) = self.shared_state.signage_points_for(&new_pos.sp_hash).await {
+            //Values of the sub-slot the proof claims, recorded from its first signage point
+            let sub_slot = self
+                .shared_state
+                .sp_chain
+                .lock()
+                .await
+                .sub_slot(&new_pos.challenge_hash);
+            //Counted once per proof for the plot leaderboard, even when several nodes sent the
+            //signage point
+            let mut counted = false;
+            for sp in &sps {
+                let (difficulty, sub_slot_iters) = match sub_slot {
+                    Some(sub_slot) => (sub_slot.difficulty, sub_slot.sub_slot_iters),
+                    None => (sp.difficulty, sp.sub_slot_iters),
+                };
                 if let Some(qs) = verify_and_get_quality_string(
                     &new_pos.proof,
                     self.constants,
                     &new_pos.challenge_hash,
                     &new_pos.sp_hash,
                 ) {
+                    if !counted {
+                        counted = true;
+                        self.shared_state
+                            .plot_leaderboard
+                            .lock()
+                            .await
+                            .record_proof(&new_pos.plot_identifier, now());
+                    }
                     let required_iters = calculate_iterations_quality(
                         self.constants.difficulty_constant_factor,
                         &qs,
                         new_pos.proof.size,
-                        sp.difficulty,
+                        difficulty,
                         &new_pos.sp_hash,
                     );
-                    if required_iters
-                        < calculate_sp_interval_iters(self.constants, sp.sub_slot_iters)?
+                    if required_iters < calculate_sp_interval_iters(self.constants, sub_slot_iters)?
                     {
-                        self._handle_proof(sp, &qs, &new_pos).await;
+                        if self.shared_state.readiness.watch_only {
+                            self.report_watch_only_proof(&new_pos, &label).await;
+                        } else {
+                            self._handle_proof(sp, &qs, &new_pos, &label).await;
+                        }
+                    } else {
+                        //Declaring it would only make the node verify a proof that can not win
+                        debug!(
+                            "{label} Proof in {} needs {required_iters} iterations, not declaring it",
+                            new_pos.plot_identifier
+                        );
+                        self.shared_state
+                            .gui_stats
+                            .lock()
+                            .await
+                            .declarations_skipped += 1;
                     }
                     if let Some(p2_singleton_puzzle_hash) = &new_pos.proof.pool_contract_puzzle_hash
                     {
                         self.handle_partial(p2_singleton_puzzle_hash, &qs, new_pos.clone())
                             .await?;
                     } else {
-                        warn!("Not a pooling proof of space");
+                        debug!("{label} Not a pooling proof of space");
                     }
                 } else {
-                    warn!("Invalid proof of space {:?}", new_pos);
+                    warn!("{label} Invalid proof of space {:?}", new_pos);
                 }
             }
         } else {
             warn!(
-                "Received response for a signage point that we do not have {}",
+                "{label} Received response for a signage point that we do not have {}",
                 &new_pos.sp_hash
             );
         }
@@ -88,25 +162,19 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> ProofHandler for NewProofOfS
 }
 
 impl<T: PoolClient + Sized + Sync + Send + 'static> NewProofOfSpaceHandle<T> {
-    async fn _handle_proof(&self, sp: &NewSignagePoint, qs: &Bytes32, new_pos: &NewProofOfSpace) {
-        let mut farmer_pos = self.shared_state.proofs_of_space.lock().await;
-        if farmer_pos.get(&new_pos.sp_hash).is_none() {
-            farmer_pos.insert(new_pos.sp_hash, vec![]);
-        }
-        farmer_pos
-            .get_mut(&new_pos.sp_hash)
-            .expect("Should not happen, item created above")
-            .push((new_pos.plot_identifier.clone(), new_pos.proof.clone()));
-        self.shared_state
-            .cache_time
-            .lock()
-            .await
-            .insert(new_pos.sp_hash, Instant::now());
+    async fn _handle_proof(
+        &self,
+        sp: &NewSignagePoint,
+        qs: &Bytes32,
+        new_pos: &NewProofOfSpace,
+        label: &str,
+    ) {
+        info!(
+            "{label} Found proof in {}, requesting signatures",
+            new_pos.plot_identifier
+        );
         self.shared_state
-            .quality_to_identifiers
-            .lock()
-            .await
-            .insert(
+            .record_proof(
                 *qs,
                 FarmerIdentifier {
                     plot_identifier: new_pos.plot_identifier.clone(),
@@ -114,18 +182,16 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> NewProofOfSpaceHandle<T> {
                     sp_hash: new_pos.sp_hash,
                     harvester_id: self.harvester_id,
                 },
-            );
-        self.shared_state
-            .cache_time
-            .lock()
-            .await
-            .insert(*qs, Instant::now());
+                new_pos.proof.clone(),
+            )
+            .await;
         let sig_handle = RespondSignaturesHandler {
             pool_client: self.pool_client.clone(),
             shared_state: self.shared_state.clone(),
             harvester_id: self.harvester_id,
             harvesters: self.harvesters.clone(),
             constants: self.constants,
+            requested_at: Instant::now(),
         };
         let request = RequestSignatures {
             plot_identifier: new_pos.plot_identifier.clone(),
@@ -134,15 +200,97 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> NewProofOfSpaceHandle<T> {
             messages: vec![sp.challenge_chain_sp, sp.reward_chain_sp],
         };
         if let Some(h) = self.harvesters.get(&self.harvester_id) {
-            let harvester = h.clone();
-            tokio::spawn(async move {
-                match harvester.as_ref() {
-                    Harvesters::DruidGarden(harvester) => {
-                        let _ = harvester.request_signatures(request, sig_handle).await;
-                    }
-                }
+            tokio::spawn(request_signatures_with_retry(
+                h.clone(),
+                request,
+                sig_handle,
+                self.shared_state.clone(),
+            ));
+        }
+    }
+
+    async fn report_watch_only_proof(&self, new_pos: &NewProofOfSpace, label: &str) {
+        info!(
+            "{label} Watch only, proof in {} would be declared",
+            new_pos.plot_identifier
+        );
+        self.shared_state.gui_stats.lock().await.watch_only.proofs += 1;
+        self.shared_state.emit(FarmerEventKind::WatchOnlyProof {
+            sp_hash: new_pos.sp_hash,
+            plot_identifier: new_pos.plot_identifier.clone(),
+            sp: self.shared_state.sp_tag(&new_pos.sp_hash).await,
+        });
+    }
+
+    async fn compression_summary(&self) -> Option<BTreeMap<u8, u64>> {
+        if !self.shared_state.config.client_metadata {
+            return None;
+        }
+        match self.harvesters.get(&self.harvester_id)?.as_ref() {
+            Harvesters::DruidGarden(h) => Some(
+                h.compression_stats
+                    .lock()
+                    .await
+                    .levels
+                    .iter()
+                    .map(|(level, stats)| (*level, stats.plot_count))
+                    .collect(),
+            ),
+            Harvesters::Remote(_) => None,
+        }
+    }
+    //There is no pool state without the owner keys, the pool config difficulty or the protocol
+    //minimum of 1 is used. Points per partial grow with the difficulty as fast as partials get
+    //rarer, so the points estimate holds either way
+    async fn report_watch_only_partial(
+        &self,
+        p2_singleton_puzzle_hash: &Bytes32,
+        qs: &Bytes32,
+        new_pos: &NewProofOfSpace,
+    ) -> Result<(), Error> {
+        let sp = self.shared_state.sp_tag(&new_pos.sp_hash).await;
+        let label = sp_label(&sp);
+        let Some(pool_config) = self
+            .shared_state
+            .config
+            .all_farms()
+            .into_iter()
+            .flat_map(|f| f.pool_info)
+            .find(|p| p.p2_singleton_puzzle_hash == *p2_singleton_puzzle_hash)
+        else {
+            debug!("{label} Did not find pool info for {p2_singleton_puzzle_hash}");
+            return Ok(());
+        };
+        if pool_config.pool_url.is_empty() {
+            return Ok(());
+        }
+        let difficulty = pool_config.difficulty.unwrap_or(1).max(1);
+        let required_iters = calculate_iterations_quality(
+            self.constants.difficulty_constant_factor,
+            qs,
+            new_pos.proof.size,
+            difficulty,
+            &new_pos.sp_hash,
+        );
+        if required_iters
+            < calculate_sp_interval_iters(self.constants, self.constants.pool_sub_slot_iters)?
+        {
+            info!(
+                "{label} Watch only, partial for {} would be submitted at difficulty {difficulty}",
+                pool_config.launcher_id
+            );
+            {
+                let mut gui_stats = self.shared_state.gui_stats.lock().await;
+                gui_stats.watch_only.partials += 1;
+                gui_stats.watch_only.points += difficulty;
+            }
+            self.shared_state.emit(FarmerEventKind::WatchOnlyPartial {
+                launcher_id: pool_config.launcher_id,
+                difficulty,
+                sp,
             });
         }
+        Ok(())
     }
 
     async fn handle_partial(
@@ -151,6 +299,25 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> NewProofOfSpaceHandle<T> {
         qs: &Bytes32,
         new_pos: NewProofOfSpace,
     ) -> Result<(), Error> {
+        if self.shared_state.readiness.watch_only {
+            return self
+                .report_watch_only_partial(p2_singleton_puzzle_hash, qs, &new_pos)
+                .await;
+        }
+        let sp = self.shared_state.sp_tag(&new_pos.sp_hash).await;
+        let label = sp_label(&sp);
+        let lookup_time_ms = sp.map(|t| t.offset_ms);
+        let compression = self.compression_summary().await;
+        if self
+            .shared_state
+            .readiness
+            .is_pool_disabled(p2_singleton_puzzle_hash)
+        {
+            debug!(
+                "{label} Partials disabled for {p2_singleton_puzzle_hash}, incomplete pool config"
+            );
+            return Ok(());
+        }
         if let Some(pool_state) = self
             .shared_state
             .pool_states
@@ -159,7 +326,16 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> NewProofOfSpaceHandle<T> {
             .get_mut(p2_singleton_puzzle_hash)
         {
             if let Some(pool_config) = &pool_state.pool_config {
-                if pool_config.pool_url.is_empty() {
+                if pool_state.is_solo() {
+                    debug!(
+                        "{label} Launcher {} is farmed solo, no partial sent",
+                        pool_config.launcher_id
+                    );
+                } else if pool_state.partials_halted() {
+                    warn!(
+                        "{label} Pool {} pays launcher {} elsewhere, no partial sent",
+                        pool_config.pool_url, pool_config.launcher_id
+                    );
                 } else if let Some(pool_dif) = pool_state.current_difficulty {
                     let required_iters = calculate_iterations_quality(
                         self.constants.difficulty_constant_factor,
@@ -174,9 +350,51 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> NewProofOfSpaceHandle<T> {
                     )?;
                     if required_iters >= pool_required_iters {
                         info!(
-                            "Proof of space not good enough for pool {}: {:?}",
+                            "{label} Proof of space not good enough for pool {}: {:?}",
                             pool_config.pool_url, pool_state.current_difficulty
                         );
+                    } else if pool_config.partial_submit_percent.is_some_and(|percent| {
+                        required_iters as u128
+                            >= pool_required_iters as u128 * percent.min(100) as u128 / 100
+                    }) {
+                        pool_state.suppressed_partials += 1;
+                        debug!(
+                            "{label} Suppressed partial for {} below quality threshold, {} suppressed",
+                            pool_config.launcher_id, pool_state.suppressed_partials
+                        );
+                        self.shared_state
+                            .record_farm_stat(
+                                self.shared_state
+                                    .farms
+                                    .farm_for_p2_singleton(p2_singleton_puzzle_hash),
+                                |s| s.partials_suppressed += 1,
+                            )
+                            .await;
+                    } else if !self
+                        .shared_state
+                        .partial_dedup
+                        .lock()
+                        .await
+                        .first_submission(
+                            (
+                                new_pos.sp_hash,
+                                new_pos.proof.get_plot_id().unwrap_or_default(),
+                                *qs,
+                            ),
+                            Duration::from_secs(self.shared_state.config.duplicate_partial_window),
+                            Instant::now(),
+                        )
+                    {
+                        warn!(
+                            "{label} Proof from {} was already sent to {}, not sending it again",
+                            new_pos.plot_identifier, pool_config.pool_url
+                        );
+                        self.shared_state.emit(FarmerEventKind::PartialDuplicate {
+                            launcher_id: pool_config.launcher_id,
+                            pool_url: pool_config.pool_url.clone(),
+                            plot_identifier: new_pos.plot_identifier.clone(),
+                            sp,
+                        });
                     } else if let Some(auth_token_timeout) = pool_state.authentication_token_timeout
                     {
                         let payload = PostPartialPayload {
@@ -199,21 +417,24 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> NewProofOfSpaceHandle<T> {
                         let handler = PartialHandler {
                             pool_client: self.pool_client.clone(),
                             shared_state: self.shared_state.clone(),
+                            harvester_id: self.harvester_id,
+                            requested_at: Instant::now(),
+                            lookup_time_ms,
+                            compression,
                             p2_singleton_puzzle_hash: *p2_singleton_puzzle_hash,
                             new_pos,
                             auth_token_timeout,
                             payload,
                             payload_bytes,
+                            constants: self.constants,
                         };
                         if let Some(h) = self.harvesters.get(&self.harvester_id) {
-                            let harvester = h.clone();
-                            tokio::spawn(async move {
-                                match harvester.as_ref() {
-                                    Harvesters::DruidGarden(h) => {
-                                        let _ = h.request_signatures(request, handler).await;
-                                    }
-                                }
-                            });
+                            tokio::spawn(request_signatures_with_retry(
+                                h.clone(),
+                                request,
+                                handler,
+                                self.shared_state.clone(),
+                            ));
                         }
                     } else {
                         warn!("No pool specific authentication_token_timeout has been set for {p2_singleton_puzzle_hash}, check communication with the pool.");
@@ -234,138 +455,331 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> NewProofOfSpaceHandle<T> {
 pub struct PartialHandler<T: PoolClient + Sized + Sync + Send + 'static> {
     pub pool_client: Arc<T>,
     pub shared_state: Arc<FarmerSharedState>,
+    pub harvester_id: Uuid,
+    pub requested_at: Instant,
+    pub lookup_time_ms: Option<u64>,
+    pub compression: Option<BTreeMap<u8, u64>>,
     pub auth_token_timeout: u8,
     pub p2_singleton_puzzle_hash: Bytes32,
     pub new_pos: NewProofOfSpace,
     pub payload: PostPartialPayload,
     pub payload_bytes: Vec<u8>,
+    pub constants: &'static ConsensusConstants,
 }
 #[async_trait]
 impl<T: PoolClient + Sized + Sync + Send + 'static> SignatureHandler for PartialHandler<T> {
     async fn handle_signature(&self, respond_sigs: RespondSignatures) -> Result<(), Error> {
+        self.shared_state.capture.record_harvester(
+            Direction::In,
+            self.harvester_id,
+            ProtocolMessageTypes::RespondSignatures,
+            &respond_sigs,
+        );
+        self.shared_state
+            .record_signing_delay(self.harvester_id, self.requested_at.elapsed())
+            .await;
+        let sp = self.shared_state.sp_tag(&self.new_pos.sp_hash).await;
+        let label = sp_label(&sp);
         let response_msg_sig = if let Some(f) = respond_sigs.message_signatures.first() {
             Signature::from_bytes(f.1.to_sized_bytes())
-                .map_err(|e| Error::new(ErrorKind::InvalidInput, format!("{:?}", e)))?
+                .map_err(|e| FarmerError::Signing(format!("{:?}", e)))?
         } else {
-            return Err(Error::new(
-                ErrorKind::InvalidInput,
-                "No Signature in Response",
-            ));
+            return Err(FarmerError::Protocol("No Signature in Response".to_string()).into());
         };
         let mut plot_sig = None;
         let local_pk = PublicKey::from_bytes(respond_sigs.local_pk.to_sized_bytes())
-            .map_err(|e| Error::new(ErrorKind::InvalidInput, format!("{:?}", e)))?;
-        for (_, sk) in self.shared_state.farmer_private_keys.iter() {
-            let pk = sk.sk_to_pk();
-            if pk.to_bytes() == *respond_sigs.farmer_pk.to_sized_bytes() {
-                let agg_pk = generate_plot_public_key(&local_pk, &pk, true)?;
-                if agg_pk.to_bytes() != *self.new_pos.proof.plot_public_key.to_sized_bytes() {
-                    return Err(Error::new(ErrorKind::InvalidInput, "Key Mismatch"));
-                }
-                let sig_farmer = sign_prepend(sk, &self.payload_bytes, &agg_pk);
-                let taproot_sk = generate_taproot_sk(&local_pk, &pk)?;
-                let taproot_sig = sign_prepend(&taproot_sk, &self.payload_bytes, &agg_pk);
-                let p_sig = AggregateSignature::aggregate(
-                    &[&sig_farmer, &response_msg_sig, &taproot_sig],
-                    true,
+            .map_err(|e| FarmerError::Signing(format!("{:?}", e)))?;
+        let mut timings = SigningTimings::default();
+        if let Some(sk) = self.shared_state.farmer_key_for(&respond_sigs.farmer_pk) {
+            let signer = timings.time(SigningStep::Taproot, || {
+                PlotSigner::new(sk, &local_pk, true)
+            })?;
+            if signer.plot_public_key().to_bytes()
+                != *self.new_pos.proof.plot_public_key.to_sized_bytes()
+            {
+                return Err(FarmerError::Key("Key Mismatch".to_string()).into());
+            }
+            match signer.aggregate(
+                &response_msg_sig,
+                &self.payload_bytes,
+                &mut timings,
+                SigningStep::VerifyPartial,
+            ) {
+                Ok(sig) => plot_sig = Some(sig),
+                Err(e) => warn!("{label} Failed to validate partial signature: {e}"),
+            }
+        }
+        self.shared_state
+            .record_signing_steps(&label, &timings)
+            .await;
+        //The pool state is not locked while the partial is sent, so partials of other launchers can
+        //join its batch
+        let (pool_config, post_request, metadata, difficulty, plot_sig, auth_sig, auth_pk) = {
+            let pool_states = self.shared_state.pool_states.lock().await;
+            let Some(pool_state) = pool_states.get(&self.p2_singleton_puzzle_hash) else {
+                warn!(
+                    "Did not find pool info for {}",
+                    &self.p2_singleton_puzzle_hash
+                );
+                return Ok(());
+            };
+            let Some(pool_config) = &pool_state.pool_config else {
+                warn!("No Pool Config for {}", &self.p2_singleton_puzzle_hash);
+                return Ok(());
+            };
+            let Some(auth_key) = self
+                .shared_state
+                .auth_secret_keys
+                .get(&pool_config.owner_public_key)
+            else {
+                warn!(
+                    "No authentication sk for {}",
+                    &self.p2_singleton_puzzle_hash
+                );
+                return Ok(());
+            };
+            let Some(plot_sig) = plot_sig else {
+                return Ok(());
+            };
+            let auth_sig = sign(auth_key, &self.payload_bytes);
+            let agg_sig = AggregateSignature::aggregate(&[&plot_sig, &auth_sig], true)
+                .map_err(|e| FarmerError::Signing(format!("{:?}", e)))?;
+            let post_request = PostPartialRequest {
+                payload: self.payload.clone(),
+                aggregate_signature: agg_sig.to_signature().to_bytes().into(),
+            };
+            let metadata = pool_state.extended_partials.then_some(PartialMetadata {
+                harvester_id: self.harvester_id,
+                plot_size: self.new_pos.proof.size,
+                lookup_time_ms: self.lookup_time_ms,
+                compression: self.compression.clone(),
+            });
+            (
+                pool_config.clone(),
+                post_request,
+                metadata,
+                pool_state.current_difficulty,
+                plot_sig,
+                auth_sig,
+                auth_key.sk_to_pk(),
+            )
+        };
+        let sp_age = sp.map(|t| Duration::from_millis(t.offset_ms));
+        if PartialDeadline::new(&self.shared_state.config).is_late(sp_age) {
+            warn!(
+                "{label} Not submitting partial for {} to {}, the pool window has passed",
+                post_request.payload.launcher_id, &pool_config.pool_url
+            );
+            self.shared_state.emit(FarmerEventKind::PartialStaleLocal {
+                launcher_id: pool_config.launcher_id,
+                pool_url: pool_config.pool_url.clone(),
+                sp_age_ms: sp_age.unwrap_or_default().as_millis() as u64,
+                sp,
+            });
+            return Ok(());
+        }
+        info!(
+            "{label} Submitting partial for {} to {}",
+            post_request.payload.launcher_id.to_string(),
+            &pool_config.pool_url
+        );
+        let submit_start = Instant::now();
+        let batched = {
+            let mut batcher = self.shared_state.partial_batcher.lock().await;
+            let receiver = batcher.enqueue(
+                &pool_config.pool_url,
+                BatchPartial {
+                    request: post_request.clone(),
+                    metadata: metadata.clone(),
+                },
+                Instant::now(),
+            );
+            //Partials close to the end of the pool window do not wait for others
+            let time_left = time_left(self.shared_state.config.partial_window, sp_age);
+            if time_left
+                .zip(batcher.window())
+                .is_some_and(|(left, window)| left < window)
+            {
+                batcher.flush_now(&pool_config.pool_url, Instant::now());
+            }
+            receiver
+        };
+        //Partials the batch could not be sent for are posted on their own
+        let batched = match batched {
+            Some(receiver) => receiver.await.ok(),
+            None => None,
+        };
+        let result = match batched {
+            Some(result) => result,
+            None => {
+                self.pool_client
+                    .post_partial(
+                        &pool_config.pool_url,
+                        post_request.clone(),
+                        &Some(partial_headers(&metadata)),
+                    )
+                    .await
+            }
+        };
+        //Partials the pool could not be reached with were not submitted, they may be queued below
+        if !matches!(&result, Err(e) if is_retryable(e)) {
+            self.shared_state
+                .record_farm_stat(
+                    self.shared_state
+                        .farms
+                        .farm_for_p2_singleton(&self.p2_singleton_puzzle_hash),
+                    |s| s.partials_submitted += 1,
                 )
-                .map_err(|e| Error::new(ErrorKind::InvalidInput, format!("{:?}", e)))?;
-                if p_sig.to_signature().verify(
-                    true,
-                    &self.payload_bytes,
-                    AUG_SCHEME_DST,
-                    &agg_pk.to_bytes(),
-                    &agg_pk,
-                    true,
-                ) != BLST_ERROR::BLST_SUCCESS
-                {
-                    warn!(
-                        "Failed to validate partial signature {:?}",
-                        p_sig.to_signature()
+                .await;
+            self.shared_state.emit(FarmerEventKind::PartialSubmitted {
+                launcher_id: pool_config.launcher_id,
+                pool_url: pool_config.pool_url.clone(),
+                sp,
+            });
+        }
+        //Batched partials include the wait for their batch, which is bounded by the pool window
+        let regression = self.shared_state.latency_baselines.lock().await.record(
+            LatencyKind::Submission,
+            &pool_config.pool_url,
+            submit_start.elapsed(),
+            now(),
+        );
+        if let Some(kind) = regression {
+            warn!("{kind}");
+            self.shared_state.emit(kind);
+        }
+        //The pool round trip is part of the offset
+        let sp = self.shared_state.sp_tag(&self.new_pos.sp_hash).await;
+        let label = sp_label(&sp);
+        let mut pool_states = self.shared_state.pool_states.lock().await;
+        let Some(pool_state) = pool_states.get_mut(&self.p2_singleton_puzzle_hash) else {
+            return Ok(());
+        };
+        match result {
+            Ok(resp) => {
+                //Pools credit the difficulty the partial was sent at
+                let points = difficulty.unwrap_or(resp.new_difficulty);
+                pool_state.points_history.record_local(points);
+                self.shared_state
+                    .plot_leaderboard
+                    .lock()
+                    .await
+                    .record_partial(&self.new_pos.plot_identifier, points, now());
+                pool_state.current_points += points;
+                if pool_state.current_difficulty != Some(resp.new_difficulty) {
+                    info!(
+                        "{label} New Pool Difficulty: {:?} ",
+                        pool_state.current_difficulty
                     );
-                    continue;
                 }
-                plot_sig = Some(p_sig);
+                pool_state.current_difficulty = Some(resp.new_difficulty);
+                info!(
+                    "{label} Partial accepted, Current Points: {:?} ",
+                    pool_state.current_points
+                );
+                self.shared_state
+                    .record_farm_stat(
+                        self.shared_state
+                            .farms
+                            .farm_for_p2_singleton(&self.p2_singleton_puzzle_hash),
+                        |s| s.partials_accepted += 1,
+                    )
+                    .await;
+                self.shared_state.emit(FarmerEventKind::PartialAccepted {
+                    launcher_id: pool_config.launcher_id,
+                    pool_url: pool_config.pool_url.clone(),
+                    new_difficulty: resp.new_difficulty,
+                    sp,
+                });
             }
-        }
-        if let Some(pool_state) = self
-            .shared_state
-            .pool_states
-            .lock()
-            .await
-            .get_mut(&self.p2_singleton_puzzle_hash)
-        {
-            if let Some(pool_config) = &pool_state.pool_config {
-                if let Some(auth_key) = self
-                    .shared_state
-                    .auth_secret_keys
-                    .get(&pool_config.owner_public_key)
-                {
-                    let auth_sig = sign(auth_key, &self.payload_bytes);
-                    if let Some(plot_sig) = plot_sig {
-                        let agg_sig = AggregateSignature::aggregate(
-                            &[&plot_sig.to_signature(), &auth_sig],
-                            true,
+            Err(e)
+                if is_retryable(&e)
+                    && self.shared_state.partial_queue.lock().await.is_enabled() =>
+            {
+                warn!(
+                    "{label} Failed to reach pool {}, queueing partial for retry: {:?}",
+                    &pool_config.pool_url, e
+                );
+                self.shared_state
+                    .partial_queue
+                    .lock()
+                    .await
+                    .push(QueuedPartial {
+                        created_at: now().saturating_sub(self.requested_at.elapsed().as_secs()),
+                        sp_time: sp.map(|t| now().saturating_sub(t.offset_ms / 1000)),
+                        pool_url: pool_config.pool_url.clone(),
+                        p2_singleton_puzzle_hash: self.p2_singleton_puzzle_hash,
+                        request: post_request,
+                        metadata,
+                        difficulty,
+                    });
+            }
+            Err(e) => {
+                if !pool_state.auth.record_error(&pool_config, &e) {
+                    error!("{label} Error in pooling: {:?}", e);
+                }
+                if should_dump(e.error_code) && self.shared_state.config.forensics_max_mib > 0 {
+                    let dump = RejectedPartialDump {
+                        timestamp: now(),
+                        pool_url: pool_config.pool_url.clone(),
+                        error_code: e.error_code,
+                        error_message: e.error_message.clone(),
+                        plot_identifier: self.new_pos.plot_identifier.clone(),
+                        pool_difficulty: pool_state.current_difficulty,
+                        serialized_payload: hex::encode(post_request.payload.to_bytes()),
+                        payload_hash: hex::encode(&self.payload_bytes),
+                        request: post_request,
+                        local_quality_string: verify_and_get_quality_string(
+                            &self.new_pos.proof,
+                            self.constants,
+                            &self.new_pos.challenge_hash,
+                            &self.new_pos.sp_hash,
+                        ),
+                        local_plot_signature_valid: PublicKey::from_bytes(
+                            self.new_pos.proof.plot_public_key.to_sized_bytes(),
                         )
-                        .map_err(|e| Error::new(ErrorKind::InvalidInput, format!("{:?}", e)))?;
-                        let post_request = PostPartialRequest {
-                            payload: self.payload.clone(),
-                            aggregate_signature: agg_sig.to_signature().to_bytes().into(),
-                        };
-                        info!(
-                            "Submitting partial for {} to {}",
-                            post_request.payload.launcher_id.to_string(),
-                            &pool_config.pool_url
-                        );
-                        match self
-                            .pool_client
-                            .post_partial(
-                                &pool_config.pool_url,
-                                post_request,
-                                &Some(HEADERS.clone()),
-                            )
-                            .await
-                        {
-                            Ok(resp) => {
-                                pool_state.current_points += resp.new_difficulty;
-                                if pool_state.current_difficulty != Some(resp.new_difficulty) {
-                                    info!(
-                                        "New Pool Difficulty: {:?} ",
-                                        pool_state.current_difficulty
-                                    );
-                                }
-                                pool_state.current_difficulty = Some(resp.new_difficulty);
-                                info!("Current Points: {:?} ", pool_state.current_points);
-                            }
-                            Err(e) => {
-                                error!("Error in pooling: {:?}", e);
-                                if e.error_code == PoolErrorCode::ProofNotGoodEnough as u8 {
-                                    error!("Partial not good enough, forcing pool farmer update to get our current difficulty.");
-                                    self.shared_state
-                                        .force_pool_update
-                                        .store(true, Ordering::Relaxed);
-                                }
-                                if e.error_code == PoolErrorCode::InvalidSignature as u8 {
-                                    error!("Invalid Signature, Forcing Pool Update");
-                                    pool_state.next_farmer_update = Instant::now();
-                                }
-                            }
+                        .is_ok_and(|plot_pk| {
+                            verify_plot_signature(&plot_sig, &self.payload_bytes, &plot_pk)
+                        }),
+                        local_auth_signature_valid: verify_signature(
+                            &auth_pk,
+                            &self.payload_bytes,
+                            &auth_sig,
+                        ),
+                    };
+                    let max_bytes = self.shared_state.config.forensics_max_mib * 1024 * 1024;
+                    tokio::task::spawn_blocking(move || {
+                        if let Err(e) = write_dump(&get_forensics_path(), max_bytes, &dump) {
+                            warn!("Failed to write rejected partial dump: {:?}", e);
                         }
-                    }
-                } else {
-                    warn!(
-                        "No authentication sk for {}",
-                        &self.p2_singleton_puzzle_hash
-                    );
+                    });
+                }
+                self.shared_state
+                    .record_farm_stat(
+                        self.shared_state
+                            .farms
+                            .farm_for_p2_singleton(&self.p2_singleton_puzzle_hash),
+                        |s| s.partials_rejected += 1,
+                    )
+                    .await;
+                self.shared_state.emit(FarmerEventKind::PartialRejected {
+                    launcher_id: pool_config.launcher_id,
+                    pool_url: pool_config.pool_url.clone(),
+                    error_code: e.error_code,
+                    error_message: e.error_message.clone(),
+                    sp,
+                });
+                if e.error_code == PoolErrorCode::ProofNotGoodEnough as u8 {
+                    error!("Partial not good enough, forcing pool farmer update to get our current difficulty.");
+                    self.shared_state
+                        .force_pool_update
+                        .store(true, Ordering::Relaxed);
+                }
+                if e.error_code == PoolErrorCode::InvalidSignature as u8 {
+                    error!("Invalid Signature, Forcing Pool Update");
+                    pool_state.next_farmer_update = Instant::now();
                 }
-            } else {
-                warn!("No Pool Config for {}", &self.p2_singleton_puzzle_hash);
             }
-        } else {
-            warn!(
-                "Did not find pool info for {}",
-                &self.p2_singleton_puzzle_hash
-            );
         }
         Ok(())
     }
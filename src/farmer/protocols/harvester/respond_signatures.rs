@@ -1,89 +1,137 @@
+use crate::error::{FarmerError, FarmerResult};
+use crate::farmer::capture::Direction;
+use crate::farmer::events::FarmerEventKind;
+use crate::farmer::protocols::fullnode::request_signed_values::send_signed_values;
+use crate::farmer::signed_values::SignedValuesKey;
+use crate::farmer::signing::{
+    aggregate_block_signature, aggregate_sp_signature, PlotSigner, SigningStep, SigningTimings,
+};
+use crate::farmer::sp_recovery::recover_signage_point;
+use crate::farmer::sp_tag::sp_label;
 use crate::farmer::FarmerSharedState;
 use crate::harvesters::{Harvesters, SignatureHandler};
 use async_trait::async_trait;
-use blst::min_pk::AggregateSignature;
-use blst::BLST_ERROR;
 use dg_xch_clients::api::pool::PoolClient;
-use dg_xch_clients::protocols::farmer::{DeclareProofOfSpace, SignedValues};
+use dg_xch_clients::protocols::farmer::{DeclareProofOfSpace, NewSignagePoint, SignedValues};
 use dg_xch_clients::protocols::harvester::RespondSignatures;
 use dg_xch_clients::protocols::ProtocolMessageTypes;
-use dg_xch_clients::websocket::{ChiaMessage, Websocket};
 use dg_xch_core::blockchain::pool_target::PoolTarget;
-use dg_xch_core::blockchain::proof_of_space::{generate_plot_public_key, generate_taproot_sk};
-use dg_xch_core::clvm::bls_bindings::{sign, sign_prepend, AUG_SCHEME_DST};
+use dg_xch_core::clvm::bls_bindings::sign;
 use dg_xch_core::consensus::constants::ConsensusConstants;
 use dg_xch_pos::verify_and_get_quality_string;
 use dg_xch_serialize::ChiaSerialize;
 use log::{debug, error, info, warn};
 use std::collections::HashMap;
-use std::io::{Error, ErrorKind};
+use std::io::Error;
 use std::sync::Arc;
-use tokio_tungstenite::tungstenite::Message;
+use std::time::Instant;
 use uuid::Uuid;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureKind {
+    SignagePoint,
+    Block,
+}
+
+//Proofs are signed over the signage point, blocks over the foliage, anything else is a broken peer
+pub fn signature_kind(
+    response: &RespondSignatures,
+    sps: &[NewSignagePoint],
+) -> FarmerResult<SignatureKind> {
+    let [(first, _), (second, _), ..] = response.message_signatures.as_slice() else {
+        return Err(FarmerError::Protocol(format!(
+            "expected 2 message signatures, got {}",
+            response.message_signatures.len()
+        )));
+    };
+    if *first != response.sp_hash {
+        Ok(SignatureKind::Block)
+    } else if sps.iter().any(|sp| sp.reward_chain_sp == *second) {
+        Ok(SignatureKind::SignagePoint)
+    } else {
+        Err(FarmerError::Protocol(format!(
+            "signatures for {} do not match a reward chain signage point",
+            response.sp_hash
+        )))
+    }
+}
+
 pub struct RespondSignaturesHandler<T: PoolClient + Sized + Sync + Send + 'static> {
     pub pool_client: Arc<T>,
     pub shared_state: Arc<FarmerSharedState>,
     pub harvester_id: Uuid,
     pub harvesters: Arc<HashMap<Uuid, Arc<Harvesters>>>,
     pub constants: &'static ConsensusConstants,
+    pub requested_at: Instant,
 }
 #[async_trait]
 impl<T: PoolClient + Sized + Sync + Send + 'static> SignatureHandler
     for RespondSignaturesHandler<T>
 {
     async fn handle_signature(&self, response: RespondSignatures) -> Result<(), Error> {
+        self.shared_state.capture.record_harvester(
+            Direction::In,
+            self.harvester_id,
+            ProtocolMessageTypes::RespondSignatures,
+            &response,
+        );
+        self.shared_state
+            .record_signing_delay(self.harvester_id, self.requested_at.elapsed())
+            .await;
+        let sp = self.shared_state.sp_tag(&response.sp_hash).await;
+        let label = sp_label(&sp);
+        if !self.shared_state.has_signage_point(&response.sp_hash).await {
+            recover_signage_point(
+                &self.shared_state,
+                &response.challenge_hash,
+                &response.sp_hash,
+                sp.map(|t| t.signage_point_index),
+                &label,
+            )
+            .await;
+        }
         if let Some(sps) = self
             .shared_state
-            .signage_points
-            .lock()
+            .signage_points_for(&response.sp_hash)
             .await
-            .get(&response.sp_hash)
         {
             if sps.is_empty() {
-                error!("Missing Signage Points for {}", &response.sp_hash);
+                error!("{label} Missing Signage Points for {}", &response.sp_hash);
             } else {
-                let sp_index = sps
-                    .first()
-                    .expect("Sps was empty, Should have been caught above")
-                    .signage_point_index;
-                let mut is_sp_signatures = false;
-                let mut found_sp_hash_debug = false;
-                for sp_candidate in sps {
-                    if response.sp_hash == response.message_signatures[0].0 {
-                        found_sp_hash_debug = true;
-                        if sp_candidate.reward_chain_sp == response.message_signatures[1].0 {
-                            is_sp_signatures = true;
-                        }
-                    }
-                }
-                if found_sp_hash_debug {
-                    assert!(is_sp_signatures);
-                }
-                let mut pospace = None;
-                {
-                    let locked = self.shared_state.proofs_of_space.lock().await;
-                    let proofs = locked.get(&response.sp_hash);
-                    if let Some(proofs) = proofs {
-                        for (plot_identifier, candidate_pospace) in proofs {
-                            if *plot_identifier == response.plot_identifier {
-                                pospace = Some(candidate_pospace.clone());
-                                break;
-                            }
-                        }
-                    } else {
-                        debug!("Failed to load farmer proofs for {}", &response.sp_hash);
-                        return Ok(());
+                let sp_index = sps[0].signage_point_index;
+                let is_sp_signatures = match signature_kind(&response, &sps) {
+                    Ok(kind) => kind == SignatureKind::SignagePoint,
+                    Err(e) => {
+                        self.shared_state
+                            .record_handler_error(
+                                "RespondSignatures",
+                                &format!(
+                                    "{label} {e}, harvester {}, plot {}, {} message signatures",
+                                    self.harvester_id,
+                                    response.plot_identifier,
+                                    response.message_signatures.len()
+                                ),
+                            )
+                            .await;
+                        return Err(e.into());
                     }
-                }
+                };
+                let pospace = self
+                    .shared_state
+                    .proof_for(&response.sp_hash, &response.plot_identifier)
+                    .await;
                 if let Some(pospace) = pospace {
                     let include_taproot = pospace.pool_contract_puzzle_hash.is_some();
-                    if let Some(computed_quality_string) = verify_and_get_quality_string(
-                        &pospace,
-                        self.constants,
-                        &response.challenge_hash,
-                        &response.sp_hash,
-                    ) {
+                    let mut timings = SigningTimings::default();
+                    let computed_quality_string = timings.time(SigningStep::Proof, || {
+                        verify_and_get_quality_string(
+                            &pospace,
+                            self.constants,
+                            &response.challenge_hash,
+                            &response.sp_hash,
+                        )
+                    });
+                    if let Some(computed_quality_string) = computed_quality_string {
                         if is_sp_signatures {
                             let (challenge_chain_sp, challenge_chain_sp_harv_sig) =
                                 &response.message_signatures[0];
@@ -93,176 +141,137 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> SignatureHandler
                                 &response.message_signatures[1];
                             let reward_chain_sp_harv_sig = reward_chain_sp_harv_sig.try_into()?;
                             let local_pk = response.local_pk.into();
-                            for (_, sk) in self.shared_state.farmer_private_keys.iter() {
-                                let pk = sk.sk_to_pk();
-                                if pk.to_bytes() == *response.farmer_pk.to_sized_bytes() {
-                                    let agg_pk =
-                                        generate_plot_public_key(&local_pk, &pk, include_taproot)?;
-                                    if agg_pk.to_bytes()
-                                        != *pospace.plot_public_key.to_sized_bytes()
-                                    {
-                                        warn!(
-                                            "Key Mismatch {:?} != {:?}",
-                                            pospace.plot_public_key, agg_pk
-                                        );
+                            if let Some(sk) = self.shared_state.farmer_key_for(&response.farmer_pk)
+                            {
+                                let signer = timings.time(SigningStep::Taproot, || {
+                                    PlotSigner::new(sk, &local_pk, include_taproot)
+                                })?;
+                                if signer.plot_public_key().to_bytes()
+                                    != *pospace.plot_public_key.to_sized_bytes()
+                                {
+                                    warn!(
+                                        "{label} Key Mismatch {:?} != {:?}",
+                                        pospace.plot_public_key,
+                                        signer.plot_public_key()
+                                    );
+                                    return Ok(());
+                                }
+                                let sigs = aggregate_sp_signature(
+                                    &signer,
+                                    (challenge_chain_sp.as_ref(), &challenge_chain_sp_harv_sig),
+                                    (reward_chain_sp.as_ref(), &reward_chain_sp_harv_sig),
+                                    &mut timings,
+                                );
+                                self.shared_state
+                                    .record_signing_steps(&label, &timings)
+                                    .await;
+                                let (agg_sig_cc_sp, agg_sig_rc_sp) = match sigs {
+                                    Ok(sigs) => sigs,
+                                    Err(e) => {
+                                        warn!("{label} Failed to validate {e}");
                                         return Ok(());
                                     }
-                                    let (taproot_share_cc_sp, taproot_share_rc_sp) =
-                                        if include_taproot {
-                                            let taproot_sk = generate_taproot_sk(&local_pk, &pk)?;
-                                            (
-                                                Some(sign_prepend(
-                                                    &taproot_sk,
-                                                    challenge_chain_sp.as_ref(),
-                                                    &agg_pk,
-                                                )),
-                                                Some(sign_prepend(
-                                                    &taproot_sk,
-                                                    reward_chain_sp.as_ref(),
-                                                    &agg_pk,
-                                                )),
-                                            )
-                                        } else {
-                                            (None, None)
-                                        };
-                                    let farmer_share_cc_sp =
-                                        sign_prepend(sk, challenge_chain_sp.as_ref(), &agg_pk);
-                                    let cc_sigs_to_agg =
-                                        if let Some(taproot_share_cc_sp) = &taproot_share_cc_sp {
-                                            vec![
-                                                &challenge_chain_sp_harv_sig,
-                                                &farmer_share_cc_sp,
-                                                taproot_share_cc_sp,
-                                            ]
-                                        } else {
-                                            vec![&challenge_chain_sp_harv_sig, &farmer_share_cc_sp]
-                                        };
-                                    let agg_sig_cc_sp =
-                                        AggregateSignature::aggregate(&cc_sigs_to_agg, true)
-                                            .map_err(|e| {
-                                                Error::new(
-                                                    ErrorKind::InvalidInput,
-                                                    format!("{:?}", e),
-                                                )
-                                            })?;
-                                    if agg_sig_cc_sp.to_signature().verify(
-                                        true,
-                                        challenge_chain_sp.as_ref(),
-                                        AUG_SCHEME_DST,
-                                        &agg_pk.to_bytes(),
-                                        &agg_pk,
-                                        true,
-                                    ) != BLST_ERROR::BLST_SUCCESS
+                                };
+                                let (pool_target, pool_target_signature) = if let Some(
+                                    pool_public_key,
+                                ) =
+                                    &pospace.pool_public_key
+                                {
+                                    if let Some(sk) =
+                                        self.shared_state.pool_public_keys.get(pool_public_key)
                                     {
-                                        warn!(
-                                            "Failed to validate cc signature {:?}",
-                                            agg_sig_cc_sp.to_signature()
-                                        );
-                                        return Ok(());
-                                    }
-
-                                    let farmer_share_rc_sp =
-                                        sign_prepend(sk, reward_chain_sp.as_ref(), &agg_pk);
-                                    let rc_sigs_to_agg =
-                                        if let Some(taproot_share_rc_sp) = &taproot_share_rc_sp {
-                                            vec![
-                                                &reward_chain_sp_harv_sig,
-                                                &farmer_share_rc_sp,
-                                                taproot_share_rc_sp,
-                                            ]
-                                        } else {
-                                            vec![&reward_chain_sp_harv_sig, &farmer_share_rc_sp]
+                                        let pool_target = PoolTarget {
+                                            max_height: 0,
+                                            puzzle_hash: self
+                                                .shared_state
+                                                .farms
+                                                .target_for_farmer_key(&response.farmer_pk)
+                                                .unwrap_or(*self.shared_state.pool_target),
                                         };
-                                    let agg_sig_rc_sp =
-                                        AggregateSignature::aggregate(&rc_sigs_to_agg, true)
-                                            .map_err(|e| {
-                                                Error::new(
-                                                    ErrorKind::InvalidInput,
-                                                    format!("{:?}", e),
-                                                )
-                                            })?;
-                                    if agg_sig_rc_sp.to_signature().verify(
-                                        true,
-                                        reward_chain_sp.as_ref(),
-                                        AUG_SCHEME_DST,
-                                        &agg_pk.to_bytes(),
-                                        &agg_pk,
-                                        true,
-                                    ) != BLST_ERROR::BLST_SUCCESS
-                                    {
-                                        warn!(
-                                            "Failed to validate rc signature {:?}",
-                                            agg_sig_rc_sp.to_signature()
-                                        );
-                                        return Ok(());
-                                    }
-                                    let (pool_target, pool_target_signature) = if let Some(
-                                        pool_public_key,
-                                    ) =
-                                        &pospace.pool_public_key
-                                    {
-                                        if let Some(sk) =
-                                            self.shared_state.pool_public_keys.get(pool_public_key)
-                                        {
-                                            let pool_target = PoolTarget {
-                                                max_height: 0,
-                                                puzzle_hash: *self.shared_state.pool_target,
-                                            };
-                                            let pool_target_signature =
-                                                sign(sk, &pool_target.to_bytes());
-                                            (Some(pool_target), Some(pool_target_signature))
-                                        } else {
-                                            error!("Don't have the private key for the pool key used by harvester: {pool_public_key}");
-                                            return Ok(());
-                                        }
+                                        let pool_target_signature =
+                                            sign(sk, &pool_target.to_bytes());
+                                        (Some(pool_target), Some(pool_target_signature))
                                     } else {
-                                        (None, None)
-                                    };
-                                    let request = DeclareProofOfSpace {
-                                        challenge_hash: response.challenge_hash,
-                                        challenge_chain_sp: *challenge_chain_sp,
-                                        signage_point_index: sp_index,
-                                        reward_chain_sp: *reward_chain_sp,
-                                        proof_of_space: pospace.clone(),
-                                        challenge_chain_sp_signature: agg_sig_cc_sp
-                                            .to_signature()
-                                            .to_bytes()
-                                            .into(),
-                                        reward_chain_sp_signature: agg_sig_rc_sp
-                                            .to_signature()
-                                            .to_bytes()
-                                            .into(),
-                                        farmer_puzzle_hash: *self.shared_state.farmer_target,
-                                        pool_target,
-                                        pool_signature: pool_target_signature
-                                            .map(|s| s.to_bytes().into()),
-                                    };
-                                    if let Some(client) =
-                                        self.shared_state.full_node_client.lock().await.as_mut()
-                                    {
-                                        let _ = client
-                                            .client
-                                            .lock()
-                                            .await
-                                            .send(Message::Binary(
-                                                ChiaMessage::new(
-                                                    ProtocolMessageTypes::DeclareProofOfSpace,
-                                                    &request,
-                                                    None,
-                                                )
-                                                .to_bytes(),
-                                            ))
-                                            .await;
-                                        info!("Declaring Proof of Space: {:?}", request);
-                                    } else {
-                                        error!(
-                                            "Failed to declare Proof of Space: {:?} No Client",
-                                            request
-                                        );
+                                        error!("Don't have the private key for the pool key used by harvester: {pool_public_key}");
+                                        return Ok(());
                                     }
+                                } else {
+                                    (None, None)
+                                };
+                                let request = DeclareProofOfSpace {
+                                    challenge_hash: response.challenge_hash,
+                                    challenge_chain_sp: *challenge_chain_sp,
+                                    signage_point_index: sp_index,
+                                    reward_chain_sp: *reward_chain_sp,
+                                    proof_of_space: pospace.as_ref().clone(),
+                                    challenge_chain_sp_signature: agg_sig_cc_sp.to_bytes().into(),
+                                    reward_chain_sp_signature: agg_sig_rc_sp.to_bytes().into(),
+                                    farmer_puzzle_hash: self
+                                        .shared_state
+                                        .farms
+                                        .target_for_farmer_key(&response.farmer_pk)
+                                        .unwrap_or(*self.shared_state.farmer_target),
+                                    pool_target,
+                                    pool_signature: pool_target_signature
+                                        .map(|s| s.to_bytes().into()),
+                                };
+                                if !self
+                                    .shared_state
+                                    .allow_declaration(&request.challenge_chain_sp)
+                                    .await
+                                {
+                                    return Ok(());
+                                }
+                                if self
+                                    .shared_state
+                                    .send_to_full_node(
+                                        ProtocolMessageTypes::DeclareProofOfSpace,
+                                        &request,
+                                    )
+                                    .await
+                                {
+                                    info!("{label} Declaring Proof of Space: {:?}", request);
+                                    let launcher_id =
+                                        match &request.proof_of_space.pool_contract_puzzle_hash {
+                                            Some(p2_singleton_puzzle_hash) => self
+                                                .shared_state
+                                                .pool_states
+                                                .lock()
+                                                .await
+                                                .get(p2_singleton_puzzle_hash)
+                                                .and_then(|s| s.pool_config.as_ref())
+                                                .map(|c| c.launcher_id),
+                                            None => None,
+                                        };
+                                    self.shared_state
+                                        .record_farm_stat(
+                                            self.shared_state
+                                                .farms
+                                                .farm_for_farmer_key(&response.farmer_pk),
+                                            |s| s.proofs_declared += 1,
+                                        )
+                                        .await;
+                                    self.shared_state.emit(FarmerEventKind::ProofDeclared {
+                                        challenge_hash: request.challenge_hash,
+                                        sp_hash: request.challenge_chain_sp,
+                                        plot_identifier: response.plot_identifier.clone(),
+                                        sp,
+                                        launcher_id,
+                                    });
+                                } else {
+                                    error!(
+                                        "{label} Failed to declare Proof of Space: {:?} No Client",
+                                        request
+                                    );
+                                    self.shared_state.emit(FarmerEventKind::Error {
+                                        message: format!(
+                                            "Failed to declare Proof of Space for {}, No Client",
+                                            response.plot_identifier
+                                        ),
+                                    });
                                 }
                             }
-                        } else if response.message_signatures.len() > 1 {
+                        } else {
                             let (foliage_block_data_hash, foliage_sig_harvester) =
                                 &response.message_signatures[0];
                             let foliage_sig_harvester = foliage_sig_harvester.try_into()?;
@@ -273,149 +282,73 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> SignatureHandler
                             let foliage_transaction_block_sig_harvester =
                                 foliage_transaction_block_sig_harvester.try_into()?;
                             let local_pk = response.local_pk.into();
-                            for (_, sk) in self.shared_state.farmer_private_keys.iter() {
-                                let pk = sk.sk_to_pk();
-                                if pk.to_bytes() == *response.farmer_pk.to_sized_bytes() {
-                                    let agg_pk =
-                                        generate_plot_public_key(&local_pk, &pk, include_taproot)?;
-                                    let (
-                                        foliage_sig_taproot,
-                                        foliage_transaction_block_sig_taproot,
-                                    ) = if include_taproot {
-                                        let taproot_sk = generate_taproot_sk(&local_pk, &pk)?;
-                                        (
-                                            Some(sign_prepend(
-                                                &taproot_sk,
-                                                foliage_block_data_hash.as_ref(),
-                                                &agg_pk,
-                                            )),
-                                            Some(sign_prepend(
-                                                &taproot_sk,
-                                                foliage_transaction_block_hash.as_ref(),
-                                                &agg_pk,
-                                            )),
-                                        )
-                                    } else {
-                                        (None, None)
-                                    };
-                                    let foliage_sig_farmer =
-                                        sign_prepend(sk, foliage_block_data_hash.as_ref(), &agg_pk);
-                                    let foliage_transaction_block_sig_farmer = sign_prepend(
-                                        sk,
+                            if let Some(sk) = self.shared_state.farmer_key_for(&response.farmer_pk)
+                            {
+                                let signer = timings.time(SigningStep::Taproot, || {
+                                    PlotSigner::new(sk, &local_pk, include_taproot)
+                                })?;
+                                let sigs = aggregate_block_signature(
+                                    &signer,
+                                    (foliage_block_data_hash.as_ref(), &foliage_sig_harvester),
+                                    (
                                         foliage_transaction_block_hash.as_ref(),
-                                        &agg_pk,
-                                    );
-                                    let foliage_sigs_to_agg =
-                                        if let Some(foliage_sig_taproot) = &foliage_sig_taproot {
-                                            vec![
-                                                &foliage_sig_harvester,
-                                                &foliage_sig_farmer,
-                                                foliage_sig_taproot,
-                                            ]
-                                        } else {
-                                            vec![&foliage_sig_harvester, &foliage_sig_farmer]
-                                        };
-                                    let foliage_agg_sig =
-                                        AggregateSignature::aggregate(&foliage_sigs_to_agg, true)
-                                            .map_err(|e| {
-                                            Error::new(ErrorKind::InvalidInput, format!("{:?}", e))
-                                        })?;
-
-                                    let foliage_block_sigs_to_agg =
-                                        if let Some(foliage_transaction_block_sig_taproot) =
-                                            &foliage_transaction_block_sig_taproot
-                                        {
-                                            vec![
-                                                &foliage_transaction_block_sig_harvester,
-                                                &foliage_transaction_block_sig_farmer,
-                                                foliage_transaction_block_sig_taproot,
-                                            ]
-                                        } else {
-                                            vec![
-                                                &foliage_transaction_block_sig_harvester,
-                                                &foliage_transaction_block_sig_farmer,
-                                            ]
-                                        };
-                                    let foliage_block_agg_sig = AggregateSignature::aggregate(
-                                        &foliage_block_sigs_to_agg,
-                                        true,
-                                    )
-                                    .map_err(|e| {
-                                        Error::new(ErrorKind::InvalidInput, format!("{:?}", e))
-                                    })?;
-                                    if foliage_agg_sig.to_signature().verify(
-                                        true,
-                                        foliage_block_data_hash.as_ref(),
-                                        AUG_SCHEME_DST,
-                                        &agg_pk.to_bytes(),
-                                        &agg_pk,
-                                        true,
-                                    ) != BLST_ERROR::BLST_SUCCESS
-                                    {
-                                        warn!(
-                                            "Failed to validate foliage signature {:?}",
-                                            foliage_agg_sig.to_signature()
-                                        );
+                                        &foliage_transaction_block_sig_harvester,
+                                    ),
+                                    &mut timings,
+                                );
+                                self.shared_state
+                                    .record_signing_steps(&label, &timings)
+                                    .await;
+                                let (foliage_agg_sig, foliage_block_agg_sig) = match sigs {
+                                    Ok(sigs) => sigs,
+                                    Err(e) => {
+                                        warn!("{label} Failed to validate {e}");
                                         return Ok(());
                                     }
-                                    if foliage_block_agg_sig.to_signature().verify(
-                                        true,
-                                        foliage_transaction_block_hash.as_ref(),
-                                        AUG_SCHEME_DST,
-                                        &agg_pk.to_bytes(),
-                                        &agg_pk,
-                                        true,
-                                    ) != BLST_ERROR::BLST_SUCCESS
-                                    {
-                                        warn!(
-                                            "Failed to validate foliage_block signature {:?}",
-                                            foliage_block_agg_sig.to_signature()
-                                        );
-                                        return Ok(());
-                                    }
-                                    let request = SignedValues {
-                                        quality_string: computed_quality_string,
-                                        foliage_block_data_signature: foliage_agg_sig
-                                            .to_signature()
-                                            .to_bytes()
-                                            .into(),
-                                        foliage_transaction_block_signature: foliage_block_agg_sig
-                                            .to_signature()
-                                            .to_bytes()
-                                            .into(),
-                                    };
+                                };
+                                let request = SignedValues {
+                                    quality_string: computed_quality_string,
+                                    foliage_block_data_signature: foliage_agg_sig.to_bytes().into(),
+                                    foliage_transaction_block_signature: foliage_block_agg_sig
+                                        .to_bytes()
+                                        .into(),
+                                };
 
-                                    if let Some(client) =
-                                        self.shared_state.full_node_client.lock().await.as_mut()
-                                    {
-                                        let _ = client
-                                            .client
-                                            .lock()
-                                            .await
-                                            .send(Message::Binary(
-                                                ChiaMessage::new(
-                                                    ProtocolMessageTypes::SignedValues,
-                                                    &request,
-                                                    None,
-                                                )
-                                                .to_bytes(),
-                                            ))
-                                            .await;
-                                        info!("Sending Signed Values: {:?}", request);
-                                    } else {
-                                        error!(
-                                            "Failed to Sending Signed Values: {:?} No Client",
-                                            request
-                                        );
-                                    }
+                                let key = SignedValuesKey {
+                                    quality_string: request.quality_string,
+                                    foliage_block_data_hash: *foliage_block_data_hash,
+                                    foliage_transaction_block_hash: *foliage_transaction_block_hash,
+                                };
+                                //A harvester answering a retried request twice
+                                if !self
+                                    .shared_state
+                                    .signed_values
+                                    .lock()
+                                    .await
+                                    .complete(key, &request)
+                                {
+                                    debug!(
+                                        "{label} Signed Values for {} were already sent",
+                                        request.quality_string
+                                    );
+                                    return Ok(());
+                                }
+                                if send_signed_values(&self.shared_state, &request).await {
+                                    info!("{label} Sending Signed Values: {:?}", request);
+                                    self.shared_state.emit(FarmerEventKind::SignedValues {
+                                        quality_string: request.quality_string,
+                                        sp,
+                                    });
+                                } else {
+                                    error!(
+                                        "{label} Failed to Sending Signed Values: {:?} No Client",
+                                        request
+                                    );
                                 }
                             }
-                        } else {
-                            debug!("Detected Partial or PoSpace {:?}", pospace);
-                            return Ok(());
                         }
                     } else {
-                        warn!("Have invalid PoSpace {:?}", pospace);
+                        warn!("{label} Have invalid PoSpace {:?}", pospace);
                         return Ok(());
                     }
                 } else {
@@ -424,7 +357,10 @@ impl<T: PoolClient + Sized + Sync + Send + 'static> SignatureHandler
                 }
             }
         } else {
-            error!("Do not have challenge hash {}", &response.challenge_hash);
+            error!(
+                "{label} Do not have challenge hash {}",
+                &response.challenge_hash
+            );
         }
         Ok(())
     }
@@ -1,2 +1,154 @@
 pub mod fullnode;
 pub mod harvester;
+
+use crate::error::{FarmerError, FarmerResult};
+use crate::farmer::peer::unknown_trailing_bytes;
+use dg_xch_serialize::ChiaSerialize;
+use log::debug;
+use std::io::Cursor;
+
+//Malformed data from a peer is a protocol error, fields appended by newer nodes are ignored
+pub fn decode_message<T: ChiaSerialize>(name: &str, data: &Vec<u8>) -> FarmerResult<T> {
+    let mut cursor = Cursor::new(data);
+    let message = T::from_bytes(&mut cursor)
+        .map_err(|e| FarmerError::Protocol(format!("Malformed {name}: {e}")))?;
+    let unknown = unknown_trailing_bytes(&cursor);
+    if unknown > 0 {
+        debug!("Ignoring {unknown} bytes of newer {name} fields");
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::protocols::harvester::respond_signatures::{
+        signature_kind, RespondSignaturesHandler, SignatureKind,
+    };
+    use crate::farmer::FarmerSharedState;
+    use crate::harvesters::SignatureHandler;
+    use dg_xch_clients::api::pool::DefaultPoolClient;
+    use dg_xch_clients::protocols::farmer::{NewSignagePoint, RequestSignedValues};
+    use dg_xch_clients::protocols::harvester::RespondSignatures;
+    use dg_xch_core::blockchain::sized_bytes::{Bytes32, Bytes96};
+    use dg_xch_core::consensus::constants::MAINNET;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::Instant;
+    use uuid::Uuid;
+
+    fn hash(rng: &mut StdRng) -> Bytes32 {
+        Bytes32::from_sized_bytes(rng.gen())
+    }
+
+    fn signage_point(rng: &mut StdRng) -> NewSignagePoint {
+        NewSignagePoint {
+            challenge_hash: hash(rng),
+            challenge_chain_sp: hash(rng),
+            reward_chain_sp: hash(rng),
+            difficulty: rng.gen(),
+            sub_slot_iters: rng.gen(),
+            signage_point_index: rng.gen(),
+        }
+    }
+
+    fn respond_signatures(rng: &mut StdRng, signatures: usize) -> RespondSignatures {
+        RespondSignatures {
+            plot_identifier: "plot".to_string(),
+            challenge_hash: hash(rng),
+            sp_hash: hash(rng),
+            local_pk: Default::default(),
+            farmer_pk: Default::default(),
+            message_signatures: (0..signatures)
+                .map(|_| (hash(rng), Bytes96::default()))
+                .collect(),
+        }
+    }
+
+    //Truncated, bit flipped and random messages must decode to an error or a value, never panic
+    #[test]
+    fn malformed_messages_do_not_panic() {
+        let mut rng = StdRng::seed_from_u64(1665);
+        let sp = signage_point(&mut rng);
+        let encoded = sp.to_bytes();
+        for len in 0..encoded.len() {
+            let truncated = encoded[..len].to_vec();
+            assert!(decode_message::<NewSignagePoint>("NewSignagePoint", &truncated).is_err());
+        }
+        assert_eq!(
+            decode_message::<NewSignagePoint>("NewSignagePoint", &encoded).unwrap(),
+            sp
+        );
+        let encoded = respond_signatures(&mut rng, 2).to_bytes();
+        for _ in 0..2000 {
+            let mut mutated = encoded.clone();
+            for _ in 0..rng.gen_range(1..4) {
+                let i = rng.gen_range(0..mutated.len());
+                mutated[i] = rng.gen();
+            }
+            mutated.truncate(rng.gen_range(0..=mutated.len()));
+            let _ = decode_message::<RespondSignatures>("RespondSignatures", &mutated);
+            let random: Vec<u8> = (0..rng.gen_range(0..200)).map(|_| rng.gen()).collect();
+            let _ = decode_message::<NewSignagePoint>("NewSignagePoint", &random);
+            let _ = decode_message::<RequestSignedValues>("RequestSignedValues", &random);
+            let _ = decode_message::<RespondSignatures>("RespondSignatures", &random);
+        }
+    }
+
+    #[test]
+    fn unexpected_signatures_are_protocol_errors() {
+        let mut rng = StdRng::seed_from_u64(1665);
+        let sps: Vec<NewSignagePoint> = (0..3).map(|_| signage_point(&mut rng)).collect();
+        for count in 0..2 {
+            let response = respond_signatures(&mut rng, count);
+            assert!(matches!(
+                signature_kind(&response, &sps),
+                Err(FarmerError::Protocol(_))
+            ));
+        }
+        let mut response = respond_signatures(&mut rng, 2);
+        assert_eq!(
+            signature_kind(&response, &sps).unwrap(),
+            SignatureKind::Block
+        );
+        response.message_signatures[0].0 = response.sp_hash;
+        assert!(matches!(
+            signature_kind(&response, &sps),
+            Err(FarmerError::Protocol(_))
+        ));
+        response.message_signatures[1].0 = sps[1].reward_chain_sp;
+        assert_eq!(
+            signature_kind(&response, &sps).unwrap(),
+            SignatureKind::SignagePoint
+        );
+    }
+
+    #[tokio::test]
+    async fn mismatching_signatures_are_counted_not_fatal() {
+        let mut rng = StdRng::seed_from_u64(1666);
+        let shared_state = Arc::new(FarmerSharedState::default());
+        let sp = signage_point(&mut rng);
+        let mut response = respond_signatures(&mut rng, 2);
+        response.message_signatures[0].0 = response.sp_hash;
+        shared_state
+            .record_recovered_signage_point(response.sp_hash, sp)
+            .await;
+        let handler = RespondSignaturesHandler {
+            pool_client: Arc::new(DefaultPoolClient::new()),
+            shared_state: shared_state.clone(),
+            harvester_id: Uuid::new_v4(),
+            harvesters: Arc::new(HashMap::new()),
+            constants: &MAINNET,
+            requested_at: Instant::now(),
+        };
+        assert!(handler.handle_signature(response.clone()).await.is_err());
+        response.message_signatures.truncate(1);
+        assert!(handler.handle_signature(response).await.is_err());
+        assert_eq!(
+            shared_state.gui_stats.lock().await.handler_errors["RespondSignatures"],
+            2
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use crate::farmer::config::Config;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use dg_xch_core::consensus::constants::CONSENSUS_CONSTANTS_MAP;
+use dg_xch_keys::decode_puzzle_hash;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisabledFeature {
+    pub feature: String,
+    pub reason: String,
+}
+impl Display for DisabledFeature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} disabled: {}", self.feature, self.reason)
+    }
+}
+
+//What an incomplete config still allows, the rest keeps running with these parts switched off
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Readiness {
+    //No usable full node or payout target, plots are loaded but nothing is farmed
+    pub harvester_only: bool,
+    //p2 singleton puzzle hashes whose partials are not sent, their plots still farm blocks
+    pub disabled_pools: HashSet<Bytes32>,
+    //Only public keys are configured, proofs and partials are counted but not signed
+    pub watch_only: bool,
+    pub disabled: Vec<DisabledFeature>,
+}
+impl Readiness {
+    pub fn check(config: &Config) -> Self {
+        let mut readiness = Self::default();
+        let mut harvester_only = |reason: String| {
+            readiness.harvester_only = true;
+            readiness.disabled.push(DisabledFeature {
+                feature: "Farming".to_string(),
+                reason,
+            });
+        };
+        if CONSENSUS_CONSTANTS_MAP
+            .get(&config.selected_network)
+            .is_none()
+        {
+            harvester_only(format!("unknown network {}", config.selected_network));
+        }
+        if config.fullnode_ws_host.is_empty() || config.fullnode_ws_port == 0 {
+            harvester_only("fullnode_ws_host or fullnode_ws_port is missing".to_string());
+        }
+        let farms = config.all_farms();
+        if farms.is_empty() {
+            harvester_only("no farmer_info is configured".to_string());
+        }
+        let mut names = HashSet::new();
+        for farm in &farms {
+            if !names.insert(&farm.name) {
+                harvester_only(format!("farm name {} is used twice", farm.name));
+            }
+            if let Err(e) = decode_puzzle_hash(&farm.payout_address) {
+                harvester_only(format!(
+                    "invalid payout_address for farm {}: {e}",
+                    farm.name
+                ));
+            }
+        }
+        if config.is_watch_only() {
+            readiness.watch_only = true;
+            readiness.disabled.push(DisabledFeature {
+                feature: "Signing".to_string(),
+                reason: "watch only, proofs and partials are reported but not sent".to_string(),
+            });
+            return readiness;
+        }
+        for info in farms.iter().flat_map(|f| f.farmer_info.iter()) {
+            if info.is_watch_only() {
+                readiness.disabled.push(DisabledFeature {
+                    feature: format!("Signing for farmer key {}", info.farmer_public_key()),
+                    reason: "only the public key is configured".to_string(),
+                });
+            }
+        }
+        for farm in &farms {
+            for pool in &farm.pool_info {
+                if pool.pool_url.is_empty() {
+                    continue;
+                }
+                let info = farm
+                    .farmer_info
+                    .iter()
+                    .find(|f| f.launcher_id == Some(pool.launcher_id));
+                let reason = match info {
+                    None => Some("no farmer_info with this launcher_id"),
+                    Some(info) if info.owner_secret_key.is_none() => {
+                        Some("owner_secret_key is missing")
+                    }
+                    Some(info) if info.auth_secret_key.is_none() => {
+                        Some("auth_secret_key is missing")
+                    }
+                    Some(_) => None,
+                };
+                if let Some(reason) = reason {
+                    readiness
+                        .disabled_pools
+                        .insert(pool.p2_singleton_puzzle_hash);
+                    readiness.disabled.push(DisabledFeature {
+                        feature: format!("Partials for launcher {}", pool.launcher_id),
+                        reason: reason.to_string(),
+                    });
+                }
+            }
+        }
+        readiness
+    }
+    pub fn is_pool_disabled(&self, p2_singleton_puzzle_hash: &Bytes32) -> bool {
+        self.disabled_pools.contains(p2_singleton_puzzle_hash)
+    }
+    pub fn log(&self) {
+        for disabled in &self.disabled {
+            warn!("{disabled}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::farmer::config::{FarmingInfo, PoolWalletConfig};
+    use dg_xch_core::blockchain::sized_bytes::Bytes48;
+    use dg_xch_keys::encode_puzzle_hash;
+
+    #[test]
+    fn incomplete_pool_only_disables_its_partials() {
+        let launcher_id = Bytes32::from_sized_bytes([1u8; 32]);
+        let config = Config {
+            payout_address: encode_puzzle_hash(&Bytes32::default(), "xch").unwrap(),
+            farmer_info: vec![FarmingInfo::default()],
+            pool_info: vec![PoolWalletConfig {
+                launcher_id,
+                pool_url: "https://pool.example".to_string(),
+                p2_singleton_puzzle_hash: launcher_id,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let readiness = Readiness::check(&config);
+        assert!(!readiness.harvester_only);
+        assert!(readiness.is_pool_disabled(&launcher_id));
+        assert_eq!(readiness.disabled.len(), 1);
+        let readiness = Readiness::check(&Config {
+            fullnode_ws_host: String::new(),
+            ..config
+        });
+        assert!(readiness.harvester_only);
+    }
+
+    #[test]
+    fn public_key_only_config_is_watch_only() {
+        let farmer_public_key = Bytes48::from_sized_bytes([2u8; 48]);
+        let pool_public_key = Bytes48::from_sized_bytes([3u8; 48]);
+        let config = Config {
+            payout_address: encode_puzzle_hash(&Bytes32::default(), "xch").unwrap(),
+            farmer_info: vec![FarmingInfo {
+                farmer_public_key: Some(farmer_public_key),
+                pool_public_key: Some(pool_public_key),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            config.public_keys(),
+            (vec![farmer_public_key], vec![pool_public_key])
+        );
+        let readiness = Readiness::check(&config);
+        assert!(readiness.watch_only);
+        assert!(!readiness.harvester_only);
+        assert!(!Readiness::check(&Config::default()).watch_only);
+    }
+}
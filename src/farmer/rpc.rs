@@ -0,0 +1,195 @@
+use crate::farmer::config::{Config, RpcPolicyConfig};
+use crate::farmer::dns::rpc_host;
+use dg_xch_clients::api::full_node::FullnodeAPI;
+use dg_xch_clients::rpc::full_node::FullnodeClient;
+use dg_xch_core::blockchain::blockchain_state::BlockchainState;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EndpointMetrics {
+    pub calls: u64,
+    pub successes: u64,
+    pub failures: u64,
+    pub timeouts: u64,
+    pub retries: u64,
+    pub rejected: u64,
+    pub total_latency_ms: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct CircuitState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+//Circuit and metrics shared by every RPC client of the farmer
+#[derive(Debug, Default)]
+pub struct RpcHealth {
+    circuit: Mutex<CircuitState>,
+    metrics: Mutex<BTreeMap<String, EndpointMetrics>>,
+}
+impl RpcHealth {
+    pub async fn metrics(&self) -> BTreeMap<String, EndpointMetrics> {
+        self.metrics.lock().await.clone()
+    }
+    pub async fn is_open(&self) -> bool {
+        self.circuit
+            .lock()
+            .await
+            .open_until
+            .map(|t| t > Instant::now())
+            .unwrap_or(false)
+    }
+    async fn record<F: FnOnce(&mut EndpointMetrics)>(&self, endpoint: &str, f: F) {
+        f(self
+            .metrics
+            .lock()
+            .await
+            .entry(endpoint.to_string())
+            .or_default())
+    }
+}
+
+pub struct FullnodeRpc {
+    client: FullnodeClient,
+    policy: RpcPolicyConfig,
+    health: Arc<RpcHealth>,
+}
+impl FullnodeRpc {
+    pub async fn new(config: &Config, health: Arc<RpcHealth>) -> Self {
+        let host = rpc_host(config, &config.fullnode_rpc_host, config.fullnode_rpc_port).await;
+        Self::with_client(
+            FullnodeClient::new(
+                &host,
+                config.fullnode_rpc_port,
+                config.node_ssl_root_path(&config.fullnode_rpc_host, config.fullnode_rpc_port),
+                &None,
+            ),
+            config.fullnode_rpc_policy.clone(),
+            health,
+        )
+    }
+    //Background reads go to the spare node when one is configured
+    pub async fn reader(config: &Config, health: Arc<RpcHealth>) -> Self {
+        let Some(spare) = &config.spare_rpc else {
+            return Self::new(config, health).await;
+        };
+        let host = rpc_host(config, &spare.host, spare.port).await;
+        Self::with_client(
+            FullnodeClient::new(
+                &host,
+                spare.port,
+                spare
+                    .ssl_root_path
+                    .clone()
+                    .or_else(|| config.node_ssl_root_path(&spare.host, spare.port)),
+                &None,
+            ),
+            config.fullnode_rpc_policy.clone(),
+            health,
+        )
+    }
+    pub fn with_client(
+        client: FullnodeClient,
+        policy: RpcPolicyConfig,
+        health: Arc<RpcHealth>,
+    ) -> Self {
+        Self {
+            client,
+            policy,
+            health,
+        }
+    }
+    pub async fn get_blockchain_state(&self) -> Result<BlockchainState, Error> {
+        self.call("get_blockchain_state", |c| c.get_blockchain_state())
+            .await
+    }
+    //Runs the request under the timeout, retry and circuit breaker policy, endpoint names the metrics entry
+    pub async fn call<'a, T, F, Fut>(&'a self, endpoint: &str, request: F) -> Result<T, Error>
+    where
+        F: Fn(&'a FullnodeClient) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        if self.health.is_open().await {
+            self.health.record(endpoint, |m| m.rejected += 1).await;
+            return Err(Error::new(
+                ErrorKind::ConnectionRefused,
+                format!("Full node RPC circuit open, skipping {endpoint}"),
+            ));
+        }
+        self.health.record(endpoint, |m| m.calls += 1).await;
+        let mut delay = Duration::from_millis(self.policy.retry_delay);
+        let mut attempt = 0;
+        loop {
+            let start = Instant::now();
+            let err = match timeout(
+                Duration::from_secs(self.policy.timeout),
+                request(&self.client),
+            )
+            .await
+            {
+                Ok(Ok(value)) => {
+                    let latency = start.elapsed().as_millis() as u64;
+                    self.health
+                        .record(endpoint, |m| {
+                            m.successes += 1;
+                            m.total_latency_ms += latency;
+                        })
+                        .await;
+                    let mut circuit = self.health.circuit.lock().await;
+                    circuit.consecutive_failures = 0;
+                    circuit.open_until = None;
+                    return Ok(value);
+                }
+                Ok(Err(e)) => e,
+                Err(_) => {
+                    self.health.record(endpoint, |m| m.timeouts += 1).await;
+                    Error::new(
+                        ErrorKind::TimedOut,
+                        format!("{endpoint} timed out after {} seconds", self.policy.timeout),
+                    )
+                }
+            };
+            if attempt >= self.policy.retries {
+                self.record_failure(endpoint, &err).await;
+                return Err(err);
+            }
+            attempt += 1;
+            debug!("Retrying {endpoint} in {:?}: {:?}", delay, err);
+            self.health.record(endpoint, |m| m.retries += 1).await;
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+    async fn record_failure(&self, endpoint: &str, err: &Error) {
+        let message = format!("{:?}", err);
+        self.health
+            .record(endpoint, |m| {
+                m.failures += 1;
+                m.last_error = Some(message);
+            })
+            .await;
+        let mut circuit = self.health.circuit.lock().await;
+        circuit.consecutive_failures += 1;
+        if self.policy.failure_threshold > 0
+            && circuit.consecutive_failures >= self.policy.failure_threshold
+        {
+            warn!(
+                "Full node RPC failed {} times in a row, pausing calls for {} seconds",
+                circuit.consecutive_failures, self.policy.open_duration
+            );
+            circuit.open_until =
+                Some(Instant::now() + Duration::from_secs(self.policy.open_duration));
+            circuit.consecutive_failures = 0;
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use dg_xch_clients::protocols::farmer::SignedValues;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+//As long as request_signatures_with_retry keeps asking the harvester, a request still pending
+//after that failed and is signed anew
+const PENDING_TIMEOUT: Duration = Duration::from_secs(30);
+
+//A block candidate, the node asks again with the same foliage when it did not get the signatures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SignedValuesKey {
+    pub quality_string: Bytes32,
+    pub foliage_block_data_hash: Bytes32,
+    pub foliage_transaction_block_hash: Bytes32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignedValuesRequest {
+    //Not seen before or the earlier attempt failed, ask the harvester to sign
+    New,
+    //The harvester is still signing, its SignedValues go out once it answers
+    Pending,
+    //Already signed, the same values are sent again
+    Signed(Box<SignedValues>),
+}
+
+//Pending until the values are set
+#[derive(Debug, Clone)]
+struct State {
+    since: Instant,
+    values: Option<SignedValues>,
+}
+
+//Foliage signatures per block candidate, so a RequestSignedValues resent by the node is answered
+//with the values already computed instead of a second signing round
+#[derive(Debug, Default)]
+pub struct SignedValuesCache {
+    requests: HashMap<SignedValuesKey, State>,
+}
+impl SignedValuesCache {
+    pub fn request(&mut self, key: SignedValuesKey) -> SignedValuesRequest {
+        match self.requests.get(&key) {
+            Some(State {
+                values: Some(values),
+                ..
+            }) => SignedValuesRequest::Signed(Box::new(values.clone())),
+            Some(State { since, .. }) if since.elapsed() < PENDING_TIMEOUT => {
+                SignedValuesRequest::Pending
+            }
+            _ => {
+                let pending = State {
+                    since: Instant::now(),
+                    values: None,
+                };
+                self.requests.insert(key, pending);
+                SignedValuesRequest::New
+            }
+        }
+    }
+    //False when the values were already sent, a duplicate harvester response is then dropped
+    pub fn complete(&mut self, key: SignedValuesKey, values: &SignedValues) -> bool {
+        if self.requests.get(&key).is_some_and(|s| s.values.is_some()) {
+            return false;
+        }
+        let signed = State {
+            since: Instant::now(),
+            values: Some(values.clone()),
+        };
+        self.requests.insert(key, signed);
+        true
+    }
+    pub fn prune(&mut self, max_age: Duration) {
+        self.requests
+            .retain(|_, state| state.since.elapsed() < max_age);
+    }
+    pub fn clear(&mut self) {
+        self.requests.clear();
+        self.requests.shrink_to_fit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_reuse_the_signed_values() {
+        let mut cache = SignedValuesCache::default();
+        let key = SignedValuesKey {
+            quality_string: Bytes32::from_sized_bytes([1u8; 32]),
+            foliage_block_data_hash: Bytes32::from_sized_bytes([2u8; 32]),
+            foliage_transaction_block_hash: Bytes32::from_sized_bytes([3u8; 32]),
+        };
+        assert_eq!(cache.request(key), SignedValuesRequest::New);
+        assert_eq!(cache.request(key), SignedValuesRequest::Pending);
+        let values = SignedValues {
+            quality_string: key.quality_string,
+            foliage_block_data_signature: Default::default(),
+            foliage_transaction_block_signature: Default::default(),
+        };
+        assert!(cache.complete(key, &values));
+        assert!(!cache.complete(key, &values));
+        assert_eq!(
+            cache.request(key),
+            SignedValuesRequest::Signed(Box::new(values))
+        );
+        let other = SignedValuesKey {
+            foliage_transaction_block_hash: Bytes32::from_sized_bytes([4u8; 32]),
+            ..key
+        };
+        assert_eq!(cache.request(other), SignedValuesRequest::New);
+        cache.prune(Duration::ZERO);
+        assert_eq!(cache.request(key), SignedValuesRequest::New);
+    }
+}
@@ -0,0 +1,373 @@
+use crate::error::{FarmerError, FarmerResult};
+use blst::min_pk::{AggregateSignature, PublicKey, SecretKey, Signature};
+use blst::BLST_ERROR;
+use dg_xch_core::blockchain::proof_of_space::{generate_plot_public_key, generate_taproot_sk};
+use dg_xch_core::clvm::bls_bindings::{sign_prepend, AUG_SCHEME_DST};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+
+//Farmer side work on a RespondSignatures, on weak CPUs it adds up during bursts of eligible plots
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SigningStep {
+    Proof,
+    //Plot public key and taproot key derivation
+    Taproot,
+    Aggregate,
+    VerifyCc,
+    VerifyRc,
+    VerifyFoliage,
+    VerifyFoliageBlock,
+    VerifyPartial,
+}
+impl Display for SigningStep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SigningStep::Proof => "proof",
+            SigningStep::Taproot => "taproot",
+            SigningStep::Aggregate => "aggregate",
+            SigningStep::VerifyCc => "verify_cc",
+            SigningStep::VerifyRc => "verify_rc",
+            SigningStep::VerifyFoliage => "verify_foliage",
+            SigningStep::VerifyFoliageBlock => "verify_foliage_block",
+            SigningStep::VerifyPartial => "verify_partial",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SigningTimings {
+    pub steps: Vec<(SigningStep, Duration)>,
+}
+impl SigningTimings {
+    pub fn time<T>(&mut self, step: SigningStep, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.steps.push((step, start.elapsed()));
+        result
+    }
+    pub fn total(&self) -> Duration {
+        self.steps.iter().map(|(_, d)| *d).sum()
+    }
+}
+
+//Farmer side of a plot signature, the harvester signs with the local key and the farmer adds
+//its own share plus the taproot share for plots that are pooled through a contract
+pub struct PlotSigner<'a> {
+    farmer_sk: &'a SecretKey,
+    plot_public_key: PublicKey,
+    taproot_sk: Option<SecretKey>,
+}
+impl<'a> PlotSigner<'a> {
+    pub fn new(
+        farmer_sk: &'a SecretKey,
+        local_pk: &PublicKey,
+        include_taproot: bool,
+    ) -> FarmerResult<Self> {
+        let farmer_pk = farmer_sk.sk_to_pk();
+        let plot_public_key = generate_plot_public_key(local_pk, &farmer_pk, include_taproot)?;
+        let taproot_sk = if include_taproot {
+            Some(generate_taproot_sk(local_pk, &farmer_pk)?)
+        } else {
+            None
+        };
+        Ok(Self {
+            farmer_sk,
+            plot_public_key,
+            taproot_sk,
+        })
+    }
+    pub fn plot_public_key(&self) -> &PublicKey {
+        &self.plot_public_key
+    }
+    //Adds the farmer and taproot shares to the harvester signature and checks the result against the plot key
+    pub fn aggregate(
+        &self,
+        harvester_sig: &Signature,
+        message: &[u8],
+        timings: &mut SigningTimings,
+        verify_step: SigningStep,
+    ) -> FarmerResult<Signature> {
+        let signature = timings.time(SigningStep::Aggregate, || {
+            let farmer_sig = sign_prepend(self.farmer_sk, message, &self.plot_public_key);
+            let taproot_sig = self
+                .taproot_sk
+                .as_ref()
+                .map(|sk| sign_prepend(sk, message, &self.plot_public_key));
+            let mut sigs = vec![harvester_sig, &farmer_sig];
+            if let Some(taproot_sig) = &taproot_sig {
+                sigs.push(taproot_sig);
+            }
+            AggregateSignature::aggregate(&sigs, true)
+                .map_err(|e| FarmerError::Signing(format!("{:?}", e)))
+                .map(|s| s.to_signature())
+        })?;
+        if timings.time(verify_step, || {
+            verify_plot_signature(&signature, message, &self.plot_public_key)
+        }) {
+            Ok(signature)
+        } else {
+            Err(FarmerError::Signing(format!(
+                "{:?} does not verify against the plot key",
+                signature
+            )))
+        }
+    }
+}
+
+pub fn verify_plot_signature(signature: &Signature, message: &[u8], plot_pk: &PublicKey) -> bool {
+    signature.verify(
+        true,
+        message,
+        AUG_SCHEME_DST,
+        &plot_pk.to_bytes(),
+        plot_pk,
+        true,
+    ) == BLST_ERROR::BLST_SUCCESS
+}
+
+fn with_context(e: FarmerError, signature: &str) -> FarmerError {
+    match e {
+        FarmerError::Signing(m) => FarmerError::Signing(format!("{signature} signature: {m}")),
+        e => e,
+    }
+}
+
+//Challenge chain and reward chain signage point signatures for DeclareProofOfSpace
+pub fn aggregate_sp_signature(
+    signer: &PlotSigner,
+    challenge_chain_sp: (&[u8], &Signature),
+    reward_chain_sp: (&[u8], &Signature),
+    timings: &mut SigningTimings,
+) -> FarmerResult<(Signature, Signature)> {
+    let cc_sig = signer
+        .aggregate(
+            challenge_chain_sp.1,
+            challenge_chain_sp.0,
+            timings,
+            SigningStep::VerifyCc,
+        )
+        .map_err(|e| with_context(e, "cc"))?;
+    let rc_sig = signer
+        .aggregate(
+            reward_chain_sp.1,
+            reward_chain_sp.0,
+            timings,
+            SigningStep::VerifyRc,
+        )
+        .map_err(|e| with_context(e, "rc"))?;
+    Ok((cc_sig, rc_sig))
+}
+
+//Foliage and foliage transaction block signatures for SignedValues
+pub fn aggregate_block_signature(
+    signer: &PlotSigner,
+    foliage_block_data: (&[u8], &Signature),
+    foliage_transaction_block: (&[u8], &Signature),
+    timings: &mut SigningTimings,
+) -> FarmerResult<(Signature, Signature)> {
+    let foliage_sig = signer
+        .aggregate(
+            foliage_block_data.1,
+            foliage_block_data.0,
+            timings,
+            SigningStep::VerifyFoliage,
+        )
+        .map_err(|e| with_context(e, "foliage"))?;
+    let foliage_transaction_block_sig = signer
+        .aggregate(
+            foliage_transaction_block.1,
+            foliage_transaction_block.0,
+            timings,
+            SigningStep::VerifyFoliageBlock,
+        )
+        .map_err(|e| with_context(e, "foliage_block"))?;
+    Ok((foliage_sig, foliage_transaction_block_sig))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dg_xch_keys::master_sk_to_local_sk;
+
+    fn key(seed: u8) -> SecretKey {
+        SecretKey::key_gen(&[seed; 32], &[]).unwrap()
+    }
+
+    //Harvester half of the exchange, as done by the harvester for RequestSignatures
+    fn harvester_sign(
+        local_master: &SecretKey,
+        farmer_pk: &PublicKey,
+        taproot: bool,
+        msg: &[u8],
+    ) -> (PublicKey, Signature) {
+        let local_sk = master_sk_to_local_sk(local_master).unwrap();
+        let agg_pk = generate_plot_public_key(&local_sk.sk_to_pk(), farmer_pk, taproot).unwrap();
+        (local_sk.sk_to_pk(), sign_prepend(&local_sk, msg, &agg_pk))
+    }
+
+    #[test]
+    fn og_plot_signature_verifies() {
+        let farmer_sk = key(1);
+        let msg = [7u8; 32];
+        let (local_pk, harvester_sig) = harvester_sign(&key(2), &farmer_sk.sk_to_pk(), false, &msg);
+        let signer = PlotSigner::new(&farmer_sk, &local_pk, false).unwrap();
+        let sig = signer
+            .aggregate(
+                &harvester_sig,
+                &msg,
+                &mut SigningTimings::default(),
+                SigningStep::VerifyPartial,
+            )
+            .unwrap();
+        assert!(verify_plot_signature(&sig, &msg, signer.plot_public_key()));
+    }
+
+    #[test]
+    fn pool_plot_signature_needs_taproot() {
+        let farmer_sk = key(3);
+        let msg = [9u8; 32];
+        let (local_pk, harvester_sig) = harvester_sign(&key(4), &farmer_sk.sk_to_pk(), true, &msg);
+        let signer = PlotSigner::new(&farmer_sk, &local_pk, true).unwrap();
+        let mut timings = SigningTimings::default();
+        assert!(signer
+            .aggregate(
+                &harvester_sig,
+                &msg,
+                &mut timings,
+                SigningStep::VerifyPartial
+            )
+            .is_ok());
+        let steps: Vec<SigningStep> = timings.steps.iter().map(|(s, _)| *s).collect();
+        assert_eq!(steps, [SigningStep::Aggregate, SigningStep::VerifyPartial]);
+        let farmer_sig = sign_prepend(&farmer_sk, &msg, signer.plot_public_key());
+        let without_taproot = AggregateSignature::aggregate(&[&harvester_sig, &farmer_sig], true)
+            .unwrap()
+            .to_signature();
+        assert!(!verify_plot_signature(
+            &without_taproot,
+            &msg,
+            signer.plot_public_key()
+        ));
+    }
+
+    #[test]
+    fn sp_and_block_signatures() {
+        let farmer_sk = key(5);
+        let (cc, rc) = ([1u8; 32], [2u8; 32]);
+        let (local_pk, cc_harv) = harvester_sign(&key(6), &farmer_sk.sk_to_pk(), true, &cc);
+        let (_, rc_harv) = harvester_sign(&key(6), &farmer_sk.sk_to_pk(), true, &rc);
+        let signer = PlotSigner::new(&farmer_sk, &local_pk, true).unwrap();
+        let mut timings = SigningTimings::default();
+        let (cc_sig, rc_sig) =
+            aggregate_sp_signature(&signer, (&cc, &cc_harv), (&rc, &rc_harv), &mut timings)
+                .unwrap();
+        assert!(verify_plot_signature(
+            &cc_sig,
+            &cc,
+            signer.plot_public_key()
+        ));
+        assert!(verify_plot_signature(
+            &rc_sig,
+            &rc,
+            signer.plot_public_key()
+        ));
+        let (foliage_sig, block_sig) =
+            aggregate_block_signature(&signer, (&cc, &cc_harv), (&rc, &rc_harv), &mut timings)
+                .unwrap();
+        assert_eq!(timings.steps.len(), 8);
+        assert_eq!(foliage_sig, cc_sig);
+        assert_eq!(block_sig, rc_sig);
+    }
+
+    #[test]
+    fn wrong_message_is_rejected() {
+        let farmer_sk = key(7);
+        let (local_pk, harvester_sig) =
+            harvester_sign(&key(8), &farmer_sk.sk_to_pk(), false, &[1u8; 32]);
+        let signer = PlotSigner::new(&farmer_sk, &local_pk, false).unwrap();
+        let err = signer
+            .aggregate(
+                &harvester_sig,
+                &[2u8; 32],
+                &mut SigningTimings::default(),
+                SigningStep::VerifyPartial,
+            )
+            .unwrap_err();
+        assert!(matches!(err, FarmerError::Signing(_)));
+    }
+
+    //Chia test vectors 3 (Augmented) of chia's bls-signatures, KeyGen is the one chia keys come from
+    #[test]
+    fn matches_chia_augmented_scheme_vectors() {
+        let sk1 = SecretKey::key_gen_v3(&[2u8; 32], &[]).unwrap();
+        let sk2 = SecretKey::key_gen_v3(&[3u8; 32], &[]).unwrap();
+        let (pk1, pk2) = (sk1.sk_to_pk(), sk2.sk_to_pk());
+        let messages: [&[u8]; 4] = [
+            &[1, 2, 3, 40],
+            &[5, 6, 70, 201],
+            &[9, 10, 11, 12, 13],
+            &[15, 63, 244, 92, 0, 1],
+        ];
+        let signed = [
+            (&sk1, &pk1, messages[0]),
+            (&sk2, &pk2, messages[1]),
+            (&sk2, &pk2, messages[0]),
+            (&sk1, &pk1, messages[2]),
+            (&sk1, &pk1, messages[0]),
+            (&sk1, &pk1, messages[3]),
+        ];
+        let sigs: Vec<Signature> = signed
+            .iter()
+            .map(|(sk, pk, msg)| sign_prepend(sk, msg, pk))
+            .collect();
+        let aggregate = AggregateSignature::aggregate(&sigs.iter().collect::<Vec<_>>(), true)
+            .unwrap()
+            .to_signature();
+        assert_eq!(
+            hex::encode(aggregate.to_bytes()),
+            "a1d5360dcb418d33b29b90b912b4accde535cf0e52caf467a005dc632d9f7af44b6c4e9acd46eac2\
+             18b28cdb07a3e3bc087df1cd1e3213aa4e11322a3ff3847bbba0b2fd19ddc25ca964871997b9bcee\
+             ab37a4c2565876da19382ea32a962200"
+        );
+        let augmented: Vec<Vec<u8>> = signed
+            .iter()
+            .map(|(_, pk, msg)| [pk.to_bytes().as_slice(), msg].concat())
+            .collect();
+        let verify = |msgs: &[Vec<u8>]| {
+            aggregate.aggregate_verify(
+                true,
+                &msgs.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+                AUG_SCHEME_DST,
+                &signed.iter().map(|(_, pk, _)| *pk).collect::<Vec<_>>(),
+                true,
+            )
+        };
+        assert_eq!(verify(&augmented), BLST_ERROR::BLST_SUCCESS);
+        let mut swapped = augmented.clone();
+        swapped.swap(0, 1);
+        assert_ne!(verify(&swapped), BLST_ERROR::BLST_SUCCESS);
+    }
+
+    //Chia test vectors 1 (Basic), keys and fingerprints match the ones chia derives from a seed
+    #[test]
+    fn matches_chia_key_vectors() {
+        let sk1 = SecretKey::key_gen_v3(&[0u8; 32], &[]).unwrap();
+        let sk2 = SecretKey::key_gen_v3(&[1u8; 32], &[]).unwrap();
+        assert_eq!(dg_xch_keys::fingerprint(&sk1.sk_to_pk()), 0xb40dd58a);
+        assert_eq!(dg_xch_keys::fingerprint(&sk2.sk_to_pk()), 0xb839add1);
+        let sig1 = sk1.sign(
+            &[7, 8, 9],
+            b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_",
+            &[],
+        );
+        assert_eq!(
+            hex::encode(sig1.to_bytes()),
+            "b8faa6d6a3881c9fdbad803b170d70ca5cbf1e6ba5a586262df368c75acd1d1ffa3ab6ee21c71f84\
+             4494659878f5eb230c958dd576b08b8564aad2ee0992e85a1e565f299cd53a285de729937f70dc17\
+             6a1f01432129bb2b94d3d5031f8065a1"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use crate::farmer::config::{EmailReportConfig, SmtpTls};
+use crate::farmer::host_vars::host_name;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use log::warn;
+use rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+use std::io::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_rustls::TlsConnector;
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(60);
+
+//Delivers one message through the configured server, the message already holds its headers and
+//uses CRLF line endings
+pub async fn send_mail(config: &EmailReportConfig, message: &str) -> Result<(), Error> {
+    timeout(SMTP_TIMEOUT, deliver(config, message))
+        .await
+        .map_err(|_| Error::other(format!("Timeout talking to {}", config.smtp_host)))?
+}
+
+async fn deliver(config: &EmailReportConfig, message: &str) -> Result<(), Error> {
+    let stream = TcpStream::connect((config.smtp_host.as_str(), config.smtp_port)).await?;
+    match config.tls {
+        SmtpTls::Tls => {
+            let mut stream = BufReader::new(connect_tls(&config.smtp_host, stream).await?);
+            expect_reply(&mut stream, 2).await?;
+            command(&mut stream, &ehlo(), 2).await?;
+            transaction(&mut stream, config, message).await
+        }
+        SmtpTls::Starttls => {
+            let mut stream = BufReader::new(stream);
+            expect_reply(&mut stream, 2).await?;
+            command(&mut stream, &ehlo(), 2).await?;
+            command(&mut stream, "STARTTLS", 2).await?;
+            //The server waits for the handshake, so nothing is left in the read buffer
+            let stream = connect_tls(&config.smtp_host, stream.into_inner()).await?;
+            let mut stream = BufReader::new(stream);
+            command(&mut stream, &ehlo(), 2).await?;
+            transaction(&mut stream, config, message).await
+        }
+        SmtpTls::None => {
+            let mut stream = BufReader::new(stream);
+            expect_reply(&mut stream, 2).await?;
+            command(&mut stream, &ehlo(), 2).await?;
+            transaction(&mut stream, config, message).await
+        }
+    }
+}
+
+async fn transaction<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut BufReader<S>,
+    config: &EmailReportConfig,
+    message: &str,
+) -> Result<(), Error> {
+    if let Some(username) = &config.username {
+        if config.tls == SmtpTls::None {
+            warn!(
+                "Sending SMTP credentials to {} unencrypted",
+                config.smtp_host
+            );
+        }
+        let password = config.password.as_deref().unwrap_or_default();
+        let credentials = STANDARD.encode(format!("\0{username}\0{password}"));
+        command(stream, &format!("AUTH PLAIN {credentials}"), 2).await?;
+    }
+    command(stream, &format!("MAIL FROM:<{}>", address(&config.from)), 2).await?;
+    for to in &config.to {
+        command(stream, &format!("RCPT TO:<{}>", address(to)), 2).await?;
+    }
+    command(stream, "DATA", 3).await?;
+    let mut data = String::with_capacity(message.len() + 16);
+    for line in message.split("\r\n") {
+        //A line starting with a dot would otherwise end or corrupt the message
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+        data.push_str("\r\n");
+    }
+    data.push_str(".\r\n");
+    stream.get_mut().write_all(data.as_bytes()).await?;
+    expect_reply(stream, 2).await?;
+    //The message is accepted at this point, a failed QUIT does not matter
+    let _ = command(stream, "QUIT", 2).await;
+    Ok(())
+}
+
+//The bare address of "Name <user@example.com>"
+pub fn address(mailbox: &str) -> &str {
+    match (mailbox.rfind('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &mailbox[start + 1..end],
+        _ => mailbox.trim(),
+    }
+}
+
+fn ehlo() -> String {
+    let host = host_name()
+        .filter(|h| {
+            !h.is_empty()
+                && h.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-.".contains(c))
+        })
+        .unwrap_or_else(|| "localhost".to_string());
+    format!("EHLO {host}")
+}
+
+async fn command<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut BufReader<S>,
+    line: &str,
+    class: u8,
+) -> Result<(), Error> {
+    let writer = stream.get_mut();
+    writer.write_all(format!("{line}\r\n").as_bytes()).await?;
+    writer.flush().await?;
+    //Only the verb goes into the error, AUTH carries the credentials
+    let verb = line.split(' ').next().unwrap_or_default();
+    expect_reply(stream, class)
+        .await
+        .map_err(|e| Error::other(format!("{verb}: {e}")))
+}
+
+//Reads a possibly multi line reply and checks the first digit of its code
+async fn expect_reply<S: AsyncRead + Unpin>(
+    stream: &mut BufReader<S>,
+    class: u8,
+) -> Result<(), Error> {
+    let mut reply = String::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            return Err(Error::other("Connection closed by the SMTP server"));
+        }
+        reply.push_str(line.trim_end());
+        if line.as_bytes().get(3) != Some(&b'-') {
+            break;
+        }
+        reply.push(' ');
+    }
+    match reply.as_bytes().first() {
+        Some(digit) if *digit == b'0' + class => Ok(()),
+        _ => Err(Error::other(format!("SMTP server replied {reply}"))),
+    }
+}
+
+async fn connect_tls<S: AsyncRead + AsyncWrite + Unpin>(
+    host: &str,
+    stream: S,
+) -> Result<tokio_rustls::client::TlsStream<S>, Error> {
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(
+            ta.subject,
+            ta.spki,
+            ta.name_constraints,
+        )
+    }));
+    let tls_config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let server_name = ServerName::try_from(host)
+        .map_err(|e| Error::other(format!("Invalid SMTP host {host}: {e}")))?;
+    TlsConnector::from(Arc::new(tls_config))
+        .connect(server_name, stream)
+        .await
+}
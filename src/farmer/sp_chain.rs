@@ -0,0 +1,166 @@
+use dg_xch_clients::protocols::farmer::NewSignagePoint;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+//Sub-slots remembered for the consistency checks, a few more than signage points are cached for
+const MAX_CHALLENGES: usize = 8;
+
+#[derive(Debug, Clone)]
+struct SubSlot {
+    difficulty: u64,
+    sub_slot_iters: u64,
+    sps: HashMap<u8, Bytes32>,
+}
+
+//Difficulty and sub slot iters of a sub-slot, the node sends signage point 0 at the end of the previous one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubSlotParams {
+    pub challenge_hash: Bytes32,
+    pub difficulty: u64,
+    pub sub_slot_iters: u64,
+}
+
+//Checks signage points against the sub-slots they claim to belong to.
+//The farmer protocol does not carry the end of sub-slot data, so the links that can be checked are
+//the sub-slot start (its challenge chain sp is the challenge itself) and that every signage point of a
+//challenge agrees with the ones seen before
+#[derive(Debug, Default)]
+pub struct SpChain {
+    order: VecDeque<Bytes32>,
+    sub_slots: HashMap<Bytes32, SubSlot>,
+}
+impl SpChain {
+    pub fn check(&mut self, sp: &NewSignagePoint, num_sps_sub_slot: u32) -> Result<(), String> {
+        if sp.signage_point_index as u32 >= num_sps_sub_slot {
+            return Err(format!(
+                "signage point index {} is outside the sub-slot ({num_sps_sub_slot} signage points)",
+                sp.signage_point_index
+            ));
+        }
+        if sp.signage_point_index == 0 && sp.challenge_chain_sp != sp.challenge_hash {
+            return Err(format!(
+                "signage point 0 of challenge {} does not start its sub-slot",
+                sp.challenge_hash
+            ));
+        }
+        if sp.signage_point_index != 0 && sp.challenge_chain_sp == sp.challenge_hash {
+            return Err(format!(
+                "signage point {} of challenge {} repeats the challenge as its hash",
+                sp.signage_point_index, sp.challenge_hash
+            ));
+        }
+        if let Some(sub_slot) = self.sub_slots.get(&sp.challenge_hash) {
+            if sub_slot.difficulty != sp.difficulty || sub_slot.sub_slot_iters != sp.sub_slot_iters
+            {
+                return Err(format!(
+                    "difficulty {} / sub slot iters {} differ from {} / {} seen for challenge {}",
+                    sp.difficulty,
+                    sp.sub_slot_iters,
+                    sub_slot.difficulty,
+                    sub_slot.sub_slot_iters,
+                    sp.challenge_hash
+                ));
+            }
+            if let Some(known) = sub_slot.sps.get(&sp.signage_point_index) {
+                if *known != sp.challenge_chain_sp {
+                    return Err(format!(
+                        "signage point {} of challenge {} has hash {}, {} was seen before",
+                        sp.signage_point_index, sp.challenge_hash, sp.challenge_chain_sp, known
+                    ));
+                }
+            }
+        }
+        self.record(sp);
+        Ok(())
+    }
+    pub fn sub_slot(&self, challenge_hash: &Bytes32) -> Option<SubSlotParams> {
+        self.sub_slots
+            .get(challenge_hash)
+            .map(|sub_slot| SubSlotParams {
+                challenge_hash: *challenge_hash,
+                difficulty: sub_slot.difficulty,
+                sub_slot_iters: sub_slot.sub_slot_iters,
+            })
+    }
+    //The most recently started sub-slot
+    pub fn current(&self) -> Option<SubSlotParams> {
+        self.order.back().and_then(|c| self.sub_slot(c))
+    }
+    fn record(&mut self, sp: &NewSignagePoint) {
+        if !self.sub_slots.contains_key(&sp.challenge_hash) {
+            self.order.push_back(sp.challenge_hash);
+            while self.order.len() > MAX_CHALLENGES {
+                if let Some(oldest) = self.order.pop_front() {
+                    self.sub_slots.remove(&oldest);
+                }
+            }
+        }
+        self.sub_slots
+            .entry(sp.challenge_hash)
+            .or_insert_with(|| SubSlot {
+                difficulty: sp.difficulty,
+                sub_slot_iters: sp.sub_slot_iters,
+                sps: HashMap::new(),
+            })
+            .sps
+            .insert(sp.signage_point_index, sp.challenge_chain_sp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sp(challenge: u8, index: u8, hash: u8) -> NewSignagePoint {
+        NewSignagePoint {
+            challenge_hash: Bytes32::from_sized_bytes([challenge; 32]),
+            challenge_chain_sp: Bytes32::from_sized_bytes([hash; 32]),
+            reward_chain_sp: Bytes32::default(),
+            difficulty: 1000,
+            sub_slot_iters: 1 << 27,
+            signage_point_index: index,
+        }
+    }
+
+    #[test]
+    fn skips_signage_points_that_do_not_chain() {
+        let mut chain = SpChain::default();
+        assert!(chain.check(&sp(1, 0, 1), 64).is_ok());
+        assert!(chain.check(&sp(1, 1, 2), 64).is_ok());
+        assert!(chain.check(&sp(1, 1, 2), 64).is_ok());
+        assert!(chain.check(&sp(1, 1, 3), 64).is_err());
+        assert!(chain.check(&sp(2, 0, 3), 64).is_err());
+        assert!(chain.check(&sp(2, 5, 2), 64).is_err());
+        assert!(chain.check(&sp(1, 64, 4), 64).is_err());
+        assert!(chain.check(&sp(1, 2, 4), 64).is_ok());
+        let mut other_difficulty = sp(1, 3, 5);
+        other_difficulty.difficulty += 1;
+        assert!(chain.check(&other_difficulty, 64).is_err());
+        for challenge in 3..3 + MAX_CHALLENGES as u8 {
+            assert!(chain.check(&sp(challenge, 0, challenge), 64).is_ok());
+        }
+        assert!(chain.check(&sp(1, 1, 3), 64).is_ok());
+    }
+
+    #[test]
+    fn tracks_the_latest_sub_slot() {
+        let mut chain = SpChain::default();
+        assert_eq!(chain.current(), None);
+        let mut next = sp(2, 0, 2);
+        next.difficulty = 2000;
+        assert!(chain.check(&sp(1, 0, 1), 64).is_ok());
+        assert!(chain.check(&next, 64).is_ok());
+        assert!(chain.check(&sp(1, 1, 3), 64).is_ok());
+        let current = chain.current().unwrap();
+        assert_eq!(current.challenge_hash, next.challenge_hash);
+        assert_eq!(current.difficulty, 2000);
+        assert_eq!(
+            chain
+                .sub_slot(&Bytes32::from_sized_bytes([1; 32]))
+                .unwrap()
+                .difficulty,
+            1000
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use crate::farmer::events::FarmerEventKind;
+use crate::farmer::sp_tag::SpTag;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+//Version of the digest line, fields are only ever appended so parsers keep working
+pub const SP_DIGEST_VERSION: u8 = 1;
+
+//Everything that happened for one signage point, logged as a single line once it settled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpDigest {
+    pub challenge_hash: Bytes32,
+    pub signage_point_index: u8,
+    //Eligible plots of each harvester that finished its lookups
+    pub eligible: BTreeMap<Uuid, u64>,
+    pub proofs: u64,
+    pub partials_submitted: u64,
+    pub partials_accepted: u64,
+    //Milliseconds from the signage point reaching the farmer to the last harvester finishing
+    pub time_ms: u64,
+}
+impl Display for SpDigest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let eligible = if self.eligible.is_empty() {
+            "-".to_string()
+        } else {
+            self.eligible
+                .iter()
+                .map(|(id, plots)| format!("{}:{plots}", id.simple()))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        write!(
+            f,
+            "SpDigest v={SP_DIGEST_VERSION} challenge={} sp={} eligible={eligible} eligible_total={} proofs={} partials_submitted={} partials_accepted={} time_ms={}",
+            hex::encode(self.challenge_hash.to_sized_bytes()),
+            self.signage_point_index,
+            self.eligible.values().sum::<u64>(),
+            self.proofs,
+            self.partials_submitted,
+            self.partials_accepted,
+            self.time_ms
+        )
+    }
+}
+
+struct Pending {
+    first_seen: Instant,
+    digest: SpDigest,
+}
+
+//Signage points still collecting lookups, proofs and pool responses
+#[derive(Default)]
+pub struct SpDigests {
+    pending: Mutex<HashMap<(Bytes32, u8), Pending>>,
+}
+impl SpDigests {
+    async fn update<F: FnOnce(&mut SpDigest)>(&self, challenge_hash: Bytes32, index: u8, f: F) {
+        let mut pending = self.pending.lock().await;
+        let entry = pending
+            .entry((challenge_hash, index))
+            .or_insert_with(|| Pending {
+                first_seen: Instant::now(),
+                digest: SpDigest {
+                    challenge_hash,
+                    signage_point_index: index,
+                    eligible: BTreeMap::new(),
+                    proofs: 0,
+                    partials_submitted: 0,
+                    partials_accepted: 0,
+                    time_ms: 0,
+                },
+            });
+        f(&mut entry.digest);
+    }
+    //Called by a harvester once all lookups of the signage point finished
+    pub async fn record_lookup(&self, tag: SpTag, harvester_id: Uuid, eligible_plots: u64) {
+        self.update(tag.challenge_hash, tag.signage_point_index, |d| {
+            *d.eligible.entry(harvester_id).or_default() += eligible_plots;
+            d.time_ms = d.time_ms.max(tag.offset_ms);
+        })
+        .await
+    }
+    pub async fn record_event(&self, kind: &FarmerEventKind) {
+        let (sp, f): (&Option<SpTag>, fn(&mut SpDigest)) = match kind {
+            FarmerEventKind::SignagePoint {
+                challenge_hash,
+                signage_point_index,
+                ..
+            } => {
+                self.update(*challenge_hash, *signage_point_index, |_| {})
+                    .await;
+                return;
+            }
+            FarmerEventKind::ProofDeclared { sp, .. } => (sp, |d| d.proofs += 1),
+            FarmerEventKind::PartialSubmitted { sp, .. } => (sp, |d| d.partials_submitted += 1),
+            FarmerEventKind::PartialAccepted { sp, .. } => (sp, |d| d.partials_accepted += 1),
+            _ => return,
+        };
+        if let Some(sp) = sp {
+            self.update(sp.challenge_hash, sp.signage_point_index, f)
+                .await;
+        }
+    }
+    //Digests first seen at least settle ago, oldest first. Pool responses arriving later are not counted
+    pub async fn take_settled(&self, settle: Duration) -> Vec<SpDigest> {
+        let mut pending = self.pending.lock().await;
+        let mut settled = pending
+            .extract_if(|_, p| p.first_seen.elapsed() >= settle)
+            .map(|(_, p)| (p.first_seen, p.digest))
+            .collect::<Vec<_>>();
+        settled.sort_by_key(|(first_seen, _)| *first_seen);
+        settled.into_iter().map(|(_, d)| d).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn one_line_per_signage_point() {
+        let digests = SpDigests::default();
+        let challenge_hash = Bytes32::from_sized_bytes([1u8; 32]);
+        let tag = SpTag {
+            challenge_hash,
+            signage_point_index: 7,
+            offset_ms: 420,
+        };
+        let harvester = Uuid::nil();
+        digests
+            .record_event(&FarmerEventKind::SignagePoint {
+                challenge_hash,
+                sp_hash: Bytes32::default(),
+                signage_point_index: 7,
+            })
+            .await;
+        digests.record_lookup(tag, harvester, 3).await;
+        for kind in [
+            FarmerEventKind::PartialSubmitted {
+                launcher_id: Bytes32::default(),
+                pool_url: String::new(),
+                sp: Some(tag),
+            },
+            FarmerEventKind::PartialAccepted {
+                launcher_id: Bytes32::default(),
+                pool_url: String::new(),
+                new_difficulty: 1,
+                sp: Some(tag),
+            },
+        ] {
+            digests.record_event(&kind).await;
+        }
+        assert!(digests
+            .take_settled(Duration::from_secs(60))
+            .await
+            .is_empty());
+        let settled = digests.take_settled(Duration::ZERO).await;
+        assert_eq!(settled.len(), 1);
+        assert_eq!(
+            settled[0].to_string(),
+            format!(
+                "SpDigest v=1 challenge={} sp=7 eligible={}:3 eligible_total=3 proofs=0 partials_submitted=1 partials_accepted=1 time_ms=420",
+                "01".repeat(32),
+                "0".repeat(32)
+            )
+        );
+        assert!(digests.take_settled(Duration::ZERO).await.is_empty());
+    }
+}
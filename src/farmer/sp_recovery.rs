@@ -0,0 +1,178 @@
+use crate::farmer::clock_drift::unix_millis;
+use crate::farmer::rpc::FullnodeRpc;
+use crate::farmer::FarmerSharedState;
+use dg_xch_clients::api::full_node::FullnodeAPI;
+use dg_xch_clients::protocols::farmer::NewSignagePoint;
+use dg_xch_core::blockchain::signage_point::SignagePoint;
+use dg_xch_core::blockchain::sized_bytes::{Bytes32, SizedBytes};
+use dg_xch_serialize::{hash_256, ChiaSerialize};
+use log::{info, warn};
+
+//Farmer view of a signage point from the full node RPC, None when it is not the signage point the
+//hash names. Without a known index it is derived from the VDF iterations, which only count from
+//the sub-slot start when no block was infused before the signage point, anything else is refused
+pub fn rebuild_signage_point(
+    sp: &SignagePoint,
+    challenge_hash: &Bytes32,
+    sp_hash: &Bytes32,
+    signage_point_index: Option<u8>,
+    difficulty: u64,
+    sub_slot_iters: u64,
+    num_sps_sub_slot: u32,
+) -> Option<NewSignagePoint> {
+    if Bytes32::new(&hash_256(sp.cc_vdf.output.to_bytes())) != *sp_hash
+        || sp.cc_vdf.challenge != *challenge_hash
+    {
+        return None;
+    }
+    let signage_point_index = match signage_point_index {
+        Some(index) => index,
+        None => {
+            let interval_iters = sub_slot_iters / num_sps_sub_slot.max(1) as u64;
+            let iters = sp.cc_vdf.number_of_iterations;
+            if interval_iters == 0 || !iters.is_multiple_of(interval_iters) {
+                return None;
+            }
+            u8::try_from(iters / interval_iters)
+                .ok()
+                .filter(|index| *index > 0 && (*index as u32) < num_sps_sub_slot)?
+        }
+    };
+    Some(NewSignagePoint {
+        challenge_hash: *challenge_hash,
+        challenge_chain_sp: *sp_hash,
+        reward_chain_sp: Bytes32::new(&hash_256(sp.rc_vdf.output.to_bytes())),
+        difficulty,
+        sub_slot_iters,
+        signage_point_index,
+    })
+}
+
+//Fetches a signage point the farmer does not have from the full node and stores it like one that
+//arrived over the websocket, true when it can be used
+pub async fn recover_signage_point(
+    shared_state: &FarmerSharedState,
+    challenge_hash: &Bytes32,
+    sp_hash: &Bytes32,
+    signage_point_index: Option<u8>,
+    label: &str,
+) -> bool {
+    let Some(recovery) = &shared_state.config.sp_recovery else {
+        return false;
+    };
+    let rpc = FullnodeRpc::new(
+        shared_state.config.as_ref(),
+        shared_state.rpc_health.clone(),
+    )
+    .await;
+    let recent = match rpc
+        .call("get_recent_signage_point_or_eos", |c| {
+            c.get_recent_signage_point_or_eos(Some(sp_hash), None)
+        })
+        .await
+    {
+        Ok(recent) => recent,
+        Err(e) => {
+            warn!("{label} Failed to fetch signage point {sp_hash} from the full node: {e}");
+            return false;
+        }
+    };
+    let age_ms = unix_millis().saturating_sub((recent.time_received * 1000.0) as u64);
+    if recent.reverted || age_ms > recovery.max_age * 1000 {
+        warn!(
+            "{label} Full node signage point {sp_hash} is {}, not signing for it",
+            if recent.reverted {
+                "reverted".to_string()
+            } else {
+                format!("{:.1}s old", age_ms as f64 / 1000.0)
+            }
+        );
+        return false;
+    }
+    //Values of the sub-slot when it was seen, the current peak otherwise
+    let (difficulty, sub_slot_iters) =
+        match shared_state.sp_chain.lock().await.sub_slot(challenge_hash) {
+            Some(sub_slot) => (sub_slot.difficulty, sub_slot.sub_slot_iters),
+            None => match rpc.get_blockchain_state().await {
+                Ok(state) => (state.difficulty, state.sub_slot_iters),
+                Err(e) => {
+                    warn!("{label} Failed to load the sub-slot of {sp_hash}: {e}");
+                    return false;
+                }
+            },
+        };
+    let Some(sp) = recent.signage_point.as_ref().and_then(|sp| {
+        rebuild_signage_point(
+            sp,
+            challenge_hash,
+            sp_hash,
+            signage_point_index,
+            difficulty,
+            sub_slot_iters,
+            shared_state.constants.num_sps_sub_slot,
+        )
+    }) else {
+        warn!("{label} Full node has no signage point {sp_hash} in sub-slot {challenge_hash}");
+        return false;
+    };
+    info!(
+        "{label} Recovered signage point {} of {challenge_hash} from the full node",
+        sp.signage_point_index
+    );
+    shared_state
+        .record_recovered_signage_point(*sp_hash, sp)
+        .await;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dg_xch_core::blockchain::class_group_element::ClassgroupElement;
+    use dg_xch_core::blockchain::sized_bytes::Bytes100;
+    use dg_xch_core::blockchain::vdf_info::VdfInfo;
+    use dg_xch_core::blockchain::vdf_proof::VdfProof;
+
+    fn vdf(challenge: Bytes32, iters: u64, output: u8) -> VdfInfo {
+        VdfInfo {
+            challenge,
+            number_of_iterations: iters,
+            output: ClassgroupElement {
+                data: Bytes100::from([output; 100]),
+            },
+        }
+    }
+
+    #[test]
+    fn rebuilds_only_the_named_signage_point() {
+        let challenge_hash = Bytes32::from_sized_bytes([3u8; 32]);
+        let proof = VdfProof {
+            witness_type: 0,
+            witness: Default::default(),
+            normalized_to_identity: false,
+        };
+        let sp = SignagePoint {
+            cc_vdf: vdf(challenge_hash, 5 * 4096, 1),
+            cc_proof: proof.clone(),
+            rc_vdf: vdf(Bytes32::default(), 5 * 4096, 2),
+            rc_proof: proof,
+        };
+        let sp_hash = Bytes32::new(&hash_256([1u8; 100]));
+        let rebuilt = rebuild_signage_point(&sp, &challenge_hash, &sp_hash, None, 7, 64 * 4096, 64)
+            .expect("signage point 5");
+        assert_eq!(rebuilt.signage_point_index, 5);
+        assert_eq!(rebuilt.reward_chain_sp, Bytes32::new(&hash_256([2u8; 100])));
+        assert_eq!(rebuilt.difficulty, 7);
+        let other = Bytes32::from_sized_bytes([4u8; 32]);
+        assert!(rebuild_signage_point(&sp, &other, &sp_hash, None, 7, 64 * 4096, 64).is_none());
+        assert!(
+            rebuild_signage_point(&sp, &challenge_hash, &other, None, 7, 64 * 4096, 64).is_none()
+        );
+        //After a block infusion the iterations no longer give the index, the proof still does
+        let shifted = rebuild_signage_point(&sp, &challenge_hash, &sp_hash, None, 7, 64 * 4000, 64);
+        assert!(shifted.is_none());
+        let known =
+            rebuild_signage_point(&sp, &challenge_hash, &sp_hash, Some(9), 7, 64 * 4000, 64);
+        assert_eq!(known.map(|sp| sp.signage_point_index), Some(9));
+    }
+}
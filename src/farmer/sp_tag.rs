@@ -0,0 +1,46 @@
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::time::Instant;
+
+//The signage point a proof, partial or signature belongs to, the challenge hash identifies the sub-slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpTag {
+    pub challenge_hash: Bytes32,
+    pub signage_point_index: u8,
+    //Milliseconds since the signage point reached the farmer
+    pub offset_ms: u64,
+}
+impl Display for SpTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[sp {}/{} +{:.2}s]",
+            hex::encode(&self.challenge_hash.to_sized_bytes()[..4]),
+            self.signage_point_index,
+            self.offset_ms as f64 / 1000.0
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SpArrival {
+    pub challenge_hash: Bytes32,
+    pub signage_point_index: u8,
+    pub arrived: Instant,
+}
+impl SpArrival {
+    pub fn tag(&self) -> SpTag {
+        SpTag {
+            challenge_hash: self.challenge_hash,
+            signage_point_index: self.signage_point_index,
+            offset_ms: self.arrived.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+//Log prefix for lines whose signage point already expired from the cache
+pub fn sp_label(tag: &Option<SpTag>) -> String {
+    tag.map(|t| t.to_string())
+        .unwrap_or_else(|| "[sp unknown]".to_string())
+}
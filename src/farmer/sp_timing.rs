@@ -0,0 +1,60 @@
+use dg_xch_core::consensus::constants::ConsensusConstants;
+use std::time::Duration;
+
+const DEFAULT_SUB_SLOT_SECS: u64 = 600;
+
+//Signage point timing of the selected network, forks run other sub-slot lengths and signage point
+//counts, so everything kept per signage point is sized from here instead of mainnet numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpTiming {
+    pub sub_slot: Duration,
+    pub sp_interval: Duration,
+}
+impl SpTiming {
+    pub fn new(constants: &ConsensusConstants) -> Self {
+        let sub_slot = Duration::from_secs(
+            u64::try_from(&constants.sub_slot_time_target)
+                .ok()
+                .filter(|secs| *secs > 0)
+                .unwrap_or(DEFAULT_SUB_SLOT_SECS),
+        );
+        Self {
+            sub_slot,
+            sp_interval: sub_slot / constants.num_sps_sub_slot.max(1),
+        }
+    }
+    //Signage points, proofs and qualities are kept for this many sub-slots
+    pub fn retention(&self, sub_slots: u8) -> Duration {
+        self.sub_slot * sub_slots.max(1) as u32
+    }
+    //Expired entries are dropped twice per sub-slot
+    pub fn prune_interval(&self) -> Duration {
+        self.sub_slot / 2
+    }
+    //Without a signage point for this long the node connection is restarted, 180s on mainnet
+    pub fn sp_timeout(&self) -> Duration {
+        self.sub_slot * 3 / 10
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dg_xch_core::consensus::constants::MAINNET;
+
+    #[test]
+    fn follows_consensus_constants() {
+        let mainnet = SpTiming::new(&MAINNET);
+        assert_eq!(mainnet.sub_slot, Duration::from_secs(600));
+        assert_eq!(mainnet.sp_interval, Duration::from_millis(9375));
+        assert_eq!(mainnet.retention(3), Duration::from_secs(1800));
+        assert_eq!(mainnet.prune_interval(), Duration::from_secs(300));
+        assert_eq!(mainnet.sp_timeout(), Duration::from_secs(180));
+        let mut fork = MAINNET.clone();
+        fork.sub_slot_time_target = 300.into();
+        fork.num_sps_sub_slot = 32;
+        let fork = SpTiming::new(&fork);
+        assert_eq!(fork.retention(3), Duration::from_secs(900));
+        assert_eq!(fork.sp_interval, Duration::from_millis(9375));
+    }
+}
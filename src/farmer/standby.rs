@@ -0,0 +1,64 @@
+use crate::error::FarmerResult;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use uuid::Uuid;
+
+//Written to the shared lock file by the instance that is farming
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub instance_id: Uuid,
+    pub timestamp: u64,
+}
+impl Heartbeat {
+    pub async fn read(path: &Path) -> Option<Self> {
+        let contents = tokio::fs::read_to_string(path).await.ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+    //Written next to the lock file and renamed, the other instance never reads a partial heartbeat
+    pub async fn write(&self, path: &Path) -> FarmerResult<()> {
+        let tmp_path = path.with_extension(format!("{}.tmp", self.instance_id));
+        tokio::fs::write(&tmp_path, serde_json::to_string(self)?).await?;
+        Ok(tokio::fs::rename(&tmp_path, path).await?)
+    }
+}
+
+//The lock is ours while we hold it or once its holder missed heartbeats for takeover_after seconds.
+//A missing lock is only claimed after waiting, so a standby started first does not beat the primary to it
+pub fn may_farm(
+    heartbeat: Option<&Heartbeat>,
+    instance_id: &Uuid,
+    now: u64,
+    takeover_after: u64,
+    waited: bool,
+) -> bool {
+    match heartbeat {
+        None => waited,
+        Some(heartbeat) => {
+            heartbeat.instance_id == *instance_id
+                || now.saturating_sub(heartbeat.timestamp) >= takeover_after
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_takes_over_stale_or_missing_locks() {
+        let us = Uuid::new_v4();
+        let other = Heartbeat {
+            instance_id: Uuid::new_v4(),
+            timestamp: 1000,
+        };
+        assert!(!may_farm(Some(&other), &us, 1010, 30, true));
+        assert!(may_farm(Some(&other), &us, 1030, 30, false));
+        let ours = Heartbeat {
+            instance_id: us,
+            timestamp: 1000,
+        };
+        assert!(may_farm(Some(&ours), &us, 1010, 30, false));
+        assert!(!may_farm(None, &us, 1010, 30, false));
+        assert!(may_farm(None, &us, 1010, 30, true));
+    }
+}
@@ -0,0 +1,317 @@
+use crate::farmer::FarmerSharedState;
+use crate::harvesters::plot_dirs::validate_plot_dirs;
+use crate::harvesters::plot_scan::validate_scan_options;
+use crate::harvesters::{Harvesters, REMOTE_HARVESTERS};
+use crate::version;
+use dg_xch_clients::protocols::shared::PROTOCOL_VERSION;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use dg_xch_core::plots::PlotHeader;
+use hex::encode;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyCounts {
+    pub farmer: usize,
+    pub pool: usize,
+    pub owner: usize,
+    pub auth: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LauncherReport {
+    pub launcher_id: Bytes32,
+    pub pool_url: String,
+    pub has_auth_key: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FarmReport {
+    pub name: String,
+    pub payout_address: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payout_label: Option<String>,
+    pub launchers: Vec<LauncherReport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HarvesterReport {
+    pub id: Uuid,
+    pub kind: String,
+    pub plots: u64,
+    pub space: u64,
+    pub by_k: BTreeMap<u8, u64>,
+    pub by_compression: BTreeMap<u8, u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StartupReport {
+    pub version: String,
+    pub network: String,
+    pub agg_sig_me_additional_data: String,
+    pub fullnode_peer: String,
+    pub fullnode_rpc: String,
+    pub protocol_version: String,
+    pub fullnode_protocol_version: Option<String>,
+    pub fullnode_software_version: Option<String>,
+    pub keys: KeyCounts,
+    pub farms: Vec<FarmReport>,
+    pub harvesters: Vec<HarvesterReport>,
+    pub warnings: Vec<String>,
+}
+impl StartupReport {
+    pub async fn build(
+        shared_state: &FarmerSharedState,
+        harvesters: &HashMap<Uuid, Arc<Harvesters>>,
+    ) -> Self {
+        let config = shared_state.config.as_ref();
+        let mut warnings = vec![];
+        warnings.extend(
+            shared_state
+                .readiness
+                .disabled
+                .iter()
+                .map(|d| d.to_string()),
+        );
+        if let Some(bb_config) = &config.harvester_configs.bladebit {
+            warnings.extend(validate_plot_dirs(bb_config).iter().map(|e| e.to_string()));
+            warnings.extend(
+                validate_scan_options(bb_config)
+                    .iter()
+                    .map(|e| e.to_string()),
+            );
+        }
+        if config.consensus_overrides.is_some() {
+            warnings.push("Consensus constants are overridden".to_string());
+        }
+        let farms = config
+            .all_farms()
+            .into_iter()
+            .map(|farm| FarmReport {
+                launchers: farm
+                    .pool_info
+                    .iter()
+                    .map(|pool| {
+                        let has_auth_key = shared_state
+                            .auth_secret_keys
+                            .contains_key(&pool.owner_public_key);
+                        if !shared_state
+                            .owner_secret_keys
+                            .contains_key(&pool.owner_public_key)
+                        {
+                            warnings
+                                .push(format!("No owner key for launcher {}", pool.launcher_id));
+                        }
+                        if !has_auth_key {
+                            warnings.push(format!(
+                                "No authentication key for launcher {}, partials can not be signed",
+                                pool.launcher_id
+                            ));
+                        }
+                        LauncherReport {
+                            launcher_id: pool.launcher_id,
+                            pool_url: pool.pool_url.clone(),
+                            has_auth_key,
+                        }
+                    })
+                    .collect(),
+                name: farm.name,
+                payout_label: config.address_label(&farm.payout_address).cloned(),
+                payout_address: farm.payout_address,
+            })
+            .collect();
+        let mut harvester_reports = vec![];
+        for (id, harvester) in harvesters {
+            match harvester.as_ref() {
+                Harvesters::DruidGarden(h) => {
+                    let mut report = HarvesterReport {
+                        id: *id,
+                        kind: "druid_garden".to_string(),
+                        plots: 0,
+                        space: 0,
+                        by_k: BTreeMap::new(),
+                        by_compression: BTreeMap::new(),
+                    };
+                    for info in h.plots.lock().await.values() {
+                        let k = match info.reader.header() {
+                            PlotHeader::V1(h) => h.k,
+                            PlotHeader::V2(h) => h.k,
+                        };
+                        report.plots += 1;
+                        report.space += info.file_size;
+                        *report.by_k.entry(k).or_default() += 1;
+                        *report
+                            .by_compression
+                            .entry(info.reader.compression_level())
+                            .or_default() += 1;
+                    }
+                    harvester_reports.push(report);
+                }
+                //Plots of remote harvesters are not synced to the farmer
+                Harvesters::Remote(_) => harvester_reports.push(HarvesterReport {
+                    id: *id,
+                    kind: REMOTE_HARVESTERS.to_string(),
+                    plots: 0,
+                    space: 0,
+                    by_k: BTreeMap::new(),
+                    by_compression: BTreeMap::new(),
+                }),
+            }
+        }
+        let unfarmable: usize = shared_state
+            .unfarmable_plots
+            .snapshot()
+            .await
+            .values()
+            .map(Vec::len)
+            .sum();
+        if unfarmable > 0 {
+            warnings.push(format!(
+                "{unfarmable} plots can not be farmed with the configured keys, see ff plots"
+            ));
+        }
+        if harvester_reports.is_empty() {
+            warnings.push("No harvesters configured".to_string());
+        } else if harvester_reports
+            .iter()
+            .all(|h| h.plots == 0 && h.kind != REMOTE_HARVESTERS)
+        {
+            warnings.push("No plots loaded".to_string());
+        }
+        let peer_info = shared_state
+            .full_node_client
+            .lock()
+            .await
+            .as_ref()
+            .map(|c| c.info.clone());
+        if let Some(info) = peer_info.as_ref().filter(|i| i.is_newer_protocol()) {
+            warnings.push(format!(
+                "Full node protocol {} is newer than {PROTOCOL_VERSION}",
+                info.protocol_version
+            ));
+        }
+        StartupReport {
+            version: version(),
+            network: config.selected_network.clone(),
+            agg_sig_me_additional_data: encode(&shared_state.constants.agg_sig_me_additional_data),
+            fullnode_peer: format!("{}:{}", config.fullnode_ws_host, config.fullnode_ws_port),
+            fullnode_rpc: match &config.spare_rpc {
+                Some(spare) => format!(
+                    "{}:{}, reads from {}:{}",
+                    config.fullnode_rpc_host, config.fullnode_rpc_port, spare.host, spare.port
+                ),
+                None => format!("{}:{}", config.fullnode_rpc_host, config.fullnode_rpc_port),
+            },
+            protocol_version: PROTOCOL_VERSION.to_string(),
+            fullnode_protocol_version: peer_info.as_ref().map(|i| i.protocol_version.clone()),
+            fullnode_software_version: peer_info.map(|i| i.software_version),
+            keys: KeyCounts {
+                farmer: shared_state.farmer_private_keys.len(),
+                pool: shared_state.pool_public_keys.len(),
+                owner: shared_state.owner_secret_keys.len(),
+                auth: shared_state.auth_secret_keys.len(),
+            },
+            farms,
+            harvesters: harvester_reports,
+            warnings,
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("Startup Report - {}", self.version),
+            format!(
+                "Network: {} (AggSig: {})",
+                self.network, self.agg_sig_me_additional_data
+            ),
+            format!(
+                "Full Node: {} ({}, protocol {}, ours {}), RPC: {}",
+                self.fullnode_peer,
+                self.fullnode_software_version
+                    .as_deref()
+                    .unwrap_or("unknown"),
+                self.fullnode_protocol_version
+                    .as_deref()
+                    .unwrap_or("unknown"),
+                self.protocol_version,
+                self.fullnode_rpc
+            ),
+            format!(
+                "Keys: {} farmer, {} pool, {} owner, {} auth",
+                self.keys.farmer, self.keys.pool, self.keys.owner, self.keys.auth
+            ),
+        ];
+        for farm in &self.farms {
+            lines.push(format!(
+                "Farm {}: payout {}, {} launchers",
+                farm.name,
+                match &farm.payout_label {
+                    Some(label) => format!("@{label} ({})", farm.payout_address),
+                    None => farm.payout_address.clone(),
+                },
+                farm.launchers.len()
+            ));
+            for launcher in &farm.launchers {
+                lines.push(format!(
+                    "  {} -> {}",
+                    launcher.launcher_id,
+                    if launcher.pool_url.is_empty() {
+                        "solo"
+                    } else {
+                        &launcher.pool_url
+                    }
+                ));
+            }
+        }
+        for harvester in &self.harvesters {
+            lines.push(format!(
+                "Harvester {} ({}): {} plots, {}",
+                harvester.kind,
+                harvester.id,
+                harvester.plots,
+                bytefmt::format_to(harvester.space, bytefmt::Unit::TIB)
+            ));
+            lines.push(format!(
+                "  k-sizes: {}",
+                format_counts(&harvester.by_k, "k")
+            ));
+            lines.push(format!(
+                "  compression: {}",
+                format_counts(&harvester.by_compression, "C")
+            ));
+        }
+        for warning in &self.warnings {
+            lines.push(format!("WARNING: {warning}"));
+        }
+        lines
+    }
+
+    pub fn log(&self) {
+        let lines = self.lines();
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        info!("┌{}┐", "─".repeat(width + 2));
+        for line in &lines {
+            let padding = " ".repeat(width - line.chars().count());
+            if line.starts_with("WARNING") {
+                warn!("│ {line}{padding} │");
+            } else {
+                info!("│ {line}{padding} │");
+            }
+        }
+        info!("└{}┘", "─".repeat(width + 2));
+    }
+}
+
+fn format_counts(counts: &BTreeMap<u8, u64>, prefix: &str) -> String {
+    if counts.is_empty() {
+        return "none".to_string();
+    }
+    counts
+        .iter()
+        .map(|(k, v)| format!("{prefix}{k}: {v}"))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -0,0 +1,155 @@
+use crate::farmer::config::Config;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const RESUME_POLL: Duration = Duration::from_secs(1);
+
+//Background tasks that can be stopped and started through the control socket while farming
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    Dashboard,
+    PublicStatus,
+    Webhooks,
+    StatsReport,
+    EmailReport,
+}
+impl Subsystem {
+    pub const ALL: [Subsystem; 5] = [
+        Subsystem::Dashboard,
+        Subsystem::PublicStatus,
+        Subsystem::Webhooks,
+        Subsystem::StatsReport,
+        Subsystem::EmailReport,
+    ];
+    pub fn name(&self) -> &'static str {
+        match self {
+            Subsystem::Dashboard => "dashboard",
+            Subsystem::PublicStatus => "public_status",
+            Subsystem::Webhooks => "webhooks",
+            Subsystem::StatsReport => "stats_report",
+            Subsystem::EmailReport => "email_report",
+        }
+    }
+    //Subsystems without config never start, starting them does nothing
+    pub fn is_configured(&self, config: &Config) -> bool {
+        match self {
+            Subsystem::Dashboard => config.dashboard.is_some(),
+            Subsystem::PublicStatus => config.public_status.is_some(),
+            Subsystem::Webhooks => !config.webhooks.is_empty(),
+            Subsystem::StatsReport => config.stats_report.as_ref().is_some_and(|s| s.interval > 0),
+            Subsystem::EmailReport => config.email_report.is_some(),
+        }
+    }
+}
+impl Display for Subsystem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+impl FromStr for Subsystem {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Subsystem::ALL
+            .into_iter()
+            .find(|sub| sub.name() == s)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown subsystem {s}, expected one of {}",
+                    Subsystem::ALL.map(|s| s.name()).join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubsystemState {
+    pub name: Subsystem,
+    pub configured: bool,
+    pub running: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubsystemParams {
+    pub name: String,
+}
+
+//All subsystems run unless stopped, a stop lasts until they are started again or the farmer restarts
+#[derive(Default)]
+pub struct Subsystems {
+    stopped: Mutex<BTreeSet<Subsystem>>,
+}
+impl Subsystems {
+    pub fn is_running(&self, subsystem: Subsystem) -> bool {
+        self.stopped
+            .lock()
+            .map(|s| !s.contains(&subsystem))
+            .unwrap_or(true)
+    }
+    //True when the state changed
+    pub fn set_running(&self, subsystem: Subsystem, running: bool) -> bool {
+        let Ok(mut stopped) = self.stopped.lock() else {
+            return false;
+        };
+        if running {
+            stopped.remove(&subsystem)
+        } else {
+            stopped.insert(subsystem)
+        }
+    }
+    //Waits while the subsystem is stopped, false once the farmer shuts down
+    pub async fn wait_until_running(&self, subsystem: Subsystem, run: &AtomicBool) -> bool {
+        while !self.is_running(subsystem) {
+            if !run.load(Ordering::Relaxed) {
+                return false;
+            }
+            tokio::time::sleep(RESUME_POLL).await;
+        }
+        run.load(Ordering::Relaxed)
+    }
+    pub fn states(&self, config: &Config) -> Vec<SubsystemState> {
+        Subsystem::ALL
+            .into_iter()
+            .map(|name| SubsystemState {
+                name,
+                configured: name.is_configured(config),
+                running: name.is_configured(config) && self.is_running(name),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stops_and_starts_single_subsystems() {
+        let subsystems = Subsystems::default();
+        let run = AtomicBool::new(true);
+        assert!(subsystems.set_running(Subsystem::Webhooks, false));
+        assert!(!subsystems.set_running(Subsystem::Webhooks, false));
+        assert!(!subsystems.is_running(Subsystem::Webhooks));
+        assert!(subsystems.is_running(Subsystem::Dashboard));
+        assert!(subsystems.set_running(Subsystem::Webhooks, true));
+        assert!(
+            subsystems
+                .wait_until_running(Subsystem::Webhooks, &run)
+                .await
+        );
+        subsystems.set_running(Subsystem::Webhooks, false);
+        run.store(false, Ordering::Relaxed);
+        assert!(
+            !subsystems
+                .wait_until_running(Subsystem::Webhooks, &run)
+                .await
+        );
+        assert_eq!("public_status".parse(), Ok(Subsystem::PublicStatus));
+        assert!("remote_harvester".parse::<Subsystem>().is_err());
+    }
+}
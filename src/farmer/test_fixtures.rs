@@ -0,0 +1,28 @@
+use dg_xch_clients::protocols::pool::{PostPartialPayload, PostPartialRequest};
+use dg_xch_core::blockchain::proof_of_space::ProofOfSpace;
+
+//A k32 proof with an empty proof string, enough for anything that does not verify it
+pub fn proof_of_space() -> ProofOfSpace {
+    ProofOfSpace {
+        challenge: Default::default(),
+        pool_public_key: None,
+        pool_contract_puzzle_hash: None,
+        plot_public_key: Default::default(),
+        size: 32,
+        proof: vec![0; 8].into(),
+    }
+}
+
+pub fn partial_request() -> PostPartialRequest {
+    PostPartialRequest {
+        payload: PostPartialPayload {
+            launcher_id: Default::default(),
+            authentication_token: 0,
+            proof_of_space: proof_of_space(),
+            sp_hash: Default::default(),
+            end_of_sub_slot: false,
+            harvester_id: Default::default(),
+        },
+        aggregate_signature: Default::default(),
+    }
+}
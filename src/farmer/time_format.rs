@@ -0,0 +1,251 @@
+use crate::farmer::units;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, TimeZone, Utc};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use simple_logger::SimpleLogger;
+use std::fmt::{Display, Formatter, Write};
+use time::format_description::FormatItem;
+use time::UtcOffset;
+
+static TIME_CONFIG: OnceCell<TimeConfig> = OnceCell::new();
+
+//Zone and strftime format of every timestamp shown to the user, files and reports keep unix timestamps
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeConfig {
+    #[serde(default)]
+    pub zone: TimeZoneSetting,
+    #[serde(default = "default_time_format")]
+    pub format: String,
+    //Servers the system clock is compared against, tried in order, empty disables the check
+    #[serde(default = "default_ntp_servers")]
+    pub ntp_servers: Vec<String>,
+    //Clock offset in milliseconds above which a warning is logged
+    #[serde(default = "default_max_drift_ms", with = "units::millis")]
+    pub max_drift_ms: u64,
+    //Minutes between clock checks
+    #[serde(default = "default_drift_check_interval", with = "units::minutes")]
+    pub drift_check_interval: u64,
+}
+impl Default for TimeConfig {
+    fn default() -> Self {
+        Self {
+            zone: TimeZoneSetting::default(),
+            format: default_time_format(),
+            ntp_servers: default_ntp_servers(),
+            max_drift_ms: default_max_drift_ms(),
+            drift_check_interval: default_drift_check_interval(),
+        }
+    }
+}
+fn default_time_format() -> String {
+    "%Y-%m-%d %H:%M:%S".to_string()
+}
+fn default_ntp_servers() -> Vec<String> {
+    vec!["pool.ntp.org".to_string()]
+}
+//Partials are judged against the signage point time, a second is already a noticeable share of
+//the pool window
+fn default_max_drift_ms() -> u64 {
+    1000
+}
+fn default_drift_check_interval() -> u64 {
+    60
+}
+
+//local, utc or a fixed offset like +02:00
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum TimeZoneSetting {
+    #[default]
+    Local,
+    Utc,
+    Offset(i32),
+}
+impl TryFrom<String> for TimeZoneSetting {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_ascii_lowercase().as_str() {
+            "local" => Ok(TimeZoneSetting::Local),
+            "utc" => Ok(TimeZoneSetting::Utc),
+            offset => {
+                let invalid = || format!("Invalid time zone {value}, use local, utc or +HH:MM");
+                let (sign, rest) = match offset.split_at_checked(1) {
+                    Some(("+", rest)) => (1, rest),
+                    Some(("-", rest)) => (-1, rest),
+                    _ => return Err(invalid()),
+                };
+                let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+                let hours: i32 = hours.parse().map_err(|_| invalid())?;
+                let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+                if hours > 23 || minutes > 59 {
+                    return Err(invalid());
+                }
+                Ok(TimeZoneSetting::Offset(
+                    sign * (hours * 3600 + minutes * 60),
+                ))
+            }
+        }
+    }
+}
+impl From<TimeZoneSetting> for String {
+    fn from(value: TimeZoneSetting) -> Self {
+        value.to_string()
+    }
+}
+impl Display for TimeZoneSetting {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeZoneSetting::Local => f.write_str("local"),
+            TimeZoneSetting::Utc => f.write_str("utc"),
+            TimeZoneSetting::Offset(seconds) => write!(
+                f,
+                "{}{:02}:{:02}",
+                if *seconds < 0 { '-' } else { '+' },
+                seconds.abs() / 3600,
+                seconds.abs() % 3600 / 60
+            ),
+        }
+    }
+}
+
+//Only the first call applies, it is made once the config is loaded
+pub fn init_time_format(config: &TimeConfig) {
+    let mut config = config.clone();
+    if StrftimeItems::new(&config.format).any(|item| item == Item::Error) {
+        eprintln!("Invalid time format {}, using the default", config.format);
+        config.format = default_time_format();
+    }
+    let _ = TIME_CONFIG.set(config);
+}
+
+pub fn time_config() -> &'static TimeConfig {
+    TIME_CONFIG.get_or_init(TimeConfig::default)
+}
+
+pub fn format_timestamp(timestamp: u64) -> String {
+    format_with(time_config(), timestamp)
+}
+
+//Seconds the configured zone is ahead of utc at the timestamp
+pub fn utc_offset(timestamp: u64) -> i32 {
+    match time_config().zone {
+        TimeZoneSetting::Local => DateTime::<Utc>::from_timestamp(timestamp as i64, 0)
+            .map(|utc| {
+                Local
+                    .offset_from_utc_datetime(&utc.naive_utc())
+                    .local_minus_utc()
+            })
+            .unwrap_or_default(),
+        TimeZoneSetting::Utc => 0,
+        TimeZoneSetting::Offset(seconds) => seconds,
+    }
+}
+
+fn format_with(config: &TimeConfig, timestamp: u64) -> String {
+    let Some(utc) = DateTime::<Utc>::from_timestamp(timestamp as i64, 0) else {
+        return String::new();
+    };
+    let formatted = match config.zone {
+        TimeZoneSetting::Local => Local
+            .from_utc_datetime(&utc.naive_utc())
+            .format(&config.format),
+        TimeZoneSetting::Utc => utc.format(&config.format),
+        TimeZoneSetting::Offset(seconds) => FixedOffset::east_opt(seconds)
+            .unwrap_or(FixedOffset::east_opt(0).expect("zero offset is valid"))
+            .from_utc_datetime(&utc.naive_utc())
+            .format(&config.format),
+    };
+    //chrono reports invalid specifiers as a fmt error instead of panicking in write!
+    let mut out = String::new();
+    if write!(out, "{formatted}").is_err() {
+        return utc.to_rfc3339();
+    }
+    out
+}
+
+//simple_logger formats with the time crate, the common strftime fields are translated and anything
+//else keeps its default format
+pub fn configure_logger(logger: SimpleLogger, config: &TimeConfig) -> SimpleLogger {
+    let logger = match config.zone {
+        TimeZoneSetting::Local => logger.with_local_timestamps(),
+        TimeZoneSetting::Utc => logger.with_utc_timestamps(),
+        TimeZoneSetting::Offset(seconds) => match UtcOffset::from_whole_seconds(seconds) {
+            Ok(offset) => logger.with_utc_offset(offset),
+            Err(_) => logger.with_utc_timestamps(),
+        },
+    };
+    match logger_format(&config.format) {
+        Some(format) => logger.with_timestamp_format(format),
+        None => {
+            eprintln!(
+                "Log timestamps keep their default format, {} uses fields the logger does not support",
+                config.format
+            );
+            logger
+        }
+    }
+}
+
+fn logger_format(format: &str) -> Option<&'static [FormatItem<'static>]> {
+    let description: &'static str = Box::leak(strftime_to_description(format)?.into_boxed_str());
+    let items = time::format_description::parse(description).ok()?;
+    Some(Box::leak(items.into_boxed_slice()))
+}
+
+fn strftime_to_description(format: &str) -> Option<String> {
+    let mut description = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            if c == '[' {
+                description.push_str("[[");
+            } else {
+                description.push(c);
+            }
+            continue;
+        }
+        let field = match chars.next()? {
+            'Y' => "[year]",
+            'm' => "[month]",
+            'd' => "[day]",
+            'H' => "[hour]",
+            'M' => "[minute]",
+            'S' => "[second]",
+            'f' => "[subsecond digits:9]",
+            'z' => "[offset_hour sign:mandatory][offset_minute]",
+            'b' => "[month repr:short]",
+            'a' => "[weekday repr:short]",
+            '%' => "%",
+            _ => return None,
+        };
+        description.push_str(field);
+    }
+    Some(description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_in_the_configured_zone() {
+        let zone = TimeZoneSetting::try_from("+02:30".to_string()).unwrap();
+        assert_eq!(zone, TimeZoneSetting::Offset(9000));
+        assert_eq!(zone.to_string(), "+02:30");
+        assert!(TimeZoneSetting::try_from("Mars".to_string()).is_err());
+        let config = TimeConfig {
+            zone,
+            format: "%d.%m.%Y %H:%M %z".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(format_with(&config, 0), "01.01.1970 02:30 +0230");
+        let utc = TimeConfig {
+            zone: TimeZoneSetting::Utc,
+            ..Default::default()
+        };
+        assert_eq!(format_with(&utc, 86400), "1970-01-02 00:00:00");
+        assert!(logger_format(&utc.format).is_some());
+        assert!(logger_format("%Q").is_none());
+    }
+}
@@ -0,0 +1,218 @@
+use serde::de::{Error, Visitor};
+use serde::{Deserializer, Serializer};
+use std::fmt::Formatter;
+
+//Config values are plain numbers in the unit of the field or strings with a unit, like 8s, 15m or
+//2GiB. They are written back as plain numbers so saved configs keep loading in older versions
+
+const DURATION_UNITS: [(&str, f64); 5] = [
+    ("ms", 1.0),
+    ("s", 1000.0),
+    ("m", 60_000.0),
+    ("h", 3_600_000.0),
+    ("d", 86_400_000.0),
+];
+const SIZE_UNITS: [(&str, f64); 9] = [
+    ("b", 1.0),
+    ("kb", 1e3),
+    ("mb", 1e6),
+    ("gb", 1e9),
+    ("tb", 1e12),
+    ("kib", 1024.0),
+    ("mib", 1_048_576.0),
+    ("gib", 1_073_741_824.0),
+    ("tib", 1_099_511_627_776.0),
+];
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Duration,
+    Size,
+}
+impl Kind {
+    fn units(&self) -> &'static [(&'static str, f64)] {
+        match self {
+            Kind::Duration => &DURATION_UNITS,
+            Kind::Size => &SIZE_UNITS,
+        }
+    }
+}
+
+//Amount in the base unit, milliseconds or bytes
+fn parse(value: &str, kind: Kind) -> Result<f64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: f64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid number in {value}"))?;
+    let unit = unit.trim().to_ascii_lowercase();
+    let Some((_, factor)) = kind.units().iter().find(|(name, _)| *name == unit) else {
+        return Err(format!(
+            "Unknown unit in {value}, use one of {}",
+            kind.units()
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    };
+    Ok(amount * factor)
+}
+
+//Converts to the unit of the field, refusing values that would be silently rounded
+fn to_field_unit(value: &str, kind: Kind, per_unit: f64, unit: &str) -> Result<u64, String> {
+    let amount = parse(value, kind)? / per_unit;
+    if amount.fract() != 0.0 || amount > u64::MAX as f64 {
+        return Err(format!("{value} is not a whole number of {unit}"));
+    }
+    Ok(amount as u64)
+}
+
+struct UnitVisitor {
+    kind: Kind,
+    per_unit: f64,
+    unit: &'static str,
+}
+impl Visitor<'_> for UnitVisitor {
+    type Value = u64;
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "a number of {} or a value with a unit",
+            self.unit
+        )
+    }
+    fn visit_u64<E: Error>(self, v: u64) -> Result<u64, E> {
+        Ok(v)
+    }
+    fn visit_i64<E: Error>(self, v: i64) -> Result<u64, E> {
+        u64::try_from(v).map_err(|_| E::custom(format!("{v} is negative")))
+    }
+    fn visit_str<E: Error>(self, v: &str) -> Result<u64, E> {
+        to_field_unit(v, self.kind, self.per_unit, self.unit).map_err(E::custom)
+    }
+}
+
+struct OptionVisitor(UnitVisitor);
+impl<'de> Visitor<'de> for OptionVisitor {
+    type Value = Option<u64>;
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        self.0.expecting(formatter)
+    }
+    fn visit_none<E: Error>(self) -> Result<Option<u64>, E> {
+        Ok(None)
+    }
+    fn visit_unit<E: Error>(self) -> Result<Option<u64>, E> {
+        Ok(None)
+    }
+    fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<Option<u64>, D::Error> {
+        d.deserialize_any(self.0).map(Some)
+    }
+}
+
+macro_rules! unit_field {
+    ($name:ident, $kind:expr, $per_unit:expr, $unit:expr) => {
+        pub mod $name {
+            use super::*;
+
+            fn visitor() -> UnitVisitor {
+                UnitVisitor {
+                    kind: $kind,
+                    per_unit: $per_unit,
+                    unit: $unit,
+                }
+            }
+            pub fn serialize<S: Serializer>(value: &u64, s: S) -> Result<S::Ok, S::Error> {
+                s.serialize_u64(*value)
+            }
+            pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<u64, D::Error> {
+                d.deserialize_any(visitor())
+            }
+            pub mod option {
+                use super::*;
+
+                pub fn serialize<S: Serializer>(
+                    value: &Option<u64>,
+                    s: S,
+                ) -> Result<S::Ok, S::Error> {
+                    match value {
+                        Some(value) => s.serialize_some(value),
+                        None => s.serialize_none(),
+                    }
+                }
+                pub fn deserialize<'de, D: Deserializer<'de>>(
+                    d: D,
+                ) -> Result<Option<u64>, D::Error> {
+                    d.deserialize_option(OptionVisitor(visitor()))
+                }
+            }
+        }
+    };
+}
+
+unit_field!(millis, Kind::Duration, 1.0, "milliseconds");
+unit_field!(secs, Kind::Duration, 1000.0, "seconds");
+unit_field!(minutes, Kind::Duration, 60_000.0, "minutes");
+unit_field!(bytes, Kind::Size, 1.0, "bytes");
+unit_field!(mib, Kind::Size, 1_048_576.0, "MiB");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Fields {
+        #[serde(with = "secs")]
+        timeout: u64,
+        #[serde(with = "minutes")]
+        interval: u64,
+        #[serde(with = "mib")]
+        cache: u64,
+        #[serde(default, with = "bytes::option")]
+        read_ahead: Option<u64>,
+    }
+
+    #[test]
+    fn accepts_plain_numbers_and_units() {
+        let fields: Fields =
+            serde_yaml::from_str("timeout: 8s\ninterval: 2h\ncache: 2GiB\nread_ahead: 64 KiB")
+                .unwrap();
+        assert_eq!(
+            fields,
+            Fields {
+                timeout: 8,
+                interval: 120,
+                cache: 2048,
+                read_ahead: Some(65536),
+            }
+        );
+        let plain: Fields =
+            serde_yaml::from_str("timeout: 8\ninterval: 120\ncache: 2048\nread_ahead: 65536")
+                .unwrap();
+        assert_eq!(plain, fields);
+        assert_eq!(
+            serde_yaml::from_str::<Fields>("timeout: 8\ninterval: 1\ncache: 1")
+                .unwrap()
+                .read_ahead,
+            None
+        );
+        assert_eq!(
+            serde_yaml::to_string(&fields).unwrap(),
+            "timeout: 8\ninterval: 120\ncache: 2048\nread_ahead: 65536\n"
+        );
+        for invalid in [
+            "timeout: 1500ms\ninterval: 1\ncache: 1",
+            "timeout: 8 parsecs\ninterval: 1\ncache: 1",
+            "timeout: -1\ninterval: 1\ncache: 1",
+        ] {
+            assert!(
+                serde_yaml::from_str::<Fields>(invalid).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::error::{FarmerError, FarmerResult};
+use crate::farmer::events::{FarmerEvent, FarmerEventKind};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const TEMPLATE_VARIABLES: &[&str] = &[
+    "event",
+    "timestamp",
+    "launcher_id",
+    "pool_url",
+    "difficulty",
+    "error_code",
+    "error_message",
+    "challenge_hash",
+    "sp_hash",
+    "plot",
+    "sp",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    PartialAccepted,
+    PartialRejected,
+    //A proof good enough for a block was declared to the full node
+    Block,
+}
+impl WebhookEvent {
+    pub fn from_event(kind: &FarmerEventKind) -> Option<Self> {
+        match kind {
+            FarmerEventKind::PartialAccepted { .. } => Some(WebhookEvent::PartialAccepted),
+            FarmerEventKind::PartialRejected { .. } => Some(WebhookEvent::PartialRejected),
+            FarmerEventKind::ProofDeclared { .. } => Some(WebhookEvent::Block),
+            _ => None,
+        }
+    }
+    fn name(&self) -> &'static str {
+        match self {
+            WebhookEvent::PartialAccepted => "partial_accepted",
+            WebhookEvent::PartialRejected => "partial_rejected",
+            WebhookEvent::Block => "block",
+        }
+    }
+}
+
+//Values available to templates, variables that do not apply to the event are left out and render empty
+pub fn template_variables(event: &FarmerEvent) -> HashMap<&'static str, String> {
+    let mut vars = HashMap::new();
+    if let Some(webhook_event) = WebhookEvent::from_event(&event.kind) {
+        vars.insert("event", webhook_event.name().to_string());
+    }
+    vars.insert("timestamp", event.timestamp.to_string());
+    match &event.kind {
+        FarmerEventKind::PartialAccepted {
+            launcher_id,
+            pool_url,
+            new_difficulty,
+            sp,
+        } => {
+            vars.insert("launcher_id", launcher_id.to_string());
+            vars.insert("pool_url", pool_url.clone());
+            vars.insert("difficulty", new_difficulty.to_string());
+            if let Some(sp) = sp {
+                vars.insert("sp", sp.to_string());
+            }
+        }
+        FarmerEventKind::PartialRejected {
+            launcher_id,
+            pool_url,
+            error_code,
+            error_message,
+            sp,
+        } => {
+            vars.insert("launcher_id", launcher_id.to_string());
+            vars.insert("pool_url", pool_url.clone());
+            vars.insert("error_code", error_code.to_string());
+            vars.insert("error_message", error_message.clone());
+            if let Some(sp) = sp {
+                vars.insert("sp", sp.to_string());
+            }
+        }
+        FarmerEventKind::ProofDeclared {
+            challenge_hash,
+            sp_hash,
+            plot_identifier,
+            sp,
+            launcher_id,
+        } => {
+            vars.insert("challenge_hash", challenge_hash.to_string());
+            vars.insert("sp_hash", sp_hash.to_string());
+            vars.insert("plot", plot_identifier.clone());
+            if let Some(sp) = sp {
+                vars.insert("sp", sp.to_string());
+            }
+            if let Some(launcher_id) = launcher_id {
+                vars.insert("launcher_id", launcher_id.to_string());
+            }
+        }
+        _ => {}
+    }
+    vars
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Text(String),
+    Variable(&'static str),
+}
+
+//Minimal {{ variable }} templates, parsed once at startup so typos fail before the first partial
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookTemplate {
+    parts: Vec<TemplatePart>,
+}
+impl WebhookTemplate {
+    pub fn parse(template: &str) -> FarmerResult<Self> {
+        Self::parse_with(template, TEMPLATE_VARIABLES)
+    }
+    //Templates of other features bring their own variables
+    pub fn parse_with(template: &str, variables: &'static [&'static str]) -> FarmerResult<Self> {
+        let mut parts = vec![];
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(TemplatePart::Text(rest[..start].to_string()));
+            }
+            let Some(end) = rest[start..].find("}}") else {
+                return Err(FarmerError::Config(format!(
+                    "Unclosed placeholder in template: {}",
+                    &rest[start..]
+                )));
+            };
+            let name = rest[start + 2..start + end].trim();
+            let Some(variable) = variables.iter().find(|v| **v == name) else {
+                return Err(FarmerError::Config(format!(
+                    "Unknown template variable {name}, expected one of {}",
+                    variables.join(", ")
+                )));
+            };
+            parts.push(TemplatePart::Variable(variable));
+            rest = &rest[start + end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Text(rest.to_string()));
+        }
+        Ok(Self { parts })
+    }
+    //Json templates get their values escaped so error messages can not break the payload
+    pub fn render(&self, vars: &HashMap<&'static str, String>, escape_json: bool) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Text(text) => out.push_str(text),
+                TemplatePart::Variable(name) => {
+                    let value = vars.get(name).map(String::as_str).unwrap_or_default();
+                    if escape_json {
+                        let quoted = serde_json::Value::from(value).to_string();
+                        out.push_str(&quoted[1..quoted.len() - 1]);
+                    } else {
+                        out.push_str(value);
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dg_xch_core::blockchain::sized_bytes::Bytes32;
+
+    #[test]
+    fn renders_pool_specific_payload() {
+        let template = WebhookTemplate::parse(
+            r#"{"text": "{{event}} at {{ pool_url }}: {{error_message}}{{difficulty}}"}"#,
+        )
+        .unwrap();
+        let event = FarmerEvent::new(FarmerEventKind::PartialRejected {
+            launcher_id: Bytes32::default(),
+            pool_url: "https://pool.example".to_string(),
+            error_code: 2,
+            error_message: "too \"late\"".to_string(),
+            sp: None,
+        });
+        let body = template.render(&template_variables(&event), true);
+        assert_eq!(
+            body,
+            r#"{"text": "partial_rejected at https://pool.example: too \"late\""}"#
+        );
+        assert!(serde_json::from_str::<serde_json::Value>(&body).is_ok());
+        assert!(WebhookTemplate::parse("{{ points }}").is_err());
+        assert!(WebhookTemplate::parse("{{ event").is_err());
+    }
+}
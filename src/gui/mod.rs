@@ -1,9 +1,14 @@
+mod plot_browser;
+
+use crate::farmer::availability::AvailabilityTracker;
+use crate::farmer::sp_timing::SpTiming;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use std::io::Error;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -13,13 +18,47 @@ use ratatui::{prelude::*, widgets::*};
 
 use tui_logger::*;
 
-use crate::farmer::config::{load_keys, Config};
+use crate::control::{control_server, set_log_level};
+use crate::dashboard::dashboard_server;
+use crate::farmer::config::{default_payout_address, load_keys, Config};
+use crate::farmer::earnings::format_earnings;
+use crate::farmer::events::{EventFilter, FarmerEvent};
+use crate::farmer::farms::FarmIndex;
+use crate::farmer::idle::IdleState;
+use crate::farmer::instance_lock::claim;
+use crate::farmer::latency_regression::LatencyBaselines;
+use crate::farmer::launcher_stats::LauncherStatsMap;
+use crate::farmer::network_stats::format_time_to_win;
+use crate::farmer::plot_leaderboard::PlotLeaderboard;
+use crate::farmer::pool_client::build_pool_client;
+use crate::farmer::readiness::Readiness;
+use crate::farmer::rpc::FullnodeRpc;
+use crate::farmer::time_format::{format_timestamp, time_config};
 use crate::farmer::{Farmer, FarmerSharedState, GuiStats};
+use crate::gui::plot_browser::{BrowserAction, PlotBrowser};
+use crate::harvesters::plot_registry::PlotRegistry;
+use crate::harvesters::stats::HarvesterStatsSnapshot;
+use crate::tasks::clock_drift::clock_drift_monitor;
+use crate::tasks::drive_health::drive_health_monitor;
+use crate::tasks::email_report::email_reporter;
+use crate::tasks::event_history::event_recorder;
+use crate::tasks::idle_monitor::idle_monitor;
+use crate::tasks::key_health::key_health_monitor;
+use crate::tasks::network_stats::network_stats_updater;
+use crate::tasks::partial_batch::partial_batch_sender;
+use crate::tasks::partial_queue::partial_queue_flusher;
+use crate::tasks::plugins::plugin_runner;
 use crate::tasks::pool_state_updater::pool_updater;
-use chrono::prelude::*;
-use dg_xch_clients::api::full_node::FullnodeAPI;
-use dg_xch_clients::api::pool::DefaultPoolClient;
-use dg_xch_clients::rpc::full_node::FullnodeClient;
+use crate::tasks::sp_digest::sp_digest_logger;
+use crate::tasks::sp_relay::sp_relay;
+use crate::tasks::standby_monitor::{instance_lock_monitor, standby_monitor};
+use crate::tasks::status_summary::status_summary;
+use crate::tasks::webhooks::webhook_sender;
+use crate::tasks::xch_price::xch_price_updater;
+use crate::{
+    get_availability_path, get_excluded_plots_path, get_latency_baselines_path,
+    get_plot_leaderboard_path,
+};
 use dg_xch_core::blockchain::blockchain_state::BlockchainState;
 use dg_xch_keys::decode_puzzle_hash;
 use log::{error, LevelFilter};
@@ -29,6 +68,8 @@ use tokio::sync::Mutex;
 use tokio::task::{spawn_blocking, JoinHandle};
 use tokio::time::sleep;
 
+const EVENT_PANE_LINES: usize = 50;
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Action {
     None,
@@ -66,26 +107,64 @@ pub struct FullNodeState {
     pub blockchain_state: BlockchainState,
 }
 
-pub async fn bootstrap(config: Arc<Config>) -> Result<(), Error> {
-    init_logger(LevelFilter::Info).unwrap();
-    set_default_level(LevelFilter::Info);
+pub async fn bootstrap(config: Arc<Config>, config_path: PathBuf) -> Result<(), Error> {
+    //Everything is captured so the level can be raised at runtime, log::max_level does the filtering
+    init_logger(LevelFilter::Trace).unwrap();
+    set_log_level(LevelFilter::Info);
+    let instance_claim = claim(config.as_ref(), &config_path)?;
     enable_raw_mode()?;
     let (farmer_private_keys, owner_secret_keys, auth_secret_keys, pool_public_keys) =
         load_keys(config.clone()).await;
-    let farmer_target_encoded = &config.payout_address;
-    let farmer_target = decode_puzzle_hash(farmer_target_encoded)?;
-    let pool_target = decode_puzzle_hash(farmer_target_encoded)?;
+    let readiness = Readiness::check(config.as_ref());
+    readiness.log();
+    let farms = FarmIndex::new(config.as_ref());
+    //An invalid payout address leaves the farmer harvester only, the targets are never used then
+    let farmer_target_encoded = default_payout_address(config.as_ref());
+    let farmer_target = decode_puzzle_hash(&farmer_target_encoded).unwrap_or_default();
+    let pool_target = decode_puzzle_hash(&farmer_target_encoded).unwrap_or_default();
+    let constants = config.consensus_constants();
     let shared_state = Arc::new(FarmerSharedState {
         config: config.clone(),
+        config_path: Arc::new(config_path),
         run: Arc::new(AtomicBool::new(true)),
+        standby: Arc::new(AtomicBool::new(
+            config.high_availability.is_some() || instance_claim.is_waiting(),
+        )),
+        idle: Arc::new(IdleState::new(config.idle_after)),
         farmer_private_keys: Arc::new(farmer_private_keys),
         owner_secret_keys: Arc::new(owner_secret_keys),
         auth_secret_keys: Arc::new(auth_secret_keys),
         pool_public_keys: Arc::new(pool_public_keys),
         farmer_target: Arc::new(farmer_target),
         pool_target: Arc::new(pool_target),
+        farms: Arc::new(farms),
+        readiness: Arc::new(readiness),
+        plot_registry: Arc::new(PlotRegistry::load(&get_excluded_plots_path())),
+        plot_leaderboard: Arc::new(Mutex::new(PlotLeaderboard::load(
+            &get_plot_leaderboard_path(),
+        ))),
+        latency_baselines: Arc::new(Mutex::new(LatencyBaselines::load(
+            &get_latency_baselines_path(),
+            config.latency_regression.clone(),
+        ))),
+        availability: Arc::new(Mutex::new(AvailabilityTracker::load(
+            &get_availability_path(),
+            SpTiming::new(constants).sp_interval,
+        ))),
+        constants,
         ..Default::default()
     });
+    shared_state.gui_stats.lock().await.payout_targets = config
+        .all_farms()
+        .iter()
+        .map(|f| (f.name.clone(), config.address_display(&f.payout_address)))
+        .collect();
+    shared_state.gui_stats.lock().await.disabled = shared_state
+        .readiness
+        .disabled
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
     let mut stdout = std::io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
@@ -97,23 +176,100 @@ pub async fn bootstrap(config: Arc<Config>) -> Result<(), Error> {
         let pool_state = farmer_state.clone();
         let pool_state_handle: JoinHandle<()> =
             tokio::spawn(async move { pool_updater(pool_state).await });
-        let pool_client = Arc::new(DefaultPoolClient::new());
+        let partial_batch_state = farmer_state.clone();
+        let partial_batch_handle: JoinHandle<()> =
+            tokio::spawn(async move { partial_batch_sender(partial_batch_state).await });
+        let partial_queue_state = farmer_state.clone();
+        let partial_queue_handle: JoinHandle<()> =
+            tokio::spawn(async move { partial_queue_flusher(partial_queue_state).await });
+        let status_state = farmer_state.clone();
+        let status_handle: JoinHandle<()> =
+            tokio::spawn(async move { status_summary(status_state).await });
+        let history_state = farmer_state.clone();
+        let history_handle: JoinHandle<()> =
+            tokio::spawn(async move { event_recorder(history_state).await });
+        let digest_state = farmer_state.clone();
+        let digest_handle: JoinHandle<()> =
+            tokio::spawn(async move { sp_digest_logger(digest_state).await });
+        let relay_state = farmer_state.clone();
+        let relay_handle: JoinHandle<()> = tokio::spawn(async move { sp_relay(relay_state).await });
+        let webhook_state = farmer_state.clone();
+        let webhook_handle: JoinHandle<()> =
+            tokio::spawn(async move { webhook_sender(webhook_state).await });
+        let email_state = farmer_state.clone();
+        let email_handle: JoinHandle<()> =
+            tokio::spawn(async move { email_reporter(email_state).await });
+        let standby_state = farmer_state.clone();
+        let standby_handle: JoinHandle<()> =
+            tokio::spawn(async move { standby_monitor(standby_state).await });
+        let lock_state = farmer_state.clone();
+        let lock_handle: JoinHandle<()> =
+            tokio::spawn(async move { instance_lock_monitor(lock_state, instance_claim).await });
+        let idle_state = farmer_state.clone();
+        let idle_handle: JoinHandle<()> =
+            tokio::spawn(async move { idle_monitor(idle_state).await });
+        let dashboard_state = farmer_state.clone();
+        let dashboard_handle: JoinHandle<()> =
+            tokio::spawn(async move { dashboard_server(dashboard_state).await });
+        let control_state = farmer_state.clone();
+        let control_handle: JoinHandle<()> =
+            tokio::spawn(async move { control_server(control_state).await });
+        let network_state = farmer_state.clone();
+        let network_handle: JoinHandle<()> =
+            tokio::spawn(async move { network_stats_updater(network_state).await });
+        let plugin_state = farmer_state.clone();
+        let plugin_handle: JoinHandle<()> =
+            tokio::spawn(async move { plugin_runner(plugin_state).await });
+        let drive_health_state = farmer_state.clone();
+        let drive_health_handle: JoinHandle<()> =
+            tokio::spawn(async move { drive_health_monitor(drive_health_state).await });
+        let key_health_state = farmer_state.clone();
+        let key_health_handle: JoinHandle<()> =
+            tokio::spawn(async move { key_health_monitor(key_health_state).await });
+        let clock_state = farmer_state.clone();
+        let clock_handle: JoinHandle<()> =
+            tokio::spawn(async move { clock_drift_monitor(clock_state).await });
+        let price_state = farmer_state.clone();
+        let price_handle: JoinHandle<()> =
+            tokio::spawn(async move { xch_price_updater(price_state).await });
+        let pool_client = Arc::new(build_pool_client(farmer_state.config.as_ref()));
         let farmer = Farmer::new(farmer_state, pool_client).await?;
         let client_handle: JoinHandle<Result<(), Error>> = tokio::spawn(async move {
             farmer.run().await;
             Ok(())
         });
-        let _ = join!(pool_state_handle, client_handle);
+        let _ = join!(
+            pool_state_handle,
+            partial_batch_handle,
+            partial_queue_handle,
+            status_handle,
+            history_handle,
+            webhook_handle,
+            email_handle,
+            relay_handle,
+            digest_handle,
+            standby_handle,
+            lock_handle,
+            idle_handle,
+            dashboard_handle,
+            control_handle,
+            network_handle,
+            plugin_handle,
+            drive_health_handle,
+            price_handle,
+            clock_handle,
+            key_health_handle,
+            client_handle
+        );
         Ok::<(), Error>(())
     });
     let fullnode_state = gui_state.clone();
     let fullnode_thread = tokio::spawn(async move {
-        let full_node_rpc = FullnodeClient::new(
-            &config.fullnode_rpc_host,
-            config.fullnode_rpc_port,
-            config.ssl_root_path.clone(),
-            &None,
-        );
+        let full_node_rpc = FullnodeRpc::reader(
+            config.as_ref(),
+            fullnode_state.farmer_state.rpc_health.clone(),
+        )
+        .await;
         let mut last_update = Instant::now();
         loop {
             if last_update.elapsed().as_secs() > 5 {
@@ -203,25 +359,73 @@ async fn run_gui<B: Backend>(
     terminal: &mut Terminal<B>,
     gui_state: Arc<GuiState>,
 ) -> std::io::Result<()> {
+    let mut plot_browser: Option<PlotBrowser> = None;
     loop {
-        {
+        if let Some(browser) = plot_browser.as_mut() {
+            if browser.needs_refresh() {
+                browser.refresh(gui_state.farmer_state.plot_registry.snapshot().await);
+            }
+            terminal.draw(|f| {
+                f.render_widget(Block::default().on_black().gray(), f.size());
+                browser.draw(f, f.size());
+            })?;
+        } else {
             let farmer_state = gui_state.farmer_state.gui_stats.lock().await.clone();
             let sys_info = *gui_state.system_info.lock().await;
             let fullnode_state = gui_state.fullnode_state.lock().await.clone();
-            terminal.draw(|f| ui(f, farmer_state, fullnode_state, sys_info))?;
+            let harvester_stats = gui_state.farmer_state.harvester_stats.snapshot().await;
+            let launchers = gui_state.farmer_state.launcher_stats().await;
+            let process_state = if gui_state.farmer_state.is_paused() {
+                "Paused"
+            } else if gui_state.farmer_state.is_standby() {
+                "Standby"
+            } else {
+                "Running"
+            };
+            let recent_events = gui_state
+                .farmer_state
+                .event_history
+                .recent(EVENT_PANE_LINES, None)
+                .await;
+            terminal.draw(|f| {
+                ui(
+                    f,
+                    farmer_state,
+                    harvester_stats,
+                    launchers,
+                    fullnode_state,
+                    sys_info,
+                    process_state,
+                    recent_events,
+                )
+            })?;
         }
         if event::poll(Duration::from_millis(25))? {
             if let Event::Key(event) = event::read()? {
-                match event.code {
-                    KeyCode::Esc => {
-                        gui_state.farmer_state.run.store(false, Ordering::Relaxed);
+                if event.code == KeyCode::Char('c') && event.modifiers == KeyModifiers::CONTROL {
+                    gui_state.farmer_state.run.store(false, Ordering::Relaxed);
+                } else if let Some(browser) = plot_browser.as_mut() {
+                    let registry = &gui_state.farmer_state.plot_registry;
+                    match browser.handle_key(event.code) {
+                        BrowserAction::None => {}
+                        BrowserAction::Close => plot_browser = None,
+                        BrowserAction::Recheck(path) => registry.request_recheck(&path).await,
+                        BrowserAction::ToggleExclude(path, exclude) => {
+                            registry.set_excluded(&path, exclude).await
+                        }
                     }
-                    KeyCode::Char('c') => {
-                        if event.modifiers == KeyModifiers::CONTROL {
+                } else {
+                    match event.code {
+                        KeyCode::Esc => {
                             gui_state.farmer_state.run.store(false, Ordering::Relaxed);
                         }
+                        KeyCode::Tab => plot_browser = Some(PlotBrowser::default()),
+                        KeyCode::Char('p') => {
+                            let farmer_state = &gui_state.farmer_state;
+                            farmer_state.set_paused(!farmer_state.is_paused());
+                        }
+                        _ => {}
                     }
-                    _ => {}
                 }
             }
         }
@@ -232,11 +436,16 @@ async fn run_gui<B: Backend>(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn ui(
     f: &mut Frame,
     farmer_state: GuiStats,
+    harvester_stats: HarvesterStatsSnapshot,
+    launchers: LauncherStatsMap,
     fullnode_state: Option<FullNodeState>,
     sys_info: SysInfo,
+    process_state: &str,
+    recent_events: Vec<FarmerEvent>,
 ) {
     let size = f.size();
     let chunks = Layout::default()
@@ -268,17 +477,99 @@ fn ui(
         .split(wrapper_chunks[0]);
 
     let farmer_info = {
-        format!(
-            "\t  Process State: Running\n\
+        let mut farmer_info = format!(
+            "\t  Process State: {}\n\
              \t  Plot Count: {:#?}\n\
              \t  Total Space: {} ({:#?})\n\
+             \t  Effective Space: {}\n\
+             \t  Expected Time To Win: {}\n\
              \t  Most Recent Signage Point: \n    {:#?} ({:#?})",
-            farmer_state.total_plot_count,
-            bytefmt::format_to(farmer_state.total_plot_space, bytefmt::Unit::TIB),
-            farmer_state.total_plot_space,
+            process_state,
+            harvester_stats.farm.plots.count,
+            bytefmt::format_to(harvester_stats.farm.plots.space, bytefmt::Unit::TIB),
+            harvester_stats.farm.plots.space,
+            bytefmt::format_to(
+                harvester_stats.farm.plots.effective_space,
+                bytefmt::Unit::TIB
+            ),
+            format_time_to_win(farmer_state.network.and_then(|n| n.time_to_win)),
             farmer_state.most_recent_sp.0,
             farmer_state.most_recent_sp.1,
-        )
+        );
+        if harvester_stats.harvesters.len() > 1 {
+            for (harvester_id, stats) in &harvester_stats.harvesters {
+                farmer_info.push_str(&format!(
+                    "\n\t  Harvester {}: {} plots, {:.2} eligible/sp, {} proofs",
+                    &harvester_id.to_string()[..8],
+                    stats.plots.count,
+                    stats.eligible_per_sp(),
+                    stats.proofs,
+                ));
+            }
+        }
+        if farmer_state.farm_stats.len() > 1 {
+            for (farm, stats) in &farmer_state.farm_stats {
+                farmer_info.push_str(&format!(
+                    "\n\t  Farm {}: {} proofs, {}/{} partials accepted, {} suppressed",
+                    farm,
+                    stats.proofs_declared,
+                    stats.partials_accepted,
+                    stats.partials_submitted,
+                    stats.partials_suppressed,
+                ));
+            }
+        }
+        for (launcher_id, stats) in &launchers {
+            if let Some(earnings) = &stats.earnings {
+                farmer_info.push_str(&format!(
+                    "\n\t  Launcher {}: ~{}, pool fee {:.1}%",
+                    &launcher_id[..8],
+                    format_earnings(earnings),
+                    stats.pool_fee.unwrap_or_default() * 100.0,
+                ));
+            }
+        }
+        for disabled in &farmer_state.disabled {
+            farmer_info.push_str(&format!("\n\t  {}", disabled));
+        }
+        for (farm, target) in &farmer_state.payout_targets {
+            farmer_info.push_str(&format!("\n\t  Payout ({}): {}", farm, target));
+        }
+        if farmer_state.queued_partials > 0 {
+            farmer_info.push_str(&format!(
+                "\n\t  Queued Partials: {}",
+                farmer_state.queued_partials
+            ));
+        }
+        for (handler, errors) in &farmer_state.handler_errors {
+            farmer_info.push_str(&format!("\n\t  Invalid Messages ({}): {}", handler, errors));
+        }
+        for (harvester_id, delay) in &farmer_state.signing_delays {
+            farmer_info.push_str(&format!(
+                "\n\t  Signing Delay ({}): {}ms avg, {}ms max",
+                &harvester_id.to_string()[..8],
+                delay.average.as_millis(),
+                delay.max.as_millis(),
+            ));
+            if delay.queued > 0 {
+                farmer_info.push_str(&format!(
+                    ", {} queued {}ms avg",
+                    delay.queued,
+                    (delay.queue_wait / delay.queued as u32).as_millis(),
+                ));
+            }
+        }
+        if !farmer_state.signing_steps.is_empty() {
+            farmer_info.push_str("\n\t  Signing Work (avg/max):");
+            for (step, stats) in &farmer_state.signing_steps {
+                farmer_info.push_str(&format!(
+                    " {step} {:.1}/{:.1}ms",
+                    stats.average_us() as f64 / 1000.0,
+                    stats.max_us as f64 / 1000.0
+                ));
+            }
+        }
+        farmer_info
     };
 
     let mut height: u32 = 0;
@@ -301,10 +592,7 @@ fn ui(
         mempool_size = full_node.blockchain_state.mempool_size;
     }
     let formatted_timestamp: String = if timestamp != 0 {
-        let naive = NaiveDateTime::from_timestamp_opt(timestamp as i64, 0);
-        let datetime: DateTime<Utc> =
-            DateTime::from_naive_utc_and_offset(naive.unwrap_or_default(), Utc);
-        datetime.format("%d-%m-%Y %H:%M:%S").to_string()
+        format_timestamp(timestamp)
     } else {
         "N/A".to_string()
     };
@@ -330,7 +618,7 @@ fn ui(
             .title("Farmer Information: ")
             .borders(Borders::ALL),
     );
-    let title = Paragraph::new(" __            __                  \n|_   _   _ |_ |_   _   _  _   _  _ \n|   (_| _) |_ |   (_| |  ||| (- |  \n\n To Select/Copy: Hold Shift   To Quit: ESC or CTL+C")
+    let title = Paragraph::new(" __            __                  \n|_   _   _ |_ |_   _   _  _   _  _ \n|   (_| _) |_ |   (_| |  ||| (- |  \n\n To Select/Copy: Hold Shift   Pause/Resume: P   Plots: Tab   To Quit: ESC or CTL+C")
         .style(Style::default().fg(Color::Green)).block(
         Block::default(),
     ).alignment(Alignment::Center);
@@ -349,12 +637,41 @@ fn ui(
     let swap_usage_widget = draw_gauge("Swap Usage", sys_info.swap_usage);
     f.render_widget(swap_usage_widget, overview_chunks[5]);
 
+    let log_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
+        .split(wrapper_chunks[1]);
     let logs_widget = draw_logs();
-    f.render_widget(logs_widget, wrapper_chunks[1]);
+    f.render_widget(logs_widget, log_chunks[0]);
+    f.render_widget(draw_events(&recent_events), log_chunks[1]);
+}
+
+//Newest events on top so they stay visible in a small pane, errors stand out in red
+fn draw_events(events: &[FarmerEvent]) -> Paragraph<'_> {
+    let lines: Vec<Line> = events
+        .iter()
+        .rev()
+        .map(|event| {
+            let timestamp = format_timestamp(event.timestamp);
+            let style = if EventFilter::Errors.matches(&event.kind) {
+                Style::default().fg(Color::Red)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            Line::styled(format!("{timestamp} {}", event.kind), style)
+        })
+        .collect();
+    Paragraph::new(lines).block(
+        Block::default()
+            .title("Recent Events")
+            .borders(Borders::ALL),
+    )
 }
 
+//tui-logger always stamps in local time, only the format follows the config
 fn draw_logs<'a>() -> TuiLoggerWidget<'a> {
     TuiLoggerWidget::default()
+        .output_timestamp(Some(time_config().format.clone()))
         .style_error(Style::default().fg(Color::Red))
         .style_debug(Style::default().fg(Color::Green))
         .style_warn(Style::default().fg(Color::Yellow))
@@ -369,7 +686,7 @@ fn draw_logs<'a>() -> TuiLoggerWidget<'a> {
         .style(Style::default().fg(Color::White).bg(Color::Black))
 }
 
-fn draw_gauge(title: &str, value: u16) -> Gauge {
+fn draw_gauge(title: &str, value: u16) -> Gauge<'_> {
     let gauge = Gauge::default()
         .block(Block::default().title(title).borders(Borders::ALL))
         .percent(value);
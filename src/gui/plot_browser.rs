@@ -0,0 +1,315 @@
+use crate::harvesters::plot_registry::{PlotHealth, PlotRecord};
+use crossterm::event::KeyCode;
+use ratatui::{prelude::*, widgets::*};
+use std::cmp::Ordering;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+//A 20k plot snapshot is copied and sorted at most this often, not on every frame
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+const PAGE: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortColumn {
+    Plot,
+    Directory,
+    K,
+    Compression,
+    Latency,
+    Proofs,
+    Health,
+}
+impl SortColumn {
+    const ALL: [SortColumn; 7] = [
+        SortColumn::Plot,
+        SortColumn::Directory,
+        SortColumn::K,
+        SortColumn::Compression,
+        SortColumn::Latency,
+        SortColumn::Proofs,
+        SortColumn::Health,
+    ];
+    fn title(&self) -> &'static str {
+        match self {
+            SortColumn::Plot => "Plot",
+            SortColumn::Directory => "Directory",
+            SortColumn::K => "K",
+            SortColumn::Compression => "C",
+            SortColumn::Latency => "Lookup ms",
+            SortColumn::Proofs => "Proofs",
+            SortColumn::Health => "Health",
+        }
+    }
+    fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|c| c == self).unwrap_or_default();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+    fn compare(&self, a: &PlotRecord, b: &PlotRecord) -> Ordering {
+        match self {
+            SortColumn::Plot => file_name(&a.path).cmp(file_name(&b.path)),
+            SortColumn::Directory => a.dir.cmp(&b.dir),
+            SortColumn::K => a.k.cmp(&b.k),
+            SortColumn::Compression => a.compression.cmp(&b.compression),
+            //Plots that were never looked up sort as the fastest
+            SortColumn::Latency => a
+                .lookup_ms
+                .unwrap_or_default()
+                .total_cmp(&b.lookup_ms.unwrap_or_default()),
+            SortColumn::Proofs => a.proofs.cmp(&b.proofs),
+            SortColumn::Health => a.health.cmp(&b.health),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BrowserAction {
+    None,
+    Close,
+    Recheck(PathBuf),
+    ToggleExclude(PathBuf, bool),
+}
+
+pub struct PlotBrowser {
+    sort: SortColumn,
+    descending: bool,
+    search: String,
+    searching: bool,
+    selected: usize,
+    rows: Vec<PlotRecord>,
+    total: usize,
+    refreshed: Option<Instant>,
+}
+impl Default for PlotBrowser {
+    fn default() -> Self {
+        Self {
+            sort: SortColumn::Latency,
+            descending: true,
+            search: String::new(),
+            searching: false,
+            selected: 0,
+            rows: vec![],
+            total: 0,
+            refreshed: None,
+        }
+    }
+}
+impl PlotBrowser {
+    pub fn needs_refresh(&self) -> bool {
+        self.refreshed
+            .is_none_or(|refreshed| refreshed.elapsed() >= REFRESH_INTERVAL)
+    }
+    //Filters by plot id or path, then sorts by the selected column
+    pub fn refresh(&mut self, plots: Vec<PlotRecord>) {
+        self.total = plots.len();
+        let search = self.search.to_lowercase();
+        let mut rows: Vec<PlotRecord> = plots
+            .into_iter()
+            .filter(|p| {
+                search.is_empty()
+                    || p.plot_id.to_lowercase().contains(&search)
+                    || p.path.to_lowercase().contains(&search)
+            })
+            .collect();
+        rows.sort_by(|a, b| {
+            let ordering = self.sort.compare(a, b).then_with(|| a.path.cmp(&b.path));
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        self.rows = rows;
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+        self.refreshed = Some(Instant::now());
+    }
+    pub fn handle_key(&mut self, code: KeyCode) -> BrowserAction {
+        if self.searching {
+            match code {
+                KeyCode::Enter => self.searching = false,
+                KeyCode::Esc => {
+                    self.searching = false;
+                    self.search.clear();
+                }
+                KeyCode::Backspace => {
+                    self.search.pop();
+                }
+                KeyCode::Char(c) => self.search.push(c),
+                _ => return BrowserAction::None,
+            }
+            self.selected = 0;
+            self.refreshed = None;
+            return BrowserAction::None;
+        }
+        let last = self.rows.len().saturating_sub(1);
+        match code {
+            KeyCode::Esc | KeyCode::Tab => return BrowserAction::Close,
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(PAGE),
+            KeyCode::PageDown => self.selected = (self.selected + PAGE).min(last),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = last,
+            KeyCode::Char('/') => self.searching = true,
+            KeyCode::Char('s') => {
+                self.sort = self.sort.next();
+                self.refreshed = None;
+            }
+            KeyCode::Char('r') => {
+                self.descending = !self.descending;
+                self.refreshed = None;
+            }
+            KeyCode::Char('c') => {
+                if let Some(plot) = self.rows.get(self.selected) {
+                    return BrowserAction::Recheck(PathBuf::from(&plot.path));
+                }
+            }
+            KeyCode::Char('x') => {
+                if let Some(plot) = self.rows.get(self.selected) {
+                    self.refreshed = None;
+                    return BrowserAction::ToggleExclude(
+                        PathBuf::from(&plot.path),
+                        plot.health != PlotHealth::Excluded,
+                    );
+                }
+            }
+            _ => {}
+        }
+        BrowserAction::None
+    }
+    pub fn draw(&self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(3)].as_ref())
+            .split(area);
+        //Only the visible rows are built, the selection is kept on screen
+        let visible = chunks[0].height.saturating_sub(3).max(1) as usize;
+        let offset = self.selected.saturating_sub(visible - 1);
+        let rows: Vec<Row> = self
+            .rows
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(visible)
+            .map(|(index, plot)| {
+                let style = if index == self.selected {
+                    Style::default().fg(Color::Black).bg(Color::Gray)
+                } else {
+                    match plot.health {
+                        PlotHealth::Failed | PlotHealth::Unreadable => {
+                            Style::default().fg(Color::Red)
+                        }
+                        PlotHealth::Recovering => Style::default().fg(Color::Yellow),
+                        PlotHealth::Excluded => Style::default().fg(Color::DarkGray),
+                        _ => Style::default().fg(Color::White),
+                    }
+                };
+                Row::new(vec![
+                    file_name(&plot.path).to_string(),
+                    plot.dir.clone(),
+                    plot.k.to_string(),
+                    plot.compression.to_string(),
+                    plot.lookup_ms
+                        .map(|ms| format!("{ms:.0}"))
+                        .unwrap_or_else(|| "-".to_string()),
+                    plot.proofs.to_string(),
+                    match &plot.error {
+                        Some(error) => format!("{} ({error})", plot.health),
+                        None => plot.health.to_string(),
+                    },
+                ])
+                .style(style)
+            })
+            .collect();
+        let header = Row::new(SortColumn::ALL.iter().map(|column| {
+            if *column == self.sort {
+                format!(
+                    "{}{}",
+                    column.title(),
+                    if self.descending { "▼" } else { "▲" }
+                )
+            } else {
+                column.title().to_string()
+            }
+        }))
+        .style(Style::default().fg(Color::Green));
+        let table = Table::new(rows)
+            .header(header)
+            .widths(&[
+                Constraint::Percentage(30),
+                Constraint::Percentage(25),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(10),
+                Constraint::Length(7),
+                Constraint::Min(10),
+            ])
+            .block(
+                Block::default()
+                    .title(format!("Plots {}/{}", self.rows.len(), self.total))
+                    .borders(Borders::ALL),
+            );
+        f.render_widget(table, chunks[0]);
+        let search = if self.searching {
+            format!("Search: {}_", self.search)
+        } else if !self.search.is_empty() {
+            format!("Search: {}", self.search)
+        } else {
+            String::new()
+        };
+        let help = Paragraph::new(format!(
+            "{search}  Move: ↑↓ PgUp PgDn   Sort: S   Reverse: R   Search: /   Re-check: C   Exclude: X   Back: Tab or ESC"
+        ))
+        .block(Block::default().borders(Borders::ALL));
+        f.render_widget(help, chunks[1]);
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plot(path: &str, lookup_ms: Option<f64>) -> PlotRecord {
+        PlotRecord {
+            path: path.to_string(),
+            dir: "/mnt/a".to_string(),
+            plot_id: format!("id-{path}"),
+            k: 32,
+            compression: 0,
+            lookup_ms,
+            proofs: 0,
+            health: PlotHealth::Unchecked,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn filters_sorts_and_acts_on_the_selection() {
+        let mut browser = PlotBrowser::default();
+        let plots = vec![
+            plot("/mnt/a/fast.plot", Some(20.0)),
+            plot("/mnt/a/slow.plot", Some(900.0)),
+            plot("/mnt/b/new.plot", None),
+        ];
+        browser.refresh(plots.clone());
+        assert_eq!(browser.rows[0].path, "/mnt/a/slow.plot");
+        browser.handle_key(KeyCode::Char('r'));
+        browser.refresh(plots.clone());
+        assert_eq!(browser.rows[0].path, "/mnt/b/new.plot");
+        for c in "/fast".chars() {
+            browser.handle_key(KeyCode::Char(c));
+        }
+        browser.handle_key(KeyCode::Enter);
+        assert!(browser.needs_refresh());
+        browser.refresh(plots);
+        assert_eq!(browser.rows.len(), 1);
+        assert_eq!(
+            browser.handle_key(KeyCode::Char('x')),
+            BrowserAction::ToggleExclude(PathBuf::from("/mnt/a/fast.plot"), true)
+        );
+        assert_eq!(browser.handle_key(KeyCode::Esc), BrowserAction::Close);
+    }
+}
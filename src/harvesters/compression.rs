@@ -0,0 +1,112 @@
+use crate::farmer::{PathInfo, PlotInfo};
+use log::{debug, info, warn};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+//Chia's harvester warns when a lookup takes longer than this, use the same budget for bursts
+const SP_LOOKUP_BUDGET_SECS: f64 = 5.0;
+const HEADROOM_WARNING_INTERVAL: Duration = Duration::from_secs(1800);
+const LOOKUP_EWMA_WEIGHT: f64 = 0.1;
+
+#[derive(Debug, Default, Clone)]
+pub struct CompressionLevelStats {
+    pub plot_count: u64,
+    pub lookups: u64,
+    pub avg_lookup_secs: f64,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SpCostEstimate {
+    pub expected_secs: f64,
+    pub worst_case_secs: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct CompressionStats {
+    pub levels: BTreeMap<u8, CompressionLevelStats>,
+    last_headroom_warning: Option<Instant>,
+}
+impl CompressionStats {
+    pub fn update_plot_counts(&mut self, plots: &HashMap<PathInfo, Arc<PlotInfo>>) {
+        let mut counts: BTreeMap<u8, u64> = BTreeMap::new();
+        for info in plots.values() {
+            *counts.entry(info.reader.compression_level()).or_default() += 1;
+        }
+        let previous: BTreeMap<u8, u64> = self
+            .levels
+            .iter()
+            .filter(|(_, l)| l.plot_count > 0)
+            .map(|(c, l)| (*c, l.plot_count))
+            .collect();
+        for level in self.levels.values_mut() {
+            level.plot_count = 0;
+        }
+        for (c_level, count) in &counts {
+            self.levels.entry(*c_level).or_default().plot_count = *count;
+        }
+        if previous != counts {
+            info!("Plot Compression Levels: {}", self.distribution_string());
+        }
+    }
+
+    pub fn record_lookup(&mut self, c_level: u8, duration: Duration) {
+        let level = self.levels.entry(c_level).or_default();
+        let secs = duration.as_secs_f64();
+        if level.lookups == 0 {
+            level.avg_lookup_secs = secs;
+        } else {
+            level.avg_lookup_secs =
+                LOOKUP_EWMA_WEIGHT * secs + (1.0 - LOOKUP_EWMA_WEIGHT) * level.avg_lookup_secs;
+        }
+        level.lookups += 1;
+    }
+
+    pub fn distribution_string(&self) -> String {
+        self.levels
+            .iter()
+            .filter(|(_, l)| l.plot_count > 0)
+            .map(|(c, l)| format!("C{c}: {}", l.plot_count))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    //Worst case assumes an eligible count 3 standard deviations above the expected count
+    pub fn estimate_sp_cost(&self, plot_filter_bits: usize, threads: usize) -> SpCostEstimate {
+        let filter = 2f64.powi(plot_filter_bits as i32);
+        let threads = threads.max(1) as f64;
+        let mut estimate = SpCostEstimate::default();
+        for (_, level) in self.levels.iter().filter(|(c, _)| **c > 0) {
+            if level.lookups == 0 || level.plot_count == 0 {
+                continue;
+            }
+            let expected = level.plot_count as f64 / filter;
+            let worst_case = (expected + 3.0 * expected.sqrt()).ceil();
+            estimate.expected_secs += expected * level.avg_lookup_secs / threads;
+            estimate.worst_case_secs += worst_case * level.avg_lookup_secs / threads;
+        }
+        estimate
+    }
+
+    pub fn check_headroom(&mut self, plot_filter_bits: usize, threads: usize) {
+        let estimate = self.estimate_sp_cost(plot_filter_bits, threads);
+        debug!(
+            "Estimated Decompression Cost per SP: {:.3}s expected, {:.3}s worst case on {threads} threads",
+            estimate.expected_secs, estimate.worst_case_secs
+        );
+        if estimate.worst_case_secs > SP_LOOKUP_BUDGET_SECS
+            && self
+                .last_headroom_warning
+                .map(|i| i.elapsed() > HEADROOM_WARNING_INTERVAL)
+                .unwrap_or(true)
+        {
+            self.last_headroom_warning = Some(Instant::now());
+            warn!(
+                "Insufficient CPU headroom for compressed plots: worst case eligible burst needs {:.2}s of decompression (expected {:.2}s) on {threads} threads, budget is {SP_LOOKUP_BUDGET_SECS}s. Compression Levels: {}",
+                estimate.worst_case_secs,
+                estimate.expected_secs,
+                self.distribution_string()
+            );
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use crate::farmer::config::LookupConcurrencyConfig;
+use log::{debug, info};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+//Weight of the newest signage point in the long term lookup latency
+const BASELINE_SMOOTHING: f64 = 0.05;
+//Weight of the newly computed limit against the current one
+const LIMIT_SMOOTHING: f64 = 0.2;
+//Lookups slower than the baseline by more than this factor halve the limit at most
+const MIN_GRADIENT: f64 = 0.5;
+//Gradient used while the system reports IO or memory pressure above the configured share
+const PRESSURE_GRADIENT: f64 = 0.75;
+
+#[derive(Debug, Default)]
+struct LimiterState {
+    limit: f64,
+    permits: usize,
+    baseline_ms: Option<f64>,
+    lookups: u64,
+    lookup_ms: f64,
+    queued: u64,
+}
+
+//Plot lookups allowed to run at once. After every signage point the limit follows the ratio of
+//the long term lookup latency to the current one, lookups getting slower mean the disks are
+//overloaded and the limit shrinks. Lookups that had to wait for a permit grow it by its square
+//root, twice as fast when the signage point missed its target time
+pub struct LookupLimiter {
+    config: LookupConcurrencyConfig,
+    semaphore: Arc<Semaphore>,
+    state: Mutex<LimiterState>,
+    //Permits dropped as they come back after the limit shrank
+    shrink: AtomicUsize,
+}
+impl LookupLimiter {
+    pub fn new(config: LookupConcurrencyConfig) -> Self {
+        let max = config.max.max(config.min).max(1);
+        Self {
+            semaphore: Arc::new(Semaphore::new(max)),
+            state: Mutex::new(LimiterState {
+                limit: max as f64,
+                permits: max,
+                ..Default::default()
+            }),
+            shrink: AtomicUsize::new(0),
+            config,
+        }
+    }
+    //None when the limit is fixed, the lookup then runs right away
+    pub async fn acquire(self: &Arc<Self>) -> Option<LookupPermit> {
+        if !self.config.adaptive {
+            return None;
+        }
+        let permit = match self.semaphore.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                if let Ok(mut state) = self.state.lock() {
+                    state.queued += 1;
+                }
+                self.semaphore.clone().acquire_owned().await.ok()?
+            }
+        };
+        Some(LookupPermit {
+            permit: Some(permit),
+            limiter: self.clone(),
+            started: Instant::now(),
+        })
+    }
+    fn finish_lookup(&self, duration: Duration) {
+        if let Ok(mut state) = self.state.lock() {
+            state.lookups += 1;
+            state.lookup_ms += duration.as_secs_f64() * 1000.0;
+        }
+    }
+    fn take_shrink(&self) -> bool {
+        self.shrink
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok()
+    }
+    //Called once all lookups of a signage point are done or cancelled
+    pub fn adjust(&self, completion: Duration) {
+        if !self.config.adaptive {
+            return;
+        }
+        let pressure = read_pressure();
+        let pressured = pressure.io > self.config.io_pressure as f64
+            || pressure.memory > self.config.memory_pressure as f64;
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if state.lookups == 0 {
+            return;
+        }
+        let average_ms = state.lookup_ms / state.lookups as f64;
+        let baseline_ms = match state.baseline_ms {
+            Some(baseline) => baseline + BASELINE_SMOOTHING * (average_ms - baseline),
+            None => average_ms,
+        };
+        let limit = next_limit(
+            state.limit,
+            baseline_ms / average_ms.max(f64::EPSILON),
+            state.queued > 0,
+            completion > Duration::from_millis(self.config.target_ms),
+            pressured,
+        )
+        .clamp(self.config.min.max(1) as f64, self.config.max.max(1) as f64);
+        let permits = limit.round() as usize;
+        if permits > state.permits {
+            let mut grow = permits - state.permits;
+            while grow > 0 && self.take_shrink() {
+                grow -= 1;
+            }
+            self.semaphore.add_permits(grow);
+        } else {
+            self.shrink
+                .fetch_add(state.permits - permits, Ordering::Relaxed);
+        }
+        let log_line = format!(
+            "Lookup concurrency {} -> {permits}, lookup {average_ms:.0}ms (baseline {baseline_ms:.0}ms), signage point {}ms, {} queued, io pressure {:.1}%, memory pressure {:.1}%",
+            state.permits,
+            completion.as_millis(),
+            state.queued,
+            pressure.io,
+            pressure.memory
+        );
+        //Small steps happen every signage point, only larger changes are worth an info line
+        if permits.abs_diff(state.permits) * 4 >= state.permits.max(4) {
+            info!("{log_line}");
+        } else {
+            debug!("{log_line}");
+        }
+        state.limit = limit;
+        state.permits = permits;
+        state.baseline_ms = Some(baseline_ms);
+        state.lookups = 0;
+        state.lookup_ms = 0.0;
+        state.queued = 0;
+    }
+}
+
+pub struct LookupPermit {
+    permit: Option<OwnedSemaphorePermit>,
+    limiter: Arc<LookupLimiter>,
+    started: Instant,
+}
+impl Drop for LookupPermit {
+    fn drop(&mut self) {
+        self.limiter.finish_lookup(self.started.elapsed());
+        if let Some(permit) = self.permit.take() {
+            if self.limiter.take_shrink() {
+                permit.forget();
+            }
+        }
+    }
+}
+
+fn next_limit(
+    limit: f64,
+    gradient: f64,
+    queued: bool,
+    missed_target: bool,
+    pressured: bool,
+) -> f64 {
+    let mut gradient = gradient.clamp(MIN_GRADIENT, 1.0);
+    if pressured {
+        gradient = gradient.min(PRESSURE_GRADIENT);
+    }
+    let allowance = match (queued && !pressured, missed_target) {
+        (false, _) => 0.0,
+        (true, false) => limit.sqrt(),
+        (true, true) => 2.0 * limit.sqrt(),
+    };
+    limit * (1.0 - LIMIT_SMOOTHING) + (limit * gradient + allowance) * LIMIT_SMOOTHING
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Pressure {
+    io: f64,
+    memory: f64,
+}
+
+//Share of the last 10 seconds some task stalled on IO or memory, zero where PSI is not available
+fn read_pressure() -> Pressure {
+    Pressure {
+        io: read_psi("/proc/pressure/io").unwrap_or_default(),
+        memory: read_psi("/proc/pressure/memory").unwrap_or_default(),
+    }
+}
+
+fn read_psi(path: &str) -> Option<f64> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    parse_psi(&std::fs::read_to_string(path).ok()?)
+}
+
+fn parse_psi(content: &str) -> Option<f64> {
+    content
+        .lines()
+        .find(|line| line.starts_with("some "))?
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("avg10="))?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_follows_latency_queueing_and_pressure() {
+        assert_eq!(
+            parse_psi("some avg10=12.50 avg60=3.00 avg300=1.00 total=1\nfull avg10=1.00"),
+            Some(12.5)
+        );
+        //Slower lookups than the baseline shrink the limit, unchanged latency with a queue grows it
+        assert!(next_limit(64.0, 0.5, true, false, false) < 64.0);
+        assert!(next_limit(64.0, 1.0, true, false, false) > 64.0);
+        assert!(
+            next_limit(64.0, 1.0, true, true, false) > next_limit(64.0, 1.0, true, false, false)
+        );
+        assert_eq!(next_limit(64.0, 1.0, false, true, false), 64.0);
+        assert!(next_limit(64.0, 1.0, true, true, true) < 64.0);
+    }
+
+    #[tokio::test]
+    async fn shrinking_drops_returned_permits() {
+        let limiter = Arc::new(LookupLimiter::new(LookupConcurrencyConfig {
+            min: 1,
+            max: 2,
+            ..Default::default()
+        }));
+        let first = limiter.acquire().await.unwrap();
+        let second = limiter.acquire().await.unwrap();
+        limiter.shrink.store(1, Ordering::Relaxed);
+        drop(first);
+        drop(second);
+        assert_eq!(limiter.semaphore.available_permits(), 1);
+    }
+}
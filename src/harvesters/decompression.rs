@@ -0,0 +1,123 @@
+use crate::farmer::config::{DecompressionBackend, DecompressionConfig};
+use dg_xch_pos::plots::decompressor::DecompressorPool;
+use log::{info, warn};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::thread::available_parallelism;
+
+impl Display for DecompressionBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DecompressionBackend::Cpu => "cpu",
+        })
+    }
+}
+
+//Decompressor a harvester's plot readers share, with the threads used for the headroom estimate
+pub struct ActiveDecompressor {
+    pub backend: DecompressionBackend,
+    pub pool: Arc<DecompressorPool>,
+    pub threads: usize,
+}
+
+pub trait DecompressionProvider: Send + Sync {
+    fn backend(&self) -> DecompressionBackend;
+    //Probes the driver and devices the backend needs, Err names what is missing on this host
+    fn start(&self) -> Result<ActiveDecompressor, String>;
+}
+
+pub struct CpuProvider {
+    pub threads: usize,
+}
+impl CpuProvider {
+    fn decompressor(&self) -> ActiveDecompressor {
+        let threads = match self.threads {
+            0 => available_parallelism().map(|u| u.get()).unwrap_or(4),
+            threads => threads,
+        }
+        .min(u8::MAX as usize);
+        ActiveDecompressor {
+            backend: DecompressionBackend::Cpu,
+            pool: Arc::new(DecompressorPool::new(1, threads as u8)),
+            threads,
+        }
+    }
+}
+impl DecompressionProvider for CpuProvider {
+    fn backend(&self) -> DecompressionBackend {
+        DecompressionBackend::Cpu
+    }
+    fn start(&self) -> Result<ActiveDecompressor, String> {
+        Ok(self.decompressor())
+    }
+}
+
+pub fn providers(config: &DecompressionConfig) -> Vec<Box<dyn DecompressionProvider>> {
+    config
+        .providers
+        .iter()
+        .map(|backend| -> Box<dyn DecompressionProvider> {
+            match backend {
+                DecompressionBackend::Cpu => Box::new(CpuProvider {
+                    threads: config.threads,
+                }),
+            }
+        })
+        .collect()
+}
+
+//Starts the first provider that works, falling back to the CPU when none does
+pub fn select_decompressor(
+    providers: Vec<Box<dyn DecompressionProvider>>,
+    threads: usize,
+) -> ActiveDecompressor {
+    for provider in providers {
+        match provider.start() {
+            Ok(active) => {
+                info!(
+                    "Decompressing compressed plots with {} on {} threads",
+                    active.backend, active.threads
+                );
+                return active;
+            }
+            Err(e) => warn!(
+                "Decompression provider {} unavailable: {e}",
+                provider.backend()
+            ),
+        }
+    }
+    let active = CpuProvider { threads }.decompressor();
+    info!(
+        "Falling back to decompressing compressed plots on {} CPU threads",
+        active.threads
+    );
+    active
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_the_cpu() {
+        struct Broken;
+        impl DecompressionProvider for Broken {
+            fn backend(&self) -> DecompressionBackend {
+                DecompressionBackend::Cpu
+            }
+            fn start(&self) -> Result<ActiveDecompressor, String> {
+                Err("no device".to_string())
+            }
+        }
+        let active = select_decompressor(vec![Box::new(Broken)], 2);
+        assert_eq!(active.backend, DecompressionBackend::Cpu);
+        assert_eq!(active.threads, 2);
+        let config = DecompressionConfig {
+            providers: vec![DecompressionBackend::Cpu],
+            threads: 3,
+        };
+        let providers = providers(&config);
+        assert_eq!(providers[0].backend(), DecompressionBackend::Cpu);
+        assert_eq!(select_decompressor(providers, 1).threads, 3);
+    }
+}
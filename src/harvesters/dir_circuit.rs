@@ -0,0 +1,81 @@
+use crate::farmer::config::DirCircuitConfig;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+#[derive(Debug, Default)]
+struct DirState {
+    consecutive_timeouts: u32,
+    open_until: Option<Instant>,
+    probing: bool,
+}
+
+//Skips plot directories whose lookups keep timing out so a stalled mount does not hold up every signage point
+#[derive(Debug)]
+pub struct DirCircuits {
+    config: DirCircuitConfig,
+    dirs: Mutex<HashMap<PathBuf, DirState>>,
+}
+impl DirCircuits {
+    pub fn new(config: DirCircuitConfig) -> Self {
+        Self {
+            config,
+            dirs: Default::default(),
+        }
+    }
+    pub fn lookup_timeout(&self) -> Duration {
+        Duration::from_secs(self.config.lookup_timeout)
+    }
+    pub async fn is_open(&self, dir: &Path) -> bool {
+        self.dirs
+            .lock()
+            .await
+            .get(dir)
+            .and_then(|s| s.open_until)
+            .map(|t| t > Instant::now())
+            .unwrap_or(false)
+    }
+    //False while the directory is skipped, once the skip ran out the next lookups probe whether it recovered
+    pub async fn allow(&self, dir: &Path) -> bool {
+        let mut dirs = self.dirs.lock().await;
+        let Some(state) = dirs.get_mut(dir) else {
+            return true;
+        };
+        match state.open_until {
+            Some(until) if until > Instant::now() => false,
+            Some(_) => {
+                info!("Probing plot directory {:?}", dir);
+                state.open_until = None;
+                state.probing = true;
+                true
+            }
+            None => true,
+        }
+    }
+    pub async fn record(&self, dir: &Path, timed_out: bool) {
+        let mut dirs = self.dirs.lock().await;
+        let state = dirs.entry(dir.to_path_buf()).or_default();
+        if !timed_out {
+            if state.probing {
+                info!("Plot directory {:?} recovered", dir);
+            }
+            *state = DirState::default();
+            return;
+        }
+        state.consecutive_timeouts += 1;
+        if self.config.failure_threshold == 0 {
+            return;
+        }
+        if state.probing || state.consecutive_timeouts >= self.config.failure_threshold {
+            warn!(
+                "Lookups in plot directory {:?} timed out {} times in a row, skipping it for {} seconds",
+                dir, state.consecutive_timeouts, self.config.skip_duration
+            );
+            state.open_until =
+                Some(Instant::now() + Duration::from_secs(self.config.skip_duration));
+            state.probing = false;
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::harvesters::drive_health::DriveHealth;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+//Weight of the newest signage point in the moving average
+const SMOOTHING: f64 = 0.2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirLatency {
+    pub dir: String,
+    pub average_ms: f64,
+    pub signage_points: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drive: Option<DriveHealth>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DirState {
+    average_ms: f64,
+    signage_points: u64,
+}
+
+//Learned lookup latency per plot directory, lookups are started fastest directory first so proofs
+//from fast storage are not queued behind a slow shelf. Directories without history go first to get measured
+#[derive(Debug, Default)]
+pub struct DirLatencies {
+    dirs: Mutex<HashMap<PathBuf, DirState>>,
+}
+impl DirLatencies {
+    //Slowest lookup of the directory for one signage point, timeouts count as the full timeout
+    pub async fn record(&self, dir: &Path, latency: Duration) {
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        let mut dirs = self.dirs.lock().await;
+        let state = dirs.entry(dir.to_path_buf()).or_default();
+        state.average_ms = if state.signage_points == 0 {
+            latency_ms
+        } else {
+            state.average_ms + SMOOTHING * (latency_ms - state.average_ms)
+        };
+        state.signage_points += 1;
+    }
+    //Sort key for a directory, lower is queried earlier
+    pub async fn ranks(&self) -> HashMap<PathBuf, u64> {
+        self.dirs
+            .lock()
+            .await
+            .iter()
+            .map(|(dir, state)| (dir.clone(), (state.average_ms * 1000.0) as u64))
+            .collect()
+    }
+    //The learned lookup order, fastest first
+    pub async fn snapshot(&self) -> Vec<DirLatency> {
+        let mut latencies: Vec<DirLatency> = self
+            .dirs
+            .lock()
+            .await
+            .iter()
+            .map(|(dir, state)| DirLatency {
+                dir: dir.to_string_lossy().to_string(),
+                average_ms: state.average_ms,
+                signage_points: state.signage_points,
+                drive: None,
+            })
+            .collect();
+        latencies.sort_by(|a, b| a.average_ms.total_cmp(&b.average_ms));
+        latencies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn orders_directories_by_average_latency() {
+        let latencies = DirLatencies::default();
+        let (usb, nvme) = (Path::new("/mnt/usb"), Path::new("/mnt/nvme"));
+        latencies.record(usb, Duration::from_millis(900)).await;
+        latencies.record(nvme, Duration::from_millis(400)).await;
+        latencies.record(nvme, Duration::from_millis(20)).await;
+        let snapshot = latencies.snapshot().await;
+        assert_eq!(snapshot[0].dir, "/mnt/nvme");
+        assert_eq!(snapshot[0].signage_points, 2);
+        assert!((snapshot[0].average_ms - 324.0).abs() < 0.001);
+        let ranks = latencies.ranks().await;
+        assert!(ranks[nvme] < ranks[usb]);
+        assert!(!ranks.contains_key(Path::new("/mnt/new")));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::farmer::config::DriveHealthConfig;
+use crate::harvesters::dir_latency::DirLatency;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+
+const SMARTCTL_TIMEOUT: Duration = Duration::from_secs(30);
+//SMART attribute ids of ATA drives
+const REALLOCATED_SECTORS: u64 = 5;
+const PENDING_SECTORS: u64 = 197;
+const UNCORRECTABLE_SECTORS: u64 = 198;
+
+//Last SMART reading of the drive backing a plot directory. Drives that cannot be read, like
+//network mounts or a smartctl without root, only carry the error
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DriveHealth {
+    pub device: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature_c: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smart_passed: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reallocated_sectors: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_sectors: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_errors: Option<u64>,
+    //Reasons the drive looks unhealthy, empty for a healthy drive
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub checked_at: u64,
+}
+
+//Reads the smartctl --json output of ATA, SCSI and NVMe drives
+pub fn parse_smartctl(device: &str, output: &Value, max_temperature: i64) -> DriveHealth {
+    let ata_raw = |id: u64| {
+        output["ata_smart_attributes"]["table"]
+            .as_array()?
+            .iter()
+            .find(|a| a["id"].as_u64() == Some(id))?["raw"]["value"]
+            .as_u64()
+    };
+    let nvme = &output["nvme_smart_health_information_log"];
+    let mut health = DriveHealth {
+        device: device.to_string(),
+        temperature_c: output["temperature"]["current"].as_i64(),
+        smart_passed: output["smart_status"]["passed"].as_bool(),
+        reallocated_sectors: ata_raw(REALLOCATED_SECTORS)
+            .or_else(|| output["scsi_grown_defect_list"].as_u64()),
+        pending_sectors: match (ata_raw(PENDING_SECTORS), ata_raw(UNCORRECTABLE_SECTORS)) {
+            (None, None) => None,
+            (pending, uncorrectable) => Some(pending.unwrap_or(0) + uncorrectable.unwrap_or(0)),
+        },
+        media_errors: nvme["media_errors"].as_u64(),
+        ..Default::default()
+    };
+    if health.smart_passed == Some(false) {
+        health
+            .flags
+            .push("SMART self-assessment failed".to_string());
+    }
+    if let Some(temperature) = health.temperature_c.filter(|t| *t >= max_temperature) {
+        health.flags.push(format!("{temperature}°C"));
+    }
+    for (count, what) in [
+        (health.reallocated_sectors, "reallocated sectors"),
+        (health.pending_sectors, "pending sectors"),
+        (health.media_errors, "media errors"),
+    ] {
+        if let Some(count) = count.filter(|c| *c > 0) {
+            health.flags.push(format!("{count} {what}"));
+        }
+    }
+    if nvme["critical_warning"].as_u64().is_some_and(|w| w > 0) {
+        health.flags.push("NVMe critical warning".to_string());
+    }
+    health
+}
+
+pub async fn read_drive_health(config: &DriveHealthConfig, device: &str) -> DriveHealth {
+    let failed = |error: String| DriveHealth {
+        device: device.to_string(),
+        error: Some(error),
+        ..Default::default()
+    };
+    let output = Command::new(&config.smartctl)
+        .args(["--json", "--all", &format!("/dev/{device}")])
+        .kill_on_drop(true)
+        .output();
+    let output = match timeout(SMARTCTL_TIMEOUT, output).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return failed(format!("failed to run {}: {e}", config.smartctl)),
+        Err(_) => return failed(format!("{} timed out", config.smartctl)),
+    };
+    //The exit code is a bit mask that is also set for failing drives, the json tells whether the
+    //device could be read at all
+    let json: Value = match serde_json::from_slice(&output.stdout) {
+        Ok(json) => json,
+        Err(e) => return failed(format!("invalid smartctl output: {e}")),
+    };
+    if json["device"].is_null() {
+        let message = json["smartctl"]["messages"][0]["string"]
+            .as_str()
+            .unwrap_or("device could not be opened");
+        return failed(message.to_string());
+    }
+    parse_smartctl(device, &json, config.max_temperature)
+}
+
+//Drive health per plot directory, directories on the same drive share one reading
+#[derive(Debug, Default)]
+pub struct DriveHealths {
+    dirs: Mutex<HashMap<PathBuf, DriveHealth>>,
+}
+impl DriveHealths {
+    pub async fn set(&self, dir: &Path, health: DriveHealth) {
+        self.dirs.lock().await.insert(dir.to_path_buf(), health);
+    }
+    pub async fn get(&self, dir: &Path) -> Option<DriveHealth> {
+        self.dirs.lock().await.get(dir).cloned()
+    }
+    //Adds the drive health to the latency of each directory, directories without lookups yet are
+    //listed after the measured ones
+    pub async fn attach(&self, mut latencies: Vec<DirLatency>) -> Vec<DirLatency> {
+        let dirs = self.dirs.lock().await;
+        for latency in &mut latencies {
+            latency.drive = dirs.get(Path::new(&latency.dir)).cloned();
+        }
+        let mut unmeasured: Vec<DirLatency> = dirs
+            .iter()
+            .map(|(dir, health)| DirLatency {
+                dir: dir.to_string_lossy().to_string(),
+                average_ms: 0.0,
+                signage_points: 0,
+                drive: Some(health.clone()),
+            })
+            .filter(|d| !latencies.iter().any(|l| l.dir == d.dir))
+            .collect();
+        unmeasured.sort_by(|a, b| a.dir.cmp(&b.dir));
+        latencies.extend(unmeasured);
+        latencies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_failing_and_hot_drives() {
+        let ata = serde_json::json!({
+            "device": {"name": "/dev/sdb"},
+            "smart_status": {"passed": true},
+            "temperature": {"current": 58},
+            "ata_smart_attributes": {"table": [
+                {"id": 5, "raw": {"value": 8}},
+                {"id": 197, "raw": {"value": 2}},
+                {"id": 198, "raw": {"value": 1}}
+            ]}
+        });
+        let health = parse_smartctl("sdb", &ata, 55);
+        assert_eq!(health.temperature_c, Some(58));
+        assert_eq!(health.pending_sectors, Some(3));
+        assert_eq!(
+            health.flags,
+            vec!["58°C", "8 reallocated sectors", "3 pending sectors"]
+        );
+        let nvme = serde_json::json!({
+            "device": {"name": "/dev/nvme0"},
+            "smart_status": {"passed": true},
+            "temperature": {"current": 40},
+            "nvme_smart_health_information_log": {"critical_warning": 0, "media_errors": 0}
+        });
+        let health = parse_smartctl("nvme0n1", &nvme, 55);
+        assert_eq!(health.media_errors, Some(0));
+        assert_eq!(health.reallocated_sectors, None);
+        assert!(health.flags.is_empty());
+    }
+}
@@ -0,0 +1,81 @@
+use crate::platform;
+use log::{debug, info};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+#[derive(Default)]
+struct Drives {
+    by_dir: HashMap<PathBuf, Arc<Semaphore>>,
+    by_drive: HashMap<String, Arc<Semaphore>>,
+}
+
+//Each physical drive gets its own few lookup workers and queue. A drive with many eligible plots
+//only waits on itself, lookups on the other drives of a JBOD still start right away
+pub struct DriveWorkers {
+    workers: usize,
+    drives: Mutex<Drives>,
+}
+impl DriveWorkers {
+    pub fn new(workers: usize) -> Self {
+        if workers > 0 {
+            info!("Running {workers} lookup workers per drive");
+        }
+        Self {
+            workers,
+            drives: Default::default(),
+        }
+    }
+    //None with per drive workers disabled, the lookup then only waits on the shared limit
+    pub async fn acquire(&self, dir: &Path) -> Option<OwnedSemaphorePermit> {
+        if self.workers == 0 {
+            return None;
+        }
+        let semaphore = self.semaphore(dir)?;
+        semaphore.acquire_owned().await.ok()
+    }
+    fn semaphore(&self, dir: &Path) -> Option<Arc<Semaphore>> {
+        let mut drives = self.drives.lock().ok()?;
+        if let Some(semaphore) = drives.by_dir.get(dir) {
+            return Some(semaphore.clone());
+        }
+        let drive = drive_id(dir);
+        debug!("Plot directory {:?} is on drive {drive}", dir);
+        let workers = self.workers;
+        let semaphore = drives
+            .by_drive
+            .entry(drive)
+            .or_insert_with(|| Arc::new(Semaphore::new(workers)))
+            .clone();
+        drives.by_dir.insert(dir.to_path_buf(), semaphore.clone());
+        Some(semaphore)
+    }
+}
+
+//The block device or Windows volume a directory is mounted from. Object stores and directories
+//that can not be read fall back to the directory itself
+pub fn drive_id(dir: &Path) -> String {
+    platform::drive_id(dir).unwrap_or_else(|| dir.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn directories_on_one_drive_share_workers() {
+        let workers = DriveWorkers::new(1);
+        let dir = std::env::temp_dir();
+        let first = workers.acquire(&dir).await.unwrap();
+        let nested = dir.join(format!("ff_drive_test_{}", std::process::id()));
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(drive_id(&dir), drive_id(&nested));
+        assert!(workers.semaphore(&nested).unwrap().try_acquire().is_err());
+        drop(first);
+        assert!(workers.acquire(&nested).await.is_some());
+        assert_eq!(workers.drives.lock().unwrap().by_drive.len(), 1);
+        assert!(DriveWorkers::new(0).acquire(&dir).await.is_none());
+        std::fs::remove_dir_all(nested).unwrap();
+    }
+}
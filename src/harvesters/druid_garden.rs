@@ -1,4 +1,35 @@
-use crate::farmer::{PathInfo, PlotInfo};
+use crate::farmer::chaos;
+use crate::farmer::config::BladebitHarvesterConfig;
+use crate::farmer::events::{FarmerEvent, FarmerEventKind};
+use crate::farmer::idle::IdleState;
+use crate::farmer::latency_regression::{LatencyBaselines, LatencyKind};
+use crate::farmer::partial_queue::now;
+use crate::farmer::plot_filter::{PlotFilter, PlotFilterSchedule};
+use crate::farmer::sp_digest::SpDigests;
+use crate::farmer::sp_tag::SpArrival;
+use crate::farmer::{EligibilityStats, PathInfo, PlotInfo};
+use crate::get_plot_audit_path;
+use crate::harvesters::compression::CompressionStats;
+use crate::harvesters::concurrency::LookupLimiter;
+use crate::harvesters::decompression::{providers, select_decompressor};
+use crate::harvesters::dir_circuit::DirCircuits;
+use crate::harvesters::dir_latency::DirLatencies;
+use crate::harvesters::drive_workers::DriveWorkers;
+use crate::harvesters::numa::NumaRuntimes;
+use crate::harvesters::plot_aliases::{plot_id, split_aliases};
+use crate::harvesters::plot_audit::plot_auditor;
+use crate::harvesters::plot_classification::{
+    classify_plot, PlotKind, UnfarmablePlot, UnfarmablePlots, UnfarmableReason,
+};
+use crate::harvesters::plot_dirs::plot_dirs;
+use crate::harvesters::plot_io::{IoPlot, PlotIoConfig};
+use crate::harvesters::plot_io_config;
+use crate::harvesters::plot_recovery::{is_io_failure, plot_recoverer, PlotRecoveries};
+use crate::harvesters::plot_registry::PlotRegistry;
+use crate::harvesters::plot_scan::PlotScanFilters;
+use crate::harvesters::sp_lookups::{superseded, SpLookups};
+use crate::harvesters::sp_stagger::SpStagger;
+use crate::harvesters::stats::{HarvesterStatsRegistry, PlotTotals};
 use crate::harvesters::{Harvester, ProofHandler, SignatureHandler};
 use async_trait::async_trait;
 use blst::min_pk::{PublicKey, SecretKey};
@@ -6,18 +37,18 @@ use dg_xch_clients::protocols::harvester::{
     NewProofOfSpace, NewSignagePointHarvester, RequestSignatures, RespondSignatures,
 };
 use dg_xch_core::blockchain::proof_of_space::{
-    calculate_pos_challenge, generate_plot_public_key, passes_plot_filter, ProofBytes, ProofOfSpace,
+    calculate_plot_filter_input, calculate_pos_challenge, generate_plot_public_key, ProofBytes,
+    ProofOfSpace,
 };
 use dg_xch_core::blockchain::sized_bytes::{Bytes32, Bytes48};
 use dg_xch_core::clvm::bls_bindings::sign_prepend;
-use dg_xch_core::consensus::constants::CONSENSUS_CONSTANTS_MAP;
+use dg_xch_core::consensus::constants::ConsensusConstants;
 use dg_xch_core::consensus::pot_iterations::{
     calculate_iterations_quality, calculate_sp_interval_iters,
 };
-use dg_xch_core::plots::PlotHeader;
+use dg_xch_core::plots::{PlotFile, PlotHeader};
 use dg_xch_keys::master_sk_to_local_sk;
 use dg_xch_pos::plots::decompressor::DecompressorPool;
-use dg_xch_pos::plots::disk_plot::DiskPlot;
 use dg_xch_pos::plots::plot_reader::{read_all_plot_headers_async, PlotReader};
 use dg_xch_pos::verifier::proof_to_bytes;
 use dg_xch_serialize::ChiaSerialize;
@@ -25,17 +56,19 @@ use futures_util::stream::FuturesUnordered;
 use futures_util::{StreamExt, TryStreamExt};
 use hex::encode;
 use log::{debug, error, info, warn};
-use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::io::{Error, ErrorKind};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::thread::available_parallelism;
 use std::time::{Duration, Instant, SystemTime};
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
 use tokio::time::timeout;
 use uuid::Uuid;
 
+const OBJECT_STORAGE_LOAD_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
 #[derive(Default)]
 struct PlotCounts {
     og_passed: Arc<AtomicUsize>,
@@ -50,12 +83,29 @@ pub struct DruidGardenHarvester {
     pub plots: Arc<Mutex<HashMap<PathInfo, Arc<PlotInfo>>>>,
     pub plot_dirs: Arc<Vec<PathBuf>>,
     pub decompressor_pool: Arc<DecompressorPool>,
+    pub decompressor_threads: usize,
+    pub compression_stats: Arc<Mutex<CompressionStats>>,
+    pub eligibility_stats: Arc<Mutex<EligibilityStats>>,
+    pub harvester_stats: Arc<HarvesterStatsRegistry>,
+    pub sp_digests: Arc<SpDigests>,
     pub plots_ready: Arc<AtomicBool>,
     pub farmer_public_keys: Arc<Vec<Bytes48>>,
     pub pool_public_keys: Arc<Vec<Bytes48>>,
     pub pool_contract_hashes: Arc<Vec<Bytes32>>,
-    pub selected_network: String,
+    pub constants: &'static ConsensusConstants,
+    pub plot_filter: Arc<PlotFilter>,
     pub uuid: Uuid,
+    pub dir_circuits: Arc<DirCircuits>,
+    pub dir_latencies: Arc<DirLatencies>,
+    pub latency_baselines: Arc<Mutex<LatencyBaselines>>,
+    pub events: broadcast::Sender<FarmerEvent>,
+    pub plot_registry: Arc<PlotRegistry>,
+    pub plot_recoveries: Arc<Mutex<PlotRecoveries>>,
+    pub sp_lookups: Arc<SpLookups>,
+    pub lookup_limiter: Arc<LookupLimiter>,
+    pub drive_workers: Arc<DriveWorkers>,
+    pub sp_stagger: Arc<SpStagger>,
+    pub numa: Arc<NumaRuntimes>,
 }
 #[async_trait]
 impl Harvester for DruidGardenHarvester {
@@ -67,22 +117,70 @@ impl Harvester for DruidGardenHarvester {
     where
         T: ProofHandler + Sync + Send,
     {
+        if let Some(latency) = chaos::harvester_latency() {
+            tokio::time::sleep(latency).await;
+        }
         let plot_counts = Arc::new(PlotCounts::default());
+        //Standalone harvesters have no farmer side arrival time, lookups are measured from here
+        let arrival = SpArrival {
+            challenge_hash: signage_point.challenge_hash,
+            signage_point_index: signage_point.signage_point_index,
+            arrived: Instant::now(),
+        };
+        let mut cancelled = self
+            .sp_lookups
+            .start(
+                signage_point.challenge_hash,
+                signage_point.signage_point_index,
+            )
+            .await;
         let harvester_point = Arc::new(signage_point);
-        let constants = Arc::new(
-            CONSENSUS_CONSTANTS_MAP
-                .get(&self.selected_network)
-                .cloned()
-                .unwrap_or_default(),
-        );
+        let constants = Arc::new(self.constants.clone());
+        let plot_filter_bits = self.plot_filter.bits();
         let mut jobs = FuturesUnordered::new();
-        self.plots.lock().await.iter().map(|(path_info, plot_info)|{
-            (path_info.clone(), plot_info.clone())
+        let mut abort_handles = vec![];
+        let mut plots: Vec<(PathInfo, Arc<PlotInfo>)> = self
+            .plots
+            .lock()
+            .await
+            .iter()
+            .map(|(path_info, plot_info)| (path_info.clone(), plot_info.clone()))
+            .collect();
+        let skipped = self.plot_registry.skipped().await;
+        if !skipped.is_empty() {
+            plots.retain(|(path, _)| !skipped.contains(&path.path));
+        }
+        let mut allowed_dirs: HashMap<PathBuf, bool> = HashMap::new();
+        for (path, _) in &plots {
+            let dir = plot_dir(path);
+            if let Entry::Vacant(entry) = allowed_dirs.entry(dir) {
+                let allowed = self.dir_circuits.allow(entry.key()).await;
+                entry.insert(allowed);
+            }
+        }
+        let skipped_dirs = allowed_dirs.values().filter(|allowed| !**allowed).count();
+        if skipped_dirs > 0 {
+            warn!("Skipping {skipped_dirs} plot directories after repeated lookup timeouts");
+        }
+        //Lookups start in spawn order, fast directories go first so a slow one can not delay their proofs
+        let ranks = self.dir_latencies.ranks().await;
+        plots.sort_by_cached_key(|(path, _)| {
+            ranks.get(&plot_dir(path)).copied().unwrap_or_default()
+        });
+        let lookup_timeout = self.dir_circuits.lookup_timeout();
+        let stagger_offsets = Arc::new(self.sp_stagger.offsets(allowed_dirs.keys()));
+        plots.into_iter().filter(|(path, _)| {
+            allowed_dirs.get(&plot_dir(path)).copied().unwrap_or(true)
         }).for_each(|(path, plot_info)| { let data_arc = harvester_point.clone();
             let constants_arc = constants.clone();
             let plot_counts = plot_counts.clone();
+            let lookup_limiter = self.lookup_limiter.clone();
+            let drive_workers = self.drive_workers.clone();
+            let dir = plot_dir(&path);
+            let lookup_dir = dir.clone();
+            let stagger = SpStagger::offset(&stagger_offsets, &dir);
             let mut responses = vec![];
-            let plot_handle = timeout(Duration::from_secs(20), tokio::spawn(async move {
+            let lookup_handle = self.numa.spawn(&dir, async move {
                 let (plot_id, k, memo, c_level) = match plot_info.reader.header() {
                     PlotHeader::V1(h) => (h.id, h.k, h.memo, 0),
                     PlotHeader::V2(h) => (h.id, h.k, h.memo, h.compression_level),
@@ -95,7 +193,7 @@ impl Harvester for DruidGardenHarvester {
                     plot_counts.pool_total.fetch_add(1, Ordering::Relaxed);
                 }
                 if passes_plot_filter(
-                    constants_arc.as_ref(),
+                    plot_filter_bits,
                     &plot_id,
                     &data_arc.challenge_hash,
                     &data_arc.sp_hash,
@@ -113,6 +211,14 @@ impl Harvester for DruidGardenHarvester {
                         &data_arc.sp_hash,
                     );
                     debug!("Starting Search for challenge {sp_challenge_hash} in plot {}", path.file_name);
+                    //Held until the proofs are read, waiting for it is not part of the directory latency.
+                    //The drive worker is taken first so a queue on a busy drive holds no shared permits
+                    if !stagger.is_zero() {
+                        tokio::time::sleep(stagger).await;
+                    }
+                    let _drive_permit = drive_workers.acquire(&lookup_dir).await;
+                    let _permit = lookup_limiter.acquire().await;
+                    let lookup_start = Instant::now();
                     let qualities = match plot_info
                         .reader
                         .fetch_qualities_for_challenge(sp_challenge_hash.as_ref()).await {
@@ -121,7 +227,7 @@ impl Harvester for DruidGardenHarvester {
                         }
                         Err(e) => {
                             debug!("Plot({:?}) - Error for Hash: {}", path.file_name, sp_challenge_hash);
-                            return Err(e);
+                            return Err((path.clone(), e));
                         }
                     };
                     if !qualities.is_empty() {
@@ -155,7 +261,7 @@ impl Harvester for DruidGardenHarvester {
                                 calculate_sp_interval_iters(&constants_arc, sub_slot_iters)
                             {
                                 if required_iters < sp_interval_iters {
-                                    info!("Plot: {}, Passed Required Iterations, Loading Index: {}", path.file_name, index);
+                                    info!("{} Plot: {}, Passed Required Iterations, Loading Index: {}", arrival.tag(), path.file_name, index);
                                     match plot_info.reader.fetch_ordered_proof(index).await {
                                         Ok(proof) => {
                                             let proof_bytes = proof_to_bytes(&proof);
@@ -195,19 +301,47 @@ impl Harvester for DruidGardenHarvester {
                             }
                         }
                     }
+                    return Ok((path.clone(), responses, Some((c_level, lookup_start.elapsed()))));
                 }
-                Ok((path.clone(), responses))
-            }));
-            jobs.push(plot_handle);
+                Ok((path.clone(), responses, None))
+            });
+            abort_handles.push(lookup_handle.abort_handle());
+            let plot_handle = timeout(lookup_timeout, lookup_handle);
+            jobs.push(async move { (dir, plot_handle.await) });
         });
         let proofs = AtomicU64::new(0);
         let nft_partials = AtomicU64::new(0);
         let compressed_partials = AtomicU64::new(0);
-        while let Some(timeout_result) = jobs.next().await {
+        let mut dir_timeouts: HashMap<PathBuf, bool> = HashMap::new();
+        let mut dir_latencies: HashMap<PathBuf, Duration> = HashMap::new();
+        let mut unstaggered = Duration::ZERO;
+        while let Some((dir, timeout_result)) = tokio::select! {
+            biased;
+            _ = superseded(&mut cancelled) => None,
+            next = jobs.next() => next,
+        } {
+            unstaggered = unstaggered.max(
+                arrival
+                    .arrived
+                    .elapsed()
+                    .saturating_sub(SpStagger::offset(&stagger_offsets, &dir)),
+            );
+            let timed_out = dir_timeouts.entry(dir.clone()).or_default();
             match timeout_result {
                 Ok(join_result) => match join_result {
                     Ok(read_result) => match read_result {
-                        Ok((path, responses)) => {
+                        Ok((path, responses, lookup)) => {
+                            if let Some((c_level, duration)) = lookup {
+                                let slowest = dir_latencies.entry(dir.clone()).or_default();
+                                *slowest = (*slowest).max(duration);
+                                self.compression_stats
+                                    .lock()
+                                    .await
+                                    .record_lookup(c_level, duration);
+                                self.plot_registry
+                                    .record_lookup(&path.path, duration, responses.len())
+                                    .await;
+                            }
                             for (quality, proof, (is_partial, c_level)) in responses {
                                 if let Err(e) = proof_handle
                                     .handle_proof(NewProofOfSpace {
@@ -232,8 +366,11 @@ impl Harvester for DruidGardenHarvester {
                                 }
                             }
                         }
-                        Err(e) => {
+                        Err((path, e)) => {
                             debug!("Failed to read plot: {:?}", e);
+                            if is_io_failure(&e) {
+                                self.schedule_recovery(&path.path, e).await;
+                            }
                         }
                     },
                     Err(e) => {
@@ -241,12 +378,54 @@ impl Harvester for DruidGardenHarvester {
                     }
                 },
                 Err(e) => {
-                    error!("Failed to read qualities due to Timeout: {:?}", e);
+                    *timed_out = true;
+                    dir_latencies.insert(dir.clone(), lookup_timeout);
+                    error!(
+                        "Failed to read qualities in {:?} due to Timeout: {:?}",
+                        dir, e
+                    );
                 }
             }
         }
+        //Directories that had not answered yet are left out, they did not time out
+        if jobs.is_empty() {
+            self.sp_stagger
+                .record(arrival.arrived.elapsed(), unstaggered);
+        } else {
+            abort_handles.iter().for_each(|h| h.abort());
+            info!(
+                "{} Superseded by a newer signage point, cancelled {} outstanding lookups",
+                arrival.tag(),
+                jobs.len()
+            );
+        }
+        drop(jobs);
+        self.lookup_limiter.adjust(arrival.arrived.elapsed());
+        self.sp_lookups
+            .finish(
+                harvester_point.challenge_hash,
+                harvester_point.signage_point_index,
+            )
+            .await;
+        for (dir, timed_out) in dir_timeouts {
+            self.dir_circuits.record(&dir, timed_out).await;
+        }
+        for (dir, latency) in dir_latencies {
+            self.dir_latencies.record(&dir, latency).await;
+            let regression = self.latency_baselines.lock().await.record(
+                LatencyKind::Lookup,
+                &dir.to_string_lossy(),
+                latency,
+                now(),
+            );
+            if let Some(kind) = regression {
+                warn!("{kind}");
+                let _ = self.events.send(FarmerEvent::new(kind));
+            }
+        }
         info!(
-            "Passed Filter - OG: {}/{}. NFT: {}/{}. Compressed: {}/{}. Proofs Found: {}. Partials Found: NFT({}), Compressed({})",
+            "{} Passed Filter - OG: {}/{}. NFT: {}/{}. Compressed: {}/{}. Proofs Found: {}. Partials Found: NFT({}), Compressed({})",
+            arrival.tag(),
             plot_counts.og_passed.load(Ordering::Relaxed),
             plot_counts.og_total.load(Ordering::Relaxed),
             plot_counts.pool_passed.load(Ordering::Relaxed),
@@ -257,6 +436,27 @@ impl Harvester for DruidGardenHarvester {
             nft_partials.load(Ordering::Relaxed),
             compressed_partials.load(Ordering::Relaxed),
         );
+        let eligible_plots = (plot_counts.og_passed.load(Ordering::Relaxed)
+            + plot_counts.pool_passed.load(Ordering::Relaxed)
+            + plot_counts.compressed_passed.load(Ordering::Relaxed))
+            as u64;
+        self.eligibility_stats.lock().await.record(eligible_plots);
+        self.sp_digests
+            .record_lookup(arrival.tag(), self.uuid, eligible_plots)
+            .await;
+        self.harvester_stats
+            .update(self.uuid, |s| {
+                s.signage_points += 1;
+                s.eligible_plots += eligible_plots;
+                s.proofs += proofs.load(Ordering::Relaxed);
+                s.partials += nft_partials.load(Ordering::Relaxed)
+                    + compressed_partials.load(Ordering::Relaxed);
+            })
+            .await;
+        self.compression_stats
+            .lock()
+            .await
+            .check_headroom(self.plot_filter.bits(), self.decompressor_threads);
         Ok(())
     }
 
@@ -317,73 +517,246 @@ impl Harvester for DruidGardenHarvester {
 }
 
 impl DruidGardenHarvester {
+    //The plot is left out of lookups until a reopen after the backoff shows it readable again
+    async fn schedule_recovery(&self, path: &Path, e: Error) {
+        let delay = self
+            .plot_recoveries
+            .lock()
+            .await
+            .schedule(path, Instant::now());
+        if let Some(delay) = delay {
+            warn!(
+                "Lookup in plot {:?} failed: {e}, reopening it in {}s",
+                path,
+                delay.as_secs()
+            );
+            self.plot_registry
+                .mark_recovering(path, format!("lookup failed: {e}"))
+                .await;
+        }
+    }
+    //Resolves once the first plot load is done, a failed load counts as done
+    pub async fn wait_for_plots(&self) {
+        while !self.plots_ready.load(Ordering::Relaxed) {
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+    }
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
-        plot_dirs: Vec<PathBuf>,
+        bb_config: &BladebitHarvesterConfig,
         farmer_public_keys: Vec<Bytes48>,
         pool_public_keys: Vec<Bytes48>,
         pool_contract_hashes: Vec<Bytes32>,
         shutdown_signal: Arc<AtomicBool>,
-        selected_network: &str,
+        eligibility_stats: Arc<Mutex<EligibilityStats>>,
+        harvester_stats: Arc<HarvesterStatsRegistry>,
+        sp_digests: Arc<SpDigests>,
+        unfarmable_plots: Arc<UnfarmablePlots>,
+        dir_latencies: Arc<DirLatencies>,
+        latency_baselines: Arc<Mutex<LatencyBaselines>>,
+        plot_registry: Arc<PlotRegistry>,
+        idle: Arc<IdleState>,
+        events: broadcast::Sender<FarmerEvent>,
+        constants: &'static ConsensusConstants,
+        network: &str,
+        chain_height: Arc<AtomicU32>,
+        uuid: Uuid,
     ) -> Result<Self, Error> {
-        let decompressor_pool = Arc::new(DecompressorPool::new(
-            1,
-            available_parallelism().map(|u| u.get()).unwrap_or(4) as u8,
+        let plot_filter_bits = bb_config.plot_filter_bits(network, constants);
+        let configured = bb_config.plot_filter_bits.contains_key(network);
+        if configured {
+            info!("Using a plot filter of {plot_filter_bits} bits on {network}");
+        }
+        let plot_filter = Arc::new(PlotFilter::new(
+            plot_filter_bits,
+            configured,
+            PlotFilterSchedule::for_network(network),
+            chain_height,
         ));
+        let io_config = plot_io_config(bb_config);
+        let mut plot_dirs = plot_dirs(bb_config);
+        plot_dirs.extend(io_config.object_stores.keys().cloned());
+        let scan_filters = Arc::new(PlotScanFilters::new(bb_config));
+        let missing_plot_grace = Duration::from_secs(bb_config.missing_plot_grace);
+        let dir_circuits = Arc::new(DirCircuits::new(bb_config.dir_circuit.clone()));
+        let sp_lookups = Arc::new(SpLookups::new(bb_config.sp_deadline));
+        let lookup_limiter = Arc::new(LookupLimiter::new(bb_config.lookup_concurrency.clone()));
+        let drive_workers = Arc::new(DriveWorkers::new(bb_config.drive_workers));
+        let sp_stagger = Arc::new(SpStagger::new(bb_config.sp_stagger_ms));
+        let decompressor = select_decompressor(
+            providers(&bb_config.decompression),
+            bb_config.decompression.threads,
+        );
+        harvester_stats
+            .update(uuid, |s| s.decompression = Some(decompressor.backend))
+            .await;
+        let decompressor_threads = decompressor.threads;
+        let decompressor_pool = decompressor.pool;
         let plot_dirs = Arc::new(plot_dirs);
-        let plots = Arc::new(Mutex::new(
-            load_plots(
-                plot_dirs.clone(),
-                &farmer_public_keys,
-                &pool_public_keys,
-                &pool_contract_hashes,
-                vec![],
-                decompressor_pool.clone(),
-            )
-            .await?,
-        ));
+        let io_config = Arc::new(io_config);
+        let numa = io_config.numa.clone();
+        let compression_stats = Arc::new(Mutex::new(CompressionStats::default()));
+        let plots = Arc::new(Mutex::new(HashMap::new()));
+        let plots_ready: Arc<AtomicBool> = Default::default();
 
         let farmer_public_keys = Arc::new(farmer_public_keys);
         let pool_public_keys = Arc::new(pool_public_keys);
         let pool_contract_hashes = Arc::new(pool_contract_hashes);
+        let _plot_auditor = tokio::spawn(plot_auditor(
+            bb_config.audit.clone(),
+            plots.clone(),
+            sp_lookups.clone(),
+            plot_registry.clone(),
+            events.clone(),
+            get_plot_audit_path(),
+            shutdown_signal.clone(),
+        ));
+        let plot_recoveries = Arc::new(Mutex::new(PlotRecoveries::new(
+            bb_config.io_recovery.clone(),
+        )));
+        let _plot_recoverer = tokio::spawn(plot_recoverer(
+            plot_recoveries.clone(),
+            plots.clone(),
+            io_config.clone(),
+            decompressor_pool.clone(),
+            plot_registry.clone(),
+            events.clone(),
+            shutdown_signal.clone(),
+        ));
         let plot_sync_mutex = plots.clone();
         let plot_sync_dirs = plot_dirs.clone();
         let plot_sync_farmer_public_keys = farmer_public_keys.clone();
         let plot_sync_pool_public_keys = pool_public_keys.clone();
         let plot_sync_pool_contract_hashes = pool_contract_hashes.clone();
         let plot_sync_decompressor_pool = decompressor_pool.clone();
+        let plot_sync_compression_stats = compression_stats.clone();
+        let plot_sync_dir_circuits = dir_circuits.clone();
+        let plot_sync_harvester_stats = harvester_stats.clone();
+        let plot_sync_registry = plot_registry.clone();
+        let plot_sync_ready = plots_ready.clone();
+        let plot_sync_events = events.clone();
+        //The first load runs here too, so the full node connection and signage points do not
+        //wait for every plot header to be read
         let _plot_sync = tokio::spawn(async move {
+            let started = Instant::now();
+            match load_plots(
+                plot_sync_dirs.clone(),
+                io_config.clone(),
+                scan_filters.clone(),
+                &plot_sync_farmer_public_keys,
+                &plot_sync_pool_public_keys,
+                &plot_sync_pool_contract_hashes,
+                HashMap::new(),
+                plot_sync_decompressor_pool.clone(),
+                true,
+            )
+            .await
+            {
+                Ok((plots, unfarmable)) => {
+                    unfarmable_plots
+                        .update(uuid, plot_sync_dirs.as_ref(), unfarmable)
+                        .await;
+                    let mut all_plots = plot_sync_mutex.lock().await;
+                    all_plots.extend(plots);
+                    plot_sync_compression_stats
+                        .lock()
+                        .await
+                        .update_plot_counts(&all_plots);
+                    plot_sync_harvester_stats
+                        .set_plots(uuid, PlotTotals::from_plots(&all_plots))
+                        .await;
+                    plot_sync_registry.sync(&all_plots).await;
+                    info!(
+                        "Initial plot load done in {:.1}s",
+                        started.elapsed().as_secs_f64()
+                    );
+                }
+                Err(e) => {
+                    error!("Failed to load plots: {:?}", e);
+                }
+            }
+            plot_sync_ready.store(true, Ordering::Relaxed);
             let mut last_sync = Instant::now();
+            let mut missing_since = HashMap::new();
+            let mut plots_released = false;
             loop {
                 if !shutdown_signal.load(Ordering::Relaxed) {
                     break;
                 }
-                if last_sync.elapsed() > Duration::from_secs(30) {
-                    let existing_plot_paths: Arc<Vec<PathBuf>> = Arc::new(
-                        plot_sync_mutex
-                            .lock()
-                            .await
-                            .keys()
-                            .map(|info| info.path.clone())
-                            .collect(),
-                    );
+                //Plot totals are kept while idle so the farm size does not drop to zero in stats
+                if idle.is_idle() {
+                    if !plots_released {
+                        let mut plots = plot_sync_mutex.lock().await;
+                        info!(
+                            "Releasing {} plots while no signage points arrive",
+                            plots.len()
+                        );
+                        plots.clear();
+                        plots.shrink_to_fit();
+                        io_config.ram_cache.clear();
+                        missing_since.clear();
+                        plots_released = true;
+                    }
+                    tokio::time::sleep(Duration::from_millis(25)).await;
+                    continue;
+                }
+                if std::mem::take(&mut plots_released)
+                    || last_sync.elapsed() > Duration::from_secs(30)
+                {
+                    check_missing_plots(
+                        plot_sync_mutex.as_ref(),
+                        io_config.as_ref(),
+                        &mut missing_since,
+                        missing_plot_grace,
+                        &plot_sync_events,
+                    )
+                    .await;
+                    let existing_plots: HashMap<Bytes32, PathBuf> = plot_sync_mutex
+                        .lock()
+                        .await
+                        .iter()
+                        .map(|(info, plot)| (plot_id(plot.reader.header()), info.path.clone()))
+                        .collect();
+                    //Listing a stalled directory would block the sync, skipped directories are rescanned once they recover
+                    let mut sync_dirs = vec![];
+                    for dir in plot_sync_dirs.iter() {
+                        if !plot_sync_dir_circuits.is_open(dir).await {
+                            sync_dirs.push(dir.clone());
+                        }
+                    }
                     match load_plots(
-                        plot_sync_dirs.clone(),
+                        Arc::new(sync_dirs.clone()),
+                        io_config.clone(),
+                        scan_filters.clone(),
                         &plot_sync_farmer_public_keys,
                         &plot_sync_pool_public_keys,
                         &plot_sync_pool_contract_hashes,
-                        existing_plot_paths.as_ref().clone(),
+                        existing_plots,
                         plot_sync_decompressor_pool.clone(),
+                        false,
                     )
                     .await
                     {
-                        Ok(plots) => {
-                            plot_sync_mutex.lock().await.extend(plots);
+                        Ok((plots, unfarmable)) => {
+                            unfarmable_plots.update(uuid, &sync_dirs, unfarmable).await;
+                            let mut all_plots = plot_sync_mutex.lock().await;
+                            all_plots.extend(plots);
+                            plot_sync_compression_stats
+                                .lock()
+                                .await
+                                .update_plot_counts(&all_plots);
                             last_sync = Instant::now();
                         }
                         Err(e) => {
                             error!("Failed to load plots: {:?}", e);
                         }
                     }
+                    //Also covers plots dropped by check_missing_plots when the load failed
+                    let all_plots = plot_sync_mutex.lock().await;
+                    plot_sync_harvester_stats
+                        .set_plots(uuid, PlotTotals::from_plots(&all_plots))
+                        .await;
+                    plot_sync_registry.sync(&all_plots).await;
                 }
                 tokio::time::sleep(Duration::from_millis(25)).await;
             }
@@ -394,22 +767,128 @@ impl DruidGardenHarvester {
             pool_contract_hashes,
             plot_dirs,
             plots,
-            plots_ready: Default::default(),
+            plots_ready,
             decompressor_pool,
-            selected_network: selected_network.to_string(),
-            uuid: Uuid::new_v4(),
+            decompressor_threads,
+            compression_stats,
+            eligibility_stats,
+            harvester_stats,
+            sp_digests,
+            constants,
+            plot_filter,
+            uuid,
+            dir_circuits,
+            dir_latencies,
+            latency_baselines,
+            events,
+            plot_registry,
+            plot_recoveries,
+            sp_lookups,
+            lookup_limiter,
+            drive_workers,
+            sp_stagger,
+            numa,
         })
     }
 }
 
+//Same as the consensus filter with the prefix size taken from the harvester config
+fn passes_plot_filter(
+    filter_bits: usize,
+    plot_id: &Bytes32,
+    challenge_hash: &Bytes32,
+    signage_point: &Bytes32,
+) -> bool {
+    let input = calculate_plot_filter_input(plot_id, challenge_hash, signage_point);
+    input
+        .to_sized_bytes()
+        .iter()
+        .flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1))
+        .take(filter_bits)
+        .all(|bit| bit == 0)
+}
+
+fn plot_dir(path: &PathInfo) -> PathBuf {
+    path.path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+//Plots whose file disappeared are kept for the grace period so short mount outages do not change the farm size.
+//Plots that come back are dropped from the map so the following load reopens them with fresh file handles
+async fn check_missing_plots(
+    plots: &Mutex<HashMap<PathInfo, Arc<PlotInfo>>>,
+    io_config: &PlotIoConfig,
+    missing_since: &mut HashMap<PathBuf, Instant>,
+    grace: Duration,
+    events: &broadcast::Sender<FarmerEvent>,
+) {
+    let plot_paths: Vec<(PathBuf, u64)> = plots
+        .lock()
+        .await
+        .iter()
+        .map(|(k, v)| (k.path.clone(), v.file_size))
+        .collect();
+    for (path, file_size) in plot_paths {
+        let exists = match io_config.object_store_for(&plot_dir(&PathInfo::new(path.clone()))) {
+            Some(store) => matches!(
+                timeout(Duration::from_secs(5), store.exists(&path)).await,
+                Ok(true)
+            ),
+            None => matches!(
+                timeout(Duration::from_secs(5), tokio::fs::metadata(&path)).await,
+                Ok(Ok(_))
+            ),
+        };
+        let kind = match (exists, missing_since.get(&path)) {
+            (true, Some(_)) => {
+                info!("Plot {:?} is available again, reopening", path);
+                missing_since.remove(&path);
+                plots.lock().await.remove(&PathInfo::new(path.clone()));
+                FarmerEventKind::PlotRecovered {
+                    path: path.to_string_lossy().to_string(),
+                }
+            }
+            (false, None) => {
+                warn!(
+                    "Plot {:?} is missing, removing it in {} seconds unless it comes back",
+                    path,
+                    grace.as_secs()
+                );
+                missing_since.insert(path.clone(), Instant::now());
+                FarmerEventKind::PlotMissing {
+                    path: path.to_string_lossy().to_string(),
+                }
+            }
+            (false, Some(since)) if since.elapsed() >= grace => {
+                warn!("Plot {:?} still missing, removing it from the farm", path);
+                missing_since.remove(&path);
+                plots.lock().await.remove(&PathInfo::new(path.clone()));
+                FarmerEventKind::PlotRemoved {
+                    path: path.to_string_lossy().to_string(),
+                    file_size,
+                }
+            }
+            _ => continue,
+        };
+        let _ = events.send(FarmerEvent::new(kind));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn load_plots(
     plot_dirs: Arc<Vec<PathBuf>>,
+    io_config: Arc<PlotIoConfig>,
+    scan_filters: Arc<PlotScanFilters>,
     farmer_public_keys: &[Bytes48],
     pool_public_keys: &[Bytes48],
     pool_contract_hashes: &[Bytes32],
-    existing_plot_paths: Vec<PathBuf>,
+    //Plot id to the path it is farmed as
+    existing_plots: HashMap<Bytes32, PathBuf>,
     decompressor_pool: Arc<DecompressorPool>,
-) -> Result<HashMap<PathInfo, Arc<PlotInfo>>, Error> {
+    report_progress: bool,
+) -> Result<(HashMap<PathInfo, Arc<PlotInfo>>, Vec<UnfarmablePlot>), Error> {
     debug!("Started Loading Plots");
     if farmer_public_keys.is_empty() {
         error!("No Public Keys Available");
@@ -424,29 +903,40 @@ async fn load_plots(
     let farmer_public_keys = Arc::new(farmer_public_keys.to_vec());
     let pool_public_keys = Arc::new(pool_public_keys.to_vec());
     let pool_contract_hashes = Arc::new(pool_contract_hashes.to_vec());
-    let existing_paths: Arc<Vec<PathBuf>> = Arc::new(existing_plot_paths);
+    let existing_plots = Arc::new(existing_plots);
     let futures = FuturesUnordered::new();
     for dir in plot_dirs.iter() {
         let farmer_public_keys = farmer_public_keys.clone();
         let pool_public_keys = pool_public_keys.clone();
         let pool_contract_hashes = pool_contract_hashes.clone();
-        let existing_paths = existing_paths.clone();
+        let existing_plots = existing_plots.clone();
         let decompressor_pool = decompressor_pool.clone();
         let dir = dir.clone();
+        let io_options = io_config.options_for(&dir);
+        let ram_cache = io_config.ram_cache.clone();
+        let scan_filter = scan_filters.filter_for(&dir);
+        let object_store = io_config.object_store_for(&dir);
+        //Object storage plots download their C tables on the first load
+        let load_timeout = if object_store.is_some() {
+            OBJECT_STORAGE_LOAD_TIMEOUT
+        } else {
+            Duration::from_secs(30)
+        };
         debug!("Validating Plot Directory: {:?}", &dir);
         futures.push(timeout(
-            Duration::from_secs(30),
-            tokio::spawn(async move {
-                match read_all_plot_headers_async(
-                    &dir,
-                    existing_paths
-                        .iter()
-                        .map(|p| p.as_path())
-                        .collect::<Vec<&Path>>()
-                        .as_slice(),
-                )
-                .await
-                {
+            load_timeout,
+            io_config.numa.spawn(&dir.clone(), async move {
+                let excluded = scan_filter.excluded_paths(&dir).await;
+                let skipped = existing_plots
+                    .values()
+                    .chain(excluded.iter())
+                    .map(|p| p.as_path())
+                    .collect::<Vec<&Path>>();
+                let headers = match &object_store {
+                    Some(store) => store.read_all_plot_headers(&skipped).await,
+                    None => read_all_plot_headers_async(&dir, &skipped).await,
+                };
+                match headers {
                     Ok((headers, failed)) => {
                         debug!(
                             "Plot Headers Processed: {}, Failed: {}",
@@ -454,7 +944,7 @@ async fn load_plots(
                             failed.len()
                         );
                         let mut results = vec![];
-                        let mut missing_keys = HashSet::new();
+                        let mut missing_keys = vec![];
                         for (path, header) in headers.into_iter() {
                             let (
                                 local_master_secret_key,
@@ -470,13 +960,31 @@ async fn load_plots(
                             .await
                             {
                                 Ok(headers) => headers,
-                                Err(e) => {
-                                    error!("Error for Plot: {:?}, {:?}", path, e);
-                                    missing_keys.insert(path);
+                                Err((kind, reason)) => {
+                                    missing_keys.push(UnfarmablePlot {
+                                        path: path.to_string_lossy().to_string(),
+                                        kind,
+                                        reason,
+                                    });
                                     continue;
                                 }
                             };
-                            let plot_file = match DiskPlot::new(&path).await {
+                            //Checked before the plot is opened a second time
+                            if let Some(farmed_as) = existing_plots.get(&plot_id(&header)) {
+                                missing_keys.push(UnfarmablePlot {
+                                    path: path.to_string_lossy().to_string(),
+                                    kind: plot_kind(pool_contract_puzzle_hash.is_some()),
+                                    reason: UnfarmableReason::Alias(
+                                        farmed_as.to_string_lossy().to_string(),
+                                    ),
+                                });
+                                continue;
+                            }
+                            let plot_file = match &object_store {
+                                Some(store) => IoPlot::from_object(store, &path, header).await,
+                                None => IoPlot::new(&path, &io_options, &ram_cache).await,
+                            };
+                            let plot_file = match plot_file {
                                 Ok(plot_file) => plot_file,
                                 Err(e) => {
                                     error!("Failed to load plot file {:?}: {:?}", &path, e);
@@ -498,7 +1006,9 @@ async fn load_plots(
                                         .map(|me| {
                                             (me.len(), me.modified().unwrap_or(SystemTime::now()))
                                         })
-                                        .unwrap_or_else(|_| (0, SystemTime::now()));
+                                        .unwrap_or_else(|_| {
+                                            (*reader.plot_file().plot_size(), SystemTime::now())
+                                        });
                                     let local_sk = match master_sk_to_local_sk(&local_master_secret)
                                     {
                                         Ok(key) => key,
@@ -556,7 +1066,7 @@ async fn load_plots(
                                 }
                             }
                         }
-                        Some((results, failed, missing_keys))
+                        Some((results, failed, missing_keys, excluded.len()))
                     }
                     Err(e) => {
                         error!("Failed to validate plot dir: {:?}, {:?}", dir, e);
@@ -567,19 +1077,23 @@ async fn load_plots(
         ));
     }
     let mut stream = futures.into_stream();
-    let mut plots = HashMap::new();
+    let mut loaded = vec![];
     let mut failed_count = 0;
-    let mut missing_keys_count = 0;
+    let mut unfarmable = vec![];
+    let mut excluded_count = 0;
+    let mut dirs_done = 0;
     while let Some(join_handle) = stream.next().await {
+        dirs_done += 1;
         match join_handle {
             Ok(Ok(o)) => {
-                if let Some((results, failed, missing_keys)) = o {
+                if let Some((results, failed, missing_keys, excluded)) = o {
                     failed_count += failed.len();
-                    missing_keys_count += missing_keys.len();
+                    unfarmable.extend(missing_keys);
+                    excluded_count += excluded;
                     for result in results {
                         match result {
                             Ok((k, v)) => {
-                                plots.insert(k, Arc::new(v));
+                                loaded.push((k.path, plot_id(v.reader.header()), v));
                             }
                             Err(e) => {
                                 error!("Failed to read plot: {:?}", e);
@@ -595,7 +1109,30 @@ async fn load_plots(
                 error!("Timeout in plot reader thread: {:?}", e);
             }
         }
+        if report_progress {
+            info!(
+                "Loaded {dirs_done}/{} plot directories, {} plots so far",
+                plot_dirs.len(),
+                loaded.len()
+            );
+        }
+    }
+    let (farmed, aliases) = split_aliases(loaded);
+    for alias in aliases {
+        unfarmable.push(UnfarmablePlot {
+            path: alias.path.to_string_lossy().to_string(),
+            kind: plot_kind(alias.plot.pool_contract_puzzle_hash.is_some()),
+            reason: UnfarmableReason::Alias(alias.farmed_as.to_string_lossy().to_string()),
+        });
     }
+    let plots: HashMap<PathInfo, Arc<PlotInfo>> = farmed
+        .into_iter()
+        .map(|(path, info)| (PathInfo::new(path), Arc::new(info)))
+        .collect();
+    let alias_count = unfarmable
+        .iter()
+        .filter(|p| matches!(p.reason, UnfarmableReason::Alias(_)))
+        .count();
     let mut og_count = 0;
     let mut pool_count = 0;
     let mut compressed_count = 0;
@@ -608,8 +1145,16 @@ async fn load_plots(
             og_count += 1;
         }
     }
-    info!("Loaded {} og plots, {} pooling plots and {} compressed plots, failed to load {}, missing keys for {}", og_count, pool_count, compressed_count, failed_count, missing_keys_count);
-    Ok(plots)
+    info!("Loaded {} og plots, {} pooling plots and {} compressed plots, failed to load {}, missing keys for {}, excluded {}, aliases {}", og_count, pool_count, compressed_count, failed_count, unfarmable.len() - alias_count, excluded_count, alias_count);
+    Ok((plots, unfarmable))
+}
+
+fn plot_kind(pool_contract: bool) -> PlotKind {
+    if pool_contract {
+        PlotKind::Nft
+    } else {
+        PlotKind::Og
+    }
 }
 
 async fn load_headers(
@@ -617,32 +1162,17 @@ async fn load_headers(
     farmer_public_keys: &[Bytes48],
     pool_public_keys: &[Bytes48],
     pool_contract_hashes: &[Bytes32],
-) -> Result<(Bytes32, Bytes48, Option<Bytes32>, Option<Bytes48>), Error> {
+) -> Result<(Bytes32, Bytes48, Option<Bytes32>, Option<Bytes48>), (PlotKind, UnfarmableReason)> {
     let memo = match &header {
         PlotHeader::V1(header) => &header.memo,
         PlotHeader::V2(header) => &header.memo,
     };
-    if let Some(key) = &memo.pool_public_key {
-        if !pool_public_keys.contains(key) {
-            return Err(Error::new(
-                ErrorKind::NotFound,
-                "Missing Pool key in provided keys",
-            ));
-        }
-    } else if let Some(pool_contract_hash) = &memo.pool_contract_puzzle_hash {
-        if !pool_contract_hashes.contains(pool_contract_hash) {
-            return Err(Error::new(
-                ErrorKind::NotFound,
-                "Missing pool contract address in provided puzzle_hashes",
-            ));
-        }
-    }
-    if !farmer_public_keys.contains(&memo.farmer_public_key) {
-        return Err(Error::new(
-            ErrorKind::NotFound,
-            "Missing Farmer key in provided keys",
-        ));
-    }
+    classify_plot(
+        memo,
+        farmer_public_keys,
+        pool_public_keys,
+        pool_contract_hashes,
+    )?;
     Ok((
         memo.local_master_secret_key,
         memo.farmer_public_key,
@@ -650,3 +1180,33 @@ async fn load_headers(
         memo.pool_public_key,
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dg_xch_core::consensus::constants::MAINNET;
+
+    #[test]
+    fn plot_filter_matches_consensus_filter() {
+        let challenge_hash = Bytes32::from_sized_bytes([7u8; 32]);
+        let sp_hash = Bytes32::from_sized_bytes([9u8; 32]);
+        for i in 0..=255u8 {
+            let plot_id = Bytes32::from_sized_bytes([i; 32]);
+            assert_eq!(
+                passes_plot_filter(
+                    MAINNET.number_zero_bits_plot_filter,
+                    &plot_id,
+                    &challenge_hash,
+                    &sp_hash
+                ),
+                dg_xch_core::blockchain::proof_of_space::passes_plot_filter(
+                    &MAINNET,
+                    &plot_id,
+                    &challenge_hash,
+                    &sp_hash
+                )
+            );
+            assert!(passes_plot_filter(0, &plot_id, &challenge_hash, &sp_hash));
+        }
+    }
+}
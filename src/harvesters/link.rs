@@ -0,0 +1,501 @@
+use dg_xch_clients::protocols::shared::CAPABILITIES;
+use dg_xch_clients::websocket::ChiaMessage;
+use dg_xch_serialize::ChiaSerialize;
+use futures_util::{Sink, SinkExt, Stream, StreamExt, TryStreamExt};
+use log::debug;
+use std::collections::VecDeque;
+use std::future::ready;
+use std::io::{Cursor, Error, ErrorKind};
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::select;
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{sleep, timeout, timeout_at, Instant};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+#[cfg(feature = "quic")]
+pub use quic::{quic_connect, quic_endpoint, quic_link};
+
+//Capabilities only dg_fast_harvester and ff put in their handshake, chia ignores unknown ones
+const BATCHING_CAPABILITY: u16 = 0x4447;
+const ZSTD_CAPABILITY: u16 = 0x4448;
+//Largest frame accepted, like the websocket message limit
+const MAX_FRAME_SIZE: usize = 64 << 20;
+//Messages queued within the window after the first one go out in the same frame
+const BATCH_WINDOW: Duration = Duration::from_millis(5);
+const MAX_BATCH_SIZE: usize = 1 << 20;
+const BATCH_QUEUE_SIZE: usize = 256;
+//Smaller batches do not get smaller with zstd
+const COMPRESSION_THRESHOLD: usize = 512;
+const COMPRESSION_LEVEL: i32 = 3;
+const COMPRESSED: u8 = 1;
+//An empty batch is sent when nothing else was, the other end drops the link after missing a few
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(20);
+
+//Frames received from the other end of a link, one protocol message each
+pub type LinkReader = Pin<Box<dyn Stream<Item = Result<Vec<u8>, Error>> + Send>>;
+type FrameSink = Pin<Box<dyn Sink<Vec<u8>, Error = Error> + Send>>;
+
+//Sending half of the connection between a farmer and a remote harvester, whatever the transport
+pub struct LinkWriter {
+    sink: Mutex<Option<FrameSink>>,
+}
+impl LinkWriter {
+    fn new(sink: impl Sink<Vec<u8>, Error = Error> + Send + 'static) -> Self {
+        Self {
+            sink: Mutex::new(Some(Box::pin(sink))),
+        }
+    }
+    pub async fn send(&self, msg: ChiaMessage) -> Result<(), Error> {
+        self.send_frame(msg.to_bytes()).await
+    }
+    async fn send_frame(&self, frame: Vec<u8>) -> Result<(), Error> {
+        match self.sink.lock().await.as_mut() {
+            Some(sink) => sink.send(frame).await,
+            None => Err(Error::new(ErrorKind::BrokenPipe, "Link is closed")),
+        }
+    }
+    pub async fn close(&self) -> Result<(), Error> {
+        match self.sink.lock().await.take() {
+            Some(mut sink) => sink.close().await,
+            None => Ok(()),
+        }
+    }
+}
+
+//None once the other end closed the link
+pub async fn read_message(reader: &mut LinkReader) -> Option<Result<ChiaMessage, Error>> {
+    match reader.next().await? {
+        Ok(frame) => Some(ChiaMessage::from_bytes(&mut Cursor::new(frame))),
+        Err(e) => Some(Err(e)),
+    }
+}
+
+//Link features of dg_fast_harvester and ff, chia harvesters and farmers support none of them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkOptions {
+    //Batches with sequence numbers and heartbeats
+    pub batching: bool,
+    //zstd compressed batches, only with batching
+    pub compression: bool,
+}
+impl LinkOptions {
+    //The chia capabilities with the options this end offers
+    pub fn capabilities(&self) -> Vec<(u16, String)> {
+        let mut capabilities: Vec<(u16, String)> = CAPABILITIES
+            .iter()
+            .map(|e| (e.0, e.1.to_string()))
+            .collect();
+        if self.batching {
+            capabilities.push((BATCHING_CAPABILITY, "1".to_string()));
+            if self.compression {
+                capabilities.push((ZSTD_CAPABILITY, "1".to_string()));
+            }
+        }
+        capabilities
+    }
+    //Only options offered by both ends are used
+    pub fn negotiate(&self, capabilities: &[(u16, String)]) -> Self {
+        let offered = |capability| capabilities.contains(&(capability, "1".to_string()));
+        let batching = self.batching && offered(BATCHING_CAPABILITY);
+        Self {
+            batching,
+            compression: batching && self.compression && offered(ZSTD_CAPABILITY),
+        }
+    }
+}
+
+//Switches a link to the negotiated options, both ends do so right after the handshake
+pub fn upgrade_link(
+    writer: LinkWriter,
+    reader: LinkReader,
+    options: LinkOptions,
+) -> (LinkWriter, LinkReader) {
+    if !options.batching {
+        return (writer, reader);
+    }
+    let (queue, queued) = mpsc::channel(BATCH_QUEUE_SIZE);
+    tokio::spawn(write_batches(writer, queued, options.compression));
+    let batched = LinkWriter::new(futures_util::sink::unfold(
+        queue,
+        |queue, frame: Vec<u8>| async move {
+            queue
+                .send(frame)
+                .await
+                .map_err(|_| Error::new(ErrorKind::BrokenPipe, "Link is closed"))?;
+            Ok::<_, Error>(queue)
+        },
+    ));
+    (batched, read_batches(reader))
+}
+
+//Frames carry a flags byte, the sequence number and the batch, each message prefixed with its length
+async fn write_batches(link: LinkWriter, mut queued: mpsc::Receiver<Vec<u8>>, compression: bool) {
+    let mut sequence = 0u64;
+    let mut open = true;
+    while open {
+        let mut batch = vec![];
+        select! {
+            msg = queued.recv() => match msg {
+                Some(msg) => batch.push(msg),
+                None => break,
+            },
+            _ = sleep(HEARTBEAT_INTERVAL) => {}
+        }
+        let deadline = Instant::now() + BATCH_WINDOW;
+        let mut size: usize = batch.iter().map(Vec::len).sum();
+        while !batch.is_empty() && size < MAX_BATCH_SIZE {
+            match timeout_at(deadline, queued.recv()).await {
+                Ok(Some(msg)) => {
+                    size += msg.len();
+                    batch.push(msg);
+                }
+                Ok(None) => {
+                    open = false;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+        let frame = match encode_batch(sequence, &batch, compression) {
+            Ok(frame) => frame,
+            Err(e) => {
+                debug!(
+                    "Failed to encode a batch of {} messages: {:?}",
+                    batch.len(),
+                    e
+                );
+                break;
+            }
+        };
+        if let Err(e) = link.send_frame(frame).await {
+            debug!("Failed to send batch {sequence}: {:?}", e);
+            break;
+        }
+        sequence += 1;
+    }
+    link.close().await.unwrap_or_default();
+}
+
+fn encode_batch(sequence: u64, batch: &[Vec<u8>], compression: bool) -> Result<Vec<u8>, Error> {
+    let mut payload = Vec::with_capacity(batch.iter().map(|m| m.len() + 4).sum());
+    for msg in batch {
+        payload.extend_from_slice(&(msg.len() as u32).to_be_bytes());
+        payload.extend_from_slice(msg);
+    }
+    let mut flags = 0;
+    if compression && payload.len() >= COMPRESSION_THRESHOLD {
+        let compressed = zstd::bulk::compress(&payload, COMPRESSION_LEVEL)?;
+        if compressed.len() < payload.len() {
+            payload = compressed;
+            flags |= COMPRESSED;
+        }
+    }
+    let mut frame = Vec::with_capacity(payload.len() + 9);
+    frame.push(flags);
+    frame.extend_from_slice(&sequence.to_be_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+fn decode_batch(expected: u64, frame: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    if frame.len() < 9 {
+        return Err(Error::new(ErrorKind::InvalidData, "Batch without a header"));
+    }
+    let sequence = u64::from_be_bytes(frame[1..9].try_into().unwrap_or_default());
+    //Frames are never lost or reordered on a working link, a gap means the link is broken
+    if sequence != expected {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Received batch {sequence}, expected {expected}"),
+        ));
+    }
+    let payload = if frame[0] & COMPRESSED != 0 {
+        zstd::bulk::decompress(&frame[9..], MAX_FRAME_SIZE)?
+    } else {
+        frame[9..].to_vec()
+    };
+    let mut messages = vec![];
+    let mut rest = payload.as_slice();
+    while !rest.is_empty() {
+        let len = rest
+            .get(..4)
+            .map(|len| u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize)
+            .filter(|len| rest.len() >= len + 4)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Truncated message in batch"))?;
+        messages.push(rest[4..len + 4].to_vec());
+        rest = &rest[len + 4..];
+    }
+    Ok(messages)
+}
+
+//Unpacks batches into single messages, the stream ends after the first error
+fn read_batches(frames: LinkReader) -> LinkReader {
+    let state = (frames, 0u64, VecDeque::new());
+    Box::pin(futures_util::stream::unfold(
+        Some(state),
+        |state| async move {
+            let (mut frames, mut sequence, mut messages) = state?;
+            loop {
+                if let Some(msg) = messages.pop_front() {
+                    return Some((Ok(msg), Some((frames, sequence, messages))));
+                }
+                let frame = match timeout(HEARTBEAT_TIMEOUT, frames.next()).await {
+                    Ok(Some(Ok(frame))) => frame,
+                    Ok(Some(Err(e))) => return Some((Err(e), None)),
+                    Ok(None) => return None,
+                    Err(_) => {
+                        return Some((
+                            Err(Error::new(
+                                ErrorKind::TimedOut,
+                                format!("No heartbeat in {HEARTBEAT_TIMEOUT:?}"),
+                            )),
+                            None,
+                        ))
+                    }
+                };
+                match decode_batch(sequence, &frame) {
+                    Ok(batch) => messages.extend(batch),
+                    Err(e) => return Some((Err(e), None)),
+                }
+                sequence += 1;
+            }
+        },
+    ))
+}
+
+//Chia harvesters and farmers send every message as a binary websocket message
+pub fn websocket_link<S>(stream: WebSocketStream<S>) -> (LinkWriter, LinkReader)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (write, read) = stream.split();
+    let writer = LinkWriter::new(
+        write
+            .sink_map_err(Error::other)
+            .with(|frame: Vec<u8>| ready(Ok::<_, Error>(Message::Binary(frame)))),
+    );
+    let reader = read.map_err(Error::other).try_filter_map(|msg| {
+        ready(Ok(match msg {
+            Message::Binary(frame) => Some(frame),
+            _ => None,
+        }))
+    });
+    (writer, Box::pin(reader))
+}
+
+//QUIC between dg_fast_harvester and ff, the connection survives address changes of the harvester
+//like a switch between Wi-Fi and LTE and keeps itself alive without the websocket redial
+#[cfg(feature = "quic")]
+mod quic {
+    use super::{LinkReader, LinkWriter, MAX_FRAME_SIZE};
+    use crate::farmer::config::DnsConfig;
+    use crate::farmer::dns::DnsResolver;
+    use log::debug;
+    use quinn::{
+        ClientConfig, Connection, Endpoint, IdleTimeout, ReadExactError, RecvStream, SendStream,
+        ServerConfig, TransportConfig,
+    };
+    use std::io::{Error, ErrorKind};
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    //Protocol both ends agree on in the TLS handshake, other QUIC clients are refused
+    const ALPN: &[u8] = b"dg-harvester/1";
+    //Keeps NAT mappings open between signage points
+    const KEEP_ALIVE: Duration = Duration::from_secs(5);
+    const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    fn transport(idle_timeout: Option<Duration>) -> Result<Arc<TransportConfig>, Error> {
+        let mut transport = TransportConfig::default();
+        transport
+            .keep_alive_interval(Some(KEEP_ALIVE))
+            .max_idle_timeout(
+                idle_timeout
+                    .map(IdleTimeout::try_from)
+                    .transpose()
+                    .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?,
+            );
+        Ok(Arc::new(transport))
+    }
+
+    //The connection is closed after idle_timeout without a packet, the lower timeout of both ends wins
+    pub fn quic_endpoint(
+        mut tls: rustls::ServerConfig,
+        bind: SocketAddr,
+        idle_timeout: Duration,
+    ) -> Result<Endpoint, Error> {
+        tls.alpn_protocols = vec![ALPN.to_vec()];
+        let mut config = ServerConfig::with_crypto(Arc::new(tls));
+        config.transport_config(transport(Some(idle_timeout))?);
+        Endpoint::server(config, bind)
+    }
+
+    //Tries the addresses of host in order, UDP has no connection attempt to race
+    pub async fn quic_connect(
+        dns: &Option<DnsConfig>,
+        host: &str,
+        port: u16,
+        mut tls: rustls::ClientConfig,
+        idle_timeout: Option<Duration>,
+    ) -> Result<(Connection, LinkWriter, LinkReader), Error> {
+        tls.alpn_protocols = vec![ALPN.to_vec()];
+        let mut config = ClientConfig::new(Arc::new(tls));
+        config.transport_config(transport(idle_timeout)?);
+        let server_name = host.trim_matches(|c| c == '[' || c == ']');
+        let addrs = DnsResolver::new(dns.clone().unwrap_or_default())
+            .lookup(host)
+            .await?;
+        let mut last_err = None;
+        for ip in addrs {
+            let bind: SocketAddr = if ip.is_ipv4() {
+                (Ipv4Addr::UNSPECIFIED, 0).into()
+            } else {
+                (Ipv6Addr::UNSPECIFIED, 0).into()
+            };
+            let mut endpoint = Endpoint::client(bind)?;
+            endpoint.set_default_client_config(config.clone());
+            let connecting = endpoint
+                .connect(SocketAddr::new(ip, port), server_name)
+                .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+            match timeout(CONNECT_TIMEOUT, connecting).await {
+                Ok(Ok(connection)) => {
+                    let (send, recv) = connection.open_bi().await.map_err(Error::other)?;
+                    let (writer, reader) = quic_link(send, recv);
+                    return Ok((connection, writer, reader));
+                }
+                Ok(Err(e)) => {
+                    debug!("Failed to connect to {ip}:{port} over QUIC: {e:?}");
+                    last_err = Some(Error::other(e));
+                }
+                Err(_) => {
+                    last_err = Some(Error::new(
+                        ErrorKind::TimedOut,
+                        format!("Timed out connecting to {ip}:{port} over QUIC"),
+                    ))
+                }
+            }
+        }
+        Err(last_err
+            .unwrap_or_else(|| Error::new(ErrorKind::NotFound, "No addresses to connect to")))
+    }
+
+    //Messages go over one bidirectional stream, each prefixed with its length
+    pub fn quic_link(send: SendStream, recv: RecvStream) -> (LinkWriter, LinkReader) {
+        let writer = LinkWriter::new(futures_util::sink::unfold(
+            send,
+            |mut send, frame: Vec<u8>| async move {
+                send.write_all(&(frame.len() as u32).to_be_bytes()).await?;
+                send.write_all(&frame).await?;
+                Ok::<_, Error>(send)
+            },
+        ));
+        //The stream ends after the first error, the rest of a broken stream can't be framed
+        let reader = futures_util::stream::unfold(Some(recv), |recv| async move {
+            let mut recv = recv?;
+            match read_frame(&mut recv).await {
+                Ok(Some(frame)) => Some((Ok(frame), Some(recv))),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+        (writer, Box::pin(reader))
+    }
+
+    async fn read_frame(recv: &mut RecvStream) -> Result<Option<Vec<u8>>, Error> {
+        let mut len = [0u8; 4];
+        match recv.read_exact(&mut len).await {
+            Ok(()) => {}
+            Err(ReadExactError::FinishedEarly) => return Ok(None),
+            Err(ReadExactError::ReadError(e)) => return Err(e.into()),
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_FRAME_SIZE {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Frame of {len} bytes is larger than {MAX_FRAME_SIZE}"),
+            ));
+        }
+        let mut frame = vec![0; len];
+        recv.read_exact(&mut frame).await.map_err(|e| match e {
+            ReadExactError::FinishedEarly => {
+                Error::new(ErrorKind::UnexpectedEof, "Link closed within a frame")
+            }
+            ReadExactError::ReadError(e) => e.into(),
+        })?;
+        Ok(Some(frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dg_xch_clients::protocols::ProtocolMessageTypes;
+
+    //Frames written to the link come back out of its reader
+    fn loopback() -> (LinkWriter, LinkReader) {
+        let (frames, received) = mpsc::channel::<Vec<u8>>(16);
+        let writer = LinkWriter::new(futures_util::sink::unfold(
+            frames,
+            |frames, frame: Vec<u8>| async move {
+                frames.send(frame).await.map_err(Error::other)?;
+                Ok::<_, Error>(frames)
+            },
+        ));
+        let reader = futures_util::stream::unfold(received, |mut received| async move {
+            received.recv().await.map(|frame| (Ok(frame), received))
+        });
+        (writer, Box::pin(reader))
+    }
+
+    #[tokio::test]
+    async fn batches_compresses_and_sequences_messages() {
+        let harvester = LinkOptions {
+            batching: true,
+            compression: true,
+        };
+        let farmer = LinkOptions {
+            batching: true,
+            compression: true,
+        };
+        assert_eq!(farmer.negotiate(&harvester.capabilities()), harvester);
+        assert_eq!(
+            farmer.negotiate(&LinkOptions::default().capabilities()),
+            LinkOptions::default()
+        );
+
+        let (writer, reader) = loopback();
+        let (writer, mut reader) = upgrade_link(writer, reader, harvester);
+        let messages: Vec<ChiaMessage> = (0..3u16)
+            .map(|id| ChiaMessage {
+                msg_type: ProtocolMessageTypes::NewProofOfSpace,
+                id: Some(id),
+                data: vec![id as u8; 4096],
+            })
+            .collect();
+        for msg in &messages {
+            writer.send(msg.clone()).await.unwrap();
+        }
+        for msg in &messages {
+            let received = read_message(&mut reader).await.unwrap().unwrap();
+            assert_eq!(received.id, msg.id);
+            assert_eq!(received.data, msg.data);
+        }
+
+        let batch = vec![vec![0u8; 4096], vec![]];
+        let frame = encode_batch(7, &batch, true).unwrap();
+        assert_eq!(frame[0], COMPRESSED);
+        assert!(frame.len() < 4096);
+        assert_eq!(decode_batch(7, &frame).unwrap(), batch);
+        assert_eq!(
+            decode_batch(6, &frame).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+        writer.close().await.unwrap();
+    }
+}
@@ -1,25 +1,77 @@
+pub mod compression;
+pub mod concurrency;
+pub mod decompression;
+pub mod dir_circuit;
+pub mod dir_latency;
+pub mod drive_health;
+pub mod drive_workers;
 pub mod druid_garden;
+pub mod link;
+pub mod numa;
+pub mod object_storage;
+pub mod plot_aliases;
+pub mod plot_audit;
+pub mod plot_classification;
+pub mod plot_dirs;
+pub mod plot_io;
+pub mod plot_recovery;
+pub mod plot_registry;
+pub mod plot_scan;
+#[cfg(feature = "remote-harvester")]
+pub mod remote;
+pub mod sp_lookups;
+pub mod sp_stagger;
+pub mod stats;
 
+use crate::farmer::capture::Direction;
+use crate::farmer::config::BladebitHarvesterConfig;
+use crate::farmer::events::FarmerEventKind;
+use crate::farmer::harvester_server::RemoteHarvesters;
 use crate::farmer::FarmerSharedState;
+use crate::get_harvester_ids_path;
 use crate::harvesters::druid_garden::DruidGardenHarvester;
+use crate::harvesters::numa::NumaRuntimes;
+use crate::harvesters::object_storage::ObjectStore;
+use crate::harvesters::plot_dirs::normalize_plot_dir;
+use crate::harvesters::plot_io::{PlotIoConfig, RamCache};
 use async_trait::async_trait;
-use blst::min_pk::SecretKey;
 use dg_xch_clients::protocols::harvester::{
     NewProofOfSpace, NewSignagePointHarvester, RequestSignatures, RespondSignatures,
 };
+use dg_xch_clients::protocols::ProtocolMessageTypes;
 use dg_xch_core::blockchain::sized_bytes::Bytes32;
-use log::error;
+use log::{error, warn};
 use std::collections::HashMap;
+use std::fs;
 use std::io::Error;
 use std::path::Path;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::time::timeout;
 use uuid::Uuid;
 
+const SIGNATURE_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const SIGNATURE_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+//Signatures arriving later than this are too late for the signage point or a partial
+const SIGNATURE_RETRY_WINDOW: Duration = Duration::from_secs(30);
+
+pub(crate) const DRUID_GARDEN_HARVESTER: &str = "druid_garden";
+pub(crate) const REMOTE_HARVESTERS: &str = "remote";
+
 #[async_trait]
 pub trait SignatureHandler {
     async fn handle_signature(&self, new_pos: RespondSignatures) -> Result<(), Error>;
 }
 
+#[async_trait]
+impl<T: SignatureHandler + Sync + Send> SignatureHandler for Arc<T> {
+    async fn handle_signature(&self, new_pos: RespondSignatures) -> Result<(), Error> {
+        self.as_ref().handle_signature(new_pos).await
+    }
+}
+
 #[async_trait]
 pub trait ProofHandler {
     async fn handle_proof(&self, new_pos: NewProofOfSpace) -> Result<(), Error>;
@@ -44,50 +96,52 @@ pub trait Harvester {
     fn uuid(&self) -> Uuid;
 }
 
+#[allow(clippy::large_enum_variant)]
 pub enum Harvesters {
     DruidGarden(DruidGardenHarvester),
+    Remote(Arc<RemoteHarvesters>),
+}
+impl Harvesters {
+    pub fn uuid(&self) -> Uuid {
+        match self {
+            Harvesters::DruidGarden(h) => h.uuid(),
+            Harvesters::Remote(h) => h.uuid(),
+        }
+    }
 }
 
 pub async fn load_harvesters(
     shared_state: Arc<FarmerSharedState>,
 ) -> Result<Arc<HashMap<Uuid, Arc<Harvesters>>>, Error> {
     let mut harvesters: HashMap<Uuid, Arc<Harvesters>> = HashMap::new();
-    let mut farmer_public_keys = vec![];
-    let mut pool_public_keys = vec![];
-    for farmer_info in &shared_state.config.farmer_info {
-        let f_sk: SecretKey = farmer_info.farmer_secret_key.into();
-        farmer_public_keys.push(f_sk.sk_to_pk().to_bytes().into());
-        if let Some(pk) = farmer_info.pool_secret_key {
-            let p_sk: SecretKey = pk.into();
-            pool_public_keys.push(p_sk.sk_to_pk().to_bytes().into());
-        }
-    }
+    let (farmer_public_keys, pool_public_keys) = shared_state.config.public_keys();
+    let farms = shared_state.config.all_farms();
     shared_state.gui_stats.lock().await.keys = farmer_public_keys.clone();
-    let pool_contract_hashes = shared_state
-        .config
-        .pool_info
+    let pool_contract_hashes = farms
         .iter()
+        .flat_map(|f| f.pool_info.iter())
         .map(|w| w.p2_singleton_puzzle_hash)
         .collect::<Vec<Bytes32>>();
-    let mut sum = 0;
-    let mut total_size = 0;
     if let Some(bb_config) = &shared_state.config.harvester_configs.bladebit {
-        for dir in &bb_config.plot_directories {
-            if let Err(e) = count_plots(Path::new(&dir), &mut sum, &mut total_size).await {
-                error!("Error Counting Plots: {e:?}")
-            }
-        }
         let harvester = DruidGardenHarvester::new(
-            bb_config
-                .plot_directories
-                .iter()
-                .map(|s| Path::new(s).to_path_buf())
-                .collect(),
+            bb_config,
             farmer_public_keys,
             pool_public_keys,
             pool_contract_hashes,
-            shared_state.run.clone(),
+            shared_state.active.clone(),
+            shared_state.eligibility_stats.clone(),
+            shared_state.harvester_stats.clone(),
+            shared_state.sp_digests.clone(),
+            shared_state.unfarmable_plots.clone(),
+            shared_state.dir_latencies.clone(),
+            shared_state.latency_baselines.clone(),
+            shared_state.plot_registry.clone(),
+            shared_state.idle.clone(),
+            shared_state.events.clone(),
+            shared_state.constants,
             &shared_state.config.selected_network,
+            shared_state.chain_height.clone(),
+            load_harvester_id(DRUID_GARDEN_HARVESTER),
         )
         .await?;
         harvesters.insert(
@@ -95,28 +149,218 @@ pub async fn load_harvesters(
             Arc::new(Harvesters::DruidGarden(harvester)),
         );
     }
-    shared_state.gui_stats.lock().await.total_plot_count = sum;
-    shared_state.gui_stats.lock().await.total_plot_space = total_size;
+    if shared_state.config.harvester_server.is_some() {
+        let remote = Arc::new(RemoteHarvesters::new(load_harvester_id(REMOTE_HARVESTERS)));
+        harvesters.insert(remote.uuid(), Arc::new(Harvesters::Remote(remote)));
+    }
     Ok(Arc::new(harvesters))
 }
 
-pub static EXPECTED_UNCOMPRESSED_MIN: u64 = 0;
+pub fn remote_harvesters(
+    harvesters: &HashMap<Uuid, Arc<Harvesters>>,
+) -> Option<Arc<RemoteHarvesters>> {
+    harvesters.values().find_map(|h| match h.as_ref() {
+        Harvesters::Remote(remote) => Some(remote.clone()),
+        _ => None,
+    })
+}
+
+//Harvesters connected to the farmer stay connected through a reload of the local harvesters
+pub fn keep_remote_harvesters(
+    previous: &HashMap<Uuid, Arc<Harvesters>>,
+    harvesters: Arc<HashMap<Uuid, Arc<Harvesters>>>,
+) -> Arc<HashMap<Uuid, Arc<Harvesters>>> {
+    let Some(remote) = remote_harvesters(previous) else {
+        return harvesters;
+    };
+    Arc::new(
+        harvesters
+            .iter()
+            .map(|(id, h)| match h.as_ref() {
+                Harvesters::Remote(_) => (*id, Arc::new(Harvesters::Remote(remote.clone()))),
+                _ => (*id, h.clone()),
+            })
+            .collect(),
+    )
+}
+
+//Plots load in the background, a swap of harvesters waits for the new ones to be complete
+pub async fn wait_for_plots(harvesters: &HashMap<Uuid, Arc<Harvesters>>) {
+    for harvester in harvesters.values() {
+        match harvester.as_ref() {
+            Harvesters::DruidGarden(h) => h.wait_for_plots().await,
+            //Remote harvesters load their plots on their own machines
+            Harvesters::Remote(_) => {}
+        }
+    }
+}
+
+pub(crate) fn plot_io_config(bb_config: &BladebitHarvesterConfig) -> PlotIoConfig {
+    PlotIoConfig {
+        options: bb_config
+            .io_options
+            .iter()
+            .map(|(k, v)| {
+                (
+                    normalize_plot_dir(k).unwrap_or_else(|_| Path::new(k).to_path_buf()),
+                    v.clone(),
+                )
+            })
+            .collect(),
+        ram_cache: Arc::new(RamCache::new(bb_config.ram_cache_mib * 1024 * 1024)),
+        numa: Arc::new(NumaRuntimes::new(
+            bb_config
+                .numa_nodes
+                .iter()
+                .map(|(k, v)| {
+                    (
+                        normalize_plot_dir(k).unwrap_or_else(|_| Path::new(k).to_path_buf()),
+                        *v,
+                    )
+                })
+                .collect(),
+        )),
+        object_stores: bb_config
+            .object_storage
+            .iter()
+            .filter_map(|c| match ObjectStore::new(c) {
+                Ok(store) => Some((store.root(), Arc::new(store))),
+                Err(e) => {
+                    error!("Skipping object storage bucket {}: {:?}", c.bucket, e);
+                    None
+                }
+            })
+            .collect(),
+    }
+}
+
+//Harvester ids are kept across restarts so stats and pool metadata stay attributed to the same harvester
+pub(crate) fn load_harvester_id(name: &str) -> Uuid {
+    let path = get_harvester_ids_path();
+    let mut ids: HashMap<String, Uuid> = match fs::read_to_string(&path) {
+        Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+            warn!("Ignoring invalid harvester ids at {:?}: {:?}", path, e);
+            HashMap::new()
+        }),
+        Err(_) => HashMap::new(),
+    };
+    if let Some(id) = ids.get(name) {
+        return *id;
+    }
+    let id = Uuid::new_v4();
+    ids.insert(name.to_string(), id);
+    match serde_json::to_string_pretty(&ids) {
+        Ok(s) => {
+            if let Err(e) = fs::write(&path, s) {
+                warn!("Failed to save harvester ids to {:?}: {:?}", path, e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize harvester ids: {:?}", e),
+    }
+    id
+}
 
-async fn count_plots(
-    path: &Path,
-    count_total: &mut u64,
-    size_total: &mut u64,
-) -> Result<(), Error> {
-    if !path.is_dir() {
-        return Ok(());
+//Keeps the response of one signature request for request_signatures_with_retry
+#[derive(Default)]
+struct SignatureCapture(Mutex<Option<RespondSignatures>>);
+#[async_trait]
+impl SignatureHandler for SignatureCapture {
+    async fn handle_signature(&self, response: RespondSignatures) -> Result<(), Error> {
+        *self.0.lock().await = Some(response);
+        Ok(())
     }
-    let mut dir = tokio::fs::read_dir(path).await?;
-    while let Ok(Some(e)) = dir.next_entry().await {
-        if e.file_name().to_string_lossy().ends_with(".plot") {
-            let file_size = e.metadata().await?.len();
-            *size_total += file_size;
-            *count_total += 1;
+}
+
+//Re-issues the request until the harvester responds, a lost response would silently drop the proof.
+//Only getting the response is retried, the handler runs once and its errors are final
+pub async fn request_signatures_with_retry<T>(
+    harvester: Arc<Harvesters>,
+    request: RequestSignatures,
+    handler: T,
+    shared_state: Arc<FarmerSharedState>,
+) where
+    T: SignatureHandler + Sync + Send + 'static,
+{
+    let harvester_id = harvester.uuid();
+    let slot = shared_state.signature_slot(harvester_id).await;
+    let start = Instant::now();
+    let mut attempts = 0;
+    let response = loop {
+        attempts += 1;
+        let sent = Instant::now();
+        shared_state.capture.record_harvester(
+            Direction::Out,
+            harvester_id,
+            ProtocolMessageTypes::RequestSignatures,
+            &request,
+        );
+        let capture = Arc::new(SignatureCapture::default());
+        let result = match harvester.as_ref() {
+            Harvesters::DruidGarden(h) => {
+                timeout(
+                    SIGNATURE_REQUEST_TIMEOUT,
+                    h.request_signatures(request.clone(), capture.clone()),
+                )
+                .await
+            }
+            Harvesters::Remote(h) => {
+                timeout(
+                    SIGNATURE_REQUEST_TIMEOUT,
+                    h.request_signatures(request.clone(), capture.clone()),
+                )
+                .await
+            }
+        };
+        let err = match result {
+            Ok(Ok(())) => match capture.0.lock().await.take() {
+                Some(response) => {
+                    shared_state
+                        .protocol_rtts
+                        .record(format!("harvester {harvester_id}"), sent.elapsed())
+                        .await;
+                    break response;
+                }
+                None => "No response from the harvester".to_string(),
+            },
+            Ok(Err(e)) => format!("{:?}", e),
+            Err(_) => "Timed out".to_string(),
+        };
+        let sp_known = shared_state.has_signage_point(&request.sp_hash).await;
+        if !sp_known
+            || start.elapsed() >= SIGNATURE_RETRY_WINDOW
+            || !shared_state.run.load(Ordering::Relaxed)
+        {
+            error!(
+                "Failed to get signatures for {} after {} attempts: {}",
+                request.plot_identifier, attempts, err
+            );
+            shared_state.emit(FarmerEventKind::Error {
+                message: format!(
+                    "Failed to get signatures for {}: {}",
+                    request.plot_identifier, err
+                ),
+            });
+            return;
         }
+        warn!(
+            "Signature request for {} failed, retrying: {}",
+            request.plot_identifier, err
+        );
+        tokio::time::sleep(SIGNATURE_RETRY_INTERVAL).await;
+    };
+    drop(slot);
+    if let Err(e) = handler.handle_signature(response).await {
+        error!(
+            "Failed to handle signatures for {}: {:?}",
+            request.plot_identifier, e
+        );
+        shared_state.emit(FarmerEventKind::Error {
+            message: format!(
+                "Failed to handle signatures for {}: {:?}",
+                request.plot_identifier, e
+            ),
+        });
     }
-    Ok(())
 }
+
+pub static EXPECTED_UNCOMPRESSED_MIN: u64 = 0;
@@ -0,0 +1,111 @@
+use crate::platform::{numa_node_cpus, pin_current_thread};
+use log::{info, warn};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use tokio::runtime::{Builder, Handle, Runtime};
+use tokio::task::JoinHandle;
+
+//A runtime per NUMA node with its worker and blocking threads pinned to the node's CPUs. Lookups
+//and plot loads of the directories mapped to a node run there, so the RAM cache and direct IO
+//buffers they fill are allocated on the node of the HBA serving the directory
+#[derive(Default)]
+pub struct NumaRuntimes {
+    by_dir: HashMap<PathBuf, usize>,
+    nodes: HashMap<usize, Runtime>,
+}
+impl NumaRuntimes {
+    pub fn new(mapping: HashMap<PathBuf, usize>) -> Self {
+        let mut nodes = HashMap::new();
+        let mut by_dir = HashMap::new();
+        for (dir, node) in mapping {
+            if let Entry::Vacant(entry) = nodes.entry(node) {
+                let Some(cpus) = numa_node_cpus(node) else {
+                    warn!(
+                        "NUMA node {node} of {:?} was not found, it runs unpinned",
+                        dir
+                    );
+                    continue;
+                };
+                match node_runtime(node, cpus) {
+                    Ok(runtime) => {
+                        entry.insert(runtime);
+                    }
+                    Err(e) => {
+                        warn!("Failed to start the runtime of NUMA node {node}: {:?}", e);
+                        continue;
+                    }
+                }
+            }
+            by_dir.insert(dir, node);
+        }
+        Self { by_dir, nodes }
+    }
+    pub fn handle_for(&self, dir: &Path) -> Option<Handle> {
+        let node = self.by_dir.get(dir)?;
+        self.nodes.get(node).map(|r| r.handle().clone())
+    }
+    //Unmapped directories stay on the main runtime
+    pub fn spawn<F>(&self, dir: &Path, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match self.handle_for(dir) {
+            Some(handle) => handle.spawn(future),
+            None => tokio::spawn(future),
+        }
+    }
+}
+impl Drop for NumaRuntimes {
+    //Harvesters are dropped inside the main runtime, where a runtime may not block on its shutdown
+    fn drop(&mut self) {
+        for (_, runtime) in self.nodes.drain() {
+            runtime.shutdown_background();
+        }
+    }
+}
+impl std::fmt::Debug for NumaRuntimes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NumaRuntimes")
+            .field("by_dir", &self.by_dir)
+            .finish()
+    }
+}
+
+fn node_runtime(node: usize, cpus: Vec<usize>) -> std::io::Result<Runtime> {
+    info!("Running lookups of NUMA node {node} on CPUs {:?}", cpus);
+    Builder::new_multi_thread()
+        .worker_threads(cpus.len())
+        .thread_name(format!("numa-{node}"))
+        .on_thread_start(move || {
+            if let Err(e) = pin_current_thread(&cpus) {
+                warn!("Failed to pin a thread to NUMA node {node}: {:?}", e);
+            }
+        })
+        .enable_all()
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn unmapped_and_unknown_nodes_run_on_the_main_runtime() {
+        let mapped = PathBuf::from("/plots/hba0");
+        let runtimes = NumaRuntimes::new(HashMap::from([(mapped.clone(), usize::MAX)]));
+        assert!(runtimes.handle_for(&mapped).is_none());
+        assert_eq!(runtimes.spawn(&mapped, async { 7 }).await.unwrap(), 7);
+        if numa_node_cpus(0).is_some() {
+            let runtimes = NumaRuntimes::new(HashMap::from([(mapped.clone(), 0)]));
+            assert!(runtimes.handle_for(&mapped).is_some());
+            let name = runtimes.spawn(&mapped, async {
+                std::thread::current().name().map(|n| n.to_string())
+            });
+            assert_eq!(name.await.unwrap().as_deref(), Some("numa-0"));
+            assert!(runtimes.handle_for(Path::new("/plots/other")).is_none());
+        }
+    }
+}
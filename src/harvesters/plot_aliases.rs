@@ -0,0 +1,100 @@
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use dg_xch_core::plots::PlotHeader;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub fn plot_id(header: &PlotHeader) -> Bytes32 {
+    match header {
+        PlotHeader::V1(h) => h.id,
+        PlotHeader::V2(h) => h.id,
+    }
+}
+
+//Paths that are their own canonical path go first, so a plot reached through a symlink and
+//directly is farmed directly. The rest is ordered by path to pick the same one on every scan
+fn farm_order(path: &Path) -> (bool, &Path) {
+    let linked = std::fs::canonicalize(path).map_or(true, |canonical| canonical != path);
+    (linked, path)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PlotAlias<T> {
+    pub path: PathBuf,
+    pub farmed_as: PathBuf,
+    pub plot: T,
+}
+
+//Plots with the same id are the same file reached through symlinks, bind mounts or a mergerfs pool
+//next to its branches. Keeps one path per id and returns the others with the path farmed instead
+pub fn split_aliases<T>(
+    plots: Vec<(PathBuf, Bytes32, T)>,
+) -> (Vec<(PathBuf, T)>, Vec<PlotAlias<T>>) {
+    let mut farmed: HashMap<Bytes32, (PathBuf, T)> = HashMap::new();
+    let mut aliases = vec![];
+    for (path, id, value) in plots {
+        match farmed.remove(&id) {
+            None => {
+                farmed.insert(id, (path, value));
+            }
+            Some(current) if farm_order(&path) < farm_order(&current.0) => {
+                farmed.insert(id, (path, value));
+                aliases.push((id, current));
+            }
+            Some(current) => {
+                farmed.insert(id, current);
+                aliases.push((id, (path, value)));
+            }
+        }
+    }
+    let aliases = aliases
+        .into_iter()
+        .map(|(id, (path, plot))| PlotAlias {
+            path,
+            farmed_as: farmed[&id].0.clone(),
+            plot,
+        })
+        .collect();
+    (farmed.into_values().collect(), aliases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn farms_each_plot_once_through_its_real_path() {
+        let dir = std::env::temp_dir()
+            .canonicalize()
+            .unwrap()
+            .join(format!("ff_alias_test_{}", std::process::id()));
+        let real = dir.join("disk1");
+        std::fs::create_dir_all(&real).unwrap();
+        let plot = real.join("plot-k32-a.plot");
+        std::fs::write(&plot, b"").unwrap();
+        let link = dir.join("link");
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+        let (a, b) = (
+            Bytes32::from_sized_bytes([1u8; 32]),
+            Bytes32::from_sized_bytes([2u8; 32]),
+        );
+        let (farmed, aliases) = split_aliases(vec![
+            (link.join("plot-k32-a.plot"), a, 1),
+            (PathBuf::from("/missing/plot-k32-b.plot"), b, 2),
+            (plot.clone(), a, 3),
+        ]);
+        let mut farmed: Vec<i32> = farmed.into_iter().map(|(_, v)| v).collect();
+        farmed.sort();
+        assert_eq!(farmed, vec![2, 3]);
+        assert_eq!(
+            aliases,
+            vec![PlotAlias {
+                path: link.join("plot-k32-a.plot"),
+                farmed_as: plot,
+                plot: 1
+            }]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
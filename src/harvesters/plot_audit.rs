@@ -0,0 +1,230 @@
+use crate::farmer::config::PlotAuditConfig;
+use crate::farmer::events::{FarmerEvent, FarmerEventKind};
+use crate::farmer::partial_queue::now;
+use crate::farmer::{PathInfo, PlotInfo};
+use crate::harvesters::plot_registry::PlotRegistry;
+use crate::harvesters::sp_lookups::SpLookups;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use dg_xch_core::plots::PlotHeader;
+use dg_xch_pos::verifier::{proof_to_bytes, validate_proof};
+use log::{debug, info, warn};
+use rand::Rng;
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Mutex};
+
+//Last successful check per plot path, kept next to the config so the rotation survives restarts
+#[derive(Debug, Default)]
+pub struct PlotAuditLog {
+    path: Option<PathBuf>,
+    verified: HashMap<String, u64>,
+}
+impl PlotAuditLog {
+    pub fn load(path: &Path) -> Self {
+        let verified = match fs::read_to_string(path).map(|s| serde_json::from_str(&s)) {
+            Ok(Ok(verified)) => verified,
+            Ok(Err(e)) => {
+                warn!("Ignoring invalid plot audit log at {:?}: {:?}", path, e);
+                HashMap::new()
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                warn!("Failed to read plot audit log at {:?}: {:?}", path, e);
+                HashMap::new()
+            }
+        };
+        Self {
+            path: Some(path.to_path_buf()),
+            verified,
+        }
+    }
+    pub fn last_verified(&self, plot: &str) -> Option<u64> {
+        self.verified.get(plot).copied()
+    }
+    //Plots never checked go first, then the one checked longest ago
+    pub fn next<'a, I: Iterator<Item = &'a String>>(&self, plots: I) -> Option<&'a String> {
+        plots.min_by_key(|p| self.last_verified(p).map(|t| t + 1).unwrap_or(0))
+    }
+    pub fn record(&mut self, plot: &str, timestamp: u64) {
+        self.verified.insert(plot.to_string(), timestamp);
+    }
+    //Drops plots that are no longer farmed and writes the log
+    pub fn save(&mut self, plots: &[String]) {
+        self.verified.retain(|p, _| plots.contains(p));
+        if let Some(path) = &self.path {
+            let result = serde_json::to_string(&self.verified)
+                .map_err(std::io::Error::from)
+                .and_then(|s| fs::write(path, s));
+            if let Err(e) = result {
+                warn!("Failed to save plot audit log to {:?}: {:?}", path, e);
+            }
+        }
+    }
+}
+
+//Looks up random challenges and validates every proof found, returns the number of proofs checked
+pub async fn audit_plot(plot_info: &PlotInfo, challenges: usize) -> Result<usize, String> {
+    let (plot_id, k) = match plot_info.reader.header() {
+        PlotHeader::V1(h) => (h.id, h.k),
+        PlotHeader::V2(h) => (h.id, h.k),
+    };
+    let mut proofs = 0;
+    for _ in 0..challenges {
+        let challenge = Bytes32::from_sized_bytes(rand::thread_rng().gen());
+        let qualities = match plot_info
+            .reader
+            .fetch_qualities_for_challenge(challenge.as_ref())
+            .await
+        {
+            Ok(qualities) => qualities,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("quality lookup failed: {e}")),
+        };
+        for (index, quality) in qualities {
+            let proof = plot_info
+                .reader
+                .fetch_ordered_proof(index)
+                .await
+                .map_err(|e| format!("proof lookup failed: {e}"))?;
+            let proof_quality = validate_proof(
+                plot_id.to_sized_bytes(),
+                k,
+                &proof_to_bytes(&proof),
+                challenge.as_ref(),
+            )
+            .map_err(|e| format!("invalid proof: {e}"))?;
+            if proof_quality != quality {
+                return Err("proof does not match the looked up quality".to_string());
+            }
+            proofs += 1;
+        }
+    }
+    Ok(proofs)
+}
+
+//Checks one plot per interval while no signage point lookups are running, plots queued for a
+//recheck in the plot browser go first and are checked even with the rotation disabled
+pub async fn plot_auditor(
+    config: PlotAuditConfig,
+    plots: Arc<Mutex<HashMap<PathInfo, Arc<PlotInfo>>>>,
+    sp_lookups: Arc<SpLookups>,
+    plot_registry: Arc<PlotRegistry>,
+    events: broadcast::Sender<FarmerEvent>,
+    log_path: PathBuf,
+    shutdown_signal: Arc<AtomicBool>,
+) {
+    let mut audit_log = PlotAuditLog::load(&log_path);
+    let interval = Duration::from_secs(config.interval);
+    let mut last_audit = Instant::now();
+    loop {
+        if !shutdown_signal.load(Ordering::Relaxed) {
+            break;
+        }
+        if !sp_lookups.is_idle().await {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            continue;
+        }
+        let requested = plot_registry.take_recheck().await;
+        if requested.is_none() && (config.interval == 0 || last_audit.elapsed() < interval) {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            continue;
+        }
+        if let Some(path) = requested {
+            let plot_info = plots
+                .lock()
+                .await
+                .get(&PathInfo::new(path.clone()))
+                .cloned();
+            match plot_info {
+                Some(plot_info) if plot_info.reader.compression_level() > 0 => {
+                    plot_registry
+                        .record_check(
+                            &path,
+                            Err("compressed plots are only checked by lookups".to_string()),
+                        )
+                        .await;
+                }
+                Some(plot_info) => {
+                    let path = path.to_string_lossy().to_string();
+                    check_plot(&config, &path, &plot_info, &plot_registry, &events).await;
+                    audit_log.record(&path, now());
+                }
+                None => debug!("Plot {:?} is no longer farmed, skipping its recheck", path),
+            }
+            continue;
+        }
+        last_audit = Instant::now();
+        //Proofs of compressed plots need the decompressor that serves signage points, they are left out
+        let farmed: HashMap<String, Arc<PlotInfo>> = plots
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, info)| info.reader.compression_level() == 0)
+            .map(|(path, info)| (path.path.to_string_lossy().to_string(), info.clone()))
+            .collect();
+        let Some((path, plot_info)) = audit_log
+            .next(farmed.keys())
+            .and_then(|p| farmed.get_key_value(p))
+        else {
+            continue;
+        };
+        check_plot(&config, path, plot_info, &plot_registry, &events).await;
+        //Failed plots are checked again once the rest of the farm had its turn
+        audit_log.record(path, now());
+        let farmed: Vec<String> = farmed.into_keys().collect();
+        audit_log.save(&farmed);
+    }
+    info!("Plot auditor stopped");
+}
+
+async fn check_plot(
+    config: &PlotAuditConfig,
+    path: &str,
+    plot_info: &PlotInfo,
+    plot_registry: &PlotRegistry,
+    events: &broadcast::Sender<FarmerEvent>,
+) {
+    let start = Instant::now();
+    let result = audit_plot(plot_info, config.challenges).await;
+    match &result {
+        Ok(proofs) => {
+            debug!(
+                "Audited plot {path}, {proofs} proofs valid in {}ms",
+                start.elapsed().as_millis()
+            );
+        }
+        Err(error) => {
+            warn!("Plot {path} failed its audit: {error}");
+            let _ = events.send(FarmerEvent::new(FarmerEventKind::PlotAuditFailed {
+                path: path.to_string(),
+                error: error.clone(),
+            }));
+        }
+    }
+    plot_registry
+        .record_check(Path::new(path), result.map(|_| ()))
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_through_unchecked_then_oldest() {
+        let mut audit_log = PlotAuditLog::default();
+        let plots: Vec<String> = ["a", "b", "c"].iter().map(|p| p.to_string()).collect();
+        audit_log.record("a", 100);
+        audit_log.record("c", 50);
+        assert_eq!(audit_log.next(plots.iter()).map(String::as_str), Some("b"));
+        audit_log.record("b", 200);
+        assert_eq!(audit_log.next(plots.iter()).map(String::as_str), Some("c"));
+        audit_log.save(&plots[..2]);
+        assert_eq!(audit_log.last_verified("c"), None);
+    }
+}
@@ -0,0 +1,150 @@
+use dg_xch_core::blockchain::sized_bytes::{Bytes32, Bytes48};
+use dg_xch_core::plots::PlotMemo;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlotKind {
+    //Plotted with a pool public key
+    Og,
+    //Plotted with a pool contract (plot NFT) puzzle hash
+    Nft,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnfarmableReason {
+    MissingFarmerKey(Bytes48),
+    MissingPoolKey(Bytes48),
+    UnknownPoolContract(Bytes32),
+    //Another path to the same plot, like a symlink or mergerfs pool, is farmed instead
+    Alias(String),
+}
+impl Display for UnfarmableReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnfarmableReason::MissingFarmerKey(key) => {
+                write!(f, "farmer key {key} is not in farmer_info")
+            }
+            UnfarmableReason::MissingPoolKey(key) => {
+                write!(f, "pool key {key} is not in farmer_info")
+            }
+            UnfarmableReason::UnknownPoolContract(hash) => {
+                write!(f, "pool contract {hash} matches no configured launcher")
+            }
+            UnfarmableReason::Alias(path) => write!(f, "same plot as {path}, which is farmed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnfarmablePlot {
+    pub path: String,
+    pub kind: PlotKind,
+    pub reason: UnfarmableReason,
+}
+
+//Checks the plot memo against the configured keys, remote harvesters pass no contracts and accept any
+pub fn classify_plot(
+    memo: &PlotMemo,
+    farmer_public_keys: &[Bytes48],
+    pool_public_keys: &[Bytes48],
+    pool_contract_hashes: &[Bytes32],
+) -> Result<PlotKind, (PlotKind, UnfarmableReason)> {
+    let kind = if memo.pool_contract_puzzle_hash.is_some() {
+        PlotKind::Nft
+    } else {
+        PlotKind::Og
+    };
+    if let Some(key) = &memo.pool_public_key {
+        if !pool_public_keys.contains(key) {
+            return Err((kind, UnfarmableReason::MissingPoolKey(*key)));
+        }
+    } else if let Some(hash) = &memo.pool_contract_puzzle_hash {
+        if !pool_contract_hashes.is_empty() && !pool_contract_hashes.contains(hash) {
+            return Err((kind, UnfarmableReason::UnknownPoolContract(*hash)));
+        }
+    }
+    if !farmer_public_keys.contains(&memo.farmer_public_key) {
+        return Err((
+            kind,
+            UnfarmableReason::MissingFarmerKey(memo.farmer_public_key),
+        ));
+    }
+    Ok(kind)
+}
+
+//Plots found on disk that the configured keys can not farm, per harvester
+#[derive(Debug, Default)]
+pub struct UnfarmablePlots {
+    inner: Mutex<BTreeMap<Uuid, BTreeMap<String, UnfarmablePlot>>>,
+}
+impl UnfarmablePlots {
+    //Replaces the entries of the scanned directories, changes are logged so a rescan does not repeat every plot
+    pub async fn update(
+        &self,
+        harvester_id: Uuid,
+        scanned_dirs: &[PathBuf],
+        found: Vec<UnfarmablePlot>,
+    ) {
+        let mut inner = self.inner.lock().await;
+        let plots = inner.entry(harvester_id).or_default();
+        let mut previous = BTreeMap::new();
+        plots.retain(|path, plot| {
+            let scanned = Path::new(path)
+                .parent()
+                .is_some_and(|dir| scanned_dirs.iter().any(|d| d == dir));
+            if scanned {
+                previous.insert(path.clone(), plot.clone());
+            }
+            !scanned
+        });
+        for plot in found {
+            if previous.remove(&plot.path).as_ref() != Some(&plot) {
+                warn!("Plot {} can not be farmed: {}", plot.path, plot.reason);
+            }
+            plots.insert(plot.path.clone(), plot);
+        }
+        for path in previous.keys() {
+            info!("Plot {} is no longer unfarmable", path);
+        }
+    }
+    pub async fn snapshot(&self) -> BTreeMap<Uuid, Vec<UnfarmablePlot>> {
+        self.inner
+            .lock()
+            .await
+            .iter()
+            .map(|(id, plots)| (*id, plots.values().cloned().collect()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plot(path: &str) -> UnfarmablePlot {
+        UnfarmablePlot {
+            path: path.to_string(),
+            kind: PlotKind::Og,
+            reason: UnfarmableReason::MissingFarmerKey(Bytes48::default()),
+        }
+    }
+
+    #[tokio::test]
+    async fn rescans_only_replace_scanned_directories() {
+        let registry = UnfarmablePlots::default();
+        let id = Uuid::new_v4();
+        let dirs = [PathBuf::from("/a"), PathBuf::from("/b")];
+        registry
+            .update(id, &dirs, vec![plot("/a/1.plot"), plot("/b/2.plot")])
+            .await;
+        registry.update(id, &dirs[..1], vec![]).await;
+        let snapshot = registry.snapshot().await;
+        assert_eq!(snapshot[&id], vec![plot("/b/2.plot")]);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::farmer::config::BladebitHarvesterConfig;
+use log::{error, warn};
+use std::fmt::{Display, Formatter};
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+//Directories longer than this need the \\?\ prefix, CreateFile reserves 12 chars for an 8.3 file name
+const MAX_DIR_PATH: usize = 248;
+const INVALID_CHARS: [char; 6] = ['<', '>', '"', '|', '?', '*'];
+const VERBATIM_PREFIX: &str = r"\\?\";
+const VERBATIM_UNC_PREFIX: &str = r"\\?\UNC\";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlotDirError {
+    pub path: String,
+    pub reason: String,
+}
+impl Display for PlotDirError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid plot directory {:?}: {}", self.path, self.reason)
+    }
+}
+impl From<PlotDirError> for Error {
+    fn from(e: PlotDirError) -> Self {
+        Error::new(ErrorKind::InvalidInput, e.to_string())
+    }
+}
+
+//Valid plot directories of the config, invalid ones are logged and skipped
+pub fn plot_dirs(bb_config: &BladebitHarvesterConfig) -> Vec<PathBuf> {
+    bb_config
+        .plot_directories
+        .iter()
+        .filter_map(|raw| match normalize_plot_dir(raw) {
+            Ok(dir) => {
+                if let Some(warning) = check_plot_dir(&dir) {
+                    warn!("{warning}");
+                }
+                Some(dir)
+            }
+            Err(e) => {
+                error!("{e}");
+                None
+            }
+        })
+        .collect()
+}
+
+pub fn validate_plot_dirs(bb_config: &BladebitHarvesterConfig) -> Vec<PlotDirError> {
+    bb_config
+        .plot_directories
+        .iter()
+        .filter_map(|raw| normalize_plot_dir(raw).err())
+        .collect()
+}
+
+pub fn normalize_plot_dir(raw: &str) -> Result<PathBuf, PlotDirError> {
+    //Quotes are kept when paths with spaces are copied from explorer
+    let trimmed = raw.trim().trim_matches('"').trim();
+    if trimmed.is_empty() {
+        return Err(invalid(raw, "path is empty"));
+    }
+    if trimmed.contains('\0') {
+        return Err(invalid(raw, "path contains a NUL character"));
+    }
+    if cfg!(windows) && is_windows_style(trimmed) {
+        normalize_windows_path(trimmed).map(PathBuf::from)
+    } else {
+        Ok(PathBuf::from(trimmed))
+    }
+}
+
+//Explains why an existing config entry can not be scanned, mapped drives are only visible to the session that mapped them
+pub fn check_plot_dir(dir: &Path) -> Option<String> {
+    if dir.is_dir() {
+        return None;
+    }
+    let raw = dir.to_string_lossy();
+    if let Some(drive) = drive_letter(raw.trim_start_matches(VERBATIM_PREFIX)) {
+        if !Path::new(&format!(r"{drive}:\")).exists() {
+            return Some(format!(
+                "Plot directory {:?} is not available, drive {drive}: is not mounted. Mapped network drives are not visible to services or other users, use the UNC path (\\\\server\\share) instead",
+                dir
+            ));
+        }
+    }
+    Some(format!(
+        "Plot directory {:?} does not exist or is not a directory",
+        dir
+    ))
+}
+
+fn is_windows_style(path: &str) -> bool {
+    path.starts_with(r"\\") || path.starts_with("//") || drive_letter(path).is_some()
+}
+
+fn drive_letter(path: &str) -> Option<char> {
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), Some(':')) if letter.is_ascii_alphabetic() => {
+            Some(letter.to_ascii_uppercase())
+        }
+        _ => None,
+    }
+}
+
+fn invalid(path: &str, reason: &str) -> PlotDirError {
+    PlotDirError {
+        path: path.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+//Normalizes separators, resolves . and .. and adds the \\?\ prefix to long paths
+fn normalize_windows_path(raw: &str) -> Result<String, PlotDirError> {
+    let path = raw.replace('/', r"\");
+    let (root, rest) = if let Some(unc) = path.strip_prefix(VERBATIM_UNC_PREFIX) {
+        unc_root(raw, unc)?
+    } else if let Some(local) = path.strip_prefix(VERBATIM_PREFIX) {
+        drive_root(raw, local)?
+    } else if let Some(unc) = path.strip_prefix(r"\\") {
+        unc_root(raw, unc)?
+    } else {
+        drive_root(raw, &path)?
+    };
+    let mut components: Vec<&str> = vec![];
+    for component in rest.split('\\').filter(|c| !c.is_empty()) {
+        match component {
+            "." => {}
+            ".." => {
+                if components.pop().is_none() {
+                    return Err(invalid(raw, "path goes above its root"));
+                }
+            }
+            c => {
+                if let Some(ch) = c
+                    .chars()
+                    .find(|ch| INVALID_CHARS.contains(ch) || *ch == ':')
+                {
+                    return Err(invalid(
+                        raw,
+                        &format!("{c:?} contains the invalid character {ch:?}"),
+                    ));
+                }
+                if c.ends_with(' ') || c.ends_with('.') {
+                    return Err(invalid(
+                        raw,
+                        &format!("{c:?} ends with a space or dot, Windows strips these"),
+                    ));
+                }
+                components.push(c);
+            }
+        }
+    }
+    let normalized = match &root {
+        Root::Drive(letter) => format!(r"{letter}:\{}", components.join(r"\")),
+        Root::Unc(server, share) => {
+            let mut p = format!(r"\\{server}\{share}");
+            for c in &components {
+                p.push('\\');
+                p.push_str(c);
+            }
+            p
+        }
+    };
+    if normalized.chars().count() < MAX_DIR_PATH {
+        return Ok(normalized);
+    }
+    Ok(match root {
+        Root::Drive(_) => format!("{VERBATIM_PREFIX}{normalized}"),
+        Root::Unc(_, _) => format!(
+            "{VERBATIM_UNC_PREFIX}{}",
+            normalized.trim_start_matches('\\')
+        ),
+    })
+}
+
+enum Root {
+    Drive(char),
+    Unc(String, String),
+}
+
+fn unc_root<'a>(raw: &str, path: &'a str) -> Result<(Root, &'a str), PlotDirError> {
+    let mut parts = path.trim_start_matches('\\').splitn(3, '\\');
+    let server = parts.next().unwrap_or_default();
+    let share = parts.next().unwrap_or_default();
+    if server.is_empty() || share.is_empty() {
+        return Err(invalid(
+            raw,
+            r"UNC paths need a server and share name, like \\nas\plots",
+        ));
+    }
+    Ok((
+        Root::Unc(server.to_string(), share.to_string()),
+        parts.next().unwrap_or_default(),
+    ))
+}
+
+fn drive_root<'a>(raw: &str, path: &'a str) -> Result<(Root, &'a str), PlotDirError> {
+    let Some(letter) = drive_letter(path) else {
+        return Err(invalid(
+            raw,
+            r"expected a drive letter (D:\plots) or UNC path (\\nas\plots)",
+        ));
+    };
+    let rest = &path[2..];
+    if !rest.is_empty() && !rest.starts_with('\\') {
+        return Err(invalid(
+            raw,
+            &format!(r"drive relative path, use {letter}:\{rest} instead"),
+        ));
+    }
+    Ok((Root::Drive(letter), rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drive_paths() {
+        assert_eq!(normalize_windows_path(r"D:\plots").unwrap(), r"D:\plots");
+        assert_eq!(normalize_windows_path("d:/plots/").unwrap(), r"D:\plots");
+        assert_eq!(normalize_windows_path("E:").unwrap(), r"E:\");
+        assert_eq!(
+            normalize_windows_path(r"D:\plots\\old\..\new\.").unwrap(),
+            r"D:\plots\new"
+        );
+    }
+
+    #[test]
+    fn drive_relative_path_is_rejected() {
+        let err = normalize_windows_path("D:plots").unwrap_err();
+        assert_eq!(err.path, "D:plots");
+        assert!(err.reason.contains(r"D:\plots"));
+    }
+
+    #[test]
+    fn unc_paths() {
+        assert_eq!(
+            normalize_windows_path(r"\\nas\plots\disk1").unwrap(),
+            r"\\nas\plots\disk1"
+        );
+        assert_eq!(
+            normalize_windows_path("//nas/plots").unwrap(),
+            r"\\nas\plots"
+        );
+        assert!(normalize_windows_path(r"\\nas").is_err());
+        assert!(normalize_windows_path(r"\\nas\").is_err());
+    }
+
+    #[test]
+    fn verbatim_paths() {
+        assert_eq!(
+            normalize_windows_path(r"\\?\D:\plots").unwrap(),
+            r"D:\plots"
+        );
+        assert_eq!(
+            normalize_windows_path(r"\\?\UNC\nas\plots").unwrap(),
+            r"\\nas\plots"
+        );
+    }
+
+    #[test]
+    fn long_paths_get_verbatim_prefix() {
+        let long = "a".repeat(100);
+        let drive = normalize_windows_path(&format!(r"D:\{long}\{long}\{long}")).unwrap();
+        assert!(drive.starts_with(r"\\?\D:\"));
+        let unc = normalize_windows_path(&format!(r"\\nas\plots\{long}\{long}\{long}")).unwrap();
+        assert!(unc.starts_with(r"\\?\UNC\nas\plots\"));
+    }
+
+    #[test]
+    fn malformed_components_are_rejected() {
+        let err = normalize_windows_path(r"D:\plots\bad|name").unwrap_err();
+        assert!(err.reason.contains("bad|name"));
+        assert!(normalize_windows_path(r"D:\plots \disk").is_err());
+        assert!(normalize_windows_path(r"D:\..").is_err());
+    }
+
+    #[test]
+    fn config_values_are_trimmed() {
+        assert_eq!(
+            normalize_plot_dir(" \"/mnt/plots\" ").unwrap(),
+            PathBuf::from("/mnt/plots")
+        );
+        assert!(normalize_plot_dir("  ").is_err());
+    }
+}
@@ -0,0 +1,587 @@
+use crate::farmer::config::PlotIoOptions;
+use crate::harvesters::numa::NumaRuntimes;
+use crate::harvesters::object_storage::{ObjectFile, ObjectStore};
+use crate::platform::{advise_random, open_direct, read_at};
+use dg_xch_core::plots::{PlotFile, PlotHeader, PlotTable};
+use dg_xch_pos::plots::plot_reader::read_plot_file_header_async;
+use log::{debug, error, warn};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::io::{Error, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+use tokio::sync::Mutex;
+use tokio::task::{spawn_blocking, JoinHandle};
+
+const ALIGNMENT: usize = 4096;
+const DEFAULT_READ_AHEAD: usize = 64 * 1024;
+const DEFAULT_BUFFER_POOL_SIZE: usize = 4;
+
+//Per directory options and the RAM cache shared by all plots of a harvester
+#[derive(Debug, Default)]
+pub struct PlotIoConfig {
+    pub options: HashMap<PathBuf, PlotIoOptions>,
+    pub ram_cache: Arc<RamCache>,
+    pub numa: Arc<NumaRuntimes>,
+    //Keyed by the s3:// root that stands in for the plot directory
+    pub object_stores: HashMap<PathBuf, Arc<ObjectStore>>,
+}
+impl PlotIoConfig {
+    pub fn options_for(&self, dir: &Path) -> PlotIoOptions {
+        self.options.get(dir).cloned().unwrap_or_default()
+    }
+    pub fn object_store_for(&self, dir: &Path) -> Option<Arc<ObjectStore>> {
+        self.object_stores.get(dir).cloned()
+    }
+}
+
+#[derive(Debug)]
+pub struct IoPlot {
+    file: Arc<Mutex<PlotIoFile>>,
+    pub filename: Arc<PathBuf>,
+    header: PlotHeader,
+    plot_size: u64,
+}
+impl IoPlot {
+    pub async fn new(
+        filename: &Path,
+        options: &PlotIoOptions,
+        ram_cache: &Arc<RamCache>,
+    ) -> Result<Self, Error> {
+        let (_, header) = read_plot_file_header_async(filename).await?;
+        let plot_size = tokio::fs::metadata(filename).await?.len();
+        let filename = Arc::new(filename.to_path_buf());
+        //C2 is already held in memory by the PlotReader, C1 is read from disk on every lookup
+        let cached = if options.ram_cache && ram_cache.is_enabled() {
+            let (offset, size) = table_region(&header, plot_size, PlotTable::C1);
+            let region = CachedRegion {
+                cache: ram_cache.clone(),
+                path: filename.clone(),
+                offset,
+                size,
+            };
+            region.load().await;
+            Some(region)
+        } else {
+            None
+        };
+        let path = filename.clone();
+        let options = options.clone();
+        let source = spawn_blocking(move || PlotIoSource::open(&path, &options, plot_size))
+            .await
+            .map_err(|e| Error::other(format!("{:?}", e)))??;
+        Ok(Self {
+            file: Arc::new(Mutex::new(PlotIoFile {
+                source,
+                pos: 0,
+                source_pos: None,
+                seeking: false,
+                len: plot_size,
+                cached,
+            })),
+            filename,
+            header,
+            plot_size,
+        })
+    }
+    pub async fn from_object(
+        store: &Arc<ObjectStore>,
+        filename: &Path,
+        header: PlotHeader,
+    ) -> Result<Self, Error> {
+        let (object, plot_size) = ObjectFile::open(store.clone(), filename, &header).await?;
+        Ok(Self {
+            file: Arc::new(Mutex::new(PlotIoFile {
+                source: PlotIoSource::Object(object),
+                pos: 0,
+                source_pos: None,
+                seeking: false,
+                len: plot_size,
+                cached: None,
+            })),
+            filename: Arc::new(filename.to_path_buf()),
+            header,
+            plot_size,
+        })
+    }
+}
+impl Display for IoPlot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            self.filename
+                .file_name()
+                .map(|s| s.to_str().unwrap_or("Invalid Path"))
+                .unwrap_or("Invalid Path"),
+        )
+    }
+}
+impl<'a> PlotFile<'a, PlotIoFile> for IoPlot {
+    fn header(&'a self) -> &'a PlotHeader {
+        &self.header
+    }
+
+    fn plot_size(&'a self) -> &'a u64 {
+        &self.plot_size
+    }
+
+    //The PlotReader of dg_xch_pos reads P7 parks itself through file() and never calls this, the
+    //trait has no way to return an error so a call is logged and finds nothing
+    fn load_p7_park(&'a self, index: u64) -> u128 {
+        error!("Unexpected P7 park read {index} of {self}, parks are read through the plot reader");
+        0
+    }
+
+    fn file(&'a self) -> Arc<Mutex<PlotIoFile>> {
+        self.file.clone()
+    }
+}
+
+//Serves reads from the RAM cache when possible, the underlying file is only seeked on a miss
+#[derive(Debug)]
+pub struct PlotIoFile {
+    source: PlotIoSource,
+    pos: u64,
+    source_pos: Option<u64>,
+    seeking: bool,
+    len: u64,
+    cached: Option<CachedRegion>,
+}
+impl AsyncRead for PlotIoFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        if let Some(region) = &this.cached {
+            if this.pos >= region.offset && this.pos < region.offset + region.size {
+                match region.cache.get(&region.path) {
+                    Some(data) if ((this.pos - region.offset) as usize) < data.len() => {
+                        let start = (this.pos - region.offset) as usize;
+                        let count = buf.remaining().min(data.len() - start);
+                        buf.put_slice(&data[start..start + count]);
+                        this.pos += count as u64;
+                        return Poll::Ready(Ok(()));
+                    }
+                    Some(_) => {}
+                    None => region.reload(),
+                }
+            }
+        }
+        while this.source_pos != Some(this.pos) {
+            if !this.seeking {
+                Pin::new(&mut this.source).start_seek(SeekFrom::Start(this.pos))?;
+                this.seeking = true;
+            }
+            let result = ready!(Pin::new(&mut this.source).poll_complete(cx));
+            this.seeking = false;
+            this.source_pos = Some(result?);
+        }
+        let filled = buf.filled().len();
+        let result = ready!(Pin::new(&mut this.source).poll_read(cx, buf));
+        if result.is_ok() {
+            this.pos += (buf.filled().len() - filled) as u64;
+            this.source_pos = Some(this.pos);
+        } else {
+            this.source_pos = None;
+        }
+        Poll::Ready(result)
+    }
+}
+impl AsyncSeek for PlotIoFile {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        let this = self.get_mut();
+        this.pos = seek_position(this.pos, this.len, position)?;
+        Ok(())
+    }
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        Poll::Ready(Ok(self.pos))
+    }
+}
+
+#[derive(Debug)]
+enum PlotIoSource {
+    Buffered(tokio::fs::File),
+    Direct(DirectFile),
+    Object(ObjectFile),
+}
+impl PlotIoSource {
+    fn open(path: &Path, options: &PlotIoOptions, len: u64) -> Result<Self, Error> {
+        if options.direct_io {
+            match open_direct(path) {
+                Ok(file) => {
+                    let read_ahead = options
+                        .read_ahead
+                        .map(|r| r as usize)
+                        .unwrap_or(DEFAULT_READ_AHEAD)
+                        .max(ALIGNMENT)
+                        .next_multiple_of(ALIGNMENT);
+                    return Ok(PlotIoSource::Direct(DirectFile {
+                        file: Arc::new(file),
+                        len,
+                        pos: 0,
+                        read_ahead,
+                        buffer_pool_size: options
+                            .buffer_pool_size
+                            .unwrap_or(DEFAULT_BUFFER_POOL_SIZE)
+                            .max(1),
+                        buffers: VecDeque::new(),
+                        pending: None,
+                    }));
+                }
+                Err(e) => {
+                    warn!(
+                        "Direct IO not supported for {:?}, falling back to buffered reads: {:?}",
+                        path, e
+                    );
+                }
+            }
+        }
+        let file = std::fs::File::open(path)?;
+        if options.read_ahead == Some(0) {
+            advise_random(&file);
+        }
+        Ok(PlotIoSource::Buffered(tokio::fs::File::from_std(file)))
+    }
+}
+impl AsyncRead for PlotIoSource {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            PlotIoSource::Buffered(f) => Pin::new(f).poll_read(cx, buf),
+            PlotIoSource::Direct(f) => Pin::new(f).poll_read(cx, buf),
+            PlotIoSource::Object(f) => Pin::new(f).poll_read(cx, buf),
+        }
+    }
+}
+impl AsyncSeek for PlotIoSource {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        match self.get_mut() {
+            PlotIoSource::Buffered(f) => Pin::new(f).start_seek(position),
+            PlotIoSource::Direct(f) => Pin::new(f).start_seek(position),
+            PlotIoSource::Object(f) => Pin::new(f).start_seek(position),
+        }
+    }
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        match self.get_mut() {
+            PlotIoSource::Buffered(f) => Pin::new(f).poll_complete(cx),
+            PlotIoSource::Direct(f) => Pin::new(f).poll_complete(cx),
+            PlotIoSource::Object(f) => Pin::new(f).poll_complete(cx),
+        }
+    }
+}
+
+//Reads bypass the OS page cache, so reads are done in aligned read_ahead sized blocks
+//and the most recent buffer_pool_size blocks are kept to serve the small reads of a lookup
+#[derive(Debug)]
+struct DirectFile {
+    file: Arc<std::fs::File>,
+    len: u64,
+    pos: u64,
+    read_ahead: usize,
+    buffer_pool_size: usize,
+    buffers: VecDeque<(u64, AlignedBuffer)>,
+    pending: Option<(u64, JoinHandle<Result<AlignedBuffer, Error>>)>,
+}
+impl AsyncRead for DirectFile {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if buf.remaining() == 0 || this.pos >= this.len {
+                return Poll::Ready(Ok(()));
+            }
+            let start = this.pos - this.pos % this.read_ahead as u64;
+            if let Some((_, buffer)) = this.buffers.iter().find(|(s, _)| *s == start) {
+                let offset = (this.pos - start) as usize;
+                let data = buffer.data();
+                if offset >= data.len() {
+                    return Poll::Ready(Ok(()));
+                }
+                let count = buf.remaining().min(data.len() - offset);
+                buf.put_slice(&data[offset..offset + count]);
+                this.pos += count as u64;
+                return Poll::Ready(Ok(()));
+            }
+            match &mut this.pending {
+                Some((pending_start, handle)) => {
+                    let result = ready!(Pin::new(handle).poll(cx));
+                    let pending_start = *pending_start;
+                    this.pending = None;
+                    let buffer = result.map_err(|e| Error::other(format!("{:?}", e)))??;
+                    if this.buffers.len() >= this.buffer_pool_size {
+                        this.buffers.pop_front();
+                    }
+                    this.buffers.push_back((pending_start, buffer));
+                }
+                None => {
+                    let file = this.file.clone();
+                    let size = this.read_ahead;
+                    this.pending = Some((
+                        start,
+                        spawn_blocking(move || read_aligned(&file, start, size)),
+                    ));
+                }
+            }
+        }
+    }
+}
+impl AsyncSeek for DirectFile {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        let this = self.get_mut();
+        this.pos = seek_position(this.pos, this.len, position)?;
+        Ok(())
+    }
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        Poll::Ready(Ok(self.pos))
+    }
+}
+
+pub(crate) fn seek_position(pos: u64, len: u64, position: SeekFrom) -> Result<u64, Error> {
+    match position {
+        SeekFrom::Start(pos) => Some(pos),
+        SeekFrom::End(offset) => len.checked_add_signed(offset),
+        SeekFrom::Current(offset) => pos.checked_add_signed(offset),
+    }
+    .ok_or_else(|| {
+        Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Invalid seek to a negative or overflowing position",
+        )
+    })
+}
+
+//Plot table regions kept in RAM, the least recently used plot is evicted to stay within budget
+#[derive(Default)]
+pub struct RamCache {
+    budget: u64,
+    state: std::sync::Mutex<RamCacheState>,
+}
+#[derive(Default)]
+struct RamCacheState {
+    used: u64,
+    tick: u64,
+    entries: HashMap<Arc<PathBuf>, (Arc<Vec<u8>>, u64)>,
+    loading: HashSet<Arc<PathBuf>>,
+}
+impl RamCache {
+    pub fn new(budget: u64) -> Self {
+        Self {
+            budget,
+            state: Default::default(),
+        }
+    }
+    pub fn is_enabled(&self) -> bool {
+        self.budget > 0
+    }
+    //Drops every cached region, plots reopened later fill the cache again
+    pub fn clear(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.entries = HashMap::new();
+            state.used = 0;
+        }
+    }
+    fn get(&self, path: &Arc<PathBuf>) -> Option<Arc<Vec<u8>>> {
+        let mut state = self.state.lock().ok()?;
+        state.tick += 1;
+        let tick = state.tick;
+        state.entries.get_mut(path).map(|(data, last_used)| {
+            *last_used = tick;
+            data.clone()
+        })
+    }
+    fn start_loading(&self, path: &Arc<PathBuf>) -> bool {
+        match self.state.lock() {
+            Ok(mut state) => {
+                !state.entries.contains_key(path) && state.loading.insert(path.clone())
+            }
+            Err(_) => false,
+        }
+    }
+    fn finish_loading(&self, path: &Arc<PathBuf>, data: Option<Vec<u8>>) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        state.loading.remove(path);
+        let Some(data) = data else {
+            return;
+        };
+        let size = data.len() as u64;
+        if size > self.budget {
+            warn!(
+                "RAM cache budget of {} bytes is too small for {:?} ({} bytes)",
+                self.budget, path, size
+            );
+            return;
+        }
+        while state.used + size > self.budget {
+            let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+            if let Some((evicted, _)) = state.entries.remove(&oldest) {
+                debug!("Evicted {:?} from RAM cache", oldest);
+                state.used -= evicted.len() as u64;
+            }
+        }
+        state.tick += 1;
+        let tick = state.tick;
+        state.used += size;
+        state.entries.insert(path.clone(), (Arc::new(data), tick));
+    }
+}
+impl std::fmt::Debug for RamCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("RamCache");
+        debug.field("budget", &self.budget);
+        if let Ok(state) = self.state.lock() {
+            debug
+                .field("used", &state.used)
+                .field("entries", &state.entries.len());
+        }
+        debug.finish()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedRegion {
+    cache: Arc<RamCache>,
+    path: Arc<PathBuf>,
+    offset: u64,
+    size: u64,
+}
+impl CachedRegion {
+    async fn load(&self) {
+        if self.cache.start_loading(&self.path) {
+            self.fetch().await;
+        }
+    }
+    //Evicted regions are reloaded in the background, the current read goes to disk
+    fn reload(&self) {
+        if self.cache.start_loading(&self.path) {
+            let region = self.clone();
+            tokio::spawn(async move { region.fetch().await });
+        }
+    }
+    async fn fetch(&self) {
+        let path = self.path.clone();
+        let (offset, size) = (self.offset, self.size as usize);
+        let data = match spawn_blocking(move || read_region(&path, offset, size)).await {
+            Ok(Ok(data)) => Some(data),
+            Ok(Err(e)) => {
+                warn!("Failed to load {:?} into RAM cache: {:?}", self.path, e);
+                None
+            }
+            Err(e) => {
+                warn!("Failed to load {:?} into RAM cache: {:?}", self.path, e);
+                None
+            }
+        };
+        self.cache.finish_loading(&self.path, data);
+    }
+}
+
+pub(crate) fn table_region(header: &PlotHeader, plot_size: u64, table: PlotTable) -> (u64, u64) {
+    let pointers = match header {
+        PlotHeader::V1(h) => &h.table_begin_pointers,
+        PlotHeader::V2(h) => &h.table_begin_pointers,
+    };
+    let address = pointers[table as usize];
+    let end = pointers
+        .iter()
+        .filter(|a| **a > address)
+        .fold(plot_size, |end, a| end.min(*a));
+    (address, end.saturating_sub(address))
+}
+
+fn read_region(path: &Path, offset: u64, size: usize) -> Result<Vec<u8>, Error> {
+    read_file_region(&std::fs::File::open(path)?, offset, size)
+}
+
+pub(crate) fn read_file_region(
+    file: &std::fs::File,
+    offset: u64,
+    size: usize,
+) -> Result<Vec<u8>, Error> {
+    let mut data = vec![0u8; size];
+    let mut read = 0;
+    while read < size {
+        let count = read_at(file, &mut data[read..], offset + read as u64)?;
+        if count == 0 {
+            break;
+        }
+        read += count;
+    }
+    data.truncate(read);
+    Ok(data)
+}
+
+#[repr(C, align(4096))]
+#[derive(Clone, Copy)]
+struct AlignedBlock([u8; ALIGNMENT]);
+
+struct AlignedBuffer {
+    blocks: Vec<AlignedBlock>,
+    len: usize,
+}
+impl AlignedBuffer {
+    fn new(size: usize) -> Self {
+        Self {
+            blocks: vec![AlignedBlock([0; ALIGNMENT]); size / ALIGNMENT],
+            len: 0,
+        }
+    }
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        //Safety: AlignedBlock is a plain byte array, the blocks are contiguous in the Vec
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                self.blocks.as_mut_ptr() as *mut u8,
+                self.blocks.len() * ALIGNMENT,
+            )
+        }
+    }
+    fn data(&self) -> &[u8] {
+        //Safety: len never exceeds the allocated blocks, see read_aligned
+        unsafe { std::slice::from_raw_parts(self.blocks.as_ptr() as *const u8, self.len) }
+    }
+}
+impl std::fmt::Debug for AlignedBuffer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AlignedBuffer")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+fn read_aligned(file: &std::fs::File, offset: u64, size: usize) -> Result<AlignedBuffer, Error> {
+    let mut buffer = AlignedBuffer::new(size);
+    let mut read = 0;
+    while read < size {
+        let count = read_at(
+            file,
+            &mut buffer.as_mut_slice()[read..],
+            offset + read as u64,
+        )?;
+        read += count;
+        //A short read is the end of the file, further reads would not be aligned
+        if count == 0 || count % ALIGNMENT != 0 {
+            break;
+        }
+    }
+    buffer.len = read.min(size);
+    Ok(buffer)
+}
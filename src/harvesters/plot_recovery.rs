@@ -0,0 +1,268 @@
+use crate::farmer::config::PlotRecoveryConfig;
+use crate::farmer::events::{FarmerEvent, FarmerEventKind};
+use crate::farmer::{PathInfo, PlotInfo};
+use crate::harvesters::plot_aliases::plot_id;
+use crate::harvesters::plot_audit::audit_plot;
+use crate::harvesters::plot_io::{IoPlot, PlotIoConfig};
+use crate::harvesters::plot_registry::PlotRegistry;
+use dg_xch_pos::plots::decompressor::DecompressorPool;
+use dg_xch_pos::plots::plot_reader::PlotReader;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Mutex};
+
+//Challenges looked up on a reopened plot before it farms again
+const RECOVERY_CHALLENGES: usize = 1;
+
+//Lookups fail with NotFound when a plot has no proof for a challenge, timeouts come from the
+//decompressor queue. Anything else means the plot file could not be read
+pub fn is_io_failure(e: &Error) -> bool {
+    !matches!(
+        e.kind(),
+        ErrorKind::NotFound | ErrorKind::TimedOut | ErrorKind::InvalidInput
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryOutcome {
+    Recovered,
+    Retry { attempt: u32, delay: Duration },
+    Unreadable { attempts: u32 },
+}
+
+#[derive(Debug)]
+struct PendingRecovery {
+    attempts: u32,
+    retry_at: Instant,
+}
+
+//Plots waiting to be reopened after an IO error, with the failed reopens so far
+#[derive(Debug, Default)]
+pub struct PlotRecoveries {
+    config: PlotRecoveryConfig,
+    pending: HashMap<PathBuf, PendingRecovery>,
+}
+impl PlotRecoveries {
+    pub fn new(config: PlotRecoveryConfig) -> Self {
+        Self {
+            config,
+            pending: HashMap::new(),
+        }
+    }
+    //Delay until the first reopen, None when recovery is off or the plot is already waiting
+    pub fn schedule(&mut self, path: &Path, now: Instant) -> Option<Duration> {
+        if self.config.attempts == 0 || self.pending.contains_key(path) {
+            return None;
+        }
+        let delay = Duration::from_secs(self.config.backoff);
+        self.pending.insert(
+            path.to_path_buf(),
+            PendingRecovery {
+                attempts: 0,
+                retry_at: now + delay,
+            },
+        );
+        Some(delay)
+    }
+    pub fn due(&self, now: Instant) -> Vec<PathBuf> {
+        self.pending
+            .iter()
+            .filter(|(_, p)| p.retry_at <= now)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+    pub fn record(&mut self, path: &Path, recovered: bool, now: Instant) -> RecoveryOutcome {
+        if recovered {
+            self.pending.remove(path);
+            return RecoveryOutcome::Recovered;
+        }
+        let Some(pending) = self.pending.get_mut(path) else {
+            return RecoveryOutcome::Unreadable { attempts: 0 };
+        };
+        pending.attempts += 1;
+        if pending.attempts >= self.config.attempts {
+            let attempts = pending.attempts;
+            self.pending.remove(path);
+            return RecoveryOutcome::Unreadable { attempts };
+        }
+        let delay = Duration::from_secs(self.config.backoff)
+            .saturating_mul(2u32.saturating_pow(pending.attempts));
+        pending.retry_at = now + delay;
+        RecoveryOutcome::Retry {
+            attempt: pending.attempts,
+            delay,
+        }
+    }
+    pub fn forget(&mut self, path: &Path) {
+        self.pending.remove(path);
+    }
+}
+
+//Opens the plot file again with fresh handles, keys are taken from the plot it replaces
+async fn reopen_plot(
+    path: &Path,
+    plot_info: &PlotInfo,
+    io_config: &PlotIoConfig,
+    decompressor_pool: &Arc<DecompressorPool>,
+) -> Result<PlotInfo, String> {
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let plot_file = match io_config.object_store_for(&dir) {
+        Some(store) => IoPlot::from_object(&store, path, plot_info.reader.header().clone()).await,
+        None => IoPlot::new(path, &io_config.options_for(&dir), &io_config.ram_cache).await,
+    }
+    .map_err(|e| format!("reopen failed: {e}"))?;
+    let reader = PlotReader::new(
+        plot_file,
+        Some(decompressor_pool.clone()),
+        Some(decompressor_pool.clone()),
+    )
+    .await
+    .map_err(|e| format!("reopen failed: {e}"))?;
+    if plot_id(reader.header()) != plot_id(plot_info.reader.header()) {
+        return Err("the file now holds another plot".to_string());
+    }
+    Ok(PlotInfo {
+        reader,
+        pool_public_key: plot_info.pool_public_key,
+        pool_contract_puzzle_hash: plot_info.pool_contract_puzzle_hash,
+        plot_public_key: plot_info.plot_public_key,
+        file_size: plot_info.file_size,
+        time_modified: plot_info.time_modified,
+    })
+}
+
+//Reopens and checks plots scheduled after an IO error, a plot that passes replaces the broken
+//one in the farm, one that keeps failing is marked unreadable and stays out of the lookups
+#[allow(clippy::too_many_arguments)]
+pub async fn plot_recoverer(
+    recoveries: Arc<Mutex<PlotRecoveries>>,
+    plots: Arc<Mutex<HashMap<PathInfo, Arc<PlotInfo>>>>,
+    io_config: Arc<PlotIoConfig>,
+    decompressor_pool: Arc<DecompressorPool>,
+    plot_registry: Arc<PlotRegistry>,
+    events: broadcast::Sender<FarmerEvent>,
+    shutdown_signal: Arc<AtomicBool>,
+) {
+    loop {
+        if !shutdown_signal.load(Ordering::Relaxed) {
+            break;
+        }
+        let due = recoveries.lock().await.due(Instant::now());
+        for path in due {
+            let Some(plot_info) = plots
+                .lock()
+                .await
+                .get(&PathInfo::new(path.clone()))
+                .cloned()
+            else {
+                recoveries.lock().await.forget(&path);
+                continue;
+            };
+            let result = match reopen_plot(&path, &plot_info, &io_config, &decompressor_pool).await
+            {
+                Ok(reopened) => audit_plot(&reopened, RECOVERY_CHALLENGES)
+                    .await
+                    .map(|_| reopened),
+                Err(e) => Err(e),
+            };
+            let outcome = recoveries
+                .lock()
+                .await
+                .record(&path, result.is_ok(), Instant::now());
+            let name = path.to_string_lossy().to_string();
+            match (outcome, result) {
+                (RecoveryOutcome::Recovered, Ok(reopened)) => {
+                    info!("Plot {name} is readable again, farming it");
+                    plots
+                        .lock()
+                        .await
+                        .insert(PathInfo::new(path.clone()), Arc::new(reopened));
+                    plot_registry.record_check(&path, Ok(())).await;
+                    let _ = events.send(FarmerEvent::new(FarmerEventKind::PlotRecovered {
+                        path: name,
+                    }));
+                }
+                (RecoveryOutcome::Retry { attempt, delay }, Err(error)) => {
+                    warn!(
+                        "Plot {name} is still unreadable after reopen {attempt}, retrying in {}s: {error}",
+                        delay.as_secs()
+                    );
+                    plot_registry.mark_recovering(&path, error).await;
+                }
+                (RecoveryOutcome::Unreadable { attempts }, Err(error)) => {
+                    warn!("Plot {name} is still unreadable after {attempts} reopens, leaving it out until a recheck: {error}");
+                    plot_registry.mark_unreadable(&path, error.clone()).await;
+                    let _ = events.send(FarmerEvent::new(FarmerEventKind::PlotUnreadable {
+                        path: name,
+                        error,
+                    }));
+                }
+                _ => {}
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    info!("Plot recoverer stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_until_the_plot_is_unreadable() {
+        assert!(is_io_failure(&Error::from(ErrorKind::UnexpectedEof)));
+        assert!(!is_io_failure(&Error::from(ErrorKind::NotFound)));
+
+        let mut recoveries = PlotRecoveries::new(PlotRecoveryConfig {
+            backoff: 10,
+            attempts: 3,
+        });
+        let plot = Path::new("/mnt/a/plot-k32.plot");
+        let start = Instant::now();
+        assert_eq!(
+            recoveries.schedule(plot, start),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(recoveries.schedule(plot, start), None);
+        assert!(recoveries.due(start).is_empty());
+        let at = start + Duration::from_secs(10);
+        assert_eq!(recoveries.due(at), vec![plot.to_path_buf()]);
+        assert_eq!(
+            recoveries.record(plot, false, at),
+            RecoveryOutcome::Retry {
+                attempt: 1,
+                delay: Duration::from_secs(20)
+            }
+        );
+        assert!(recoveries.due(at + Duration::from_secs(19)).is_empty());
+        assert_eq!(
+            recoveries.record(plot, false, at),
+            RecoveryOutcome::Retry {
+                attempt: 2,
+                delay: Duration::from_secs(40)
+            }
+        );
+        assert_eq!(
+            recoveries.record(plot, false, at),
+            RecoveryOutcome::Unreadable { attempts: 3 }
+        );
+        assert!(recoveries.due(at + Duration::from_secs(3600)).is_empty());
+
+        recoveries.schedule(plot, start);
+        assert_eq!(
+            recoveries.record(plot, true, at),
+            RecoveryOutcome::Recovered
+        );
+        let mut disabled = PlotRecoveries::new(PlotRecoveryConfig {
+            backoff: 10,
+            attempts: 0,
+        });
+        assert_eq!(disabled.schedule(plot, start), None);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::farmer::{PathInfo, PlotInfo};
+use dg_xch_core::plots::PlotHeader;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+//Weight of the newest lookup in the moving average
+const SMOOTHING: f64 = 0.2;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlotHealth {
+    Unchecked,
+    Ok,
+    Failed,
+    Excluded,
+    //Left out of lookups after an IO error until a reopen succeeds
+    Recovering,
+    Unreadable,
+}
+impl Display for PlotHealth {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PlotHealth::Unchecked => "unchecked",
+            PlotHealth::Ok => "ok",
+            PlotHealth::Failed => "failed",
+            PlotHealth::Excluded => "excluded",
+            PlotHealth::Recovering => "recovering",
+            PlotHealth::Unreadable => "unreadable",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlotRecord {
+    pub path: String,
+    pub dir: String,
+    pub plot_id: String,
+    pub k: u8,
+    pub compression: u8,
+    pub lookup_ms: Option<f64>,
+    pub proofs: u64,
+    pub health: PlotHealth,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+//Every loaded plot with its lookup and audit results since startup, for the plot browser.
+//Excluded plots are skipped on signage points and kept excluded across restarts, quarantined
+//plots are skipped until they pass a check
+#[derive(Debug, Default)]
+pub struct PlotRegistry {
+    path: Option<PathBuf>,
+    plots: Mutex<HashMap<PathBuf, PlotRecord>>,
+    excluded: Mutex<BTreeSet<PathBuf>>,
+    quarantined: Mutex<HashMap<PathBuf, PlotHealth>>,
+    rechecks: Mutex<VecDeque<PathBuf>>,
+}
+impl PlotRegistry {
+    pub fn load(path: &Path) -> Self {
+        let excluded = match fs::read_to_string(path).map(|s| serde_json::from_str(&s)) {
+            Ok(Ok(excluded)) => excluded,
+            Ok(Err(e)) => {
+                warn!("Ignoring invalid excluded plot list at {:?}: {:?}", path, e);
+                BTreeSet::new()
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => BTreeSet::new(),
+            Err(e) => {
+                warn!("Failed to read excluded plot list at {:?}: {:?}", path, e);
+                BTreeSet::new()
+            }
+        };
+        Self {
+            path: Some(path.to_path_buf()),
+            excluded: Mutex::new(excluded),
+            ..Default::default()
+        }
+    }
+    //Adds newly loaded plots and drops the ones no longer farmed, results of known plots are kept
+    pub async fn sync(&self, loaded: &HashMap<PathInfo, Arc<PlotInfo>>) {
+        let excluded = self.excluded.lock().await;
+        let mut plots = self.plots.lock().await;
+        plots.retain(|path, _| loaded.contains_key(&PathInfo::new(path.clone())));
+        self.quarantined
+            .lock()
+            .await
+            .retain(|path, _| plots.contains_key(path));
+        for (path_info, plot_info) in loaded {
+            plots.entry(path_info.path.clone()).or_insert_with(|| {
+                let (plot_id, k, compression) = match plot_info.reader.header() {
+                    PlotHeader::V1(h) => (h.id, h.k, 0),
+                    PlotHeader::V2(h) => (h.id, h.k, h.compression_level),
+                };
+                PlotRecord {
+                    path: path_info.path.to_string_lossy().to_string(),
+                    dir: path_info
+                        .path
+                        .parent()
+                        .map(|p| p.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    plot_id: plot_id.to_string(),
+                    k,
+                    compression,
+                    lookup_ms: None,
+                    proofs: 0,
+                    health: if excluded.contains(&path_info.path) {
+                        PlotHealth::Excluded
+                    } else {
+                        PlotHealth::Unchecked
+                    },
+                    error: None,
+                }
+            });
+        }
+    }
+    pub async fn record_lookup(&self, path: &Path, latency: Duration, proofs: usize) {
+        if let Some(record) = self.plots.lock().await.get_mut(path) {
+            let latency_ms = latency.as_secs_f64() * 1000.0;
+            record.lookup_ms = Some(match record.lookup_ms {
+                Some(average) => average + SMOOTHING * (latency_ms - average),
+                None => latency_ms,
+            });
+            record.proofs += proofs as u64;
+        }
+    }
+    //A passed check also ends a quarantine, a failed one keeps it
+    pub async fn record_check(&self, path: &Path, result: Result<(), String>) {
+        let mut quarantined = self.quarantined.lock().await;
+        if result.is_ok() {
+            quarantined.remove(path);
+        }
+        if let Some(record) = self.plots.lock().await.get_mut(path) {
+            if record.health != PlotHealth::Excluded && !quarantined.contains_key(path) {
+                record.health = if result.is_ok() {
+                    PlotHealth::Ok
+                } else {
+                    PlotHealth::Failed
+                };
+            }
+            record.error = result.err();
+        }
+    }
+    pub async fn mark_recovering(&self, path: &Path, error: String) {
+        self.quarantine(path, PlotHealth::Recovering, error).await
+    }
+    pub async fn mark_unreadable(&self, path: &Path, error: String) {
+        self.quarantine(path, PlotHealth::Unreadable, error).await
+    }
+    async fn quarantine(&self, path: &Path, health: PlotHealth, error: String) {
+        self.quarantined
+            .lock()
+            .await
+            .insert(path.to_path_buf(), health.clone());
+        if let Some(record) = self.plots.lock().await.get_mut(path) {
+            if record.health != PlotHealth::Excluded {
+                record.health = health;
+            }
+            record.error = Some(error);
+        }
+    }
+    //Plots the lookups leave out
+    pub async fn skipped(&self) -> HashSet<PathBuf> {
+        let mut skipped = self.excluded().await;
+        skipped.extend(self.quarantined.lock().await.keys().cloned());
+        skipped
+    }
+    pub async fn snapshot(&self) -> Vec<PlotRecord> {
+        self.plots.lock().await.values().cloned().collect()
+    }
+    pub async fn excluded(&self) -> HashSet<PathBuf> {
+        self.excluded.lock().await.iter().cloned().collect()
+    }
+    pub async fn set_excluded(&self, path: &Path, exclude: bool) {
+        let mut excluded = self.excluded.lock().await;
+        let changed = if exclude {
+            excluded.insert(path.to_path_buf())
+        } else {
+            excluded.remove(path)
+        };
+        if !changed {
+            return;
+        }
+        let quarantined = self.quarantined.lock().await.get(path).cloned();
+        if let Some(record) = self.plots.lock().await.get_mut(path) {
+            record.health = match (exclude, &record.error, quarantined) {
+                (true, _, _) => PlotHealth::Excluded,
+                (false, _, Some(health)) => health,
+                (false, Some(_), None) => PlotHealth::Failed,
+                (false, None, None) => PlotHealth::Unchecked,
+            };
+        }
+        info!(
+            "{} plot {:?}",
+            if exclude { "Excluded" } else { "Included" },
+            path
+        );
+        if let Some(file) = &self.path {
+            let result = serde_json::to_string(&*excluded)
+                .map_err(std::io::Error::from)
+                .and_then(|s| fs::write(file, s));
+            if let Err(e) = result {
+                warn!("Failed to save excluded plot list to {:?}: {:?}", file, e);
+            }
+        }
+    }
+    //Queued for the plot auditor, which checks them ahead of its rotation
+    pub async fn request_recheck(&self, path: &Path) {
+        let mut rechecks = self.rechecks.lock().await;
+        if !rechecks.iter().any(|p| p == path) {
+            rechecks.push_back(path.to_path_buf());
+        }
+    }
+    pub async fn take_recheck(&self) -> Option<PathBuf> {
+        self.rechecks.lock().await.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tracks_exclusions_and_rechecks() {
+        let registry = PlotRegistry::default();
+        let plot = Path::new("/mnt/a/plot-k32.plot");
+        registry.set_excluded(plot, true).await;
+        assert!(registry.excluded().await.contains(plot));
+        registry.set_excluded(plot, false).await;
+        assert!(registry.excluded().await.is_empty());
+        registry
+            .mark_unreadable(plot, "read failed".to_string())
+            .await;
+        assert!(registry.skipped().await.contains(plot));
+        registry.record_check(plot, Ok(())).await;
+        assert!(registry.skipped().await.is_empty());
+        registry.request_recheck(plot).await;
+        registry.request_recheck(plot).await;
+        assert_eq!(registry.take_recheck().await.as_deref(), Some(plot));
+        assert_eq!(registry.take_recheck().await, None);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::farmer::config::{BladebitHarvesterConfig, PlotScanOptions};
+use crate::harvesters::plot_dirs::{normalize_plot_dir, PlotDirError};
+use glob::Pattern;
+use log::{debug, error};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+//Files of a plot directory that are never loaded, so copies in progress are not farmed and flagged as corrupt
+#[derive(Debug, Default)]
+pub struct PlotScanFilter {
+    exclude: Vec<Pattern>,
+    min_file_size: u64,
+}
+impl PlotScanFilter {
+    pub fn new(options: &PlotScanOptions) -> Result<Self, String> {
+        let exclude = options
+            .exclude
+            .iter()
+            .map(|p| {
+                Pattern::new(p).map_err(|e| format!("invalid exclude pattern {p:?}: {}", e.msg))
+            })
+            .collect::<Result<Vec<Pattern>, String>>()?;
+        Ok(Self {
+            exclude,
+            min_file_size: options.min_file_size,
+        })
+    }
+    pub fn is_empty(&self) -> bool {
+        self.exclude.is_empty() && self.min_file_size == 0
+    }
+    pub fn excludes(&self, file_name: &str, file_size: u64) -> bool {
+        file_size < self.min_file_size || self.exclude.iter().any(|p| p.matches(file_name))
+    }
+    pub async fn excluded_paths(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut excluded = vec![];
+        if self.is_empty() {
+            return excluded;
+        }
+        let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+            return excluded;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let file_size = match entry.metadata().await {
+                Ok(metadata) if metadata.is_file() => metadata.len(),
+                _ => continue,
+            };
+            if self.excludes(&entry.file_name().to_string_lossy(), file_size) {
+                debug!("Excluding {:?} from plot scan", entry.path());
+                excluded.push(entry.path());
+            }
+        }
+        excluded
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PlotScanFilters {
+    filters: HashMap<PathBuf, Arc<PlotScanFilter>>,
+}
+impl PlotScanFilters {
+    //Invalid patterns are logged and the directory is scanned without its filter
+    pub fn new(bb_config: &BladebitHarvesterConfig) -> Self {
+        let mut filters = HashMap::new();
+        for (dir, options) in &bb_config.scan_options {
+            match PlotScanFilter::new(options) {
+                Ok(filter) => {
+                    filters.insert(
+                        normalize_plot_dir(dir).unwrap_or_else(|_| Path::new(dir).to_path_buf()),
+                        Arc::new(filter),
+                    );
+                }
+                Err(e) => error!("Ignoring scan options for {:?}: {e}", dir),
+            }
+        }
+        Self { filters }
+    }
+    pub fn filter_for(&self, dir: &Path) -> Arc<PlotScanFilter> {
+        self.filters.get(dir).cloned().unwrap_or_default()
+    }
+}
+
+pub fn validate_scan_options(bb_config: &BladebitHarvesterConfig) -> Vec<PlotDirError> {
+    bb_config
+        .scan_options
+        .iter()
+        .filter_map(|(dir, options)| {
+            PlotScanFilter::new(options)
+                .err()
+                .map(|reason| PlotDirError {
+                    path: dir.clone(),
+                    reason,
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(exclude: &[&str], min_file_size: u64) -> PlotScanFilter {
+        PlotScanFilter::new(&PlotScanOptions {
+            exclude: exclude.iter().map(|s| s.to_string()).collect(),
+            min_file_size,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn patterns_match_file_names() {
+        let filter = filter(&["*.tmp", "*.plot.part", "copy_*"], 0);
+        assert!(filter.excludes("plot-k32-abc.plot.tmp", 1));
+        assert!(filter.excludes("plot-k32-abc.plot.part", 1));
+        assert!(filter.excludes("copy_plot-k32-abc.plot", 1));
+        assert!(!filter.excludes("plot-k32-abc.plot", 1));
+    }
+
+    #[test]
+    fn small_files_are_excluded() {
+        let filter = filter(&[], 100);
+        assert!(filter.excludes("plot-k32-abc.plot", 99));
+        assert!(!filter.excludes("plot-k32-abc.plot", 100));
+    }
+
+    #[test]
+    fn invalid_pattern_is_rejected() {
+        let err = PlotScanFilter::new(&PlotScanOptions {
+            exclude: vec!["[*.tmp".to_string()],
+            min_file_size: 0,
+        })
+        .unwrap_err();
+        assert!(err.contains("[*.tmp"));
+        assert!(filter(&[], 0).is_empty());
+    }
+}
@@ -0,0 +1,515 @@
+use crate::farmer::config::{HarvesterNodeConfig, LinkTransport};
+#[cfg(feature = "quic")]
+use crate::farmer::dns::client_tls_config;
+use crate::farmer::dns::connect_websocket;
+use crate::farmer::events::{FarmerEvent, EVENT_CHANNEL_SIZE};
+use crate::farmer::EligibilityStats;
+use crate::get_ssl_root_path;
+use crate::harvesters::dir_latency::DirLatencies;
+use crate::harvesters::druid_garden::DruidGardenHarvester;
+#[cfg(feature = "quic")]
+use crate::harvesters::link::quic_connect;
+use crate::harvesters::link::{
+    read_message, upgrade_link, websocket_link, LinkOptions, LinkReader, LinkWriter,
+};
+use crate::harvesters::plot_classification::UnfarmablePlots;
+use crate::harvesters::plot_dirs::normalize_plot_dir;
+use crate::harvesters::plot_scan::{PlotScanFilter, PlotScanFilters};
+use crate::harvesters::stats::HarvesterStatsRegistry;
+use crate::harvesters::{
+    load_harvester_id, Harvester, ProofHandler, SignatureHandler, DRUID_GARDEN_HARVESTER,
+};
+use async_trait::async_trait;
+use dg_xch_clients::protocols::harvester::{
+    HarvesterHandshake, NewProofOfSpace, NewSignagePointHarvester, RequestSignatures,
+    RespondSignatures,
+};
+use dg_xch_clients::protocols::shared::{Handshake, PROTOCOL_VERSION, SOFTWARE_VERSION};
+use dg_xch_clients::protocols::ProtocolMessageTypes;
+use dg_xch_clients::websocket::{ChiaMessage, ClientSSLConfig, NodeType};
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use dg_xch_core::consensus::constants::ConsensusConstants;
+use dg_xch_core::ssl::create_all_ssl;
+use dg_xch_serialize::ChiaSerialize;
+use futures_util::StreamExt;
+use log::{debug, error, info, warn};
+use std::io::{Cursor, Error, ErrorKind};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::select;
+use tokio::sync::{broadcast, Mutex};
+use tokio::time::{sleep, timeout};
+
+static HARVESTER_CRT: &str = "harvester/private_harvester.crt";
+static HARVESTER_KEY: &str = "harvester/private_harvester.key";
+static CA_PRIVATE_CRT: &str = "ca/private_ca.crt";
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(15);
+
+struct LoadedHarvester {
+    handshake: HarvesterHandshake,
+    harvester: Arc<DruidGardenHarvester>,
+    run: Arc<AtomicBool>,
+}
+
+struct RemoteHarvesterState {
+    config: Arc<HarvesterNodeConfig>,
+    constants: &'static ConsensusConstants,
+    eligibility_stats: Arc<Mutex<EligibilityStats>>,
+    harvester_stats: Arc<HarvesterStatsRegistry>,
+    unfarmable_plots: Arc<UnfarmablePlots>,
+    dir_latencies: Arc<DirLatencies>,
+    //Plot events are only logged on a standalone harvester
+    events: broadcast::Sender<FarmerEvent>,
+    harvester: Mutex<Option<LoadedHarvester>>,
+    link: Mutex<LinkState>,
+}
+impl RemoteHarvesterState {
+    async fn harvester(&self) -> Option<Arc<DruidGardenHarvester>> {
+        self.harvester
+            .lock()
+            .await
+            .as_ref()
+            .map(|h| h.harvester.clone())
+    }
+}
+
+//Liveness and signage point continuity of the current farmer connection
+struct LinkState {
+    last_message: Instant,
+    last_signage_point: Option<(Bytes32, u8)>,
+    missed_signage_points: u64,
+}
+impl Default for LinkState {
+    fn default() -> Self {
+        Self {
+            last_message: Instant::now(),
+            last_signage_point: None,
+            missed_signage_points: 0,
+        }
+    }
+}
+impl LinkState {
+    //Signage point indexes of a challenge arrive in order, a gap means the link dropped some
+    fn record_signage_point(&mut self, challenge_hash: Bytes32, index: u8) -> u8 {
+        let missed = match self.last_signage_point {
+            Some((last_challenge, last_index))
+                if last_challenge == challenge_hash && index > last_index =>
+            {
+                index - last_index - 1
+            }
+            _ => 0,
+        };
+        self.missed_signage_points += missed as u64;
+        self.last_signage_point = Some((challenge_hash, index));
+        missed
+    }
+}
+
+//Runs only the harvester, connected to a farmer over the harvester protocol
+pub async fn run_harvester(
+    config: Arc<HarvesterNodeConfig>,
+    run: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    let ssl_path = get_ssl_root_path(&config.ssl_root_path);
+    if !ssl_path.join(CA_PRIVATE_CRT).exists() {
+        warn!(
+            "No private CA found in {:?}, copy the farmer's ssl/ca directory there to connect",
+            ssl_path
+        );
+    }
+    let cert_path = ssl_path.clone();
+    tokio::task::spawn_blocking(move || create_all_ssl(&cert_path, false))
+        .await
+        .map_err(Error::other)??;
+    if let Some(bb_config) = &config.harvester_configs.bladebit {
+        let mut sum = 0;
+        let mut total_size = 0;
+        let scan_filters = PlotScanFilters::new(bb_config);
+        for dir in bb_config
+            .plot_directories
+            .iter()
+            .filter_map(|d| normalize_plot_dir(d).ok())
+        {
+            if let Err(e) = count_plots(
+                &dir,
+                &scan_filters.filter_for(&dir),
+                &mut sum,
+                &mut total_size,
+            )
+            .await
+            {
+                error!("Error Counting Plots: {e:?}")
+            }
+        }
+        info!("Found {sum} plots, {total_size} bytes");
+    }
+    let state = Arc::new(RemoteHarvesterState {
+        constants: config.consensus_constants(),
+        config,
+        eligibility_stats: Default::default(),
+        harvester_stats: Default::default(),
+        unfarmable_plots: Default::default(),
+        dir_latencies: Default::default(),
+        events: broadcast::channel(EVENT_CHANNEL_SIZE).0,
+        harvester: Mutex::new(None),
+        link: Default::default(),
+    });
+    while run.load(Ordering::Relaxed) {
+        if let Err(e) = connect(state.clone(), &ssl_path, run.clone()).await {
+            error!(
+                "Farmer connection to {}:{} failed: {:?}",
+                state.config.farmer_host, state.config.farmer_port, e
+            );
+        }
+        if !run.load(Ordering::Relaxed) {
+            break;
+        }
+        info!("Reconnecting to farmer in {:?}", RECONNECT_INTERVAL);
+        tokio::time::sleep(RECONNECT_INTERVAL).await;
+    }
+    if let Some(loaded) = state.harvester.lock().await.take() {
+        loaded.run.store(false, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+async fn connect(
+    state: Arc<RemoteHarvesterState>,
+    ssl_path: &Path,
+    run: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    let config = state.config.clone();
+    info!(
+        "Connecting to farmer at {}:{} over {:?}",
+        config.farmer_host, config.farmer_port, config.link.transport
+    );
+    let (crt, key, ca) = (
+        ssl_path.join(HARVESTER_CRT),
+        ssl_path.join(HARVESTER_KEY),
+        ssl_path.join(CA_PRIVATE_CRT),
+    );
+    let ssl_info = ClientSSLConfig {
+        ssl_crt_path: &crt.to_string_lossy(),
+        ssl_key_path: &key.to_string_lossy(),
+        ssl_ca_crt_path: &ca.to_string_lossy(),
+    };
+    let (link, mut read) = match config.link.transport {
+        LinkTransport::Websocket => websocket_link(
+            connect_websocket(
+                &config.dns,
+                &config.farmer_host,
+                config.farmer_port,
+                ssl_info,
+            )
+            .await?,
+        ),
+        #[cfg(feature = "quic")]
+        LinkTransport::Quic => {
+            let idle_timeout = Some(config.link.idle_timeout)
+                .filter(|t| *t > 0)
+                .map(Duration::from_secs);
+            //The connection stays open as long as its streams
+            let (_connection, link, read) = quic_connect(
+                &config.dns,
+                &config.farmer_host,
+                config.farmer_port,
+                client_tls_config(&ssl_info)?,
+                idle_timeout,
+            )
+            .await?;
+            (link, read)
+        }
+        #[cfg(not(feature = "quic"))]
+        LinkTransport::Quic => {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "link transport quic needs a build with the quic feature",
+            ))
+        }
+    };
+    let options = LinkOptions {
+        batching: true,
+        compression: config.link.compression,
+    };
+    link.send(ChiaMessage::new(
+        ProtocolMessageTypes::Handshake,
+        &Handshake {
+            network_id: config.selected_network.clone(),
+            protocol_version: PROTOCOL_VERSION.to_string(),
+            software_version: SOFTWARE_VERSION.to_string(),
+            server_port: config.farmer_port,
+            node_type: NodeType::Harvester as u8,
+            capabilities: options.capabilities(),
+        },
+        None,
+    ))
+    .await?;
+    let handshake = match timeout(HANDSHAKE_TIMEOUT, read_message(&mut read)).await {
+        Ok(Some(Ok(msg))) if msg.msg_type == ProtocolMessageTypes::Handshake => {
+            Handshake::from_bytes(&mut Cursor::new(&msg.data))?
+        }
+        Ok(Some(Err(e))) => return Err(e),
+        _ => {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "No handshake received from farmer",
+            ))
+        }
+    };
+    let options = options.negotiate(&handshake.capabilities);
+    debug!("Farmer link options: {:?}", options);
+    let (link, mut read) = upgrade_link(link, read, options);
+    let link = Arc::new(link);
+    *state.link.lock().await = LinkState::default();
+    info!("Connected to farmer");
+    let idle_timeout = config.link.idle_timeout;
+    select! {
+        result = read_messages(state.clone(), link.clone(), &mut read, run) => {
+            result?;
+            info!("Farmer connection closed");
+        }
+        _ = idle_watchdog(state.clone(), idle_timeout) => {
+            //Half open connections over NAT or VPN never see a close frame
+            warn!("No message from farmer in {idle_timeout} seconds, dropping connection");
+        }
+    }
+    let missed = state.link.lock().await.missed_signage_points;
+    if missed > 0 {
+        warn!("Missed {missed} signage points on the last farmer connection");
+    }
+    link.close().await.unwrap_or_default();
+    Ok(())
+}
+
+async fn count_plots(
+    path: &Path,
+    filter: &PlotScanFilter,
+    count_total: &mut u64,
+    size_total: &mut u64,
+) -> Result<(), Error> {
+    if !path.is_dir() {
+        return Ok(());
+    }
+    let mut dir = tokio::fs::read_dir(path).await?;
+    while let Ok(Some(e)) = dir.next_entry().await {
+        let file_name = e.file_name().to_string_lossy().to_string();
+        if file_name.ends_with(".plot") {
+            let file_size = e.metadata().await?.len();
+            if filter.excludes(&file_name, file_size) {
+                continue;
+            }
+            *size_total += file_size;
+            *count_total += 1;
+        }
+    }
+    Ok(())
+}
+
+//Resolves once the farmer was silent for idle_timeout seconds, it sends signage points every few seconds
+async fn idle_watchdog(state: Arc<RemoteHarvesterState>, idle_timeout: u64) {
+    if idle_timeout == 0 {
+        return std::future::pending().await;
+    }
+    let idle_timeout = Duration::from_secs(idle_timeout);
+    loop {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        if state.link.lock().await.last_message.elapsed() >= idle_timeout {
+            return;
+        }
+    }
+}
+
+//Every message is handled in its own task, plot lookups do not hold up signature requests
+async fn read_messages(
+    state: Arc<RemoteHarvesterState>,
+    link: Arc<LinkWriter>,
+    read: &mut LinkReader,
+    run: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    while run.load(Ordering::Relaxed) {
+        let received = select! {
+            frame = read.next() => frame,
+            _ = sleep(Duration::from_secs(1)) => continue,
+        };
+        let frame = match received {
+            Some(Ok(frame)) => frame,
+            Some(Err(e)) => return Err(e),
+            None => return Ok(()),
+        };
+        state.link.lock().await.last_message = Instant::now();
+        let msg = match ChiaMessage::from_bytes(&mut Cursor::new(frame)) {
+            Ok(msg) => msg,
+            Err(e) => {
+                warn!("Invalid message from farmer: {:?}", e);
+                continue;
+            }
+        };
+        let state = state.clone();
+        let link = link.clone();
+        tokio::spawn(async move {
+            let msg_type = msg.msg_type;
+            let handled = match msg_type {
+                ProtocolMessageTypes::HarvesterHandshake => harvester_handshake(&state, msg).await,
+                ProtocolMessageTypes::NewSignagePointHarvester => {
+                    new_signage_point(&state, link, msg).await
+                }
+                ProtocolMessageTypes::RequestSignatures => {
+                    request_signatures(&state, link, msg).await
+                }
+                other => {
+                    debug!("Ignoring {:?} from farmer", other);
+                    Ok(())
+                }
+            };
+            if let Err(e) = handled {
+                error!("Error handling {:?} from farmer: {:?}", msg_type, e);
+            }
+        });
+    }
+    Ok(())
+}
+
+async fn harvester_handshake(state: &RemoteHarvesterState, msg: ChiaMessage) -> Result<(), Error> {
+    let mut cursor = Cursor::new(&msg.data);
+    let handshake = HarvesterHandshake::from_bytes(&mut cursor)?;
+    if let Some(existing) = state.harvester.lock().await.as_ref() {
+        if existing.handshake == handshake {
+            info!("Farmer keys unchanged, keeping loaded plots");
+            return Ok(());
+        }
+    }
+    let Some(bb_config) = &state.config.harvester_configs.bladebit else {
+        warn!("No bladebit harvester configured, not loading plots");
+        return Ok(());
+    };
+    info!(
+        "Received {} farmer and {} pool keys, loading plots",
+        handshake.farmer_public_keys.len(),
+        handshake.pool_public_keys.len()
+    );
+    let run = Arc::new(AtomicBool::new(true));
+    let harvester = DruidGardenHarvester::new(
+        bb_config,
+        handshake.farmer_public_keys.clone(),
+        handshake.pool_public_keys.clone(),
+        vec![],
+        run.clone(),
+        state.eligibility_stats.clone(),
+        state.harvester_stats.clone(),
+        //Signage point digests are logged by the farmer
+        Default::default(),
+        state.unfarmable_plots.clone(),
+        state.dir_latencies.clone(),
+        //Kept in memory only, the baselines file belongs to the farmer
+        Default::default(),
+        //The plot browser is part of the farmer, standalone harvesters farm every plot
+        Default::default(),
+        //Plots stay loaded, a lost farmer link is reconnected rather than waited out
+        Default::default(),
+        state.events.clone(),
+        state.constants,
+        &state.config.selected_network,
+        //No full node to read the height from, plot_filter_bits in the config covers a fork
+        Default::default(),
+        load_harvester_id(DRUID_GARDEN_HARVESTER),
+    )
+    .await?;
+    harvester.wait_for_plots().await;
+    info!("Loaded {} plots", harvester.plots.lock().await.len());
+    //The previous harvester keeps serving signage points until the new one is loaded
+    let previous = state.harvester.lock().await.replace(LoadedHarvester {
+        handshake,
+        harvester: Arc::new(harvester),
+        run,
+    });
+    if let Some(previous) = previous {
+        previous.run.store(false, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+async fn new_signage_point(
+    state: &RemoteHarvesterState,
+    link: Arc<LinkWriter>,
+    msg: ChiaMessage,
+) -> Result<(), Error> {
+    let mut cursor = Cursor::new(&msg.data);
+    let sp = NewSignagePointHarvester::from_bytes(&mut cursor)?;
+    let missed = state
+        .link
+        .lock()
+        .await
+        .record_signage_point(sp.challenge_hash, sp.signage_point_index);
+    if missed > 0 {
+        warn!(
+            "Missed {missed} signage points before index {} of challenge {}",
+            sp.signage_point_index, sp.challenge_hash
+        );
+    } else {
+        debug!(
+            "Signage point {} of challenge {}",
+            sp.signage_point_index, sp.challenge_hash
+        );
+    }
+    let Some(harvester) = state.harvester().await else {
+        warn!("Skipping signage point, plots are not loaded yet");
+        return Ok(());
+    };
+    harvester
+        .new_signage_point(Arc::new(sp), FarmerProofHandler { link })
+        .await
+}
+
+async fn request_signatures(
+    state: &RemoteHarvesterState,
+    link: Arc<LinkWriter>,
+    msg: ChiaMessage,
+) -> Result<(), Error> {
+    let mut cursor = Cursor::new(&msg.data);
+    let request = RequestSignatures::from_bytes(&mut cursor)?;
+    let Some(harvester) = state.harvester().await else {
+        return Err(Error::other(
+            "Signatures requested before plots were loaded",
+        ));
+    };
+    harvester
+        .request_signatures(request, FarmerSignatureHandler { link, id: msg.id })
+        .await
+}
+
+struct FarmerProofHandler {
+    link: Arc<LinkWriter>,
+}
+#[async_trait]
+impl ProofHandler for FarmerProofHandler {
+    async fn handle_proof(&self, new_pos: NewProofOfSpace) -> Result<(), Error> {
+        self.link
+            .send(ChiaMessage::new(
+                ProtocolMessageTypes::NewProofOfSpace,
+                &new_pos,
+                None,
+            ))
+            .await
+    }
+}
+
+struct FarmerSignatureHandler {
+    link: Arc<LinkWriter>,
+    //The farmer awaits the response by the id of its request
+    id: Option<u16>,
+}
+#[async_trait]
+impl SignatureHandler for FarmerSignatureHandler {
+    async fn handle_signature(&self, response: RespondSignatures) -> Result<(), Error> {
+        self.link
+            .send(ChiaMessage::new(
+                ProtocolMessageTypes::RespondSignatures,
+                &response,
+                self.id,
+            ))
+            .await
+    }
+}
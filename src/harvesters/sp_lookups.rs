@@ -0,0 +1,94 @@
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, Mutex};
+
+struct ActiveSp {
+    challenge_hash: Bytes32,
+    signage_point_index: u8,
+    started: Instant,
+    cancel: watch::Sender<bool>,
+}
+
+//Signage points with lookups in flight, older points of the same challenge are cancelled once past their deadline
+pub struct SpLookups {
+    deadline: Option<Duration>,
+    active: Mutex<Vec<ActiveSp>>,
+}
+impl SpLookups {
+    pub fn new(deadline_secs: u64) -> Self {
+        Self::with_deadline((deadline_secs > 0).then(|| Duration::from_secs(deadline_secs)))
+    }
+    fn with_deadline(deadline: Option<Duration>) -> Self {
+        Self {
+            deadline,
+            active: Default::default(),
+        }
+    }
+    //The receiver turns true when a newer signage point superseded this one
+    pub async fn start(
+        &self,
+        challenge_hash: Bytes32,
+        signage_point_index: u8,
+    ) -> watch::Receiver<bool> {
+        let (cancel, cancelled) = watch::channel(false);
+        let mut active = self.active.lock().await;
+        if let Some(deadline) = self.deadline {
+            active.retain(|sp| {
+                let superseded = sp.challenge_hash == challenge_hash
+                    && sp.signage_point_index < signage_point_index
+                    && sp.started.elapsed() >= deadline;
+                if superseded {
+                    let _ = sp.cancel.send(true);
+                }
+                !superseded
+            });
+        }
+        active.push(ActiveSp {
+            challenge_hash,
+            signage_point_index,
+            started: Instant::now(),
+            cancel,
+        });
+        cancelled
+    }
+    pub async fn is_idle(&self) -> bool {
+        self.active.lock().await.is_empty()
+    }
+    pub async fn finish(&self, challenge_hash: Bytes32, signage_point_index: u8) {
+        self.active.lock().await.retain(|sp| {
+            sp.challenge_hash != challenge_hash || sp.signage_point_index != signage_point_index
+        });
+    }
+}
+
+//Resolves once the signage point is superseded, never when the registry dropped it without cancelling
+pub async fn superseded(cancelled: &mut watch::Receiver<bool>) {
+    if cancelled.wait_for(|c| *c).await.is_err() {
+        std::future::pending::<()>().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cancels_stale_points_of_the_same_challenge() {
+        let lookups = SpLookups::with_deadline(Some(Duration::from_millis(10)));
+        let challenge = Bytes32::default();
+        let other = Bytes32::from_sized_bytes([1u8; 32]);
+        let first = lookups.start(challenge, 1).await;
+        let other_challenge = lookups.start(other, 0).await;
+        let fresh = lookups.start(challenge, 2).await;
+        assert!(!*first.borrow());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let _newest = lookups.start(challenge, 3).await;
+        assert!(*first.borrow());
+        assert!(*fresh.borrow());
+        assert!(!*other_challenge.borrow());
+        let disabled = SpLookups::new(0);
+        let first = disabled.start(challenge, 1).await;
+        let _newer = disabled.start(challenge, 2).await;
+        assert!(!*first.borrow());
+    }
+}
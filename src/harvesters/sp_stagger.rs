@@ -0,0 +1,93 @@
+use log::info;
+use rand::Rng;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+//Signage points between two reports of the stagger effect, about 10 minutes
+const REPORT_INTERVAL: u64 = 64;
+
+#[derive(Debug, Default)]
+struct StaggerStats {
+    signage_points: u64,
+    completion_ms: f64,
+    added_ms: f64,
+}
+
+//Delays the first read of every plot directory by a random offset after a signage point, so
+//thousands of drives do not all seek at the same instant. The offset is per directory, the plots
+//of one directory still start together
+pub struct SpStagger {
+    max: Duration,
+    stats: Mutex<StaggerStats>,
+}
+impl SpStagger {
+    pub fn new(max_ms: u64) -> Self {
+        if max_ms > 0 {
+            info!("Staggering plot directory lookups by up to {max_ms}ms");
+        }
+        Self {
+            max: Duration::from_millis(max_ms),
+            stats: Default::default(),
+        }
+    }
+    pub fn offsets<'a>(
+        &self,
+        dirs: impl Iterator<Item = &'a PathBuf>,
+    ) -> HashMap<PathBuf, Duration> {
+        if self.max.is_zero() {
+            return HashMap::new();
+        }
+        let mut rng = rand::thread_rng();
+        dirs.map(|dir| (dir.clone(), rng.gen_range(Duration::ZERO..=self.max)))
+            .collect()
+    }
+    pub fn offset(offsets: &HashMap<PathBuf, Duration>, dir: &Path) -> Duration {
+        offsets.get(dir).copied().unwrap_or_default()
+    }
+    //completion is when the last directory answered, unstaggered when it would have without its
+    //offset. The difference is what the stagger added to the signage point
+    pub fn record(&self, completion: Duration, unstaggered: Duration) {
+        if self.max.is_zero() {
+            return;
+        }
+        let Ok(mut stats) = self.stats.lock() else {
+            return;
+        };
+        stats.signage_points += 1;
+        stats.completion_ms += completion.as_secs_f64() * 1000.0;
+        stats.added_ms += completion.saturating_sub(unstaggered).as_secs_f64() * 1000.0;
+        if stats.signage_points >= REPORT_INTERVAL {
+            let points = stats.signage_points as f64;
+            info!(
+                "Lookup stagger of up to {}ms added {:.1}ms on average to {:.1}ms signage point lookups over the last {} signage points",
+                self.max.as_millis(),
+                stats.added_ms / points,
+                stats.completion_ms / points,
+                stats.signage_points
+            );
+            *stats = StaggerStats::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_stay_within_max() {
+        let dirs = [PathBuf::from("/a"), PathBuf::from("/b")];
+        assert!(SpStagger::new(0).offsets(dirs.iter()).is_empty());
+        let stagger = SpStagger::new(50);
+        let offsets = stagger.offsets(dirs.iter());
+        assert_eq!(offsets.len(), 2);
+        assert!(offsets.values().all(|o| *o <= Duration::from_millis(50)));
+        assert_eq!(SpStagger::offset(&offsets, Path::new("/c")), Duration::ZERO);
+        stagger.record(Duration::from_millis(120), Duration::from_millis(100));
+        let stats = stagger.stats.lock().unwrap();
+        assert_eq!(stats.signage_points, 1);
+        assert!((stats.added_ms - 20.0).abs() < 0.001);
+    }
+}
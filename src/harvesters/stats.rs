@@ -0,0 +1,169 @@
+use crate::farmer::config::DecompressionBackend;
+use crate::farmer::network_stats::effective_plot_size;
+use crate::farmer::{PathInfo, PlotInfo};
+use dg_xch_core::plots::PlotHeader;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlotTotals {
+    pub count: u64,
+    pub space: u64,
+    pub og: u64,
+    pub pool: u64,
+    pub compressed: u64,
+    //Plot space as counted in the netspace, compressed plots count at their uncompressed size
+    #[serde(default)]
+    pub effective_space: u64,
+}
+impl PlotTotals {
+    pub fn from_plots(plots: &HashMap<PathInfo, Arc<PlotInfo>>) -> Self {
+        let mut totals = Self::default();
+        for info in plots.values() {
+            totals.count += 1;
+            totals.space += info.file_size;
+            totals.effective_space += effective_plot_size(match info.reader.header() {
+                PlotHeader::V1(h) => h.k,
+                PlotHeader::V2(h) => h.k,
+            });
+            if info.reader.compression_level() > 0 {
+                totals.compressed += 1;
+            } else if info.pool_contract_puzzle_hash.is_some() {
+                totals.pool += 1;
+            } else if info.pool_public_key.is_some() {
+                totals.og += 1;
+            }
+        }
+        totals
+    }
+}
+
+//Counters since startup, the status summary keeps its own per interval view in EligibilityStats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HarvesterStats {
+    pub plots: PlotTotals,
+    pub signage_points: u64,
+    pub eligible_plots: u64,
+    pub proofs: u64,
+    pub partials: u64,
+    //Provider decompressing the compressed plots, not set on the farm rollup
+    #[serde(default)]
+    pub decompression: Option<DecompressionBackend>,
+}
+impl HarvesterStats {
+    pub fn eligible_per_sp(&self) -> f64 {
+        if self.signage_points == 0 {
+            0.0
+        } else {
+            self.eligible_plots as f64 / self.signage_points as f64
+        }
+    }
+    fn add(&mut self, other: &Self) {
+        self.plots.count += other.plots.count;
+        self.plots.space += other.plots.space;
+        self.plots.og += other.plots.og;
+        self.plots.pool += other.plots.pool;
+        self.plots.compressed += other.plots.compressed;
+        self.plots.effective_space += other.plots.effective_space;
+        self.signage_points += other.signage_points;
+        self.eligible_plots += other.eligible_plots;
+        self.proofs += other.proofs;
+        self.partials += other.partials;
+    }
+    fn subtract(&mut self, other: &Self) {
+        self.plots.count = self.plots.count.saturating_sub(other.plots.count);
+        self.plots.space = self.plots.space.saturating_sub(other.plots.space);
+        self.plots.og = self.plots.og.saturating_sub(other.plots.og);
+        self.plots.pool = self.plots.pool.saturating_sub(other.plots.pool);
+        self.plots.compressed = self.plots.compressed.saturating_sub(other.plots.compressed);
+        self.plots.effective_space = self
+            .plots
+            .effective_space
+            .saturating_sub(other.plots.effective_space);
+        self.signage_points = self.signage_points.saturating_sub(other.signage_points);
+        self.eligible_plots = self.eligible_plots.saturating_sub(other.eligible_plots);
+        self.proofs = self.proofs.saturating_sub(other.proofs);
+        self.partials = self.partials.saturating_sub(other.partials);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HarvesterStatsSnapshot {
+    pub farm: HarvesterStats,
+    pub harvesters: BTreeMap<Uuid, HarvesterStats>,
+}
+
+//Per harvester stats and the farm rollup, kept in step on every update so readers never sum themselves
+#[derive(Debug, Default)]
+pub struct HarvesterStatsRegistry {
+    inner: Mutex<HarvesterStatsSnapshot>,
+}
+impl HarvesterStatsRegistry {
+    pub async fn update<F: FnOnce(&mut HarvesterStats)>(&self, harvester_id: Uuid, f: F) {
+        let mut inner = self.inner.lock().await;
+        let stats = inner.harvesters.entry(harvester_id).or_default();
+        let before = *stats;
+        f(stats);
+        let after = *stats;
+        inner.farm.subtract(&before);
+        inner.farm.add(&after);
+    }
+    pub async fn set_plots(&self, harvester_id: Uuid, plots: PlotTotals) {
+        self.update(harvester_id, |s| s.plots = plots).await
+    }
+    pub async fn remove(&self, harvester_id: Uuid) {
+        let mut inner = self.inner.lock().await;
+        if let Some(stats) = inner.harvesters.remove(&harvester_id) {
+            inner.farm.subtract(&stats);
+        }
+    }
+    pub async fn farm(&self) -> HarvesterStats {
+        self.inner.lock().await.farm
+    }
+    pub async fn snapshot(&self) -> HarvesterStatsSnapshot {
+        self.inner.lock().await.clone()
+    }
+    pub async fn reset(&self) {
+        *self.inner.lock().await = HarvesterStatsSnapshot::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plots(count: u64, space: u64) -> PlotTotals {
+        PlotTotals {
+            count,
+            space,
+            og: count,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn farm_rollup_follows_harvester_updates() {
+        let registry = HarvesterStatsRegistry::default();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        registry.set_plots(a, plots(10, 1000)).await;
+        registry.set_plots(b, plots(5, 500)).await;
+        registry
+            .update(a, |s| {
+                s.signage_points += 1;
+                s.eligible_plots += 2;
+                s.proofs += 1;
+            })
+            .await;
+        assert_eq!(registry.farm().await.plots, plots(15, 1500));
+        registry.set_plots(a, plots(8, 800)).await;
+        let farm = registry.farm().await;
+        assert_eq!(farm.plots, plots(13, 1300));
+        assert_eq!(farm.proofs, 1);
+        registry.remove(a).await;
+        let snapshot = registry.snapshot().await;
+        assert_eq!(snapshot.farm, snapshot.harvesters[&b]);
+    }
+}
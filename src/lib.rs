@@ -0,0 +1,354 @@
+use crate::control::control_server;
+use crate::dashboard::dashboard_server;
+use crate::farmer::availability::AvailabilityTracker;
+use crate::farmer::capture::ProtocolCapture;
+use crate::farmer::config::{default_payout_address, load_keys, Config};
+use crate::farmer::farms::FarmIndex;
+use crate::farmer::idle::IdleState;
+use crate::farmer::instance_lock::claim;
+use crate::farmer::latency_regression::LatencyBaselines;
+use crate::farmer::plot_leaderboard::PlotLeaderboard;
+use crate::farmer::pool_client::build_pool_client;
+use crate::farmer::readiness::Readiness;
+use crate::farmer::sp_timing::SpTiming;
+use crate::farmer::{Farmer, FarmerSharedState};
+use crate::harvesters::plot_registry::PlotRegistry;
+use crate::tasks::clock_drift::clock_drift_monitor;
+use crate::tasks::drive_health::drive_health_monitor;
+use crate::tasks::email_report::email_reporter;
+use crate::tasks::event_history::event_recorder;
+use crate::tasks::idle_monitor::idle_monitor;
+use crate::tasks::key_health::key_health_monitor;
+use crate::tasks::network_stats::network_stats_updater;
+use crate::tasks::partial_batch::partial_batch_sender;
+use crate::tasks::partial_queue::partial_queue_flusher;
+use crate::tasks::plugins::plugin_runner;
+use crate::tasks::pool_state_updater::pool_updater;
+use crate::tasks::sp_digest::sp_digest_logger;
+use crate::tasks::sp_relay::sp_relay;
+use crate::tasks::standby_monitor::{instance_lock_monitor, standby_monitor};
+use crate::tasks::stats_report::stats_reporter;
+use crate::tasks::status_summary::status_summary;
+use crate::tasks::webhooks::webhook_sender;
+use crate::tasks::xch_price::xch_price_updater;
+use dg_xch_keys::decode_puzzle_hash;
+use hex::encode;
+use home::home_dir;
+use log::info;
+use once_cell::sync::Lazy;
+use reqwest::header::USER_AGENT;
+use std::collections::HashMap;
+use std::env;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use tokio::join;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+fn _version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+fn _pkg_name() -> &'static str {
+    env!("CARGO_PKG_NAME")
+}
+
+pub fn version() -> String {
+    format!("{}: {}", _pkg_name(), _version())
+}
+
+#[test]
+fn version_test() {
+    println!("{}", version());
+}
+
+pub static HEADERS: Lazy<HashMap<String, String>> = Lazy::new(|| {
+    let mut headers = HashMap::new();
+    headers.insert(
+        String::from("X-fast-farmer-version"),
+        _version().to_string(),
+    );
+    headers.insert(USER_AGENT.to_string(), version());
+    headers.insert(String::from("X-dg-xch-pos-version"), dg_xch_pos::version());
+    headers
+});
+
+pub mod build_info;
+pub mod cli;
+pub mod control;
+pub mod dashboard;
+pub mod error;
+pub mod farmer;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod harvesters;
+pub mod platform;
+pub mod plugins;
+pub mod tasks;
+
+pub fn get_root_path() -> PathBuf {
+    let prefix = match home_dir() {
+        Some(path) => path,
+        None => Path::new("/").to_path_buf(),
+    };
+    prefix.as_path().join(Path::new(".config/fast_farmer/"))
+}
+
+pub fn get_config_path() -> PathBuf {
+    get_root_path()
+        .as_path()
+        .join(Path::new("fast_farmer.yaml"))
+}
+
+pub fn get_harvester_config_path() -> PathBuf {
+    get_root_path()
+        .as_path()
+        .join(Path::new("fast_harvester.yaml"))
+}
+
+fn get_pool_info_cache_path() -> PathBuf {
+    get_root_path()
+        .as_path()
+        .join(Path::new("pool_info_cache.json"))
+}
+
+fn get_availability_path() -> PathBuf {
+    get_root_path()
+        .as_path()
+        .join(Path::new("availability.json"))
+}
+
+fn get_plot_leaderboard_path() -> PathBuf {
+    get_root_path()
+        .as_path()
+        .join(Path::new("plot_leaderboard.json"))
+}
+
+fn get_latency_baselines_path() -> PathBuf {
+    get_root_path()
+        .as_path()
+        .join(Path::new("latency_baselines.json"))
+}
+
+fn get_pool_difficulty_path() -> PathBuf {
+    get_root_path()
+        .as_path()
+        .join(Path::new("pool_difficulty.json"))
+}
+
+fn get_partial_queue_path() -> PathBuf {
+    get_root_path()
+        .as_path()
+        .join(Path::new("partial_queue.json"))
+}
+
+fn get_plot_audit_path() -> PathBuf {
+    get_root_path().as_path().join(Path::new("plot_audit.json"))
+}
+
+fn get_excluded_plots_path() -> PathBuf {
+    get_root_path()
+        .as_path()
+        .join(Path::new("excluded_plots.json"))
+}
+
+fn get_harvester_ids_path() -> PathBuf {
+    get_root_path()
+        .as_path()
+        .join(Path::new("harvester_ids.json"))
+}
+
+fn get_farm_id_path() -> PathBuf {
+    get_root_path().as_path().join(Path::new("farm_id"))
+}
+
+fn get_control_key_path() -> PathBuf {
+    get_root_path()
+        .as_path()
+        .join(Path::new("control_identity.key"))
+}
+
+fn get_forensics_path() -> PathBuf {
+    get_root_path().as_path().join(Path::new("forensics/"))
+}
+
+fn get_profiles_path() -> PathBuf {
+    get_root_path().as_path().join(Path::new("profiles/"))
+}
+
+fn get_object_cache_path() -> PathBuf {
+    get_root_path().as_path().join(Path::new("object_cache/"))
+}
+
+fn get_ssl_root_path(ssl_root_path: &Option<String>) -> PathBuf {
+    if let Some(ssl_root_path) = ssl_root_path {
+        PathBuf::from(ssl_root_path)
+    } else {
+        get_root_path().as_path().join(Path::new("ssl/"))
+    }
+}
+
+pub async fn run_farmer(
+    config_arc: Arc<Config>,
+    config_path: PathBuf,
+    capture: Option<PathBuf>,
+    profile: bool,
+) -> Result<(), Error> {
+    let constants = config_arc.consensus_constants();
+    info!(
+        "Selected Network: {}, AggSig: {}",
+        &config_arc.selected_network,
+        &encode(&constants.agg_sig_me_additional_data)
+    );
+    let instance_claim = claim(config_arc.as_ref(), &config_path)?;
+    let (farmer_private_keys, owner_secret_keys, auth_secret_keys, pool_public_keys) =
+        load_keys(config_arc.clone()).await;
+    let readiness = Readiness::check(config_arc.as_ref());
+    readiness.log();
+    let farms = FarmIndex::new(config_arc.as_ref());
+    //An invalid payout address leaves the farmer harvester only, the targets are never used then
+    let farmer_target_encoded = default_payout_address(config_arc.as_ref());
+    let farmer_target = decode_puzzle_hash(&farmer_target_encoded).unwrap_or_default();
+    let pool_target = decode_puzzle_hash(&farmer_target_encoded).unwrap_or_default();
+    let capture = match capture {
+        Some(path) => {
+            info!("Capturing protocol messages to {:?}", path);
+            ProtocolCapture::open(&path)?
+        }
+        None => ProtocolCapture::default(),
+    };
+    let shared_state = Arc::new(FarmerSharedState {
+        farmer_private_keys: Arc::new(farmer_private_keys),
+        owner_secret_keys: Arc::new(owner_secret_keys),
+        auth_secret_keys: Arc::new(auth_secret_keys),
+        pool_public_keys: Arc::new(pool_public_keys),
+        config: config_arc.clone(),
+        config_path: Arc::new(config_path),
+        run: Arc::new(AtomicBool::new(true)),
+        //Held back until the first lock check says this instance may farm, or until the other farmer
+        //with this config stops
+        standby: Arc::new(AtomicBool::new(
+            config_arc.high_availability.is_some() || instance_claim.is_waiting(),
+        )),
+        idle: Arc::new(IdleState::new(config_arc.idle_after)),
+        farmer_target: Arc::new(farmer_target),
+        pool_target: Arc::new(pool_target),
+        farms: Arc::new(farms),
+        readiness: Arc::new(readiness),
+        plot_registry: Arc::new(PlotRegistry::load(&get_excluded_plots_path())),
+        plot_leaderboard: Arc::new(Mutex::new(PlotLeaderboard::load(
+            &get_plot_leaderboard_path(),
+        ))),
+        latency_baselines: Arc::new(Mutex::new(LatencyBaselines::load(
+            &get_latency_baselines_path(),
+            config_arc.latency_regression.clone(),
+        ))),
+        availability: Arc::new(Mutex::new(AvailabilityTracker::load(
+            &get_availability_path(),
+            SpTiming::new(constants).sp_interval,
+        ))),
+        constants,
+        capture: Arc::new(capture),
+        profiling: profile,
+        ..Default::default()
+    });
+
+    info!("Using Additional Headers: {:?}", &*HEADERS);
+    //Pool Updater vars
+    let pool_state = shared_state.clone();
+    let pool_state_handle: JoinHandle<()> =
+        tokio::spawn(async move { pool_updater(pool_state).await });
+    let partial_batch_state = shared_state.clone();
+    let partial_batch_handle: JoinHandle<()> =
+        tokio::spawn(async move { partial_batch_sender(partial_batch_state).await });
+    let partial_queue_state = shared_state.clone();
+    let partial_queue_handle: JoinHandle<()> =
+        tokio::spawn(async move { partial_queue_flusher(partial_queue_state).await });
+    let status_state = shared_state.clone();
+    let status_handle: JoinHandle<()> =
+        tokio::spawn(async move { status_summary(status_state).await });
+    let history_state = shared_state.clone();
+    let history_handle: JoinHandle<()> =
+        tokio::spawn(async move { event_recorder(history_state).await });
+    let stats_state = shared_state.clone();
+    let stats_handle: JoinHandle<()> =
+        tokio::spawn(async move { stats_reporter(stats_state).await });
+    let email_state = shared_state.clone();
+    let email_handle: JoinHandle<()> =
+        tokio::spawn(async move { email_reporter(email_state).await });
+    let digest_state = shared_state.clone();
+    let digest_handle: JoinHandle<()> =
+        tokio::spawn(async move { sp_digest_logger(digest_state).await });
+    let relay_state = shared_state.clone();
+    let relay_handle: JoinHandle<()> = tokio::spawn(async move { sp_relay(relay_state).await });
+    let webhook_state = shared_state.clone();
+    let webhook_handle: JoinHandle<()> =
+        tokio::spawn(async move { webhook_sender(webhook_state).await });
+    let standby_state = shared_state.clone();
+    let standby_handle: JoinHandle<()> =
+        tokio::spawn(async move { standby_monitor(standby_state).await });
+    let lock_state = shared_state.clone();
+    let lock_handle: JoinHandle<()> =
+        tokio::spawn(async move { instance_lock_monitor(lock_state, instance_claim).await });
+    let idle_state = shared_state.clone();
+    let idle_handle: JoinHandle<()> = tokio::spawn(async move { idle_monitor(idle_state).await });
+    let dashboard_state = shared_state.clone();
+    let dashboard_handle: JoinHandle<()> =
+        tokio::spawn(async move { dashboard_server(dashboard_state).await });
+    let control_state = shared_state.clone();
+    let control_handle: JoinHandle<()> =
+        tokio::spawn(async move { control_server(control_state).await });
+    let network_state = shared_state.clone();
+    let network_handle: JoinHandle<()> =
+        tokio::spawn(async move { network_stats_updater(network_state).await });
+    let plugin_state = shared_state.clone();
+    let plugin_handle: JoinHandle<()> =
+        tokio::spawn(async move { plugin_runner(plugin_state).await });
+    let drive_health_state = shared_state.clone();
+    let drive_health_handle: JoinHandle<()> =
+        tokio::spawn(async move { drive_health_monitor(drive_health_state).await });
+    let key_health_state = shared_state.clone();
+    let key_health_handle: JoinHandle<()> =
+        tokio::spawn(async move { key_health_monitor(key_health_state).await });
+    let clock_state = shared_state.clone();
+    let clock_handle: JoinHandle<()> =
+        tokio::spawn(async move { clock_drift_monitor(clock_state).await });
+    let price_state = shared_state.clone();
+    let price_handle: JoinHandle<()> =
+        tokio::spawn(async move { xch_price_updater(price_state).await });
+
+    let pool_client = Arc::new(build_pool_client(config_arc.as_ref()));
+    let farmer = Farmer::new(shared_state, pool_client).await?;
+
+    //Client Vars
+    let client_handle: JoinHandle<Result<(), Error>> = tokio::spawn(async move {
+        farmer.run().await;
+        Ok(())
+    });
+    let _ = join!(
+        pool_state_handle,
+        partial_batch_handle,
+        partial_queue_handle,
+        status_handle,
+        history_handle,
+        stats_handle,
+        email_handle,
+        webhook_handle,
+        relay_handle,
+        digest_handle,
+        standby_handle,
+        lock_handle,
+        idle_handle,
+        dashboard_handle,
+        control_handle,
+        network_handle,
+        plugin_handle,
+        drive_health_handle,
+        price_handle,
+        clock_handle,
+        key_health_handle,
+        client_handle
+    );
+    Ok(())
+}
@@ -1,85 +1,60 @@
-use crate::cli::{generate_config_from_mnemonic, Action, Cli, GenerateConfig};
-use crate::farmer::config::{load_keys, Config};
-use crate::farmer::{Farmer, FarmerSharedState};
-use crate::tasks::pool_state_updater::pool_updater;
 use clap::Parser;
-use dg_xch_clients::api::pool::DefaultPoolClient;
-use dg_xch_core::consensus::constants::{CONSENSUS_CONSTANTS_MAP, MAINNET};
-use dg_xch_keys::decode_puzzle_hash;
-use hex::encode;
-use home::home_dir;
-use log::{info, LevelFilter};
-use once_cell::sync::Lazy;
-use reqwest::header::USER_AGENT;
+use dg_fast_farmer::cli::backup::{backup, restore};
+use dg_fast_farmer::cli::e2e::{run_e2e, E2eOptions};
+use dg_fast_farmer::cli::keys::verify_keys;
+use dg_fast_farmer::cli::pool::{migrate_pool, pool_login};
+use dg_fast_farmer::cli::replay::replay;
+use dg_fast_farmer::cli::simulate::{print_simulation, SimulateOptions};
+use dg_fast_farmer::cli::validate::validate_config;
+use dg_fast_farmer::cli::{
+    capture_profile, generate_config_from_mnemonic, log_level, print_dir_latency,
+    print_harvester_stats, print_plot_leaderboard, print_recent_events, print_rpc_metrics,
+    print_status, print_unfarmable_plots, print_version, reload_config, set_paused, set_subsystem,
+    soft_restart, tail_events, Action, Cli, GenerateConfig, KeysAction, PoolAction,
+};
+use dg_fast_farmer::control::{get_control_socket_path, set_log_level};
+use dg_fast_farmer::farmer::chaos;
+use dg_fast_farmer::farmer::config::{Config, RuntimeConfig};
+use dg_fast_farmer::farmer::keyring::resolve_keyring_keys;
+use dg_fast_farmer::farmer::plot_leaderboard::LeaderboardParams;
+use dg_fast_farmer::farmer::time_format::{configure_logger, init_time_format};
+#[cfg(feature = "gui")]
+use dg_fast_farmer::gui;
+use dg_fast_farmer::{get_config_path, get_root_path, run_farmer};
+use log::LevelFilter;
 use simple_logger::SimpleLogger;
-use std::collections::HashMap;
-use std::env;
 use std::io::Error;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use tokio::fs::create_dir_all;
-use tokio::join;
-use tokio::task::JoinHandle;
 
-fn _version() -> &'static str {
-    env!("CARGO_PKG_VERSION")
-}
-fn _pkg_name() -> &'static str {
-    env!("CARGO_PKG_NAME")
-}
-
-pub fn version() -> String {
-    format!("{}: {}", _pkg_name(), _version())
-}
-
-#[test]
-fn version_test() {
-    println!("{}", version());
-}
-
-pub static HEADERS: Lazy<HashMap<String, String>> = Lazy::new(|| {
-    let mut headers = HashMap::new();
-    headers.insert(
-        String::from("X-fast-farmer-version"),
-        _version().to_string(),
-    );
-    headers.insert(USER_AGENT.to_string(), version());
-    headers.insert(String::from("X-dg-xch-pos-version"), dg_xch_pos::version());
-    headers
-});
-
-pub mod cli;
-pub mod farmer;
-pub mod gui;
-pub mod harvesters;
-pub mod tasks;
-
-fn get_root_path() -> PathBuf {
-    let prefix = match home_dir() {
-        Some(path) => path,
-        None => Path::new("/").to_path_buf(),
+fn main() -> Result<(), Error> {
+    let cli = Cli::parse();
+    //The runtime is built before the async main so the config can size it
+    let config_path = cli
+        .config
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(get_config_path);
+    let runtime = if config_path.exists() {
+        Config::try_from(&config_path)
+            .map(|c| c.runtime)
+            .unwrap_or_default()
+    } else {
+        RuntimeConfig::default()
     };
-    prefix.as_path().join(Path::new(".config/fast_farmer/"))
+    runtime.build()?.block_on(run(cli))
 }
 
-fn get_config_path() -> PathBuf {
-    get_root_path()
-        .as_path()
-        .join(Path::new("fast_farmer.yaml"))
-}
-
-fn get_ssl_root_path(shared_state: &FarmerSharedState) -> PathBuf {
-    if let Some(ssl_root_path) = &shared_state.config.ssl_root_path {
-        PathBuf::from(ssl_root_path)
-    } else {
-        get_root_path().as_path().join(Path::new("ssl/"))
-    }
+//Reading and migrating the config is blocking IO, it is kept off the executor threads
+async fn read_config(config_path: &Path) -> Config {
+    let path = config_path.to_path_buf();
+    tokio::task::spawn_blocking(move || Config::try_from(&path).unwrap_or_default())
+        .await
+        .unwrap_or_default()
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Error> {
-    let cli = Cli::parse();
+async fn run(cli: Cli) -> Result<(), Error> {
     let config_path = if let Some(s) = &cli.config {
         PathBuf::from(s)
     } else {
@@ -88,6 +63,12 @@ async fn main() -> Result<(), Error> {
     };
     let action = cli.action.unwrap_or_default();
     match action {
+        #[cfg(not(feature = "gui"))]
+        Action::Gui {} => {
+            eprintln!("ff was built without the gui feature, use ff run");
+            Ok(())
+        }
+        #[cfg(feature = "gui")]
         Action::Gui {} => {
             if !config_path.exists() {
                 eprintln!(
@@ -96,12 +77,18 @@ async fn main() -> Result<(), Error> {
                 );
                 return Ok(());
             }
-            let config = Config::try_from(&config_path).unwrap_or_default();
+            let mut config = read_config(&config_path).await;
+            init_time_format(&config.time);
+            resolve_keyring_keys(&mut config)?;
             let config_arc = Arc::new(config);
-            gui::bootstrap(config_arc).await?;
+            gui::bootstrap(config_arc, config_path).await?;
             Ok(())
         }
-        Action::Run {} => {
+        Action::Run {
+            capture,
+            profile,
+            chaos: scenario,
+        } => {
             if !config_path.exists() {
                 eprintln!(
                     "Failed to find config at {:?}, please run init",
@@ -109,55 +96,32 @@ async fn main() -> Result<(), Error> {
                 );
                 return Ok(());
             }
-            SimpleLogger::new()
-                .with_colors(true)
-                .with_level(LevelFilter::Info)
-                .env()
-                .init()
-                .unwrap_or_default();
-            let config = Config::try_from(&config_path).unwrap_or_default();
-            let config_arc = Arc::new(config);
-            let constants = CONSENSUS_CONSTANTS_MAP
-                .get(&config_arc.selected_network)
-                .unwrap_or(&MAINNET);
-            info!(
-                "Selected Network: {}, AggSig: {}",
-                &config_arc.selected_network,
-                &encode(&constants.agg_sig_me_additional_data)
-            );
-            let (farmer_private_keys, owner_secret_keys, auth_secret_keys, pool_public_keys) =
-                load_keys(config_arc.clone()).await;
-            let farmer_target_encoded = &config_arc.payout_address;
-            let farmer_target = decode_puzzle_hash(farmer_target_encoded)?;
-            let pool_target = decode_puzzle_hash(farmer_target_encoded)?;
-            let shared_state = Arc::new(FarmerSharedState {
-                farmer_private_keys: Arc::new(farmer_private_keys),
-                owner_secret_keys: Arc::new(owner_secret_keys),
-                auth_secret_keys: Arc::new(auth_secret_keys),
-                pool_public_keys: Arc::new(pool_public_keys),
-                config: config_arc.clone(),
-                run: Arc::new(AtomicBool::new(true)),
-                farmer_target: Arc::new(farmer_target),
-                pool_target: Arc::new(pool_target),
-                ..Default::default()
-            });
-
-            info!("Using Additional Headers: {:?}", &*HEADERS);
-            //Pool Updater vars
-            let pool_state = shared_state.clone();
-            let pool_state_handle: JoinHandle<()> =
-                tokio::spawn(async move { pool_updater(pool_state).await });
-
-            let pool_client = Arc::new(DefaultPoolClient::new());
-            let farmer = Farmer::new(shared_state, pool_client).await?;
-
-            //Client Vars
-            let client_handle: JoinHandle<Result<(), Error>> = tokio::spawn(async move {
-                farmer.run().await;
-                Ok(())
-            });
-            let _ = join!(pool_state_handle, client_handle);
-            Ok(())
+            let mut config = read_config(&config_path).await;
+            init_time_format(&config.time);
+            //Everything is passed to the logger so `ff log-level` can raise the level at runtime
+            configure_logger(
+                SimpleLogger::new()
+                    .with_colors(true)
+                    .with_level(LevelFilter::Trace)
+                    .env(),
+                &config.time,
+            )
+            .init()
+            .unwrap_or_default();
+            if std::env::var("RUST_LOG").is_err() {
+                set_log_level(LevelFilter::Info);
+            }
+            if let Some(scenario) = scenario {
+                chaos::install(Path::new(&scenario))?;
+            }
+            resolve_keyring_keys(&mut config)?;
+            run_farmer(
+                Arc::new(config),
+                config_path,
+                capture.map(PathBuf::from),
+                profile,
+            )
+            .await
         }
         Action::Init {
             mnemonic,
@@ -169,6 +133,7 @@ async fn main() -> Result<(), Error> {
             network,
             payout_address,
             plot_directories,
+            discover,
         } => {
             SimpleLogger::new()
                 .with_colors(true)
@@ -188,9 +153,285 @@ async fn main() -> Result<(), Error> {
                 payout_address,
                 plot_directories,
                 additional_headers: None,
+                discover,
             })
             .await?;
             Ok(())
         }
+        Action::Tail { filter } => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            init_time_format(&config.time);
+            tail_events(&get_control_socket_path(&config), filter).await
+        }
+        Action::Events { limit, filter } => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            init_time_format(&config.time);
+            print_recent_events(&get_control_socket_path(&config), limit, filter).await
+        }
+        Action::RpcMetrics {} => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            print_rpc_metrics(&get_control_socket_path(&config)).await
+        }
+        Action::Harvesters {} => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            print_harvester_stats(&get_control_socket_path(&config)).await
+        }
+        Action::Leaderboard {
+            sort,
+            limit,
+            ascending,
+        } => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            print_plot_leaderboard(
+                &get_control_socket_path(&config),
+                LeaderboardParams {
+                    sort,
+                    limit: Some(limit),
+                    ascending,
+                },
+            )
+            .await
+        }
+        Action::Plots {} => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            print_unfarmable_plots(&get_control_socket_path(&config)).await
+        }
+        Action::Dirs {} => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            print_dir_latency(&get_control_socket_path(&config)).await
+        }
+        Action::Status { json } => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            print_status(&get_control_socket_path(&config), json).await
+        }
+        Action::Version { json } => {
+            let config = config_path
+                .exists()
+                .then(|| Config::try_from(&config_path).ok())
+                .flatten();
+            print_version(config.as_ref(), json)
+        }
+        Action::Reload {} => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            reload_config(&get_control_socket_path(&config)).await
+        }
+        Action::SoftRestart {} => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            soft_restart(&get_control_socket_path(&config)).await
+        }
+        Action::LogLevel { level } => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            log_level(&get_control_socket_path(&config), level).await
+        }
+        Action::Profile { seconds, all } => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            capture_profile(&get_control_socket_path(&config), seconds, all).await
+        }
+        Action::Simulate {
+            days,
+            difficulty,
+            runs,
+            netspace_eib,
+        } => {
+            if !config_path.exists() {
+                eprintln!(
+                    "Failed to find config at {:?}, please run init",
+                    config_path
+                );
+                return Ok(());
+            }
+            let config = Config::try_from(&config_path)?;
+            print_simulation(
+                &config,
+                SimulateOptions {
+                    days,
+                    difficulty,
+                    runs,
+                    netspace_eib,
+                },
+            )
+            .await
+        }
+        Action::Keys {
+            action: KeysAction::Verify { sample },
+        } => {
+            if !config_path.exists() {
+                eprintln!(
+                    "Failed to find config at {:?}, please run init",
+                    config_path
+                );
+                return Ok(());
+            }
+            let mut config = Config::try_from(&config_path)?;
+            resolve_keyring_keys(&mut config)?;
+            verify_keys(config, sample).await
+        }
+        Action::Pool {
+            action:
+                PoolAction::Migrate {
+                    launcher_id,
+                    pool_url,
+                    yes,
+                },
+        } => {
+            if !config_path.exists() {
+                eprintln!(
+                    "Failed to find config at {:?}, please run init",
+                    config_path
+                );
+                return Ok(());
+            }
+            SimpleLogger::new()
+                .with_colors(true)
+                .with_level(LevelFilter::Info)
+                .env()
+                .init()
+                .unwrap_or_default();
+            let mut config = Config::try_from(&config_path)?;
+            resolve_keyring_keys(&mut config)?;
+            migrate_pool(&config_path, config, &launcher_id, &pool_url, yes).await
+        }
+        Action::Pool {
+            action: PoolAction::Login { launcher_id },
+        } => {
+            if !config_path.exists() {
+                eprintln!(
+                    "Failed to find config at {:?}, please run init",
+                    config_path
+                );
+                return Ok(());
+            }
+            let mut config = Config::try_from(&config_path)?;
+            resolve_keyring_keys(&mut config)?;
+            pool_login(config, &launcher_id).await
+        }
+        Action::Backup { out, encrypt } => {
+            if !config_path.exists() {
+                eprintln!(
+                    "Failed to find config at {:?}, please run init",
+                    config_path
+                );
+                return Ok(());
+            }
+            backup(&config_path, Path::new(&out), encrypt)
+        }
+        Action::Restore { input, force } => restore(&config_path, Path::new(&input), force),
+        Action::Replay { capture, out } => {
+            if !config_path.exists() {
+                eprintln!(
+                    "Failed to find config at {:?}, please run init",
+                    config_path
+                );
+                return Ok(());
+            }
+            let mut config = Config::try_from(&config_path)?;
+            resolve_keyring_keys(&mut config)?;
+            replay(config, Path::new(&capture), out.as_deref().map(Path::new)).await
+        }
+        Action::ValidateConfig { host, all_hosts } => {
+            if !config_path.exists() {
+                eprintln!("Failed to find config at {:?}", config_path);
+                return Ok(());
+            }
+            validate_config(&config_path, host, all_hosts)
+        }
+        Action::E2e {
+            chia,
+            plot_dir,
+            k,
+            plots,
+            timeout,
+        } => {
+            if !config_path.exists() {
+                eprintln!(
+                    "Failed to find config at {:?}, please run init",
+                    config_path
+                );
+                return Ok(());
+            }
+            let config = Config::try_from(&config_path)?;
+            run_e2e(
+                &config_path,
+                config,
+                E2eOptions {
+                    chia,
+                    plot_dir: plot_dir
+                        .map(PathBuf::from)
+                        .unwrap_or_else(|| get_root_path().join("e2e").join("plots")),
+                    k,
+                    plots,
+                    timeout,
+                },
+            )
+            .await
+        }
+        Action::Subsystem { action } => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            set_subsystem(&get_control_socket_path(&config), action).await
+        }
+        Action::Pause {} | Action::Resume {} => {
+            let config = if config_path.exists() {
+                Config::try_from(&config_path).unwrap_or_default()
+            } else {
+                Config::default()
+            };
+            set_paused(
+                &get_control_socket_path(&config),
+                matches!(action, Action::Pause {}),
+            )
+            .await
+        }
     }
 }
@@ -0,0 +1,24 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+pub fn open_direct(_path: &Path) -> Result<File, Error> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "Direct IO is not supported on this platform",
+    ))
+}
+
+pub fn advise_random(_file: &File) {}
+
+//NUMA placement needs the Linux node and affinity interfaces
+pub fn numa_node_cpus(_node: usize) -> Option<Vec<usize>> {
+    None
+}
+
+pub fn pin_current_thread(_cpus: &[usize]) -> Result<(), Error> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "Thread affinity is not supported on this platform",
+    ))
+}
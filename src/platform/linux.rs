@@ -0,0 +1,75 @@
+use std::fs::{File, OpenOptions};
+use std::io::Error;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+pub fn open_direct(path: &Path) -> Result<File, Error> {
+    OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECT)
+        .open(path)
+}
+
+//Disables kernel read-ahead for this descriptor, lookups are random reads
+pub fn advise_random(file: &File) {
+    unsafe {
+        libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_RANDOM);
+    }
+}
+
+//Partitions map to their disk
+pub(super) fn block_device(major: u64, minor: u64) -> Option<String> {
+    let sys_path = PathBuf::from(format!("/sys/dev/block/{major}:{minor}"));
+    let device = sys_path.canonicalize().ok()?;
+    let disk = if sys_path.join("partition").exists() {
+        device.parent()?.to_path_buf()
+    } else {
+        device
+    };
+    disk.file_name().map(|n| n.to_string_lossy().to_string())
+}
+
+//CPUs of a NUMA node, the kernel lists them as ranges like "0-15,32-47"
+pub fn numa_node_cpus(node: usize) -> Option<Vec<usize>> {
+    let list =
+        std::fs::read_to_string(format!("/sys/devices/system/node/node{node}/cpulist")).ok()?;
+    parse_cpu_list(&list).filter(|cpus| !cpus.is_empty())
+}
+
+//Memory the thread touches first is then placed on the node of these CPUs
+pub fn pin_current_thread(cpus: &[usize]) -> Result<(), Error> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for cpu in cpus {
+            libc::CPU_SET(*cpu, &mut set);
+        }
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = vec![];
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => cpus.extend(start.parse::<usize>().ok()?..=end.parse().ok()?),
+            None => cpus.push(range.parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cpu_lists() {
+        assert_eq!(parse_cpu_list("0-2,8\n"), Some(vec![0, 1, 2, 8]));
+        assert_eq!(parse_cpu_list(""), Some(vec![]));
+        assert_eq!(parse_cpu_list("0-x"), None);
+    }
+}
@@ -0,0 +1,61 @@
+//OS specific calls live here so the rest of the crate builds the same on every target. Linux, on
+//x86_64 and aarch64 alike, gets direct IO, read advice, block device names and NUMA placement,
+//Windows unbuffered reads and volume names. Other platforms fall back to buffered reads and per
+//directory drives
+#[cfg(not(any(target_os = "linux", windows)))]
+mod fallback;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub use fallback::{advise_random, numa_node_cpus, open_direct, pin_current_thread};
+#[cfg(target_os = "linux")]
+pub use linux::{advise_random, numa_node_cpus, open_direct, pin_current_thread};
+#[cfg(unix)]
+pub use unix::{create_private, drive_id, read_at, set_mode};
+#[cfg(windows)]
+pub use windows::{
+    advise_random, create_private, drive_id, numa_node_cpus, open_direct, pin_current_thread,
+    read_at, set_mode,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn platform_calls_work_on_this_target() {
+        let dir = std::env::temp_dir().join(format!("ff_platform_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("plot");
+        std::fs::File::create(&path)
+            .unwrap()
+            .write_all(b"0123456789")
+            .unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+        advise_random(&file);
+        let mut buf = [0u8; 4];
+        assert_eq!(read_at(&file, &mut buf, 3).unwrap(), 4);
+        assert_eq!(&buf, b"3456");
+        set_mode(&path, 0o600).unwrap();
+        let private = dir.join("private");
+        create_private(&private).unwrap().write_all(b"key").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&private).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        //Direct IO may be refused by the file system, like tmpfs, it must not panic either way
+        let _ = open_direct(&path);
+        let drive = drive_id(&dir).unwrap();
+        assert_eq!(drive_id(&path), Some(drive));
+        assert_eq!(drive_id(&dir.join("missing")), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,59 @@
+use std::fs::{File, OpenOptions};
+use std::io::Error;
+use std::os::unix::fs::{FileExt, MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+
+#[cfg(target_os = "linux")]
+use super::linux::block_device;
+
+pub fn read_at(file: &File, buf: &mut [u8], offset: u64) -> Result<usize, Error> {
+    file.read_at(buf, offset)
+}
+
+pub fn set_mode(path: &Path, mode: u32) -> Result<(), Error> {
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+//Truncated or created owner only before anything is written, an existing file keeps its inode so
+//its mode is narrowed too
+pub fn create_private(path: &Path) -> Result<File, Error> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    Ok(file)
+}
+
+//The block device a path is stored on, named where the platform can tell, otherwise by its
+//device numbers
+pub fn drive_id(path: &Path) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let (major, minor) = split_dev(metadata.dev());
+    Some(block_device(major, minor).unwrap_or_else(|| format!("{major}:{minor}")))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn block_device(_major: u64, _minor: u64) -> Option<String> {
+    None
+}
+
+//Linux encoding of dev_t, the same as the libc major/minor macros
+fn split_dev(dev: u64) -> (u64, u64) {
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+    (major, minor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_device_numbers() {
+        assert_eq!(split_dev(0x0811), (8, 17));
+        assert_eq!(split_dev((259 << 8) | 3), (259, 3));
+    }
+}
@@ -0,0 +1,79 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Error, ErrorKind};
+use std::os::windows::fs::{FileExt, OpenOptionsExt};
+use std::path::{Component, Path, Prefix};
+
+const FILE_FLAG_NO_BUFFERING: u32 = 0x20000000;
+
+pub fn open_direct(path: &Path) -> Result<File, Error> {
+    OpenOptions::new()
+        .read(true)
+        .custom_flags(FILE_FLAG_NO_BUFFERING)
+        .open(path)
+}
+
+//Windows takes access hints when the file is opened, not per descriptor
+pub fn advise_random(_file: &File) {}
+
+pub fn read_at(file: &File, buf: &mut [u8], offset: u64) -> Result<usize, Error> {
+    file.seek_read(buf, offset)
+}
+
+//Files inherit the ACL of their directory, which the user profile already restricts
+pub fn set_mode(_path: &Path, _mode: u32) -> Result<(), Error> {
+    Ok(())
+}
+
+pub fn create_private(path: &Path) -> Result<File, Error> {
+    File::create(path)
+}
+
+//The volume a path is stored on, a drive letter or a network share
+pub fn drive_id(path: &Path) -> Option<String> {
+    volume(&path.canonicalize().ok()?)
+}
+
+fn volume(path: &Path) -> Option<String> {
+    let Some(Component::Prefix(prefix)) = path.components().next() else {
+        return None;
+    };
+    match prefix.kind() {
+        Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+            Some(format!("{}:", letter.to_ascii_uppercase() as char))
+        }
+        Prefix::UNC(server, share) | Prefix::VerbatimUNC(server, share) => Some(format!(
+            r"\\{}\{}",
+            server.to_string_lossy(),
+            share.to_string_lossy()
+        )),
+        _ => Some(prefix.as_os_str().to_string_lossy().to_string()),
+    }
+}
+
+//NUMA placement needs the Linux node and affinity interfaces
+pub fn numa_node_cpus(_node: usize) -> Option<Vec<usize>> {
+    None
+}
+
+pub fn pin_current_thread(_cpus: &[usize]) -> Result<(), Error> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "Thread affinity is not supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volumes_of_drives_and_shares() {
+        assert_eq!(volume(Path::new(r"\\?\d:\plots")), Some("D:".to_string()));
+        assert_eq!(volume(Path::new(r"E:\plots\a")), Some("E:".to_string()));
+        assert_eq!(
+            volume(Path::new(r"\\?\UNC\nas\plots\k32")),
+            Some(r"\\nas\plots".to_string())
+        );
+        assert_eq!(volume(Path::new(r"plots\a")), None);
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod process;
+
+use crate::farmer::config::Config;
+use crate::farmer::events::FarmerEvent;
+use crate::farmer::FarmerSharedState;
+use crate::plugins::process::ProcessPlugin;
+use async_trait::async_trait;
+use log::warn;
+use once_cell::sync::Lazy;
+use std::sync::{Arc, Mutex};
+
+//Extension point for alerting, pools or forks that should not need a fork of the crate. Every
+//plugin gets its own event subscription, a slow plugin lags behind without holding up the others
+#[async_trait]
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &str;
+    async fn on_event(&self, _shared_state: &FarmerSharedState, _event: &FarmerEvent) {}
+    //Called every PLUGIN_TICK while the farmer runs
+    async fn on_tick(&self, _shared_state: &FarmerSharedState) {}
+}
+
+static REGISTERED: Lazy<Mutex<Vec<Arc<dyn Plugin>>>> = Lazy::new(Default::default);
+
+//For crates embedding the farmer, call before run_farmer
+pub fn register_plugin(plugin: Arc<dyn Plugin>) {
+    if let Ok(mut registered) = REGISTERED.lock() {
+        registered.push(plugin);
+    }
+}
+
+//Compiled in plugins are added here behind their cargo feature, then registered and process plugins
+pub fn load_plugins(config: &Config) -> Vec<Arc<dyn Plugin>> {
+    let mut plugins: Vec<Arc<dyn Plugin>> = vec![];
+    if let Some(mqtt) = &config.mqtt {
+        #[cfg(feature = "mqtt")]
+        match mqtt::MqttPlugin::new(mqtt.clone()) {
+            Ok(plugin) => plugins.push(Arc::new(plugin)),
+            Err(e) => warn!("Failed to set up MQTT for {}: {e}", mqtt.host),
+        }
+        #[cfg(not(feature = "mqtt"))]
+        warn!(
+            "MQTT is configured for {} but this build has no mqtt feature",
+            mqtt.host
+        );
+    }
+    if let Ok(registered) = REGISTERED.lock() {
+        plugins.extend(registered.iter().cloned());
+    }
+    for plugin_config in &config.plugins {
+        plugins.push(Arc::new(ProcessPlugin::new(plugin_config.clone(), config)));
+    }
+    plugins
+}
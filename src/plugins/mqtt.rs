@@ -0,0 +1,150 @@
+use crate::control::status;
+use crate::farmer::config::MqttConfig;
+use crate::farmer::events::FarmerEvent;
+use crate::farmer::FarmerSharedState;
+use crate::plugins::Plugin;
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use rumqttc::{
+    qos, AsyncClient, Event, LastWill, MqttOptions, Packet, QoS, TlsConfiguration, Transport,
+};
+use std::io::Error;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+//Messages queued while the broker is unreachable, newer ones are dropped once it is full
+const QUEUE_SIZE: usize = 256;
+
+//Topics below topic_prefix:
+//  availability  online/offline, retained, offline is the last will
+//  status        the ff status json, retained
+//  events/<type> every event as json
+pub struct MqttPlugin {
+    config: MqttConfig,
+    qos: QoS,
+    client: AsyncClient,
+    last_status: Mutex<Option<Instant>>,
+}
+impl MqttPlugin {
+    pub fn new(config: MqttConfig) -> Result<Self, Error> {
+        let qos = qos(config.qos).map_err(|e| Error::other(format!("{e:?}")))?;
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(KEEP_ALIVE);
+        options.set_last_will(LastWill::new(
+            topic(&config, "availability"),
+            "offline",
+            qos,
+            true,
+        ));
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.clone().unwrap_or_default());
+        }
+        if config.tls {
+            let transport = match &config.ca_file {
+                Some(ca_file) => Transport::tls_with_config(TlsConfiguration::Simple {
+                    ca: std::fs::read(ca_file)?,
+                    alpn: None,
+                    client_auth: None,
+                }),
+                None => Transport::tls_with_default_config(),
+            };
+            options.set_transport(transport);
+        }
+        let (client, mut event_loop) = AsyncClient::new(options, QUEUE_SIZE);
+        let availability = topic(&config, "availability");
+        let online = client.clone();
+        let broker = format!("{}:{}", config.host, config.port);
+        tokio::spawn(async move {
+            loop {
+                match event_loop.poll().await {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        info!("Connected to MQTT broker {broker}");
+                        let _ = online.try_publish(&availability, qos, true, "online");
+                    }
+                    Ok(_) => {}
+                    Err(rumqttc::ConnectionError::RequestsDone) => break,
+                    Err(e) => {
+                        warn!("MQTT connection to {broker} failed: {e}");
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            config,
+            qos,
+            client,
+            last_status: Mutex::new(None),
+        })
+    }
+    fn publish(&self, sub_topic: &str, retain: bool, payload: Vec<u8>) {
+        if let Err(e) =
+            self.client
+                .try_publish(topic(&self.config, sub_topic), self.qos, retain, payload)
+        {
+            debug!("Dropped MQTT message for {sub_topic}: {e}");
+        }
+    }
+}
+
+fn topic(config: &MqttConfig, sub_topic: &str) -> String {
+    format!("{}/{sub_topic}", config.topic_prefix.trim_end_matches('/'))
+}
+
+#[async_trait]
+impl Plugin for MqttPlugin {
+    fn name(&self) -> &str {
+        "mqtt"
+    }
+    async fn on_event(&self, _shared_state: &FarmerSharedState, event: &FarmerEvent) {
+        if !self.config.events.is_empty()
+            && !self.config.events.iter().any(|f| f.matches(&event.kind))
+        {
+            return;
+        }
+        let Ok(payload) = serde_json::to_value(event) else {
+            return;
+        };
+        let kind = payload["type"].as_str().unwrap_or("unknown").to_string();
+        self.publish(&format!("events/{kind}"), false, payload.to_string().into());
+    }
+    async fn on_tick(&self, shared_state: &FarmerSharedState) {
+        let mut last_status = self.last_status.lock().await;
+        let interval = Duration::from_secs(self.config.status_interval.max(1));
+        if last_status.is_some_and(|last| last.elapsed() < interval) {
+            return;
+        }
+        *last_status = Some(Instant::now());
+        match serde_json::to_vec(&status(shared_state).await) {
+            Ok(payload) => self.publish("status", true, payload),
+            Err(e) => warn!("Failed to serialize status for MQTT: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn topics_are_below_prefix() {
+        let config = MqttConfig {
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: "test".to_string(),
+            username: None,
+            password: None,
+            topic_prefix: "farm/".to_string(),
+            tls: false,
+            ca_file: None,
+            qos: 1,
+            events: vec![],
+            status_interval: 60,
+        };
+        assert_eq!(topic(&config, "status"), "farm/status");
+        assert!(MqttPlugin::new(MqttConfig { qos: 3, ..config }).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::control::get_control_socket_path;
+use crate::farmer::config::{Config, PluginConfig};
+use crate::farmer::FarmerSharedState;
+use crate::plugins::Plugin;
+use async_trait::async_trait;
+use log::{info, warn};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+pub const PLUGIN_SOCKET_ENV: &str = "FF_CONTROL_SOCKET";
+pub const PLUGIN_NAME_ENV: &str = "FF_PLUGIN_NAME";
+const RESTART_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct ProcessState {
+    child: Option<Child>,
+    next_start: Option<Instant>,
+}
+
+//Runs an external plugin, it subscribes to events and calls methods over the control socket like
+//ff does. The process is killed when the farmer stops
+pub struct ProcessPlugin {
+    config: PluginConfig,
+    socket_path: PathBuf,
+    state: Mutex<ProcessState>,
+}
+impl ProcessPlugin {
+    pub fn new(config: PluginConfig, farmer_config: &Config) -> Self {
+        Self {
+            config,
+            socket_path: get_control_socket_path(farmer_config),
+            state: Default::default(),
+        }
+    }
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.config.command);
+        command
+            .args(&self.config.args)
+            .envs(&self.config.env)
+            .env(PLUGIN_SOCKET_ENV, &self.socket_path)
+            .env(PLUGIN_NAME_ENV, &self.config.name)
+            .kill_on_drop(true);
+        command
+    }
+}
+
+#[async_trait]
+impl Plugin for ProcessPlugin {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+    async fn on_tick(&self, _shared_state: &FarmerSharedState) {
+        let mut state = self.state.lock().await;
+        if let Some(child) = state.child.as_mut() {
+            match child.try_wait() {
+                Ok(None) => return,
+                Ok(Some(status)) => warn!(
+                    "Plugin {} exited with {status}, restarting in {}s",
+                    self.config.name,
+                    RESTART_DELAY.as_secs()
+                ),
+                Err(e) => warn!("Failed to check plugin {}: {:?}", self.config.name, e),
+            }
+            state.child = None;
+        }
+        if state.next_start.is_some_and(|next| Instant::now() < next) {
+            return;
+        }
+        state.next_start = Some(Instant::now() + RESTART_DELAY);
+        match self.command().spawn() {
+            Ok(child) => {
+                info!(
+                    "Started plugin {} ({}), pid {}",
+                    self.config.name,
+                    self.config.command,
+                    child.id().unwrap_or_default()
+                );
+                state.child = Some(child);
+            }
+            Err(e) => warn!(
+                "Failed to start plugin {} ({}): {:?}",
+                self.config.name, self.config.command, e
+            ),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn exited_process_is_restarted_after_delay() {
+        let plugin = ProcessPlugin::new(
+            PluginConfig {
+                name: "probe".to_string(),
+                command: "sh".to_string(),
+                args: vec![
+                    "-c".to_string(),
+                    format!(
+                        "test -n \"${PLUGIN_SOCKET_ENV}\" && test \"${PLUGIN_NAME_ENV}\" = probe"
+                    ),
+                ],
+                env: HashMap::new(),
+            },
+            &Config::default(),
+        );
+        let shared_state = FarmerSharedState::default();
+        plugin.on_tick(&shared_state).await;
+        let status = plugin
+            .state
+            .lock()
+            .await
+            .child
+            .as_mut()
+            .unwrap()
+            .wait()
+            .await
+            .unwrap();
+        assert!(status.success());
+        plugin.on_tick(&shared_state).await;
+        assert!(plugin.state.lock().await.child.is_none());
+    }
+}
@@ -0,0 +1,52 @@
+use crate::farmer::clock_drift::query_ntp;
+use crate::farmer::FarmerSharedState;
+use log::{debug, info, warn};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+//Compares the system clock against NTP, a skewed clock makes partials look stale to the pool and
+//every latency measured against signage point timestamps wrong
+pub async fn clock_drift_monitor(shared_state: Arc<FarmerSharedState>) {
+    let config = shared_state.config.time.clone();
+    if config.ntp_servers.is_empty() {
+        return;
+    }
+    let interval = Duration::from_secs(config.drift_check_interval.max(1) * 60);
+    let mut last_check: Option<Instant> = None;
+    let mut warned = false;
+    while shared_state.run.load(Ordering::Relaxed) {
+        if last_check.is_none_or(|last| last.elapsed() >= interval) {
+            last_check = Some(Instant::now());
+            let mut drift = None;
+            for server in &config.ntp_servers {
+                match query_ntp(server).await {
+                    Ok(d) => {
+                        drift = Some(d);
+                        break;
+                    }
+                    Err(e) => debug!("Failed to check the clock against {server}: {e}"),
+                }
+            }
+            if let Some(drift) = drift {
+                if drift.offset_ms.unsigned_abs() > config.max_drift_ms {
+                    warn!(
+                        "System clock is {}ms {} {}, partials may be rejected as stale and latency stats are off, check the time sync of this host",
+                        drift.offset_ms.unsigned_abs(),
+                        if drift.offset_ms > 0 { "ahead of" } else { "behind" },
+                        drift.server
+                    );
+                    warned = true;
+                } else if warned {
+                    info!(
+                        "System clock is back within {}ms of {}",
+                        config.max_drift_ms, drift.server
+                    );
+                    warned = false;
+                }
+                shared_state.gui_stats.lock().await.clock_drift = Some(drift);
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
@@ -0,0 +1,75 @@
+use crate::farmer::partial_queue::now;
+use crate::farmer::FarmerSharedState;
+use crate::harvesters::drive_health::read_drive_health;
+use crate::harvesters::drive_workers::drive_id;
+use crate::harvesters::plot_dirs::plot_dirs;
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+//Reads the SMART data of the drives backing the plot directories so a rising lookup latency can be
+//matched with a failing or overheating drive. Newly raised flags are logged
+pub async fn drive_health_monitor(shared_state: Arc<FarmerSharedState>) {
+    let Some(bb_config) = shared_state.config.harvester_configs.bladebit.clone() else {
+        return;
+    };
+    let config = bb_config.drive_health.clone();
+    if config.interval == 0 {
+        return;
+    }
+    //Directories that do not resolve to a block device, like network mounts, are not read
+    let mut drives: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    for dir in plot_dirs(&bb_config) {
+        let drive = drive_id(&dir);
+        if drive.contains([':', '/', '\\']) {
+            info!(
+                "Skipping drive health of {:?}, it is not on a local drive",
+                dir
+            );
+            continue;
+        }
+        drives.entry(drive).or_default().push(dir);
+    }
+    info!(
+        "Reading health of {} drives every {}s",
+        drives.len(),
+        config.interval
+    );
+    let interval = Duration::from_secs(config.interval);
+    let mut last_check: Option<Instant> = None;
+    while shared_state.run.load(Ordering::Relaxed) {
+        if last_check.is_none_or(|last| last.elapsed() >= interval) {
+            last_check = Some(Instant::now());
+            for (drive, dirs) in &drives {
+                let mut health = read_drive_health(&config, drive).await;
+                health.checked_at = now();
+                let previous = shared_state.drive_health.get(&dirs[0]).await;
+                //Read errors usually persist, like a missing smartctl, they are logged once
+                if let Some(error) = &health.error {
+                    if previous.as_ref().is_none_or(|p| p.error.is_none()) {
+                        warn!("Failed to read health of drive {drive}: {error}");
+                    }
+                }
+                let new_flags: Vec<&String> = health
+                    .flags
+                    .iter()
+                    .filter(|f| previous.as_ref().is_none_or(|p| !p.flags.contains(f)))
+                    .collect();
+                if !new_flags.is_empty() {
+                    warn!(
+                        "Drive {drive} of {:?} looks unhealthy: {}",
+                        dirs,
+                        health.flags.join(", ")
+                    );
+                }
+                for dir in dirs {
+                    shared_state.drive_health.set(dir, health.clone()).await;
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
@@ -0,0 +1,106 @@
+use crate::farmer::config::EmailReportConfig;
+use crate::farmer::email_report::{
+    compose_message, next_report, EmailTemplates, ReportCounters, Summary,
+};
+use crate::farmer::partial_queue::now;
+use crate::farmer::smtp::send_mail;
+use crate::farmer::subsystems::Subsystem;
+use crate::farmer::time_format::{format_timestamp, utc_offset};
+use crate::farmer::FarmerSharedState;
+use crate::version;
+use log::{debug, info, warn};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::TryRecvError;
+
+pub async fn email_reporter(shared_state: Arc<FarmerSharedState>) {
+    let Some(report_config) = shared_state.config.email_report.clone() else {
+        return;
+    };
+    if report_config.to.is_empty() {
+        warn!("Email reports disabled: no recipients configured");
+        return;
+    }
+    let templates = match EmailTemplates::parse(&report_config) {
+        Ok(templates) => templates,
+        Err(e) => {
+            warn!("Email reports disabled: {:?}", e);
+            return;
+        }
+    };
+    let mut events = shared_state.events.subscribe();
+    let mut counters = ReportCounters::default();
+    let mut period_start = now();
+    let mut next = schedule(&report_config, period_start);
+    loop {
+        if !shared_state.run.load(Ordering::Relaxed) {
+            break;
+        }
+        //A report only covers the time the reporter ran
+        if !shared_state.subsystems.is_running(Subsystem::EmailReport) {
+            if !shared_state
+                .subsystems
+                .wait_until_running(Subsystem::EmailReport, &shared_state.run)
+                .await
+            {
+                break;
+            }
+            events = events.resubscribe();
+            counters.reset();
+            period_start = now();
+            next = schedule(&report_config, period_start);
+        }
+        loop {
+            match events.try_recv() {
+                Ok(event) => counters.record(&event),
+                Err(TryRecvError::Lagged(skipped)) => {
+                    debug!("Email reporter skipped {skipped} events");
+                }
+                Err(_) => break,
+            }
+        }
+        let timestamp = now();
+        if timestamp >= next {
+            let plots = shared_state.harvester_stats.farm().await.plots;
+            let summary = Summary {
+                schedule: report_config.schedule,
+                period_start,
+                period_end: timestamp,
+                network: shared_state.config.selected_network.clone(),
+                version: version(),
+                plots: plots.count,
+                space: plots.space,
+                availability: shared_state.availability.lock().await.report(timestamp),
+            };
+            let message = compose_message(
+                &report_config,
+                &templates,
+                &summary.variables(&counters),
+                timestamp,
+            );
+            match send_mail(&report_config, &message).await {
+                Ok(()) => debug!("Sent email report to {}", report_config.to.join(", ")),
+                Err(e) => warn!(
+                    "Failed to send email report through {}: {:?}",
+                    report_config.smtp_host, e
+                ),
+            }
+            counters.reset();
+            period_start = timestamp;
+            next = schedule(&report_config, timestamp);
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+}
+
+fn schedule(report_config: &EmailReportConfig, from: u64) -> u64 {
+    let next = next_report(
+        from,
+        report_config.schedule,
+        report_config.hour,
+        utc_offset(from),
+    );
+    info!("Next email report at {}", format_timestamp(next));
+    next
+}
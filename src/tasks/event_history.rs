@@ -0,0 +1,33 @@
+use crate::farmer::launcher_stats::record_launcher_event;
+use crate::farmer::FarmerSharedState;
+use log::debug;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::TryRecvError;
+
+//Copies the event stream into the history queried by the control socket and the gui, and the per launcher stats
+pub async fn event_recorder(shared_state: Arc<FarmerSharedState>) {
+    let capacity = shared_state.config.event_history_size;
+    let mut events = shared_state.events.subscribe();
+    loop {
+        if !shared_state.run.load(Ordering::Relaxed) {
+            break;
+        }
+        loop {
+            match events.try_recv() {
+                Ok(event) => {
+                    record_launcher_event(&mut *shared_state.launcher_stats.lock().await, &event);
+                    if capacity > 0 {
+                        shared_state.event_history.record(event, capacity).await;
+                    }
+                }
+                Err(TryRecvError::Lagged(skipped)) => {
+                    debug!("Event history skipped {skipped} events");
+                }
+                Err(_) => break,
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+}
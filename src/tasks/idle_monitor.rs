@@ -0,0 +1,37 @@
+use crate::farmer::events::FarmerEventKind;
+use crate::farmer::FarmerSharedState;
+use log::info;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+//Drops the signage point and proof caches while the full node is gone, the harvester releases its plots on its own
+pub async fn idle_monitor(shared_state: Arc<FarmerSharedState>) {
+    let mut idle = false;
+    loop {
+        if !shared_state.run.load(Ordering::Relaxed) {
+            break;
+        }
+        match shared_state.idle.idle_for(Instant::now()) {
+            Some(idle_for) if !idle => {
+                idle = true;
+                info!(
+                    "No signage point for {} seconds, releasing caches until signage points resume",
+                    idle_for.as_secs()
+                );
+                shared_state.release_caches().await;
+                shared_state.emit(FarmerEventKind::FarmIdle {
+                    seconds: idle_for.as_secs(),
+                });
+            }
+            None if idle => {
+                idle = false;
+                info!("Signage points resumed, reloading plots");
+                shared_state.emit(FarmerEventKind::FarmAwake {});
+            }
+            _ => {}
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    info!("Idle Monitor Stopped");
+}
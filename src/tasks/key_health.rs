@@ -0,0 +1,127 @@
+use crate::farmer::events::FarmerEventKind;
+use crate::farmer::key_health::{
+    check_auth, check_owner, KeyCheck, KeyHealth, KeyHealthChange, KeyProblem,
+};
+use crate::farmer::pool_auth::PoolAuth;
+use crate::farmer::pool_client::build_pool_client;
+use crate::farmer::rpc::FullnodeRpc;
+use crate::farmer::FarmerSharedState;
+use crate::tasks::pool_state_updater::get_farmer;
+use dg_xch_cli::wallets::plotnft_utils::get_pool_state;
+use dg_xch_core::blockchain::sized_bytes::Bytes32;
+use log::{debug, info, warn};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+//Verifies that each owner key still controls its plot NFT on chain, wallet operations can transfer
+//it, and that the pools still accept the authentication keys. Problems are sent as events once
+//instead of surfacing later as rejected partials
+pub async fn key_health_monitor(shared_state: Arc<FarmerSharedState>) {
+    let interval = shared_state.config.key_health.interval;
+    //Watch only farms have no owner or authentication keys to check
+    if interval == 0 || shared_state.readiness.watch_only {
+        return;
+    }
+    let full_node_rpc = FullnodeRpc::reader(
+        shared_state.config.as_ref(),
+        shared_state.rpc_health.clone(),
+    )
+    .await;
+    let pool_client = Arc::new(build_pool_client(shared_state.config.as_ref()));
+    let interval = Duration::from_secs(interval);
+    let mut health = KeyHealth::default();
+    let mut last_check: Option<Instant> = None;
+    while shared_state.run.load(Ordering::Relaxed) {
+        if last_check.is_none_or(|last| last.elapsed() >= interval) {
+            last_check = Some(Instant::now());
+            for farm in shared_state.config.all_farms() {
+                for pool_config in &farm.pool_info {
+                    let launcher_id = pool_config.launcher_id;
+                    match full_node_rpc
+                        .call("get_pool_state", |c| get_pool_state(c, &launcher_id))
+                        .await
+                    {
+                        Ok(singleton) => {
+                            let problem = check_owner(
+                                pool_config,
+                                singleton.owner_pubkey,
+                                &shared_state.owner_secret_keys,
+                            );
+                            report(
+                                &shared_state,
+                                &mut health,
+                                launcher_id,
+                                KeyCheck::Owner,
+                                problem,
+                            );
+                        }
+                        Err(e) => {
+                            debug!("Failed to load singleton state of {launcher_id}: {:?}", e)
+                        }
+                    }
+                    //Solo launchers and pools that were not reached yet have nothing to authenticate with
+                    let token_timeout = shared_state
+                        .pool_states
+                        .lock()
+                        .await
+                        .get(&pool_config.p2_singleton_puzzle_hash)
+                        .filter(|s| !s.is_solo())
+                        .and_then(|s| s.authentication_token_timeout);
+                    let (Some(token_timeout), Some(auth_sk)) = (
+                        token_timeout,
+                        shared_state
+                            .auth_secret_keys
+                            .get(&pool_config.owner_public_key),
+                    ) else {
+                        continue;
+                    };
+                    let response = get_farmer(
+                        pool_config,
+                        token_timeout,
+                        auth_sk,
+                        &mut PoolAuth::default(),
+                        pool_client.clone(),
+                    )
+                    .await;
+                    match check_auth(auth_sk, response) {
+                        Ok(problem) => report(
+                            &shared_state,
+                            &mut health,
+                            launcher_id,
+                            KeyCheck::Auth,
+                            problem,
+                        ),
+                        Err(e) => debug!(
+                            "Failed to check the authentication key of {launcher_id} with {}: {:?}",
+                            pool_config.pool_url, e
+                        ),
+                    }
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+fn report(
+    shared_state: &FarmerSharedState,
+    health: &mut KeyHealth,
+    launcher_id: Bytes32,
+    check: KeyCheck,
+    problem: Option<KeyProblem>,
+) {
+    match health.update(launcher_id, check, problem) {
+        KeyHealthChange::Unchanged => {}
+        KeyHealthChange::Failed(problem) => {
+            warn!("Key check of {launcher_id} failed, {problem}");
+            shared_state.emit(FarmerEventKind::KeyHealthFailed {
+                launcher_id,
+                problem: problem.to_string(),
+            });
+        }
+        KeyHealthChange::Recovered(problem) => {
+            info!("Key check of {launcher_id} passes again, previously {problem}")
+        }
+    }
+}
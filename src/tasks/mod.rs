@@ -1 +1,18 @@
+pub mod clock_drift;
+pub mod drive_health;
+pub mod email_report;
+pub mod event_history;
+pub mod idle_monitor;
+pub mod key_health;
+pub mod network_stats;
+pub mod partial_batch;
+pub mod partial_queue;
+pub mod plugins;
 pub mod pool_state_updater;
+pub mod sp_digest;
+pub mod sp_relay;
+pub mod standby_monitor;
+pub mod stats_report;
+pub mod status_summary;
+pub mod webhooks;
+pub mod xch_price;
@@ -0,0 +1,48 @@
+use crate::farmer::network_stats::NetworkStats;
+use crate::farmer::partial_queue::now;
+use crate::farmer::rpc::FullnodeRpc;
+use crate::farmer::FarmerSharedState;
+use log::debug;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const NETWORK_STATS_INTERVAL: Duration = Duration::from_secs(60);
+
+//Netspace and difficulty from the full node with the expected time to win of the farm
+pub async fn network_stats_updater(shared_state: Arc<FarmerSharedState>) {
+    let full_node_rpc = FullnodeRpc::reader(
+        shared_state.config.as_ref(),
+        shared_state.rpc_health.clone(),
+    )
+    .await;
+    let mut last_update: Option<Instant> = None;
+    while shared_state.run.load(Ordering::Relaxed) {
+        if last_update.is_none_or(|last| last.elapsed() >= NETWORK_STATS_INTERVAL) {
+            last_update = Some(Instant::now());
+            match full_node_rpc.get_blockchain_state().await {
+                Ok(state) => {
+                    if let Some(peak) = &state.peak {
+                        shared_state
+                            .chain_height
+                            .store(peak.height, Ordering::Relaxed);
+                    }
+                    let effective_space = shared_state
+                        .harvester_stats
+                        .farm()
+                        .await
+                        .plots
+                        .effective_space;
+                    shared_state.gui_stats.lock().await.network = Some(NetworkStats::new(
+                        &state,
+                        effective_space,
+                        shared_state.constants,
+                        now(),
+                    ));
+                }
+                Err(e) => debug!("Failed to load blockchain state for network stats: {:?}", e),
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
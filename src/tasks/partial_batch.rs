@@ -0,0 +1,73 @@
+use crate::farmer::partial_batch::{BatchError, BatchedPartial, PartialBatcher};
+use crate::farmer::pool_client::{build_pool_client, PoolClients};
+use crate::farmer::FarmerSharedState;
+use log::{debug, info, warn};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub async fn partial_batch_sender(shared_state: Arc<FarmerSharedState>) {
+    let Some(batch_config) = shared_state.config.partial_batch.clone() else {
+        return;
+    };
+    let pool_client = Arc::new(build_pool_client(shared_state.config.as_ref()));
+    let pools = pool_client.batch_pools();
+    if pools.is_empty() {
+        warn!("partial_batch is set but no pool has a partials endpoint, partials are sent one by one");
+    }
+    *shared_state.partial_batcher.lock().await = PartialBatcher::new(Some(batch_config), pools);
+    while shared_state.run.load(Ordering::Relaxed) {
+        let due = shared_state
+            .partial_batcher
+            .lock()
+            .await
+            .take_due(Instant::now());
+        for (pool_url, batch) in due {
+            let shared_state = shared_state.clone();
+            let pool_client = pool_client.clone();
+            tokio::spawn(
+                async move { send_batch(&shared_state, &pool_client, &pool_url, batch).await },
+            );
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    //Handlers of partials still waiting post them on their own
+    *shared_state.partial_batcher.lock().await = PartialBatcher::default();
+    info!("Partial Batching Stopped");
+}
+
+//Partials of a failed batch are dropped, which makes each handler post its partial on its own
+async fn send_batch(
+    shared_state: &FarmerSharedState,
+    pool_client: &PoolClients,
+    pool_url: &str,
+    batch: Vec<BatchedPartial>,
+) {
+    //A single partial gains nothing from the batch endpoint
+    if batch.len() < 2 {
+        return;
+    }
+    let partials: Vec<_> = batch.iter().map(|p| p.partial.clone()).collect();
+    match pool_client.post_partials(pool_url, &partials).await {
+        Ok(results) => {
+            debug!("Sent {} partials to {pool_url} in one request", batch.len());
+            for (partial, result) in batch.into_iter().zip(results) {
+                partial.respond(result);
+            }
+        }
+        Err(BatchError::Unsupported) => {
+            info!("Pool {pool_url} does not accept partial batches, sending partials one by one");
+            shared_state
+                .partial_batcher
+                .lock()
+                .await
+                .mark_unsupported(pool_url);
+        }
+        Err(BatchError::Failed(e)) => {
+            warn!(
+                "Failed to send {} partials to {pool_url} in one request, sending them one by one: {e}",
+                batch.len()
+            );
+        }
+    }
+}
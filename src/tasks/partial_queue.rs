@@ -0,0 +1,156 @@
+use crate::farmer::events::FarmerEventKind;
+use crate::farmer::partial_deadline::PartialDeadline;
+use crate::farmer::partial_metadata::partial_headers;
+use crate::farmer::partial_queue::{is_retryable, now, PartialQueue};
+use crate::farmer::pool_client::build_pool_client;
+use crate::farmer::FarmerSharedState;
+use crate::get_partial_queue_path;
+use dg_xch_clients::api::pool::PoolClient;
+use log::{info, warn};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const RETRY_INTERVAL: u64 = 10;
+
+pub async fn partial_queue_flusher(shared_state: Arc<FarmerSharedState>) {
+    let Some(queue_config) = shared_state.config.partial_queue.clone() else {
+        return;
+    };
+    let pool_client = build_pool_client(shared_state.config.as_ref());
+    *shared_state.partial_queue.lock().await =
+        PartialQueue::load(&get_partial_queue_path(), &queue_config);
+    let mut last_retry = Instant::now();
+    loop {
+        if !shared_state.run.load(Ordering::Relaxed) {
+            break;
+        } else if last_retry.elapsed().as_secs() >= RETRY_INTERVAL
+            && !shared_state.is_paused()
+            && !shared_state.is_standby()
+        {
+            flush(&shared_state, &pool_client).await;
+            shared_state.gui_stats.lock().await.queued_partials =
+                shared_state.partial_queue.lock().await.len();
+            last_retry = Instant::now();
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    info!("Partial Queue Stopped");
+}
+
+async fn flush<T: PoolClient + Sized + Sync + Send>(
+    shared_state: &FarmerSharedState,
+    pool_client: &T,
+) {
+    let mut pending = shared_state.partial_queue.lock().await.take_valid();
+    if pending.is_empty() {
+        return;
+    }
+    info!("Retrying {} queued partials", pending.len());
+    let deadline = PartialDeadline::new(&shared_state.config);
+    while !pending.is_empty() {
+        let partial = pending.remove(0);
+        let sp_age = partial
+            .sp_time
+            .map(|t| Duration::from_secs(now().saturating_sub(t)));
+        if deadline.is_late(sp_age) {
+            shared_state.emit(FarmerEventKind::PartialStaleLocal {
+                launcher_id: partial.request.payload.launcher_id,
+                pool_url: partial.pool_url,
+                sp_age_ms: sp_age.unwrap_or_default().as_millis() as u64,
+                sp: shared_state.sp_tag(&partial.request.payload.sp_hash).await,
+            });
+            continue;
+        }
+        let farm = shared_state
+            .farms
+            .farm_for_p2_singleton(&partial.p2_singleton_puzzle_hash);
+        let result = pool_client
+            .post_partial(
+                &partial.pool_url,
+                partial.request.clone(),
+                &Some(partial_headers(&partial.metadata)),
+            )
+            .await;
+        //Queued partials are submitted once the pool answers
+        if !matches!(&result, Err(e) if is_retryable(e)) {
+            shared_state
+                .record_farm_stat(farm, |s| s.partials_submitted += 1)
+                .await;
+            shared_state.emit(FarmerEventKind::PartialSubmitted {
+                launcher_id: partial.request.payload.launcher_id,
+                pool_url: partial.pool_url.clone(),
+                sp: shared_state.sp_tag(&partial.request.payload.sp_hash).await,
+            });
+        }
+        match result {
+            Ok(resp) => {
+                if let Some(pool_state) = shared_state
+                    .pool_states
+                    .lock()
+                    .await
+                    .get_mut(&partial.p2_singleton_puzzle_hash)
+                {
+                    //Pools credit the difficulty the partial was sent at
+                    let points = partial
+                        .difficulty
+                        .or(pool_state.current_difficulty)
+                        .unwrap_or(resp.new_difficulty);
+                    pool_state.points_history.record_local(points);
+                    pool_state.current_points += points;
+                    pool_state.current_difficulty = Some(resp.new_difficulty);
+                }
+                info!(
+                    "Queued partial for {} accepted by {}",
+                    partial.request.payload.launcher_id, &partial.pool_url
+                );
+                shared_state
+                    .record_farm_stat(farm, |s| s.partials_accepted += 1)
+                    .await;
+                shared_state.emit(FarmerEventKind::PartialAccepted {
+                    launcher_id: partial.request.payload.launcher_id,
+                    pool_url: partial.pool_url,
+                    new_difficulty: resp.new_difficulty,
+                    sp: shared_state.sp_tag(&partial.request.payload.sp_hash).await,
+                });
+            }
+            Err(e) if is_retryable(&e) => {
+                warn!(
+                    "Pool {} still unreachable, keeping {} queued partials: {:?}",
+                    &partial.pool_url,
+                    pending.len() + 1,
+                    e
+                );
+                pending.insert(0, partial);
+                shared_state.partial_queue.lock().await.requeue(pending);
+                return;
+            }
+            Err(e) => {
+                warn!(
+                    "Queued partial for {} rejected by {}: {:?}",
+                    partial.request.payload.launcher_id, &partial.pool_url, e
+                );
+                if let Some(pool_state) = shared_state
+                    .pool_states
+                    .lock()
+                    .await
+                    .get_mut(&partial.p2_singleton_puzzle_hash)
+                {
+                    if let Some(pool_config) = &pool_state.pool_config {
+                        pool_state.auth.record_error(pool_config, &e);
+                    }
+                }
+                shared_state
+                    .record_farm_stat(farm, |s| s.partials_rejected += 1)
+                    .await;
+                shared_state.emit(FarmerEventKind::PartialRejected {
+                    launcher_id: partial.request.payload.launcher_id,
+                    pool_url: partial.pool_url,
+                    error_code: e.error_code,
+                    error_message: e.error_message,
+                    sp: shared_state.sp_tag(&partial.request.payload.sp_hash).await,
+                });
+            }
+        }
+    }
+}
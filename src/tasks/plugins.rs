@@ -0,0 +1,52 @@
+use crate::farmer::FarmerSharedState;
+use crate::plugins::{load_plugins, Plugin};
+use futures_util::future::join_all;
+use log::{debug, info};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{interval, sleep, MissedTickBehavior};
+
+pub const PLUGIN_TICK: Duration = Duration::from_secs(10);
+
+pub async fn plugin_runner(shared_state: Arc<FarmerSharedState>) {
+    let plugins = load_plugins(shared_state.config.as_ref());
+    if plugins.is_empty() {
+        return;
+    }
+    info!(
+        "Running plugins: {}",
+        plugins
+            .iter()
+            .map(|p| p.name())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    join_all(
+        plugins
+            .into_iter()
+            .map(|plugin| tokio::spawn(run_plugin(shared_state.clone(), plugin))),
+    )
+    .await;
+}
+
+async fn run_plugin(shared_state: Arc<FarmerSharedState>, plugin: Arc<dyn Plugin>) {
+    let mut events = shared_state.events.subscribe();
+    let mut tick = interval(PLUGIN_TICK);
+    tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    while shared_state.run.load(Ordering::Relaxed) {
+        select! {
+            event = events.recv() => match event {
+                Ok(event) => plugin.on_event(&shared_state, &event).await,
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!("Plugin {} skipped {skipped} events", plugin.name());
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = tick.tick() => plugin.on_tick(&shared_state).await,
+            _ = sleep(Duration::from_secs(1)) => {}
+        }
+    }
+}
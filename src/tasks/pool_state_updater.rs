@@ -1,20 +1,29 @@
-use crate::farmer::config::{Config, PoolWalletConfig};
+use crate::farmer::config::{Config, PayoutMismatchPolicy, PoolWalletConfig};
+use crate::farmer::earnings::parse_pool_fee;
+use crate::farmer::events::FarmerEventKind;
+use crate::farmer::partial_metadata::sends_partial_metadata;
+use crate::farmer::payout_check::{check_payout, parse_payout_address, PayoutMismatch};
+use crate::farmer::pool_auth::PoolAuth;
+use crate::farmer::pool_client::build_pool_client;
+use crate::farmer::pool_difficulty::PoolDifficultyStore;
+use crate::farmer::pool_info_cache::PoolInfoCache;
+use crate::farmer::pool_points::PoolPointsHistory;
+use crate::farmer::rpc::FullnodeRpc;
 use crate::farmer::FarmerSharedState;
-use crate::HEADERS;
+use crate::{get_pool_difficulty_path, get_pool_info_cache_path, HEADERS};
 use blst::min_pk::SecretKey;
-use dg_xch_clients::api::pool::{DefaultPoolClient, PoolClient};
+use dg_xch_cli::wallets::plotnft_utils::get_pool_state;
+use dg_xch_clients::api::pool::PoolClient;
 use dg_xch_clients::protocols::pool::{
-    get_current_authentication_token, AuthenticationPayload, GetFarmerRequest, GetFarmerResponse,
+    get_current_authentication_token, GetFarmerRequest, GetFarmerResponse, GetPoolInfoResponse,
     PoolError, PoolErrorCode, PostFarmerPayload, PostFarmerRequest, PostFarmerResponse,
-    PutFarmerPayload, PutFarmerRequest, PutFarmerResponse,
+    PutFarmerPayload, PutFarmerRequest, PutFarmerResponse, SELF_POOLING,
 };
-use dg_xch_core::blockchain::sized_bytes::{hex_to_bytes, Bytes32, Bytes48};
+use dg_xch_core::blockchain::sized_bytes::{Bytes32, Bytes48};
 use dg_xch_core::clvm::bls_bindings::{sign, verify_signature};
-use dg_xch_keys::decode_puzzle_hash;
 use dg_xch_serialize::{hash_256, ChiaSerialize};
 use log::{debug, error, info, warn};
 use std::collections::HashMap;
-use std::io::Error;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime};
@@ -23,6 +32,7 @@ use tokio::sync::Mutex;
 const UPDATE_POOL_INFO_INTERVAL: u64 = 600;
 const UPDATE_POOL_INFO_FAILURE_RETRY_INTERVAL: u64 = 120;
 const UPDATE_POOL_FARMER_INFO_INTERVAL: u64 = 300;
+const UPDATE_SINGLETON_STATE_INTERVAL: u64 = 3600;
 
 #[derive(Debug, Clone)]
 pub struct FarmerPoolState {
@@ -32,6 +42,29 @@ pub struct FarmerPoolState {
     pub(crate) current_difficulty: Option<u64>,
     pub(crate) pool_config: Option<PoolWalletConfig>,
     pub(crate) authentication_token_timeout: Option<u8>,
+    pub(crate) suppressed_partials: u64,
+    pub(crate) extended_partials: bool,
+    //The plot NFT is self pooling, its pool_url is stale and no partials are sent
+    pub(crate) solo: bool,
+    pub(crate) points_history: PoolPointsHistory,
+    pub(crate) auth: PoolAuth,
+    pub(crate) pool_fee: Option<f64>,
+    //Set from the last farmer update while the pool pays elsewhere
+    pub(crate) payout_mismatch: Option<PayoutMismatch>,
+}
+impl FarmerPoolState {
+    pub fn is_solo(&self) -> bool {
+        self.solo
+            || self
+                .pool_config
+                .as_ref()
+                .is_some_and(|c| c.pool_url.is_empty())
+    }
+    pub fn partials_halted(&self) -> bool {
+        self.payout_mismatch
+            .as_ref()
+            .is_some_and(|m| m.halts_partials())
+    }
 }
 impl Default for FarmerPoolState {
     fn default() -> Self {
@@ -42,6 +75,13 @@ impl Default for FarmerPoolState {
             current_difficulty: None,
             pool_config: None,
             authentication_token_timeout: None,
+            suppressed_partials: 0,
+            extended_partials: false,
+            solo: false,
+            points_history: PoolPointsHistory::default(),
+            auth: PoolAuth::default(),
+            pool_fee: None,
+            payout_mismatch: None,
         }
     }
 }
@@ -49,13 +89,35 @@ impl Default for FarmerPoolState {
 pub async fn pool_updater(shared_state: Arc<FarmerSharedState>) {
     let mut last_update = Instant::now();
     let mut first = true;
-    let pool_client = Arc::new(DefaultPoolClient::new());
+    let pool_client = Arc::new(build_pool_client(shared_state.config.as_ref()));
+    let mut pool_info_cache = PoolInfoCache::load(&get_pool_info_cache_path());
+    let mut difficulties = PoolDifficultyStore::load(&get_pool_difficulty_path());
+    let full_node_rpc = FullnodeRpc::reader(
+        shared_state.config.as_ref(),
+        shared_state.rpc_health.clone(),
+    )
+    .await;
+    let mut last_singleton_update = None;
     loop {
-        if !shared_state.run.load(Ordering::Relaxed) {
+        if !shared_state.is_active() {
             break;
-        } else if first
-            || shared_state.force_pool_update.load(Ordering::Relaxed)
-            || Instant::now().duration_since(last_update).as_secs() >= 60
+        }
+        if last_singleton_update
+            .is_none_or(|last: Instant| last.elapsed().as_secs() >= UPDATE_SINGLETON_STATE_INTERVAL)
+        {
+            update_solo_launchers(
+                &full_node_rpc,
+                shared_state.pool_states.as_ref(),
+                shared_state.config.as_ref(),
+            )
+            .await;
+            last_singleton_update = Some(Instant::now());
+        }
+        //Watch only farms have no owner keys to authenticate with the pools
+        if !shared_state.readiness.watch_only
+            && (first
+                || shared_state.force_pool_update.load(Ordering::Relaxed)
+                || Instant::now().duration_since(last_update).as_secs() >= 60)
         {
             info!("Updating Pool State");
             update_pool_state(
@@ -63,14 +125,19 @@ pub async fn pool_updater(shared_state: Arc<FarmerSharedState>) {
                 shared_state.owner_secret_keys.as_ref(),
                 shared_state.pool_states.clone(),
                 pool_client.clone(),
+                &mut pool_info_cache,
+                &difficulties,
                 shared_state.config.clone(),
             )
             .await;
+            store_difficulties(&shared_state, &mut difficulties).await;
+            report_points_discrepancies(&shared_state).await;
+            report_payout_mismatches(&shared_state).await;
             first = false;
             last_update = Instant::now();
             shared_state.gui_stats.lock().await.last_pool_update = SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
-                .expect("System Time should be Greater than Epoch")
+                .unwrap_or_default()
                 .as_secs();
             shared_state
                 .force_pool_update
@@ -78,39 +145,138 @@ pub async fn pool_updater(shared_state: Arc<FarmerSharedState>) {
         }
         tokio::time::sleep(Duration::from_millis(25)).await;
     }
+    //Partials accepted since the last round can have raised the difficulty
+    store_difficulties(&shared_state, &mut difficulties).await;
     info!("Pool Handle Stopped");
 }
 
+async fn report_points_discrepancies(shared_state: &FarmerSharedState) {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut discrepancies = vec![];
+    for pool_state in shared_state.pool_states.lock().await.values_mut() {
+        let Some(pool_config) = &pool_state.pool_config else {
+            continue;
+        };
+        pool_state
+            .points_history
+            .reconcile(now, &shared_state.config.points_reconciliation);
+        for discrepancy in pool_state.points_history.unreported.drain(..) {
+            discrepancies.push((
+                pool_config.launcher_id,
+                pool_config.pool_url.clone(),
+                discrepancy,
+            ));
+        }
+    }
+    for (launcher_id, pool_url, discrepancy) in discrepancies {
+        warn!(
+            "Pool {pool_url} credited {} of {} locally accepted points for {launcher_id} over {} windows",
+            discrepancy.pool_points, discrepancy.local_points, discrepancy.windows
+        );
+        shared_state.emit(FarmerEventKind::PoolPointsDiscrepancy {
+            launcher_id,
+            pool_url,
+            local_points: discrepancy.local_points,
+            pool_points: discrepancy.pool_points,
+            windows: discrepancy.windows,
+        });
+    }
+}
+
+async fn report_payout_mismatches(shared_state: &FarmerSharedState) {
+    let mut mismatches = vec![];
+    for pool_state in shared_state.pool_states.lock().await.values_mut() {
+        if let (Some(pool_config), Some(mismatch)) =
+            (&pool_state.pool_config, &mut pool_state.payout_mismatch)
+        {
+            if !mismatch.reported {
+                mismatch.reported = true;
+                mismatches.push((
+                    pool_config.launcher_id,
+                    pool_config.pool_url.clone(),
+                    mismatch.clone(),
+                ));
+            }
+        }
+    }
+    for (launcher_id, pool_url, mismatch) in mismatches {
+        warn!(
+            "Pool {pool_url} pays {launcher_id} to {} instead of the configured {}, policy {:?}",
+            mismatch.pool, mismatch.configured, mismatch.policy
+        );
+        shared_state.emit(FarmerEventKind::PayoutMismatch {
+            launcher_id,
+            pool_url,
+            configured: mismatch.configured,
+            pool: mismatch.pool,
+            policy: mismatch.policy,
+        });
+    }
+}
+
+//Launchers whose plot NFT left its pool are farmed solo until the singleton joins a pool again
+async fn update_solo_launchers(
+    full_node_rpc: &FullnodeRpc,
+    pool_states: &Mutex<HashMap<Bytes32, FarmerPoolState>>,
+    config: &Config,
+) {
+    for farm in config.all_farms() {
+        for pool_config in farm.pool_info.iter().filter(|p| !p.pool_url.is_empty()) {
+            let launcher_id = pool_config.launcher_id;
+            let singleton_state = match full_node_rpc
+                .call("get_pool_state", |c| get_pool_state(c, &launcher_id))
+                .await
+            {
+                Ok(pool_state) => pool_state.state,
+                Err(e) => {
+                    debug!("Failed to load singleton state of {launcher_id}: {:?}", e);
+                    continue;
+                }
+            };
+            let solo = singleton_state == SELF_POOLING;
+            let mut pool_states = pool_states.lock().await;
+            let pool_state = pool_states
+                .entry(pool_config.p2_singleton_puzzle_hash)
+                .or_default();
+            if pool_state.solo != solo {
+                if solo {
+                    info!(
+                        "Launcher {launcher_id} is self pooling, farming solo instead of with {}",
+                        pool_config.pool_url
+                    );
+                } else {
+                    info!(
+                        "Launcher {launcher_id} is no longer self pooling, sending partials to {}",
+                        pool_config.pool_url
+                    );
+                }
+                pool_state.solo = solo;
+            }
+        }
+    }
+}
+
 pub async fn get_farmer<T: PoolClient + Sized + Sync + Send>(
     pool_config: &PoolWalletConfig,
     authentication_token_timeout: u8,
     authentication_sk: &SecretKey,
+    auth: &mut PoolAuth,
     client: Arc<T>,
 ) -> Result<GetFarmerResponse, PoolError> {
     let authentication_token = get_current_authentication_token(authentication_token_timeout);
-    let msg = AuthenticationPayload {
-        method_name: "get_farmer".to_string(),
-        launcher_id: pool_config.launcher_id,
-        target_puzzle_hash: pool_config.target_puzzle_hash,
-        authentication_token,
-    }
-    .to_bytes();
-    let to_sign = hash_256(&msg);
-    let signature = sign(authentication_sk, &to_sign);
-    if !verify_signature(&authentication_sk.sk_to_pk(), &to_sign, &signature) {
-        error!("Farmer GET Failed to Validate Signature");
-        return Err(PoolError {
-            error_code: PoolErrorCode::InvalidSignature as u8,
-            error_message: "Local Failed to Validate Signature".to_string(),
-        });
-    }
+    let signature = auth
+        .get_farmer_signature(pool_config, authentication_token, authentication_sk)
+        .inspect_err(|_| error!("Farmer GET Failed to Validate Signature"))?;
     client
         .get_farmer(
             &pool_config.pool_url,
             GetFarmerRequest {
                 launcher_id: pool_config.launcher_id,
                 authentication_token,
-                signature: signature.to_bytes().into(),
+                signature,
             },
             &Some(HEADERS.clone()),
         )
@@ -228,11 +394,23 @@ pub async fn update_pool_farmer_info<T: PoolClient + Sized + Sync + Send>(
         pool_config,
         authentication_token_timeout,
         authentication_sk,
+        &mut pool_state.auth,
         client,
     )
-    .await?;
+    .await
+    .inspect_err(|e| {
+        pool_state.auth.record_error(pool_config, e);
+    })?;
     pool_state.current_difficulty = Some(response.current_difficulty);
     pool_state.current_points = response.current_points;
+    pool_state.points_history.record_pool(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        response.current_points,
+        response.current_difficulty,
+    );
     info!(
         "Updating Pool Difficulty: {:?} ",
         pool_state.current_difficulty
@@ -246,9 +424,21 @@ pub async fn update_pool_state<'a, T: 'a + PoolClient + Sized + Sync + Send>(
     owner_keys: &HashMap<Bytes48, SecretKey>,
     pool_states: Arc<Mutex<HashMap<Bytes32, FarmerPoolState>>>,
     client: Arc<T>,
+    pool_info_cache: &mut PoolInfoCache,
+    difficulties: &PoolDifficultyStore,
     config: Arc<Config>,
 ) {
-    for pool_config in &config.pool_info {
+    let pools = config
+        .all_farms()
+        .into_iter()
+        .flat_map(|farm| {
+            farm.pool_info
+                .clone()
+                .into_iter()
+                .map(move |pool_config| (farm.clone(), pool_config))
+        })
+        .collect::<Vec<_>>();
+    for (farm, pool_config) in &pools {
         if let (Some(owner_secret_key), Some(auth_secret_key)) = (
             owner_keys.get(&pool_config.owner_public_key),
             auth_keys.get(&pool_config.owner_public_key),
@@ -259,15 +449,30 @@ pub async fn update_pool_state<'a, T: 'a + PoolClient + Sized + Sync + Send>(
                 .get(&pool_config.p2_singleton_puzzle_hash)
                 .is_some();
             if !state_exists {
+                let stored_difficulty =
+                    difficulties.get(&pool_config.launcher_id, &pool_config.pool_url);
+                if let Some(difficulty) = stored_difficulty {
+                    info!(
+                        "Resuming {} at difficulty {difficulty} from the last run",
+                        pool_config.launcher_id
+                    );
+                }
                 pool_states.lock().await.insert(
                     pool_config.p2_singleton_puzzle_hash,
                     FarmerPoolState {
                         next_farmer_update: Instant::now(),
                         next_pool_info_update: Instant::now(),
                         current_points: 0,
-                        current_difficulty: None,
+                        current_difficulty: stored_difficulty,
                         pool_config: None,
                         authentication_token_timeout: None,
+                        suppressed_partials: 0,
+                        extended_partials: false,
+                        solo: false,
+                        points_history: PoolPointsHistory::default(),
+                        auth: PoolAuth::default(),
+                        pool_fee: None,
+                        payout_mismatch: None,
                     },
                 );
                 info!("Added pool: {:?}", pool_config);
@@ -279,7 +484,12 @@ pub async fn update_pool_state<'a, T: 'a + PoolClient + Sized + Sync + Send>(
                 .cloned()
                 .unwrap_or_default();
             pool_state.pool_config = Some(pool_config.clone());
-            if pool_config.pool_url.is_empty() {
+            if pool_state.is_solo() {
+                //Kept so proofs of solo launchers are recognized, there is no pool to contact
+                pool_states
+                    .lock()
+                    .await
+                    .insert(pool_config.p2_singleton_puzzle_hash, pool_state);
                 continue;
             }
             if config.selected_network == "mainnet" && !pool_config.pool_url.starts_with("https") {
@@ -295,17 +505,30 @@ pub async fn update_pool_state<'a, T: 'a + PoolClient + Sized + Sync + Send>(
                 //Makes a GET request to the pool to get the updated information
                 match client.get_pool_info(&pool_config.pool_url).await {
                     Ok(pool_info) => {
-                        pool_state.authentication_token_timeout =
-                            Some(pool_info.authentication_token_timeout);
-                        // Only update the first time from GET /pool_info, gets updated from GET /farmer later
-                        if pool_state.current_difficulty.is_none() {
-                            pool_state.current_difficulty = Some(pool_info.minimum_difficulty);
+                        if sends_partial_metadata(Some(pool_config), &pool_info)
+                            && !pool_state.extended_partials
+                        {
+                            info!(
+                                "Sending extended partials to pool {}, protocol version {}",
+                                pool_config.pool_url, pool_info.protocol_version
+                            );
                         }
+                        apply_pool_info(&mut pool_state, &pool_info);
+                        pool_info_cache.insert(&pool_config.pool_url, pool_info);
                     }
                     Err(e) => {
                         pool_state.next_pool_info_update = Instant::now()
                             + Duration::from_secs(UPDATE_POOL_INFO_FAILURE_RETRY_INTERVAL);
                         error!("Update Pool Info Error: {:?}", e);
+                        if let Some((pool_info, age)) =
+                            pool_info_cache.get(&pool_config.pool_url, config.pool_info_cache_ttl)
+                        {
+                            warn!(
+                                "Running on stale cached pool info for {}, last fetched {}s ago",
+                                pool_config.pool_url, age
+                            );
+                            apply_pool_info(&mut pool_state, pool_info);
+                        }
                     }
                 }
             } else {
@@ -332,7 +555,7 @@ pub async fn update_pool_state<'a, T: 'a + PoolClient + Sized + Sync + Send>(
                                 warn!("Farmer Pool Not Known");
                                 match post_farmer(
                                     pool_config,
-                                    &config.payout_address,
+                                    &farm.payout_address,
                                     authentication_token_timeout,
                                     owner_secret_key,
                                     auth_keys,
@@ -348,6 +571,7 @@ pub async fn update_pool_state<'a, T: 'a + PoolClient + Sized + Sync + Send>(
                                         );
                                     }
                                     Err(e) => {
+                                        pool_state.auth.record_error(pool_config, &e);
                                         error!("Failed post farmer info. {:?}", e);
                                     }
                                 }
@@ -373,7 +597,7 @@ pub async fn update_pool_state<'a, T: 'a + PoolClient + Sized + Sync + Send>(
                                 warn!("Invalid Signature Detected, Updating Farmer Auth Key");
                                 match put_farmer(
                                     pool_config,
-                                    &config.payout_address,
+                                    &farm.payout_address,
                                     authentication_token_timeout,
                                     owner_secret_key,
                                     auth_keys,
@@ -395,6 +619,7 @@ pub async fn update_pool_state<'a, T: 'a + PoolClient + Sized + Sync + Send>(
                                         .ok()
                                     }
                                     Err(e) => {
+                                        pool_state.auth.record_error(pool_config, &e);
                                         error!("Failed to update farmer auth key. {:?}", e);
                                         None
                                     }
@@ -404,30 +629,45 @@ pub async fn update_pool_state<'a, T: 'a + PoolClient + Sized + Sync + Send>(
                             }
                         }
                     };
-                    let old_instructions;
-                    let payout_instructions_update_required = if let Some(info) = farmer_info {
-                        if let (Ok(p1), Ok(p2)) = (
-                            parse_payout_address(config.payout_address.to_ascii_lowercase()),
-                            parse_payout_address(info.payout_instructions.to_ascii_lowercase()),
+                    if let Some(info) = &farmer_info {
+                        match check_payout(
+                            &farm.payout_address,
+                            &info.payout_instructions,
+                            config.payout_mismatch,
                         ) {
-                            old_instructions = p2;
-                            p1 != old_instructions
-                        } else {
-                            old_instructions = String::new();
-                            false
+                            None => {
+                                if pool_state.payout_mismatch.take().is_some() {
+                                    info!(
+                                        "Pool {} pays {} to the configured payout address again",
+                                        pool_config.pool_url, pool_config.launcher_id
+                                    );
+                                }
+                            }
+                            //Kept while unchanged so it is reported once
+                            Some(mismatch) => {
+                                if pool_state
+                                    .payout_mismatch
+                                    .as_ref()
+                                    .is_none_or(|m| m.pool != mismatch.pool)
+                                {
+                                    pool_state.payout_mismatch = Some(mismatch);
+                                }
+                            }
                         }
-                    } else {
-                        old_instructions = String::new();
-                        false
-                    };
+                    }
+                    let payout_instructions_update_required = config.payout_mismatch
+                        == PayoutMismatchPolicy::Correct
+                        && pool_state.payout_mismatch.is_some();
                     let difficulty_update_required = pool_config.difficulty.unwrap_or_default() > 0
                         && pool_state.current_difficulty < pool_config.difficulty;
                     if payout_instructions_update_required || difficulty_update_required {
-                        if payout_instructions_update_required {
+                        if let (true, Some(mismatch)) = (
+                            payout_instructions_update_required,
+                            &pool_state.payout_mismatch,
+                        ) {
                             info!(
                                 "Updating Payout Address from {} to {}",
-                                config.payout_address.to_ascii_lowercase(),
-                                old_instructions
+                                mismatch.pool, mismatch.configured
                             );
                         }
                         if difficulty_update_required {
@@ -446,9 +686,16 @@ pub async fn update_pool_state<'a, T: 'a + PoolClient + Sized + Sync + Send>(
                                 continue;
                             }
                             Some(sk) => {
+                                //A difficulty update must not correct payouts the policy leaves alone
+                                let payout_instructions = match &pool_state.payout_mismatch {
+                                    Some(mismatch) if !payout_instructions_update_required => {
+                                        mismatch.pool.clone()
+                                    }
+                                    _ => farm.payout_address.clone(),
+                                };
                                 match put_farmer(
                                     pool_config,
-                                    &config.payout_address,
+                                    &payout_instructions,
                                     authentication_token_timeout,
                                     sk,
                                     auth_keys,
@@ -488,15 +735,28 @@ pub async fn update_pool_state<'a, T: 'a + PoolClient + Sized + Sync + Send>(
     }
 }
 
-fn parse_payout_address(s: String) -> Result<String, Error> {
-    Ok(if s.starts_with("xch") || s.starts_with("txch") {
-        hex::encode(decode_puzzle_hash(&s)?)
-    } else if s.len() == 64 {
-        match hex_to_bytes(&s) {
-            Ok(h) => hex::encode(h),
-            Err(_) => s,
+async fn store_difficulties(
+    shared_state: &FarmerSharedState,
+    difficulties: &mut PoolDifficultyStore,
+) {
+    for pool_state in shared_state.pool_states.lock().await.values() {
+        if let (Some(pool_config), Some(difficulty)) =
+            (&pool_state.pool_config, pool_state.current_difficulty)
+        {
+            if !pool_state.is_solo() {
+                difficulties.set(&pool_config.launcher_id, &pool_config.pool_url, difficulty);
+            }
         }
-    } else {
-        s
-    })
+    }
+}
+
+fn apply_pool_info(pool_state: &mut FarmerPoolState, pool_info: &GetPoolInfoResponse) {
+    pool_state.authentication_token_timeout = Some(pool_info.authentication_token_timeout);
+    pool_state.extended_partials =
+        sends_partial_metadata(pool_state.pool_config.as_ref(), pool_info);
+    pool_state.pool_fee = parse_pool_fee(&pool_info.fee);
+    // Only update the first time from GET /pool_info, gets updated from GET /farmer later
+    if pool_state.current_difficulty.is_none() {
+        pool_state.current_difficulty = Some(pool_info.minimum_difficulty);
+    }
 }
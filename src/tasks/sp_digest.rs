@@ -0,0 +1,40 @@
+use crate::farmer::partial_queue::now;
+use crate::farmer::sp_timing::SpTiming;
+use crate::farmer::FarmerSharedState;
+use log::{debug, info};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::TryRecvError;
+
+//Signage points settle after this many signage point intervals, long enough for most pool responses
+const SETTLE_INTERVALS: u32 = 3;
+
+//Logs one SpDigest line per signage point for log parsing tools and counts it for the availability
+pub async fn sp_digest_logger(shared_state: Arc<FarmerSharedState>) {
+    let settle = SpTiming::new(shared_state.constants).sp_interval * SETTLE_INTERVALS;
+    let mut events = shared_state.events.subscribe();
+    loop {
+        if !shared_state.run.load(Ordering::Relaxed) {
+            break;
+        }
+        loop {
+            match events.try_recv() {
+                Ok(event) => shared_state.sp_digests.record_event(&event.kind).await,
+                Err(TryRecvError::Lagged(skipped)) => {
+                    debug!("Signage point digests skipped {skipped} events");
+                }
+                Err(_) => break,
+            }
+        }
+        for digest in shared_state.sp_digests.take_settled(settle).await {
+            info!("{digest}");
+            shared_state
+                .availability
+                .lock()
+                .await
+                .record(&digest, now());
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
@@ -0,0 +1,127 @@
+use crate::farmer::config::{SpRelayConfig, SpRelayFormat};
+use crate::farmer::events::FarmerEventKind;
+use crate::farmer::FarmerSharedState;
+use dg_xch_clients::protocols::farmer::NewSignagePoint;
+use dg_xch_serialize::ChiaSerialize;
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, info, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+const RELAY_CHANNEL_SIZE: usize = 64;
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+//Fans signage points out to the configured websocket consumers
+pub async fn sp_relay(shared_state: Arc<FarmerSharedState>) {
+    if shared_state.config.sp_relay.is_empty() {
+        return;
+    }
+    let (sender, _) = broadcast::channel::<NewSignagePoint>(RELAY_CHANNEL_SIZE);
+    for relay in shared_state.config.sp_relay.iter().cloned() {
+        let receiver = sender.subscribe();
+        let run = shared_state.run.clone();
+        tokio::spawn(async move { relay_connection(relay, receiver, run).await });
+    }
+    let mut events = shared_state.events.subscribe();
+    loop {
+        if !shared_state.run.load(Ordering::Relaxed) {
+            break;
+        }
+        loop {
+            match events.try_recv() {
+                Ok(event) => {
+                    if let FarmerEventKind::SignagePoint {
+                        challenge_hash,
+                        sp_hash,
+                        signage_point_index,
+                    } = event.kind
+                    {
+                        let sp = shared_state
+                            .signage_points_for(&sp_hash)
+                            .await
+                            .and_then(|sps| {
+                                sps.into_iter().rev().find(|sp| {
+                                    sp.challenge_hash == challenge_hash
+                                        && sp.signage_point_index == signage_point_index
+                                })
+                            });
+                        if let Some(sp) = sp {
+                            let _ = sender.send(sp);
+                        }
+                    }
+                }
+                Err(TryRecvError::Lagged(skipped)) => {
+                    debug!("Signage point relay skipped {skipped} events");
+                }
+                Err(_) => break,
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+}
+
+fn relay_message(format: SpRelayFormat, sp: &NewSignagePoint) -> Message {
+    match format {
+        SpRelayFormat::Json => Message::Text(serde_json::to_string(sp).unwrap_or_default()),
+        SpRelayFormat::Binary => Message::Binary(sp.to_bytes()),
+    }
+}
+
+//Signage points that arrive while the consumer is disconnected are dropped, they would be stale on reconnect
+async fn relay_connection(
+    relay: SpRelayConfig,
+    mut receiver: broadcast::Receiver<NewSignagePoint>,
+    run: Arc<AtomicBool>,
+) {
+    while run.load(Ordering::Relaxed) {
+        let mut stream = match connect_async(relay.url.as_str()).await {
+            Ok((stream, _)) => {
+                info!("Relaying signage points to {}", relay.url);
+                stream
+            }
+            Err(e) => {
+                debug!(
+                    "Failed to connect signage point relay {}: {:?}",
+                    relay.url, e
+                );
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                continue;
+            }
+        };
+        receiver = receiver.resubscribe();
+        loop {
+            tokio::select! {
+                sp = receiver.recv() => match sp {
+                    Ok(sp) => {
+                        if let Err(e) = stream.send(relay_message(relay.format, &sp)).await {
+                            warn!("Signage point relay {} failed: {:?}", relay.url, e);
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!("Signage point relay {} skipped {skipped} signage points", relay.url);
+                    }
+                    Err(RecvError::Closed) => return,
+                },
+                //Consumers are not expected to send anything, reading keeps pings answered and notices closes
+                msg = stream.next() => match msg {
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => {
+                        warn!("Signage point relay {} failed: {:?}", relay.url, e);
+                        break;
+                    }
+                    None => {
+                        warn!("Signage point relay {} closed the connection", relay.url);
+                        break;
+                    }
+                },
+            }
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
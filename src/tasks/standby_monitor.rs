@@ -0,0 +1,101 @@
+use crate::farmer::instance_lock::{try_lock, InstanceClaim};
+use crate::farmer::partial_queue::now;
+use crate::farmer::standby::{may_farm, Heartbeat};
+use crate::farmer::FarmerSharedState;
+use log::{info, warn};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+//Keeps the heartbeat fresh while farming and switches to standby when another instance holds the lock
+pub async fn standby_monitor(shared_state: Arc<FarmerSharedState>) {
+    let Some(ha) = shared_state.config.high_availability.clone() else {
+        return;
+    };
+    let instance_id = Uuid::new_v4();
+    let lock_file = PathBuf::from(&ha.lock_file);
+    let started = Instant::now();
+    let mut last_check: Option<Instant> = None;
+    info!(
+        "High availability enabled as {} instance {instance_id}, lock file {}",
+        if ha.standby { "standby" } else { "primary" },
+        lock_file.display()
+    );
+    loop {
+        if !shared_state.run.load(Ordering::Relaxed) {
+            break;
+        }
+        if last_check.is_none_or(|c| c.elapsed().as_secs() >= ha.heartbeat_interval) {
+            let waited = !ha.standby || started.elapsed().as_secs() >= ha.takeover_after;
+            let heartbeat = Heartbeat::read(&lock_file).await;
+            let mut active = may_farm(
+                heartbeat.as_ref(),
+                &instance_id,
+                now(),
+                ha.takeover_after,
+                waited,
+            );
+            if active {
+                let heartbeat = Heartbeat {
+                    instance_id,
+                    timestamp: now(),
+                };
+                //Both instances can claim a stale lock at once, only the one whose heartbeat stuck farms
+                active = match heartbeat.write(&lock_file).await {
+                    Ok(()) => Heartbeat::read(&lock_file)
+                        .await
+                        .is_some_and(|h| h.instance_id == instance_id),
+                    Err(e) => {
+                        //Without a heartbeat the other instance takes over, stop before it does
+                        warn!("Failed to write heartbeat to {}: {e}", lock_file.display());
+                        false
+                    }
+                };
+            }
+            shared_state.set_standby(!active);
+            last_check = Some(Instant::now());
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    //Releasing the lock on a clean stop lets the other instance take over without waiting
+    if !shared_state.is_standby()
+        && Heartbeat::read(&lock_file)
+            .await
+            .is_some_and(|h| h.instance_id == instance_id)
+    {
+        if let Err(e) = tokio::fs::remove_file(&lock_file).await {
+            warn!("Failed to release lock file {}: {e}", lock_file.display());
+        }
+    }
+    info!("Standby Monitor Stopped");
+}
+
+//Holds the single instance lock until the farmer stops, a waiting instance takes it over once the
+//other process exits
+pub async fn instance_lock_monitor(shared_state: Arc<FarmerSharedState>, claim: InstanceClaim) {
+    let _lock = match claim {
+        InstanceClaim::Held(lock) => lock,
+        InstanceClaim::Unlocked => return,
+        InstanceClaim::Waiting(path) => loop {
+            if !shared_state.run.load(Ordering::Relaxed) {
+                return;
+            }
+            match try_lock(&path) {
+                Ok(Some(lock)) => {
+                    info!("The other farmer released {}, taking over", path.display());
+                    shared_state.set_standby(false);
+                    break lock;
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to lock {}: {e}", path.display()),
+            }
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        },
+    };
+    while shared_state.run.load(Ordering::Relaxed) {
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+    info!("Instance Lock Monitor Stopped");
+}
@@ -0,0 +1,209 @@
+use crate::farmer::availability::AvailabilityReport;
+use crate::farmer::config::StatsReportConfig;
+use crate::farmer::events::{FarmerEvent, FarmerEventKind};
+use crate::farmer::launcher_stats::{record_launcher_event, LauncherStatsMap};
+use crate::farmer::partial_queue::now;
+use crate::farmer::subsystems::Subsystem;
+use crate::farmer::FarmerSharedState;
+use crate::{get_farm_id_path, version};
+use dg_xch_clients::protocols::pool::PoolErrorCode;
+use log::{debug, info, warn};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Error;
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::TryRecvError;
+use uuid::Uuid;
+
+pub const STATS_SCHEMA_VERSION: u32 = 1;
+const REPORT_TIMEOUT: Duration = Duration::from_secs(30);
+
+//Body posted to the stats endpoint, bump STATS_SCHEMA_VERSION when fields change meaning
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsReport {
+    pub schema_version: u32,
+    pub farm_id: Uuid,
+    pub timestamp: u64,
+    pub version: String,
+    pub network: String,
+    //Seconds covered by the counters below
+    pub period: u64,
+    pub plots: u64,
+    pub space: u64,
+    pub proofs_declared: u64,
+    pub partials_submitted: u64,
+    pub partials_accepted: u64,
+    pub partials_rejected: u64,
+    pub partials_stale: u64,
+    #[serde(default)]
+    pub partials_stale_local: u64,
+    #[serde(default)]
+    pub partials_duplicate: u64,
+    //None when no partial was answered in the period
+    pub acceptance_rate: Option<f64>,
+    //The same counters per plot NFT for the period, difficulty is the latest one seen
+    #[serde(default)]
+    pub launchers: LauncherStatsMap,
+    //Rolling 24 hour and 7 day windows, not reset per period
+    #[serde(default)]
+    pub availability: Option<AvailabilityReport>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Counters {
+    proofs_declared: u64,
+    partials_submitted: u64,
+    partials_accepted: u64,
+    partials_rejected: u64,
+    partials_stale: u64,
+    partials_stale_local: u64,
+    partials_duplicate: u64,
+    launchers: LauncherStatsMap,
+}
+impl Counters {
+    fn record(&mut self, event: &FarmerEvent) {
+        record_launcher_event(&mut self.launchers, event);
+        match &event.kind {
+            FarmerEventKind::ProofDeclared { .. } => self.proofs_declared += 1,
+            FarmerEventKind::PartialSubmitted { .. } => self.partials_submitted += 1,
+            FarmerEventKind::PartialAccepted { .. } => self.partials_accepted += 1,
+            FarmerEventKind::PartialRejected { error_code, .. } => {
+                self.partials_rejected += 1;
+                if *error_code == PoolErrorCode::TooLate as u8 {
+                    self.partials_stale += 1;
+                }
+            }
+            FarmerEventKind::PartialStaleLocal { .. } => self.partials_stale_local += 1,
+            FarmerEventKind::PartialDuplicate { .. } => self.partials_duplicate += 1,
+            _ => {}
+        }
+    }
+}
+
+pub async fn stats_reporter(shared_state: Arc<FarmerSharedState>) {
+    let Some(report_config) = shared_state.config.stats_report.clone() else {
+        return;
+    };
+    if report_config.interval == 0 {
+        return;
+    }
+    let client = match build_client(&report_config) {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Stats reporting disabled: {:?}", e);
+            return;
+        }
+    };
+    let farm_id = load_farm_id();
+    info!(
+        "Reporting stats as farm {farm_id} to {} every {} minutes",
+        report_config.endpoint, report_config.interval
+    );
+    let mut events = shared_state.events.subscribe();
+    let mut counters = Counters::default();
+    let mut last_report = Instant::now();
+    loop {
+        if !shared_state.run.load(Ordering::Relaxed) {
+            break;
+        }
+        //A report only covers the time the reporter ran
+        if !shared_state.subsystems.is_running(Subsystem::StatsReport) {
+            if !shared_state
+                .subsystems
+                .wait_until_running(Subsystem::StatsReport, &shared_state.run)
+                .await
+            {
+                break;
+            }
+            events = events.resubscribe();
+            counters = Counters::default();
+            last_report = Instant::now();
+        }
+        loop {
+            match events.try_recv() {
+                Ok(event) => counters.record(&event),
+                Err(TryRecvError::Lagged(skipped)) => {
+                    debug!("Stats reporter skipped {skipped} events");
+                }
+                Err(_) => break,
+            }
+        }
+        if last_report.elapsed().as_secs() >= report_config.interval * 60 {
+            let plots = shared_state.harvester_stats.farm().await.plots;
+            let answered = counters.partials_accepted + counters.partials_rejected;
+            let availability = shared_state.availability.lock().await.report(now());
+            let report = StatsReport {
+                schema_version: STATS_SCHEMA_VERSION,
+                farm_id,
+                timestamp: now(),
+                version: version(),
+                network: shared_state.config.selected_network.clone(),
+                period: last_report.elapsed().as_secs(),
+                plots: plots.count,
+                space: plots.space,
+                proofs_declared: counters.proofs_declared,
+                partials_submitted: counters.partials_submitted,
+                partials_accepted: counters.partials_accepted,
+                partials_rejected: counters.partials_rejected,
+                partials_stale: counters.partials_stale,
+                partials_stale_local: counters.partials_stale_local,
+                partials_duplicate: counters.partials_duplicate,
+                acceptance_rate: (answered > 0)
+                    .then(|| counters.partials_accepted as f64 / answered as f64),
+                launchers: std::mem::take(&mut counters).launchers,
+                availability: Some(availability),
+            };
+            last_report = Instant::now();
+            match client
+                .post(&report_config.endpoint)
+                .json(&report)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+            {
+                Ok(_) => debug!("Sent stats report to {}", report_config.endpoint),
+                Err(e) => warn!(
+                    "Failed to send stats report to {}: {:?}",
+                    report_config.endpoint, e
+                ),
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+}
+
+fn build_client(report_config: &StatsReportConfig) -> Result<reqwest::Client, Error> {
+    let mut headers = HeaderMap::new();
+    for (k, v) in &report_config.headers {
+        headers.insert(
+            HeaderName::from_str(k).map_err(|e| Error::other(format!("{:?}", e)))?,
+            HeaderValue::from_str(v).map_err(|e| Error::other(format!("{:?}", e)))?,
+        );
+    }
+    reqwest::Client::builder()
+        .default_headers(headers)
+        .user_agent(version())
+        .timeout(REPORT_TIMEOUT)
+        .build()
+        .map_err(|e| Error::other(format!("{:?}", e)))
+}
+
+//Random id kept next to the config, it is not derived from any key so reports can not be linked to a wallet
+fn load_farm_id() -> Uuid {
+    let path = get_farm_id_path();
+    if let Ok(s) = fs::read_to_string(&path) {
+        match Uuid::from_str(s.trim()) {
+            Ok(id) => return id,
+            Err(e) => warn!("Ignoring invalid farm id at {:?}: {:?}", path, e),
+        }
+    }
+    let id = Uuid::new_v4();
+    if let Err(e) = fs::write(&path, id.to_string()) {
+        warn!("Failed to save farm id to {:?}: {:?}", path, e);
+    }
+    id
+}
@@ -0,0 +1,138 @@
+use crate::farmer::events::{FarmerEvent, FarmerEventKind};
+use crate::farmer::network_stats::format_time_to_win;
+use crate::farmer::partial_queue::now;
+use crate::farmer::rpc::FullnodeRpc;
+use crate::farmer::FarmerSharedState;
+use dg_xch_clients::protocols::pool::PoolErrorCode;
+use log::{debug, info};
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::TryRecvError;
+
+const HISTORY_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Proof,
+    PartialAccepted,
+    PartialStale,
+}
+
+//Logs a single key=value line at a fixed interval so the last log line shows farm health
+pub async fn status_summary(shared_state: Arc<FarmerSharedState>) {
+    let interval = shared_state.config.status_interval;
+    if interval == 0 {
+        return;
+    }
+    let mut events = shared_state.events.subscribe();
+    let full_node_rpc = FullnodeRpc::reader(
+        shared_state.config.as_ref(),
+        shared_state.rpc_health.clone(),
+    )
+    .await;
+    let mut history: VecDeque<(u64, Outcome)> = VecDeque::new();
+    let mut last_summary = Instant::now();
+    loop {
+        if !shared_state.run.load(Ordering::Relaxed) {
+            break;
+        }
+        loop {
+            match events.try_recv() {
+                Ok(event) => {
+                    if let Some(outcome) = outcome(&event) {
+                        history.push_back((event.timestamp, outcome));
+                    }
+                }
+                Err(TryRecvError::Lagged(skipped)) => {
+                    debug!("Status summary skipped {skipped} events");
+                }
+                Err(_) => break,
+            }
+        }
+        if last_summary.elapsed().as_secs() >= interval * 60 {
+            last_summary = Instant::now();
+            let cutoff = now().saturating_sub(HISTORY_SECS);
+            history.retain(|(timestamp, _)| *timestamp >= cutoff);
+            let count = |o: Outcome| history.iter().filter(|(_, h)| *h == o).count();
+            let plots = shared_state.harvester_stats.farm().await.plots.count;
+            let eligible = std::mem::take(&mut *shared_state.eligibility_stats.lock().await);
+            let difficulties = shared_state
+                .pool_states
+                .lock()
+                .await
+                .values()
+                .filter_map(|s| {
+                    s.pool_config.as_ref().and_then(|c| {
+                        let launcher = &c.launcher_id.to_string()[..8];
+                        if s.is_solo() {
+                            Some(format!("{launcher}:solo"))
+                        } else {
+                            s.current_difficulty.map(|d| format!("{launcher}:{d}"))
+                        }
+                    })
+                })
+                .collect::<Vec<String>>();
+            let (invalid_messages, declarations_skipped) = {
+                let gui_stats = shared_state.gui_stats.lock().await;
+                (
+                    gui_stats.handler_errors.values().sum::<u64>(),
+                    gui_stats.declarations_skipped,
+                )
+            };
+            let height = match full_node_rpc.get_blockchain_state().await {
+                Ok(state) => state
+                    .peak
+                    .map(|p| p.height.to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                Err(e) => {
+                    debug!(
+                        "Failed to load blockchain state for status summary: {:?}",
+                        e
+                    );
+                    "unknown".to_string()
+                }
+            };
+            let time_to_win = shared_state
+                .gui_stats
+                .lock()
+                .await
+                .network
+                .and_then(|n| n.time_to_win);
+            info!(
+                "Status plots={} eligible_per_sp={:.2} proofs_24h={} partials_accepted_24h={} partials_stale_24h={} difficulties={} height={} time_to_win=\"{}\" invalid_messages={} declarations_skipped={}",
+                plots,
+                eligible.average(),
+                count(Outcome::Proof),
+                count(Outcome::PartialAccepted),
+                count(Outcome::PartialStale),
+                if difficulties.is_empty() {
+                    "none".to_string()
+                } else {
+                    difficulties.join(",")
+                },
+                height,
+                format_time_to_win(time_to_win),
+                invalid_messages,
+                declarations_skipped
+            );
+            //Repeated with every summary so an incomplete config is not lost in the scrollback
+            shared_state.readiness.log();
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+}
+
+fn outcome(event: &FarmerEvent) -> Option<Outcome> {
+    match &event.kind {
+        FarmerEventKind::ProofDeclared { .. } => Some(Outcome::Proof),
+        FarmerEventKind::PartialAccepted { .. } => Some(Outcome::PartialAccepted),
+        FarmerEventKind::PartialRejected { error_code, .. }
+            if *error_code == PoolErrorCode::TooLate as u8 =>
+        {
+            Some(Outcome::PartialStale)
+        }
+        _ => None,
+    }
+}
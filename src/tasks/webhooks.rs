@@ -0,0 +1,142 @@
+use crate::farmer::config::WebhookConfig;
+use crate::farmer::events::{FarmerEvent, FarmerEventKind};
+use crate::farmer::subsystems::Subsystem;
+use crate::farmer::webhook::{template_variables, WebhookEvent, WebhookTemplate};
+use crate::farmer::FarmerSharedState;
+use crate::version;
+use log::{debug, info, warn};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use std::io::Error;
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::TryRecvError;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Webhook {
+    config: WebhookConfig,
+    template: Option<WebhookTemplate>,
+    client: reqwest::Client,
+}
+impl Webhook {
+    fn new(config: WebhookConfig) -> Result<Self, Error> {
+        let template = config
+            .template
+            .as_deref()
+            .map(WebhookTemplate::parse)
+            .transpose()?;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_str(&config.content_type)
+                .map_err(|e| Error::other(format!("{:?}", e)))?,
+        );
+        for (k, v) in &config.headers {
+            headers.insert(
+                HeaderName::from_str(k).map_err(|e| Error::other(format!("{:?}", e)))?,
+                HeaderValue::from_str(v).map_err(|e| Error::other(format!("{:?}", e)))?,
+            );
+        }
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .user_agent(version())
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .map_err(|e| Error::other(format!("{:?}", e)))?;
+        Ok(Self {
+            config,
+            template,
+            client,
+        })
+    }
+    fn wants(&self, event: &FarmerEvent) -> bool {
+        let Some(webhook_event) = WebhookEvent::from_event(&event.kind) else {
+            return false;
+        };
+        if !self.config.events.contains(&webhook_event) {
+            return false;
+        }
+        match (&self.config.pool_url, &event.kind) {
+            (Some(wanted), FarmerEventKind::PartialAccepted { pool_url, .. })
+            | (Some(wanted), FarmerEventKind::PartialRejected { pool_url, .. }) => {
+                wanted.trim_end_matches('/') == pool_url.trim_end_matches('/')
+            }
+            _ => true,
+        }
+    }
+    fn body(&self, event: &FarmerEvent) -> String {
+        match &self.template {
+            Some(template) => template.render(
+                &template_variables(event),
+                self.config.content_type.contains("json"),
+            ),
+            None => serde_json::to_string(event).unwrap_or_default(),
+        }
+    }
+}
+
+pub async fn webhook_sender(shared_state: Arc<FarmerSharedState>) {
+    let webhooks: Vec<Arc<Webhook>> = shared_state
+        .config
+        .webhooks
+        .iter()
+        .filter_map(|config| match Webhook::new(config.clone()) {
+            Ok(webhook) => Some(Arc::new(webhook)),
+            Err(e) => {
+                warn!("Webhook to {} disabled: {:?}", config.url, e);
+                None
+            }
+        })
+        .collect();
+    if webhooks.is_empty() {
+        return;
+    }
+    info!("Sending farm events to {} webhooks", webhooks.len());
+    let mut events = shared_state.events.subscribe();
+    loop {
+        if !shared_state.run.load(Ordering::Relaxed) {
+            break;
+        }
+        //Events of the time the webhooks were stopped are not sent later
+        if !shared_state.subsystems.is_running(Subsystem::Webhooks) {
+            if !shared_state
+                .subsystems
+                .wait_until_running(Subsystem::Webhooks, &shared_state.run)
+                .await
+            {
+                break;
+            }
+            events = events.resubscribe();
+        }
+        loop {
+            match events.try_recv() {
+                Ok(event) => {
+                    for webhook in webhooks.iter().filter(|w| w.wants(&event)) {
+                        let body = webhook.body(&event);
+                        let webhook = webhook.clone();
+                        //Sent in the background so a slow endpoint does not hold up the others
+                        tokio::spawn(async move {
+                            if let Err(e) = webhook
+                                .client
+                                .post(&webhook.config.url)
+                                .body(body)
+                                .send()
+                                .await
+                                .and_then(|r| r.error_for_status())
+                            {
+                                warn!("Failed to send webhook to {}: {:?}", webhook.config.url, e);
+                            }
+                        });
+                    }
+                }
+                Err(TryRecvError::Lagged(skipped)) => {
+                    debug!("Webhook sender skipped {skipped} events");
+                }
+                Err(_) => break,
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+}
@@ -0,0 +1,62 @@
+use crate::farmer::earnings::{parse_price, XchPrice};
+use crate::farmer::partial_queue::now;
+use crate::farmer::FarmerSharedState;
+use crate::version;
+use log::{info, warn};
+use serde_json::Value;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const PRICE_TIMEOUT: Duration = Duration::from_secs(30);
+
+//Keeps the XCH price for the earnings estimate, only runs with a configured price_url
+pub async fn xch_price_updater(shared_state: Arc<FarmerSharedState>) {
+    let Some(config) = shared_state.config.earnings.clone() else {
+        return;
+    };
+    let Some(price_url) = config.price_url.clone() else {
+        return;
+    };
+    let client = match reqwest::Client::builder()
+        .user_agent(version())
+        .timeout(PRICE_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!("Failed to build price client: {:?}", e);
+            return;
+        }
+    };
+    info!(
+        "Reading the XCH price from {price_url} every {}s",
+        config.price_interval
+    );
+    let interval = Duration::from_secs(config.price_interval.max(60));
+    let mut last_update: Option<Instant> = None;
+    while shared_state.run.load(Ordering::Relaxed) {
+        if last_update.is_none_or(|last| last.elapsed() >= interval) {
+            last_update = Some(Instant::now());
+            let body = match client.get(&price_url).send().await {
+                Ok(resp) => resp.json::<Value>().await,
+                Err(e) => Err(e),
+            };
+            match body.map(|b| parse_price(&b, &config.price_pointer)) {
+                Ok(Some(price)) => {
+                    shared_state.gui_stats.lock().await.xch_price = Some(XchPrice {
+                        price,
+                        currency: config.currency.clone(),
+                        updated: now(),
+                    });
+                }
+                Ok(None) => warn!(
+                    "No price at {} in the response of {price_url}",
+                    config.price_pointer
+                ),
+                Err(e) => warn!("Failed to read the XCH price: {:?}", e),
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}